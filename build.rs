@@ -0,0 +1,186 @@
+//! Generates the API schema served by `api_schema()` from the crate sources, so
+//! it cannot drift from the functions and DTOs it describes.
+
+use std::fs;
+use std::path::Path;
+use quote::ToTokens;
+use serde_json::{json, Value};
+
+fn tokens(t: &impl ToTokens) -> String {
+    t.to_token_stream()
+        .to_string()
+        .replace(" < ", "<")
+        .replace("< ", "<")
+        .replace(" >", ">")
+        .replace(" , ", ", ")
+        .replace(" ,", ",")
+        .replace(" (", "(")
+        .replace("( ", "(")
+        .replace(" )", ")")
+        .replace("& ", "&")
+        .replace(" :: ", "::")
+}
+
+fn docs(attrs: &[syn::Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|a| a.path().is_ident("doc"))
+        .filter_map(|a| match &a.meta {
+            syn::Meta::NameValue(nv) => match &nv.value {
+                syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(s), .. }) => Some(s.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    (!lines.is_empty()).then(|| lines.join(" "))
+}
+
+fn is_serialized(attrs: &[syn::Attribute]) -> bool {
+    attrs
+        .iter()
+        .filter(|a| a.path().is_ident("derive"))
+        .any(|a| tokens(&a.meta).contains("Serialize"))
+}
+
+fn fields(fields: &syn::Fields) -> Value {
+    match fields {
+        syn::Fields::Named(named) => Value::Array(
+            named
+                .named
+                .iter()
+                .filter(|f| matches!(f.vis, syn::Visibility::Public(_)))
+                .map(|f| json!({ "name": f.ident.as_ref().map(|i| i.to_string()), "type": tokens(&f.ty) }))
+                .collect(),
+        ),
+        syn::Fields::Unnamed(unnamed) => Value::Array(unnamed.unnamed.iter().map(|f| json!({ "type": tokens(&f.ty) })).collect()),
+        syn::Fields::Unit => Value::Array(Vec::new()),
+    }
+}
+
+fn function(f: &syn::ItemFn) -> Value {
+    let sig = &f.sig;
+    let params: Vec<Value> = sig
+        .inputs
+        .iter()
+        .filter_map(|arg| match arg {
+            syn::FnArg::Typed(p) => Some(json!({ "name": tokens(&p.pat), "type": tokens(&p.ty) })),
+            syn::FnArg::Receiver(_) => None,
+        })
+        .collect();
+    let returns = match &sig.output {
+        syn::ReturnType::Default => "()".to_string(),
+        syn::ReturnType::Type(_, ty) => tokens(ty),
+    };
+    let feature = f
+        .attrs
+        .iter()
+        .find(|a| a.path().is_ident("cfg"))
+        .map(|a| tokens(&a.meta));
+    let deprecated = f.attrs.iter().find(|a| a.path().is_ident("deprecated")).map(|a| match &a.meta {
+        syn::Meta::List(list) => list
+            .parse_args_with(syn::punctuated::Punctuated::<syn::MetaNameValue, syn::Token![,]>::parse_terminated)
+            .ok()
+            .and_then(|args| args.into_iter().find(|nv| nv.path.is_ident("note")))
+            .map(|nv| tokens(&nv.value).trim_matches('"').to_string())
+            .unwrap_or_default(),
+        _ => String::new(),
+    });
+    json!({
+        "name": sig.ident.to_string(),
+        "params": params,
+        "returns": returns,
+        "generics": (!sig.generics.params.is_empty()).then(|| tokens(&sig.generics)),
+        "where": sig.generics.where_clause.as_ref().map(tokens),
+        "cfg": feature,
+        "doc": docs(&f.attrs),
+        "deprecated": deprecated,
+    })
+}
+
+/// Commit the crate is built from, for the signed version records.
+fn git_hash() -> Option<String> {
+    let output = std::process::Command::new("git").args(["rev-parse", "--short=12", "HEAD"]).output().ok()?;
+    let hash = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (output.status.success() && !hash.is_empty()).then_some(hash)
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=build.rs");
+    for git_path in [".git/HEAD", ".git/refs"] {
+        if Path::new(git_path).exists() {
+            println!("cargo:rerun-if-changed={}", git_path);
+        }
+    }
+    if let Some(hash) = git_hash() {
+        println!("cargo:rustc-env=ECOBLOCK_GIT_HASH={}", hash);
+    }
+
+    let mut functions = Vec::new();
+    let mut functions_v2 = Vec::new();
+    let mut dtos = Vec::new();
+    let mut events = Vec::new();
+    let mut errors = Vec::new();
+
+    let mut paths: Vec<_> = fs::read_dir("src")
+        .expect("src directory")
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e == "rs"))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let source = fs::read_to_string(&path).expect("readable source");
+        let file = syn::parse_file(&source).expect("parsable source");
+        let module = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_string();
+        for item in &file.items {
+            if let syn::Item::Enum(e) = item {
+                if module == "error" && e.ident == "ErrorKind" {
+                    errors.extend(e.variants.iter().map(|v| v.ident.to_string()));
+                }
+            }
+            match item {
+                syn::Item::Fn(f) if module == "lib" && matches!(f.vis, syn::Visibility::Public(_)) => functions.push(function(f)),
+                syn::Item::Fn(f) if module == "v2" && matches!(f.vis, syn::Visibility::Public(_)) => functions_v2.push(function(f)),
+                syn::Item::Struct(s) if matches!(s.vis, syn::Visibility::Public(_)) && is_serialized(&s.attrs) => dtos.push(json!({
+                    "name": s.ident.to_string(),
+                    "module": module,
+                    "kind": "struct",
+                    "fields": fields(&s.fields),
+                    "doc": docs(&s.attrs),
+                })),
+                syn::Item::Enum(e) if matches!(e.vis, syn::Visibility::Public(_)) && is_serialized(&e.attrs) => dtos.push(json!({
+                    "name": e.ident.to_string(),
+                    "module": module,
+                    "kind": "enum",
+                    "variants": e.variants.iter().map(|v| json!({ "name": v.ident.to_string(), "fields": fields(&v.fields) })).collect::<Vec<_>>(),
+                    "doc": docs(&e.attrs),
+                })),
+                syn::Item::Type(t) if t.ident.to_string().ends_with("Callback") => {
+                    let ty = tokens(&t.ty);
+                    let event = ty
+                        .split_once("Fn(")
+                        .and_then(|(_, rest)| rest.split_once(')'))
+                        .map(|(arg, _)| arg.trim().to_string());
+                    if let Some(event) = event.filter(|e| !e.is_empty()) {
+                        events.push(json!({ "callback": t.ident.to_string(), "module": module, "event": event }));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let schema = json!({
+        "crate": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "functions": functions,
+        "v2": { "functions": functions_v2 },
+        "dtos": dtos,
+        "events": events,
+        "errors": { "style": "enum", "type": "BridgeError", "kinds": errors },
+    });
+    let out = Path::new(&std::env::var("OUT_DIR").expect("OUT_DIR")).join("api_schema.json");
+    fs::write(out, serde_json::to_string_pretty(&schema).expect("serializable schema")).expect("writable OUT_DIR");
+}
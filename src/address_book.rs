@@ -0,0 +1,89 @@
+use std::collections::{BTreeMap, BTreeSet};
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+use crate::peer_id::PeerId;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerEntry {
+    pub node_id: PeerId,
+    pub alias: Option<String>,
+    pub added_at: u64,
+    /// Previous key of the peer when the entry was carried over by an identity handover.
+    pub previous_id: Option<PeerId>,
+    /// Groups the peer is tagged into; see `peer_groups`.
+    #[serde(default)]
+    pub groups: BTreeSet<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct AddressBook {
+    entries: BTreeMap<String, PeerEntry>,
+}
+
+impl AddressBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, node_id: &PeerId, alias: Option<String>, now: u64) {
+        let entry = self.entries.entry(node_id.to_string()).or_insert_with(|| PeerEntry {
+            node_id: node_id.clone(),
+            alias: None,
+            added_at: now,
+            previous_id: None,
+            groups: BTreeSet::new(),
+        });
+        if alias.is_some() {
+            entry.alias = alias;
+        }
+    }
+
+    pub fn remove(&mut self, node_id: &str) -> bool {
+        self.entries.remove(node_id).is_some()
+    }
+
+    pub fn get(&self, node_id: &str) -> Option<&PeerEntry> {
+        self.entries.get(node_id)
+    }
+
+    pub fn list(&self) -> Vec<PeerEntry> {
+        self.entries.values().cloned().collect()
+    }
+
+    pub fn tag(&mut self, node_id: &str, group: &str) -> Result<(), BridgeError> {
+        let entry = self.entries.get_mut(node_id).ok_or_else(|| BridgeError::NotFound(format!("Unknown peer {}: not in the address book", node_id)))?;
+        entry.groups.insert(group.to_string());
+        Ok(())
+    }
+
+    pub fn untag(&mut self, node_id: &str, group: &str) -> bool {
+        self.entries.get_mut(node_id).is_some_and(|e| e.groups.remove(group))
+    }
+
+    /// Every group some entry is tagged into.
+    pub fn groups(&self) -> BTreeSet<String> {
+        self.entries.values().flat_map(|e| e.groups.iter().cloned()).collect()
+    }
+
+    pub fn members(&self, group: &str) -> Vec<PeerId> {
+        self.entries.values().filter(|e| e.groups.contains(group)).map(|e| e.node_id.clone()).collect()
+    }
+
+    /// Moves the entry of `old` to `new`, keeping its alias and groups. Returns `false` if `old` is unknown.
+    pub fn replace(&mut self, old: &str, new: &PeerId, now: u64) -> bool {
+        let Some(entry) = self.entries.remove(old) else {
+            return false;
+        };
+        self.entries.insert(
+            new.to_string(),
+            PeerEntry {
+                node_id: new.clone(),
+                alias: entry.alias,
+                added_at: now,
+                previous_id: Some(entry.node_id),
+                groups: entry.groups,
+            },
+        );
+        true
+    }
+}
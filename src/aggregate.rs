@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricAggregate {
+    pub metric: String,
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub sum: f64,
+}
+
+impl MetricAggregate {
+    pub fn from_values<I: IntoIterator<Item = f64>>(metric: &str, values: I) -> Self {
+        let mut count = 0;
+        let mut min = f64::INFINITY;
+        let mut max = f64::NEG_INFINITY;
+        let mut sum = 0.0;
+        for v in values {
+            count += 1;
+            min = min.min(v);
+            max = max.max(v);
+            sum += v;
+        }
+        if count == 0 {
+            min = 0.0;
+            max = 0.0;
+        }
+        Self {
+            metric: metric.to_string(),
+            count,
+            min,
+            max,
+            mean: if count == 0 { 0.0 } else { sum / count as f64 },
+            sum,
+        }
+    }
+}
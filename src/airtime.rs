@@ -0,0 +1,257 @@
+//! Duty-cycle budgets for radio transports. Bands such as EU868 let a device
+//! transmit for only a share of each hour. Each frame's time on air is
+//! estimated from the transport's radio settings and counted against a sliding
+//! window; frames that would overrun the budget wait until it frees up.
+
+use std::collections::{HashMap, VecDeque};
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+use crate::peer_id::PeerId;
+use crate::wire::Frame;
+use crate::{clock, EcoBlockContext};
+
+/// Frames waiting for airtime per transport; the oldest are dropped first.
+pub const MAX_DEFERRED_FRAMES: usize = 1024;
+/// LoRa preamble symbols.
+const LORA_PREAMBLE: f64 = 8.0;
+
+/// How long frames occupy the channel.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AirtimeModel {
+    /// LoRa with an explicit header, CRC and 4/5 coding rate.
+    Lora { spreading_factor: u8, bandwidth_hz: u32 },
+    /// A fixed bitrate plus per-frame overhead, for FSK and similar radios.
+    Bitrate { bits_per_second: u32, overhead_ms: u32 },
+}
+
+impl AirtimeModel {
+    pub fn validate(&self) -> Result<(), BridgeError> {
+        match *self {
+            AirtimeModel::Lora { spreading_factor, bandwidth_hz } => {
+                if !(7..=12).contains(&spreading_factor) || bandwidth_hz == 0 {
+                    return Err(BridgeError::InvalidInput(format!(
+                        "Invalid LoRa settings SF{} at {} Hz: expected SF7 to SF12 and a positive bandwidth",
+                        spreading_factor, bandwidth_hz
+                    )));
+                }
+            }
+            AirtimeModel::Bitrate { bits_per_second, .. } => {
+                if bits_per_second == 0 {
+                    return Err(BridgeError::InvalidInput("Invalid bitrate: expected a positive value".to_string()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Time on air of a frame of `len` bytes, in milliseconds, rounded up.
+    pub fn airtime_ms(&self, len: usize) -> u64 {
+        match *self {
+            AirtimeModel::Lora { spreading_factor, bandwidth_hz } => {
+                let sf = f64::from(spreading_factor);
+                let symbol_ms = 2f64.powf(sf) / f64::from(bandwidth_hz) * 1000.0;
+                // Low data rate optimisation is mandatory above 16 ms symbols.
+                let optimise = if symbol_ms > 16.0 { 2.0 } else { 0.0 };
+                let bits = 8.0 * len as f64 - 4.0 * sf + 28.0 + 16.0;
+                let payload_symbols = 8.0 + ((bits / (4.0 * (sf - optimise))).ceil() * 5.0).max(0.0);
+                ((LORA_PREAMBLE + 4.25 + payload_symbols) * symbol_ms).ceil() as u64
+            }
+            AirtimeModel::Bitrate { bits_per_second, overhead_ms } => {
+                u64::from(overhead_ms) + (len as u64 * 8 * 1000).div_ceil(u64::from(bits_per_second))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DutyCycleBudget {
+    /// Regulatory region or sub-band, for display.
+    pub region: String,
+    /// Share of the window the radio may transmit, above 0 and at most 1.
+    pub duty_cycle: f64,
+    /// Length of the sliding window, in seconds.
+    pub window_secs: u64,
+    pub model: AirtimeModel,
+}
+
+impl DutyCycleBudget {
+    /// The 1% per hour limit of most EU868 sub-bands.
+    pub fn eu868(model: AirtimeModel) -> Self {
+        Self {
+            region: "EU868".to_string(),
+            duty_cycle: 0.01,
+            window_secs: 3600,
+            model,
+        }
+    }
+
+    /// The 10% per hour limit of the EU433 band.
+    pub fn eu433(model: AirtimeModel) -> Self {
+        Self {
+            region: "EU433".to_string(),
+            duty_cycle: 0.1,
+            window_secs: 3600,
+            model,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), BridgeError> {
+        if !(self.duty_cycle > 0.0 && self.duty_cycle <= 1.0) || self.window_secs == 0 {
+            return Err(BridgeError::InvalidInput(format!(
+                "Invalid duty-cycle budget {} over {} seconds: expected a share in (0, 1] and a positive window",
+                self.duty_cycle, self.window_secs
+            )));
+        }
+        self.model.validate()
+    }
+
+    /// Airtime allowed per window, in milliseconds.
+    pub fn allowance_ms(&self) -> u64 {
+        (self.window_secs as f64 * 1000.0 * self.duty_cycle) as u64
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AirtimeStatus {
+    pub transport: String,
+    pub region: String,
+    pub allowance_ms: u64,
+    /// Airtime spent within the current window.
+    pub used_ms: u64,
+    pub remaining_ms: u64,
+    pub deferred_frames: usize,
+    /// Frames deferred since the budget was set.
+    pub deferred_total: u64,
+}
+
+#[derive(Debug)]
+struct Ledger {
+    budget: DutyCycleBudget,
+    /// Send time and airtime of each frame in the window, in milliseconds.
+    sent: VecDeque<(u64, u64)>,
+    deferred: VecDeque<(PeerId, Frame)>,
+    deferred_total: u64,
+}
+
+impl Ledger {
+    fn used_ms(&mut self, now_ms: u64) -> u64 {
+        let window_ms = self.budget.window_secs * 1000;
+        while self.sent.front().is_some_and(|(at, _)| now_ms.saturating_sub(*at) >= window_ms) {
+            self.sent.pop_front();
+        }
+        self.sent.iter().map(|(_, ms)| ms).sum()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AirtimeState {
+    ledgers: HashMap<String, Ledger>,
+}
+
+impl AirtimeState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn budget(&self, transport: &str) -> Option<&DutyCycleBudget> {
+        self.ledgers.get(transport).map(|l| &l.budget)
+    }
+
+    /// Limits `transport` to `budget`, or with `None` lifts its limit. Frames
+    /// waiting under the old budget are kept.
+    pub fn set_budget(&mut self, transport: &str, budget: Option<DutyCycleBudget>) -> Result<(), BridgeError> {
+        let Some(budget) = budget else {
+            self.ledgers.remove(transport);
+            return Ok(());
+        };
+        budget.validate()?;
+        match self.ledgers.get_mut(transport) {
+            Some(ledger) => ledger.budget = budget,
+            None => {
+                self.ledgers.insert(
+                    transport.to_string(),
+                    Ledger {
+                        budget,
+                        sent: VecDeque::new(),
+                        deferred: VecDeque::new(),
+                        deferred_total: 0,
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts `frame` of `len` bytes against `transport`'s budget and hands it
+    /// back to be sent, or queues it when the budget would be exceeded.
+    pub(crate) fn admit(&mut self, transport: &str, peer: &PeerId, frame: Frame, len: usize, now_ms: u64) -> Option<Frame> {
+        let Some(ledger) = self.ledgers.get_mut(transport) else {
+            return Some(frame);
+        };
+        let airtime = ledger.budget.model.airtime_ms(len);
+        if ledger.used_ms(now_ms) + airtime <= ledger.budget.allowance_ms() {
+            ledger.sent.push_back((now_ms, airtime));
+            return Some(frame);
+        }
+        if ledger.deferred.len() == MAX_DEFERRED_FRAMES {
+            ledger.deferred.pop_front();
+        }
+        ledger.deferred.push_back((peer.clone(), frame));
+        ledger.deferred_total += 1;
+        None
+    }
+
+    pub fn status(&mut self, now_ms: u64) -> Vec<AirtimeStatus> {
+        let mut statuses: Vec<AirtimeStatus> = self
+            .ledgers
+            .iter_mut()
+            .map(|(transport, ledger)| {
+                let used_ms = ledger.used_ms(now_ms);
+                let allowance_ms = ledger.budget.allowance_ms();
+                AirtimeStatus {
+                    transport: transport.clone(),
+                    region: ledger.budget.region.clone(),
+                    allowance_ms,
+                    used_ms,
+                    remaining_ms: allowance_ms.saturating_sub(used_ms),
+                    deferred_frames: ledger.deferred.len(),
+                    deferred_total: ledger.deferred_total,
+                }
+            })
+            .collect();
+        statuses.sort_by(|a, b| a.transport.cmp(&b.transport));
+        statuses
+    }
+}
+
+impl EcoBlockContext {
+    /// Retries the frames waiting for airtime on the attached transport. Those
+    /// that still do not fit are queued again in order.
+    pub fn flush_airtime_queue(&mut self) -> usize {
+        let Some(transport) = self.sync.transport_name() else {
+            return 0;
+        };
+        let Some(ledger) = self.airtime.ledgers.get_mut(&transport) else {
+            return 0;
+        };
+        let waiting = std::mem::take(&mut ledger.deferred);
+        let before = ledger.deferred_total;
+        let count = waiting.len();
+        for (peer, frame) in waiting {
+            self.send_frame(&peer, frame);
+        }
+        let Some(ledger) = self.airtime.ledgers.get_mut(&transport) else {
+            return count;
+        };
+        // Frames queued again are still the same deferrals.
+        let requeued = ledger.deferred_total - before;
+        ledger.deferred_total = before;
+        count - requeued as usize
+    }
+
+    /// Remaining airtime of each budgeted transport, recorded by `sample_metrics`.
+    pub fn airtime_status(&mut self) -> Vec<AirtimeStatus> {
+        self.airtime.status(clock::now_millis())
+    }
+}
@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+use crate::block_id::BlockId;
+use crate::error::BridgeError;
+use crate::records::Record;
+
+pub const ANNOTATION_KIND: &str = "annotation";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub id: String,
+    pub block_id: BlockId,
+    pub note: String,
+    pub author: String,
+    pub timestamp: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct AnnotationBody {
+    block_id: String,
+    note: String,
+}
+
+pub fn body(block_id: &BlockId, note: &str) -> serde_json::Value {
+    serde_json::to_value(AnnotationBody {
+        block_id: block_id.to_string(),
+        note: note.to_string(),
+    })
+    .unwrap_or_default()
+}
+
+impl TryFrom<&Record> for Annotation {
+    type Error = BridgeError;
+
+    fn try_from(record: &Record) -> Result<Self, BridgeError> {
+        if record.kind != ANNOTATION_KIND {
+            return Err(BridgeError::InvalidInput(format!("Record {} is not an annotation", record.id)));
+        }
+        let body: AnnotationBody = serde_json::from_value(record.body.clone())
+            .map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+        Ok(Self {
+            id: record.id.clone(),
+            block_id: BlockId::parse(&body.block_id)?,
+            note: body.note,
+            author: record.author.clone(),
+            timestamp: record.timestamp,
+        })
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use ecoblock_storage::tangle::block::TangleBlock;
+use crate::block_id::BlockId;
+use crate::error::BridgeError;
+use crate::logs::LogLevel;
+use crate::validation::RateViolation;
+use crate::EcoBlockContext;
+
+/// A validated, signed block waiting to be stored.
+#[derive(Debug, Clone)]
+pub struct PreparedBlock {
+    pub block: TangleBlock,
+    /// Canonical signature, made when the node signs canonically.
+    pub(crate) signature: Option<String>,
+    pub(crate) payload: serde_json::Value,
+    pub(crate) violations: Vec<RateViolation>,
+}
+
+#[derive(Debug, Default)]
+pub struct Batches {
+    next_id: u64,
+    open: HashMap<u64, Vec<PreparedBlock>>,
+}
+
+impl Batches {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn begin(&mut self) -> u64 {
+        self.next_id += 1;
+        self.open.insert(self.next_id, Vec::new());
+        self.next_id
+    }
+
+    pub fn abort(&mut self, batch_id: u64) -> bool {
+        self.open.remove(&batch_id).is_some()
+    }
+
+    pub fn is_open(&self, batch_id: u64) -> bool {
+        self.open.contains_key(&batch_id)
+    }
+
+    fn unknown(batch_id: u64) -> BridgeError {
+        BridgeError::NotFound(format!("Unknown batch {}", batch_id))
+    }
+}
+
+impl EcoBlockContext {
+    /// Rate rules are checked against stored readings only, not against earlier
+    /// blocks of the same batch. A block that fails validation rolls the whole
+    /// batch back.
+    pub fn add_to_batch(&mut self, batch_id: u64, data: Vec<u8>, parents: Vec<BlockId>) -> Result<BlockId, BridgeError> {
+        if !self.batches.is_open(batch_id) {
+            return Err(Batches::unknown(batch_id));
+        }
+        let prepared = match self.prepare_block(data, parents) {
+            Ok(prepared) => prepared,
+            Err(e) => {
+                let discarded = self.batches.open.remove(&batch_id).map_or(0, |blocks| blocks.len());
+                self.log(LogLevel::Warn, format!("Batch {} rolled back: {} blocks discarded", batch_id, discarded));
+                return Err(self.reject_block(e));
+            }
+        };
+        let id = BlockId::from_storage(prepared.block.id.clone());
+        self.batches.open.entry(batch_id).or_default().push(prepared);
+        Ok(id)
+    }
+
+    /// Every write is checked before the first block is stored, so a failure
+    /// leaves the tangle untouched.
+    pub fn commit_batch(&mut self, batch_id: u64) -> Result<Vec<BlockId>, BridgeError> {
+        let blocks = self.batches.open.remove(&batch_id).ok_or_else(|| Batches::unknown(batch_id))?;
+        if let Some(chaos) = self.chaos.as_mut() {
+            if (0..blocks.len()).any(|_| chaos.fail_write()) {
+                self.log(LogLevel::Warn, format!("Batch {} rolled back: {} blocks discarded", batch_id, blocks.len()));
+                let error = BridgeError::Io("Erreur de stockage: échec d'écriture injecté".to_string());
+                return Err(self.reject_block(error));
+            }
+        }
+        Ok(blocks.into_iter().map(|prepared| self.commit_block(prepared)).collect())
+    }
+}
@@ -0,0 +1,99 @@
+use std::time::Instant;
+use ecoblock_core::domain::tangle_data::TangleBlockData;
+use ecoblock_core::domain::SensorData;
+use ecoblock_crypto::keys::keypair::CryptoKeypair;
+use ecoblock_storage::tangle::block::TangleBlock;
+use ecoblock_storage::tangle::Tangle;
+use serde::{Deserialize, Serialize};
+use crate::wire::{self, BlockEnvelope, Frame, FrameKind};
+use crate::EcoBlockContext;
+
+pub const ITERATIONS: u32 = 500;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchResult {
+    pub name: String,
+    pub iterations: u32,
+    pub total_ms: f64,
+    pub ops_per_sec: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchReport {
+    pub crate_version: String,
+    pub results: Vec<BenchResult>,
+}
+
+fn measure(name: &str, iterations: u32, mut op: impl FnMut(u32)) -> BenchResult {
+    let start = Instant::now();
+    for i in 0..iterations {
+        op(i);
+    }
+    let secs = start.elapsed().as_secs_f64();
+    BenchResult {
+        name: name.to_string(),
+        iterations,
+        total_ms: secs * 1000.0,
+        ops_per_sec: if secs > 0.0 { f64::from(iterations) / secs } else { f64::INFINITY },
+    }
+}
+
+fn sample(i: u32) -> SensorData {
+    SensorData {
+        pm25: 10.0 + (i % 7) as f32,
+        co2: 400.0 + (i % 50) as f32,
+        temperature: 20.0 + (i % 5) as f32,
+        humidity: 45.0,
+        timestamp: 1_700_000_000 + u64::from(i),
+    }
+}
+
+/// Runs every benchmark on private state; the global context is untouched.
+pub fn run() -> BenchReport {
+    let keypair = CryptoKeypair::generate();
+    let mut blocks = Vec::with_capacity(ITERATIONS as usize);
+    let mut results = vec![measure("block_create_sign", ITERATIONS, |i| {
+        blocks.push(TangleBlock::new(TangleBlockData { parents: Vec::new(), data: sample(i) }, &keypair));
+    })];
+
+    results.push(measure("block_verify", ITERATIONS, |i| {
+        std::hint::black_box(blocks[i as usize].verify());
+    }));
+
+    let mut tangle = Tangle::new();
+    results.push(measure("tangle_insert", ITERATIONS, |i| {
+        tangle.insert(blocks[i as usize].clone()).ok();
+    }));
+
+    let mut ctx = EcoBlockContext::new();
+    for i in 0..ITERATIONS {
+        if let Ok(data) = serde_json::to_vec(&sample(i)) {
+            ctx.try_create_block(data, Vec::new()).ok();
+        }
+    }
+    ctx.take_pending();
+    results.push(measure("metric_query", ITERATIONS, |i| {
+        std::hint::black_box(ctx.aggregate_metric("co2", 1_700_000_000 + u64::from(i / 2), u64::MAX));
+    }));
+
+    let mut peer = Tangle::new();
+    results.push(measure("propagate_loopback", ITERATIONS, |i| {
+        let envelope = BlockEnvelope::Sensor(blocks[i as usize].clone());
+        let Ok(payload) = serde_json::to_vec(&envelope) else {
+            return;
+        };
+        let Ok(bytes) = Frame::new(FrameKind::Block, payload).encode() else {
+            return;
+        };
+        if let Ok(BlockEnvelope::Sensor(block)) = wire::parse_frame(&bytes).and_then(|f| wire::parse_block_envelope(&f.payload)) {
+            if block.verify() {
+                peer.insert(block).ok();
+            }
+        }
+    }));
+
+    BenchReport {
+        crate_version: env!("CARGO_PKG_VERSION").to_string(),
+        results,
+    }
+}
@@ -0,0 +1,105 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+
+pub const MAX_LEN: usize = 128;
+/// Characters shown by `BlockId::short`.
+pub const SHORT_LEN: usize = 8;
+
+const TAG_TEXT: u8 = 0;
+const TAG_HEX: u8 = 1;
+
+/// Identifier of a block. The format is owned by ecoblock-storage and treated
+/// as opaque here beyond basic validation: 1 to 128 printable ASCII characters.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct BlockId(String);
+
+impl BlockId {
+    pub fn parse(id: &str) -> Result<Self, BridgeError> {
+        if id.is_empty() || id.len() > MAX_LEN {
+            return Err(BridgeError::InvalidInput(format!("Invalid block id: length {} not in 1..={}", id.len(), MAX_LEN)));
+        }
+        if !id.bytes().all(|b| b.is_ascii_graphic()) {
+            return Err(BridgeError::InvalidInput(format!("Invalid block id: {:?}", id)));
+        }
+        Ok(Self(id.to_string()))
+    }
+
+    /// Wraps an id produced by the local storage layer, which is valid by construction.
+    pub(crate) fn from_storage(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Leading characters of the id, for display in lists and logs.
+    pub fn short(&self) -> &str {
+        self.0.get(..SHORT_LEN).unwrap_or(&self.0)
+    }
+
+    /// Compact binary form: lowercase hex ids are stored as raw bytes, anything
+    /// else as its UTF-8 text, behind a one-byte tag.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match hex::decode(&self.0) {
+            Ok(raw) if hex::encode(&raw) == self.0 => [vec![TAG_HEX], raw].concat(),
+            _ => [vec![TAG_TEXT], self.0.as_bytes().to_vec()].concat(),
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BridgeError> {
+        match bytes.split_first() {
+            Some((&TAG_HEX, raw)) => Self::parse(&hex::encode(raw)),
+            Some((&TAG_TEXT, text)) => {
+                let text = std::str::from_utf8(text).map_err(|e| BridgeError::InvalidInput(format!("Invalid block id: {}", e)))?;
+                Self::parse(text)
+            }
+            Some((tag, _)) => Err(BridgeError::InvalidInput(format!("Invalid block id: unknown tag {}", tag))),
+            None => Err(BridgeError::InvalidInput("Invalid block id: empty encoding".to_string())),
+        }
+    }
+}
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for BlockId {
+    type Err = BridgeError;
+
+    fn from_str(s: &str) -> Result<Self, BridgeError> {
+        Self::parse(s)
+    }
+}
+
+impl TryFrom<String> for BlockId {
+    type Error = BridgeError;
+
+    fn try_from(id: String) -> Result<Self, BridgeError> {
+        Self::parse(&id)
+    }
+}
+
+impl From<BlockId> for String {
+    fn from(id: BlockId) -> Self {
+        id.0
+    }
+}
+
+impl AsRef<str> for BlockId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for BlockId {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
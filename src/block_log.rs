@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use ecoblock_storage::tangle::block::TangleBlock;
+
+/// Sensor blocks in local insertion order, kept alongside the tangle so the
+/// bridge can enumerate and look them up. Removed blocks leave an empty slot so
+/// positions stay stable for cursors and checkpoints.
+#[derive(Debug, Default)]
+pub struct BlockLog {
+    blocks: Vec<Option<TangleBlock>>,
+    by_id: HashMap<String, usize>,
+}
+
+impl BlockLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns false if the block was already present.
+    pub fn insert(&mut self, block: TangleBlock) -> bool {
+        if self.by_id.contains_key(&block.id) {
+            return false;
+        }
+        self.by_id.insert(block.id.clone(), self.blocks.len());
+        self.blocks.push(Some(block));
+        true
+    }
+
+    pub fn remove(&mut self, id: &str) -> Option<TangleBlock> {
+        let position = self.by_id.remove(id)?;
+        self.blocks[position].take()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&TangleBlock> {
+        self.by_id.get(id).and_then(|&i| self.blocks[i].as_ref())
+    }
+
+    /// Log position of a held block.
+    pub fn position(&self, id: &str) -> Option<usize> {
+        self.by_id.get(id).copied()
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.by_id.contains_key(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_id.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_id.is_empty()
+    }
+
+    /// Position the next inserted block will take.
+    pub fn end(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &TangleBlock> {
+        self.blocks.iter().flatten()
+    }
+
+    /// Blocks with their log positions.
+    pub fn positions(&self) -> impl Iterator<Item = (usize, &TangleBlock)> {
+        self.blocks.iter().enumerate().filter_map(|(i, b)| b.as_ref().map(|b| (i, b)))
+    }
+
+    /// Blocks inserted at or after `position`.
+    pub fn since(&self, position: usize) -> impl Iterator<Item = &TangleBlock> {
+        self.blocks.get(position..).unwrap_or_default().iter().flatten()
+    }
+}
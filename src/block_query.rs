@@ -0,0 +1,70 @@
+use std::collections::HashSet;
+use ecoblock_storage::tangle::block::TangleBlock;
+use serde::{Deserialize, Serialize};
+use crate::block_id::BlockId;
+use crate::error::BridgeError;
+use crate::EcoBlockContext;
+
+/// Conditions a block must meet to be listed; unset fields match everything.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BlockFilter {
+    /// Reading timestamps, inclusive.
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+    /// Public key of the signer.
+    pub author: Option<String>,
+    /// A `SensorData` field, such as `temperature`, whose value must lie in
+    /// `min..=max`.
+    pub field: Option<String>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl BlockFilter {
+    pub fn matches(&self, block: &TangleBlock) -> Result<bool, BridgeError> {
+        let timestamp = block.data.data.timestamp;
+        if self.from.is_some_and(|from| timestamp < from) || self.to.is_some_and(|to| timestamp > to) {
+            return Ok(false);
+        }
+        if self.author.as_ref().is_some_and(|a| !a.eq_ignore_ascii_case(&block.public_key)) {
+            return Ok(false);
+        }
+        let Some(field) = &self.field else {
+            return Ok(true);
+        };
+        let data = serde_json::to_value(&block.data.data).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        let value = data
+            .get(field)
+            .filter(|_| field != "timestamp")
+            .and_then(serde_json::Value::as_f64)
+            .ok_or_else(|| BridgeError::InvalidInput(format!("Invalid block filter: unknown sensor field {:?}", field)))?;
+        Ok(self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max))
+    }
+}
+
+impl EcoBlockContext {
+    pub fn get_block(&self, id: &BlockId) -> Option<&TangleBlock> {
+        self.block_log.get(id.as_str())
+    }
+
+    /// Blocks no other local block names as a parent, in insertion order.
+    pub fn list_tips(&self) -> Vec<BlockId> {
+        let parents: HashSet<&str> = self.block_log.iter().flat_map(|b| b.data.parents.iter().map(String::as_str)).collect();
+        self.block_log
+            .iter()
+            .filter(|b| !parents.contains(b.id.as_str()))
+            .map(|b| BlockId::from_storage(b.id.clone()))
+            .collect()
+    }
+
+    /// Blocks matching `filter` with their log positions, for paging.
+    pub fn query_blocks(&self, filter: &BlockFilter) -> Result<Vec<(usize, TangleBlock)>, BridgeError> {
+        let mut matched = Vec::new();
+        for (position, block) in self.block_log.positions() {
+            if filter.matches(block)? {
+                matched.push((position, block.clone()));
+            }
+        }
+        Ok(matched)
+    }
+}
@@ -0,0 +1,395 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use ecoblock_storage::tangle::block::TangleBlock;
+use serde::{Deserialize, Serialize};
+use crate::energy;
+use crate::error::BridgeError;
+use crate::logs::LogLevel;
+use crate::runtime;
+use crate::EcoBlockContext;
+
+pub const BLOCK_STORE_FILE: &str = "tangle.log";
+
+/// Where `open_tangle` keeps the blocks of the node at `path`, next to its keypair.
+pub fn block_store_path(path: &str) -> PathBuf {
+    PathBuf::from(path).join(BLOCK_STORE_FILE)
+}
+
+/// One line of the block store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Entry {
+    Insert {
+        block: TangleBlock,
+        /// Canonical signature; absent for blocks that only carry their own.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        signature: Option<String>,
+    },
+    Remove { id: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockStoreStatus {
+    pub path: String,
+    /// Blocks a reload would restore.
+    pub live_blocks: usize,
+    /// Lines in the file, including removals and removed blocks until compaction.
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+/// Append-only JSON-lines log of inserted and removed sensor blocks. Every
+/// write reaches the OS before the call returns; `flush` also syncs it to disk.
+#[derive(Debug)]
+pub struct BlockStore {
+    path: PathBuf,
+    file: File,
+    entries: usize,
+    /// Canonical signatures of the loaded blocks, until taken.
+    signatures: HashMap<String, String>,
+}
+
+impl BlockStore {
+    /// Opens or creates the store and returns it with the blocks it holds, in
+    /// insertion order. A torn last line, left by a crash mid-write, is cut off.
+    pub fn open(path: &Path) -> Result<(Self, Vec<TangleBlock>), BridgeError> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| BridgeError::Io(e.to_string()))?;
+        }
+        let bytes = if path.exists() { fs::read(path).map_err(|e| BridgeError::Io(e.to_string()))? } else { Vec::new() };
+        let mut order: Vec<Option<TangleBlock>> = Vec::new();
+        let mut by_id: HashMap<String, usize> = HashMap::new();
+        let mut signatures: HashMap<String, String> = HashMap::new();
+        let (mut entries, mut valid_len) = (0, 0);
+        for line in bytes.split_inclusive(|b| *b == b'\n') {
+            if !line.ends_with(b"\n") {
+                // Only the last line can lack its newline: a write cut short by a crash.
+                break;
+            }
+            let entry: Entry = serde_json::from_slice(line)
+                .map_err(|e| BridgeError::Deserialization(format!("{} line {}: {}", path.display(), entries + 1, e)))?;
+            match entry {
+                Entry::Insert { block, signature } => {
+                    if !by_id.contains_key(&block.id) {
+                        if let Some(signature) = signature {
+                            signatures.insert(block.id.clone(), signature);
+                        }
+                        by_id.insert(block.id.clone(), order.len());
+                        order.push(Some(block));
+                    }
+                }
+                Entry::Remove { id } => {
+                    if let Some(i) = by_id.remove(&id) {
+                        order[i] = None;
+                        signatures.remove(&id);
+                    }
+                }
+            }
+            entries += 1;
+            valid_len += line.len();
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path).map_err(|e| BridgeError::Io(e.to_string()))?;
+        if valid_len < bytes.len() {
+            file.set_len(valid_len as u64).map_err(|e| BridgeError::Io(e.to_string()))?;
+        }
+        let store = Self {
+            path: path.to_path_buf(),
+            file,
+            entries,
+            signatures,
+        };
+        Ok((store, order.into_iter().flatten().collect()))
+    }
+
+    fn append(&mut self, entry: &Entry) -> Result<(), BridgeError> {
+        let mut line = serde_json::to_vec(entry).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        line.push(b'\n');
+        self.file.write_all(&line).map_err(|e| BridgeError::Io(e.to_string()))?;
+        energy::count_disk_write(line.len());
+        self.entries += 1;
+        Ok(())
+    }
+
+    pub fn insert(&mut self, block: &TangleBlock, signature: Option<&str>) -> Result<(), BridgeError> {
+        self.append(&Entry::Insert {
+            block: block.clone(),
+            signature: signature.map(str::to_string),
+        })
+    }
+
+    pub fn remove(&mut self, id: &str) -> Result<(), BridgeError> {
+        self.append(&Entry::Remove { id: id.to_string() })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Canonical signatures stored with the blocks `open` returned.
+    pub(crate) fn take_signatures(&mut self) -> HashMap<String, String> {
+        std::mem::take(&mut self.signatures)
+    }
+
+    pub fn flush(&mut self) -> Result<(), BridgeError> {
+        self.file.sync_all().map_err(|e| BridgeError::Io(e.to_string()))
+    }
+
+    /// Rewrites the store with only `blocks`, through a temporary file renamed
+    /// over the old one so a crash leaves either version intact.
+    pub fn compact<'a>(&mut self, blocks: impl Iterator<Item = &'a TangleBlock>, signatures: &HashMap<String, String>) -> Result<(), BridgeError> {
+        let tmp = self.path.with_extension("log.tmp");
+        let mut out = File::create(&tmp).map_err(|e| BridgeError::Io(e.to_string()))?;
+        let mut entries = 0;
+        for block in blocks {
+            let entry = Entry::Insert {
+                block: block.clone(),
+                signature: signatures.get(&block.id).cloned(),
+            };
+            let mut line = serde_json::to_vec(&entry).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+            line.push(b'\n');
+            out.write_all(&line).map_err(|e| BridgeError::Io(e.to_string()))?;
+            entries += 1;
+        }
+        out.sync_all().map_err(|e| BridgeError::Io(e.to_string()))?;
+        fs::rename(&tmp, &self.path).map_err(|e| BridgeError::Io(e.to_string()))?;
+        sync_parent_dir(&self.path)?;
+        self.file = OpenOptions::new().append(true).open(&self.path).map_err(|e| BridgeError::Io(e.to_string()))?;
+        self.entries = entries;
+        Ok(())
+    }
+
+    pub fn status(&self, live_blocks: usize) -> BlockStoreStatus {
+        BlockStoreStatus {
+            path: self.path.display().to_string(),
+            live_blocks,
+            entries: self.entries,
+            bytes: self.file.metadata().map(|m| m.len()).unwrap_or_default(),
+        }
+    }
+}
+
+/// Makes a rename into the directory of `path` durable; until the directory
+/// is synced a crash can bring the old file back.
+#[cfg(unix)]
+pub(crate) fn sync_parent_dir(path: &Path) -> Result<(), BridgeError> {
+    let dir = path.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    File::open(dir).and_then(|d| d.sync_all()).map_err(|e| BridgeError::Io(e.to_string()))
+}
+
+/// Directories cannot be opened for syncing here; renames are durable once
+/// the call returns.
+#[cfg(not(unix))]
+pub(crate) fn sync_parent_dir(_path: &Path) -> Result<(), BridgeError> {
+    Ok(())
+}
+
+impl EcoBlockContext {
+    /// Loads the blocks stored at `path` and writes every block stored or
+    /// removed from now on. Loaded blocks are verified, then indexed lazily
+    /// like `load_blocks`. Returns how many were loaded.
+    pub fn open_block_store(&mut self, path: &Path) -> Result<usize, BridgeError> {
+        let (mut store, blocks) = BlockStore::open(path)?;
+        if let Some(bad) = runtime::find_invalid(&blocks, self.config.verification_threads()) {
+            return Err(BridgeError::Crypto(format!("stored block {} failed verification", bad.id)));
+        }
+        self.block_store = None;
+        let loaded = blocks.len();
+        let stored: HashSet<String> = blocks.iter().map(|b| b.id.clone()).collect();
+        self.block_signatures.extend(store.take_signatures());
+        self.load_blocks(blocks);
+        // Blocks already in memory are written too, so the file holds all of them.
+        for block in self.block_log.iter().filter(|b| !stored.contains(&b.id)) {
+            store.insert(block, self.block_signatures.get(&block.id).map(String::as_str))?;
+        }
+        self.block_store = Some(store);
+        Ok(loaded)
+    }
+
+    pub(crate) fn persist_insert(&mut self, block: &TangleBlock) {
+        let Some(store) = self.block_store.as_mut() else {
+            return;
+        };
+        if let Err(e) = store.insert(block, self.block_signatures.get(&block.id).map(String::as_str)) {
+            self.log(LogLevel::Error, format!("Block {} not persisted: {}", block.id, e));
+        }
+    }
+
+    pub(crate) fn persist_remove(&mut self, id: &str) {
+        let Some(store) = self.block_store.as_mut() else {
+            return;
+        };
+        if let Err(e) = store.remove(id) {
+            self.log(LogLevel::Error, format!("Removal of block {} not persisted: {}", id, e));
+        }
+    }
+
+    pub fn compact_block_store(&mut self) -> Result<BlockStoreStatus, BridgeError> {
+        let store = self.block_store.as_mut().ok_or_else(|| BridgeError::NotFound("Unknown block store: none is open".to_string()))?;
+        store.compact(self.block_log.iter(), &self.block_signatures)?;
+        Ok(store.status(self.block_log.len()))
+    }
+
+    pub fn flush_block_store(&mut self) -> Result<(), BridgeError> {
+        match self.block_store.as_mut() {
+            Some(store) => store.flush(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn block_store_status(&self) -> Option<BlockStoreStatus> {
+        self.block_store.as_ref().map(|s| s.status(self.block_log.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ecoblock_core::domain::tangle_data::TangleBlockData;
+    use ecoblock_core::domain::SensorData;
+    use ecoblock_crypto::keys::keypair::CryptoKeypair;
+    use super::*;
+
+    /// A fresh store path in its own temporary directory.
+    fn store_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ecoblock-block-store-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join(BLOCK_STORE_FILE)
+    }
+
+    fn blocks(count: u64) -> Vec<TangleBlock> {
+        let keypair = CryptoKeypair::generate();
+        (0..count)
+            .map(|i| {
+                let data = SensorData {
+                    pm25: 12.0,
+                    co2: 410.0,
+                    temperature: 21.0,
+                    humidity: 45.0,
+                    timestamp: 1_700_000_000 + i,
+                };
+                TangleBlock::new(TangleBlockData { parents: Vec::new(), data }, &keypair)
+            })
+            .collect()
+    }
+
+    fn ids(blocks: &[TangleBlock]) -> Vec<String> {
+        blocks.iter().map(|b| b.id.clone()).collect()
+    }
+
+    fn reopen(path: &Path) -> Vec<String> {
+        ids(&BlockStore::open(path).unwrap().1)
+    }
+
+    #[test]
+    fn reopening_restores_blocks_in_insertion_order() {
+        let path = store_path("reopen");
+        let blocks = blocks(3);
+        let (mut store, loaded) = BlockStore::open(&path).unwrap();
+        assert!(loaded.is_empty());
+        for block in &blocks {
+            store.insert(block, None).unwrap();
+        }
+        store.remove(&blocks[1].id).unwrap();
+        drop(store);
+        assert_eq!(reopen(&path), vec![blocks[0].id.clone(), blocks[2].id.clone()]);
+    }
+
+    #[test]
+    fn a_truncated_write_loses_only_the_block_being_written() {
+        let path = store_path("truncated");
+        let blocks = blocks(2);
+        let (mut store, _) = BlockStore::open(&path).unwrap();
+        store.insert(&blocks[0], None).unwrap();
+        store.insert(&blocks[1], None).unwrap();
+        drop(store);
+        let len = fs::metadata(&path).unwrap().len();
+        OpenOptions::new().write(true).open(&path).unwrap().set_len(len - 10).unwrap();
+        assert_eq!(reopen(&path), ids(&blocks[..1]));
+    }
+
+    #[test]
+    fn a_torn_last_line_is_cut_off_before_appending() {
+        let path = store_path("torn");
+        let blocks = blocks(3);
+        let (mut store, _) = BlockStore::open(&path).unwrap();
+        store.insert(&blocks[0], None).unwrap();
+        store.file.write_all(br#"{"op":"insert","block":{"id":"#).unwrap();
+        drop(store);
+
+        let (mut store, loaded) = BlockStore::open(&path).unwrap();
+        assert_eq!(ids(&loaded), ids(&blocks[..1]));
+        store.insert(&blocks[1], None).unwrap();
+        store.insert(&blocks[2], None).unwrap();
+        drop(store);
+        assert_eq!(reopen(&path), ids(&blocks));
+    }
+
+    #[test]
+    fn a_corrupt_complete_line_is_an_error() {
+        let path = store_path("corrupt");
+        let (mut store, _) = BlockStore::open(&path).unwrap();
+        store.insert(&blocks(1)[0], None).unwrap();
+        store.file.write_all(b"not json\n").unwrap();
+        drop(store);
+        let error = BlockStore::open(&path).unwrap_err();
+        assert!(matches!(error, BridgeError::Deserialization(_)), "{}", error);
+    }
+
+    #[test]
+    fn a_duplicate_insert_loads_the_block_once() {
+        let path = store_path("duplicate");
+        let blocks = blocks(2);
+        let (mut store, _) = BlockStore::open(&path).unwrap();
+        store.insert(&blocks[0], None).unwrap();
+        store.insert(&blocks[1], None).unwrap();
+        store.insert(&blocks[0], None).unwrap();
+        drop(store);
+        assert_eq!(reopen(&path), ids(&blocks));
+    }
+
+    #[test]
+    fn a_removal_before_the_insert_does_not_hide_the_block() {
+        let path = store_path("remove-first");
+        let blocks = blocks(1);
+        let (mut store, _) = BlockStore::open(&path).unwrap();
+        store.remove(&blocks[0].id).unwrap();
+        store.insert(&blocks[0], None).unwrap();
+        drop(store);
+        assert_eq!(reopen(&path), ids(&blocks));
+    }
+
+    #[test]
+    fn compaction_keeps_only_live_blocks() {
+        let path = store_path("compact");
+        let blocks = blocks(3);
+        let (mut store, _) = BlockStore::open(&path).unwrap();
+        for block in &blocks {
+            store.insert(block, None).unwrap();
+        }
+        store.remove(&blocks[0].id).unwrap();
+        store.compact(blocks[1..].iter(), &HashMap::new()).unwrap();
+        assert_eq!(store.status(2).entries, 2);
+        assert!(!path.with_extension("log.tmp").exists());
+        store.insert(&blocks[0], None).unwrap();
+        drop(store);
+        assert_eq!(reopen(&path), vec![blocks[1].id.clone(), blocks[2].id.clone(), blocks[0].id.clone()]);
+    }
+
+    #[test]
+    fn canonical_signatures_survive_reopening_and_compaction() {
+        let path = store_path("signatures");
+        let blocks = blocks(2);
+        let (mut store, _) = BlockStore::open(&path).unwrap();
+        store.insert(&blocks[0], Some("aa")).unwrap();
+        store.insert(&blocks[1], None).unwrap();
+        drop(store);
+
+        let (mut store, _) = BlockStore::open(&path).unwrap();
+        let signatures = store.take_signatures();
+        assert_eq!(signatures, HashMap::from([(blocks[0].id.clone(), "aa".to_string())]));
+        store.compact(blocks.iter(), &signatures).unwrap();
+        drop(store);
+        assert_eq!(BlockStore::open(&path).unwrap().0.take_signatures(), signatures);
+    }
+}
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+use crate::change_feed::ChangeKind;
+use crate::error::BridgeError;
+use crate::events::BridgeEvent;
+use crate::logs::LogLevel;
+use crate::peer_id::PeerId;
+use crate::{clock, EcoBlockContext};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerSpec {
+    pub node_id: PeerId,
+    pub alias: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BulkReport {
+    pub added: usize,
+    /// Already present. Known peers get their alias updated when one is given;
+    /// existing edges are left unchanged.
+    pub existing: usize,
+}
+
+impl EcoBlockContext {
+    /// Adds every peer to the address book and mesh in one pass.
+    pub fn add_peers_bulk(&mut self, peers: Vec<PeerSpec>) -> BulkReport {
+        let now = clock::now_secs();
+        let mut report = BulkReport::default();
+        for peer in peers {
+            let kind = if self.address_book.get(peer.node_id.as_str()).is_some() {
+                report.existing += 1;
+                ChangeKind::PeerUpdated
+            } else {
+                report.added += 1;
+                ChangeKind::PeerAdded
+            };
+            self.address_book.add(&peer.node_id, peer.alias, now);
+            self.changes.push(kind, peer.node_id.to_string(), now);
+            self.add_mesh_node(peer.node_id.as_str());
+        }
+        self.log(LogLevel::Info, format!("Bulk peer import: {} added, {} existing", report.added, report.existing));
+        report
+    }
+
+    /// Adds every edge, or none of them if any weight is invalid.
+    pub fn add_connections_bulk(&mut self, connections: Vec<(PeerId, PeerId, f32)>) -> Result<BulkReport, BridgeError> {
+        if let Some((from, to, weight)) = connections.iter().find(|(_, _, w)| !w.is_finite() || *w < 0.0) {
+            return Err(BridgeError::InvalidInput(format!("Invalid weight {} for {} -> {}", weight, from, to)));
+        }
+        let mut report = BulkReport::default();
+        for (from, to, weight) in connections {
+            let exists = self
+                .mesh
+                .get_neighbors(from.as_str())
+                .is_some_and(|n| n.iter().any(|(peer, _)| peer == to.as_str()));
+            if exists {
+                report.existing += 1;
+            } else {
+                report.added += 1;
+                self.add_mesh_connection(from.as_str(), to.as_str(), weight);
+                self.record_change(ChangeKind::ConnectionAdded, format!("{}->{}", from, to));
+                self.emit_event(BridgeEvent::PeerConnected { from, to, weight });
+            }
+        }
+        self.log(LogLevel::Info, format!("Bulk connection import: {} added, {} existing", report.added, report.existing));
+        Ok(report)
+    }
+}
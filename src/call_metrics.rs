@@ -0,0 +1,155 @@
+//! Call counts and latency histograms for the public bridge functions.
+//!
+//! The registry lives outside `CONTEXT`, so a call's time includes waiting for
+//! the context lock, which is usually what shows up as UI jank.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::Instant;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use crate::logs::LogLevel;
+use crate::{EcoBlockContext, CONTEXT};
+
+/// Upper bounds of the histogram buckets; a last bucket counts slower calls.
+pub const LATENCY_BUCKETS_MICROS: [u64; 8] = [100, 1_000, 5_000, 16_000, 50_000, 100_000, 500_000, 1_000_000];
+/// One frame at 60 Hz.
+pub const DEFAULT_CALL_BUDGET_MICROS: u64 = 16_000;
+/// Slow-call warnings held until the context can be locked to log them.
+const MAX_PENDING_WARNINGS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallStats {
+    pub function: String,
+    pub calls: u64,
+    /// Calls over the function's latency budget.
+    pub slow_calls: u64,
+    pub total_micros: u64,
+    pub max_micros: u64,
+    /// Counts per `LATENCY_BUCKETS_MICROS` bucket, plus one for slower calls.
+    pub buckets: Vec<u64>,
+}
+
+impl CallStats {
+    fn new(function: &str) -> Self {
+        Self {
+            function: function.to_string(),
+            calls: 0,
+            slow_calls: 0,
+            total_micros: 0,
+            max_micros: 0,
+            buckets: vec![0; LATENCY_BUCKETS_MICROS.len() + 1],
+        }
+    }
+}
+
+pub struct CallRegistry {
+    pub default_budget_micros: u64,
+    budgets: HashMap<String, u64>,
+    stats: BTreeMap<&'static str, CallStats>,
+    warnings: Vec<String>,
+}
+
+impl Default for CallRegistry {
+    fn default() -> Self {
+        Self {
+            default_budget_micros: DEFAULT_CALL_BUDGET_MICROS,
+            budgets: HashMap::new(),
+            stats: BTreeMap::new(),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl CallRegistry {
+    pub fn budget(&self, function: &str) -> u64 {
+        self.budgets.get(function).copied().unwrap_or(self.default_budget_micros)
+    }
+
+    /// `None` restores the default budget for the function.
+    pub fn set_budget(&mut self, function: &str, micros: Option<u64>) {
+        match micros {
+            Some(micros) => self.budgets.insert(function.to_string(), micros),
+            None => self.budgets.remove(function),
+        };
+    }
+
+    /// Records one call and queues a warning when it was over budget.
+    /// Arguments may hold sensor data or keys, so only the name is kept.
+    pub fn record(&mut self, function: &'static str, micros: u64) {
+        let budget = self.budget(function);
+        let slow = micros > budget;
+        let stats = self.stats.entry(function).or_insert_with(|| CallStats::new(function));
+        stats.calls += 1;
+        stats.total_micros += micros;
+        stats.max_micros = stats.max_micros.max(micros);
+        let bucket = LATENCY_BUCKETS_MICROS.iter().position(|b| micros <= *b).unwrap_or(LATENCY_BUCKETS_MICROS.len());
+        stats.buckets[bucket] += 1;
+        if slow {
+            stats.slow_calls += 1;
+            if self.warnings.len() < MAX_PENDING_WARNINGS {
+                self.warnings.push(format!("Slow call: {}(<redacted>) took {} us, budget {} us", function, micros, budget));
+            }
+        }
+    }
+
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    pub fn stats(&self) -> Vec<CallStats> {
+        self.stats.values().cloned().collect()
+    }
+
+    pub fn slow_calls(&self) -> u64 {
+        self.stats.values().map(|s| s.slow_calls).sum()
+    }
+
+    pub fn reset(&mut self) {
+        self.stats.clear();
+    }
+}
+
+lazy_static! {
+    pub static ref CALLS: Mutex<CallRegistry> = Mutex::new(CallRegistry::default());
+}
+
+/// Times a public function from creation until it is dropped, so it is
+/// declared first in the function.
+pub struct CallTimer {
+    function: &'static str,
+    started: Instant,
+}
+
+impl CallTimer {
+    pub fn start(function: &'static str) -> Self {
+        Self {
+            function,
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Drop for CallTimer {
+    fn drop(&mut self) {
+        let micros = self.started.elapsed().as_micros() as u64;
+        if let Ok(mut calls) = CALLS.lock() {
+            calls.record(self.function, micros);
+        }
+        // Most functions still hold the context guard here; their warnings
+        // are logged by the next call that does not.
+        if let Ok(mut ctx) = CONTEXT.try_lock() {
+            ctx.flush_slow_calls();
+        }
+    }
+}
+
+impl EcoBlockContext {
+    /// Moves queued slow-call warnings into the node log.
+    pub fn flush_slow_calls(&mut self) {
+        let warnings = CALLS.lock().map(|mut c| c.take_warnings()).unwrap_or_default();
+        for warning in warnings {
+            self.log(LogLevel::Warn, warning);
+        }
+    }
+}
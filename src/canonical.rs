@@ -0,0 +1,283 @@
+//! Canonical encoding of signed content. The legacy form signs whatever bytes
+//! `serde_json` produces, which depend on field order, on features such as
+//! `preserve_order` enabled anywhere in the build, and on whether a reading
+//! was parsed as `415` or `415.0`. The canonical form is compact JSON with
+//! object keys sorted by their UTF-8 bytes and integral floats written as
+//! integers, prefixed with `CANONICAL_TAG` so it can never be mistaken for a
+//! legacy message.
+//!
+//! The Ed25519 signature inside a sensor block is made by ecoblock-storage over
+//! its own layout. A node signing canonically adds its own signature over the
+//! canonical encoding of the whole block, sent as a `CanonicalSensor` envelope
+//! and kept with the block so relays forward it.
+//!
+//! Signers switch to the canonical form with `set_encoding_policy`; verifiers
+//! accept both until `accept_legacy` is turned off at the end of the
+//! transition.
+
+use ecoblock_crypto::keys::keypair::CryptoKeypair;
+use ecoblock_storage::tangle::block::TangleBlock;
+use serde::{Deserialize, Serialize};
+use serde_json::{Number, Value};
+use crate::energy;
+use crate::error::BridgeError;
+use crate::peer_id::PeerId;
+use crate::records::verify_signature;
+use crate::wire::BlockEnvelope;
+use crate::EcoBlockContext;
+
+/// Leads every canonical message, binding the encoding into the signature.
+pub const CANONICAL_TAG: &str = "ecoblock-canonical-v1";
+/// Largest integer an `f64` holds exactly.
+const MAX_EXACT_FLOAT: f64 = 9_007_199_254_740_992.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    /// Bytes as produced by `serde_json`; records without an encoding field use it.
+    #[default]
+    Legacy,
+    Canonical,
+}
+
+impl Encoding {
+    pub fn is_legacy(&self) -> bool {
+        *self == Encoding::Legacy
+    }
+}
+
+/// How this node signs and which encodings it accepts from others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncodingPolicy {
+    /// Encoding of records and block signatures made locally.
+    /// Stays legacy until every peer verifies canonical messages.
+    pub sign: Encoding,
+    /// Off once the transition is over and legacy signatures are refused.
+    pub accept_legacy: bool,
+}
+
+impl Default for EncodingPolicy {
+    fn default() -> Self {
+        Self {
+            sign: Encoding::Legacy,
+            accept_legacy: true,
+        }
+    }
+}
+
+impl EncodingPolicy {
+    pub fn validate(&self) -> Result<(), BridgeError> {
+        if self.sign.is_legacy() && !self.accept_legacy {
+            return Err(BridgeError::InvalidInput("Invalid encoding policy: cannot sign in the legacy encoding while refusing it".to_string()));
+        }
+        Ok(())
+    }
+
+    pub fn accepts(&self, encoding: Encoding) -> bool {
+        !encoding.is_legacy() || self.accept_legacy
+    }
+}
+
+/// Canonical JSON of `value`, without the tag.
+pub fn to_vec<T: Serialize>(value: &T) -> Result<Vec<u8>, BridgeError> {
+    let value = serde_json::to_value(value).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+    let mut out = Vec::new();
+    write_value(&value, &mut out);
+    Ok(out)
+}
+
+/// The bytes signed for `value` in the canonical encoding: `[CANONICAL_TAG, value]`.
+pub fn message<T: Serialize>(value: &T) -> Result<Vec<u8>, BridgeError> {
+    to_vec(&(CANONICAL_TAG, value))
+}
+
+/// The bytes block signatures made by the bridge cover: the JSON of the block
+/// as sent, or its canonical message.
+pub fn block_message(block: &TangleBlock, encoding: Encoding) -> Vec<u8> {
+    match encoding {
+        Encoding::Legacy => serde_json::to_vec(block).unwrap_or_default(),
+        Encoding::Canonical => message(block).unwrap_or_default(),
+    }
+}
+
+/// Hex Ed25519 signature of the block's canonical message.
+pub fn sign_block(block: &TangleBlock, keypair: &CryptoKeypair) -> String {
+    energy::count_signature();
+    hex::encode(keypair.sign(&block_message(block, Encoding::Canonical)).to_bytes())
+}
+
+/// Checks a canonical block signature against the key that signed the block.
+pub fn verify_block_signature(block: &TangleBlock, signature: &str) -> bool {
+    verify_signature(&block.public_key, &block_message(block, Encoding::Canonical), signature)
+}
+
+/// A sensor block with its author's signature over the canonical encoding.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CanonicalBlock {
+    pub block: TangleBlock,
+    pub signature: String,
+}
+
+fn write_value(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Number(n) => write_number(n, out),
+        Value::Array(items) => {
+            out.push(b'[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_value(item, out);
+            }
+            out.push(b']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+            out.push(b'{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                out.extend_from_slice(Value::String(key.clone()).to_string().as_bytes());
+                out.push(b':');
+                write_value(item, out);
+            }
+            out.push(b'}');
+        }
+        other => out.extend_from_slice(other.to_string().as_bytes()),
+    }
+}
+
+/// `415.0` and `415` encode the same, as `415`; `-0.0` encodes as `0`.
+fn write_number(n: &Number, out: &mut Vec<u8>) {
+    match n.as_f64() {
+        Some(f) if n.is_f64() && f.fract() == 0.0 && f.abs() < MAX_EXACT_FLOAT => {
+            out.extend_from_slice((f as i64).to_string().as_bytes());
+        }
+        _ => out.extend_from_slice(n.to_string().as_bytes()),
+    }
+}
+
+impl EcoBlockContext {
+    pub fn set_encoding_policy(&mut self, policy: EncodingPolicy) -> Result<(), BridgeError> {
+        policy.validate()?;
+        self.encoding = policy;
+        Ok(())
+    }
+
+    /// The canonical signature of a block this node creates, if it signs canonically.
+    pub(crate) fn canonical_block_signature(&self, block: &TangleBlock) -> Option<String> {
+        (!self.encoding.sign.is_legacy()).then(|| sign_block(block, &self.keypair))
+    }
+
+    /// How `block` goes out to peers: with its canonical signature when one is
+    /// known, else in the legacy envelope.
+    pub(crate) fn sensor_envelope(&self, block: &TangleBlock) -> BlockEnvelope {
+        match self.block_signatures.get(&block.id) {
+            Some(signature) => BlockEnvelope::CanonicalSensor(CanonicalBlock {
+                block: block.clone(),
+                signature: signature.clone(),
+            }),
+            None => BlockEnvelope::Sensor(block.clone()),
+        }
+    }
+
+    /// `accept_block` for a block received with a verified canonical signature,
+    /// which is kept so the block is stored and forwarded with it.
+    pub(crate) fn accept_signed_block(&mut self, block: TangleBlock, signature: Option<String>, from: Option<&PeerId>) -> Result<bool, BridgeError> {
+        let Some(signature) = signature else {
+            return self.accept_block(block, from);
+        };
+        let id = block.id.clone();
+        let known = self.block_signatures.contains_key(&id);
+        if !known {
+            self.block_signatures.insert(id.clone(), signature);
+        }
+        let result = self.accept_block(block, from);
+        if result.is_err() && !known {
+            self.block_signatures.remove(&id);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ecoblock_core::domain::tangle_data::TangleBlockData;
+    use ecoblock_core::domain::SensorData;
+    use serde_json::json;
+    use super::*;
+
+    fn block(keypair: &CryptoKeypair) -> TangleBlock {
+        let data = SensorData {
+            pm25: 12.5,
+            co2: 415.0,
+            temperature: 21.25,
+            humidity: 48.0,
+            timestamp: 1_700_000_000,
+        };
+        TangleBlock::new(TangleBlockData { parents: Vec::new(), data }, keypair)
+    }
+
+    #[test]
+    fn keys_are_sorted_and_integral_floats_written_as_integers() {
+        let a: Value = serde_json::from_str(r#"{"b":{"y":1,"x":415.0},"a":[2.5,-0.0],"é":null,"Z":true}"#).unwrap();
+        let b: Value = serde_json::from_str(r#"{"Z":true,"é":null,"a":[2.5,0],"b":{"x":415,"y":1}}"#).unwrap();
+        let expected = r#"{"Z":true,"a":[2.5,0],"b":{"x":415,"y":1},"é":null}"#;
+        assert_eq!(String::from_utf8(to_vec(&a).unwrap()).unwrap(), expected);
+        assert_eq!(to_vec(&a).unwrap(), to_vec(&b).unwrap());
+        assert_eq!(String::from_utf8(message(&json!({ "k": 1 })).unwrap()).unwrap(), r#"["ecoblock-canonical-v1",{"k":1}]"#);
+    }
+
+    #[test]
+    fn canonical_block_messages_survive_a_json_round_trip() {
+        let keypair = CryptoKeypair::generate();
+        let block = block(&keypair);
+        let signature = sign_block(&block, &keypair);
+        let envelope = BlockEnvelope::CanonicalSensor(CanonicalBlock { block: block.clone(), signature: signature.clone() });
+
+        let bytes = serde_json::to_vec(&envelope).unwrap();
+        let Ok(BlockEnvelope::CanonicalSensor(parsed)) = serde_json::from_slice::<BlockEnvelope>(&bytes) else {
+            panic!("not a canonical envelope");
+        };
+        assert_eq!(block_message(&parsed.block, Encoding::Canonical), block_message(&block, Encoding::Canonical));
+        assert!(verify_block_signature(&parsed.block, &parsed.signature));
+        assert!(!verify_block_signature(&parsed.block, &sign_block(&parsed.block, &CryptoKeypair::generate())));
+    }
+
+    #[test]
+    fn legacy_block_envelopes_are_accepted_only_while_the_policy_allows() {
+        let keypair = CryptoKeypair::generate();
+        let block = block(&keypair);
+        let legacy = BlockEnvelope::Sensor(block.clone());
+        let canonical = BlockEnvelope::CanonicalSensor(CanonicalBlock { signature: sign_block(&block, &keypair), block: block.clone() });
+        let forged = BlockEnvelope::CanonicalSensor(CanonicalBlock { signature: hex::encode([0u8; 64]), block });
+
+        let mut ctx = EcoBlockContext::new();
+        assert!(ctx.verify_block_envelope(&legacy));
+        assert!(ctx.verify_block_envelope(&canonical));
+        ctx.set_encoding_policy(EncodingPolicy { sign: Encoding::Canonical, accept_legacy: false }).unwrap();
+        assert!(!ctx.verify_block_envelope(&legacy));
+        assert!(ctx.verify_block_envelope(&canonical));
+        assert!(!ctx.verify_block_envelope(&forged));
+    }
+
+    #[test]
+    fn blocks_created_while_signing_canonically_go_out_with_their_signature() {
+        let mut ctx = EcoBlockContext::new();
+        let payload = json!({ "pm25": 12.5, "co2": 415.0, "temperature": 21.25, "humidity": 48.0, "timestamp": 1_700_000_000 });
+        let data = serde_json::to_vec(&payload).unwrap();
+        let legacy = ctx.prepare_block(data.clone(), Vec::new()).unwrap();
+        assert!(legacy.signature.is_none());
+
+        ctx.set_encoding_policy(EncodingPolicy { sign: Encoding::Canonical, accept_legacy: true }).unwrap();
+        let prepared = ctx.prepare_block(data, Vec::new()).unwrap();
+        let block = prepared.block.clone();
+        ctx.commit_block(prepared);
+        let BlockEnvelope::CanonicalSensor(envelope) = ctx.sensor_envelope(&block) else {
+            panic!("not a canonical envelope");
+        };
+        assert!(ctx.verify_block_envelope(&BlockEnvelope::CanonicalSensor(envelope)));
+    }
+}
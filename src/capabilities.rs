@@ -0,0 +1,83 @@
+//! What the running binary can do: the optional features it was built with
+//! and the transports and backends currently active, so host apps and admin
+//! tools can adapt their UI at runtime instead of failing on a missing call.
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use crate::hybrid::HybridPolicy;
+use crate::v2::{API_VERSION, MIN_API_VERSION};
+use crate::versions::local_build;
+use crate::{clock, EcoBlockContext};
+
+/// The optional Cargo features of the crate and whether this build has them.
+pub const FEATURES: [(&str, bool); 7] = [
+    ("bench", cfg!(feature = "bench")),
+    ("graphql", cfg!(feature = "graphql")),
+    ("pq-hybrid", cfg!(feature = "pq-hybrid")),
+    ("presentation", cfg!(feature = "presentation")),
+    ("scripting", cfg!(feature = "scripting")),
+    ("sensorthings", cfg!(feature = "sensorthings")),
+    ("sparkplug", cfg!(feature = "sparkplug")),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActiveBackends {
+    /// Name of the attached transport, such as `udp`.
+    pub transport: Option<String>,
+    pub block_store: bool,
+    /// Set by the host; S3 backups and cloud sync need it.
+    pub http_client: bool,
+    pub s3_backup: bool,
+    pub cloud_sync: bool,
+    pub home_assistant: bool,
+    pub gateway: bool,
+    pub data_mule: bool,
+    /// Serving other processes over the IPC socket; always false off Unix.
+    pub ipc: bool,
+    pub hybrid_policy: HybridPolicy,
+    /// False when `single_threaded` is set and background work waits for
+    /// `run_background_step`.
+    pub background_threads: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapabilityReport {
+    pub crate_version: String,
+    pub git_hash: Option<String>,
+    /// Target OS and architecture, e.g. `android-aarch64`.
+    pub platform: String,
+    pub api_versions: Vec<u32>,
+    /// Every optional feature, true for those compiled in.
+    pub features: BTreeMap<String, bool>,
+    pub active: ActiveBackends,
+}
+
+impl EcoBlockContext {
+    pub fn capabilities(&self) -> CapabilityReport {
+        let build = local_build(&self.keypair.public_key_hex(), clock::now_secs());
+        #[cfg(unix)]
+        let ipc = self.ipc.is_serving();
+        #[cfg(not(unix))]
+        let ipc = false;
+        CapabilityReport {
+            crate_version: build.crate_version,
+            git_hash: build.git_hash,
+            platform: build.platform,
+            api_versions: (MIN_API_VERSION..=API_VERSION).collect(),
+            features: FEATURES.iter().map(|(name, on)| (name.to_string(), *on)).collect(),
+            active: ActiveBackends {
+                transport: self.sync.transport_name(),
+                block_store: self.block_store.is_some(),
+                http_client: self.http_client.is_some(),
+                s3_backup: self.config.backup.is_some(),
+                cloud_sync: self.cloud_sync.status().enabled,
+                home_assistant: self.home_assistant.config().is_some(),
+                gateway: self.gateway.config().is_some(),
+                data_mule: self.data_mule.config().is_some(),
+                ipc,
+                hybrid_policy: self.hybrid.policy,
+                background_threads: !self.config.single_threaded,
+            },
+        }
+    }
+}
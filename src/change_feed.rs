@@ -0,0 +1,97 @@
+use std::collections::VecDeque;
+use serde::{Deserialize, Serialize};
+use crate::{clock, EcoBlockContext};
+
+pub const DEFAULT_FEED_CAPACITY: usize = 10_000;
+/// Most changes returned by one `changes_since` call.
+pub const MAX_CHANGES_PER_CALL: usize = 1_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChangeKind {
+    BlockInserted,
+    BlockRemoved,
+    PeerAdded,
+    PeerUpdated,
+    PeerRemoved,
+    /// Key is `from->to`.
+    ConnectionAdded,
+    /// Key is the shared config key, or `policy` for a new mesh policy.
+    ConfigUpdated,
+    /// Changes after the cursor were dropped from the feed; refresh everything.
+    Reset,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeRecord {
+    /// Pass the last `seq` seen back as the cursor.
+    pub seq: u64,
+    pub kind: ChangeKind,
+    pub key: String,
+    pub at: u64,
+}
+
+#[derive(Debug)]
+pub struct ChangeFeed {
+    capacity: usize,
+    next_seq: u64,
+    changes: VecDeque<ChangeRecord>,
+}
+
+impl Default for ChangeFeed {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_FEED_CAPACITY,
+            next_seq: 1,
+            changes: VecDeque::new(),
+        }
+    }
+}
+
+impl ChangeFeed {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, kind: ChangeKind, key: String, now: u64) {
+        if self.changes.len() == self.capacity {
+            self.changes.pop_front();
+        }
+        self.changes.push_back(ChangeRecord {
+            seq: self.next_seq,
+            kind,
+            key,
+            at: now,
+        });
+        self.next_seq += 1;
+    }
+
+    /// Changes after `cursor`, oldest first. Starts with a `Reset` record when
+    /// some of them have already been dropped.
+    pub fn since(&self, cursor: u64) -> Vec<ChangeRecord> {
+        let mut out = Vec::new();
+        if let Some(oldest) = self.changes.front() {
+            if oldest.seq > cursor + 1 {
+                out.push(ChangeRecord {
+                    seq: oldest.seq - 1,
+                    kind: ChangeKind::Reset,
+                    key: String::new(),
+                    at: oldest.at,
+                });
+            }
+        }
+        let skip = self.changes.iter().take_while(|c| c.seq <= cursor).count();
+        out.extend(self.changes.iter().skip(skip).take(MAX_CHANGES_PER_CALL - out.len()).cloned());
+        out
+    }
+
+    /// Sequence number of the latest change, usable as a cursor.
+    pub fn head(&self) -> u64 {
+        self.next_seq - 1
+    }
+}
+
+impl EcoBlockContext {
+    pub(crate) fn record_change(&mut self, kind: ChangeKind, key: impl Into<String>) {
+        self.changes.push(kind, key.into(), clock::now_secs());
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+
+pub const CHANNEL_MESSAGE_KIND: &str = "channel_message";
+pub const DEFAULT_CHANNEL: &str = "default";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Visibility {
+    Public,
+    Members,
+    Admins,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EncryptedPayload {
+    pub channel: String,
+    pub tier: Visibility,
+    pub epoch: u32,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ChannelPayload {
+    Plain(serde_json::Value),
+    Sealed(EncryptedPayload),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelMessage {
+    pub id: String,
+    pub channel: String,
+    pub author: String,
+    pub timestamp: u64,
+    pub visibility: Visibility,
+    /// `None` when the local node holds no key for the message's tier and epoch.
+    pub data: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelMessageBody {
+    pub channel: String,
+    pub payload: ChannelPayload,
+}
+
+type EpochKeys = Vec<(u32, [u8; 32])>;
+
+#[derive(Debug, Default)]
+pub struct ChannelRegistry {
+    visibility: HashMap<String, Visibility>,
+    keys: HashMap<(String, Visibility), EpochKeys>,
+}
+
+impl ChannelRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_visibility(&mut self, channel: &str, visibility: Visibility) {
+        self.visibility.insert(channel.to_string(), visibility);
+    }
+
+    pub fn visibility(&self, channel: &str) -> Visibility {
+        self.visibility.get(channel).copied().unwrap_or(Visibility::Public)
+    }
+
+    pub fn channels(&self) -> Vec<(String, Visibility)> {
+        let mut channels: Vec<(String, Visibility)> = self.visibility.iter().map(|(c, v)| (c.clone(), *v)).collect();
+        channels.sort();
+        channels
+    }
+
+    pub fn current_epoch(&self, channel: &str, tier: Visibility) -> Option<u32> {
+        self.keys
+            .get(&(channel.to_string(), tier))
+            .and_then(|keys| keys.iter().map(|(epoch, _)| *epoch).max())
+    }
+
+    pub fn key(&self, channel: &str, tier: Visibility, epoch: u32) -> Option<[u8; 32]> {
+        self.keys
+            .get(&(channel.to_string(), tier))
+            .and_then(|keys| keys.iter().find(|(e, _)| *e == epoch))
+            .map(|(_, key)| *key)
+    }
+
+    pub fn install_key(&mut self, channel: &str, tier: Visibility, epoch: u32, key: [u8; 32]) {
+        let keys = self.keys.entry((channel.to_string(), tier)).or_default();
+        keys.retain(|(e, _)| *e != epoch);
+        keys.push((epoch, key));
+    }
+
+    pub fn all_keys(&self) -> Vec<(String, Visibility, u32, [u8; 32])> {
+        let mut keys: Vec<(String, Visibility, u32, [u8; 32])> = self
+            .keys
+            .iter()
+            .flat_map(|((channel, tier), epochs)| {
+                epochs.iter().map(move |(epoch, key)| (channel.clone(), *tier, *epoch, *key))
+            })
+            .collect();
+        keys.sort_by(|a, b| (&a.0, a.1, a.2).cmp(&(&b.0, b.1, b.2)));
+        keys
+    }
+
+    /// Generates a fresh key for the next epoch of `channel`/`tier`.
+    pub fn rotate_key(&mut self, channel: &str, tier: Visibility) -> (u32, [u8; 32]) {
+        let epoch = self.current_epoch(channel, tier).map_or(0, |e| e + 1);
+        let key: [u8; 32] = ChaCha20Poly1305::generate_key(&mut OsRng).into();
+        self.install_key(channel, tier, epoch, key);
+        (epoch, key)
+    }
+
+    pub fn seal(&mut self, channel: &str, data: serde_json::Value) -> Result<ChannelPayload, BridgeError> {
+        let tier = self.visibility(channel);
+        if tier == Visibility::Public {
+            return Ok(ChannelPayload::Plain(data));
+        }
+        let (epoch, key) = match self.current_epoch(channel, tier) {
+            Some(epoch) => (epoch, self.key(channel, tier, epoch).unwrap_or_default()),
+            None => self.rotate_key(channel, tier),
+        };
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let plaintext = serde_json::to_vec(&data).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|e| BridgeError::Crypto(format!("{:?}", e)))?;
+        Ok(ChannelPayload::Sealed(EncryptedPayload {
+            channel: channel.to_string(),
+            tier,
+            epoch,
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        }))
+    }
+
+    pub fn open(&self, payload: &ChannelPayload) -> Result<serde_json::Value, BridgeError> {
+        let sealed = match payload {
+            ChannelPayload::Plain(data) => return Ok(data.clone()),
+            ChannelPayload::Sealed(sealed) => sealed,
+        };
+        let key = self
+            .key(&sealed.channel, sealed.tier, sealed.epoch)
+            .ok_or_else(|| BridgeError::NotFound(format!("No key for channel {} epoch {}", sealed.channel, sealed.epoch)))?;
+        let nonce = hex::decode(&sealed.nonce).map_err(|e| BridgeError::Decoding(e.to_string()))?;
+        if nonce.len() != 12 {
+            return Err(BridgeError::Decoding("invalid nonce length".to_string()));
+        }
+        let ciphertext = hex::decode(&sealed.ciphertext).map_err(|e| BridgeError::Decoding(e.to_string()))?;
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|e| BridgeError::Crypto(format!("{:?}", e)))?;
+        serde_json::from_slice(&plaintext).map_err(|e| BridgeError::Deserialization(e.to_string()))
+    }
+}
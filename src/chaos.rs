@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use crate::simulator::{Delivery, DeliveryFilter};
+
+/// Fault injection settings. Rates are probabilities in `[0, 1]`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ChaosConfig {
+    pub drop_rate: f64,
+    pub delay_rate: f64,
+    /// Upper bound for injected delays, in simulator ticks or transport milliseconds.
+    pub max_delay: u64,
+    pub write_failure_rate: f64,
+    pub clock_skew_secs: i64,
+    pub seed: u64,
+}
+
+/// Deterministic fault source shared by transports, stores and the simulator.
+#[derive(Debug, Clone)]
+pub struct ChaosInjector {
+    config: ChaosConfig,
+    state: u64,
+}
+
+impl ChaosInjector {
+    pub fn new(config: ChaosConfig) -> Self {
+        let state = config.seed.max(1);
+        Self { config, state }
+    }
+
+    pub fn config(&self) -> &ChaosConfig {
+        &self.config
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn roll(&mut self, rate: f64) -> bool {
+        rate > 0.0 && self.next_f64() < rate
+    }
+
+    pub fn drop_message(&mut self) -> bool {
+        self.roll(self.config.drop_rate)
+    }
+
+    /// Delay to apply to a message, if one is injected.
+    pub fn delay(&mut self) -> Option<u64> {
+        if self.config.max_delay == 0 || !self.roll(self.config.delay_rate) {
+            return None;
+        }
+        Some(1 + (self.next_f64() * self.config.max_delay as f64) as u64 % self.config.max_delay)
+    }
+
+    pub fn fail_write(&mut self) -> bool {
+        self.roll(self.config.write_failure_rate)
+    }
+}
+
+impl DeliveryFilter for ChaosInjector {
+    fn deliver(&mut self, _tick: u64, _from: &str, _to: &str) -> Delivery {
+        if self.drop_message() {
+            return Delivery::Drop;
+        }
+        match self.delay() {
+            Some(ticks) => Delivery::Delay(ticks),
+            None => Delivery::Now,
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static SKEW_SECS: AtomicI64 = AtomicI64::new(0);
+
+/// Shifts every bridge timestamp by `secs`, for clock skew fault injection.
+pub fn set_skew(secs: i64) {
+    SKEW_SECS.store(secs, Ordering::Relaxed);
+}
+
+pub fn now_secs() -> u64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    now.saturating_add_signed(SKEW_SECS.load(Ordering::Relaxed))
+}
+
+pub fn now_millis() -> u64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    now.saturating_add_signed(SKEW_SECS.load(Ordering::Relaxed).saturating_mul(1000))
+}
+
+/// `secs` since the Unix epoch as an ISO 8601 UTC time, e.g. `2024-05-01T12:00:00Z`.
+pub fn iso8601(secs: u64) -> String {
+    let (days, rem) = ((secs / 86_400) as i64, secs % 86_400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, rem / 3_600, rem % 3_600 / 60, rem % 60)
+}
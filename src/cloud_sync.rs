@@ -0,0 +1,263 @@
+//! Cloud uplink. A gateway can mirror its tangle to a remote EcoBlock service
+//! over HTTPS and pull the blocks other gateways pushed there. Each round is
+//! one `POST` carrying the same messages as mesh anti-entropy: a summary of
+//! recent block ids and the ids wanted, plus blocks added since the last round
+//! and those the service asked for. The service answers in kind. Blocks are
+//! content-addressed and only ever added, so the cloud and mesh copies
+//! converge whatever order rounds and gossip happen in.
+
+use std::collections::BTreeSet;
+use ecoblock_storage::tangle::block::TangleBlock;
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+use crate::have_lists::MAX_HAVE_IDS;
+use crate::http::{self, HttpRequest};
+use crate::logs::LogLevel;
+use crate::records::Record;
+use crate::sync::SyncMessage;
+use crate::{clock, EcoBlockContext};
+
+/// Blocks sent per round when the config does not say.
+pub const DEFAULT_MAX_BLOCKS: usize = 500;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CloudSyncConfig {
+    /// Base URL of the service; rounds are posted to `<url>/sync`.
+    pub url: String,
+    /// Sent as a bearer token.
+    #[serde(default)]
+    pub token: Option<String>,
+    pub interval_secs: u64,
+    /// Most blocks pushed per round; the rest follow in later rounds.
+    #[serde(default)]
+    pub max_blocks: Option<usize>,
+}
+
+impl CloudSyncConfig {
+    pub fn validate(&self) -> Result<(), BridgeError> {
+        http::split_url(&self.url)?;
+        if self.interval_secs == 0 || self.max_blocks == Some(0) {
+            return Err(BridgeError::InvalidInput("Invalid cloud sync config: interval_secs and max_blocks must be positive".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Body of a round, in both directions.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CloudExchange {
+    pub node_id: String,
+    /// Announcements, so the receiver accepts the blocks of their authors.
+    #[serde(default)]
+    pub records: Vec<Record>,
+    #[serde(default)]
+    pub blocks: Vec<TangleBlock>,
+    #[serde(default)]
+    pub messages: Vec<SyncMessage>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CloudSyncReport {
+    pub pushed: usize,
+    /// New blocks stored from the service.
+    pub pulled: usize,
+    /// Ids asked for in the next round.
+    pub wanted: usize,
+    pub at: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CloudSyncStatus {
+    pub enabled: bool,
+    pub last_round: Option<CloudSyncReport>,
+    pub last_error: Option<String>,
+    pub pushed_total: u64,
+    pub pulled_total: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct CloudSync {
+    config: Option<CloudSyncConfig>,
+    /// Block log position pushed so far.
+    cursor: usize,
+    /// Ids the service asked for.
+    requested: BTreeSet<String>,
+    /// Ids to ask the service for.
+    missing: BTreeSet<String>,
+    last_attempt: Option<u64>,
+    status: CloudSyncStatus,
+}
+
+impl CloudSync {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn config(&self) -> Option<&CloudSyncConfig> {
+        self.config.as_ref()
+    }
+
+    pub fn status(&self) -> CloudSyncStatus {
+        CloudSyncStatus {
+            enabled: self.config.is_some(),
+            ..self.status.clone()
+        }
+    }
+}
+
+impl EcoBlockContext {
+    /// Starts mirroring to the service. The first round pushes every block.
+    pub fn enable_cloud_sync(&mut self, config: CloudSyncConfig) -> Result<(), BridgeError> {
+        config.validate()?;
+        self.cloud_sync = CloudSync {
+            config: Some(config),
+            ..CloudSync::default()
+        };
+        Ok(())
+    }
+
+    pub fn disable_cloud_sync(&mut self) -> bool {
+        self.cloud_sync.config.take().is_some()
+    }
+
+    /// The next round's body, and the log position it pushes up to.
+    fn cloud_exchange(&mut self, limit: usize) -> Result<(CloudExchange, usize), BridgeError> {
+        // Drops ids that arrived over the mesh or that this node never held.
+        let log = &self.block_log;
+        self.cloud_sync.missing.retain(|id| !log.contains(id));
+        self.cloud_sync.requested.retain(|id| log.contains(id));
+        let mut blocks: Vec<TangleBlock> = self
+            .cloud_sync
+            .requested
+            .iter()
+            .filter_map(|id| self.block_log.get(id).cloned())
+            .take(limit)
+            .collect();
+        let mut cursor = self.block_log.end();
+        for (position, block) in self.block_log.positions().filter(|(i, _)| *i >= self.cloud_sync.cursor) {
+            if blocks.len() == limit {
+                cursor = position;
+                break;
+            }
+            if !self.cloud_sync.requested.contains(&block.id) {
+                blocks.push(block.clone());
+            }
+        }
+        let mut messages = vec![SyncMessage::Summary {
+            ids: self.summary_ids(),
+            reply: false,
+        }];
+        if !self.cloud_sync.missing.is_empty() {
+            messages.push(SyncMessage::Want {
+                ids: self.cloud_sync.missing.iter().take(MAX_HAVE_IDS).cloned().collect(),
+            });
+        }
+        let exchange = CloudExchange {
+            node_id: self.keypair.public_key_hex(),
+            records: vec![self.own_announcement()?],
+            blocks,
+            messages,
+        };
+        Ok((exchange, cursor))
+    }
+
+    /// Runs one round with the service now. Blocks pulled are stored and
+    /// gossiped to the mesh like blocks from a peer.
+    pub fn cloud_sync_now(&mut self) -> Result<CloudSyncReport, BridgeError> {
+        let config = self.cloud_sync.config.clone().ok_or_else(|| BridgeError::InvalidInput("Invalid cloud sync: not enabled".to_string()))?;
+        if self.http_client.is_none() {
+            return Err(BridgeError::NotFound("Unknown HTTP client: none is set".to_string()));
+        }
+        let (exchange, cursor) = self.cloud_exchange(config.max_blocks.unwrap_or(DEFAULT_MAX_BLOCKS))?;
+        let body = serde_json::to_vec(&exchange).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        let mut headers = vec![("content-type".to_string(), "application/json".to_string())];
+        if let Some(token) = &config.token {
+            headers.push(("authorization".to_string(), format!("Bearer {}", token)));
+        }
+        let request = HttpRequest {
+            method: "POST".to_string(),
+            url: format!("{}/sync", config.url.trim_end_matches('/')),
+            headers,
+            body,
+        };
+        let client = self.http_client.as_ref().ok_or_else(|| BridgeError::NotFound("Unknown HTTP client: none is set".to_string()))?;
+        let response = client.execute(&request)?;
+        match response.status {
+            401 | 403 => return Err(BridgeError::Unauthorized(format!("cloud service answered {}", response.status))),
+            status if !response.is_success() => return Err(BridgeError::Io(format!("cloud service answered {}", status))),
+            _ => {}
+        }
+        let reply: CloudExchange = serde_json::from_slice(&response.body).map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+        let pushed = exchange.blocks.len();
+        for block in &exchange.blocks {
+            self.cloud_sync.requested.remove(&block.id);
+        }
+        // Blocks pulled in this round need not be pushed back.
+        let caught_up = cursor == self.block_log.end();
+        let pulled = self.apply_cloud_exchange(reply);
+        self.cloud_sync.cursor = if caught_up { self.block_log.end() } else { cursor };
+        let report = CloudSyncReport {
+            pushed,
+            pulled,
+            wanted: self.cloud_sync.missing.len(),
+            at: clock::now_secs(),
+        };
+        let status = &mut self.cloud_sync.status;
+        status.last_round = Some(report);
+        status.pushed_total += pushed as u64;
+        status.pulled_total += pulled as u64;
+        Ok(report)
+    }
+
+    /// Stores what the service sent and notes what it asked for.
+    fn apply_cloud_exchange(&mut self, reply: CloudExchange) -> usize {
+        for record in reply.records {
+            if let Err(e) = self.receive_record(record) {
+                self.log(LogLevel::Warn, format!("Refused record from cloud service: {}", e));
+            }
+        }
+        let mut pulled = 0;
+        for block in reply.blocks {
+            let (id, parents) = (block.id.clone(), block.data.parents.clone());
+            match self.receive_block(block, None) {
+                Ok(stored) => pulled += usize::from(stored),
+                Err(e) => {
+                    self.log(LogLevel::Warn, format!("Refused block {} from cloud service: {}", id, e));
+                    continue;
+                }
+            }
+            self.cloud_sync.missing.remove(&id);
+            let missing: Vec<String> = parents.into_iter().filter(|p| !self.block_log.contains(p)).collect();
+            self.cloud_sync.missing.extend(missing);
+        }
+        for message in reply.messages {
+            match message {
+                SyncMessage::Summary { ids, .. } => {
+                    let missing: Vec<String> = ids.into_iter().filter(|id| !self.block_log.contains(id)).take(MAX_HAVE_IDS).collect();
+                    self.cloud_sync.missing.extend(missing);
+                }
+                SyncMessage::Want { ids } => self.cloud_sync.requested.extend(ids.into_iter().take(MAX_HAVE_IDS)),
+            }
+        }
+        pulled
+    }
+
+    /// Runs a round when one is due and an HTTP client is set.
+    pub(crate) fn run_scheduled_cloud_sync(&mut self, now: u64) {
+        let Some(config) = &self.cloud_sync.config else {
+            return;
+        };
+        let due = self.cloud_sync.last_attempt.is_none_or(|at| now.saturating_sub(at) >= config.interval_secs);
+        if !due || self.http_client.is_none() {
+            return;
+        }
+        self.cloud_sync.last_attempt = Some(now);
+        match self.cloud_sync_now() {
+            Ok(_) => self.cloud_sync.status.last_error = None,
+            Err(e) => {
+                self.log(LogLevel::Warn, format!("Cloud sync failed: {}", e));
+                self.cloud_sync.status.last_error = Some(e.to_string());
+            }
+        }
+    }
+}
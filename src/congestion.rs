@@ -0,0 +1,150 @@
+use std::collections::{BTreeMap, HashMap};
+use serde::{Deserialize, Serialize};
+use crate::block_id::BlockId;
+use crate::peer_id::PeerId;
+use crate::{clock, EcoBlockContext};
+
+pub const INITIAL_WINDOW: f64 = 2.0;
+pub const MIN_WINDOW: f64 = 1.0;
+pub const MAX_WINDOW: f64 = 64.0;
+pub const INITIAL_SSTHRESH: f64 = 16.0;
+/// Unacknowledged sends older than this count as lost.
+pub const DEFAULT_ACK_TIMEOUT_MS: u64 = 5_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerWindow {
+    pub peer_id: PeerId,
+    pub window: f64,
+    pub ssthresh: f64,
+    pub in_flight: usize,
+    pub acked: u64,
+    pub lost: u64,
+}
+
+/// Slow start up to `ssthresh`, then additive increase; a loss halves
+/// `ssthresh` and drops the window back to the minimum.
+#[derive(Debug, Clone)]
+struct SendWindow {
+    window: f64,
+    ssthresh: f64,
+    in_flight: HashMap<String, u64>,
+    acked: u64,
+    lost: u64,
+}
+
+impl Default for SendWindow {
+    fn default() -> Self {
+        Self {
+            window: INITIAL_WINDOW,
+            ssthresh: INITIAL_SSTHRESH,
+            in_flight: HashMap::new(),
+            acked: 0,
+            lost: 0,
+        }
+    }
+}
+
+impl SendWindow {
+    fn on_ack(&mut self) {
+        self.acked += 1;
+        self.window = if self.window < self.ssthresh {
+            self.window + 1.0
+        } else {
+            self.window + 1.0 / self.window
+        }
+        .min(MAX_WINDOW);
+    }
+
+    fn on_loss(&mut self) {
+        self.lost += 1;
+        self.ssthresh = (self.window / 2.0).max(MIN_WINDOW * 2.0);
+        self.window = MIN_WINDOW;
+    }
+}
+
+#[derive(Debug)]
+pub struct CongestionControl {
+    pub ack_timeout_ms: u64,
+    windows: BTreeMap<String, SendWindow>,
+}
+
+impl Default for CongestionControl {
+    fn default() -> Self {
+        Self {
+            ack_timeout_ms: DEFAULT_ACK_TIMEOUT_MS,
+            windows: BTreeMap::new(),
+        }
+    }
+}
+
+impl CongestionControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Times out stale sends to `peer`, then admits as many candidates as its window allows.
+    pub fn admit(&mut self, peer: &str, candidates: Vec<BlockId>, now_ms: u64) -> Vec<BlockId> {
+        let timeout = self.ack_timeout_ms;
+        let window = self.windows.entry(peer.to_string()).or_default();
+        let expired: Vec<String> = window
+            .in_flight
+            .iter()
+            .filter(|(_, sent)| now_ms.saturating_sub(**sent) > timeout)
+            .map(|(id, _)| id.clone())
+            .collect();
+        if !expired.is_empty() {
+            for id in &expired {
+                window.in_flight.remove(id);
+            }
+            window.on_loss();
+        }
+        let room = (window.window.floor() as usize).saturating_sub(window.in_flight.len());
+        let admitted: Vec<BlockId> = candidates
+            .into_iter()
+            .filter(|id| !window.in_flight.contains_key(id.as_str()))
+            .take(room)
+            .collect();
+        for id in &admitted {
+            window.in_flight.insert(id.to_string(), now_ms);
+        }
+        admitted
+    }
+
+    /// Returns false for acks of blocks that were not in flight.
+    pub fn ack(&mut self, peer: &str, id: &str) -> bool {
+        let Some(window) = self.windows.get_mut(peer) else {
+            return false;
+        };
+        if window.in_flight.remove(id).is_none() {
+            return false;
+        }
+        window.on_ack();
+        true
+    }
+
+    pub fn windows(&self) -> Vec<PeerWindow> {
+        self.windows
+            .iter()
+            .map(|(peer, w)| PeerWindow {
+                peer_id: PeerId::from_mesh(peer.clone()),
+                window: w.window,
+                ssthresh: w.ssthresh,
+                in_flight: w.in_flight.len(),
+                acked: w.acked,
+                lost: w.lost,
+            })
+            .collect()
+    }
+}
+
+impl EcoBlockContext {
+    /// Blocks to send to `peer` now: those it has not advertised, limited by its send window.
+    pub fn schedule_sends(&mut self, peer: &PeerId, candidates: Vec<BlockId>) -> Vec<BlockId> {
+        let wanted = self.blocks_to_send(peer, candidates);
+        self.congestion.admit(peer.as_str(), wanted, clock::now_millis())
+    }
+
+    pub fn ack_block(&mut self, peer: &PeerId, id: &BlockId) -> bool {
+        self.congestion.ack(peer.as_str(), id.as_str())
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use crate::peer_id::PeerId;
+use crate::aggregate::MetricAggregate;
+use crate::thresholds::ActiveAlert;
+
+pub const DAY_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatestValue {
+    pub sensor_id: String,
+    pub metric: String,
+    pub value: f64,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricRange {
+    pub metric: String,
+    pub min: f64,
+    pub max: f64,
+    pub count: usize,
+}
+
+impl From<MetricAggregate> for MetricRange {
+    fn from(aggregate: MetricAggregate) -> Self {
+        Self {
+            metric: aggregate.metric,
+            min: aggregate.min,
+            max: aggregate.max,
+            count: aggregate.count,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerHealth {
+    pub peer_id: PeerId,
+    pub link_weight: f32,
+    /// Timestamp of the newest record signed by the peer, if any was received.
+    pub last_seen: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DashboardSummary {
+    pub node_id: String,
+    pub generated_at: u64,
+    pub tangle_size: usize,
+    pub latest: Vec<LatestValue>,
+    pub last_24h: Vec<MetricRange>,
+    pub active_alerts: Vec<ActiveAlert>,
+    pub silent_sensors: Vec<String>,
+    pub peers: Vec<PeerHealth>,
+}
+
+/// Latest value per `(sensor, metric)`, sorted by sensor then metric.
+pub fn latest_values<'a, I>(values: I) -> Vec<LatestValue>
+where
+    I: IntoIterator<Item = (&'a str, &'a str, f64, u64)>,
+{
+    let mut latest: BTreeMap<(String, String), (f64, u64)> = BTreeMap::new();
+    for (sensor_id, metric, value, timestamp) in values {
+        let entry = latest
+            .entry((sensor_id.to_string(), metric.to_string()))
+            .or_insert((value, timestamp));
+        if timestamp >= entry.1 {
+            *entry = (value, timestamp);
+        }
+    }
+    latest
+        .into_iter()
+        .map(|((sensor_id, metric), (value, timestamp))| LatestValue {
+            sensor_id,
+            metric,
+            value,
+            timestamp,
+        })
+        .collect()
+}
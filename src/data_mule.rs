@@ -0,0 +1,219 @@
+//! Courier mode for phones carrying blocks between nodes that never meet.
+//! The host reports each proximity contact (e.g. a BLE scan hit) with
+//! `report_encounter`; the bridge then syncs with that peer, bounded by the
+//! byte and time budgets of one encounter.
+
+use std::collections::{HashMap, VecDeque};
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+use crate::peer_id::PeerId;
+use crate::{clock, EcoBlockContext};
+
+/// Encounters kept in the log; older ones are dropped first.
+pub const MAX_LOGGED_ENCOUNTERS: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataMuleConfig {
+    /// Frame bytes sent and received per encounter.
+    pub max_bytes: u64,
+    /// Seconds an encounter may last before it is closed.
+    pub max_seconds: u64,
+}
+
+impl Default for DataMuleConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: 256 * 1024,
+            max_seconds: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EncounterOutcome {
+    /// Still exchanging.
+    Open,
+    /// The host reported that the contact was lost.
+    Ended,
+    /// Frames to the peer are dropped until the encounter closes.
+    ByteBudgetExhausted,
+    TimeBudgetExhausted,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Encounter {
+    pub id: u64,
+    pub peer: PeerId,
+    pub started_at: u64,
+    pub ended_at: Option<u64>,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub blocks_sent: u64,
+    pub blocks_received: u64,
+    pub outcome: EncounterOutcome,
+}
+
+impl Encounter {
+    fn bytes(&self) -> u64 {
+        self.bytes_sent + self.bytes_received
+    }
+
+    /// Seconds from start to close, or to `now` while still open.
+    pub fn duration(&self, now: u64) -> u64 {
+        self.ended_at.unwrap_or(now).saturating_sub(self.started_at)
+    }
+}
+
+#[derive(Default)]
+pub struct DataMuleState {
+    config: Option<DataMuleConfig>,
+    active: HashMap<PeerId, Encounter>,
+    log: VecDeque<Encounter>,
+    next_id: u64,
+}
+
+impl DataMuleState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn config(&self) -> Option<DataMuleConfig> {
+        self.config
+    }
+
+    /// Closed encounters, oldest first, followed by the open ones.
+    pub fn encounters(&self) -> Vec<Encounter> {
+        let mut active: Vec<Encounter> = self.active.values().cloned().collect();
+        active.sort_by_key(|e| e.id);
+        self.log.iter().cloned().chain(active).collect()
+    }
+
+    fn close(&mut self, peer: &PeerId, outcome: EncounterOutcome, now: u64) -> Option<Encounter> {
+        let mut encounter = self.active.remove(peer)?;
+        if encounter.outcome == EncounterOutcome::Open {
+            encounter.outcome = outcome;
+        }
+        encounter.ended_at = Some(now);
+        if self.log.len() == MAX_LOGGED_ENCOUNTERS {
+            self.log.pop_front();
+        }
+        self.log.push_back(encounter.clone());
+        Some(encounter)
+    }
+
+    /// Closes encounters that outlived the time budget.
+    pub fn expire(&mut self, now: u64) {
+        let Some(config) = self.config else {
+            return;
+        };
+        let expired: Vec<PeerId> = self
+            .active
+            .values()
+            .filter(|e| now.saturating_sub(e.started_at) >= config.max_seconds)
+            .map(|e| e.peer.clone())
+            .collect();
+        for peer in expired {
+            self.close(&peer, EncounterOutcome::TimeBudgetExhausted, now);
+        }
+    }
+
+    /// Charges a frame of `len` bytes to `peer`'s open encounter, if any, and
+    /// tells whether it may be sent.
+    pub(crate) fn admit_send(&mut self, peer: &PeerId, len: usize, is_block: bool, now: u64) -> bool {
+        self.expire(now);
+        let (Some(config), Some(encounter)) = (self.config, self.active.get_mut(peer)) else {
+            return true;
+        };
+        if encounter.outcome != EncounterOutcome::Open {
+            return false;
+        }
+        if encounter.bytes() + len as u64 > config.max_bytes {
+            encounter.outcome = EncounterOutcome::ByteBudgetExhausted;
+            return false;
+        }
+        encounter.bytes_sent += len as u64;
+        if is_block {
+            encounter.blocks_sent += 1;
+        }
+        true
+    }
+
+    pub(crate) fn record_received(&mut self, peer: &PeerId, len: usize) {
+        if let Some(encounter) = self.active.get_mut(peer) {
+            encounter.bytes_received += len as u64;
+        }
+    }
+
+    /// Counts a block received from `peer`; false outside an encounter.
+    pub(crate) fn record_block_received(&mut self, peer: &PeerId) -> bool {
+        let Some(encounter) = self.active.get_mut(peer) else {
+            return false;
+        };
+        encounter.blocks_received += 1;
+        true
+    }
+}
+
+impl EcoBlockContext {
+    /// Turns courier mode on; encounters already open keep their budgets.
+    pub fn enable_data_mule(&mut self, config: DataMuleConfig) -> Result<(), BridgeError> {
+        if config.max_bytes == 0 || config.max_seconds == 0 {
+            return Err(BridgeError::InvalidInput("Invalid data-mule budget: bytes and seconds must be positive".to_string()));
+        }
+        self.data_mule.config = Some(config);
+        Ok(())
+    }
+
+    /// Turns courier mode off and closes every open encounter.
+    pub fn disable_data_mule(&mut self) {
+        let now = clock::now_secs();
+        let peers: Vec<PeerId> = self.data_mule.active.keys().cloned().collect();
+        for peer in peers {
+            self.data_mule.close(&peer, EncounterOutcome::Ended, now);
+        }
+        self.data_mule.config = None;
+    }
+
+    /// Opens an encounter with `peer` and starts a sync with it. A peer that is
+    /// already in an open encounter keeps it, so repeated proximity callbacks
+    /// are harmless. Returns the encounter id.
+    pub fn report_encounter(&mut self, peer: &PeerId) -> Result<u64, BridgeError> {
+        let now = clock::now_secs();
+        self.data_mule.expire(now);
+        if self.data_mule.config.is_none() {
+            return Err(BridgeError::Rejected("Data-mule mode is off".to_string()));
+        }
+        if let Some(encounter) = self.data_mule.active.get(peer) {
+            return Ok(encounter.id);
+        }
+        if !self.sync.is_attached() {
+            return Err(BridgeError::NotFound("Unknown transport: none is attached".to_string()));
+        }
+        self.data_mule.next_id += 1;
+        let id = self.data_mule.next_id;
+        self.data_mule.active.insert(
+            peer.clone(),
+            Encounter {
+                id,
+                peer: peer.clone(),
+                started_at: now,
+                ended_at: None,
+                bytes_sent: 0,
+                bytes_received: 0,
+                blocks_sent: 0,
+                blocks_received: 0,
+                outcome: EncounterOutcome::Open,
+            },
+        );
+        if let Err(e) = self.sync_with_peer(peer) {
+            self.data_mule.active.remove(peer);
+            return Err(e);
+        }
+        Ok(id)
+    }
+
+    /// Closes the encounter with `peer` when the host loses contact with it.
+    pub fn end_encounter(&mut self, peer: &PeerId) -> Option<Encounter> {
+        self.data_mule.close(peer, EncounterOutcome::Ended, clock::now_secs())
+    }
+}
@@ -0,0 +1,110 @@
+//! Delivery deadlines. A caller can give a block or directed record a time
+//! after which it is no longer worth sending. Copies still waiting in the
+//! outbox (quiet hours, airtime budgets, wake windows) are dropped once it has
+//! passed, and each drop raises `DeliveryExpired`. Per-peer counts show how
+//! much deadline-bound traffic goes out in time.
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::events::BridgeEvent;
+use crate::logs::LogLevel;
+use crate::peer_id::PeerId;
+use crate::source_routing::RoutedMessage;
+use crate::wire::{self, BlockEnvelope, Frame, FrameKind};
+use crate::EcoBlockContext;
+
+/// Deadlines are forgotten this long after they pass.
+const KEEP_EXPIRED_SECS: u64 = 24 * 3600;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerDeliveryStats {
+    pub peer: PeerId,
+    /// Deadline-bound frames sent to the peer before their deadline.
+    pub on_time: u64,
+    /// Deadline-bound frames for the peer dropped as expired.
+    pub expired: u64,
+    /// `on_time` over both counts.
+    pub on_time_rate: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct DeliveryDeadlines {
+    deadlines: HashMap<String, u64>,
+    /// On-time and expired counts per peer.
+    peers: HashMap<PeerId, (u64, u64)>,
+}
+
+impl DeliveryDeadlines {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, id: &str, deadline: u64) {
+        self.deadlines.insert(id.to_string(), deadline);
+    }
+
+    pub fn deadline(&self, id: &str) -> Option<u64> {
+        self.deadlines.get(id).copied()
+    }
+
+    pub fn prune(&mut self, now: u64) {
+        self.deadlines.retain(|_, deadline| deadline.saturating_add(KEEP_EXPIRED_SECS) > now);
+    }
+
+    /// The id and deadline of the block or record `frame` carries, if it has one.
+    pub(crate) fn tracked(&self, frame: &Frame) -> Option<(String, u64)> {
+        if self.deadlines.is_empty() {
+            return None;
+        }
+        let id = match frame.kind {
+            FrameKind::Block => match wire::parse_block_envelope(&frame.payload).ok()? {
+                BlockEnvelope::Sensor(block) => block.id,
+                BlockEnvelope::CanonicalSensor(canonical) => canonical.block.id,
+                BlockEnvelope::HybridSensor(hybrid) => hybrid.block.id,
+                BlockEnvelope::Record(record) => record.id,
+            },
+            FrameKind::Routed => serde_json::from_slice::<RoutedMessage>(&frame.payload).ok()?.record.id,
+            _ => return None,
+        };
+        let deadline = self.deadline(&id)?;
+        Some((id, deadline))
+    }
+
+    pub fn stats(&self) -> Vec<PeerDeliveryStats> {
+        let mut stats: Vec<PeerDeliveryStats> = self
+            .peers
+            .iter()
+            .map(|(peer, (on_time, expired))| PeerDeliveryStats {
+                peer: peer.clone(),
+                on_time: *on_time,
+                expired: *expired,
+                on_time_rate: *on_time as f64 / (on_time + expired).max(1) as f64,
+            })
+            .collect();
+        stats.sort_by(|a, b| a.peer.cmp(&b.peer));
+        stats
+    }
+
+    /// On-time rate over all peers, or `None` before any deadline-bound send.
+    pub fn on_time_rate(&self) -> Option<f64> {
+        let (on_time, expired) = self.peers.values().fold((0, 0), |(a, b), (o, e)| (a + o, b + e));
+        (on_time + expired > 0).then(|| on_time as f64 / (on_time + expired) as f64)
+    }
+}
+
+impl EcoBlockContext {
+    /// Drops a frame for `peer` whose deadline has passed.
+    pub(crate) fn delivery_expired(&mut self, peer: &PeerId, id: String, deadline: u64) {
+        self.deadlines.peers.entry(peer.clone()).or_default().1 += 1;
+        self.log(LogLevel::Info, format!("Dropped {} for {}: deadline {} passed", id, peer, deadline));
+        self.emit_event(BridgeEvent::DeliveryExpired {
+            id,
+            peer: peer.clone(),
+            deadline,
+        });
+    }
+
+    pub(crate) fn delivered_on_time(&mut self, peer: &PeerId) {
+        self.deadlines.peers.entry(peer.clone()).or_default().0 += 1;
+    }
+}
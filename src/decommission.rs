@@ -0,0 +1,113 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+use crate::multicast::TargetState;
+use crate::peer_id::PeerId;
+use crate::records::Record;
+use crate::{clock, EcoBlockContext};
+
+pub const RETIREMENT_KIND: &str = "retirement";
+/// Seconds the successor has to confirm each batch of handed-off records.
+pub const HANDOFF_TIMEOUT_SECS: u64 = 60;
+/// Records handed off before waiting for their receipts.
+pub const HANDOFF_BATCH: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetirementBody {
+    pub node_id: String,
+    pub successor: Option<String>,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DecommissionReport {
+    pub retirement: Record,
+    pub successor: Option<PeerId>,
+    /// Records authored by the retired node. When there is a successor, it
+    /// confirmed receiving every one before anything was wiped.
+    pub handoff: Vec<Record>,
+    pub wiped_files: Vec<String>,
+}
+
+/// Overwrites a file with zeros before removing it. Missing files are ignored.
+pub fn wipe_file(path: &Path) -> Result<bool, BridgeError> {
+    let Ok(metadata) = fs::metadata(path) else {
+        return Ok(false);
+    };
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(path)
+        .map_err(|e| BridgeError::Io(e.to_string()))?;
+    file.write_all(&vec![0u8; metadata.len() as usize])
+        .map_err(|e| BridgeError::Io(e.to_string()))?;
+    file.sync_all().map_err(|e| BridgeError::Io(e.to_string()))?;
+    fs::remove_file(path).map_err(|e| BridgeError::Io(e.to_string()))?;
+    Ok(true)
+}
+
+impl EcoBlockContext {
+    /// Fails unless the context is bound to the node at `path`, so only the
+    /// open node's files are ever wiped.
+    pub(crate) fn check_open_node(&self, path: &Path) -> Result<(), BridgeError> {
+        match &self.node_path {
+            Some(open) if open == path => Ok(()),
+            Some(open) => Err(BridgeError::InvalidInput(format!("Invalid path {}: the open node is {}", path.display(), open.display()))),
+            None => Err(BridgeError::NotInitialized(format!("no node is open; call init_context({}) first", path.display()))),
+        }
+    }
+
+    /// Sends each record of `batch` to `successor` over the transport, asking
+    /// for a delivery receipt by `deadline`.
+    pub(crate) fn send_handoff(&mut self, batch: &[Record], successor: &PeerId, deadline: u64) -> Result<(), BridgeError> {
+        for record in batch {
+            let delivery = self.send_to_many(record.clone(), std::slice::from_ref(successor), Some(deadline))?;
+            if let Some(TargetState::Failed { error }) = delivery.targets.first().map(|t| &t.state) {
+                return Err(BridgeError::Io(format!("handoff of record {} to {} not sent: {}", record.id, successor, error)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Records of `batch` whose receipt has not arrived.
+    pub(crate) fn unconfirmed_handoff(&self, batch: &[Record]) -> usize {
+        let now = clock::now_secs();
+        batch
+            .iter()
+            .filter(|r| self.multicasts.get(&r.id, now).is_none_or(|d| d.delivered() == 0))
+            .count()
+    }
+
+    /// Signs a retirement record; peers stop accepting records from this key once they see it.
+    pub fn retire(&mut self, successor: Option<String>, reason: &str) -> Result<Record, BridgeError> {
+        let body = RetirementBody {
+            node_id: self.keypair.public_key_hex(),
+            successor,
+            reason: reason.to_string(),
+        };
+        let body = serde_json::to_value(&body).unwrap_or_default();
+        let record = self.sign_record(RETIREMENT_KIND, body, clock::now_secs())?;
+        self.records.insert(record.clone());
+        Ok(record)
+    }
+
+    pub fn handoff_records(&self) -> Vec<Record> {
+        let me = self.keypair.public_key_hex();
+        self.records
+            .iter()
+            .filter(|r| r.author == me)
+            .cloned()
+            .collect()
+    }
+
+    pub(crate) fn apply_retirement(&mut self, record: &Record) -> Result<(), BridgeError> {
+        let body: RetirementBody = serde_json::from_value(record.body.clone())
+            .map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+        if body.node_id != record.author {
+            return Err(BridgeError::Unauthorized(format!("Retirement of {} signed by {}", body.node_id, record.author)));
+        }
+        self.retired_keys.insert(record.author.clone());
+        Ok(())
+    }
+}
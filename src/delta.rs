@@ -0,0 +1,112 @@
+use std::fs;
+use ecoblock_storage::tangle::block::TangleBlock;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::block_log::BlockLog;
+use crate::error::BridgeError;
+use crate::runtime;
+use crate::{clock, EcoBlockContext};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// SHA-256 over the sorted ids of every block covered, so it does not depend
+    /// on the order in which a node received them.
+    pub id: String,
+    /// Block log position covered, counting blocks removed since.
+    pub block_count: usize,
+    pub created_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Delta {
+    pub base: Checkpoint,
+    pub checkpoint: Checkpoint,
+    pub blocks: Vec<TangleBlock>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeltaImport {
+    pub checkpoint: Checkpoint,
+    pub imported: usize,
+}
+
+pub(crate) fn checkpoint_id<'a>(ids: impl Iterator<Item = &'a str>) -> String {
+    let mut ids: Vec<&str> = ids.collect();
+    ids.sort_unstable();
+    let mut hasher = Sha256::new();
+    for id in ids {
+        hasher.update(id.as_bytes());
+        hasher.update([0]);
+    }
+    hex::encode(hasher.finalize())
+}
+
+fn checkpoint_of(log: &BlockLog, now: u64) -> Checkpoint {
+    Checkpoint {
+        id: checkpoint_id(log.iter().map(|b| b.id.as_str())),
+        block_count: log.end(),
+        created_at: now,
+    }
+}
+
+impl EcoBlockContext {
+    /// Records the current block set as a checkpoint later deltas can start from.
+    pub fn create_checkpoint(&mut self) -> Checkpoint {
+        let checkpoint = checkpoint_of(&self.block_log, clock::now_secs());
+        self.checkpoints.push(checkpoint.clone());
+        checkpoint
+    }
+
+    /// Writes the blocks added since `since_checkpoint_id` and returns the new
+    /// checkpoint the delta ends at.
+    pub fn export_delta(&mut self, since_checkpoint_id: &str, path: &str) -> Result<Checkpoint, BridgeError> {
+        let base = self
+            .checkpoints
+            .iter()
+            .rev()
+            .find(|c| c.id == since_checkpoint_id)
+            .cloned()
+            .ok_or_else(|| BridgeError::NotFound(format!("Unknown checkpoint {}", since_checkpoint_id)))?;
+        let blocks = self.block_log.since(base.block_count).cloned().collect();
+        let checkpoint = self.create_checkpoint();
+        let delta = Delta {
+            base,
+            checkpoint: checkpoint.clone(),
+            blocks,
+        };
+        let json = serde_json::to_vec(&delta).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        fs::write(path, json).map_err(|e| BridgeError::Io(e.to_string()))?;
+        Ok(checkpoint)
+    }
+
+    /// Applies a delta only if the local block set matches its base checkpoint,
+    /// every block verifies and the result matches the delta's checkpoint.
+    pub fn import_delta(&mut self, path: &str) -> Result<DeltaImport, BridgeError> {
+        let bytes = fs::read(path).map_err(|e| BridgeError::Io(e.to_string()))?;
+        let delta: Delta = serde_json::from_slice(&bytes).map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+        let local = checkpoint_id(self.block_log.iter().map(|b| b.id.as_str()));
+        if local != delta.base.id {
+            return Err(BridgeError::Rejected(format!("Delta base {} does not match local state {}", delta.base.id, local)));
+        }
+        if let Some(bad) = runtime::find_invalid(&delta.blocks, self.config.verification_threads()) {
+            return Err(BridgeError::Crypto(format!("Delta block {} failed verification", bad.id)));
+        }
+        let resulting = checkpoint_id(
+            self.block_log
+                .iter()
+                .map(|b| b.id.as_str())
+                .chain(delta.blocks.iter().filter(|b| !self.block_log.contains(&b.id)).map(|b| b.id.as_str())),
+        );
+        if resulting != delta.checkpoint.id {
+            return Err(BridgeError::Rejected(format!("Delta does not reach checkpoint {}", delta.checkpoint.id)));
+        }
+        let imported = delta.blocks.len();
+        self.load_blocks(delta.blocks);
+        self.checkpoints.push(delta.base);
+        self.checkpoints.push(delta.checkpoint.clone());
+        Ok(DeltaImport {
+            checkpoint: delta.checkpoint,
+            imported,
+        })
+    }
+}
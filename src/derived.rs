@@ -0,0 +1,340 @@
+use std::collections::{BTreeMap, BTreeSet};
+use crate::error::BridgeError;
+use crate::readings::{QueryOptions, Reading, ReadingStore};
+
+/// Deepest expression tree accepted, so parsing and evaluation cannot
+/// exhaust the stack.
+pub const MAX_EXPRESSION_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DerivedMode {
+    /// Evaluated once when a block is inserted and stored with the reading.
+    OnIngest,
+    /// Evaluated against stored readings each time the metric is queried.
+    OnQuery,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(f64),
+    Metric(String),
+    Neg(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, values: &BTreeMap<String, f64>) -> Option<f64> {
+        match self {
+            Expr::Number(n) => Some(*n),
+            Expr::Metric(name) => values.get(name).copied(),
+            Expr::Neg(inner) => inner.eval(values).map(|v| -v),
+            Expr::Binary(op, lhs, rhs) => {
+                let (a, b) = (lhs.eval(values)?, rhs.eval(values)?);
+                let result = match op {
+                    '+' => a + b,
+                    '-' => a - b,
+                    '*' => a * b,
+                    '/' => a / b,
+                    '^' => a.powf(b),
+                    _ => return None,
+                };
+                result.is_finite().then_some(result)
+            }
+            Expr::Call(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|a| a.eval(values))
+                    .collect::<Option<Vec<f64>>>()?;
+                let result = match (name.as_str(), args.as_slice()) {
+                    ("abs", [x]) => x.abs(),
+                    ("sqrt", [x]) => x.sqrt(),
+                    ("exp", [x]) => x.exp(),
+                    ("ln", [x]) => x.ln(),
+                    ("pow", [x, y]) => x.powf(*y),
+                    ("min", [first, rest @ ..]) => rest.iter().fold(*first, |m, v| m.min(*v)),
+                    ("max", [first, rest @ ..]) => rest.iter().fold(*first, |m, v| m.max(*v)),
+                    _ => return None,
+                };
+                result.is_finite().then_some(result)
+            }
+        }
+    }
+
+    fn collect_metrics(&self, out: &mut BTreeSet<String>) {
+        match self {
+            Expr::Number(_) => {}
+            Expr::Metric(name) => {
+                out.insert(name.clone());
+            }
+            Expr::Neg(inner) => inner.collect_metrics(out),
+            Expr::Binary(_, lhs, rhs) => {
+                lhs.collect_metrics(out);
+                rhs.collect_metrics(out);
+            }
+            Expr::Call(_, args) => args.iter().for_each(|a| a.collect_metrics(out)),
+        }
+    }
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn parse(source: &'a str) -> Result<Expr, BridgeError> {
+        let mut parser = Parser {
+            chars: source.chars().peekable(),
+            depth: 0,
+        };
+        let expr = parser.expr()?;
+        parser.skip_whitespace();
+        match parser.chars.next() {
+            None => Ok(expr),
+            Some(c) => Err(BridgeError::Expression(format!("unexpected '{}'", c))),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+            self.chars.next();
+        }
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&expected) {
+            self.chars.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Counts one more level of the tree being built; parsing fails past
+    /// `MAX_EXPRESSION_DEPTH`. Callers restore the depth once the level is
+    /// parsed.
+    fn descend(&mut self) -> Result<(), BridgeError> {
+        self.depth += 1;
+        if self.depth > MAX_EXPRESSION_DEPTH {
+            return Err(BridgeError::Expression(format!("nested deeper than {} levels", MAX_EXPRESSION_DEPTH)));
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self) -> Result<Expr, BridgeError> {
+        let depth = self.depth;
+        let mut lhs = self.term()?;
+        loop {
+            let op = if self.eat('+') {
+                '+'
+            } else if self.eat('-') {
+                '-'
+            } else {
+                self.depth = depth;
+                return Ok(lhs);
+            };
+            // Each operator of a chain nests the chain one level deeper.
+            self.descend()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, BridgeError> {
+        let depth = self.depth;
+        let mut lhs = self.power()?;
+        loop {
+            let op = if self.eat('*') {
+                '*'
+            } else if self.eat('/') {
+                '/'
+            } else {
+                self.depth = depth;
+                return Ok(lhs);
+            };
+            self.descend()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.power()?));
+        }
+    }
+
+    fn power(&mut self) -> Result<Expr, BridgeError> {
+        let base = self.unary()?;
+        if self.eat('^') {
+            self.descend()?;
+            let exponent = self.power()?;
+            self.depth -= 1;
+            Ok(Expr::Binary('^', Box::new(base), Box::new(exponent)))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, BridgeError> {
+        if self.eat('-') {
+            self.descend()?;
+            let inner = self.unary()?;
+            self.depth -= 1;
+            return Ok(Expr::Neg(Box::new(inner)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, BridgeError> {
+        if self.eat('(') {
+            self.descend()?;
+            let inner = self.expr()?;
+            if !self.eat(')') {
+                return Err(BridgeError::Expression("missing ')'".to_string()));
+            }
+            self.depth -= 1;
+            return Ok(inner);
+        }
+        self.skip_whitespace();
+        match self.chars.peek().copied() {
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let mut literal = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if !(c.is_ascii_digit() || c == '.') {
+                        break;
+                    }
+                    literal.push(c);
+                    self.chars.next();
+                }
+                literal
+                    .parse()
+                    .map(Expr::Number)
+                    .map_err(|_| BridgeError::Expression(format!("invalid number '{}'", literal)))
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let mut name = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    name.push(c);
+                    self.chars.next();
+                }
+                if !self.eat('(') {
+                    return Ok(Expr::Metric(name));
+                }
+                self.descend()?;
+                let mut args = Vec::new();
+                if !self.eat(')') {
+                    loop {
+                        args.push(self.expr()?);
+                        if self.eat(')') {
+                            break;
+                        }
+                        if !self.eat(',') {
+                            return Err(BridgeError::Expression(format!("expected ',' in call to {}", name)));
+                        }
+                    }
+                }
+                self.depth -= 1;
+                Ok(Expr::Call(name, args))
+            }
+            Some(c) => Err(BridgeError::Expression(format!("unexpected '{}'", c))),
+            None => Err(BridgeError::Expression("unexpected end of input".to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DerivedMetric {
+    pub name: String,
+    pub expression: String,
+    pub mode: DerivedMode,
+    expr: Expr,
+}
+
+impl DerivedMetric {
+    pub fn new(name: &str, expression: &str, mode: DerivedMode) -> Result<Self, BridgeError> {
+        if name.is_empty() {
+            return Err(BridgeError::Expression("derived metric name is empty".to_string()));
+        }
+        let expr = Parser::parse(expression)?;
+        let mut inputs = BTreeSet::new();
+        expr.collect_metrics(&mut inputs);
+        if inputs.contains(name) {
+            return Err(BridgeError::Expression(format!("{} references itself", name)));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            expression: expression.to_string(),
+            mode,
+            expr,
+        })
+    }
+
+    pub fn inputs(&self) -> BTreeSet<String> {
+        let mut inputs = BTreeSet::new();
+        self.expr.collect_metrics(&mut inputs);
+        inputs
+    }
+
+    /// Returns `None` when an input metric is missing from the reading.
+    pub fn evaluate(&self, reading: &Reading) -> Option<f64> {
+        self.expr.eval(&reading.values)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DerivedRegistry {
+    metrics: BTreeMap<String, DerivedMetric>,
+}
+
+impl DerivedRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, metric: DerivedMetric) {
+        self.metrics.insert(metric.name.clone(), metric);
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.metrics.remove(name).is_some()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&DerivedMetric> {
+        self.metrics.get(name)
+    }
+
+    pub fn list(&self) -> Vec<DerivedMetric> {
+        self.metrics.values().cloned().collect()
+    }
+
+    /// Adds every on-ingest metric to the reading. Metrics are applied in name order,
+    /// so a derived metric may build on one whose name sorts before it.
+    pub fn apply_on_ingest(&self, reading: &mut Reading) {
+        for metric in self.metrics.values().filter(|m| m.mode == DerivedMode::OnIngest) {
+            if let Some(value) = metric.evaluate(reading) {
+                reading.values.insert(metric.name.clone(), value);
+            }
+        }
+    }
+
+    /// Value of `metric` for a stored reading, evaluating on-query metrics lazily.
+    pub fn value_of(&self, reading: &Reading, metric: &str) -> Option<f64> {
+        match reading.value(metric) {
+            Some(v) => Some(v),
+            None => match self.metrics.get(metric) {
+                Some(m) if m.mode == DerivedMode::OnQuery => m.evaluate(reading),
+                _ => None,
+            },
+        }
+    }
+
+    /// `(timestamp, value)` pairs for `metric` within `[from, to]`, derived metrics included.
+    pub fn series(&self, store: &ReadingStore, metric: &str, from: u64, to: u64) -> Vec<(u64, f64)> {
+        self.series_with(store, metric, from, to, QueryOptions::default())
+    }
+
+    pub fn series_with(&self, store: &ReadingStore, metric: &str, from: u64, to: u64, options: QueryOptions) -> Vec<(u64, f64)> {
+        store
+            .in_range_with(from, to, options)
+            .filter_map(|r| self.value_of(r, metric).map(|v| (r.timestamp, v)))
+            .collect()
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+use crate::logs::{LogEntry, LogLevel};
+use crate::power::{PowerMode, PowerState};
+use crate::records::Record;
+use crate::relay::RelayRole;
+use crate::runtime::{self, ThreadUsage};
+use crate::{clock, EcoBlockContext};
+
+pub const DIAGNOSTICS_REQUEST_KIND: &str = "diagnostics_request";
+pub const DIAGNOSTICS_RESPONSE_KIND: &str = "diagnostics_response";
+const LOG_LINES: usize = 50;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiagnosticsRequestBody {
+    pub target: String,
+    pub nonce: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeStatusSnapshot {
+    pub node_id: String,
+    pub tangle_size: usize,
+    pub readings: usize,
+    pub records: usize,
+    pub active_alerts: usize,
+    pub policy_version: Option<u64>,
+    #[serde(default)]
+    pub threads: ThreadUsage,
+    #[serde(default)]
+    pub relay_role: RelayRole,
+    #[serde(default)]
+    pub power: Option<PowerState>,
+    #[serde(default)]
+    pub power_mode: PowerMode,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiagnosticsBundle {
+    pub request_id: String,
+    pub requested_by: String,
+    pub generated_at: u64,
+    pub status: NodeStatusSnapshot,
+    pub recent_logs: Vec<LogEntry>,
+    /// Stored record counts per kind.
+    pub metrics: BTreeMap<String, u64>,
+}
+
+impl EcoBlockContext {
+    pub fn status_snapshot(&self) -> NodeStatusSnapshot {
+        NodeStatusSnapshot {
+            node_id: self.keypair.public_key_hex(),
+            tangle_size: self.tangle_size(),
+            readings: self.readings.len(),
+            records: self.records.len(),
+            active_alerts: self.thresholds.active_alerts().len(),
+            policy_version: self.policy.current().map(|p| p.document.version),
+            threads: runtime::thread_usage(&self.config),
+            relay_role: self.relay_role(),
+            power: self.power.state(),
+            power_mode: self.power.mode(),
+        }
+    }
+
+    pub fn log(&mut self, level: LogLevel, message: String) {
+        let entry = LogEntry {
+            timestamp: clock::now_secs(),
+            level,
+            message,
+        };
+        self.log_stream.offer(&entry);
+        self.logs.push(entry.timestamp, entry.level, entry.message);
+    }
+
+    /// Asks `target` for a diagnostics bundle. Only policy admins are answered.
+    pub fn request_diagnostics(&mut self, target: &str) -> Result<Record, BridgeError> {
+        let now = clock::now_secs();
+        let body = DiagnosticsRequestBody {
+            target: target.to_string(),
+            nonce: now,
+        };
+        let body = serde_json::to_value(&body).unwrap_or_default();
+        let record = self.sign_record(DIAGNOSTICS_REQUEST_KIND, body, now)?;
+        self.records.insert(record.clone());
+        Ok(record)
+    }
+
+    pub(crate) fn answer_diagnostics(&mut self, request: &Record) -> Result<Option<Record>, BridgeError> {
+        let body: DiagnosticsRequestBody = serde_json::from_value(request.body.clone())
+            .map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+        let me = self.keypair.public_key_hex();
+        if body.target != me {
+            return Ok(None);
+        }
+        if !self.policy.authorities(&me).contains(&request.author) {
+            self.log(LogLevel::Warn, format!("Refused diagnostics request from {}", request.author));
+            return Err(BridgeError::Unauthorized(format!("{} may not request diagnostics", request.author)));
+        }
+        let mut metrics = BTreeMap::new();
+        for kind in self.records.kinds() {
+            metrics.insert(kind.clone(), self.records.of_kind(&kind).count() as u64);
+        }
+        let bundle = DiagnosticsBundle {
+            request_id: request.id.clone(),
+            requested_by: request.author.clone(),
+            generated_at: clock::now_secs(),
+            status: self.status_snapshot(),
+            recent_logs: self.logs.recent(LOG_LINES, LogLevel::Debug),
+            metrics,
+        };
+        let bundle = serde_json::to_value(&bundle).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        let response = self.sign_record(DIAGNOSTICS_RESPONSE_KIND, bundle, clock::now_secs())?;
+        self.records.insert(response.clone());
+        Ok(Some(response))
+    }
+
+    /// Signed bundles returned by `target`, newest last.
+    pub fn diagnostics_from(&self, target: &str) -> Vec<DiagnosticsBundle> {
+        self.records
+            .of_kind(DIAGNOSTICS_RESPONSE_KIND)
+            .filter(|r| r.author == target)
+            .filter_map(|r| serde_json::from_value(r.body.clone()).ok())
+            .collect()
+    }
+}
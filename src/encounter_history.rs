@@ -0,0 +1,112 @@
+//! Analytics over data-mule encounters: which peers were met, for how long and
+//! with how much exchanged, and how long blocks took to reach this node,
+//! grouped by the region their reading was taken in.
+
+use std::collections::{BTreeMap, VecDeque};
+use serde::{Deserialize, Serialize};
+use crate::data_mule::Encounter;
+use crate::geofence::GeoPoint;
+use crate::EcoBlockContext;
+
+/// Side of a region cell, in degrees (about 11 km north-south).
+pub const REGION_CELL_DEGREES: f64 = 0.1;
+/// Region of blocks whose reading has no `latitude` and `longitude`.
+pub const UNKNOWN_REGION: &str = "unknown";
+/// Latency samples kept; older ones are dropped first.
+pub const MAX_LATENCY_SAMPLES: usize = 10_000;
+
+/// Time between a reading and its arrival at this node during an encounter.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatencySample {
+    pub received_at: u64,
+    pub region: String,
+    pub latency: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegionLatency {
+    pub region: String,
+    pub blocks: usize,
+    pub mean_seconds: f64,
+    pub median_seconds: u64,
+    /// 90th percentile, nearest rank.
+    pub p90_seconds: u64,
+    pub max_seconds: u64,
+}
+
+/// Cell `"<lat>,<lon>"` named after its south-west corner.
+pub fn region_of(point: Option<GeoPoint>) -> String {
+    match point {
+        Some(p) => {
+            let corner = |degrees: f64| (degrees / REGION_CELL_DEGREES).floor() * REGION_CELL_DEGREES;
+            format!("{:.1},{:.1}", corner(p.latitude), corner(p.longitude))
+        }
+        None => UNKNOWN_REGION.to_string(),
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct LatencyLog {
+    samples: VecDeque<LatencySample>,
+}
+
+impl LatencyLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, sample: LatencySample) {
+        if self.samples.len() == MAX_LATENCY_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// Latency statistics per region for blocks received within `[from, to]`.
+    pub fn by_region(&self, from: u64, to: u64) -> Vec<RegionLatency> {
+        let mut regions: BTreeMap<&str, Vec<u64>> = BTreeMap::new();
+        for sample in self.samples.iter().filter(|s| s.received_at >= from && s.received_at <= to) {
+            regions.entry(sample.region.as_str()).or_default().push(sample.latency);
+        }
+        regions
+            .into_iter()
+            .map(|(region, mut latencies)| {
+                latencies.sort_unstable();
+                let rank = |q: f64| latencies[((q * latencies.len() as f64).ceil() as usize).clamp(1, latencies.len()) - 1];
+                RegionLatency {
+                    region: region.to_string(),
+                    blocks: latencies.len(),
+                    mean_seconds: latencies.iter().sum::<u64>() as f64 / latencies.len() as f64,
+                    median_seconds: rank(0.5),
+                    p90_seconds: rank(0.9),
+                    max_seconds: latencies[latencies.len() - 1],
+                }
+            })
+            .collect()
+    }
+}
+
+impl EcoBlockContext {
+    /// Encounters that started within `[from, to]`, oldest first.
+    pub fn encounter_history(&self, from: u64, to: u64) -> Vec<Encounter> {
+        self.data_mule
+            .encounters()
+            .into_iter()
+            .filter(|e| e.started_at >= from && e.started_at <= to)
+            .collect()
+    }
+
+    /// Records how late the block `block_id`, just received from a peer in an
+    /// encounter, arrived.
+    pub(crate) fn record_delivery_latency(&mut self, block_id: &str, received_at: u64) {
+        let Some(reading) = self.readings.get(block_id) else {
+            return;
+        };
+        let sample = LatencySample {
+            received_at,
+            region: region_of(GeoPoint::of_reading(reading)),
+            latency: received_at.saturating_sub(reading.timestamp),
+        };
+        self.encounter_latencies.record(sample);
+    }
+}
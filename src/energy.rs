@@ -0,0 +1,172 @@
+//! Rough energy accounting. Counters of the costly operations (radio bytes,
+//! signatures, disk writes) are multiplied by per-platform coefficients to show
+//! which subsystem drives consumption. The figures are estimates for comparing
+//! settings, not measurements.
+//!
+//! Like the call metrics, the counters live outside `CONTEXT`, so code that
+//! only borrows the context can count.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use ecoblock_storage::tangle::block::TangleBlock;
+use serde::{Deserialize, Serialize};
+use crate::canonical;
+use crate::error::BridgeError;
+use crate::{clock, EcoBlockContext};
+
+static RADIO_BYTES_SENT: AtomicU64 = AtomicU64::new(0);
+static RADIO_BYTES_RECEIVED: AtomicU64 = AtomicU64::new(0);
+static SIGNATURES: AtomicU64 = AtomicU64::new(0);
+static VERIFICATIONS: AtomicU64 = AtomicU64::new(0);
+static DISK_BYTES_WRITTEN: AtomicU64 = AtomicU64::new(0);
+static COUNTING_SINCE: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) fn count_radio_sent(bytes: usize) {
+    RADIO_BYTES_SENT.fetch_add(bytes as u64, Ordering::Relaxed);
+}
+
+pub(crate) fn count_radio_received(bytes: usize) {
+    RADIO_BYTES_RECEIVED.fetch_add(bytes as u64, Ordering::Relaxed);
+}
+
+pub(crate) fn count_signature() {
+    SIGNATURES.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn count_verification() {
+    VERIFICATIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Verifies `block`'s signature, counting the check.
+pub(crate) fn verify_block(block: &TangleBlock) -> bool {
+    count_verification();
+    block.verify()
+}
+
+/// Verifies a canonical signature of `block`, counting the check.
+pub(crate) fn verify_canonical_block(block: &TangleBlock, signature: &str) -> bool {
+    count_verification();
+    canonical::verify_block_signature(block, signature)
+}
+
+pub(crate) fn count_disk_write(bytes: usize) {
+    DISK_BYTES_WRITTEN.fetch_add(bytes as u64, Ordering::Relaxed);
+}
+
+/// Zeroes every counter, starting a new measurement period.
+pub fn reset_counters() {
+    for counter in [&RADIO_BYTES_SENT, &RADIO_BYTES_RECEIVED, &SIGNATURES, &VERIFICATIONS, &DISK_BYTES_WRITTEN] {
+        counter.store(0, Ordering::Relaxed);
+    }
+    COUNTING_SINCE.store(clock::now_secs(), Ordering::Relaxed);
+}
+
+/// Microjoules per operation on a platform.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EnergyCoefficients {
+    pub radio_send_per_byte: f64,
+    pub radio_receive_per_byte: f64,
+    pub signature: f64,
+    pub verification: f64,
+    pub disk_write_per_byte: f64,
+}
+
+impl EnergyCoefficients {
+    /// A phone on BLE with flash storage.
+    pub fn phone() -> Self {
+        Self {
+            radio_send_per_byte: 0.4,
+            radio_receive_per_byte: 0.3,
+            signature: 50.0,
+            verification: 120.0,
+            disk_write_per_byte: 0.02,
+        }
+    }
+
+    /// A 240 MHz microcontroller (ESP32 class) on Wi-Fi or LoRa with SPI flash.
+    pub fn microcontroller() -> Self {
+        Self {
+            radio_send_per_byte: 1.0,
+            radio_receive_per_byte: 0.6,
+            signature: 500.0,
+            verification: 1_200.0,
+            disk_write_per_byte: 0.1,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), BridgeError> {
+        let all = [
+            self.radio_send_per_byte,
+            self.radio_receive_per_byte,
+            self.signature,
+            self.verification,
+            self.disk_write_per_byte,
+        ];
+        if all.iter().any(|c| !c.is_finite() || *c < 0.0) {
+            return Err(BridgeError::InvalidInput("Invalid energy coefficients: expected finite, non-negative values".to_string()));
+        }
+        Ok(())
+    }
+}
+
+impl Default for EnergyCoefficients {
+    fn default() -> Self {
+        Self::phone()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnergyEntry {
+    /// `radio_send`, `radio_receive`, `signing`, `verification` or `disk_writes`.
+    pub subsystem: String,
+    /// Bytes for the radio and disk, operations for signatures.
+    pub count: u64,
+    pub microjoules: f64,
+    /// Fraction of the total, 0 when nothing was counted.
+    pub share: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnergyReport {
+    /// Start of the measurement period: the last reset, or 0 if never reset.
+    pub since: u64,
+    pub coefficients: EnergyCoefficients,
+    /// Largest first.
+    pub entries: Vec<EnergyEntry>,
+    pub total_microjoules: f64,
+}
+
+pub fn report(coefficients: EnergyCoefficients) -> EnergyReport {
+    let counts = [
+        ("radio_send", RADIO_BYTES_SENT.load(Ordering::Relaxed), coefficients.radio_send_per_byte),
+        ("radio_receive", RADIO_BYTES_RECEIVED.load(Ordering::Relaxed), coefficients.radio_receive_per_byte),
+        ("signing", SIGNATURES.load(Ordering::Relaxed), coefficients.signature),
+        ("verification", VERIFICATIONS.load(Ordering::Relaxed), coefficients.verification),
+        ("disk_writes", DISK_BYTES_WRITTEN.load(Ordering::Relaxed), coefficients.disk_write_per_byte),
+    ];
+    let total: f64 = counts.iter().map(|(_, count, cost)| *count as f64 * cost).sum();
+    let mut entries: Vec<EnergyEntry> = counts
+        .iter()
+        .map(|(subsystem, count, cost)| {
+            let microjoules = *count as f64 * cost;
+            EnergyEntry {
+                subsystem: subsystem.to_string(),
+                count: *count,
+                microjoules,
+                share: if total > 0.0 { microjoules / total } else { 0.0 },
+            }
+        })
+        .collect();
+    entries.sort_by(|a, b| b.microjoules.total_cmp(&a.microjoules));
+    EnergyReport {
+        since: COUNTING_SINCE.load(Ordering::Relaxed),
+        coefficients,
+        entries,
+        total_microjoules: total,
+    }
+}
+
+impl EcoBlockContext {
+    pub fn energy_report(&self) -> EnergyReport {
+        report(self.energy_coefficients)
+    }
+}
@@ -0,0 +1,178 @@
+//! The error type of the crate-root API. Each error is built as the variant
+//! for its category where it happens, so callers can match on it; `Display`
+//! writes the category in front of the message (`IO error: …`,
+//! `Unauthorized: …`), except for the sentence-style categories whose message
+//! already says what is wrong (`Invalid …`, `Unknown …`).
+
+use serde::{Deserialize, Serialize};
+
+/// The category of a [`BridgeError`], without its message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ErrorKind {
+    Io,
+    Serialization,
+    Deserialization,
+    Decoding,
+    Crypto,
+    Unauthorized,
+    Wire,
+    Expression,
+    Scenario,
+    InvalidInput,
+    NotFound,
+    /// A compare-and-set lost to a newer write.
+    Conflict,
+    /// Refused by validation, policy or admission rules.
+    Rejected,
+    /// No node at the path, or no context bound to one.
+    NotInitialized,
+    AlreadyInitialized,
+    /// A call panicked while holding the context lock; `shutdown_context` recovers.
+    PoisonedLock,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "message")]
+pub enum BridgeError {
+    Io(String),
+    Serialization(String),
+    Deserialization(String),
+    Decoding(String),
+    Crypto(String),
+    Unauthorized(String),
+    Wire(String),
+    Expression(String),
+    Scenario(String),
+    InvalidInput(String),
+    NotFound(String),
+    /// A compare-and-set lost to a newer write.
+    Conflict(String),
+    /// Refused by validation, policy or admission rules.
+    Rejected(String),
+    /// No node at the path, or no context bound to one.
+    NotInitialized(String),
+    AlreadyInitialized(String),
+    /// A call panicked while holding the context lock; `shutdown_context` recovers.
+    PoisonedLock(String),
+}
+
+impl BridgeError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            BridgeError::Io(_) => ErrorKind::Io,
+            BridgeError::Serialization(_) => ErrorKind::Serialization,
+            BridgeError::Deserialization(_) => ErrorKind::Deserialization,
+            BridgeError::Decoding(_) => ErrorKind::Decoding,
+            BridgeError::Crypto(_) => ErrorKind::Crypto,
+            BridgeError::Unauthorized(_) => ErrorKind::Unauthorized,
+            BridgeError::Wire(_) => ErrorKind::Wire,
+            BridgeError::Expression(_) => ErrorKind::Expression,
+            BridgeError::Scenario(_) => ErrorKind::Scenario,
+            BridgeError::InvalidInput(_) => ErrorKind::InvalidInput,
+            BridgeError::NotFound(_) => ErrorKind::NotFound,
+            BridgeError::Conflict(_) => ErrorKind::Conflict,
+            BridgeError::Rejected(_) => ErrorKind::Rejected,
+            BridgeError::NotInitialized(_) => ErrorKind::NotInitialized,
+            BridgeError::AlreadyInitialized(_) => ErrorKind::AlreadyInitialized,
+            BridgeError::PoisonedLock(_) => ErrorKind::PoisonedLock,
+        }
+    }
+
+    /// The message without its category prefix.
+    pub fn message(&self) -> &str {
+        match self {
+            BridgeError::Io(m)
+            | BridgeError::Serialization(m)
+            | BridgeError::Deserialization(m)
+            | BridgeError::Decoding(m)
+            | BridgeError::Crypto(m)
+            | BridgeError::Unauthorized(m)
+            | BridgeError::Wire(m)
+            | BridgeError::Expression(m)
+            | BridgeError::Scenario(m)
+            | BridgeError::InvalidInput(m)
+            | BridgeError::NotFound(m)
+            | BridgeError::Conflict(m)
+            | BridgeError::Rejected(m)
+            | BridgeError::NotInitialized(m)
+            | BridgeError::AlreadyInitialized(m)
+            | BridgeError::PoisonedLock(m) => m,
+        }
+    }
+}
+
+/// The text `Display` writes before the message, for categories whose
+/// message does not say it already.
+fn category_prefix(kind: ErrorKind) -> Option<&'static str> {
+    match kind {
+        ErrorKind::Io => Some("IO error"),
+        ErrorKind::Serialization => Some("Serialization error"),
+        ErrorKind::Deserialization => Some("Deserialization error"),
+        ErrorKind::Decoding => Some("Decoding error"),
+        ErrorKind::Crypto => Some("Crypto error"),
+        ErrorKind::Unauthorized => Some("Unauthorized"),
+        ErrorKind::Wire => Some("Wire error"),
+        ErrorKind::Expression => Some("Expression error"),
+        ErrorKind::Scenario => Some("Scenario error"),
+        ErrorKind::Conflict => Some("Conflict"),
+        ErrorKind::NotInitialized => Some("NotInitialized"),
+        ErrorKind::AlreadyInitialized => Some("AlreadyInitialized"),
+        ErrorKind::PoisonedLock => Some("PoisonedLock"),
+        ErrorKind::InvalidInput | ErrorKind::NotFound | ErrorKind::Rejected => None,
+    }
+}
+
+impl std::fmt::Display for BridgeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (category_prefix(self.kind()), self.message()) {
+            (Some(prefix), "") => f.write_str(prefix),
+            (Some(prefix), message) => write!(f, "{}: {}", prefix, message),
+            (None, message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for BridgeError {}
+
+/// The full text, for callers that still carry errors as strings.
+impl From<BridgeError> for String {
+    fn from(error: BridgeError) -> Self {
+        error.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block_id::BlockId;
+    use crate::wire;
+
+    #[test]
+    fn display_prefixes_only_categories_the_message_does_not_name() {
+        assert_eq!(BridgeError::Io("disk full".to_string()).to_string(), "IO error: disk full");
+        assert_eq!(BridgeError::Unauthorized("key retired".to_string()).to_string(), "Unauthorized: key retired");
+        assert_eq!(BridgeError::NotInitialized(String::new()).to_string(), "NotInitialized");
+        assert_eq!(BridgeError::InvalidInput("Invalid block id: \"\"".to_string()).to_string(), "Invalid block id: \"\"");
+        assert_eq!(BridgeError::NotFound("Unknown peer x".to_string()).to_string(), "Unknown peer x");
+        assert_eq!(String::from(BridgeError::Crypto("bad signature".to_string())), "Crypto error: bad signature");
+    }
+
+    #[test]
+    fn kind_and_message_survive_serialization() {
+        let error = BridgeError::Conflict("version 3 is newer".to_string());
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json, serde_json::json!({ "kind": "Conflict", "message": "version 3 is newer" }));
+        let back: BridgeError = serde_json::from_value(json).unwrap();
+        assert_eq!(back.kind(), ErrorKind::Conflict);
+        assert_eq!(back.message(), "version 3 is newer");
+    }
+
+    #[test]
+    fn errors_are_classified_where_they_happen() {
+        assert_eq!(BlockId::parse("").unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(wire::parse_frame(&[]).unwrap_err().kind(), ErrorKind::Wire);
+        assert_eq!(wire::parse_block_envelope(b"not json").unwrap_err().kind(), ErrorKind::Wire);
+        let missing = std::env::temp_dir().join(format!("ecoblock-error-missing-{}", std::process::id()));
+        assert_eq!(crate::v2::get_public_key(missing.display().to_string()).unwrap_err().kind(), ErrorKind::Io);
+    }
+}
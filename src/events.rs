@@ -0,0 +1,84 @@
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use crate::block_id::BlockId;
+use crate::peer_id::PeerId;
+use crate::power::PowerMode;
+use crate::error::{BridgeError, ErrorKind};
+use crate::EcoBlockContext;
+
+pub type EventCallback = Arc<dyn Fn(BridgeEvent) + Send + Sync>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BridgeEvent {
+    /// A block created locally was stored.
+    BlockInserted {
+        id: BlockId,
+        parents: Vec<BlockId>,
+        /// The reading's timestamp.
+        timestamp: u64,
+    },
+    /// A block from a peer was verified and stored.
+    BlockReceived { id: BlockId, from: Option<PeerId> },
+    /// A block was refused before it was stored.
+    BlockRejected { kind: ErrorKind, reason: String },
+    PeerConnected { from: PeerId, to: PeerId, weight: f32 },
+    /// Removed from the address book.
+    PeerRemoved { peer: PeerId },
+    /// The node switched power mode on a charge threshold or by the host.
+    PowerModeChanged { from: PowerMode, to: PowerMode },
+    /// A block or record waiting in the outbox for `peer` passed its delivery
+    /// deadline and was dropped.
+    DeliveryExpired { id: String, peer: PeerId, deadline: u64 },
+    /// A job for the host was queued; fetch it with `poll_jobs`.
+    JobQueued { id: u64, kind: String },
+    /// A target of `send_to_many` confirmed receipt of the record.
+    DeliveryConfirmed { record_id: String, target: PeerId },
+}
+
+#[derive(Default)]
+pub struct EventBus {
+    next_id: u64,
+    subscribers: Vec<(u64, EventCallback)>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&mut self, callback: EventCallback) -> u64 {
+        self.next_id += 1;
+        self.subscribers.push((self.next_id, callback));
+        self.next_id
+    }
+
+    pub fn unsubscribe(&mut self, id: u64) -> bool {
+        let before = self.subscribers.len();
+        self.subscribers.retain(|(sid, _)| *sid != id);
+        self.subscribers.len() != before
+    }
+
+    pub fn subscribers(&self) -> Vec<EventCallback> {
+        self.subscribers.iter().map(|(_, cb)| cb.clone()).collect()
+    }
+}
+
+impl EcoBlockContext {
+    /// Queues `event` for every subscriber; it is delivered once the context
+    /// lock is released.
+    pub(crate) fn emit_event(&mut self, event: BridgeEvent) {
+        for callback in self.events.subscribers() {
+            let event = event.clone();
+            self.pending.push(Box::new(move || callback(event)));
+        }
+    }
+
+    /// Reports a block refused with `error` and hands the error back.
+    pub(crate) fn reject_block(&mut self, error: BridgeError) -> BridgeError {
+        self.emit_event(BridgeEvent::BlockRejected {
+            kind: error.kind(),
+            reason: error.to_string(),
+        });
+        error
+    }
+}
@@ -0,0 +1,232 @@
+//! Incremental tangle explorer for debug screens. A session starts from one
+//! block and grows as the user expands nodes to show their parents and
+//! children, so the app only ever receives the part of the DAG on screen.
+//! Each expand or collapse returns just the nodes that appeared or went away.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use ecoblock_storage::tangle::block::TangleBlock;
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+use crate::EcoBlockContext;
+
+/// Children shown per expanded node; the node's `children` count tells the app
+/// how many there are in all.
+pub const MAX_CHILDREN_SHOWN: usize = 64;
+/// Open sessions; opening another closes the oldest.
+pub const MAX_SESSIONS: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExplorerNode {
+    pub id: String,
+    pub author: String,
+    pub timestamp: u64,
+    /// All parents; edges to those in the view can be drawn.
+    pub parents: Vec<String>,
+    /// Number of local blocks naming this one as a parent.
+    pub children: usize,
+    pub expanded: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExplorerDelta {
+    /// Oldest first.
+    pub added: Vec<ExplorerNode>,
+    /// Ids that left the view.
+    pub removed: Vec<String>,
+    /// Nodes still in the view whose expanded flag changed.
+    pub updated: Vec<ExplorerNode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExplorerView {
+    pub session: u64,
+    pub root: String,
+    /// Oldest first.
+    pub nodes: Vec<ExplorerNode>,
+}
+
+#[derive(Debug)]
+struct Session {
+    root: String,
+    expanded: HashSet<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ExplorerState {
+    /// Children of each block, built from the block log as it grows.
+    children: HashMap<String, Vec<String>>,
+    indexed_to: usize,
+    next_id: u64,
+    sessions: BTreeMap<u64, Session>,
+}
+
+impl ExplorerState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn close(&mut self, session: u64) -> bool {
+        self.sessions.remove(&session).is_some()
+    }
+}
+
+impl EcoBlockContext {
+    fn index_children(&mut self) {
+        let end = self.block_log.end();
+        for block in self.block_log.since(self.explorer.indexed_to) {
+            for parent in &block.data.parents {
+                self.explorer.children.entry(parent.clone()).or_default().push(block.id.clone());
+            }
+        }
+        self.explorer.indexed_to = end;
+    }
+
+    fn children_of(&self, id: &str) -> Vec<&str> {
+        self.explorer
+            .children
+            .get(id)
+            .into_iter()
+            .flatten()
+            .filter(|child| self.block_log.contains(child))
+            .map(String::as_str)
+            .collect()
+    }
+
+    fn explorer_node(&self, block: &TangleBlock, expanded: bool) -> ExplorerNode {
+        ExplorerNode {
+            id: block.id.clone(),
+            author: block.public_key.clone(),
+            timestamp: block.data.data.timestamp,
+            parents: block.data.parents.clone(),
+            children: self.children_of(&block.id).len(),
+            expanded,
+        }
+    }
+
+    /// Parents held locally and the first children of `id`.
+    fn neighbours(&self, id: &str) -> Vec<String> {
+        let mut neighbours: Vec<String> = self
+            .block_log
+            .get(id)
+            .map(|b| b.data.parents.iter().filter(|p| self.block_log.contains(p)).cloned().collect())
+            .unwrap_or_default();
+        neighbours.extend(self.children_of(id).into_iter().take(MAX_CHILDREN_SHOWN).map(str::to_string));
+        neighbours
+    }
+
+    /// Ids in `session`'s view: the root, expanded nodes and their neighbours.
+    fn visible(&self, session: &Session) -> HashSet<String> {
+        let mut visible = HashSet::from([session.root.clone()]);
+        for id in &session.expanded {
+            visible.insert(id.clone());
+            visible.extend(self.neighbours(id));
+        }
+        visible
+    }
+
+    fn session(&self, session: u64) -> Result<&Session, BridgeError> {
+        self.explorer.sessions.get(&session).ok_or_else(|| BridgeError::NotFound(format!("Unknown explorer session {}", session)))
+    }
+
+    pub fn open_explorer(&mut self, start_id: &str) -> Result<u64, BridgeError> {
+        if !self.block_log.contains(start_id) {
+            return Err(BridgeError::NotFound(format!("Unknown block {}", start_id)));
+        }
+        self.index_children();
+        if self.explorer.sessions.len() == MAX_SESSIONS {
+            self.explorer.sessions.pop_first();
+        }
+        self.explorer.next_id += 1;
+        let id = self.explorer.next_id;
+        self.explorer.sessions.insert(
+            id,
+            Session {
+                root: start_id.to_string(),
+                expanded: HashSet::new(),
+            },
+        );
+        Ok(id)
+    }
+
+    /// Shows the parents and children of `node`, which must be in the view.
+    pub fn expand_explorer_node(&mut self, session: u64, node: &str) -> Result<ExplorerDelta, BridgeError> {
+        self.index_children();
+        let current = self.session(session)?;
+        let before = self.visible(current);
+        if !before.contains(node) {
+            return Err(BridgeError::InvalidInput(format!("Invalid node {}: not in explorer session {}", node, session)));
+        }
+        let was_expanded = current.expanded.clone();
+        if let Some(s) = self.explorer.sessions.get_mut(&session) {
+            s.expanded.insert(node.to_string());
+        }
+        Ok(self.explorer_delta(session, &before, &was_expanded))
+    }
+
+    /// Hides what expanding `node` showed. Expanded nodes no longer connected
+    /// to the root through expanded nodes collapse as well.
+    pub fn collapse_explorer_node(&mut self, session: u64, node: &str) -> Result<ExplorerDelta, BridgeError> {
+        self.index_children();
+        let current = self.session(session)?;
+        let before = self.visible(current);
+        let was_expanded = current.expanded.clone();
+        let mut expanded = was_expanded.clone();
+        if !expanded.remove(node) {
+            return Ok(ExplorerDelta::default());
+        }
+        let root = current.root.clone();
+        let mut kept = HashSet::new();
+        let mut reached = HashSet::from([root.clone()]);
+        let mut queue = vec![root];
+        while let Some(id) = queue.pop() {
+            if !expanded.contains(&id) || !kept.insert(id.clone()) {
+                continue;
+            }
+            for next in self.neighbours(&id) {
+                if reached.insert(next.clone()) {
+                    queue.push(next);
+                }
+            }
+        }
+        if let Some(s) = self.explorer.sessions.get_mut(&session) {
+            s.expanded = kept;
+        }
+        Ok(self.explorer_delta(session, &before, &was_expanded))
+    }
+
+    fn explorer_delta(&self, session: u64, before: &HashSet<String>, was_expanded: &HashSet<String>) -> ExplorerDelta {
+        let Some(s) = self.explorer.sessions.get(&session) else {
+            return ExplorerDelta::default();
+        };
+        let after = self.visible(s);
+        let node = |id: &str| self.block_log.get(id).map(|b| self.explorer_node(b, s.expanded.contains(id)));
+        let mut added: Vec<ExplorerNode> = after.difference(before).filter_map(|id| node(id)).collect();
+        added.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.id.cmp(&b.id)));
+        let mut removed: Vec<String> = before.difference(&after).cloned().collect();
+        removed.sort();
+        let mut updated: Vec<ExplorerNode> = after
+            .intersection(before)
+            .filter(|id| was_expanded.contains(*id) != s.expanded.contains(*id))
+            .filter_map(|id| node(id))
+            .collect();
+        updated.sort_by(|a, b| a.id.cmp(&b.id));
+        ExplorerDelta { added, removed, updated }
+    }
+
+    pub fn explorer_view(&mut self, session: u64) -> Result<ExplorerView, BridgeError> {
+        self.index_children();
+        let s = self.session(session)?;
+        let visible = self.visible(s);
+        let mut nodes: Vec<ExplorerNode> = visible
+            .iter()
+            .filter_map(|id| self.block_log.get(id))
+            .map(|b| self.explorer_node(b, s.expanded.contains(&b.id)))
+            .collect();
+        nodes.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.id.cmp(&b.id)));
+        Ok(ExplorerView {
+            session,
+            root: s.root.clone(),
+            nodes,
+        })
+    }
+}
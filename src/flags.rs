@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use crate::block_id::BlockId;
+use crate::error::BridgeError;
+use crate::records::Record;
+
+pub const FLAG_KIND: &str = "flag";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FlagReason {
+    SensorFault,
+    CalibrationError,
+    Tampering,
+    Duplicate,
+    OutOfRange,
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Flag {
+    pub id: String,
+    pub block_id: BlockId,
+    pub reason: FlagReason,
+    pub comment: Option<String>,
+    pub author: String,
+    pub timestamp: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FlagBody {
+    block_id: String,
+    reason: FlagReason,
+    comment: Option<String>,
+}
+
+pub fn body(block_id: &BlockId, reason: FlagReason, comment: Option<String>) -> serde_json::Value {
+    serde_json::to_value(FlagBody {
+        block_id: block_id.to_string(),
+        reason,
+        comment,
+    })
+    .unwrap_or_default()
+}
+
+impl TryFrom<&Record> for Flag {
+    type Error = BridgeError;
+
+    fn try_from(record: &Record) -> Result<Self, BridgeError> {
+        if record.kind != FLAG_KIND {
+            return Err(BridgeError::InvalidInput(format!("Record {} is not a flag", record.id)));
+        }
+        let body: FlagBody = serde_json::from_value(record.body.clone())
+            .map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+        Ok(Self {
+            id: record.id.clone(),
+            block_id: BlockId::parse(&body.block_id)?,
+            reason: body.reason,
+            comment: body.comment,
+            author: record.author.clone(),
+            timestamp: record.timestamp,
+        })
+    }
+}
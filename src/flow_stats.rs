@@ -0,0 +1,173 @@
+//! Traffic counters per peer and per channel, kept up to date as frames and
+//! records pass, for drilling into uneven data flow: a peer that sends little
+//! or mostly invalid frames, or a channel fed by a single source.
+
+use std::collections::{BTreeMap, HashMap};
+use serde::{Deserialize, Serialize};
+use crate::dashboard::DAY_SECS;
+use crate::peer_id::PeerId;
+
+/// Peers and channels tracked; the least recently active is dropped first.
+pub const MAX_TRACKED: usize = 1024;
+/// Sources listed in `ChannelStats::top_sources`.
+pub const TOP_SOURCES: usize = 5;
+const HOUR_SECS: u64 = 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerStats {
+    pub peer_id: PeerId,
+    pub frames_received: u64,
+    pub frames_sent: u64,
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+    /// New blocks stored from the peer.
+    pub blocks_received: u64,
+    pub blocks_sent: u64,
+    /// Frames from the peer that were refused.
+    pub invalid: u64,
+    pub last_frame_at: Option<u64>,
+    /// Last summary exchanged with the peer, in either direction.
+    pub last_sync: Option<u64>,
+}
+
+impl PeerStats {
+    fn new(peer_id: PeerId) -> Self {
+        Self {
+            peer_id,
+            frames_received: 0,
+            frames_sent: 0,
+            bytes_received: 0,
+            bytes_sent: 0,
+            blocks_received: 0,
+            blocks_sent: 0,
+            invalid: 0,
+            last_frame_at: None,
+            last_sync: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceCount {
+    pub author: String,
+    pub messages: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelStats {
+    pub channel: String,
+    pub messages: u64,
+    pub last_hour: u64,
+    pub last_day: u64,
+    /// Mean over the last 24 hours.
+    pub per_hour: f64,
+    pub sources: usize,
+    /// Authors with the most messages, most first.
+    pub top_sources: Vec<SourceCount>,
+    pub first_at: u64,
+    pub last_at: u64,
+}
+
+#[derive(Debug, Default)]
+struct ChannelCounters {
+    messages: u64,
+    authors: HashMap<String, u64>,
+    /// Messages per minute over the last day, by message timestamp.
+    minutes: BTreeMap<u64, u64>,
+    first_at: u64,
+    last_at: u64,
+    active_at: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct FlowStats {
+    peers: HashMap<PeerId, PeerStats>,
+    channels: HashMap<String, ChannelCounters>,
+}
+
+impl FlowStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The counters of `peer`, created on first use.
+    pub(crate) fn peer(&mut self, peer: &PeerId) -> &mut PeerStats {
+        if !self.peers.contains_key(peer) && self.peers.len() >= MAX_TRACKED {
+            let idle = self.peers.values().min_by_key(|p| p.last_frame_at.max(p.last_sync)).map(|p| p.peer_id.clone());
+            if let Some(idle) = idle {
+                self.peers.remove(&idle);
+            }
+        }
+        self.peers.entry(peer.clone()).or_insert_with(|| PeerStats::new(peer.clone()))
+    }
+
+    pub(crate) fn frame_received(&mut self, peer: &PeerId, bytes: usize, now: u64) {
+        let stats = self.peer(peer);
+        stats.frames_received += 1;
+        stats.bytes_received += bytes as u64;
+        stats.last_frame_at = Some(now);
+    }
+
+    pub(crate) fn frame_sent(&mut self, peer: &PeerId, bytes: usize) {
+        let stats = self.peer(peer);
+        stats.frames_sent += 1;
+        stats.bytes_sent += bytes as u64;
+    }
+
+    pub(crate) fn channel_message(&mut self, channel: &str, author: &str, timestamp: u64, now: u64) {
+        if !self.channels.contains_key(channel) && self.channels.len() >= MAX_TRACKED {
+            let idle = self.channels.iter().min_by_key(|(_, c)| c.active_at).map(|(name, _)| name.clone());
+            if let Some(idle) = idle {
+                self.channels.remove(&idle);
+            }
+        }
+        let counters = self.channels.entry(channel.to_string()).or_default();
+        if counters.messages == 0 {
+            counters.first_at = timestamp;
+        }
+        counters.messages += 1;
+        counters.first_at = counters.first_at.min(timestamp);
+        counters.last_at = counters.last_at.max(timestamp);
+        counters.active_at = now;
+        *counters.authors.entry(author.to_string()).or_default() += 1;
+        let cutoff = now.saturating_sub(DAY_SECS);
+        if timestamp >= cutoff {
+            *counters.minutes.entry(timestamp / 60).or_default() += 1;
+        }
+        counters.minutes = counters.minutes.split_off(&(cutoff / 60));
+    }
+
+    pub fn peer_stats(&self, peer: &PeerId) -> Option<PeerStats> {
+        self.peers.get(peer).cloned()
+    }
+
+    pub fn channel_stats(&self, channel: &str, now: u64) -> Option<ChannelStats> {
+        let counters = self.channels.get(channel)?;
+        let since = |secs: u64| {
+            let from = now.saturating_sub(secs) / 60;
+            counters.minutes.range(from..).map(|(_, n)| n).sum::<u64>()
+        };
+        let last_day = since(DAY_SECS);
+        let mut top_sources: Vec<SourceCount> = counters
+            .authors
+            .iter()
+            .map(|(author, messages)| SourceCount {
+                author: author.clone(),
+                messages: *messages,
+            })
+            .collect();
+        top_sources.sort_by(|a, b| b.messages.cmp(&a.messages).then_with(|| a.author.cmp(&b.author)));
+        top_sources.truncate(TOP_SOURCES);
+        Some(ChannelStats {
+            channel: channel.to_string(),
+            messages: counters.messages,
+            last_hour: since(HOUR_SECS),
+            last_day,
+            per_hour: last_day as f64 / (DAY_SECS / HOUR_SECS) as f64,
+            sources: counters.authors.len(),
+            top_sources,
+            first_at: counters.first_at,
+            last_at: counters.last_at,
+        })
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+
+pub type SilenceCallback = Arc<dyn Fn(SilenceEvent) + Send + Sync>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Gap {
+    pub sensor_id: String,
+    pub start: u64,
+    pub end: u64,
+    pub missing_readings: u64,
+}
+
+/// Periods in `[from, to]` where consecutive readings are further apart than
+/// `expected_interval`. The range bounds count as readings so leading and
+/// trailing silence is reported too.
+pub fn find_gaps(sensor_id: &str, timestamps: &[u64], expected_interval: u64, from: u64, to: u64) -> Vec<Gap> {
+    if expected_interval == 0 || from > to {
+        return Vec::new();
+    }
+    let mut points: Vec<u64> = timestamps.iter().copied().filter(|t| *t >= from && *t <= to).collect();
+    points.sort_unstable();
+    points.insert(0, from);
+    points.push(to);
+    points
+        .windows(2)
+        .filter(|w| w[1] - w[0] > expected_interval)
+        .map(|w| Gap {
+            sensor_id: sensor_id.to_string(),
+            start: w[0],
+            end: w[1],
+            missing_readings: (w[1] - w[0]) / expected_interval - u64::from((w[1] - w[0]) % expected_interval == 0),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SilenceEventKind {
+    WentSilent,
+    Resumed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SilenceEvent {
+    pub sensor_id: String,
+    pub kind: SilenceEventKind,
+    pub last_seen: u64,
+    pub detected_at: u64,
+}
+
+#[derive(Default)]
+pub struct SilenceMonitor {
+    threshold: Option<u64>,
+    last_seen: HashMap<String, u64>,
+    silent: HashSet<String>,
+    next_id: u64,
+    subscribers: Vec<(u64, SilenceCallback)>,
+}
+
+impl SilenceMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_threshold(&mut self, threshold: Option<u64>) {
+        self.threshold = threshold;
+        if threshold.is_none() {
+            self.silent.clear();
+        }
+    }
+
+    pub fn threshold(&self) -> Option<u64> {
+        self.threshold
+    }
+
+    pub fn last_seen(&self, sensor_id: &str) -> Option<u64> {
+        self.last_seen.get(sensor_id).copied()
+    }
+
+    pub fn silent_sensors(&self) -> Vec<String> {
+        let mut sensors: Vec<String> = self.silent.iter().cloned().collect();
+        sensors.sort();
+        sensors
+    }
+
+    pub fn subscribe(&mut self, callback: SilenceCallback) -> u64 {
+        self.next_id += 1;
+        self.subscribers.push((self.next_id, callback));
+        self.next_id
+    }
+
+    pub fn unsubscribe(&mut self, id: u64) -> bool {
+        let before = self.subscribers.len();
+        self.subscribers.retain(|(sid, _)| *sid != id);
+        self.subscribers.len() != before
+    }
+
+    pub fn subscribers(&self) -> Vec<SilenceCallback> {
+        self.subscribers.iter().map(|(_, cb)| cb.clone()).collect()
+    }
+
+    pub fn on_reading(&mut self, sensor_id: &str, timestamp: u64, now: u64) -> Option<SilenceEvent> {
+        let last = self.last_seen.entry(sensor_id.to_string()).or_insert(timestamp);
+        *last = (*last).max(timestamp);
+        self.silent.remove(sensor_id).then(|| SilenceEvent {
+            sensor_id: sensor_id.to_string(),
+            kind: SilenceEventKind::Resumed,
+            last_seen: timestamp,
+            detected_at: now,
+        })
+    }
+
+    /// Reports each sensor once when its silence first exceeds the threshold.
+    pub fn check(&mut self, now: u64) -> Vec<SilenceEvent> {
+        let Some(threshold) = self.threshold else {
+            return Vec::new();
+        };
+        let mut events = Vec::new();
+        for (sensor_id, last_seen) in &self.last_seen {
+            if now.saturating_sub(*last_seen) > threshold && self.silent.insert(sensor_id.clone()) {
+                events.push(SilenceEvent {
+                    sensor_id: sensor_id.clone(),
+                    kind: SilenceEventKind::WentSilent,
+                    last_seen: *last_seen,
+                    detected_at: now,
+                });
+            }
+        }
+        events.sort_by(|a, b| a.sensor_id.cmp(&b.sensor_id));
+        events
+    }
+}
@@ -0,0 +1,347 @@
+//! Public gateway mode. A gateway node can answer unauthenticated clients, such
+//! as a community dashboard, from a read-only subset of its data: metric
+//! aggregates and messages on public channels. Each client address gets its
+//! own request budget, and successful responses are cached for a few seconds
+//! so a busy page costs one computation. The host's HTTP server routes public
+//! `GET` requests to `gateway_get`, or to `gateway_get_conditional` to answer
+//! `If-None-Match`.
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::aggregate::MetricAggregate;
+use crate::channels::{ChannelMessage, Visibility};
+use crate::dashboard::DAY_SECS;
+use crate::error::BridgeError;
+#[cfg(feature = "graphql")]
+use crate::graphql::GraphqlLimits;
+use crate::response_cache::{self, CachedResponse};
+use crate::{clock, EcoBlockContext};
+
+/// Client addresses tracked at once; idle ones are forgotten first.
+const MAX_CLIENTS: usize = 4096;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GatewayConfig {
+    /// Sustained requests per client address.
+    pub requests_per_minute: u32,
+    /// Requests a client may make at once before being throttled.
+    pub burst: u32,
+    /// Seconds a successful response may be served from the cache. Any new
+    /// block, record or change ends it sooner.
+    pub cache_secs: u64,
+    /// Most messages returned per request.
+    pub max_rows: usize,
+    /// Serves `/graphql` to public clients, with the `graphql` feature. They
+    /// get the public schema, and its lists hold at most `max_rows` items.
+    #[serde(default)]
+    pub graphql: bool,
+}
+
+impl Default for GatewayConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: 30,
+            burst: 10,
+            cache_secs: 10,
+            max_rows: 100,
+            graphql: false,
+        }
+    }
+}
+
+impl GatewayConfig {
+    pub fn validate(&self) -> Result<(), BridgeError> {
+        if self.requests_per_minute == 0 || self.burst == 0 || self.max_rows == 0 {
+            return Err(BridgeError::InvalidInput("Invalid gateway config: rates, burst and max_rows must be positive".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GatewayResponse {
+    pub status: u16,
+    pub content_type: String,
+    pub body: String,
+    /// Seconds the client should wait, for `429` answers.
+    pub retry_after: Option<u64>,
+    /// Sent on successful and `304` answers.
+    pub etag: Option<String>,
+}
+
+impl GatewayResponse {
+    fn json(status: u16, body: Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json".to_string(),
+            body: body.to_string(),
+            retry_after: None,
+            etag: None,
+        }
+    }
+
+    fn error(status: u16, message: String) -> Self {
+        Self::json(status, json!({ "error": message }))
+    }
+}
+
+impl From<CachedResponse> for GatewayResponse {
+    fn from(response: CachedResponse) -> Self {
+        Self {
+            status: response.status,
+            content_type: response.content_type,
+            body: response.body,
+            retry_after: None,
+            etag: Some(response.etag),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GatewayStats {
+    pub served: u64,
+    /// Answers taken from the response cache.
+    pub cached: u64,
+    /// `304` answers to clients that already held the response.
+    pub not_modified: u64,
+    pub throttled: u64,
+    /// Client addresses currently tracked.
+    pub clients: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated_at: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Gateway {
+    config: Option<GatewayConfig>,
+    clients: HashMap<String, Bucket>,
+    stats: GatewayStats,
+}
+
+impl Gateway {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn config(&self) -> Option<&GatewayConfig> {
+        self.config.as_ref()
+    }
+
+    pub fn stats(&self) -> GatewayStats {
+        GatewayStats {
+            clients: self.clients.len(),
+            ..self.stats
+        }
+    }
+
+    /// Takes a request from `client`'s budget, or returns the seconds until one
+    /// is available.
+    fn admit(&mut self, config: &GatewayConfig, client: &str, now: u64) -> Result<(), u64> {
+        let rate = config.requests_per_minute as f64 / 60.0;
+        let burst = config.burst as f64;
+        if self.clients.len() >= MAX_CLIENTS && !self.clients.contains_key(client) {
+            // Clients whose budget has refilled lose nothing by being forgotten.
+            self.clients.retain(|_, b| b.tokens + now.saturating_sub(b.updated_at) as f64 * rate < burst);
+            if self.clients.len() >= MAX_CLIENTS {
+                if let Some(oldest) = self.clients.iter().min_by_key(|(_, b)| b.updated_at).map(|(c, _)| c.clone()) {
+                    self.clients.remove(&oldest);
+                }
+            }
+        }
+        let bucket = self.clients.entry(client.to_string()).or_insert(Bucket { tokens: burst, updated_at: now });
+        bucket.tokens = (bucket.tokens + now.saturating_sub(bucket.updated_at) as f64 * rate).min(burst);
+        bucket.updated_at = now;
+        if bucket.tokens < 1.0 {
+            return Err(((1.0 - bucket.tokens) / rate).ceil() as u64);
+        }
+        bucket.tokens -= 1.0;
+        Ok(())
+    }
+}
+
+pub(crate) fn percent_decode(text: &str) -> Result<String, BridgeError> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = text.get(i + 1..i + 3).ok_or_else(|| BridgeError::InvalidInput("Invalid percent-encoding".to_string()))?;
+            out.push(u8::from_str_radix(hex, 16).map_err(|_| BridgeError::InvalidInput("Invalid percent-encoding".to_string()))?);
+            i += 3;
+        } else {
+            out.push(if bytes[i] == b'+' { b' ' } else { bytes[i] });
+            i += 1;
+        }
+    }
+    String::from_utf8(out).map_err(|_| BridgeError::InvalidInput("Invalid percent-encoding".to_string()))
+}
+
+fn parse_query(query: &str) -> Result<HashMap<String, String>, BridgeError> {
+    query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Ok((percent_decode(key)?, percent_decode(value)?))
+        })
+        .collect()
+}
+
+impl EcoBlockContext {
+    pub fn enable_gateway(&mut self, config: GatewayConfig) -> Result<(), BridgeError> {
+        config.validate()?;
+        self.responses.clear();
+        self.gateway = Gateway {
+            config: Some(config),
+            ..Gateway::default()
+        };
+        Ok(())
+    }
+
+    pub fn disable_gateway(&mut self) {
+        self.gateway = Gateway::default();
+    }
+
+    /// Answers a public `GET` for `path` (with its query) from `client`, the
+    /// caller's IP address. `if_none_match` is the request's header of that
+    /// name, if any.
+    pub fn gateway_get(&mut self, client: &str, path_and_query: &str, if_none_match: Option<&str>) -> GatewayResponse {
+        let Some(config) = self.gateway.config.clone() else {
+            return GatewayResponse::error(503, "Public gateway is disabled".to_string());
+        };
+        let now = clock::now_secs();
+        if let Err(wait) = self.gateway.admit(&config, client, now) {
+            self.gateway.stats.throttled += 1;
+            return GatewayResponse {
+                retry_after: Some(wait.max(1)),
+                ..GatewayResponse::error(429, format!("Too many requests from {}", client))
+            };
+        }
+        self.gateway.stats.served += 1;
+        let (key, stamp) = (format!("gateway {}", path_and_query), self.response_stamp());
+        let response = match self.responses.get(&key, stamp, now, Some(config.cache_secs)) {
+            Some(cached) => {
+                self.gateway.stats.cached += 1;
+                cached.clone()
+            }
+            None => {
+                let response = self.route_gateway(&config, path_and_query, now);
+                if response.status != 200 {
+                    return response;
+                }
+                let response = CachedResponse::new(stamp, response.status, response.content_type, response.body);
+                if config.cache_secs > 0 {
+                    self.responses.insert(&key, stamp, now, &response);
+                }
+                response
+            }
+        };
+        if response_cache::matches(if_none_match, &response.etag) {
+            self.gateway.stats.not_modified += 1;
+            return response.not_modified().into();
+        }
+        response.into()
+    }
+
+    fn route_gateway(&self, config: &GatewayConfig, path_and_query: &str, now: u64) -> GatewayResponse {
+        let (path, query) = path_and_query.split_once('?').unwrap_or((path_and_query, ""));
+        let params = match parse_query(query) {
+            Ok(params) => params,
+            Err(e) => return GatewayResponse::error(400, e.to_string()),
+        };
+        let number = |name: &str, default: u64| -> Result<u64, BridgeError> {
+            params
+                .get(name)
+                .map(|v| v.parse().map_err(|_| BridgeError::InvalidInput(format!("Invalid {} {:?}", name, v))))
+                .unwrap_or(Ok(default))
+        };
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let result = match segments.as_slice() {
+            ["aggregates"] => number("from", now.saturating_sub(DAY_SECS))
+                .and_then(|from| Ok((from, number("to", now)?)))
+                .map(|(from, to)| self.gateway_aggregates(params.get("metric"), from, to)),
+            ["channels"] => Ok(self.gateway_channels()),
+            ["channels", channel, "messages"] => percent_decode(channel).and_then(|channel| {
+                let limit = number("limit", config.max_rows as u64)?.min(config.max_rows as u64) as usize;
+                self.gateway_messages(&channel, limit)
+            }),
+            #[cfg(feature = "graphql")]
+            ["graphql"] if config.graphql => {
+                let query = params.get("query").map(String::as_str).unwrap_or_default();
+                let response = self.graphql_with(query, params.get("variables").map(String::as_str), GraphqlLimits::public(config.max_rows));
+                let status = if response.data.is_some() { 200 } else { 400 };
+                return GatewayResponse::json(status, serde_json::to_value(&response).unwrap_or_default());
+            }
+            _ => return GatewayResponse::error(404, format!("Unknown path {}", path)),
+        };
+        match result {
+            Ok(body) => GatewayResponse::json(200, body),
+            Err(e @ BridgeError::NotFound(_)) => GatewayResponse::error(404, e.to_string()),
+            Err(e) => GatewayResponse::error(400, e.to_string()),
+        }
+    }
+
+    fn gateway_aggregates(&self, metric: Option<&String>, from: u64, to: u64) -> Value {
+        let aggregates: Vec<Value> = self
+            .public_aggregates(metric.map(String::as_str), from, to)
+            .into_iter()
+            .map(|a| serde_json::to_value(a).unwrap_or_default())
+            .collect();
+        json!({ "from": from, "to": to, "aggregates": aggregates })
+    }
+
+    fn gateway_channels(&self) -> Value {
+        json!({ "channels": self.public_channels() })
+    }
+
+    fn gateway_messages(&self, channel: &str, limit: usize) -> Result<Value, BridgeError> {
+        let messages: Vec<Value> = self
+            .public_messages(channel, limit)?
+            .into_iter()
+            .map(|m| json!({ "id": m.id, "author": m.author, "timestamp": m.timestamp, "data": m.data }))
+            .collect();
+        Ok(json!({ "channel": channel, "messages": messages }))
+    }
+
+    /// Aggregates of `metric`, or of every metric with readings between
+    /// `from` and `to`.
+    pub(crate) fn public_aggregates(&self, metric: Option<&str>, from: u64, to: u64) -> Vec<MetricAggregate> {
+        let mut metrics: Vec<String> = match metric {
+            Some(metric) => vec![metric.to_string()],
+            None => self.readings.in_range(from, to).flat_map(|r| r.values.keys().cloned()).collect(),
+        };
+        metrics.sort();
+        metrics.dedup();
+        metrics
+            .iter()
+            .map(|m| self.aggregate_metric(m, from, to))
+            .filter(|a| metric.is_some() || a.count > 0)
+            .collect()
+    }
+
+    pub(crate) fn public_channels(&self) -> Vec<String> {
+        self.channels
+            .channels()
+            .into_iter()
+            .filter(|(_, visibility)| *visibility == Visibility::Public)
+            .map(|(channel, _)| channel)
+            .collect()
+    }
+
+    /// Newest plain messages of a public channel.
+    pub(crate) fn public_messages(&self, channel: &str, limit: usize) -> Result<Vec<ChannelMessage>, BridgeError> {
+        if self.channels.visibility(channel) != Visibility::Public {
+            return Err(BridgeError::NotFound(format!("Unknown channel {}", channel)));
+        }
+        let mut messages = self.read_channel(channel);
+        messages.retain(|m| m.visibility == Visibility::Public);
+        messages.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        messages.truncate(limit);
+        Ok(messages)
+    }
+}
@@ -0,0 +1,241 @@
+//! Areas of interest. A node signs the polygon it cares about into a
+//! `geofence` record; while scoping is on, blocks whose reading lies outside a
+//! peer's declared area are not pushed to that peer. Local subscribers can
+//! also receive only the block events located inside a polygon.
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::block_id::BlockId;
+use crate::error::BridgeError;
+use crate::events::{BridgeEvent, EventCallback};
+use crate::peer_id::PeerId;
+use crate::readings::Reading;
+use crate::records::Record;
+use crate::{clock, EcoBlockContext};
+
+pub const GEOFENCE_KIND: &str = "geofence";
+/// Most vertices accepted in a polygon, so declarations stay small.
+pub const MAX_VERTICES: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoPoint {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl GeoPoint {
+    /// The `latitude` and `longitude` values of a reading, if it has both.
+    pub fn of_reading(reading: &Reading) -> Option<Self> {
+        Some(Self {
+            latitude: reading.value("latitude")?,
+            longitude: reading.value("longitude")?,
+        })
+    }
+}
+
+/// A simple polygon in degrees, closed implicitly from the last vertex to the
+/// first. Polygons crossing the antimeridian are not supported.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<GeoPoint>", into = "Vec<GeoPoint>")]
+pub struct Geofence {
+    vertices: Vec<GeoPoint>,
+}
+
+impl Geofence {
+    pub fn new(vertices: Vec<GeoPoint>) -> Result<Self, BridgeError> {
+        if vertices.len() < 3 || vertices.len() > MAX_VERTICES {
+            return Err(BridgeError::InvalidInput(format!("Invalid geofence: expected 3 to {} vertices, got {}", MAX_VERTICES, vertices.len())));
+        }
+        if let Some(p) = vertices
+            .iter()
+            .find(|p| !(-90.0..=90.0).contains(&p.latitude) || !(-180.0..=180.0).contains(&p.longitude))
+        {
+            return Err(BridgeError::InvalidInput(format!("Invalid geofence vertex ({}, {}): out of range", p.latitude, p.longitude)));
+        }
+        Ok(Self { vertices })
+    }
+
+    pub fn vertices(&self) -> &[GeoPoint] {
+        &self.vertices
+    }
+
+    /// Even-odd ray casting; points on an edge may fall either side.
+    pub fn contains(&self, point: GeoPoint) -> bool {
+        let mut inside = false;
+        let mut previous = self.vertices[self.vertices.len() - 1];
+        for &vertex in &self.vertices {
+            if (vertex.latitude > point.latitude) != (previous.latitude > point.latitude) {
+                let crossing = vertex.longitude
+                    + (point.latitude - vertex.latitude) / (previous.latitude - vertex.latitude)
+                        * (previous.longitude - vertex.longitude);
+                if point.longitude < crossing {
+                    inside = !inside;
+                }
+            }
+            previous = vertex;
+        }
+        inside
+    }
+}
+
+impl TryFrom<Vec<GeoPoint>> for Geofence {
+    type Error = BridgeError;
+
+    fn try_from(vertices: Vec<GeoPoint>) -> Result<Self, BridgeError> {
+        Self::new(vertices)
+    }
+}
+
+impl From<Geofence> for Vec<GeoPoint> {
+    fn from(geofence: Geofence) -> Self {
+        geofence.vertices
+    }
+}
+
+/// Body of a `geofence` record. `area: None` withdraws an earlier declaration.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeofenceDeclaration {
+    pub node_id: String,
+    pub area: Option<Geofence>,
+    pub declared_at: u64,
+}
+
+pub struct GeofenceState {
+    /// When false, blocks are propagated regardless of declared areas.
+    pub scoping: bool,
+    local: Option<Geofence>,
+    remote: HashMap<String, GeofenceDeclaration>,
+    subscriptions: Vec<(u64, Geofence, EventCallback)>,
+    next_subscription: u64,
+    /// Pushes skipped because the block lay outside the peer's area.
+    skipped: u64,
+}
+
+impl Default for GeofenceState {
+    fn default() -> Self {
+        Self {
+            scoping: true,
+            local: None,
+            remote: HashMap::new(),
+            subscriptions: Vec::new(),
+            next_subscription: 0,
+            skipped: 0,
+        }
+    }
+}
+
+impl GeofenceState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn local(&self) -> Option<&Geofence> {
+        self.local.as_ref()
+    }
+
+    /// Declared areas of other nodes, by node id.
+    pub fn declared(&self) -> Vec<GeofenceDeclaration> {
+        let mut declared: Vec<GeofenceDeclaration> = self.remote.values().filter(|d| d.area.is_some()).cloned().collect();
+        declared.sort_by(|a, b| a.node_id.cmp(&b.node_id));
+        declared
+    }
+
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Keeps only the most recent declaration per node.
+    pub fn receive(&mut self, declaration: GeofenceDeclaration) {
+        match self.remote.get(&declaration.node_id) {
+            Some(existing) if existing.declared_at >= declaration.declared_at => {}
+            _ => {
+                self.remote.insert(declaration.node_id.clone(), declaration);
+            }
+        }
+    }
+
+    pub fn subscribe(&mut self, area: Geofence, callback: EventCallback) -> u64 {
+        self.next_subscription += 1;
+        self.subscriptions.push((self.next_subscription, area, callback));
+        self.next_subscription
+    }
+
+    pub fn unsubscribe(&mut self, id: u64) -> bool {
+        let before = self.subscriptions.len();
+        self.subscriptions.retain(|(sid, _, _)| *sid != id);
+        self.subscriptions.len() != before
+    }
+
+    /// Whether a block at `location` should be kept from `peer`. Blocks without
+    /// a location, and peers without a declared area, are never excluded.
+    pub fn excludes(&self, peer: &PeerId, location: Option<GeoPoint>) -> bool {
+        let (Some(point), true) = (location, self.scoping) else {
+            return false;
+        };
+        self.remote
+            .get(peer.as_str())
+            .and_then(|d| d.area.as_ref())
+            .is_some_and(|area| !area.contains(point))
+    }
+}
+
+impl EcoBlockContext {
+    /// Signs this node's area of interest as a `geofence` record for gossip.
+    /// `None` withdraws it, so every block is pushed to this node again.
+    pub fn declare_geofence(&mut self, area: Option<Geofence>) -> Result<Record, BridgeError> {
+        let now = clock::now_secs();
+        let declaration = GeofenceDeclaration {
+            node_id: self.keypair.public_key_hex(),
+            area: area.clone(),
+            declared_at: now,
+        };
+        let body = serde_json::to_value(&declaration).unwrap_or_default();
+        let record = self.sign_record(GEOFENCE_KIND, body, now)?;
+        self.records.insert(record.clone());
+        self.geofences.local = area;
+        Ok(record)
+    }
+
+    pub(crate) fn apply_geofence(&mut self, record: &Record) -> Result<(), BridgeError> {
+        let declaration: GeofenceDeclaration = serde_json::from_value(record.body.clone())
+            .map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+        if declaration.node_id != record.author {
+            return Err(BridgeError::Unauthorized(format!("Geofence record {} not signed by its node", record.id)));
+        }
+        self.geofences.receive(declaration);
+        Ok(())
+    }
+
+    pub(crate) fn block_location(&self, block_id: &str) -> Option<GeoPoint> {
+        self.readings.get(block_id).and_then(GeoPoint::of_reading)
+    }
+
+    /// Drops the blocks that lie outside `peer`'s declared area, counting them.
+    pub(crate) fn scope_to_peer(&mut self, peer: &PeerId, blocks: Vec<BlockId>) -> Vec<BlockId> {
+        let before = blocks.len();
+        let kept: Vec<BlockId> = blocks
+            .into_iter()
+            .filter(|id| !self.geofences.excludes(peer, self.block_location(id.as_str())))
+            .collect();
+        self.geofences.skipped += (before - kept.len()) as u64;
+        kept
+    }
+
+    /// Queues block events for the subscribers whose polygon contains the
+    /// block's location.
+    pub(crate) fn emit_geofenced(&mut self, event: &BridgeEvent) {
+        let id = match event {
+            BridgeEvent::BlockInserted { id, .. } | BridgeEvent::BlockReceived { id, .. } => id,
+            _ => return,
+        };
+        let Some(point) = self.block_location(id.as_str()) else {
+            return;
+        };
+        for (_, area, callback) in &self.geofences.subscriptions {
+            if area.contains(point) {
+                let (callback, event) = (callback.clone(), event.clone());
+                self.pending.push(Box::new(move || callback(event)));
+            }
+        }
+    }
+}
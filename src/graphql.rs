@@ -0,0 +1,824 @@
+//! Read-only GraphQL over blocks, sensors, readings, peers and active alerts,
+//! so a frontend can fetch the shape it needs in one request. The parser
+//! covers queries with aliases, arguments and variables; fragments,
+//! directives, mutations and subscriptions are refused. Schema:
+//!
+//! ```graphql
+//! type Query {
+//!   blocks(author: String, since: Int, until: Int, first: Int): [Block!]!
+//!   block(id: String!): Block
+//!   sensors(first: Int): [Sensor!]!
+//!   sensor(id: String!): Sensor
+//!   readings(sensor: String, metric: String, from: Int, to: Int, first: Int): [Reading!]!
+//!   peers: [Peer!]!
+//!   alerts(metric: String, sensor: String): [Alert!]!
+//! }
+//! type Block { id: String! author: String! timestamp: Int! parentIds: [String!]! parents: [Block!]! reading: Reading }
+//! type Sensor { id: String! metrics: [String!]! readingCount: Int! lastReading: Int!
+//!               latest(metric: String!): Float readings(metric: String, from: Int, to: Int, first: Int): [Reading!]! alerts: [Alert!]! }
+//! type Reading { blockId: String! sensorId: String! timestamp: Int! metrics: [String!]! values: JSON!
+//!                value(metric: String!): Float block: Block sensor: Sensor }
+//! type Peer { id: String! linkWeight: Float! lastSeen: Int }
+//! type Alert { metric: String! sensorId: String! direction: String! since: Int! lastValue: Float! sensor: Sensor }
+//! ```
+//!
+//! Lists are newest first and hold at most `first` items (100 by default,
+//! 1000 at most). Readings leave out flagged values.
+//!
+//! Public clients of a gateway get a smaller schema, limited to what the
+//! gateway's REST paths serve:
+//!
+//! ```graphql
+//! type Query {
+//!   aggregates(metric: String, from: Int, to: Int): [Aggregate!]!
+//!   channels: [String!]!
+//!   messages(channel: String!, first: Int): [Message!]!
+//! }
+//! type Aggregate { metric: String! count: Int! min: Float max: Float mean: Float sum: Float! }
+//! type Message { id: String! author: String! timestamp: Int! data: JSON }
+//! ```
+//!
+//! Before a query runs, its cost is counted: one per field, with every list
+//! counted at the most items it may return. Queries over the budget are
+//! refused.
+
+use std::cell::OnceCell;
+use ecoblock_storage::tangle::block::TangleBlock;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use crate::aggregate::MetricAggregate;
+use crate::channels::ChannelMessage;
+use crate::dashboard::{PeerHealth, DAY_SECS};
+use crate::error::BridgeError;
+use crate::inventory::{self, SensorSummary};
+use crate::peer_id::PeerId;
+use crate::readings::Reading;
+use crate::thresholds::{ActiveAlert, ThresholdDirection};
+use crate::{clock, EcoBlockContext};
+
+pub const DEFAULT_FIRST: usize = 100;
+pub const MAX_FIRST: usize = 1000;
+/// Deepest nesting of selection sets a query may use.
+pub const MAX_DEPTH: usize = 8;
+/// Most fields a host query may resolve.
+pub const MAX_COST: u64 = 100_000;
+/// Most fields a public gateway client's query may resolve.
+pub const PUBLIC_MAX_COST: u64 = 2_000;
+
+/// What one query may read, and how much.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GraphqlLimits {
+    /// Largest `first` served; larger values are clamped to it.
+    pub max_first: usize,
+    /// Most fields the query may resolve, checked before it runs.
+    pub max_cost: u64,
+    /// Serves the public schema only.
+    pub public: bool,
+}
+
+impl GraphqlLimits {
+    pub const HOST: Self = Self {
+        max_first: MAX_FIRST,
+        max_cost: MAX_COST,
+        public: false,
+    };
+
+    /// For public gateway clients, whose lists hold at most `max_rows` items.
+    pub fn public(max_rows: usize) -> Self {
+        Self {
+            max_first: max_rows.min(MAX_FIRST),
+            max_cost: PUBLIC_MAX_COST,
+            public: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphqlError {
+    pub message: String,
+}
+
+/// The standard GraphQL response: `data` is `null` when the query was refused.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphqlResponse {
+    pub data: Option<Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<GraphqlError>,
+}
+
+impl GraphqlResponse {
+    fn error(message: String) -> Self {
+        Self {
+            data: None,
+            errors: vec![GraphqlError { message }],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Punct(char),
+    Str(String),
+    Int(i64),
+    Float(f64),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, BridgeError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            ' ' | '\t' | '\n' | '\r' | ',' | '\u{feff}' => i += 1,
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '{' | '}' | '(' | ')' | ':' | '!' | '[' | ']' | '$' | '=' => {
+                tokens.push(Token::Punct(c));
+                i += 1;
+            }
+            '.' | '@' | '|' | '&' => return Err(BridgeError::InvalidInput("Invalid query: fragments and directives are not supported".to_string())),
+            '"' => {
+                let mut text = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None | Some('\n') => return Err(BridgeError::InvalidInput("Invalid query: unterminated string".to_string())),
+                        Some('"') => break,
+                        Some('\\') => {
+                            let escaped = match chars.get(i + 1) {
+                                Some('n') => '\n',
+                                Some('t') => '\t',
+                                Some('r') => '\r',
+                                Some(e @ ('"' | '\\' | '/')) => *e,
+                                Some('u') => {
+                                    let hex: String = chars.get(i + 2..i + 6).unwrap_or_default().iter().collect();
+                                    i += 4;
+                                    u32::from_str_radix(&hex, 16)
+                                        .ok()
+                                        .and_then(char::from_u32)
+                                        .ok_or_else(|| BridgeError::InvalidInput(format!("Invalid query: bad escape \\u{}", hex)))?
+                                }
+                                _ => return Err(BridgeError::InvalidInput("Invalid query: bad escape in string".to_string())),
+                            };
+                            text.push(escaped);
+                            i += 2;
+                        }
+                        Some(other) => {
+                            text.push(*other);
+                            i += 1;
+                        }
+                    }
+                }
+                i += 1;
+                tokens.push(Token::Str(text));
+            }
+            '-' | '0'..='9' => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_digit() || matches!(chars[i], '.' | 'e' | 'E' | '+' | '-')) {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let token = match text.parse::<i64>() {
+                    Ok(n) => Token::Int(n),
+                    Err(_) => Token::Float(text.parse().map_err(|_| BridgeError::InvalidInput(format!("Invalid query: bad number {}", text)))?),
+                };
+                tokens.push(token);
+            }
+            c if c == '_' || c.is_ascii_alphabetic() => {
+                let start = i;
+                while i < chars.len() && (chars[i] == '_' || chars[i].is_ascii_alphanumeric()) {
+                    i += 1;
+                }
+                tokens.push(Token::Name(chars[start..i].iter().collect()));
+            }
+            other => return Err(BridgeError::InvalidInput(format!("Invalid query: unexpected character {:?}", other))),
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Literal {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    /// Enum values are treated as strings.
+    Enum(String),
+    Variable(String),
+    List(Vec<Literal>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Field {
+    alias: Option<String>,
+    name: String,
+    arguments: Vec<(String, Literal)>,
+    selection: Vec<Field>,
+}
+
+impl Field {
+    fn key(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Token, BridgeError> {
+        let token = self.tokens.get(self.pos).cloned().ok_or_else(|| BridgeError::InvalidInput("Invalid query: unexpected end".to_string()))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(&Token::Punct(c)) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), BridgeError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(BridgeError::InvalidInput(format!("Invalid query: expected '{}'", c)))
+        }
+    }
+
+    fn name(&mut self) -> Result<String, BridgeError> {
+        match self.next()? {
+            Token::Name(name) => Ok(name),
+            other => Err(BridgeError::InvalidInput(format!("Invalid query: expected a name, found {:?}", other))),
+        }
+    }
+
+    /// `query Name($v: Type = default) { ... }` or a bare selection set.
+    /// Returns the selection and the variables' default values.
+    fn document(&mut self) -> Result<(Vec<Field>, Map<String, Value>), BridgeError> {
+        let mut defaults = Map::new();
+        if let Some(Token::Name(keyword)) = self.peek() {
+            if keyword != "query" {
+                return Err(BridgeError::InvalidInput(format!("Invalid query: {} operations are not supported", keyword)));
+            }
+            self.pos += 1;
+            if matches!(self.peek(), Some(Token::Name(_))) {
+                self.pos += 1;
+            }
+            if self.eat('(') {
+                while !self.eat(')') {
+                    self.expect('$')?;
+                    let name = self.name()?;
+                    self.expect(':')?;
+                    self.skip_type()?;
+                    if self.eat('=') {
+                        let value = self.literal(false)?;
+                        defaults.insert(name, literal_json(&value, &Map::new())?);
+                    }
+                }
+            }
+        }
+        let selection = self.selection(1)?;
+        if self.pos != self.tokens.len() {
+            return Err(BridgeError::InvalidInput("Invalid query: only one operation is supported".to_string()));
+        }
+        Ok((selection, defaults))
+    }
+
+    fn skip_type(&mut self) -> Result<(), BridgeError> {
+        if self.eat('[') {
+            self.skip_type()?;
+            self.expect(']')?;
+        } else {
+            self.name()?;
+        }
+        self.eat('!');
+        Ok(())
+    }
+
+    fn selection(&mut self, depth: usize) -> Result<Vec<Field>, BridgeError> {
+        if depth > MAX_DEPTH {
+            return Err(BridgeError::InvalidInput(format!("Invalid query: nested deeper than {} levels", MAX_DEPTH)));
+        }
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        while !self.eat('}') {
+            let mut name = self.name()?;
+            let mut alias = None;
+            if self.eat(':') {
+                alias = Some(name);
+                name = self.name()?;
+            }
+            let mut arguments = Vec::new();
+            if self.eat('(') {
+                while !self.eat(')') {
+                    let argument = self.name()?;
+                    self.expect(':')?;
+                    arguments.push((argument, self.literal(true)?));
+                }
+            }
+            let selection = if self.peek() == Some(&Token::Punct('{')) {
+                self.selection(depth + 1)?
+            } else {
+                Vec::new()
+            };
+            fields.push(Field { alias, name, arguments, selection });
+        }
+        Ok(fields)
+    }
+
+    fn literal(&mut self, variables: bool) -> Result<Literal, BridgeError> {
+        Ok(match self.next()? {
+            Token::Punct('$') if variables => Literal::Variable(self.name()?),
+            Token::Punct('[') => {
+                let mut items = Vec::new();
+                while !self.eat(']') {
+                    items.push(self.literal(variables)?);
+                }
+                Literal::List(items)
+            }
+            Token::Str(s) => Literal::Str(s),
+            Token::Int(n) => Literal::Int(n),
+            Token::Float(f) => Literal::Float(f),
+            Token::Name(n) => match n.as_str() {
+                "null" => Literal::Null,
+                "true" => Literal::Bool(true),
+                "false" => Literal::Bool(false),
+                _ => Literal::Enum(n),
+            },
+            other => return Err(BridgeError::InvalidInput(format!("Invalid query: unexpected {:?} in argument", other))),
+        })
+    }
+}
+
+fn literal_json(literal: &Literal, variables: &Map<String, Value>) -> Result<Value, BridgeError> {
+    Ok(match literal {
+        Literal::Null => Value::Null,
+        Literal::Bool(b) => json!(b),
+        Literal::Int(n) => json!(n),
+        Literal::Float(f) => json!(f),
+        Literal::Str(s) | Literal::Enum(s) => json!(s),
+        Literal::Variable(name) => variables.get(name).cloned().unwrap_or(Value::Null),
+        Literal::List(items) => Value::Array(items.iter().map(|i| literal_json(i, variables)).collect::<Result<_, _>>()?),
+    })
+}
+
+struct Executor<'a> {
+    ctx: &'a EcoBlockContext,
+    variables: Map<String, Value>,
+    limits: GraphqlLimits,
+    sensors: OnceCell<Vec<SensorSummary>>,
+}
+
+impl Executor<'_> {
+    fn argument(&self, field: &Field, name: &str) -> Result<Value, BridgeError> {
+        match field.arguments.iter().find(|(n, _)| n == name) {
+            Some((_, literal)) => literal_json(literal, &self.variables),
+            None => Ok(Value::Null),
+        }
+    }
+
+    fn string(&self, field: &Field, name: &str) -> Result<Option<String>, BridgeError> {
+        match self.argument(field, name)? {
+            Value::Null => Ok(None),
+            Value::String(s) => Ok(Some(s)),
+            other => Err(BridgeError::InvalidInput(format!("Invalid argument {} of {}: expected a string, got {}", name, field.name, other))),
+        }
+    }
+
+    fn required(&self, field: &Field, name: &str) -> Result<String, BridgeError> {
+        self.string(field, name)?
+            .ok_or_else(|| BridgeError::InvalidInput(format!("Invalid argument {} of {}: required", name, field.name)))
+    }
+
+    fn number(&self, field: &Field, name: &str) -> Result<Option<u64>, BridgeError> {
+        match self.argument(field, name)? {
+            Value::Null => Ok(None),
+            value => value
+                .as_u64()
+                .map(Some)
+                .ok_or_else(|| BridgeError::InvalidInput(format!("Invalid argument {} of {}: expected a non-negative integer", name, field.name))),
+        }
+    }
+
+    fn first(&self, field: &Field) -> Result<usize, BridgeError> {
+        Ok(self.number(field, "first")?.map_or(DEFAULT_FIRST, |n| n as usize).min(self.limits.max_first))
+    }
+
+    /// Upper bound on the fields `fields` resolve, counting every list at the
+    /// most items it may return.
+    fn cost(&self, fields: &[Field]) -> Result<u64, BridgeError> {
+        let mut total: u64 = 0;
+        for field in fields {
+            let items = match field.name.as_str() {
+                "blocks" | "sensors" | "readings" | "messages" => self.first(field)?,
+                "peers" | "alerts" | "parents" | "aggregates" | "channels" => self.limits.max_first,
+                _ => 1,
+            };
+            let each = self.cost(&field.selection)?.saturating_add(1);
+            total = total.saturating_add((items as u64).saturating_mul(each));
+        }
+        Ok(total)
+    }
+
+    fn sensors(&self) -> &[SensorSummary] {
+        self.sensors.get_or_init(|| inventory::summarize(&self.ctx.readings))
+    }
+
+    /// Resolves `field`'s selection on one object.
+    fn object<T>(&self, field: &Field, typename: &str, item: &T, resolve: fn(&Self, &Field, &T) -> Result<Value, BridgeError>) -> Result<Value, BridgeError> {
+        if field.selection.is_empty() {
+            return Err(BridgeError::InvalidInput(format!("Invalid query: {} needs a selection of subfields", field.name)));
+        }
+        let mut object = Map::new();
+        for sub in &field.selection {
+            let value = match sub.name.as_str() {
+                "__typename" => json!(typename),
+                _ => resolve(self, sub, item)?,
+            };
+            object.insert(sub.key().to_string(), value);
+        }
+        Ok(Value::Object(object))
+    }
+
+    fn list<'i, T: 'i>(
+        &self,
+        field: &Field,
+        typename: &str,
+        items: impl IntoIterator<Item = &'i T>,
+        resolve: fn(&Self, &Field, &T) -> Result<Value, BridgeError>,
+    ) -> Result<Value, BridgeError> {
+        items.into_iter().map(|item| self.object(field, typename, item, resolve)).collect::<Result<Vec<_>, _>>().map(Value::Array)
+    }
+
+    fn leaf(field: &Field, value: Value) -> Result<Value, BridgeError> {
+        if !field.selection.is_empty() {
+            return Err(BridgeError::InvalidInput(format!("Invalid query: {} has no subfields", field.name)));
+        }
+        Ok(value)
+    }
+
+    fn query(&self, field: &Field, _: &()) -> Result<Value, BridgeError> {
+        match field.name.as_str() {
+            "blocks" => {
+                let author = self.string(field, "author")?;
+                let since = self.number(field, "since")?.unwrap_or(0);
+                let until = self.number(field, "until")?.unwrap_or(u64::MAX);
+                let mut blocks: Vec<&TangleBlock> = self
+                    .ctx
+                    .block_log
+                    .iter()
+                    .filter(|b| author.as_ref().is_none_or(|a| *a == b.public_key))
+                    .filter(|b| (since..=until).contains(&b.data.data.timestamp))
+                    .collect();
+                blocks.sort_by(|a, b| b.data.data.timestamp.cmp(&a.data.data.timestamp));
+                blocks.truncate(self.first(field)?);
+                self.list(field, "Block", blocks, Self::block)
+            }
+            "block" => match self.ctx.block_log.get(&self.required(field, "id")?) {
+                Some(block) => self.object(field, "Block", block, Self::block),
+                None => Ok(Value::Null),
+            },
+            "sensors" => self.list(field, "Sensor", self.sensors().iter().take(self.first(field)?), Self::sensor),
+            "sensor" => {
+                let id = self.required(field, "id")?;
+                match self.sensors().iter().find(|s| s.sensor_id == id) {
+                    Some(sensor) => self.object(field, "Sensor", sensor, Self::sensor),
+                    None => Ok(Value::Null),
+                }
+            }
+            "readings" => self.readings(field, self.string(field, "sensor")?),
+            "peers" => {
+                let local = self.ctx.keypair.public_key_hex();
+                let peers: Vec<PeerHealth> = self
+                    .ctx
+                    .mesh
+                    .get_neighbors(&local)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .take(self.limits.max_first)
+                    .map(|(peer_id, link_weight)| PeerHealth {
+                        last_seen: self.ctx.records.latest_from(&peer_id),
+                        peer_id: PeerId::from_mesh(peer_id),
+                        link_weight,
+                    })
+                    .collect();
+                self.list(field, "Peer", &peers, Self::peer)
+            }
+            "alerts" => {
+                let metric = self.string(field, "metric")?;
+                let sensor = self.string(field, "sensor")?;
+                let alerts: Vec<ActiveAlert> = self
+                    .ctx
+                    .thresholds
+                    .active_alerts()
+                    .into_iter()
+                    .filter(|a| metric.as_ref().is_none_or(|m| *m == a.metric) && sensor.as_ref().is_none_or(|s| *s == a.sensor_id))
+                    .take(self.limits.max_first)
+                    .collect();
+                self.list(field, "Alert", &alerts, Self::alert)
+            }
+            other => Err(BridgeError::NotFound(format!("Unknown field {} on Query", other))),
+        }
+    }
+
+    /// The root of the public schema: aggregates and public channels only.
+    fn public_query(&self, field: &Field, _: &()) -> Result<Value, BridgeError> {
+        match field.name.as_str() {
+            "aggregates" => {
+                let now = clock::now_secs();
+                let from = self.number(field, "from")?.unwrap_or(now.saturating_sub(DAY_SECS));
+                let to = self.number(field, "to")?.unwrap_or(now);
+                let mut aggregates = self.ctx.public_aggregates(self.string(field, "metric")?.as_deref(), from, to);
+                aggregates.truncate(self.limits.max_first);
+                self.list(field, "Aggregate", &aggregates, Self::aggregate)
+            }
+            "channels" => {
+                let mut channels = self.ctx.public_channels();
+                channels.truncate(self.limits.max_first);
+                Self::leaf(field, json!(channels))
+            }
+            "messages" => {
+                let messages = self.ctx.public_messages(&self.required(field, "channel")?, self.first(field)?)?;
+                self.list(field, "Message", &messages, Self::message)
+            }
+            other => Err(BridgeError::NotFound(format!("Unknown field {} on Query", other))),
+        }
+    }
+
+    /// Newest readings first, of one sensor when `sensor` is given.
+    fn readings(&self, field: &Field, sensor: Option<String>) -> Result<Value, BridgeError> {
+        let metric = self.string(field, "metric")?;
+        let from = self.number(field, "from")?.unwrap_or(0);
+        let to = self.number(field, "to")?.unwrap_or(u64::MAX);
+        let mut readings: Vec<&Reading> = self
+            .ctx
+            .readings
+            .in_range(from, to)
+            .filter(|r| sensor.as_ref().is_none_or(|s| *s == r.sensor_id))
+            .filter(|r| metric.as_ref().is_none_or(|m| self.ctx.derived.value_of(r, m).is_some()))
+            .collect();
+        readings.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        readings.truncate(self.first(field)?);
+        self.list(field, "Reading", readings, Self::reading)
+    }
+
+    fn block(&self, field: &Field, block: &TangleBlock) -> Result<Value, BridgeError> {
+        match field.name.as_str() {
+            "id" => Self::leaf(field, json!(block.id)),
+            "author" => Self::leaf(field, json!(block.public_key)),
+            "timestamp" => Self::leaf(field, json!(block.data.data.timestamp)),
+            "parentIds" => Self::leaf(field, json!(block.data.parents)),
+            "parents" => {
+                let parents: Vec<&TangleBlock> = block
+                    .data
+                    .parents
+                    .iter()
+                    .filter_map(|p| self.ctx.block_log.get(p))
+                    .take(self.limits.max_first)
+                    .collect();
+                self.list(field, "Block", parents, Self::block)
+            }
+            "reading" => match self.ctx.readings.get(&block.id) {
+                Some(reading) => self.object(field, "Reading", reading, Self::reading),
+                None => Ok(Value::Null),
+            },
+            other => Err(BridgeError::NotFound(format!("Unknown field {} on Block", other))),
+        }
+    }
+
+    fn sensor(&self, field: &Field, sensor: &SensorSummary) -> Result<Value, BridgeError> {
+        match field.name.as_str() {
+            "id" => Self::leaf(field, json!(sensor.sensor_id)),
+            "metrics" => Self::leaf(field, json!(sensor.metrics)),
+            "readingCount" => Self::leaf(field, json!(sensor.reading_count)),
+            "lastReading" => Self::leaf(field, json!(sensor.last_reading)),
+            "latest" => {
+                let metric = self.required(field, "metric")?;
+                let latest = self.ctx.readings.last_value(&sensor.sensor_id, &metric);
+                Self::leaf(field, json!(latest.map(|(_, v)| v)))
+            }
+            "readings" => self.readings(field, Some(sensor.sensor_id.clone())),
+            "alerts" => {
+                let alerts: Vec<ActiveAlert> = self
+                    .ctx
+                    .thresholds
+                    .active_alerts()
+                    .into_iter()
+                    .filter(|a| a.sensor_id == sensor.sensor_id)
+                    .take(self.limits.max_first)
+                    .collect();
+                self.list(field, "Alert", &alerts, Self::alert)
+            }
+            other => Err(BridgeError::NotFound(format!("Unknown field {} on Sensor", other))),
+        }
+    }
+
+    fn reading(&self, field: &Field, reading: &Reading) -> Result<Value, BridgeError> {
+        match field.name.as_str() {
+            "blockId" => Self::leaf(field, json!(reading.block_id)),
+            "sensorId" => Self::leaf(field, json!(reading.sensor_id)),
+            "timestamp" => Self::leaf(field, json!(reading.timestamp)),
+            "metrics" => Self::leaf(field, json!(reading.values.keys().collect::<Vec<_>>())),
+            "values" => Self::leaf(field, json!(reading.values)),
+            "value" => {
+                let metric = self.required(field, "metric")?;
+                Self::leaf(field, json!(self.ctx.derived.value_of(reading, &metric)))
+            }
+            "block" => match self.ctx.block_log.get(&reading.block_id) {
+                Some(block) => self.object(field, "Block", block, Self::block),
+                None => Ok(Value::Null),
+            },
+            "sensor" => match self.sensors().iter().find(|s| s.sensor_id == reading.sensor_id) {
+                Some(sensor) => self.object(field, "Sensor", sensor, Self::sensor),
+                None => Ok(Value::Null),
+            },
+            other => Err(BridgeError::NotFound(format!("Unknown field {} on Reading", other))),
+        }
+    }
+
+    fn peer(&self, field: &Field, peer: &PeerHealth) -> Result<Value, BridgeError> {
+        match field.name.as_str() {
+            "id" => Self::leaf(field, json!(peer.peer_id)),
+            "linkWeight" => Self::leaf(field, json!(peer.link_weight)),
+            "lastSeen" => Self::leaf(field, json!(peer.last_seen)),
+            other => Err(BridgeError::NotFound(format!("Unknown field {} on Peer", other))),
+        }
+    }
+
+    fn alert(&self, field: &Field, alert: &ActiveAlert) -> Result<Value, BridgeError> {
+        match field.name.as_str() {
+            "metric" => Self::leaf(field, json!(alert.metric)),
+            "sensorId" => Self::leaf(field, json!(alert.sensor_id)),
+            "direction" => Self::leaf(
+                field,
+                json!(match alert.direction {
+                    ThresholdDirection::Above => "ABOVE",
+                    ThresholdDirection::Below => "BELOW",
+                }),
+            ),
+            "since" => Self::leaf(field, json!(alert.since)),
+            "lastValue" => Self::leaf(field, json!(alert.last_value)),
+            "sensor" => match self.sensors().iter().find(|s| s.sensor_id == alert.sensor_id) {
+                Some(sensor) => self.object(field, "Sensor", sensor, Self::sensor),
+                None => Ok(Value::Null),
+            },
+            other => Err(BridgeError::NotFound(format!("Unknown field {} on Alert", other))),
+        }
+    }
+
+    fn aggregate(&self, field: &Field, aggregate: &MetricAggregate) -> Result<Value, BridgeError> {
+        match field.name.as_str() {
+            "metric" => Self::leaf(field, json!(aggregate.metric)),
+            "count" => Self::leaf(field, json!(aggregate.count)),
+            "min" => Self::leaf(field, json!(aggregate.min)),
+            "max" => Self::leaf(field, json!(aggregate.max)),
+            "mean" => Self::leaf(field, json!(aggregate.mean)),
+            "sum" => Self::leaf(field, json!(aggregate.sum)),
+            other => Err(BridgeError::NotFound(format!("Unknown field {} on Aggregate", other))),
+        }
+    }
+
+    fn message(&self, field: &Field, message: &ChannelMessage) -> Result<Value, BridgeError> {
+        match field.name.as_str() {
+            "id" => Self::leaf(field, json!(message.id)),
+            "author" => Self::leaf(field, json!(message.author)),
+            "timestamp" => Self::leaf(field, json!(message.timestamp)),
+            "data" => Self::leaf(field, json!(message.data)),
+            other => Err(BridgeError::NotFound(format!("Unknown field {} on Message", other))),
+        }
+    }
+}
+
+impl EcoBlockContext {
+    /// Runs a GraphQL query. `variables` is the JSON object of the request's
+    /// `variables`, if any.
+    pub fn graphql(&self, query: &str, variables: Option<&str>) -> GraphqlResponse {
+        self.graphql_with(query, variables, GraphqlLimits::HOST)
+    }
+
+    /// Like `graphql`, within `limits`.
+    pub fn graphql_with(&self, query: &str, variables: Option<&str>, limits: GraphqlLimits) -> GraphqlResponse {
+        let parsed = tokenize(query).and_then(|tokens| Parser { tokens, pos: 0 }.document());
+        let (selection, mut defaults) = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => return GraphqlResponse::error(e.to_string()),
+        };
+        if let Some(text) = variables.filter(|v| !v.trim().is_empty()) {
+            match serde_json::from_str::<Map<String, Value>>(text) {
+                Ok(given) => defaults.extend(given),
+                Err(e) => return GraphqlResponse::error(format!("Invalid variables: {}", e)),
+            }
+        }
+        let executor = Executor {
+            ctx: self,
+            variables: defaults,
+            limits,
+            sensors: OnceCell::new(),
+        };
+        match executor.cost(&selection) {
+            Ok(cost) if cost > limits.max_cost => {
+                return GraphqlResponse::error(format!("Invalid query: costs {} fields, over the budget of {}", cost, limits.max_cost));
+            }
+            Ok(_) => {}
+            Err(e) => return GraphqlResponse::error(e.to_string()),
+        }
+        let root = Field {
+            alias: None,
+            name: "Query".to_string(),
+            arguments: Vec::new(),
+            selection,
+        };
+        let resolve: fn(&Executor, &Field, &()) -> Result<Value, BridgeError> = if limits.public { Executor::public_query } else { Executor::query };
+        match executor.object(&root, "Query", &(), resolve) {
+            Ok(data) => GraphqlResponse { data: Some(data), errors: Vec::new() },
+            Err(e) => GraphqlResponse::error(e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ecoblock_core::domain::SensorData;
+    use crate::channels::Visibility;
+    use crate::gateway::GatewayConfig;
+    use super::*;
+
+    fn context() -> EcoBlockContext {
+        let mut ctx = EcoBlockContext::new();
+        let data = SensorData {
+            pm25: 12.0,
+            co2: 410.0,
+            temperature: 21.0,
+            humidity: 45.0,
+            timestamp: 1_700_000_000,
+        };
+        ctx.try_create_block(serde_json::to_vec(&data).unwrap(), Vec::new()).unwrap();
+        ctx.channels.set_visibility("town", Visibility::Public);
+        for i in 0..3 {
+            ctx.publish_to_channel("town", format!("{{\"aqi\": {}}}", i).as_bytes()).unwrap();
+        }
+        ctx
+    }
+
+    #[test]
+    fn queries_over_the_cost_budget_are_refused_before_they_run() {
+        let ctx = context();
+        let response = ctx.graphql("{ blocks(first: 1000) { id parents { id parents { id } } } }", None);
+        assert!(response.data.is_none());
+        assert!(response.errors[0].message.contains("budget"), "{:?}", response.errors);
+
+        let response = ctx.graphql("{ blocks { id } }", None);
+        assert_eq!(response.data.unwrap()["blocks"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn public_clients_only_reach_aggregates_and_public_channels() {
+        let ctx = context();
+        let limits = GraphqlLimits::public(2);
+        for query in ["{ blocks { id } }", "{ sensors { id } }", "{ readings { blockId } }", "{ peers { id } }"] {
+            let response = ctx.graphql_with(query, None, limits);
+            assert!(response.data.is_none(), "{}", query);
+        }
+
+        let query = "{ aggregates(from: 0, to: 2000000000) { metric count } channels messages(channel: \"town\", first: 50) { id data } }";
+        let data = ctx.graphql_with(query, None, limits).data.unwrap();
+        assert!(data["aggregates"].as_array().unwrap().iter().any(|a| a["metric"] == "co2" && a["count"] == 1));
+        assert_eq!(data["channels"], json!(["town"]));
+        assert_eq!(data["messages"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn the_gateway_serves_the_public_schema_clamped_to_max_rows() {
+        let mut ctx = context();
+        ctx.enable_gateway(GatewayConfig {
+            max_rows: 2,
+            graphql: true,
+            ..GatewayConfig::default()
+        })
+        .unwrap();
+        let response = ctx.gateway_get("192.0.2.1", "/graphql?query=%7B+messages(channel:+%22town%22,+first:+50)+%7B+id+%7D+%7D", None);
+        assert_eq!(response.status, 200, "{}", response.body);
+        let body: Value = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(body["data"]["messages"].as_array().unwrap().len(), 2);
+
+        let response = ctx.gateway_get("192.0.2.1", "/graphql?query=%7B+blocks+%7B+id+%7D+%7D", None);
+        assert_eq!(response.status, 400, "{}", response.body);
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::{BTreeSet, HashMap};
+use serde::{Deserialize, Serialize};
+use crate::channels::Visibility;
+
+pub const GROUP_KEY_KIND: &str = "group_key";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WrappedKey {
+    pub recipient: String,
+    pub sealed: String,
+}
+
+/// Body of a `group_key` record: one epoch key sealed to each member.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupKeyBody {
+    pub channel: String,
+    pub tier: Visibility,
+    pub epoch: u32,
+    pub wrapped: Vec<WrappedKey>,
+}
+
+#[derive(Debug, Default)]
+pub struct GroupMembers {
+    members: HashMap<(String, Visibility), BTreeSet<String>>,
+}
+
+impl GroupMembers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn members(&self, channel: &str, tier: Visibility) -> Vec<String> {
+        self.members
+            .get(&(channel.to_string(), tier))
+            .map(|m| m.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn set(&mut self, channel: &str, tier: Visibility, members: impl IntoIterator<Item = String>) {
+        self.members.insert((channel.to_string(), tier), members.into_iter().collect());
+    }
+
+    pub fn add(&mut self, channel: &str, tier: Visibility, member: &str) -> bool {
+        self.members
+            .entry((channel.to_string(), tier))
+            .or_default()
+            .insert(member.to_string())
+    }
+
+    pub fn remove(&mut self, channel: &str, tier: Visibility, member: &str) -> bool {
+        self.members
+            .get_mut(&(channel.to_string(), tier))
+            .is_some_and(|m| m.remove(member))
+    }
+
+    pub fn replace_member(&mut self, old: &str, new: &str) {
+        for members in self.members.values_mut() {
+            if members.remove(old) {
+                members.insert(new.to_string());
+            }
+        }
+    }
+}
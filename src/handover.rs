@@ -0,0 +1,92 @@
+use ecoblock_crypto::keys::keypair::CryptoKeypair;
+use serde::{Deserialize, Serialize};
+use crate::change_feed::ChangeKind;
+use crate::channels::Visibility;
+use crate::error::BridgeError;
+use crate::logs::LogLevel;
+use crate::peer_id::{PeerId, PublicKeyHex};
+use crate::records::Record;
+use crate::{clock, sealed, EcoBlockContext};
+
+pub const HANDOVER_KIND: &str = "handover";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SealedChannelKey {
+    pub channel: String,
+    pub tier: Visibility,
+    pub epoch: u32,
+    pub sealed: String,
+}
+
+/// Signed by the old key: `new_id` takes over the identity of `old_id`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HandoverBody {
+    pub old_id: String,
+    pub new_id: String,
+    /// Channel keys held by the old node, sealed to the new key.
+    pub channel_keys: Vec<SealedChannelKey>,
+}
+
+pub fn sign_handover(old: &CryptoKeypair, new_id: &PublicKeyHex, channel_keys: Vec<SealedChannelKey>) -> Record {
+    let body = HandoverBody {
+        old_id: old.public_key_hex(),
+        new_id: new_id.to_string(),
+        channel_keys,
+    };
+    let body = serde_json::to_value(&body).unwrap_or_default();
+    Record::sign(HANDOVER_KIND, body, old, clock::now_secs())
+}
+
+impl EcoBlockContext {
+    /// Hands the local identity to `new_id`, sealing the local channel keys to it.
+    pub fn hand_over_identity(&mut self, new_id: &PublicKeyHex) -> Result<Record, BridgeError> {
+        let channel_keys = self
+            .channels
+            .all_keys()
+            .into_iter()
+            .map(|(channel, tier, epoch, key)| {
+                Ok(SealedChannelKey {
+                    channel,
+                    tier,
+                    epoch,
+                    sealed: hex::encode(sealed::seal_to(new_id.as_str(), &key)?),
+                })
+            })
+            .collect::<Result<Vec<_>, BridgeError>>()?;
+        let record = sign_handover(&self.keypair, new_id, channel_keys);
+        self.apply_handover(&record)?;
+        self.records.insert(record.clone());
+        Ok(record)
+    }
+
+    pub(crate) fn apply_handover(&mut self, record: &Record) -> Result<(), BridgeError> {
+        let body: HandoverBody = serde_json::from_value(record.body.clone())
+            .map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+        if body.old_id != record.author {
+            return Err(BridgeError::Unauthorized(format!("Handover of {} signed by {}", body.old_id, record.author)));
+        }
+        let now = clock::now_secs();
+        let (old, new) = (body.old_id.as_str(), body.new_id.as_str());
+        self.address_book.replace(old, &PeerId::parse(new)?, now);
+        self.changes.push(ChangeKind::PeerRemoved, old.to_string(), now);
+        self.changes.push(ChangeKind::PeerAdded, new.to_string(), now);
+        for (peer, weight) in self.mesh.get_neighbors(old).unwrap_or_default() {
+            self.add_mesh_connection(new, &peer, weight);
+            self.add_mesh_connection(&peer, new, weight);
+        }
+        self.roster.replace_member(old, new);
+        self.group_members.replace_member(old, new);
+        if new == self.keypair.public_key_hex() {
+            for key in &body.channel_keys {
+                let sealed_key = hex::decode(&key.sealed).map_err(|e| BridgeError::Decoding(e.to_string()))?;
+                let opened: [u8; 32] = sealed::open_sealed(&self.keypair, &sealed_key)?
+                    .try_into()
+                    .map_err(|_| BridgeError::Crypto("channel key must be 32 bytes".to_string()))?;
+                self.channels.install_key(&key.channel, key.tier, key.epoch, opened);
+            }
+        }
+        self.retired_keys.insert(old.to_string());
+        self.log(LogLevel::Info, format!("Identity {} handed over to {}", old, new));
+        Ok(())
+    }
+}
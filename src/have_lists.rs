@@ -0,0 +1,97 @@
+use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use crate::block_id::BlockId;
+use crate::error::BridgeError;
+use crate::peer_id::PeerId;
+use crate::records::Record;
+use crate::{clock, EcoBlockContext};
+
+pub const HAVE_KIND: &str = "have";
+/// Advertisements older than this are ignored.
+pub const DEFAULT_HAVE_TTL: u64 = 600;
+/// Most ids carried by one advertisement.
+pub const MAX_HAVE_IDS: usize = 512;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HaveBody {
+    pub ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SuppressionStats {
+    pub advertisements_received: u64,
+    pub sends_considered: u64,
+    pub sends_suppressed: u64,
+}
+
+#[derive(Debug)]
+pub struct HaveLists {
+    pub ttl: u64,
+    peers: HashMap<String, (u64, HashSet<String>)>,
+    stats: SuppressionStats,
+}
+
+impl Default for HaveLists {
+    fn default() -> Self {
+        Self {
+            ttl: DEFAULT_HAVE_TTL,
+            peers: HashMap::new(),
+            stats: SuppressionStats::default(),
+        }
+    }
+}
+
+impl HaveLists {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the peer's previous advertisement.
+    pub fn receive(&mut self, peer: &str, ids: Vec<String>, now: u64) {
+        self.stats.advertisements_received += 1;
+        self.peers.insert(peer.to_string(), (now, ids.into_iter().take(MAX_HAVE_IDS).collect()));
+    }
+
+    pub fn claims(&self, peer: &str, id: &str, now: u64) -> bool {
+        self.peers
+            .get(peer)
+            .is_some_and(|(at, ids)| now.saturating_sub(*at) <= self.ttl && ids.contains(id))
+    }
+
+    /// Keeps the blocks `peer` has not claimed to hold and counts the rest as suppressed.
+    pub fn filter(&mut self, peer: &str, candidates: Vec<BlockId>, now: u64) -> Vec<BlockId> {
+        let total = candidates.len();
+        let keep: Vec<BlockId> = candidates.into_iter().filter(|id| !self.claims(peer, id.as_str(), now)).collect();
+        self.stats.sends_considered += total as u64;
+        self.stats.sends_suppressed += (total - keep.len()) as u64;
+        keep
+    }
+
+    pub fn stats(&self) -> SuppressionStats {
+        self.stats
+    }
+}
+
+impl EcoBlockContext {
+    /// Signs an advertisement of the most recent block and record ids held locally.
+    pub fn advertise_haves(&mut self) -> Result<Record, BridgeError> {
+        let mut ids: Vec<String> = self.block_log.iter().rev().take(MAX_HAVE_IDS / 2).map(|b| b.id.clone()).collect();
+        ids.extend(self.records.iter().rev().take(MAX_HAVE_IDS - ids.len()).map(|r| r.id.clone()));
+        let body = serde_json::to_value(HaveBody { ids }).unwrap_or_default();
+        self.sign_record(HAVE_KIND, body, clock::now_secs())
+    }
+
+    pub(crate) fn apply_have(&mut self, record: &Record) -> Result<(), BridgeError> {
+        let body: HaveBody = serde_json::from_value(record.body.clone())
+            .map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+        self.have_lists.receive(&record.author, body.ids, clock::now_secs());
+        Ok(())
+    }
+
+    /// Blocks from `candidates` still worth sending to `peer`: not advertised
+    /// by it and, while geofence scoping is on, inside its declared area.
+    pub fn blocks_to_send(&mut self, peer: &PeerId, candidates: Vec<BlockId>) -> Vec<BlockId> {
+        let candidates = self.have_lists.filter(peer.as_str(), candidates, clock::now_secs());
+        self.scope_to_peer(peer, candidates)
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use crate::error::BridgeError;
+use crate::inventory;
+use crate::EcoBlockContext;
+
+pub const DEFAULT_DISCOVERY_PREFIX: &str = "homeassistant";
+pub const DEFAULT_STATE_PREFIX: &str = "ecoblock";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HomeAssistantConfig {
+    /// Where the hub listens for discovery configs.
+    pub discovery_prefix: String,
+    /// Root of the state and availability topics.
+    pub state_prefix: String,
+}
+
+impl Default for HomeAssistantConfig {
+    fn default() -> Self {
+        Self {
+            discovery_prefix: DEFAULT_DISCOVERY_PREFIX.to_string(),
+            state_prefix: DEFAULT_STATE_PREFIX.to_string(),
+        }
+    }
+}
+
+/// A message for the host's MQTT client to publish.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MqttMessage {
+    pub topic: String,
+    pub payload: String,
+    pub retain: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct HomeAssistantExport {
+    config: Option<HomeAssistantConfig>,
+    /// Sensor and metric pairs whose discovery config was handed out.
+    announced: HashSet<(String, String)>,
+    /// Timestamp of the last value handed out per sensor and metric.
+    published: HashMap<(String, String), u64>,
+    online: bool,
+}
+
+/// Device class and unit for metrics the hubs know, in the units the bridge stores.
+pub(crate) fn device_class(metric: &str) -> Option<(&'static str, &'static str)> {
+    match metric {
+        "temperature" => Some(("temperature", "°C")),
+        "humidity" => Some(("humidity", "%")),
+        "co2" => Some(("carbon_dioxide", "ppm")),
+        "pm25" => Some(("pm25", "µg/m³")),
+        "pressure" => Some(("atmospheric_pressure", "hPa")),
+        "illuminance" => Some(("illuminance", "lx")),
+        "voltage" => Some(("voltage", "V")),
+        "current" => Some(("current", "A")),
+        "power" => Some(("power", "W")),
+        _ => None,
+    }
+}
+
+/// Discovery object ids allow only ASCII letters, digits, `_` and `-`.
+fn object_id(text: &str) -> String {
+    text.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' }).collect()
+}
+
+fn valid_prefix(prefix: &str) -> bool {
+    !prefix.is_empty() && !prefix.starts_with('/') && !prefix.ends_with('/') && !prefix.contains(['#', '+'])
+}
+
+impl HomeAssistantExport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn config(&self) -> Option<&HomeAssistantConfig> {
+        self.config.as_ref()
+    }
+}
+
+impl EcoBlockContext {
+    fn home_assistant_node(&self) -> String {
+        let key = self.keypair.public_key_hex();
+        format!("ecoblock_{}", &key[..key.len().min(12)])
+    }
+
+    fn availability_topic(&self, config: &HomeAssistantConfig) -> String {
+        format!("{}/{}/availability", config.state_prefix, self.home_assistant_node())
+    }
+
+    /// Starts exporting; every sensor is announced again on the next take.
+    pub fn enable_home_assistant(&mut self, config: HomeAssistantConfig) -> Result<(), BridgeError> {
+        for prefix in [&config.discovery_prefix, &config.state_prefix] {
+            if !valid_prefix(prefix) {
+                return Err(BridgeError::InvalidInput(format!("Invalid MQTT topic prefix {:?}", prefix)));
+            }
+        }
+        self.home_assistant = HomeAssistantExport {
+            config: Some(config),
+            ..HomeAssistantExport::default()
+        };
+        Ok(())
+    }
+
+    /// Stops exporting and returns the message marking this node's entities
+    /// unavailable, if any were published.
+    pub fn disable_home_assistant(&mut self) -> Vec<MqttMessage> {
+        let export = std::mem::take(&mut self.home_assistant);
+        match export.config {
+            Some(config) if export.online => vec![MqttMessage {
+                topic: self.availability_topic(&config),
+                payload: "offline".to_string(),
+                retain: true,
+            }],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Messages to publish since the last call: the node's availability, a
+    /// retained discovery config for each new sensor metric, and the latest
+    /// value of each metric that has a newer reading.
+    pub fn take_home_assistant_messages(&mut self) -> Vec<MqttMessage> {
+        let Some(config) = self.home_assistant.config.clone() else {
+            return Vec::new();
+        };
+        let node = self.home_assistant_node();
+        let availability = self.availability_topic(&config);
+        let mut messages = Vec::new();
+        if !self.home_assistant.online {
+            self.home_assistant.online = true;
+            messages.push(MqttMessage {
+                topic: availability.clone(),
+                payload: "online".to_string(),
+                retain: true,
+            });
+        }
+        for summary in inventory::summarize(&self.readings) {
+            let sensor = object_id(&summary.sensor_id);
+            for metric in summary.metrics {
+                let key = (summary.sensor_id.clone(), metric.clone());
+                let state_topic = format!("{}/{}/{}/{}", config.state_prefix, node, sensor, object_id(&metric));
+                if self.home_assistant.announced.insert(key.clone()) {
+                    let unique_id = format!("{}_{}_{}", node, sensor, object_id(&metric));
+                    let mut payload = json!({
+                        "name": metric,
+                        "unique_id": unique_id,
+                        "object_id": unique_id,
+                        "state_topic": state_topic,
+                        "availability_topic": availability,
+                        "state_class": "measurement",
+                        "device": {
+                            "identifiers": [format!("{}_{}", node, sensor)],
+                            "name": summary.sensor_id,
+                            "manufacturer": "EcoBlock",
+                            "model": "ecoblock-bridge",
+                            "sw_version": env!("CARGO_PKG_VERSION"),
+                        },
+                    });
+                    if let Some((class, unit)) = device_class(&metric) {
+                        payload["device_class"] = json!(class);
+                        payload["unit_of_measurement"] = json!(unit);
+                    }
+                    messages.push(MqttMessage {
+                        topic: format!("{}/sensor/{}/config", config.discovery_prefix, unique_id),
+                        payload: payload.to_string(),
+                        retain: true,
+                    });
+                }
+                let Some((timestamp, value)) = self.readings.last_value(&summary.sensor_id, &metric) else {
+                    continue;
+                };
+                if self.home_assistant.published.get(&key).is_some_and(|t| *t >= timestamp) {
+                    continue;
+                }
+                self.home_assistant.published.insert(key, timestamp);
+                messages.push(MqttMessage {
+                    topic: state_topic,
+                    payload: value.to_string(),
+                    retain: true,
+                });
+            }
+        }
+        messages
+    }
+}
@@ -0,0 +1,49 @@
+//! Outgoing HTTP for features that talk to remote services, such as S3
+//! backups. The bridge builds and signs each request; the host's HTTP stack
+//! sends it, so TLS, proxies and certificates stay the platform's business.
+
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HttpRequest {
+    pub method: String,
+    /// Absolute URL, including the query string.
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// Sends requests for the bridge. Calls are made with the context lock held,
+/// like `Transport`, so implementations should apply a timeout and must not
+/// call back into the bridge.
+pub trait HttpClient: Send {
+    /// Returns the response for any status; `Err` only when no response came.
+    fn execute(&self, request: &HttpRequest) -> Result<HttpResponse, BridgeError>;
+}
+
+/// Splits `https://host:port/path` into the scheme and host, and the path
+/// without a trailing `/`.
+pub(crate) fn split_url(url: &str) -> Result<(&str, &str), BridgeError> {
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .ok_or_else(|| BridgeError::InvalidInput(format!("Invalid URL {}: expected http:// or https://", url)))?;
+    let host = rest.split('/').next().unwrap_or_default();
+    if host.is_empty() {
+        return Err(BridgeError::InvalidInput(format!("Invalid URL {}: no host", url)));
+    }
+    Ok((host, rest[host.len()..].trim_end_matches('/')))
+}
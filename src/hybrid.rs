@@ -0,0 +1,580 @@
+//! Hash-based post-quantum signatures carried next to the Ed25519 signature.
+//!
+//! The scheme is Winternitz one-time signatures (w = 16) over SHA-256 under a
+//! Merkle tree of height 10, so one key signs 1024 messages. It only relies on
+//! the hash function, and the signer is stateful: every signature uses a fresh
+//! leaf. Every build can verify hybrid signatures; generating keys and signing
+//! needs the `pq-hybrid` feature.
+
+use std::collections::HashMap;
+use ecoblock_storage::tangle::block::TangleBlock;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::canonical::{block_message, Encoding};
+use crate::energy;
+use crate::error::BridgeError;
+use crate::pow::{AnnouncementBody, ANNOUNCEMENT_KIND};
+use crate::records::{verify_signature, Record};
+use crate::wire::BlockEnvelope;
+use crate::EcoBlockContext;
+
+pub const PQ_SCHEME: &str = "wots16-merkle10-sha256";
+pub const TREE_HEIGHT: usize = 10;
+pub const PQ_SIGNATURES_PER_KEY: u32 = 1 << TREE_HEIGHT;
+
+const N: usize = 32;
+const W: u32 = 16;
+/// Base-16 digits of the 256-bit message digest.
+const LEN1: usize = 64;
+/// Checksum digits; the checksum is at most 64 * 15 = 960 < 16^3.
+const LEN2: usize = 3;
+const LEN: usize = LEN1 + LEN2;
+
+type Hash = [u8; N];
+
+/// How Ed25519 and post-quantum signatures combine when accepting records.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HybridPolicy {
+    /// Ed25519 must verify; post-quantum signatures are ignored.
+    #[default]
+    ClassicOnly,
+    /// Either signature is enough.
+    Either,
+    /// Both must verify, so records without a post-quantum signature are refused.
+    Both,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PqSignature {
+    pub scheme: String,
+    /// Hex of the public seed followed by the tree root.
+    pub public_key: String,
+    pub leaf: u32,
+    /// Hex of the one-time signature chains.
+    pub ots: String,
+    /// Hex of the Merkle authentication path, leaf level first.
+    pub path: String,
+}
+
+/// A sensor block with a post-quantum signature over its JSON encoding.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HybridBlock {
+    pub block: TangleBlock,
+    pub pq_signature: PqSignature,
+    /// Encoding of the message the post-quantum signature covers.
+    #[serde(default, skip_serializing_if = "Encoding::is_legacy")]
+    pub encoding: Encoding,
+    /// Canonical Ed25519 signature, present when `encoding` is canonical and
+    /// checked in place of the one inside the block.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+fn hash(parts: &[&[u8]]) -> Hash {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+fn chain(public_seed: &[u8], leaf: u32, index: usize, mut x: Hash, start: u32, steps: u32) -> Hash {
+    for step in start..start + steps {
+        x = hash(&[b"chain", public_seed, &leaf.to_be_bytes(), &(index as u16).to_be_bytes(), &(step as u16).to_be_bytes(), &x]);
+    }
+    x
+}
+
+fn digits(public_seed: &[u8], leaf: u32, message: &[u8]) -> [u32; LEN] {
+    let digest = hash(&[b"message", public_seed, &leaf.to_be_bytes(), message]);
+    let mut out = [0; LEN];
+    for (i, byte) in digest.iter().enumerate() {
+        out[2 * i] = u32::from(byte >> 4);
+        out[2 * i + 1] = u32::from(byte & 0x0f);
+    }
+    let checksum: u32 = out[..LEN1].iter().map(|d| W - 1 - d).sum();
+    out[LEN1] = (checksum >> 8) & 0x0f;
+    out[LEN1 + 1] = (checksum >> 4) & 0x0f;
+    out[LEN1 + 2] = checksum & 0x0f;
+    out
+}
+
+fn leaf_hash(public_seed: &[u8], leaf: u32, ends: &[Hash]) -> Hash {
+    let ends: Vec<u8> = ends.concat();
+    hash(&[b"leaf", public_seed, &leaf.to_be_bytes(), &ends])
+}
+
+fn node_hash(public_seed: &[u8], level: usize, index: u32, left: &Hash, right: &Hash) -> Hash {
+    hash(&[b"node", public_seed, &[level as u8], &index.to_be_bytes(), left, right])
+}
+
+fn split_hashes(hex_str: &str, count: usize) -> Option<Vec<Hash>> {
+    let bytes = hex::decode(hex_str).ok()?;
+    if bytes.len() != count * N {
+        return None;
+    }
+    Some(bytes.chunks_exact(N).map(|c| c.try_into().expect("chunk of N bytes")).collect())
+}
+
+/// Checks `signature` over `message` against `public_key`.
+pub fn verify(signature: &PqSignature, message: &[u8], public_key: &str) -> bool {
+    if signature.scheme != PQ_SCHEME || signature.public_key != public_key || signature.leaf >= PQ_SIGNATURES_PER_KEY {
+        return false;
+    }
+    let Some(key) = hex::decode(public_key).ok().filter(|k| k.len() == 2 * N) else {
+        return false;
+    };
+    let (public_seed, root) = key.split_at(N);
+    let (Some(ots), Some(path)) = (split_hashes(&signature.ots, LEN), split_hashes(&signature.path, TREE_HEIGHT)) else {
+        return false;
+    };
+    let leaf = signature.leaf;
+    let d = digits(public_seed, leaf, message);
+    let ends: Vec<Hash> = ots
+        .iter()
+        .enumerate()
+        .map(|(i, x)| chain(public_seed, leaf, i, *x, d[i], W - 1 - d[i]))
+        .collect();
+    let mut node = leaf_hash(public_seed, leaf, &ends);
+    let mut index = leaf;
+    for (level, sibling) in path.iter().enumerate() {
+        node = if index.is_multiple_of(2) {
+            node_hash(public_seed, level + 1, index / 2, &node, sibling)
+        } else {
+            node_hash(public_seed, level + 1, index / 2, sibling, &node)
+        };
+        index /= 2;
+    }
+    node.as_slice() == root
+}
+
+/// Post-quantum keys announced by each author, and the acceptance policy.
+#[derive(Default)]
+pub struct HybridState {
+    pub policy: HybridPolicy,
+    keys: HashMap<String, String>,
+    #[cfg(feature = "pq-hybrid")]
+    pub(crate) signer: std::cell::RefCell<Option<signer::PqSigner>>,
+}
+
+impl HybridState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, author: &str, public_key: &str) {
+        self.keys.insert(author.to_string(), public_key.to_string());
+    }
+
+    pub fn key_of(&self, author: &str) -> Option<&str> {
+        self.keys.get(author).map(String::as_str)
+    }
+
+    /// Combines the two checks according to the policy. `pq` is `None` when the
+    /// record carries no post-quantum signature or its author announced no key.
+    pub fn accepts(&self, classic: bool, pq: Option<bool>) -> bool {
+        match self.policy {
+            HybridPolicy::ClassicOnly => classic,
+            HybridPolicy::Either => classic || pq == Some(true),
+            HybridPolicy::Both => classic && pq == Some(true),
+        }
+    }
+
+    /// Like `accepts`, for the announcement that binds its author's first
+    /// post-quantum key. The key is not trusted yet, so Ed25519 must verify
+    /// under every policy; `pq` is checked against the announced key.
+    pub fn accepts_first_key(&self, classic: bool, pq: Option<bool>) -> bool {
+        classic && self.accepts(classic, pq)
+    }
+
+    #[cfg(feature = "pq-hybrid")]
+    pub fn local_key(&self) -> Option<String> {
+        self.signer.borrow().as_ref().map(|s| s.public_key_hex())
+    }
+
+    #[cfg(not(feature = "pq-hybrid"))]
+    pub fn local_key(&self) -> Option<String> {
+        None
+    }
+}
+
+fn announced_pq_key(record: &Record) -> Option<String> {
+    if record.kind != ANNOUNCEMENT_KIND {
+        return None;
+    }
+    serde_json::from_value::<AnnouncementBody>(record.body.clone()).ok()?.pq_key
+}
+
+impl EcoBlockContext {
+    /// Checks a record under the hybrid policy. The id must always match.
+    /// Post-quantum signatures are checked against the key bound to the author.
+    /// An announcement may bind its author's first key, but only with a valid
+    /// Ed25519 signature: a record cannot vouch for a key it brings itself.
+    /// Legacy encodings are refused once the encoding policy stops accepting
+    /// them.
+    pub fn verify_record(&self, record: &Record) -> bool {
+        if !self.encoding.accepts(record.encoding) || !record.id_matches() {
+            return false;
+        }
+        energy::count_verification();
+        let classic = verify_signature(&record.author, &record.signed_bytes(), &record.signature);
+        match (self.hybrid.key_of(&record.author), announced_pq_key(record)) {
+            (Some(key), _) => self.hybrid.accepts(classic, record.verify_pq(key)),
+            (None, Some(key)) => self.hybrid.accepts_first_key(classic, record.verify_pq(&key)),
+            (None, None) => self.hybrid.accepts(classic, None),
+        }
+    }
+
+    /// Checks a block envelope under the hybrid and encoding policies. The
+    /// block's own signature counts as legacy and is refused with it; a
+    /// canonical signature replaces it.
+    pub fn verify_block_envelope(&self, envelope: &BlockEnvelope) -> bool {
+        match envelope {
+            BlockEnvelope::Sensor(block) => self.hybrid.accepts(self.verify_classic_block(block, None), None),
+            BlockEnvelope::CanonicalSensor(canonical) => self.hybrid.accepts(self.verify_classic_block(&canonical.block, Some(&canonical.signature)), None),
+            BlockEnvelope::HybridSensor(hybrid) => {
+                let pq = self
+                    .hybrid
+                    .key_of(&hybrid.block.public_key)
+                    .filter(|_| self.encoding.accepts(hybrid.encoding))
+                    .map(|k| verify(&hybrid.pq_signature, &block_message(&hybrid.block, hybrid.encoding), k));
+                self.hybrid.accepts(self.verify_classic_block(&hybrid.block, hybrid.signature.as_deref()), pq)
+            }
+            BlockEnvelope::Record(record) => self.verify_record(record),
+        }
+    }
+
+    fn verify_classic_block(&self, block: &TangleBlock, canonical_signature: Option<&str>) -> bool {
+        match canonical_signature {
+            Some(signature) => energy::verify_canonical_block(block, signature),
+            None => self.encoding.accepts(Encoding::Legacy) && energy::verify_block(block),
+        }
+    }
+}
+
+#[cfg(feature = "pq-hybrid")]
+pub mod signer {
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+    use chacha20poly1305::aead::rand_core::RngCore;
+    use chacha20poly1305::aead::OsRng;
+    use crate::block_store;
+    use super::*;
+
+    /// Secret seed, public seed and the next unused leaf.
+    pub const PQ_KEY_FILE_LEN: usize = 2 * N + 4;
+
+    pub struct PqSigner {
+        secret_seed: Hash,
+        public_seed: Hash,
+        next_leaf: u32,
+        /// Tree levels from the leaves up to the root.
+        levels: Vec<Vec<Hash>>,
+        path: Option<PathBuf>,
+    }
+
+    impl PqSigner {
+        pub fn generate() -> Self {
+            let mut secret_seed = [0; N];
+            let mut public_seed = [0; N];
+            OsRng.fill_bytes(&mut secret_seed);
+            OsRng.fill_bytes(&mut public_seed);
+            Self::from_seeds(secret_seed, public_seed, 0)
+        }
+
+        fn from_seeds(secret_seed: Hash, public_seed: Hash, next_leaf: u32) -> Self {
+            let mut signer = Self {
+                secret_seed,
+                public_seed,
+                next_leaf,
+                levels: Vec::new(),
+                path: None,
+            };
+            let leaves: Vec<Hash> = (0..PQ_SIGNATURES_PER_KEY).map(|leaf| signer.leaf_public(leaf)).collect();
+            signer.levels.push(leaves);
+            for level in 1..=TREE_HEIGHT {
+                let below = &signer.levels[level - 1];
+                let nodes = below
+                    .chunks_exact(2)
+                    .enumerate()
+                    .map(|(i, pair)| node_hash(&signer.public_seed, level, i as u32, &pair[0], &pair[1]))
+                    .collect();
+                signer.levels.push(nodes);
+            }
+            signer
+        }
+
+        fn chain_start(&self, leaf: u32, index: usize) -> Hash {
+            hash(&[b"secret", &self.secret_seed, &leaf.to_be_bytes(), &(index as u16).to_be_bytes()])
+        }
+
+        fn leaf_public(&self, leaf: u32) -> Hash {
+            let ends: Vec<Hash> = (0..LEN)
+                .map(|i| chain(&self.public_seed, leaf, i, self.chain_start(leaf, i), 0, W - 1))
+                .collect();
+            leaf_hash(&self.public_seed, leaf, &ends)
+        }
+
+        pub fn public_key_hex(&self) -> String {
+            hex::encode([self.public_seed.as_slice(), self.levels[TREE_HEIGHT][0].as_slice()].concat())
+        }
+
+        pub fn remaining(&self) -> u32 {
+            PQ_SIGNATURES_PER_KEY - self.next_leaf
+        }
+
+        pub fn to_bytes(&self) -> Vec<u8> {
+            [self.secret_seed.as_slice(), self.public_seed.as_slice(), &self.next_leaf.to_be_bytes()].concat()
+        }
+
+        pub fn from_bytes(bytes: &[u8]) -> Result<Self, BridgeError> {
+            if bytes.len() != PQ_KEY_FILE_LEN {
+                return Err(BridgeError::Crypto(format!("PQ key file must be {} bytes", PQ_KEY_FILE_LEN)));
+            }
+            let secret_seed = bytes[..N].try_into().expect("N bytes");
+            let public_seed = bytes[N..2 * N].try_into().expect("N bytes");
+            let next_leaf = u32::from_be_bytes(bytes[2 * N..].try_into().expect("4 bytes"));
+            Ok(Self::from_seeds(secret_seed, public_seed, next_leaf))
+        }
+
+        /// Loads the key at `path`, or generates and stores one if there is none.
+        /// The file is rewritten after every signature.
+        pub fn open(path: &Path) -> Result<Self, BridgeError> {
+            let mut signer = if path.exists() {
+                Self::from_bytes(&fs::read(path).map_err(|e| BridgeError::Io(e.to_string()))?)?
+            } else {
+                Self::generate()
+            };
+            signer.path = Some(path.to_path_buf());
+            signer.persist()?;
+            Ok(signer)
+        }
+
+        /// Writes the key through a synced temporary file renamed over the old
+        /// one, so a crash leaves either the previous or the new leaf index.
+        fn persist(&self) -> Result<(), BridgeError> {
+            let Some(path) = &self.path else {
+                return Ok(());
+            };
+            let tmp = path.with_extension("bin.tmp");
+            let mut out = File::create(&tmp).map_err(|e| BridgeError::Io(e.to_string()))?;
+            out.write_all(&self.to_bytes()).map_err(|e| BridgeError::Io(e.to_string()))?;
+            out.sync_all().map_err(|e| BridgeError::Io(e.to_string()))?;
+            fs::rename(&tmp, path).map_err(|e| BridgeError::Io(e.to_string()))?;
+            block_store::sync_parent_dir(path)
+        }
+
+        /// Uses the next leaf. The advanced leaf index is durably stored before
+        /// the signature is returned, so a crash can never reuse a leaf. Fails
+        /// once every leaf is used.
+        pub fn sign(&mut self, message: &[u8]) -> Result<PqSignature, BridgeError> {
+            if self.next_leaf >= PQ_SIGNATURES_PER_KEY {
+                return Err(BridgeError::Crypto("PQ key exhausted".to_string()));
+            }
+            let leaf = self.next_leaf;
+            self.next_leaf += 1;
+            self.persist()?;
+            let d = digits(&self.public_seed, leaf, message);
+            let ots: Vec<u8> = (0..LEN)
+                .flat_map(|i| chain(&self.public_seed, leaf, i, self.chain_start(leaf, i), 0, d[i]))
+                .collect();
+            let mut index = leaf as usize;
+            let mut path = Vec::with_capacity(TREE_HEIGHT * N);
+            for level in &self.levels[..TREE_HEIGHT] {
+                path.extend_from_slice(&level[index ^ 1]);
+                index /= 2;
+            }
+            Ok(PqSignature {
+                scheme: PQ_SCHEME.to_string(),
+                public_key: self.public_key_hex(),
+                leaf,
+                ots: hex::encode(ots),
+                path: hex::encode(path),
+            })
+        }
+    }
+}
+
+#[cfg(feature = "pq-hybrid")]
+impl EcoBlockContext {
+    /// Loads the post-quantum key at `path`, creating it if missing, and signs
+    /// every later record with it too. Returns the public key.
+    pub fn load_pq_signer(&mut self, path: &std::path::Path) -> Result<String, BridgeError> {
+        let signer = signer::PqSigner::open(path)?;
+        let key = signer.public_key_hex();
+        self.hybrid.register(&self.keypair.public_key_hex(), &key);
+        *self.hybrid.signer.borrow_mut() = Some(signer);
+        Ok(key)
+    }
+
+    pub fn pq_signatures_remaining(&self) -> Option<u32> {
+        self.hybrid.signer.borrow().as_ref().map(|s| s.remaining())
+    }
+
+    /// Fails once the key is exhausted or its state cannot be stored, rather
+    /// than letting the record go out Ed25519-only.
+    pub(crate) fn attach_pq_signature(&self, record: &mut Record) -> Result<(), BridgeError> {
+        if let Some(signer) = self.hybrid.signer.borrow_mut().as_mut() {
+            record.pq_signature = Some(signer.sign(&record.signed_bytes())?);
+        }
+        Ok(())
+    }
+
+    pub fn sign_block_envelope(&self, block: TangleBlock) -> Result<BlockEnvelope, BridgeError> {
+        let mut signer = self.hybrid.signer.borrow_mut();
+        let signer = signer.as_mut().ok_or_else(|| BridgeError::Crypto("no PQ key loaded".to_string()))?;
+        let encoding = self.encoding.sign;
+        let pq_signature = signer.sign(&block_message(&block, encoding))?;
+        Ok(BlockEnvelope::HybridSensor(HybridBlock {
+            signature: self.canonical_block_signature(&block),
+            block,
+            pq_signature,
+            encoding,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ecoblock_crypto::keys::keypair::CryptoKeypair;
+    use serde_json::json;
+    use super::*;
+
+    fn announcement(keypair: &CryptoKeypair, pq_key: Option<&str>) -> Record {
+        let body = json!({ "node_id": keypair.public_key_hex(), "difficulty": 0, "nonce": 0, "pq_key": pq_key });
+        Record::sign(ANNOUNCEMENT_KIND, body, keypair, 1_700_000_000)
+    }
+
+    fn context(policy: HybridPolicy) -> EcoBlockContext {
+        let mut ctx = EcoBlockContext::new();
+        ctx.hybrid.policy = policy;
+        ctx
+    }
+
+    #[test]
+    fn each_policy_combines_the_two_checks() {
+        let inputs = [(true, None), (true, Some(false)), (false, Some(true)), (true, Some(true)), (false, None)];
+        let expected = [
+            (HybridPolicy::ClassicOnly, [true, true, false, true, false], [true, true, false, true, false]),
+            (HybridPolicy::Either, [true, true, true, true, false], [true, true, false, true, false]),
+            (HybridPolicy::Both, [false, false, false, true, false], [false, false, false, true, false]),
+        ];
+        for (policy, accepts, accepts_first_key) in expected {
+            let state = HybridState { policy, ..HybridState::default() };
+            for (i, (classic, pq)) in inputs.into_iter().enumerate() {
+                assert_eq!(state.accepts(classic, pq), accepts[i], "{:?} {:?}", policy, (classic, pq));
+                assert_eq!(state.accepts_first_key(classic, pq), accepts_first_key[i], "{:?} {:?}", policy, (classic, pq));
+            }
+        }
+    }
+
+    #[test]
+    fn announcements_need_a_valid_ed25519_signature() {
+        let keypair = CryptoKeypair::generate();
+        let mut record = announcement(&keypair, None);
+        for policy in [HybridPolicy::ClassicOnly, HybridPolicy::Either, HybridPolicy::Both] {
+            let ctx = context(policy);
+            assert_eq!(ctx.verify_record(&record), policy != HybridPolicy::Both, "{:?}", policy);
+        }
+        record.signature = hex::encode([0u8; 64]);
+        for policy in [HybridPolicy::ClassicOnly, HybridPolicy::Either, HybridPolicy::Both] {
+            assert!(!context(policy).verify_record(&record), "{:?}", policy);
+        }
+    }
+
+    #[cfg(feature = "pq-hybrid")]
+    #[test]
+    fn a_forged_announcement_cannot_vouch_for_its_own_pq_key() {
+        let victim = CryptoKeypair::generate();
+        let attacker = CryptoKeypair::generate();
+        let mut attacker_pq = signer::PqSigner::generate();
+        let mut record = announcement(&victim, Some(&attacker_pq.public_key_hex()));
+        record.signature = hex::encode(attacker.sign(&record.signed_bytes()).to_bytes());
+        record.pq_signature = Some(attacker_pq.sign(&record.signed_bytes()).unwrap());
+        assert_eq!(record.verify_pq(&attacker_pq.public_key_hex()), Some(true));
+
+        let mut alert = Record::sign("alert", json!({ "metric": "co2" }), &victim, 1_700_000_001);
+        alert.signature = record.signature.clone();
+        alert.pq_signature = Some(attacker_pq.sign(&alert.signed_bytes()).unwrap());
+        for policy in [HybridPolicy::ClassicOnly, HybridPolicy::Either, HybridPolicy::Both] {
+            let ctx = context(policy);
+            assert!(!ctx.verify_record(&record), "{:?}", policy);
+            assert!(!ctx.verify_record(&alert), "{:?}", policy);
+        }
+    }
+
+    #[cfg(feature = "pq-hybrid")]
+    #[test]
+    fn a_bound_pq_key_stands_in_for_ed25519_only_under_either() {
+        let author = CryptoKeypair::generate();
+        let mut pq = signer::PqSigner::generate();
+        let mut record = announcement(&author, Some(&pq.public_key_hex()));
+        record.pq_signature = Some(pq.sign(&record.signed_bytes()).unwrap());
+
+        let classic = Record::sign("alert", json!({ "metric": "co2" }), &author, 1_700_000_001);
+        let mut broken = classic.clone();
+        broken.signature = hex::encode([0u8; 64]);
+        broken.pq_signature = Some(pq.sign(&broken.signed_bytes()).unwrap());
+        let cases = [
+            (HybridPolicy::ClassicOnly, true, false),
+            (HybridPolicy::Either, true, true),
+            (HybridPolicy::Both, false, false),
+        ];
+        for (policy, accepts_classic, accepts_broken) in cases {
+            let mut ctx = context(policy);
+            assert!(ctx.verify_record(&record), "{:?}", policy);
+            ctx.hybrid.register(&author.public_key_hex(), &pq.public_key_hex());
+            assert_eq!(ctx.verify_record(&classic), accepts_classic, "{:?}", policy);
+            assert_eq!(ctx.verify_record(&broken), accepts_broken, "{:?}", policy);
+        }
+    }
+
+    #[cfg(feature = "pq-hybrid")]
+    #[test]
+    fn signatures_verify_only_for_the_signed_message() {
+        let mut pq = signer::PqSigner::generate();
+        let signature = pq.sign(b"reading").unwrap();
+        assert!(verify(&signature, b"reading", &pq.public_key_hex()));
+        assert!(!verify(&signature, b"tampered", &pq.public_key_hex()));
+        assert_eq!(pq.remaining(), PQ_SIGNATURES_PER_KEY - 1);
+    }
+
+    #[cfg(feature = "pq-hybrid")]
+    #[test]
+    fn the_used_leaf_is_stored_before_the_signature_is_returned() {
+        let dir = std::env::temp_dir().join(format!("ecoblock-pq-signer-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("node_pq_keypair.bin");
+        let mut pq = signer::PqSigner::open(&path).unwrap();
+        let first = pq.sign(b"reading").unwrap();
+
+        let reopened = signer::PqSigner::open(&path).unwrap();
+        assert_eq!(reopened.public_key_hex(), pq.public_key_hex());
+        assert_eq!(reopened.remaining(), PQ_SIGNATURES_PER_KEY - 1);
+        assert_eq!(first.leaf, 0);
+        assert!(!path.with_extension("bin.tmp").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "pq-hybrid")]
+    #[test]
+    fn an_exhausted_key_fails_instead_of_signing_ed25519_only() {
+        let dir = std::env::temp_dir().join(format!("ecoblock-pq-exhausted-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("node_pq_keypair.bin");
+        let mut bytes = signer::PqSigner::generate().to_bytes();
+        bytes[signer::PQ_KEY_FILE_LEN - 4..].copy_from_slice(&PQ_SIGNATURES_PER_KEY.to_be_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+
+        let mut pq = signer::PqSigner::from_bytes(&bytes).unwrap();
+        assert!(matches!(pq.sign(b"reading"), Err(BridgeError::Crypto(_))));
+
+        let mut ctx = EcoBlockContext::new();
+        ctx.load_pq_signer(&path).unwrap();
+        assert_eq!(ctx.pq_signatures_remaining(), Some(0));
+        let error = ctx.sign_record("alert", json!({ "metric": "co2" }), 1_700_000_000).unwrap_err();
+        assert!(matches!(error, BridgeError::Crypto(_)), "{}", error);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::VecDeque;
+use ecoblock_storage::tangle::block::TangleBlock;
+use serde::{Deserialize, Serialize};
+use crate::readings::Reading;
+use crate::{clock, EcoBlockContext};
+
+/// Blocks indexed per lock acquisition, so queries can interleave with the build.
+pub const BATCH_SIZE: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexStatus {
+    pub ready: bool,
+    pub indexed: usize,
+    pub pending: usize,
+}
+
+/// Blocks loaded into the tangle whose readings are not indexed yet.
+///
+/// Consistency: while the build runs, queries (aggregates, dashboards, gaps,
+/// inventory) see only the readings indexed so far, oldest loaded first. Blocks
+/// created or received live are indexed immediately and are always visible.
+#[derive(Debug, Default)]
+pub struct IndexBuilder {
+    queue: VecDeque<TangleBlock>,
+    indexed: usize,
+    running: bool,
+}
+
+impl IndexBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn enqueue(&mut self, block: TangleBlock) {
+        self.queue.push_back(block);
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn status(&self) -> IndexStatus {
+        IndexStatus {
+            ready: self.is_ready(),
+            indexed: self.indexed,
+            pending: self.queue.len(),
+        }
+    }
+
+    /// Marks the background builder as started; false if one is already running.
+    pub fn claim(&mut self) -> bool {
+        !std::mem::replace(&mut self.running, true)
+    }
+
+    pub fn release(&mut self) {
+        self.running = false;
+    }
+}
+
+impl EcoBlockContext {
+    /// Inserts stored blocks without indexing them; readings become queryable as
+    /// `build_indexes` works through the queue. Historical blocks do not fire
+    /// window, threshold or silence callbacks.
+    pub fn load_blocks(&mut self, blocks: Vec<TangleBlock>) {
+        for block in blocks {
+            if self.store_block(block.clone()) {
+                self.index_builder.enqueue(block);
+            }
+        }
+    }
+
+    /// Indexes up to `limit` queued blocks and returns how many were processed.
+    pub fn build_indexes(&mut self, limit: usize) -> usize {
+        let mut done = 0;
+        while done < limit {
+            let Some(block) = self.index_builder.queue.pop_front() else {
+                break;
+            };
+            done += 1;
+            if self.readings.contains(&block.id) {
+                continue;
+            }
+            let Ok(payload) = serde_json::to_value(&block.data.data) else {
+                continue;
+            };
+            let mut reading = Reading::from_payload(&block.id, &block.public_key, &payload, clock::now_secs());
+            self.derived.apply_on_ingest(&mut reading);
+            if !self.readings.is_flagged(&block.id) {
+                self.update_materialized_views(&reading);
+            }
+            self.readings.insert(reading);
+        }
+        self.index_builder.indexed += done;
+        done
+    }
+
+    pub fn indexes_ready(&self) -> bool {
+        self.index_builder.is_ready()
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::{BTreeMap, HashMap};
+use serde_json::{Map, Value};
+use ecoblock_core::domain::SensorData;
+use crate::block_id::BlockId;
+use crate::error::BridgeError;
+use crate::EcoBlockContext;
+
+/// Readings from one device at one time, decoded from a sensor protocol and
+/// ready to become a block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedReading {
+    /// `None` attributes the reading to the signing node.
+    pub sensor_id: Option<String>,
+    pub timestamp: u64,
+    pub values: BTreeMap<String, f64>,
+}
+
+/// Maps a measurement onto a `SensorData` field when its name and unit say
+/// what it is, converting Kelvin and humidity ratios.
+pub fn sensor_field(name: &str, unit: Option<&str>, value: f64) -> Option<(&'static str, f64)> {
+    match (name.to_ascii_lowercase().as_str(), unit) {
+        ("temperature" | "temp", None | Some("Cel")) => Some(("temperature", value)),
+        ("temperature" | "temp", Some("K")) => Some(("temperature", value - 273.15)),
+        ("humidity" | "hum" | "rh", None | Some("%RH")) => Some(("humidity", value)),
+        ("humidity" | "hum" | "rh", Some("/")) => Some(("humidity", value * 100.0)),
+        ("co2", None | Some("ppm")) => Some(("co2", value)),
+        ("pm25" | "pm2.5" | "pm2_5", None | Some("ug/m3")) => Some(("pm25", value)),
+        _ => None,
+    }
+}
+
+/// Collects values into one reading per device and time, in order of first
+/// appearance.
+#[derive(Debug, Default)]
+pub struct ReadingGroups {
+    readings: Vec<DecodedReading>,
+    slots: HashMap<(Option<String>, u64), usize>,
+}
+
+impl ReadingGroups {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a value, renamed to its `SensorData` field when `sensor_field` knows it.
+    pub fn insert(&mut self, sensor_id: Option<String>, timestamp: u64, metric: &str, unit: Option<&str>, value: f64) {
+        let (metric, value) = sensor_field(metric, unit, value).map_or((metric.to_string(), value), |(field, v)| (field.to_string(), v));
+        let readings = &mut self.readings;
+        let slot = *self.slots.entry((sensor_id.clone(), timestamp)).or_insert_with(|| {
+            readings.push(DecodedReading {
+                sensor_id,
+                timestamp,
+                values: BTreeMap::new(),
+            });
+            readings.len() - 1
+        });
+        self.readings[slot].values.insert(metric, value);
+    }
+
+    pub fn into_readings(self) -> Vec<DecodedReading> {
+        self.readings
+    }
+}
+
+impl DecodedReading {
+    /// The block payload: `sensor_id`, `timestamp` and every value, so the
+    /// reading index sees exactly what the device reported.
+    pub fn payload(&self) -> Value {
+        let mut payload = Map::new();
+        if let Some(sensor_id) = &self.sensor_id {
+            payload.insert("sensor_id".to_string(), Value::from(sensor_id.as_str()));
+        }
+        payload.insert("timestamp".to_string(), Value::from(self.timestamp));
+        for (metric, value) in &self.values {
+            payload.insert(metric.clone(), Value::from(*value));
+        }
+        Value::Object(payload)
+    }
+
+    /// Quantities the device did not report are zero in the signed data.
+    pub fn sensor_data(&self) -> SensorData {
+        let field = |name: &str| self.values.get(name).copied().unwrap_or_default() as f32;
+        SensorData {
+            pm25: field("pm25"),
+            co2: field("co2"),
+            temperature: field("temperature"),
+            humidity: field("humidity"),
+            timestamp: self.timestamp,
+        }
+    }
+}
+
+impl EcoBlockContext {
+    /// Signs one block per reading. Every block is validated before the first
+    /// is stored, so a rejected reading stores nothing.
+    pub fn ingest_decoded(&mut self, readings: &[DecodedReading]) -> Result<Vec<BlockId>, BridgeError> {
+        let prepared = readings
+            .iter()
+            .map(|r| self.prepare_sensor_block(r.sensor_data(), r.payload(), Vec::new()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| self.reject_block(e))?;
+        Ok(prepared.into_iter().map(|p| self.commit_block(p)).collect())
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use serde::{Deserialize, Serialize};
+use crate::readings::ReadingStore;
+
+pub const DEFAULT_INVENTORY_INTERVAL: u64 = 3600;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SensorSummary {
+    pub sensor_id: String,
+    pub metrics: Vec<String>,
+    pub reading_count: u64,
+    pub last_reading: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeInventory {
+    pub node_id: String,
+    pub published_at: u64,
+    pub sensors: Vec<SensorSummary>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkInventory {
+    pub nodes: Vec<NodeInventory>,
+    /// Number of distinct sensors per metric whose last reading is within the live window.
+    pub live_sensors_per_metric: BTreeMap<String, usize>,
+    pub total_sensors: usize,
+}
+
+pub fn summarize(store: &ReadingStore) -> Vec<SensorSummary> {
+    let mut by_sensor: BTreeMap<String, (BTreeSet<String>, u64, u64)> = BTreeMap::new();
+    for reading in store.iter() {
+        let entry = by_sensor.entry(reading.sensor_id.clone()).or_default();
+        entry.0.extend(reading.values.keys().cloned());
+        entry.1 += 1;
+        entry.2 = entry.2.max(reading.timestamp);
+    }
+    by_sensor
+        .into_iter()
+        .map(|(sensor_id, (metrics, reading_count, last_reading))| SensorSummary {
+            sensor_id,
+            metrics: metrics.into_iter().collect(),
+            reading_count,
+            last_reading,
+        })
+        .collect()
+}
+
+pub struct InventoryState {
+    pub interval: u64,
+    last_published: Option<u64>,
+    remote: HashMap<String, NodeInventory>,
+}
+
+impl Default for InventoryState {
+    fn default() -> Self {
+        Self {
+            interval: DEFAULT_INVENTORY_INTERVAL,
+            last_published: None,
+            remote: HashMap::new(),
+        }
+    }
+}
+
+impl InventoryState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_due(&self, now: u64) -> bool {
+        self.last_published.is_none_or(|last| now.saturating_sub(last) >= self.interval)
+    }
+
+    pub fn mark_published(&mut self, now: u64) {
+        self.last_published = Some(now);
+    }
+
+    /// Keeps only the most recent inventory per node.
+    pub fn receive(&mut self, inventory: NodeInventory) {
+        match self.remote.get(&inventory.node_id) {
+            Some(existing) if existing.published_at >= inventory.published_at => {}
+            _ => {
+                self.remote.insert(inventory.node_id.clone(), inventory);
+            }
+        }
+    }
+
+    pub fn aggregate(&self, local: NodeInventory, now: u64, live_within: u64) -> NetworkInventory {
+        let mut nodes: Vec<NodeInventory> = self
+            .remote
+            .values()
+            .filter(|n| n.node_id != local.node_id)
+            .cloned()
+            .collect();
+        nodes.push(local);
+        nodes.sort_by(|a, b| a.node_id.cmp(&b.node_id));
+        let mut live: BTreeMap<String, BTreeSet<&str>> = BTreeMap::new();
+        let mut all = BTreeSet::new();
+        for sensor in nodes.iter().flat_map(|n| n.sensors.iter()) {
+            all.insert(sensor.sensor_id.as_str());
+            if now.saturating_sub(sensor.last_reading) <= live_within {
+                for metric in &sensor.metrics {
+                    live.entry(metric.clone()).or_default().insert(sensor.sensor_id.as_str());
+                }
+            }
+        }
+        NetworkInventory {
+            live_sensors_per_metric: live.into_iter().map(|(m, s)| (m, s.len())).collect(),
+            total_sensors: all.len(),
+            nodes,
+        }
+    }
+}
@@ -0,0 +1,559 @@
+//! Shares one bridge between processes. The context is a per-process
+//! singleton, and two processes opening the same node would corrupt its block
+//! store, so on Android the process that owns the node (usually a background
+//! service) calls `serve_ipc` and the others, such as the UI, connect an
+//! `IpcClient` to its Unix socket. Each request is one JSON line naming a
+//! bridge function and its arguments by parameter name; the host calls the
+//! function and answers with its result on one line. `IpcClient` has a method
+//! per function, with the same name and arguments.
+
+use std::collections::BTreeMap;
+use std::fs::{self, DirBuilder, Permissions};
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use ecoblock_storage::tangle::block::TangleBlock;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use crate::address_book::PeerEntry;
+use crate::aggregate::MetricAggregate;
+use crate::annotations::Annotation;
+use crate::block_id::BlockId;
+use crate::block_query::BlockFilter;
+use crate::block_store::BlockStoreStatus;
+use crate::change_feed::ChangeRecord;
+use crate::channels::{ChannelMessage, Visibility};
+use crate::cloud_sync::{CloudSyncReport, CloudSyncStatus};
+use crate::dashboard::DashboardSummary;
+use crate::delta::Checkpoint;
+use crate::diagnostics::NodeStatusSnapshot;
+use crate::energy::EnergyReport;
+use crate::error::BridgeError;
+use crate::flags::{Flag, FlagReason};
+use crate::gaps::Gap;
+use crate::index_builder::IndexStatus;
+use crate::logs::{LogEntry, LogLevel};
+use crate::materialized_views::{ViewDefinition, ViewRow};
+use crate::pagination::Page;
+use crate::peer_id::PeerId;
+use crate::power::PowerMode;
+use crate::records::Record;
+use crate::runtime::{ThreadGuard, ThreadRole};
+use crate::s3_backup::{S3Backup, S3BackupStatus};
+use crate::saved_queries::{SavedQuery, SavedQueryResult};
+use crate::shared_config::ConfigEntry;
+use crate::snapshot::SnapshotImport;
+use crate::sync::SyncStats;
+use crate::thresholds::{ActiveAlert, ThresholdRule};
+use crate::watchdog;
+use crate::{run_pending, CONTEXT};
+
+/// Connections served at once; further clients are refused.
+pub const MAX_IPC_CLIENTS: usize = 16;
+/// Longest request line accepted.
+pub const MAX_REQUEST_BYTES: usize = 16 * 1024 * 1024;
+/// How long a client waits for an answer.
+pub const CALL_TIMEOUT: Duration = Duration::from_secs(30);
+/// How often serving threads check whether to stop.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// How often the accept loop reports to the watchdog.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+pub fn ipc_socket_path(node_path: &Path) -> PathBuf {
+    node_path.join("bridge.sock")
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IpcRequest {
+    pub id: u64,
+    pub method: String,
+    #[serde(default)]
+    pub params: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IpcResponse {
+    pub id: u64,
+    #[serde(default)]
+    pub result: Value,
+    #[serde(default)]
+    pub error: Option<BridgeError>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IpcStatus {
+    pub socket: Option<String>,
+    pub clients: usize,
+    pub calls: u64,
+}
+
+/// The socket this process serves, if any. Dropping it, as `shutdown_context`
+/// does with the context, stops serving and removes the socket.
+#[derive(Debug, Default)]
+pub struct IpcHost {
+    socket: Option<PathBuf>,
+    listener: Option<UnixListener>,
+    stop: Arc<AtomicBool>,
+    clients: Arc<AtomicUsize>,
+    calls: Arc<AtomicU64>,
+}
+
+impl IpcHost {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_serving(&self) -> bool {
+        self.socket.is_some()
+    }
+
+    pub fn status(&self) -> IpcStatus {
+        IpcStatus {
+            socket: self.socket.as_ref().map(|p| p.display().to_string()),
+            clients: self.clients.load(Ordering::Relaxed),
+            calls: self.calls.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Binds `socket` and serves it on a thread of watchdog `generation`. A
+    /// socket file left by a dead host is replaced; one a live host still
+    /// answers on is not.
+    pub(crate) fn serve(&mut self, socket: &Path, generation: u64) -> Result<(), BridgeError> {
+        if let Some(current) = &self.socket {
+            return Err(BridgeError::AlreadyInitialized(format!("IPC is served at {}", current.display())));
+        }
+        if socket.exists() {
+            if UnixStream::connect(socket).is_ok() {
+                return Err(BridgeError::AlreadyInitialized(format!("another process serves {}", socket.display())));
+            }
+            fs::remove_file(socket).map_err(|e| BridgeError::Io(e.to_string()))?;
+        }
+        let listener = bind_private(socket)?;
+        listener.set_nonblocking(true).map_err(|e| BridgeError::Io(e.to_string()))?;
+        self.stop = Arc::new(AtomicBool::new(false));
+        self.socket = Some(socket.to_path_buf());
+        self.listener = Some(listener);
+        self.restart(generation)
+    }
+
+    /// Starts an accept loop of `generation` on the served socket. The
+    /// watchdog calls it to replace a stalled loop, which stops at its next
+    /// heartbeat.
+    pub(crate) fn restart(&mut self, generation: u64) -> Result<(), BridgeError> {
+        let listener = self.listener.as_ref().ok_or_else(|| BridgeError::NotFound("Unknown IPC socket: none is served".to_string()))?;
+        let listener = listener.try_clone().map_err(|e| BridgeError::Io(e.to_string()))?;
+        let (stop, clients, calls) = (self.stop.clone(), self.clients.clone(), self.calls.clone());
+        std::thread::spawn(move || accept_loop(listener, generation, stop, clients, calls));
+        Ok(())
+    }
+
+    pub(crate) fn stop(&mut self) -> bool {
+        let Some(socket) = self.socket.take() else {
+            return false;
+        };
+        self.listener = None;
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = fs::remove_file(socket);
+        true
+    }
+}
+
+impl Drop for IpcHost {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Binds `socket` so that only processes of the app's own user can ever
+/// connect. The socket is created in a directory only the owner can enter,
+/// restricted to the owner, and then renamed into place; a socket bound
+/// directly at `socket` would be open to others until it is chmodded.
+fn bind_private(socket: &Path) -> Result<UnixListener, BridgeError> {
+    let parent = socket.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let staging = parent.join(format!(".bridge-ipc-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&staging);
+    DirBuilder::new().mode(0o700).create(&staging).map_err(|e| BridgeError::Io(e.to_string()))?;
+    let staged = staging.join("sock");
+    let bound = UnixListener::bind(&staged).and_then(|listener| {
+        fs::set_permissions(&staged, Permissions::from_mode(0o600))?;
+        fs::rename(&staged, socket)?;
+        Ok(listener)
+    });
+    let _ = fs::remove_dir_all(&staging);
+    bound.map_err(|e| BridgeError::Io(e.to_string()))
+}
+
+/// Reports the accept loop of `generation` alive. False once a restart has
+/// replaced it.
+fn heartbeat(generation: u64) -> bool {
+    let pending = {
+        let Ok(mut ctx) = CONTEXT.lock() else {
+            return true;
+        };
+        if !ctx.worker_heartbeat(watchdog::IPC, generation) {
+            return false;
+        }
+        ctx.take_pending()
+    };
+    run_pending(pending);
+    true
+}
+
+fn accept_loop(listener: UnixListener, generation: u64, stop: Arc<AtomicBool>, clients: Arc<AtomicUsize>, calls: Arc<AtomicU64>) {
+    let _running = ThreadGuard::enter(ThreadRole::Ipc);
+    let mut last_heartbeat: Option<Instant> = None;
+    while !stop.load(Ordering::Relaxed) {
+        if last_heartbeat.is_none_or(|at| at.elapsed() >= HEARTBEAT_INTERVAL) {
+            if !heartbeat(generation) {
+                return;
+            }
+            last_heartbeat = Some(Instant::now());
+        }
+        match listener.accept() {
+            Ok((mut stream, _)) => {
+                if clients.load(Ordering::Relaxed) >= MAX_IPC_CLIENTS {
+                    let _ = write_response(&mut stream, &refusal(0, BridgeError::InvalidInput(format!("Invalid IPC connection: {} clients already connected", MAX_IPC_CLIENTS))));
+                    continue;
+                }
+                clients.fetch_add(1, Ordering::Relaxed);
+                let (stop, clients, calls) = (stop.clone(), clients.clone(), calls.clone());
+                std::thread::spawn(move || {
+                    serve_connection(stream, &stop, &calls);
+                    clients.fetch_sub(1, Ordering::Relaxed);
+                });
+            }
+            Err(_) => std::thread::sleep(POLL_INTERVAL),
+        }
+    }
+}
+
+fn serve_connection(stream: UnixStream, stop: &AtomicBool, calls: &AtomicU64) {
+    let _running = ThreadGuard::enter(ThreadRole::Ipc);
+    if stream.set_nonblocking(false).is_err() || stream.set_read_timeout(Some(POLL_INTERVAL)).is_err() {
+        return;
+    }
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    let mut reader = BufReader::new(stream);
+    let mut line = Vec::new();
+    while !stop.load(Ordering::Relaxed) {
+        let limit = (MAX_REQUEST_BYTES + 1 - line.len()) as u64;
+        match (&mut reader).take(limit).read_until(b'\n', &mut line) {
+            Ok(_) if line.ends_with(b"\n") => {}
+            // Closed, or a request over the size limit.
+            Ok(_) => return,
+            // A timeout keeps what was read so far for the next attempt.
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+            Err(_) => return,
+        }
+        let response = match serde_json::from_slice::<IpcRequest>(&line) {
+            Ok(request) => {
+                calls.fetch_add(1, Ordering::Relaxed);
+                match dispatch(&request.method, request.params) {
+                    Ok(result) => IpcResponse { id: request.id, result, error: None },
+                    Err(e) => refusal(request.id, e),
+                }
+            }
+            Err(e) => refusal(0, BridgeError::Deserialization(e.to_string())),
+        };
+        line.clear();
+        if write_response(&mut writer, &response).is_err() {
+            return;
+        }
+    }
+}
+
+fn refusal(id: u64, error: BridgeError) -> IpcResponse {
+    IpcResponse {
+        id,
+        result: Value::Null,
+        error: Some(error),
+    }
+}
+
+fn write_response(stream: &mut UnixStream, response: &IpcResponse) -> std::io::Result<()> {
+    let mut bytes = serde_json::to_vec(response).map_err(std::io::Error::other)?;
+    bytes.push(b'\n');
+    stream.write_all(&bytes)
+}
+
+/// Takes argument `name`; a missing argument reads as `null`, so optional
+/// arguments can be left out.
+fn param<T: DeserializeOwned>(params: &mut Map<String, Value>, name: &str) -> Result<T, BridgeError> {
+    let value = params.remove(name).unwrap_or(Value::Null);
+    serde_json::from_value(value).map_err(|e| BridgeError::Deserialization(format!("argument {}: {}", name, e)))
+}
+
+fn args<const N: usize>(args: [(&str, serde_json::Result<Value>); N]) -> Result<Map<String, Value>, BridgeError> {
+    args.into_iter()
+        .map(|(name, value)| value.map(|v| (name.to_string(), v)).map_err(|e| BridgeError::Serialization(e.to_string())))
+        .collect()
+}
+
+/// A connection to the process serving the node.
+pub struct IpcClient {
+    socket: PathBuf,
+    timeout: Duration,
+    /// The open connection, `None` after a call failed on it, and the last
+    /// request id used.
+    connection: Mutex<(Option<BufReader<UnixStream>>, u64)>,
+}
+
+impl IpcClient {
+    pub fn connect(socket: &Path) -> Result<Self, BridgeError> {
+        Self::connect_with_timeout(socket, CALL_TIMEOUT)
+    }
+
+    /// Like `connect`, waiting at most `timeout` for each answer.
+    pub fn connect_with_timeout(socket: &Path, timeout: Duration) -> Result<Self, BridgeError> {
+        let stream = open_stream(socket, timeout)?;
+        Ok(Self {
+            socket: socket.to_path_buf(),
+            timeout,
+            connection: Mutex::new((Some(stream), 0)),
+        })
+    }
+
+    /// Calls bridge function `method` in the host. Errors of the function
+    /// come back with their kind. After a timeout or any other failure on the
+    /// connection, the next call opens a new one, so a late answer is never
+    /// taken for the answer to a later request.
+    pub fn call<T: DeserializeOwned>(&self, method: &str, params: Map<String, Value>) -> Result<T, BridgeError> {
+        let mut connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        let (stream, next_id) = &mut *connection;
+        *next_id += 1;
+        let request = IpcRequest {
+            id: *next_id,
+            method: method.to_string(),
+            params,
+        };
+        if stream.is_none() {
+            *stream = Some(open_stream(&self.socket, self.timeout)?);
+        }
+        let response = match exchange(stream.as_mut().expect("connection just opened"), &request) {
+            Ok(response) => response,
+            Err(e) => {
+                *stream = None;
+                return Err(e);
+            }
+        };
+        if let Some(error) = response.error {
+            return Err(error);
+        }
+        serde_json::from_value(response.result).map_err(|e| BridgeError::Deserialization(e.to_string()))
+    }
+}
+
+fn open_stream(socket: &Path, timeout: Duration) -> Result<BufReader<UnixStream>, BridgeError> {
+    let stream = UnixStream::connect(socket).map_err(|e| BridgeError::Io(format!("{}: {}", socket.display(), e)))?;
+    stream.set_read_timeout(Some(timeout)).map_err(|e| BridgeError::Io(e.to_string()))?;
+    Ok(BufReader::new(stream))
+}
+
+/// Sends `request` and reads up to its answer. Answers to earlier requests are
+/// skipped; the host answers with id 0 when it refuses the connection or
+/// cannot read the request.
+fn exchange(reader: &mut BufReader<UnixStream>, request: &IpcRequest) -> Result<IpcResponse, BridgeError> {
+    let mut bytes = serde_json::to_vec(request).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+    bytes.push(b'\n');
+    reader.get_mut().write_all(&bytes).map_err(|e| BridgeError::Io(e.to_string()))?;
+    loop {
+        let mut line = Vec::new();
+        reader.read_until(b'\n', &mut line).map_err(|e| BridgeError::Io(e.to_string()))?;
+        if !line.ends_with(b"\n") {
+            return Err(BridgeError::Io("IPC host closed the connection".to_string()));
+        }
+        let response: IpcResponse = serde_json::from_slice(&line).map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+        match response.id {
+            id if id == request.id => return Ok(response),
+            0 if response.error.is_some() => return Ok(response),
+            id if id < request.id => continue,
+            id => return Err(BridgeError::Wire(format!("answer to request {} received for {}", id, request.id))),
+        }
+    }
+}
+
+/// Declares the functions served over IPC: the host's dispatch table and the
+/// matching `IpcClient` methods. Fallible functions are served in their
+/// version 2 form and return their own error through the client; the others
+/// only fail on IPC errors.
+macro_rules! ipc_functions {
+    (
+        infallible { $(fn $name:ident($($arg:ident: $ty:ty),*) -> $ret:ty;)* }
+        fallible { $(fn $try_name:ident($($try_arg:ident: $try_ty:ty),*) -> $try_ret:ty;)* }
+    ) => {
+        fn dispatch(method: &str, mut params: Map<String, Value>) -> Result<Value, BridgeError> {
+            let result = match method {
+                $(stringify!($name) => serde_json::to_value(&crate::$name($(param(&mut params, stringify!($arg))?),*)),)*
+                $(stringify!($try_name) => serde_json::to_value(&crate::v2::$try_name($(param(&mut params, stringify!($try_arg))?),*)?),)*
+                _ => return Err(BridgeError::NotFound(format!("Unknown IPC function {}", method))),
+            };
+            result.map_err(|e| BridgeError::Serialization(e.to_string()))
+        }
+
+        impl IpcClient {
+            $(pub fn $name(&self, $($arg: $ty),*) -> Result<$ret, BridgeError> {
+                self.call(stringify!($name), args([$((stringify!($arg), serde_json::to_value(&$arg))),*])?)
+            })*
+            $(pub fn $try_name(&self, $($try_arg: $try_ty),*) -> Result<$try_ret, BridgeError> {
+                self.call(stringify!($try_name), args([$((stringify!($try_arg), serde_json::to_value(&$try_arg))),*])?)
+            })*
+        }
+    };
+}
+
+// Context lifecycle (`init_context`, `shutdown_context`, `reset_node`) and
+// callback subscriptions stay with the host; clients follow changes through
+// `changes_since`.
+ipc_functions! {
+    infallible {
+        fn get_tangle_size() -> usize;
+        fn list_tips() -> Vec<BlockId>;
+        fn add_peer_connection(from: PeerId, to: PeerId, weight: f32) -> ();
+        fn list_peers(peer_id: PeerId) -> Vec<PeerId>;
+        fn add_address_book_entry(node_id: PeerId, alias: Option<String>) -> ();
+        fn remove_address_book_entry(node_id: PeerId) -> bool;
+        fn list_address_book() -> Vec<PeerEntry>;
+        fn aggregate_metric(metric: String, from: u64, to: u64) -> MetricAggregate;
+        fn set_threshold(rule: ThresholdRule) -> ();
+        fn remove_threshold(metric: String) -> bool;
+        fn list_thresholds() -> Vec<ThresholdRule>;
+        fn active_alerts() -> Vec<ActiveAlert>;
+        fn list_alert_blocks() -> Vec<Record>;
+        fn data_gaps(sensor_id: String, expected_interval: u64, from: u64, to: u64) -> Vec<Gap>;
+        fn dashboard_summary() -> DashboardSummary;
+        fn annotations_for(block_id: BlockId) -> Vec<Annotation>;
+        fn list_flagged() -> Vec<Flag>;
+        fn list_channels() -> Vec<(String, Visibility)>;
+        fn read_channel(channel: String) -> Vec<ChannelMessage>;
+        fn recent_logs(limit: usize, min_level: LogLevel) -> Vec<LogEntry>;
+        fn indexes_ready() -> bool;
+        fn index_status() -> IndexStatus;
+        fn create_checkpoint() -> Checkpoint;
+        fn list_checkpoints() -> Vec<Checkpoint>;
+        fn changes_since(cursor: u64) -> Vec<ChangeRecord>;
+        fn change_feed_head() -> u64;
+        fn node_status() -> NodeStatusSnapshot;
+        fn sync_stats() -> SyncStats;
+        fn energy_report() -> EnergyReport;
+        fn power_mode() -> PowerMode;
+        fn block_store_status() -> Option<BlockStoreStatus>;
+        fn saved_queries() -> Vec<SavedQuery>;
+        fn materialized_views() -> Vec<ViewDefinition>;
+        fn s3_backup_status() -> S3BackupStatus;
+        fn cloud_sync_status() -> CloudSyncStatus;
+    }
+    fallible {
+        fn create_block(data: Vec<u8>, parents: Vec<BlockId>) -> BlockId;
+        fn get_block(id: BlockId) -> String;
+        fn list_blocks_page(cursor: Option<String>, limit: usize) -> Page<TangleBlock>;
+        fn query_blocks_page(filter: BlockFilter, cursor: Option<String>, limit: usize) -> Page<TangleBlock>;
+        fn active_alerts_page(cursor: Option<String>, limit: usize) -> Page<ActiveAlert>;
+        fn annotate_block(block_id: BlockId, note: String) -> Annotation;
+        fn flag_block(block_id: BlockId, reason: FlagReason, comment: Option<String>) -> Flag;
+        fn publish_to_channel(channel: String, data: Vec<u8>) -> String;
+        fn set_shared_config(key: String, value: String, expected_token: Option<String>) -> ConfigEntry;
+        fn run_saved_query(name: String, params: BTreeMap<String, String>) -> SavedQueryResult;
+        fn materialized_view_rows(name: String) -> Vec<ViewRow>;
+        fn export_snapshot(path: String) -> Checkpoint;
+        fn import_snapshot(path: String) -> SnapshotImport;
+        fn flush_block_store() -> ();
+        fn sync_with_peer(peer: PeerId) -> ();
+        fn backup_to_s3() -> S3Backup;
+        fn cloud_sync_now() -> CloudSyncReport;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::MetadataExt;
+    use serde_json::json;
+    use super::*;
+
+    fn socket_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ecoblock-ipc-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("bridge.sock")
+    }
+
+    fn read_request(reader: &mut BufReader<UnixStream>) -> IpcRequest {
+        let mut line = Vec::new();
+        reader.read_until(b'\n', &mut line).unwrap();
+        serde_json::from_slice(&line).unwrap()
+    }
+
+    fn answer(stream: &mut UnixStream, id: u64, result: Value) {
+        write_response(stream, &IpcResponse { id, result, error: None }).unwrap();
+    }
+
+    #[test]
+    fn calls_round_trip_with_their_errors_through_a_private_socket() {
+        let socket = socket_path("round-trip");
+        let mut host = IpcHost::new();
+        host.serve(&socket, 0).unwrap();
+        assert_eq!(fs::metadata(&socket).unwrap().mode() & 0o777, 0o600);
+        assert!(fs::read_dir(socket.parent().unwrap()).unwrap().all(|e| !e.unwrap().file_name().to_string_lossy().starts_with(".bridge-ipc")));
+
+        let client = IpcClient::connect(&socket).unwrap();
+        assert!(client.get_tangle_size().is_ok());
+        let error = client.get_block(BlockId::from_storage("missing")).unwrap_err();
+        assert!(matches!(error, BridgeError::NotFound(_)), "{}", error);
+        assert_eq!(host.status().calls, 2);
+
+        drop(host);
+        assert!(!socket.exists());
+        fs::remove_dir_all(socket.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn a_late_answer_after_a_timeout_is_not_taken_for_the_next_call() {
+        let socket = socket_path("timeout");
+        let listener = UnixListener::bind(&socket).unwrap();
+        let host = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let first = read_request(&mut reader);
+            std::thread::sleep(Duration::from_millis(300));
+            let _ = write_response(&mut stream.try_clone().unwrap(), &IpcResponse { id: first.id, result: json!("late"), error: None });
+
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let second = read_request(&mut reader);
+            answer(&mut stream, second.id, json!("fresh"));
+        });
+
+        let client = IpcClient::connect_with_timeout(&socket, Duration::from_millis(100)).unwrap();
+        let error = client.call::<String>("get_block", Map::new()).unwrap_err();
+        assert!(matches!(error, BridgeError::Io(_)), "{}", error);
+        assert_eq!(client.call::<String>("get_block", Map::new()).unwrap(), "fresh");
+        host.join().unwrap();
+        fs::remove_dir_all(socket.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn answers_to_earlier_requests_are_skipped() {
+        let socket = socket_path("stale");
+        let listener = UnixListener::bind(&socket).unwrap();
+        let host = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let first = read_request(&mut reader);
+            answer(&mut stream, first.id, json!(1));
+            let second = read_request(&mut reader);
+            write_response(&mut stream, &refusal(first.id, BridgeError::NotFound("stale".to_string()))).unwrap();
+            answer(&mut stream, second.id, json!(2));
+        });
+
+        let client = IpcClient::connect(&socket).unwrap();
+        assert_eq!(client.call::<u64>("get_tangle_size", Map::new()).unwrap(), 1);
+        assert_eq!(client.call::<u64>("get_tangle_size", Map::new()).unwrap(), 2);
+        host.join().unwrap();
+        fs::remove_dir_all(socket.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,150 @@
+//! Durable queue of work only the host can do, such as sending through the
+//! platform's BLE stack or showing a notification. The host polls for jobs,
+//! carries them out and completes them. Polled jobs are leased, not removed:
+//! one not completed within `LEASE_SECS`, because the host crashed or was
+//! killed, is handed out again. The queue is kept in `jobs.json` in the node
+//! directory and rewritten on every enqueue and completion, so no job is lost
+//! across restarts.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::error::BridgeError;
+use crate::events::BridgeEvent;
+use crate::{clock, EcoBlockContext};
+
+const JOBS_FILE: &str = "jobs.json";
+pub const MAX_JOBS: usize = 1024;
+/// Jobs returned by one poll.
+pub const MAX_POLLED: usize = 64;
+/// How long a polled job stays with the host before it is handed out again.
+pub const LEASE_SECS: u64 = 300;
+
+pub fn jobs_path(path: &Path) -> PathBuf {
+    path.join(JOBS_FILE)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    pub id: u64,
+    /// Chosen by whoever enqueues the job, such as `ble_send` or `notify`.
+    pub kind: String,
+    pub payload: Value,
+    pub created_at: u64,
+    /// Times the job was handed to the host.
+    pub attempts: u32,
+    /// Leases are not persisted: after a restart every job is due again.
+    #[serde(skip)]
+    pub leased_until: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct JobQueue {
+    next_id: u64,
+    jobs: BTreeMap<u64, Job>,
+}
+
+impl JobQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    /// Every queued job, oldest first, leased or not.
+    pub fn list(&self) -> Vec<Job> {
+        self.jobs.values().cloned().collect()
+    }
+
+    pub fn load(&mut self, path: &Path) -> Result<(), BridgeError> {
+        if !path.exists() {
+            return Ok(());
+        }
+        let bytes = fs::read(path).map_err(|e| BridgeError::Io(e.to_string()))?;
+        *self = serde_json::from_slice(&bytes).map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Writes a temporary file and renames it over the old one, so a crash
+    /// leaves either version intact.
+    fn save(&self, path: &Path) -> Result<(), BridgeError> {
+        let json = serde_json::to_vec_pretty(self).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json).map_err(|e| BridgeError::Io(e.to_string()))?;
+        fs::rename(&tmp, path).map_err(|e| BridgeError::Io(e.to_string()))
+    }
+
+    fn push(&mut self, kind: &str, payload: Value, now: u64) -> Result<u64, BridgeError> {
+        if kind.is_empty() {
+            return Err(BridgeError::InvalidInput("Invalid job: kind must not be empty".to_string()));
+        }
+        if self.jobs.len() >= MAX_JOBS {
+            return Err(BridgeError::InvalidInput(format!("Invalid job {}: queue holds {} jobs; complete some first", kind, MAX_JOBS)));
+        }
+        self.next_id += 1;
+        let id = self.next_id;
+        self.jobs.insert(
+            id,
+            Job {
+                id,
+                kind: kind.to_string(),
+                payload,
+                created_at: now,
+                attempts: 0,
+                leased_until: None,
+            },
+        );
+        Ok(id)
+    }
+
+    /// Leases up to `MAX_POLLED` jobs that are not leased, oldest first.
+    pub fn poll(&mut self, now: u64) -> Vec<Job> {
+        self.jobs
+            .values_mut()
+            .filter(|job| job.leased_until.is_none_or(|until| until <= now))
+            .take(MAX_POLLED)
+            .map(|job| {
+                job.attempts += 1;
+                job.leased_until = Some(now + LEASE_SECS);
+                job.clone()
+            })
+            .collect()
+    }
+}
+
+impl EcoBlockContext {
+    fn persist_jobs(&self) -> Result<(), BridgeError> {
+        match &self.node_path {
+            Some(path) => self.jobs.save(&jobs_path(path)),
+            None => Ok(()),
+        }
+    }
+
+    /// Queues a job for the host and persists the queue before returning.
+    pub fn enqueue_job(&mut self, kind: &str, payload: Value) -> Result<u64, BridgeError> {
+        let id = self.jobs.push(kind, payload, clock::now_secs())?;
+        if let Err(e) = self.persist_jobs() {
+            self.jobs.jobs.remove(&id);
+            return Err(e);
+        }
+        self.emit_event(BridgeEvent::JobQueued { id, kind: kind.to_string() });
+        Ok(id)
+    }
+
+    /// Removes a finished job. False if no job has `id`.
+    pub fn complete_job(&mut self, id: u64) -> Result<bool, BridgeError> {
+        if self.jobs.jobs.remove(&id).is_none() {
+            return Ok(false);
+        }
+        self.persist_jobs()?;
+        Ok(true)
+    }
+}
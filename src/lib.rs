@@ -1,5 +1,119 @@
+pub mod address_book;
+pub mod aggregate;
+pub mod airtime;
+pub mod annotations;
+pub mod batch;
+#[cfg(feature = "bench")]
+pub mod bench;
+pub mod block_id;
+pub mod block_log;
+pub mod block_query;
+pub mod block_store;
+pub mod bulk_peers;
+pub mod call_metrics;
+pub mod canonical;
+pub mod capabilities;
+pub mod change_feed;
+pub mod channels;
+pub mod chaos;
+pub mod clock;
+pub mod cloud_sync;
+pub mod congestion;
+pub mod dashboard;
+pub mod deadlines;
+pub mod data_mule;
+pub mod decommission;
+pub mod delta;
+pub mod derived;
+pub mod diagnostics;
+pub mod encounter_history;
+pub mod energy;
+pub mod error;
+pub mod events;
+pub mod explorer;
+pub mod flags;
+pub mod flow_stats;
+pub mod gaps;
+pub mod gateway;
+pub mod geofence;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+pub mod group_keys;
+pub mod handover;
+pub mod have_lists;
+pub mod home_assistant;
+pub mod http;
+pub mod hybrid;
+pub mod index_builder;
+pub mod ingest;
+pub mod inventory;
+#[cfg(unix)]
+pub mod ipc;
+pub mod jobs;
+pub mod lifecycle;
+pub mod lineage;
+pub mod log_stream;
+pub mod logs;
+pub mod lorawan;
+pub mod maintenance;
+pub mod materialized_views;
+pub mod membership;
+pub mod metrics_history;
+pub mod multicast;
+pub mod multipath;
+pub mod network_stats;
+pub mod node_lock;
+pub mod pagination;
+pub mod payloads;
+pub mod peer_groups;
+pub mod peer_id;
+pub mod policy;
+pub mod pow;
+pub mod power;
+pub mod prefetch;
+pub mod preflight;
+#[cfg(feature = "presentation")]
+pub mod presentation;
+pub mod probation;
+pub mod quiet_hours;
+pub mod readings;
+pub mod records;
+pub mod rejections;
+pub mod relay;
+pub mod response_cache;
+pub mod retention;
+pub mod runtime;
+pub mod s3_backup;
+pub mod saved_queries;
+#[cfg(feature = "scripting")]
+pub mod scripting;
+pub mod sealed;
+pub mod senml;
+#[cfg(feature = "sensorthings")]
+pub mod sensorthings;
+pub mod shared_config;
+pub mod simulator;
+pub mod snapshot;
+pub mod source_routing;
+pub mod sparkplug;
+pub mod storage_analysis;
+pub mod suites;
+pub mod sync;
+pub mod test_vectors;
+pub mod thresholds;
+pub mod topology_log;
+pub mod transport;
+pub mod v2;
+pub mod validation;
+pub mod versions;
+pub mod wake_windows;
+pub mod watchdog;
+pub mod windowed;
+pub mod wire;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use ecoblock_storage::tangle::block::TangleBlock;
 use ecoblock_storage::tangle::Tangle;
 use ecoblock_core::domain::tangle_data::TangleBlockData;
@@ -7,71 +121,179 @@ use ecoblock_core::domain::SensorData;
 use ecoblock_crypto::keys::keypair::CryptoKeypair;
 use ecoblock_gossip::engine::gossip::GossipEngine;
 use ecoblock_mesh::topology::TopologyGraph;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use lazy_static::lazy_static;
-use serde_json;
+use address_book::{AddressBook, PeerEntry};
+use aggregate::MetricAggregate;
+use airtime::{AirtimeState, AirtimeStatus, DutyCycleBudget};
+use annotations::{Annotation, ANNOTATION_KIND};
+use batch::{Batches, PreparedBlock};
+use block_id::BlockId;
+use block_log::BlockLog;
+use block_query::BlockFilter;
+use block_store::{BlockStore, BlockStoreStatus};
+use bulk_peers::{BulkReport, PeerSpec};
+use call_metrics::{CallStats, CallTimer, CALLS, DEFAULT_CALL_BUDGET_MICROS};
+use canonical::EncodingPolicy;
+use capabilities::CapabilityReport;
+use change_feed::{ChangeFeed, ChangeKind, ChangeRecord};
+use channels::{ChannelMessage, ChannelMessageBody, ChannelPayload, ChannelRegistry, Visibility, CHANNEL_MESSAGE_KIND};
+use chaos::{ChaosConfig, ChaosInjector};
+use cloud_sync::{CloudSync, CloudSyncConfig, CloudSyncReport, CloudSyncStatus};
+use congestion::{CongestionControl, PeerWindow};
+use dashboard::{DashboardSummary, MetricRange, PeerHealth};
+use deadlines::{DeliveryDeadlines, PeerDeliveryStats};
+use data_mule::{DataMuleConfig, DataMuleState, Encounter};
+use decommission::{DecommissionReport, RETIREMENT_KIND};
+use delta::{Checkpoint, DeltaImport};
+use derived::{DerivedMetric, DerivedMode, DerivedRegistry};
+use diagnostics::{DiagnosticsBundle, NodeStatusSnapshot, DIAGNOSTICS_REQUEST_KIND};
+use encounter_history::{LatencyLog, RegionLatency};
+use energy::{EnergyCoefficients, EnergyReport};
+use error::BridgeError;
+use events::{BridgeEvent, EventBus, EventCallback};
+use explorer::{ExplorerDelta, ExplorerState, ExplorerView};
+use flags::{Flag, FlagReason, FLAG_KIND};
+use flow_stats::{ChannelStats, FlowStats, PeerStats};
+use gaps::{Gap, SilenceCallback, SilenceEvent, SilenceMonitor};
+use gateway::{Gateway, GatewayConfig, GatewayResponse, GatewayStats};
+use geofence::{GeoPoint, Geofence, GeofenceDeclaration, GeofenceState, GEOFENCE_KIND};
+#[cfg(feature = "graphql")]
+use graphql::GraphqlResponse;
+use group_keys::{GroupKeyBody, GroupMembers, WrappedKey, GROUP_KEY_KIND};
+use handover::HANDOVER_KIND;
+use have_lists::{HaveLists, SuppressionStats, HAVE_KIND};
+use home_assistant::{HomeAssistantConfig, HomeAssistantExport, MqttMessage};
+use http::HttpClient;
+use hybrid::{HybridPolicy, HybridState};
+use index_builder::{IndexBuilder, IndexStatus};
+use inventory::{InventoryState, NetworkInventory, NodeInventory};
+#[cfg(unix)]
+use ipc::{IpcHost, IpcStatus};
+use jobs::{Job, JobQueue};
+use lifecycle::{Lifecycle, LifecycleCallback, LifecycleEvent, REDACTION_KIND};
+use lineage::Lineage;
+use log_stream::{LogStream, LogStreamFrame, LogStreamStatus};
+use logs::{LogBuffer, LogEntry, LogLevel};
+use lorawan::{LorawanIngest, LorawanSensor, LorawanState, LorawanUplink, UplinkCodec};
+use maintenance::{MaintenanceProgress, MaintenanceReport, SyncSliceReport};
+use materialized_views::{MaterializedViews, ViewDefinition, ViewRow};
+use membership::{AcceptanceBody, InvitationBody, Member, RevocationBody, Role, Roster, ACCEPTANCE_KIND, INVITATION_KIND, REVOCATION_KIND};
+use metrics_history::{HistoryPoint, MetricsHistory};
+use multicast::{MulticastDelivery, Multicasts};
+use multipath::{Priority, RoutePlan, RoutingConfig};
+use network_stats::{NetworkOverview, NetworkStats, OPS_CHANNEL};
+use node_lock::NodeLock;
+use pagination::Page;
+use payloads::{PayloadStats, PayloadStore};
+use peer_groups::{GroupPolicies, GroupPolicy, PeerGroup};
+use peer_id::{PeerId, PublicKeyHex};
+use policy::{AppliedPolicy, PolicyDocument, PolicyState, PolicyTrial, TrialOutcome, POLICY_KIND, POLICY_STATUS_KIND};
+use pow::{AnnouncementBody, PowGate, ANNOUNCEMENT_KIND};
+use power::{PowerManager, PowerMode, PowerState, PowerThresholds};
+use prefetch::{PrefetchStatus, Prefetcher, QuerySpec};
+use preflight::ValidationReport;
+#[cfg(feature = "presentation")]
+use presentation::{FormattedAggregate, FormattedDashboard, FormattedValue, Locale, MetricFormat, MetricFormats};
+use probation::{EndorsementBody, ProbationPolicy, ProbationPool, Released, ENDORSEMENT_KIND};
+use quiet_hours::{QuietHours, QuietStatus, QuietWindow};
+use readings::{QueryOptions, Reading, ReadingStore};
+use records::{Record, RecordLog};
+use rejections::{RejectionStats, Rejections, REJECTION_KIND};
+use relay::{RelayConfig, RelayRole, RelayState};
+use response_cache::ResponseCache;
+use retention::{RetentionForecast, RetentionPolicy};
+use runtime::{BridgeConfig, ThreadGuard, ThreadRole};
+use s3_backup::{S3Backup, S3BackupStatus, S3Backups};
+use saved_queries::{SavedQueries, SavedQuery, SavedQueryResult};
+#[cfg(feature = "scripting")]
+use scripting::{ScriptDefinition, ScriptEvent, ScriptStatus, Scripts};
+use senml::SenmlIngest;
+#[cfg(feature = "sensorthings")]
+use sensorthings::SensorThingsResponse;
+use shared_config::{ConfigEntry, SharedConfig, SHARED_CONFIG_KIND};
+use simulator::{Scenario, SimulationReport};
+use snapshot::SnapshotImport;
+use source_routing::{DirectedDelivery, RoutingStats, SourceRouting};
+#[cfg(feature = "sparkplug")]
+use sparkplug::{SparkplugDevice, SparkplugIngest};
+use sparkplug::SparkplugState;
+use storage_analysis::StorageAnalysis;
+use suites::{Suite, SuiteRegistry};
+use sync::{SyncState, SyncStats};
+use test_vectors::TestVectors;
+use thresholds::{ActiveAlert, ThresholdCallback, ThresholdEvent, ThresholdMonitor, ThresholdRule};
+use topology_log::{TopologyChange, TopologyLog, TopologySnapshot};
+use transport::{Transport, UdpTransport};
+use v2::ApiVersion;
+use validation::{RateAction, RateRule, RateValidator, RateViolation};
+use versions::{NodeVersion, VersionState, VERSION_KIND};
+use wake_windows::{WakeSchedule, WakeState};
+use watchdog::{ComponentHealth, HealthCallback, HealthEvent, RestartHook, Watchdog, INDEX_BUILDER, INDEX_BUILDER_TIMEOUT};
+use windowed::{WindowCallback, WindowSubscriptions, WindowedAggregate};
+use wire::{BlockEnvelope, Frame, Handshake};
 
 
 pub fn keypair_path(path: &str) -> PathBuf {
+    let _call = CallTimer::start("keypair_path");
     PathBuf::from(path).join("node_keypair.bin")
 }
 
-fn load_keypair(path: &str) -> Result<CryptoKeypair, String> {
-    let bytes = fs::read(keypair_path(path)).map_err(|e| format!("IO error: {}", e))?;
-    CryptoKeypair::from_bytes(&bytes).map_err(|e| format!("Crypto error: {:?}", e))
+fn load_keypair(path: &str) -> Result<CryptoKeypair, BridgeError> {
+    let bytes = fs::read(keypair_path(path)).map_err(|e| BridgeError::Io(e.to_string()))?;
+    CryptoKeypair::from_bytes(&bytes).map_err(|e| BridgeError::Crypto(format!("{:?}", e)))
 }
 
 pub fn generate_keypair(path: String) -> Result<String, String> {
-    let keypair = CryptoKeypair::generate();
-    let bytes = keypair.to_bytes();
-    eprintln!("[generate_keypair] bytes len: {}", bytes.len());
-    fs::write(keypair_path(&path), &bytes).map_err(|e| format!("IO error: {}", e))?;
-    let file_bytes = fs::read(keypair_path(&path)).map_err(|e| format!("IO error: {}", e))?;
-    eprintln!("[generate_keypair] file bytes len: {}", file_bytes.len());
-    eprintln!("[generate_keypair] file bytes: {:?}", file_bytes);
-    Ok(keypair.public_key_hex())
+    v2::generate_keypair(path).map_err(String::from)
 }
 
 pub fn get_public_key(path: String) -> Result<String, String> {
-    let keypair = load_keypair(&path)?;
-    Ok(keypair.public_key_hex())
+    v2::get_public_key(path).map_err(String::from)
 }
 
 pub fn get_node_id(path: String) -> Result<String, String> {
-    let keypair = load_keypair(&path)?;
-    let node_id = keypair.public_key_hex();
-    Ok(node_id)
+    v2::get_node_id(path).map_err(String::from)
 }
 
 pub fn initialize_tangle() -> Result<(), String> {
-    let _tangle = Tangle::new();
-    Ok(())
+    v2::initialize_tangle().map_err(String::from)
 }
 
 pub fn initialize_mesh(path: String) -> Result<(), String> {
-    let mut mesh = TopologyGraph::new();
-    let node_id = get_node_id(path.clone())?;
-    mesh.add_node(&node_id);
-    Ok(())
+    v2::initialize_mesh(path).map_err(String::from)
 }
 
 pub fn create_local_node(path: String) -> Result<String, String> {
-    if node_is_initialized(path.clone())? {
-        return Err("AlreadyInitialized".to_string());
-    }
-    generate_keypair(path.clone())?;
-    initialize_tangle()?;
-    initialize_mesh(path.clone())?;
-    get_node_id(path)
+    v2::create_local_node(path).map_err(String::from)
 }
 
 pub fn reset_node(path: String) -> Result<(), String> {
-    let _ = fs::remove_file(keypair_path(&path));
-    Ok(())
+    v2::reset_node(path).map_err(String::from)
+}
+
+/// Builds the global context from the node created at `path`: its persisted
+/// keypair and the blocks in its block store, indexed in the background. Until
+/// then the context signs with a throwaway keypair. Anything configured on the
+/// previous context is discarded. Fails while another process has the node
+/// open; other processes reach it through `serve_ipc`. Returns the node id.
+pub fn init_context(path: String) -> Result<String, String> {
+    v2::init_context(path).map_err(String::from)
+}
+
+/// Syncs the block store and drops the context bound by `init_context`, also
+/// recovering the context lock after a panic. Queued callbacks are discarded.
+pub fn shutdown_context() -> Result<(), String> {
+    v2::shutdown_context().map_err(String::from)
+}
+
+pub fn context_node_path() -> Option<String> {
+    let _call = CallTimer::start("context_node_path");
+    CONTEXT.lock().unwrap().node_path.as_ref().map(|p| p.display().to_string())
 }
 
 pub fn node_is_initialized(path: String) -> Result<bool, String> {
-    Ok(keypair_path(&path).exists())
+    v2::node_is_initialized(path).map_err(String::from)
 }
 
 
@@ -80,45 +302,978 @@ pub struct EcoBlockContext {
     pub keypair: CryptoKeypair,
     pub gossip_engine: GossipEngine,
     pub mesh: TopologyGraph,
+    pub readings: ReadingStore,
+    pub derived: DerivedRegistry,
+    pub windows: WindowSubscriptions,
+    pub thresholds: ThresholdMonitor,
+    pub records: RecordLog,
+    pub silence: SilenceMonitor,
+    pub inventory: InventoryState,
+    pub channels: ChannelRegistry,
+    pub group_members: GroupMembers,
+    pub roster: Roster,
+    pub rate_rules: RateValidator,
+    pub pow: PowGate,
+    pub probation: ProbationPool,
+    pub policy: PolicyState,
+    pub logs: LogBuffer,
+    pub retired_keys: HashSet<String>,
+    pub address_book: AddressBook,
+    pub group_policies: GroupPolicies,
+    pub chaos: Option<ChaosInjector>,
+    pub index_builder: IndexBuilder,
+    pub block_log: BlockLog,
+    pub block_store: Option<BlockStore>,
+    /// Canonical signatures of held blocks, by block id.
+    pub block_signatures: HashMap<String, String>,
+    pub checkpoints: Vec<Checkpoint>,
+    pub payloads: PayloadStore,
+    pub routing: RoutingConfig,
+    pub have_lists: HaveLists,
+    pub congestion: CongestionControl,
+    pub metrics_history: MetricsHistory,
+    pub batches: Batches,
+    pub shared_config: SharedConfig,
+    pub lifecycle: Lifecycle,
+    pub changes: ChangeFeed,
+    pub suites: SuiteRegistry,
+    pub hybrid: HybridState,
+    pub encoding: EncodingPolicy,
+    pub versions: VersionState,
+    pub log_stream: LogStream,
+    pub watchdog: Watchdog,
+    pub config: BridgeConfig,
+    pub prefetch: Prefetcher,
+    pub events: EventBus,
+    pub sparkplug: SparkplugState,
+    pub lorawan: LorawanState,
+    pub sync: SyncState,
+    pub home_assistant: HomeAssistantExport,
+    pub geofences: GeofenceState,
+    pub data_mule: DataMuleState,
+    pub encounter_latencies: LatencyLog,
+    pub relay: RelayState,
+    pub wake: WakeState,
+    pub energy_coefficients: EnergyCoefficients,
+    pub power: PowerManager,
+    pub quiet_hours: QuietHours,
+    pub airtime: AirtimeState,
+    pub source_routing: SourceRouting,
+    pub deadlines: DeliveryDeadlines,
+    pub rejections: Rejections,
+    pub explorer: ExplorerState,
+    pub saved_queries: SavedQueries,
+    pub materialized_views: MaterializedViews,
+    pub gateway: Gateway,
+    /// Answers of the HTTP endpoints, kept until the tangle changes.
+    pub responses: ResponseCache,
+    pub s3_backups: S3Backups,
+    pub cloud_sync: CloudSync,
+    /// Where time-bounded maintenance and sync slices resume.
+    pub maintenance: MaintenanceProgress,
+    pub flow_stats: FlowStats,
+    pub network_stats: NetworkStats,
+    pub topology_log: TopologyLog,
+    /// Work waiting for the host, kept in the node directory.
+    pub jobs: JobQueue,
+    pub multicasts: Multicasts,
+    #[cfg(feature = "presentation")]
+    pub metric_formats: MetricFormats,
+    #[cfg(feature = "scripting")]
+    pub scripts: Scripts,
+    /// Sends the requests of S3 backups and cloud sync; set by the host.
+    pub http_client: Option<Box<dyn HttpClient>>,
+    /// Node directory the context was initialized from by `init_context`.
+    pub node_path: Option<PathBuf>,
+    /// Held while the context is bound to `node_path`.
+    pub node_lock: Option<NodeLock>,
+    #[cfg(unix)]
+    pub ipc: IpcHost,
+    pending: Vec<PendingCallback>,
+}
+
+type PendingCallback = Box<dyn FnOnce() + Send>;
+
+impl Default for EcoBlockContext {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl EcoBlockContext {
+    /// A context with a throwaway keypair, until `init_context` binds one to a node.
     pub fn new() -> Self {
+        Self::with_keypair(CryptoKeypair::generate())
+    }
+
+    pub fn with_keypair(keypair: CryptoKeypair) -> Self {
         Self {
             tangle: Tangle::new(),
-            keypair: CryptoKeypair::generate(),
+            keypair,
             gossip_engine: GossipEngine::new(),
             mesh: TopologyGraph::new(),
+            readings: ReadingStore::new(),
+            derived: DerivedRegistry::new(),
+            windows: WindowSubscriptions::new(),
+            thresholds: ThresholdMonitor::new(),
+            records: RecordLog::new(),
+            silence: SilenceMonitor::new(),
+            inventory: InventoryState::new(),
+            channels: ChannelRegistry::new(),
+            group_members: GroupMembers::new(),
+            roster: Roster::new(),
+            rate_rules: RateValidator::new(),
+            pow: PowGate::new(),
+            probation: ProbationPool::new(),
+            policy: PolicyState::new(),
+            logs: LogBuffer::default(),
+            retired_keys: HashSet::new(),
+            address_book: AddressBook::new(),
+            group_policies: GroupPolicies::new(),
+            chaos: None,
+            index_builder: IndexBuilder::new(),
+            block_log: BlockLog::new(),
+            block_store: None,
+            block_signatures: HashMap::new(),
+            checkpoints: Vec::new(),
+            payloads: PayloadStore::new(),
+            routing: RoutingConfig::default(),
+            have_lists: HaveLists::new(),
+            congestion: CongestionControl::new(),
+            metrics_history: MetricsHistory::new(),
+            batches: Batches::new(),
+            shared_config: SharedConfig::new(),
+            lifecycle: Lifecycle::new(),
+            changes: ChangeFeed::new(),
+            suites: SuiteRegistry::new(),
+            hybrid: HybridState::new(),
+            encoding: EncodingPolicy::default(),
+            versions: VersionState::new(),
+            log_stream: LogStream::new(),
+            watchdog: Watchdog::new(),
+            config: BridgeConfig::default(),
+            prefetch: Prefetcher::new(),
+            events: EventBus::new(),
+            sparkplug: SparkplugState::new(),
+            lorawan: LorawanState::new(),
+            sync: SyncState::new(),
+            home_assistant: HomeAssistantExport::new(),
+            geofences: GeofenceState::new(),
+            data_mule: DataMuleState::new(),
+            encounter_latencies: LatencyLog::new(),
+            relay: RelayState::new(),
+            wake: WakeState::new(),
+            energy_coefficients: EnergyCoefficients::default(),
+            power: PowerManager::new(),
+            quiet_hours: QuietHours::new(),
+            airtime: AirtimeState::new(),
+            source_routing: SourceRouting::new(),
+            deadlines: DeliveryDeadlines::new(),
+            rejections: Rejections::new(),
+            explorer: ExplorerState::new(),
+            saved_queries: SavedQueries::new(),
+            materialized_views: MaterializedViews::new(),
+            gateway: Gateway::new(),
+            responses: ResponseCache::new(),
+            s3_backups: S3Backups::new(),
+            cloud_sync: CloudSync::new(),
+            maintenance: MaintenanceProgress::new(),
+            flow_stats: FlowStats::new(),
+            network_stats: NetworkStats::new(),
+            topology_log: TopologyLog::new(),
+            jobs: JobQueue::new(),
+            multicasts: Multicasts::new(),
+            #[cfg(feature = "presentation")]
+            metric_formats: MetricFormats::new(),
+            #[cfg(feature = "scripting")]
+            scripts: Scripts::new(),
+            http_client: None,
+            node_path: None,
+            node_lock: None,
+            #[cfg(unix)]
+            ipc: IpcHost::new(),
+            pending: Vec::new(),
         }
     }
 
-    pub fn create_block(&mut self, data: Vec<u8>, parents: Vec<String>) -> String {
-        let sensor_data: SensorData = match serde_json::from_slice(&data) {
-            Ok(d) => d,
-            Err(e) => return format!("Erreur de désérialisation SensorData: {}", e),
-        };
+    pub fn try_create_block(&mut self, data: Vec<u8>, parents: Vec<BlockId>) -> Result<BlockId, BridgeError> {
+        let prepared = self.prepare_block(data, parents).map_err(|e| self.reject_block(e))?;
+        if self.chaos.as_mut().is_some_and(|c| c.fail_write()) {
+            let error = BridgeError::Io("Erreur de stockage: échec d'écriture injecté".to_string());
+            return Err(self.reject_block(error));
+        }
+        Ok(self.commit_block(prepared))
+    }
+
+    /// Validates and signs a block without storing it.
+    pub(crate) fn prepare_block(&self, data: Vec<u8>, parents: Vec<BlockId>) -> Result<PreparedBlock, BridgeError> {
+        let (sensor_data, payload) = preflight::decode_block_input(&data)?;
+        self.prepare_sensor_block(sensor_data, payload, parents)
+    }
+
+    /// Validates and signs a block whose data and indexed payload are already
+    /// decoded.
+    pub(crate) fn prepare_sensor_block(&self, sensor_data: SensorData, payload: serde_json::Value, parents: Vec<BlockId>) -> Result<PreparedBlock, BridgeError> {
+        let violations = self.check_rates(&payload);
+        if let Some(v) = violations.iter().find(|v| v.action == RateAction::Reject) {
+            return Err(BridgeError::Rejected(format!("Bloc rejeté: {}", v)));
+        }
         let block_data = TangleBlockData {
-            parents,
+            parents: parents.into_iter().map(String::from).collect(),
             data: sensor_data,
         };
-        let block = TangleBlock::new(block_data, &self.keypair);
+        self.check_block_size(&block_data)?;
+        self.check_block_author()?;
+        let block = {
+            energy::count_signature();
+            TangleBlock::new(block_data, &self.keypair)
+        };
+        Ok(PreparedBlock {
+            signature: self.canonical_block_signature(&block),
+            block,
+            payload,
+            violations,
+        })
+    }
+
+    /// Stores, indexes and propagates a prepared block.
+    pub(crate) fn commit_block(&mut self, prepared: PreparedBlock) -> BlockId {
+        let PreparedBlock { block, signature, payload, violations } = prepared;
         let id = block.id.clone();
-        self.tangle.insert(block.clone()).ok();
+        if let Some(signature) = signature {
+            self.block_signatures.insert(id.clone(), signature);
+        }
+        let inserted = self.store_block(block.clone());
+        let event = BridgeEvent::BlockInserted {
+            id: BlockId::from_storage(id.clone()),
+            parents: block.data.parents.iter().cloned().map(BlockId::from_storage).collect(),
+            timestamp: block.data.data.timestamp,
+        };
+        if inserted {
+            self.emit_event(event.clone());
+        }
+        if !violations.is_empty() {
+            let comment = violations.iter().map(|v| v.to_string()).collect::<Vec<_>>().join("; ");
+            match self.sign_record(FLAG_KIND, flags::body(&BlockId::from_storage(id.clone()), FlagReason::OutOfRange, Some(comment)), clock::now_secs()) {
+                Ok(record) => {
+                    self.readings.flag(&id);
+                    self.records.insert(record);
+                }
+                Err(e) => self.log(LogLevel::Warn, format!("Out-of-range flag for block {} not signed: {}", id, e)),
+            }
+        }
+        let signer = self.keypair.public_key_hex();
+        self.index_reading(&id, &signer, &payload);
+        if inserted {
+            // Geofenced subscribers need the indexed reading's location.
+            self.emit_geofenced(&event);
+        }
         self.gossip_engine.propagate_block(&block);
-        id
+        self.gossip_block(&block, None);
+        BlockId::from_storage(id)
+    }
+
+    pub fn check_rates(&self, payload: &serde_json::Value) -> Vec<RateViolation> {
+        let reading = Reading::from_payload("", &self.keypair.public_key_hex(), payload, clock::now_secs());
+        self.rate_rules
+            .check(&reading, |sensor, metric| self.readings.last_value(sensor, metric))
+    }
+
+    /// Adds a block to the tangle and block log, sharing its payload bytes with
+    /// any identical payload already stored. Returns false for duplicates.
+    pub fn store_block(&mut self, block: TangleBlock) -> bool {
+        if self.block_log.contains(&block.id) || self.lifecycle.is_redacted(&block) {
+            return false;
+        }
+        if let Ok(bytes) = serde_json::to_vec(&block.data.data) {
+            self.payloads.retain(&bytes).ok();
+        }
+        self.tangle.insert(block.clone()).ok();
+        let id = block.id.clone();
+        self.persist_insert(&block);
+        let inserted = self.block_log.insert(block);
+        if inserted {
+            self.record_change(ChangeKind::BlockInserted, id);
+        }
+        inserted
+    }
+
+    pub(crate) fn index_reading(&mut self, block_id: &str, signer: &str, payload: &serde_json::Value) {
+        let mut reading = Reading::from_payload(block_id, signer, payload, clock::now_secs());
+        self.derived.apply_on_ingest(&mut reading);
+        if !self.readings.is_flagged(block_id) {
+            self.update_windows(&reading);
+            self.evaluate_thresholds(&reading);
+            self.update_materialized_views(&reading);
+        }
+        let now = clock::now_secs();
+        let mut silence_events: Vec<SilenceEvent> = self.silence.on_reading(&reading.sensor_id, reading.timestamp, now).into_iter().collect();
+        silence_events.extend(self.silence.check(now));
+        self.queue_silence_events(silence_events);
+        #[cfg(feature = "scripting")]
+        let event = ScriptEvent::Block {
+            block_id: block_id.to_string(),
+            signer: signer.to_string(),
+            sensor_id: reading.sensor_id.clone(),
+            timestamp: reading.timestamp,
+            values: reading.values.clone(),
+        };
+        self.readings.insert(reading);
+        #[cfg(feature = "scripting")]
+        self.run_scripts(event);
+        if self.inventory.is_due(now) {
+            if let Err(e) = self.publish_inventory() {
+                self.log(LogLevel::Warn, format!("Inventory not published: {}", e));
+            }
+        }
+        if self.versions.is_due(now) {
+            if let Err(e) = self.publish_version() {
+                self.log(LogLevel::Warn, format!("Version not published: {}", e));
+            }
+        }
+        self.sample_metrics().ok();
+    }
+
+    fn update_windows(&mut self, reading: &Reading) {
+        let (readings, derived) = (&self.readings, &self.derived);
+        let due = self.windows.on_reading(
+            reading.timestamp,
+            |metric| derived.value_of(reading, metric).is_some(),
+            |metric, from, to| {
+                let mut samples = derived.series(readings, metric, from, to);
+                if let Some(v) = derived.value_of(reading, metric) {
+                    samples.push((reading.timestamp, v));
+                }
+                samples
+            },
+        );
+        for (callback, aggregate) in due {
+            self.pending.push(Box::new(move || callback(aggregate)));
+        }
+    }
+
+    pub fn local_inventory(&self) -> NodeInventory {
+        NodeInventory {
+            node_id: self.keypair.public_key_hex(),
+            published_at: clock::now_secs(),
+            sensors: inventory::summarize(&self.readings),
+        }
+    }
+
+    /// Signs the local sensor summary as an `inventory` record for gossip.
+    pub fn publish_inventory(&mut self) -> Result<Record, BridgeError> {
+        let local = self.local_inventory();
+        let now = local.published_at;
+        let body = serde_json::to_value(&local).unwrap_or_default();
+        let record = self.sign_record("inventory", body, now)?;
+        self.records.insert(record.clone());
+        self.inventory.mark_published(now);
+        self.gossip_record(&record);
+        Ok(record)
+    }
+
+    /// Verifies and stores a record received from a peer.
+    pub fn receive_record(&mut self, record: Record) -> Result<bool, BridgeError> {
+        if self.chaos.as_mut().is_some_and(|c| c.drop_message()) {
+            return Ok(false);
+        }
+        if !self.verify_record(&record) {
+            return Err(BridgeError::InvalidInput(format!("Invalid signature on record {}", record.id)));
+        }
+        if !self.suites.accepts(record.suite) {
+            return Err(BridgeError::Crypto(format!(
+                "record {} uses suite {}, not accepted on network {}",
+                record.id,
+                record.suite.name(),
+                self.suites.network_id()
+            )));
+        }
+        if self.retired_keys.contains(&record.author) {
+            return Err(BridgeError::Unauthorized(format!("key {} has been retired", record.author)));
+        }
+        if record.kind == ANNOUNCEMENT_KIND {
+            let body: AnnouncementBody = serde_json::from_value(record.body.clone())
+                .map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+            if let Some(network) = body.network_id.as_deref().filter(|n| *n != self.suites.network_id()) {
+                return Err(BridgeError::Unauthorized(format!("{} announced network {}", record.author, network)));
+            }
+            self.pow.admit(&record.author, &body)?;
+            if let Some(key) = &body.pq_key {
+                if self.hybrid.key_of(&record.author).is_none() {
+                    self.hybrid.register(&record.author, key);
+                }
+            }
+            if !body.suites.is_empty() {
+                self.suites.record_peer(&record.author, body.suites);
+            }
+            if let Some(metrics) = body.relay {
+                self.relay.receive(&record.author, record.timestamp, metrics);
+            }
+        } else if !self.is_known_author(&record.author) {
+            return Err(BridgeError::Unauthorized(format!("unknown key {} has not announced itself with proof of work", record.author)));
+        }
+        if record.kind == ENDORSEMENT_KIND {
+            let body: EndorsementBody = serde_json::from_value(record.body.clone())
+                .map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+            let released = self.probation.endorse(&record.author, &body.key)?;
+            let inserted = self.records.insert(record);
+            self.apply_released(released)?;
+            return Ok(inserted);
+        }
+        if self.probation.should_hold(&record.author, &self.keypair.public_key_hex()) {
+            self.probation.hold(record);
+            return Ok(false);
+        }
+        let mut channel = None;
+        match record.kind.as_str() {
+            "inventory" => {
+                let inventory: NodeInventory = serde_json::from_value(record.body.clone())
+                    .map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+                if inventory.node_id != record.author {
+                    return Err(BridgeError::Unauthorized(format!("Inventory record {} not signed by its node", record.id)));
+                }
+                self.inventory.receive(inventory);
+            }
+            ANNOTATION_KIND => {
+                Annotation::try_from(&record)?;
+            }
+            CHANNEL_MESSAGE_KIND => {
+                let body: ChannelMessageBody = serde_json::from_value(record.body.clone())
+                    .map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+                if !self.roster.is_authorized(&body.channel, &record.author, Role::Member) {
+                    return Err(BridgeError::Unauthorized(format!("{} is not a member of {}", record.author, body.channel)));
+                }
+                if body.channel == OPS_CHANNEL {
+                    self.apply_ops_message(&record, &body)?;
+                }
+                channel = Some(body.channel);
+            }
+            GROUP_KEY_KIND => {
+                let body: GroupKeyBody = serde_json::from_value(record.body.clone())
+                    .map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+                if !self.roster.is_authorized(&body.channel, &record.author, Role::Admin) {
+                    return Err(BridgeError::Unauthorized(format!("{} is not an admin of {}", record.author, body.channel)));
+                }
+                self.install_group_key(&body)?;
+            }
+            INVITATION_KIND => {
+                self.roster.apply_invitation(&record)?;
+            }
+            ACCEPTANCE_KIND => {
+                let member = self.roster.apply_acceptance(&record)?;
+                let scope = serde_json::from_value::<AcceptanceBody>(record.body.clone())
+                    .map(|b| b.scope)
+                    .unwrap_or_default();
+                self.share_group_keys(&scope, &member)?;
+            }
+            REVOCATION_KIND => {
+                let body = self.roster.apply_revocation(&record)?;
+                self.revoke_group_keys(&body.scope, &body.member)?;
+            }
+            SHARED_CONFIG_KIND => {
+                self.apply_shared_config(&record)?;
+            }
+            REDACTION_KIND => {
+                self.apply_redaction(&record)?;
+            }
+            FLAG_KIND => {
+                let flag = Flag::try_from(&record)?;
+                self.readings.flag(flag.block_id.as_str());
+                self.materialized_views.invalidate();
+            }
+            POLICY_KIND => {
+                self.apply_policy_record(&record)?;
+            }
+            POLICY_STATUS_KIND => {
+                self.record_policy_status(&record);
+            }
+            HANDOVER_KIND => {
+                self.apply_handover(&record)?;
+            }
+            RETIREMENT_KIND => {
+                self.apply_retirement(&record)?;
+            }
+            DIAGNOSTICS_REQUEST_KIND => {
+                self.answer_diagnostics(&record)?;
+            }
+            VERSION_KIND => {
+                self.apply_version(&record)?;
+            }
+            GEOFENCE_KIND => {
+                self.apply_geofence(&record)?;
+            }
+            HAVE_KIND => {
+                // Advertisements are transient and not kept in the record log.
+                self.apply_have(&record)?;
+                return Ok(false);
+            }
+            REJECTION_KIND => {
+                self.apply_rejection(&record)?;
+                return Ok(false);
+            }
+            _ => {}
+        }
+        let message = channel.map(|channel| (channel, record.author.clone(), record.timestamp));
+        let inserted = self.records.insert(record);
+        if let Some((channel, author, timestamp)) = message.filter(|_| inserted) {
+            self.flow_stats.channel_message(&channel, &author, timestamp, clock::now_secs());
+        }
+        Ok(inserted)
+    }
+
+    pub fn dashboard_summary(&self) -> DashboardSummary {
+        let now = clock::now_secs();
+        let node_id = self.keypair.public_key_hex();
+        let latest = dashboard::latest_values(self.readings.iter().flat_map(|r| {
+            r.values
+                .iter()
+                .map(move |(metric, value)| (r.sensor_id.as_str(), metric.as_str(), *value, r.timestamp))
+        }));
+        let mut metrics: Vec<String> = latest.iter().map(|l| l.metric.clone()).collect();
+        metrics.sort();
+        metrics.dedup();
+        let from = now.saturating_sub(dashboard::DAY_SECS);
+        let last_24h = metrics
+            .iter()
+            .map(|m| MetricRange::from(self.aggregate_metric(m, from, now)))
+            .filter(|r| r.count > 0)
+            .collect();
+        let peers = self
+            .mesh
+            .get_neighbors(&node_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(peer_id, link_weight)| PeerHealth {
+                last_seen: self.records.latest_from(&peer_id),
+                peer_id: PeerId::from_mesh(peer_id),
+                link_weight,
+            })
+            .collect();
+        DashboardSummary {
+            generated_at: now,
+            tangle_size: self.tangle_size(),
+            latest,
+            last_24h,
+            active_alerts: self.thresholds.active_alerts(),
+            silent_sensors: self.silence.silent_sensors(),
+            peers,
+            node_id,
+        }
+    }
+
+    pub fn annotate_block(&mut self, block_id: &BlockId, note: &str) -> Result<Annotation, BridgeError> {
+        if !self.readings.contains(block_id.as_str()) {
+            return Err(BridgeError::NotFound(format!("Unknown block: {}", block_id)));
+        }
+        let record = self.sign_record(ANNOTATION_KIND, annotations::body(block_id, note), clock::now_secs())?;
+        let annotation = Annotation::try_from(&record)?;
+        self.records.insert(record);
+        Ok(annotation)
+    }
+
+    pub fn annotations_for(&self, block_id: &BlockId) -> Vec<Annotation> {
+        self.records
+            .of_kind(ANNOTATION_KIND)
+            .filter_map(|r| Annotation::try_from(r).ok())
+            .filter(|a| &a.block_id == block_id)
+            .collect()
+    }
+
+    pub fn flag_block(&mut self, block_id: &BlockId, reason: FlagReason, comment: Option<String>) -> Result<Flag, BridgeError> {
+        if !self.readings.contains(block_id.as_str()) {
+            return Err(BridgeError::NotFound(format!("Unknown block: {}", block_id)));
+        }
+        let record = self.sign_record(FLAG_KIND, flags::body(block_id, reason, comment), clock::now_secs())?;
+        let flag = Flag::try_from(&record)?;
+        self.readings.flag(block_id.as_str());
+        self.materialized_views.invalidate();
+        self.records.insert(record);
+        Ok(flag)
+    }
+
+    pub fn list_flagged(&self) -> Vec<Flag> {
+        self.records
+            .of_kind(FLAG_KIND)
+            .filter_map(|r| Flag::try_from(r).ok())
+            .collect()
+    }
+
+    /// Publishes `data` on a channel, encrypted with the group key of the channel's tier unless it is public.
+    pub fn publish_to_channel(&mut self, channel: &str, data: &[u8]) -> Result<String, BridgeError> {
+        if !self.roster.is_authorized(channel, &self.keypair.public_key_hex(), Role::Member) {
+            return Err(BridgeError::Unauthorized(format!("local node is not a member of {}", channel)));
+        }
+        let data: serde_json::Value = serde_json::from_slice(data).map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+        let payload = self.channels.seal(channel, data)?;
+        let body = ChannelMessageBody {
+            channel: channel.to_string(),
+            payload,
+        };
+        let body = serde_json::to_value(&body).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        let now = clock::now_secs();
+        let record = self.sign_record(CHANNEL_MESSAGE_KIND, body, now)?;
+        let id = record.id.clone();
+        self.flow_stats.channel_message(channel, &record.author, now, now);
+        self.records.insert(record);
+        Ok(id)
+    }
+
+    pub fn read_channel(&self, channel: &str) -> Vec<ChannelMessage> {
+        self.records
+            .of_kind(CHANNEL_MESSAGE_KIND)
+            .filter_map(|r| {
+                let body: ChannelMessageBody = serde_json::from_value(r.body.clone()).ok()?;
+                if body.channel != channel {
+                    return None;
+                }
+                let visibility = match &body.payload {
+                    ChannelPayload::Plain(_) => Visibility::Public,
+                    ChannelPayload::Sealed(sealed) => sealed.tier,
+                };
+                Some(ChannelMessage {
+                    id: r.id.clone(),
+                    channel: body.channel.clone(),
+                    author: r.author.clone(),
+                    timestamp: r.timestamp,
+                    visibility,
+                    data: self.channels.open(&body.payload).ok(),
+                })
+            })
+            .collect()
+    }
+
+    /// Starts a new key epoch for `channel`/`tier` and seals it to every member and the local node.
+    pub fn rotate_group_key(&mut self, channel: &str, tier: Visibility) -> Result<Record, BridgeError> {
+        let (epoch, key) = self.channels.rotate_key(channel, tier);
+        let mut recipients = self.group_members.members(channel, tier);
+        let me = self.keypair.public_key_hex();
+        if !recipients.contains(&me) {
+            recipients.push(me);
+        }
+        self.group_key_record(channel, tier, epoch, &key, &recipients)
+    }
+
+    fn group_key_record(&mut self, channel: &str, tier: Visibility, epoch: u32, key: &[u8; 32], recipients: &[String]) -> Result<Record, BridgeError> {
+        let wrapped = recipients
+            .iter()
+            .map(|recipient| {
+                Ok(WrappedKey {
+                    recipient: recipient.clone(),
+                    sealed: hex::encode(sealed::seal_to(recipient, key)?),
+                })
+            })
+            .collect::<Result<Vec<_>, BridgeError>>()?;
+        let body = GroupKeyBody {
+            channel: channel.to_string(),
+            tier,
+            epoch,
+            wrapped,
+        };
+        let body = serde_json::to_value(&body).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        let record = self.sign_record(GROUP_KEY_KIND, body, clock::now_secs())?;
+        self.records.insert(record.clone());
+        Ok(record)
+    }
+
+    pub fn create_group_key(&mut self, channel: &str, tier: Visibility, members: Vec<String>) -> Result<Record, BridgeError> {
+        self.group_members.set(channel, tier, members);
+        self.rotate_group_key(channel, tier)
+    }
+
+    /// Seals the current epoch key to a new member; earlier epochs stay unreadable to them.
+    pub fn add_group_member(&mut self, channel: &str, tier: Visibility, member: &str) -> Result<Record, BridgeError> {
+        self.group_members.add(channel, tier, member);
+        let epoch = self
+            .channels
+            .current_epoch(channel, tier)
+            .ok_or_else(|| BridgeError::NotFound(format!("No group key for channel {}", channel)))?;
+        let key = self.channels.key(channel, tier, epoch).unwrap_or_default();
+        self.group_key_record(channel, tier, epoch, &key, &[member.to_string()])
+    }
+
+    /// Removing a member rotates the key so they cannot read anything published afterwards.
+    pub fn remove_group_member(&mut self, channel: &str, tier: Visibility, member: &str) -> Result<Record, BridgeError> {
+        if !self.group_members.remove(channel, tier, member) {
+            return Err(BridgeError::NotFound(format!("{} is not a member of channel {}", member, channel)));
+        }
+        self.rotate_group_key(channel, tier)
+    }
+
+    fn install_group_key(&mut self, body: &GroupKeyBody) -> Result<(), BridgeError> {
+        let me = self.keypair.public_key_hex();
+        let Some(wrapped) = body.wrapped.iter().find(|w| w.recipient == me) else {
+            return Ok(());
+        };
+        let sealed = hex::decode(&wrapped.sealed).map_err(|e| BridgeError::Decoding(e.to_string()))?;
+        let key: [u8; 32] = sealed::open_sealed(&self.keypair, &sealed)?
+            .try_into()
+            .map_err(|_| BridgeError::Crypto("group key must be 32 bytes".to_string()))?;
+        self.channels.install_key(&body.channel, body.tier, body.epoch, key);
+        Ok(())
+    }
+
+    /// Signs an invitation to `scope`; the local node becomes its first admin if it has no roster yet.
+    pub fn invite_member(&mut self, scope: &str, role: Role, invitee: Option<String>, ttl: u64) -> Result<Record, BridgeError> {
+        let me = self.keypair.public_key_hex();
+        let now = clock::now_secs();
+        self.roster.bootstrap(scope, &me, now);
+        if !self.roster.is_authorized(scope, &me, Role::Admin) {
+            return Err(BridgeError::Unauthorized(format!("local node is not an admin of {}", scope)));
+        }
+        let body = InvitationBody {
+            scope: scope.to_string(),
+            role,
+            invitee,
+            expires_at: now + ttl,
+        };
+        let body = serde_json::to_value(&body).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        let record = self.sign_record(INVITATION_KIND, body, now)?;
+        self.roster.apply_invitation(&record)?;
+        self.records.insert(record.clone());
+        Ok(record)
+    }
+
+    /// Accepts an invitation received out of band or over gossip, returning the acceptance to send back.
+    pub fn accept_invitation(&mut self, invitation: Record) -> Result<Record, BridgeError> {
+        let invitation_id = invitation.id.clone();
+        if self.records.get(&invitation_id).is_none() {
+            self.receive_record(invitation)?;
+        }
+        let (_, body) = self
+            .roster
+            .invitation(&invitation_id)
+            .ok_or_else(|| BridgeError::NotFound(format!("Unknown invitation: {}", invitation_id)))?;
+        let acceptance = AcceptanceBody {
+            invitation_id,
+            scope: body.scope,
+        };
+        let acceptance = serde_json::to_value(&acceptance).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        let record = self.sign_record(ACCEPTANCE_KIND, acceptance, clock::now_secs())?;
+        self.roster.apply_acceptance(&record)?;
+        self.records.insert(record.clone());
+        Ok(record)
+    }
+
+    pub fn revoke_member(&mut self, scope: &str, member: &str) -> Result<Record, BridgeError> {
+        let body = RevocationBody {
+            scope: scope.to_string(),
+            member: member.to_string(),
+        };
+        let body = serde_json::to_value(&body).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        let record = self.sign_record(REVOCATION_KIND, body, clock::now_secs())?;
+        self.roster.apply_revocation(&record)?;
+        self.records.insert(record.clone());
+        self.revoke_group_keys(scope, member)?;
+        Ok(record)
+    }
+
+    /// When the local node administers a channel, hands existing group keys to a new member.
+    fn share_group_keys(&mut self, scope: &str, member: &Member) -> Result<(), BridgeError> {
+        let me = self.keypair.public_key_hex();
+        if self.roster.role_of(scope, &me) != Some(Role::Admin) {
+            return Ok(());
+        }
+        let mut tiers = vec![Visibility::Members];
+        if member.role == Role::Admin {
+            tiers.push(Visibility::Admins);
+        }
+        for tier in tiers {
+            if self.channels.current_epoch(scope, tier).is_some() {
+                self.add_group_member(scope, tier, &member.public_key)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn revoke_group_keys(&mut self, scope: &str, member: &str) -> Result<(), BridgeError> {
+        let me = self.keypair.public_key_hex();
+        for tier in [Visibility::Members, Visibility::Admins] {
+            let is_member = self.group_members.members(scope, tier).iter().any(|m| m == member);
+            if is_member && self.roster.role_of(scope, &me) == Some(Role::Admin) {
+                self.remove_group_member(scope, tier, member)?;
+            } else if is_member {
+                self.group_members.remove(scope, tier, member);
+            }
+        }
+        Ok(())
+    }
+
+    /// With proof of work disabled every key is known; otherwise a key must have
+    /// announced itself or be a direct mesh neighbour.
+    pub fn is_known_author(&self, author: &str) -> bool {
+        self.pow.difficulty == 0
+            || author == self.keypair.public_key_hex()
+            || self.pow.is_verified(author)
+            || self.list_peers(&PeerId::from_mesh(self.keypair.public_key_hex())).iter().any(|p| p.as_str() == author)
+    }
+
+    /// Solves the current proof of work for the local key and signs an announcement.
+    pub fn announce(&mut self) -> Result<Record, BridgeError> {
+        let difficulty = self.pow.difficulty;
+        let nonce = pow::solve(&self.keypair.public_key_hex(), difficulty);
+        self.sign_announcement(difficulty, nonce)
+    }
+
+    pub fn sign_announcement(&mut self, difficulty: u8, nonce: u64) -> Result<Record, BridgeError> {
+        let body = AnnouncementBody {
+            node_id: self.keypair.public_key_hex(),
+            difficulty,
+            nonce,
+            suites: self.suites.accepted(),
+            network_id: Some(self.suites.network_id().to_string()),
+            pq_key: self.hybrid.local_key(),
+            relay: Some(self.local_relay_metrics()),
+            power: self.power.state(),
+        };
+        let body = serde_json::to_value(&body).unwrap_or_default();
+        let record = self.sign_record(ANNOUNCEMENT_KIND, body, clock::now_secs())?;
+        self.records.insert(record.clone());
+        Ok(record)
+    }
+
+    pub fn endorse_key(&mut self, key: &str) -> Result<Record, BridgeError> {
+        let body = EndorsementBody { key: key.to_string() };
+        let body = serde_json::to_value(&body).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        let record = self.sign_record(ENDORSEMENT_KIND, body, clock::now_secs())?;
+        let released = self.probation.endorse(&record.author, key)?;
+        self.records.insert(record.clone());
+        self.apply_released(released)?;
+        Ok(record)
+    }
+
+    /// Applies what an endorsement let out of probation as if it had just
+    /// arrived: records are applied and blocks stored, indexed and gossiped.
+    fn apply_released(&mut self, released: Released) -> Result<(), BridgeError> {
+        for held in released.records {
+            self.receive_record(held)?;
+        }
+        for held in released.blocks {
+            self.accept_block(held, None)?;
+        }
+        Ok(())
+    }
+
+    pub fn network_inventory(&self, live_within: u64) -> NetworkInventory {
+        self.inventory.aggregate(self.local_inventory(), clock::now_secs(), live_within)
+    }
+
+    fn queue_silence_events(&mut self, events: Vec<SilenceEvent>) {
+        for callback in self.silence.subscribers() {
+            for event in events.iter().cloned() {
+                let callback = callback.clone();
+                self.pending.push(Box::new(move || callback(event)));
+            }
+        }
+    }
+
+    pub fn check_silent_sensors(&mut self) -> Vec<SilenceEvent> {
+        let events = self.silence.check(clock::now_secs());
+        self.queue_silence_events(events.clone());
+        events
+    }
+
+    pub fn data_gaps(&self, sensor_id: &str, expected_interval: u64, from: u64, to: u64) -> Vec<Gap> {
+        let timestamps: Vec<u64> = self
+            .readings
+            .in_range(from, to)
+            .filter(|r| r.sensor_id == sensor_id)
+            .map(|r| r.timestamp)
+            .collect();
+        gaps::find_gaps(sensor_id, &timestamps, expected_interval, from, to)
+    }
+
+    fn evaluate_thresholds(&mut self, reading: &Reading) {
+        for metric in self.thresholds.metrics() {
+            let Some(value) = self.derived.value_of(reading, &metric) else {
+                continue;
+            };
+            let Some(event) = self.thresholds.evaluate(&metric, &reading.sensor_id, &reading.block_id, value, reading.timestamp) else {
+                continue;
+            };
+            if self.thresholds.rule(&metric).is_some_and(|r| r.emit_alert_block) {
+                let body = serde_json::to_value(&event).unwrap_or_default();
+                match self.sign_record("alert", body, event.timestamp) {
+                    Ok(record) => {
+                        self.gossip_record(&record);
+                        self.records.insert(record);
+                    }
+                    Err(e) => self.log(LogLevel::Warn, format!("Alert for {} not published: {}", metric, e)),
+                }
+            }
+            for callback in self.thresholds.subscribers() {
+                let event = event.clone();
+                self.pending.push(Box::new(move || callback(event)));
+            }
+            #[cfg(feature = "scripting")]
+            self.run_scripts(ScriptEvent::Threshold(event));
+        }
+    }
+
+    /// Callbacks queued by the last operations, to be run once the context lock is released.
+    pub fn take_pending(&mut self) -> Vec<Box<dyn FnOnce() + Send>> {
+        std::mem::take(&mut self.pending)
+    }
+
+    pub fn register_derived_metric(&mut self, name: &str, expression: &str, mode: DerivedMode) -> Result<(), BridgeError> {
+        let metric = DerivedMetric::new(name, expression, mode)?;
+        self.derived.register(metric);
+        Ok(())
+    }
+
+    pub fn metric_values(&self, metric: &str, from: u64, to: u64) -> Vec<(u64, f64)> {
+        self.derived.series(&self.readings, metric, from, to)
+    }
+
+    pub fn aggregate_metric(&self, metric: &str, from: u64, to: u64) -> MetricAggregate {
+        self.aggregate_metric_with(metric, from, to, QueryOptions::default())
+    }
+
+    pub fn aggregate_metric_with(&self, metric: &str, from: u64, to: u64, options: QueryOptions) -> MetricAggregate {
+        let values = self
+            .derived
+            .series_with(&self.readings, metric, from, to, options)
+            .into_iter()
+            .map(|(_, v)| v);
+        MetricAggregate::from_values(metric, values)
     }
 
     pub fn tangle_size(&self) -> usize {
         self.tangle.len()
     }
 
-    pub fn add_peer_connection(&mut self, from: &str, to: &str, weight: f32) {
-        self.mesh.add_connection(from, to, weight);
+    pub fn add_peer_connection(&mut self, from: &PeerId, to: &PeerId, weight: f32) {
+        self.add_mesh_connection(from.as_str(), to.as_str(), weight);
+        self.record_change(ChangeKind::ConnectionAdded, format!("{}->{}", from, to));
+        self.emit_event(BridgeEvent::PeerConnected {
+            from: from.clone(),
+            to: to.clone(),
+            weight,
+        });
+        let local = self.keypair.public_key_hex();
+        let peer = if from.as_str() == local { Some(to) } else if to.as_str() == local { Some(from) } else { None };
+        if let Some(peer) = peer.filter(|_| self.sync.is_attached()) {
+            self.sync_with_peer(peer).ok();
+        }
+    }
+
+    pub fn add_address_book_entry(&mut self, node_id: &PeerId, alias: Option<String>) {
+        let kind = if self.address_book.get(node_id.as_str()).is_some() { ChangeKind::PeerUpdated } else { ChangeKind::PeerAdded };
+        self.address_book.add(node_id, alias, clock::now_secs());
+        self.record_change(kind, node_id.as_str());
     }
 
-    pub fn list_peers(&self, peer_id: &str) -> Vec<String> {
-        match self.mesh.get_neighbors(peer_id) {
-            Some(neighbors) => neighbors.into_iter().map(|(id, _)| id).collect(),
+    pub fn remove_address_book_entry(&mut self, node_id: &PeerId) -> bool {
+        let removed = self.address_book.remove(node_id.as_str());
+        if removed {
+            self.record_change(ChangeKind::PeerRemoved, node_id.as_str());
+            self.emit_event(BridgeEvent::PeerRemoved { peer: node_id.clone() });
+        }
+        removed
+    }
+
+    pub fn list_peers(&self, peer_id: &PeerId) -> Vec<PeerId> {
+        match self.mesh.get_neighbors(peer_id.as_str()) {
+            Some(neighbors) => neighbors.into_iter().map(|(id, _)| PeerId::from_mesh(id)).collect(),
             None => vec![],
         }
     }
@@ -128,18 +1283,1869 @@ lazy_static! {
     pub static ref CONTEXT: Mutex<EcoBlockContext> = Mutex::new(EcoBlockContext::new());
 }
 
-pub fn create_block(data: Vec<u8>, parents: Vec<String>) -> String {
-    CONTEXT.lock().unwrap().create_block(data, parents)
+fn run_pending(pending: Vec<PendingCallback>) {
+    for callback in pending {
+        callback();
+    }
+}
+
+/// Runs every check `create_block` would for `data` and `parents` without
+/// signing or storing a block, and reports all problems found.
+pub fn validate_block_input(data: Vec<u8>, parents: Vec<BlockId>) -> ValidationReport {
+    let _call = CallTimer::start("validate_block_input");
+    CONTEXT.lock().unwrap().validate_block_input(&data, &parents)
+}
+
+#[deprecated(since = "0.2.0", note = "returns error text in place of the id; use v2::create_block")]
+pub fn create_block(data: Vec<u8>, parents: Vec<BlockId>) -> String {
+    match v2::create_block(data, parents) {
+        Ok(id) => id.into(),
+        Err(e) => e.to_string(),
+    }
 }
 
 pub fn get_tangle_size() -> usize {
+    let _call = CallTimer::start("get_tangle_size");
     CONTEXT.lock().unwrap().tangle_size()
 }
 
-pub fn add_peer_connection(from: String, to: String, weight: f32) {
-    CONTEXT.lock().unwrap().add_peer_connection(&from, &to, weight);
+pub fn add_peer_connection(from: PeerId, to: PeerId, weight: f32) {
+    let _call = CallTimer::start("add_peer_connection");
+    let pending = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        ctx.add_peer_connection(&from, &to, weight);
+        ctx.take_pending()
+    };
+    run_pending(pending);
+}
+
+pub fn list_peers(peer_id: PeerId) -> Vec<PeerId> {
+    let _call = CallTimer::start("list_peers");
+    CONTEXT.lock().unwrap().cached_peers(&peer_id)
+}
+
+/// The mesh as it stood at `timestamp`, replayed from the topology log.
+pub fn topology_at(timestamp: u64) -> TopologySnapshot {
+    let _call = CallTimer::start("topology_at");
+    CONTEXT.lock().unwrap().topology_log.snapshot(timestamp)
+}
+
+/// Topology changes made from `from` to `to`, both inclusive, oldest first.
+pub fn topology_changes(from: u64, to: u64) -> Vec<TopologyChange> {
+    let _call = CallTimer::start("topology_changes");
+    CONTEXT.lock().unwrap().topology_log.between(from, to)
+}
+
+pub fn register_derived_metric(name: String, expression: String, mode: DerivedMode) -> Result<(), String> {
+    v2::register_derived_metric(name, expression, mode).map_err(String::from)
+}
+
+pub fn remove_derived_metric(name: String) -> bool {
+    let _call = CallTimer::start("remove_derived_metric");
+    CONTEXT.lock().unwrap().derived.remove(&name)
+}
+
+pub fn list_derived_metrics() -> Vec<DerivedMetric> {
+    let _call = CallTimer::start("list_derived_metrics");
+    CONTEXT.lock().unwrap().derived.list()
+}
+
+pub fn aggregate_metric(metric: String, from: u64, to: u64) -> MetricAggregate {
+    let _call = CallTimer::start("aggregate_metric");
+    CONTEXT.lock().unwrap().aggregate_metric(&metric, from, to)
+}
+
+pub fn aggregate_metric_with(metric: String, from: u64, to: u64, options: QueryOptions) -> MetricAggregate {
+    let _call = CallTimer::start("aggregate_metric_with");
+    CONTEXT.lock().unwrap().aggregate_metric_with(&metric, from, to, options)
+}
+
+pub fn subscribe_windowed<F>(metric: String, window: u64, step: u64, callback: F) -> u64
+where
+    F: Fn(WindowedAggregate) + Send + Sync + 'static,
+{
+    let _call = CallTimer::start("subscribe_windowed");
+    let callback: WindowCallback = Arc::new(callback);
+    CONTEXT.lock().unwrap().windows.subscribe(&metric, window, step, callback)
+}
+
+pub fn unsubscribe_windowed(subscription_id: u64) -> bool {
+    let _call = CallTimer::start("unsubscribe_windowed");
+    CONTEXT.lock().unwrap().windows.unsubscribe(subscription_id)
+}
+
+pub fn set_threshold(rule: ThresholdRule) {
+    let _call = CallTimer::start("set_threshold");
+    CONTEXT.lock().unwrap().thresholds.set_rule(rule);
+}
+
+pub fn remove_threshold(metric: String) -> bool {
+    let _call = CallTimer::start("remove_threshold");
+    CONTEXT.lock().unwrap().thresholds.remove_rule(&metric)
+}
+
+pub fn list_thresholds() -> Vec<ThresholdRule> {
+    let _call = CallTimer::start("list_thresholds");
+    CONTEXT.lock().unwrap().thresholds.rules()
+}
+
+pub fn active_alerts() -> Vec<ActiveAlert> {
+    let _call = CallTimer::start("active_alerts");
+    CONTEXT.lock().unwrap().thresholds.active_alerts()
+}
+
+pub fn list_alert_blocks() -> Vec<Record> {
+    let _call = CallTimer::start("list_alert_blocks");
+    CONTEXT.lock().unwrap().records.of_kind("alert").cloned().collect()
+}
+
+pub fn subscribe_threshold_events<F>(callback: F) -> u64
+where
+    F: Fn(ThresholdEvent) + Send + Sync + 'static,
+{
+    let _call = CallTimer::start("subscribe_threshold_events");
+    let callback: ThresholdCallback = Arc::new(callback);
+    CONTEXT.lock().unwrap().thresholds.subscribe(callback)
+}
+
+pub fn unsubscribe_threshold_events(subscription_id: u64) -> bool {
+    let _call = CallTimer::start("unsubscribe_threshold_events");
+    CONTEXT.lock().unwrap().thresholds.unsubscribe(subscription_id)
+}
+
+pub fn data_gaps(sensor_id: String, expected_interval: u64, from: u64, to: u64) -> Vec<Gap> {
+    let _call = CallTimer::start("data_gaps");
+    CONTEXT.lock().unwrap().data_gaps(&sensor_id, expected_interval, from, to)
+}
+
+pub fn set_silence_threshold(seconds: Option<u64>) {
+    let _call = CallTimer::start("set_silence_threshold");
+    CONTEXT.lock().unwrap().silence.set_threshold(seconds);
+}
+
+pub fn check_silent_sensors() -> Vec<SilenceEvent> {
+    let _call = CallTimer::start("check_silent_sensors");
+    let (events, pending) = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        let events = ctx.check_silent_sensors();
+        (events, ctx.take_pending())
+    };
+    run_pending(pending);
+    events
+}
+
+pub fn subscribe_sensor_silence<F>(callback: F) -> u64
+where
+    F: Fn(SilenceEvent) + Send + Sync + 'static,
+{
+    let _call = CallTimer::start("subscribe_sensor_silence");
+    let callback: SilenceCallback = Arc::new(callback);
+    CONTEXT.lock().unwrap().silence.subscribe(callback)
+}
+
+pub fn unsubscribe_sensor_silence(subscription_id: u64) -> bool {
+    let _call = CallTimer::start("unsubscribe_sensor_silence");
+    CONTEXT.lock().unwrap().silence.unsubscribe(subscription_id)
+}
+
+pub fn set_inventory_interval(seconds: u64) {
+    let _call = CallTimer::start("set_inventory_interval");
+    CONTEXT.lock().unwrap().inventory.interval = seconds;
+}
+
+pub fn publish_inventory() -> Result<Record, String> {
+    v2::publish_inventory().map_err(String::from)
+}
+
+pub fn receive_record(record: Record) -> Result<bool, String> {
+    v2::receive_record(record).map_err(String::from)
+}
+
+pub fn network_inventory(live_within: u64) -> NetworkInventory {
+    let _call = CallTimer::start("network_inventory");
+    CONTEXT.lock().unwrap().network_inventory(live_within)
+}
+
+pub fn set_version_interval(seconds: u64) {
+    let _call = CallTimer::start("set_version_interval");
+    CONTEXT.lock().unwrap().versions.interval = seconds;
+}
+
+/// Git hashes of vetted builds; nodes running any other hash are reported as
+/// `Unknown`. An empty list accepts every hash.
+pub fn set_known_builds(git_hashes: Vec<String>) {
+    let _call = CallTimer::start("set_known_builds");
+    CONTEXT.lock().unwrap().versions.known_builds = git_hashes.into_iter().collect();
+}
+
+pub fn publish_version() -> Result<Record, String> {
+    v2::publish_version().map_err(String::from)
+}
+
+pub fn network_versions() -> Vec<NodeVersion> {
+    let _call = CallTimer::start("network_versions");
+    CONTEXT.lock().unwrap().network_versions()
+}
+
+/// How often this node publishes its stats summary on the ops channel; 0
+/// stops publishing.
+pub fn set_stats_interval(seconds: u64) {
+    let _call = CallTimer::start("set_stats_interval");
+    CONTEXT.lock().unwrap().network_stats.interval = seconds;
+}
+
+pub fn publish_stats_summary() -> Result<String, String> {
+    v2::publish_stats_summary().map_err(String::from)
+}
+
+/// The latest stats summary of every node heard on the ops channel, with
+/// network-wide totals.
+pub fn network_overview() -> NetworkOverview {
+    let _call = CallTimer::start("network_overview");
+    CONTEXT.lock().unwrap().network_overview()
+}
+
+pub fn dashboard_summary() -> DashboardSummary {
+    let _call = CallTimer::start("dashboard_summary");
+    CONTEXT.lock().unwrap().cached_dashboard_summary()
+}
+
+pub fn annotate_block(block_id: BlockId, note: String) -> Result<Annotation, String> {
+    v2::annotate_block(block_id, note).map_err(String::from)
+}
+
+pub fn annotations_for(block_id: BlockId) -> Vec<Annotation> {
+    let _call = CallTimer::start("annotations_for");
+    CONTEXT.lock().unwrap().annotations_for(&block_id)
+}
+
+pub fn flag_block(block_id: BlockId, reason: FlagReason, comment: Option<String>) -> Result<Flag, String> {
+    v2::flag_block(block_id, reason, comment).map_err(String::from)
+}
+
+pub fn list_flagged() -> Vec<Flag> {
+    let _call = CallTimer::start("list_flagged");
+    CONTEXT.lock().unwrap().list_flagged()
+}
+
+pub fn set_channel_visibility(channel: String, visibility: Visibility) {
+    let _call = CallTimer::start("set_channel_visibility");
+    CONTEXT.lock().unwrap().channels.set_visibility(&channel, visibility);
+}
+
+pub fn list_channels() -> Vec<(String, Visibility)> {
+    let _call = CallTimer::start("list_channels");
+    CONTEXT.lock().unwrap().channels.channels()
+}
+
+pub fn publish_to_channel(channel: String, data: Vec<u8>) -> Result<String, String> {
+    v2::publish_to_channel(channel, data).map_err(String::from)
+}
+
+pub fn read_channel(channel: String) -> Vec<ChannelMessage> {
+    let _call = CallTimer::start("read_channel");
+    CONTEXT.lock().unwrap().read_channel(&channel)
+}
+
+pub fn create_group_key(channel: String, tier: Visibility, members: Vec<PublicKeyHex>) -> Result<Record, String> {
+    v2::create_group_key(channel, tier, members).map_err(String::from)
+}
+
+pub fn rotate_group_key(channel: String, tier: Visibility) -> Result<Record, String> {
+    v2::rotate_group_key(channel, tier).map_err(String::from)
+}
+
+pub fn add_group_member(channel: String, tier: Visibility, member: PublicKeyHex) -> Result<Record, String> {
+    v2::add_group_member(channel, tier, member).map_err(String::from)
+}
+
+pub fn remove_group_member(channel: String, tier: Visibility, member: PublicKeyHex) -> Result<Record, String> {
+    v2::remove_group_member(channel, tier, member).map_err(String::from)
+}
+
+pub fn list_group_members(channel: String, tier: Visibility) -> Vec<PublicKeyHex> {
+    let _call = CallTimer::start("list_group_members");
+    let members = CONTEXT.lock().unwrap().group_members.members(&channel, tier);
+    members.iter().filter_map(|m| PublicKeyHex::parse(m).ok()).collect()
+}
+
+pub fn invite_member(scope: String, role: Role, invitee: Option<PublicKeyHex>, ttl: u64) -> Result<Record, String> {
+    v2::invite_member(scope, role, invitee, ttl).map_err(String::from)
+}
+
+pub fn accept_invitation(invitation: Record) -> Result<Record, String> {
+    v2::accept_invitation(invitation).map_err(String::from)
+}
+
+pub fn revoke_member(scope: String, member: PublicKeyHex) -> Result<Record, String> {
+    v2::revoke_member(scope, member).map_err(String::from)
+}
+
+pub fn list_members(scope: String) -> Vec<Member> {
+    let _call = CallTimer::start("list_members");
+    CONTEXT.lock().unwrap().roster.members(&scope)
+}
+
+pub fn set_rate_rule(rule: RateRule) {
+    let _call = CallTimer::start("set_rate_rule");
+    CONTEXT.lock().unwrap().rate_rules.set_rule(rule);
+}
+
+pub fn remove_rate_rule(metric: String) -> bool {
+    let _call = CallTimer::start("remove_rate_rule");
+    CONTEXT.lock().unwrap().rate_rules.remove_rule(&metric)
+}
+
+pub fn list_rate_rules() -> Vec<RateRule> {
+    let _call = CallTimer::start("list_rate_rules");
+    CONTEXT.lock().unwrap().rate_rules.rules()
+}
+
+pub fn set_pow_difficulty(bits: u8) -> Result<(), String> {
+    v2::set_pow_difficulty(bits).map_err(String::from)
+}
+
+pub fn announce() -> Result<Record, String> {
+    v2::announce().map_err(String::from)
+}
+
+pub fn set_probation_policy(policy: ProbationPolicy) {
+    let _call = CallTimer::start("set_probation_policy");
+    CONTEXT.lock().unwrap().probation.set_policy(policy);
+}
+
+/// Sensor blocks held back from keys on probation, in arrival order.
+pub fn list_probation_blocks() -> Vec<TangleBlock> {
+    let _call = CallTimer::start("list_probation_blocks");
+    CONTEXT.lock().unwrap().probation.held_blocks()
+}
+
+/// Records held back from keys on probation, in arrival order.
+pub fn list_probation_records() -> Vec<Record> {
+    let _call = CallTimer::start("list_probation_records");
+    CONTEXT.lock().unwrap().probation.held()
+}
+
+pub fn endorse_key(key: PublicKeyHex) -> Result<Record, String> {
+    v2::endorse_key(key).map_err(String::from)
+}
+
+pub fn publish_policy(document: PolicyDocument) -> Result<Record, String> {
+    v2::publish_policy(document).map_err(String::from)
+}
+
+pub fn current_policy() -> Option<AppliedPolicy> {
+    let _call = CallTimer::start("current_policy");
+    CONTEXT.lock().unwrap().policy.current().cloned()
+}
+
+pub fn set_policy_bootstrap_keys(keys: Vec<PublicKeyHex>) {
+    let _call = CallTimer::start("set_policy_bootstrap_keys");
+    CONTEXT.lock().unwrap().policy.set_bootstrap_keys(keys.into_iter().map(String::from).collect());
+}
+
+pub fn policy_trial() -> Option<PolicyTrial> {
+    let _call = CallTimer::start("policy_trial");
+    CONTEXT.lock().unwrap().policy.trial().cloned()
+}
+
+pub fn check_policy_trial() -> TrialOutcome {
+    let _call = CallTimer::start("check_policy_trial");
+    CONTEXT.lock().unwrap().check_policy_trial()
+}
+
+pub fn request_diagnostics(target: PublicKeyHex) -> Result<Record, String> {
+    v2::request_diagnostics(target).map_err(String::from)
+}
+
+pub fn diagnostics_from(target: PublicKeyHex) -> Vec<DiagnosticsBundle> {
+    let _call = CallTimer::start("diagnostics_from");
+    CONTEXT.lock().unwrap().diagnostics_from(target.as_str())
+}
+
+pub fn recent_logs(limit: usize, min_level: LogLevel) -> Vec<LogEntry> {
+    let _call = CallTimer::start("recent_logs");
+    let mut ctx = CONTEXT.lock().unwrap();
+    ctx.flush_slow_calls();
+    ctx.logs.recent(limit, min_level)
+}
+
+/// Retires the open node at `path`: hands its records to `successor` and
+/// waits for their receipts, then signs and gossips a retirement record, wipes
+/// the key and block store files and resets the in-memory context. Nothing is
+/// retired or wiped if `path` is not the open node or the successor does not
+/// confirm the handoff.
+pub fn decommission_node(path: String, successor: Option<PeerId>, reason: String) -> Result<DecommissionReport, String> {
+    v2::decommission_node(path, successor, reason).map_err(String::from)
+}
+
+pub fn add_address_book_entry(node_id: PeerId, alias: Option<String>) {
+    let _call = CallTimer::start("add_address_book_entry");
+    CONTEXT.lock().unwrap().add_address_book_entry(&node_id, alias);
+}
+
+pub fn remove_address_book_entry(node_id: PeerId) -> bool {
+    let _call = CallTimer::start("remove_address_book_entry");
+    let (removed, pending) = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        let removed = ctx.remove_address_book_entry(&node_id);
+        (removed, ctx.take_pending())
+    };
+    run_pending(pending);
+    removed
+}
+
+pub fn list_address_book() -> Vec<PeerEntry> {
+    let _call = CallTimer::start("list_address_book");
+    CONTEXT.lock().unwrap().address_book.list()
+}
+
+/// Tags an address book entry into `group`, such as `couriers`.
+pub fn tag_peer(node_id: PeerId, group: String) -> Result<(), String> {
+    v2::tag_peer(node_id, group).map_err(String::from)
+}
+
+pub fn untag_peer(node_id: PeerId, group: String) -> bool {
+    let _call = CallTimer::start("untag_peer");
+    CONTEXT.lock().unwrap().address_book.untag(node_id.as_str(), &group)
+}
+
+/// Sets the sync interval, push priority and pushed channels of every
+/// member of `group`, replacing its previous policy.
+pub fn set_group_policy(group: String, policy: GroupPolicy) -> Result<(), String> {
+    v2::set_group_policy(group, policy).map_err(String::from)
+}
+
+pub fn remove_group_policy(group: String) -> bool {
+    let _call = CallTimer::start("remove_group_policy");
+    CONTEXT.lock().unwrap().remove_group_policy(&group)
+}
+
+pub fn peer_groups() -> Vec<PeerGroup> {
+    let _call = CallTimer::start("peer_groups");
+    CONTEXT.lock().unwrap().peer_groups()
+}
+
+pub fn hand_over_identity(new_node_id: PublicKeyHex) -> Result<Record, String> {
+    v2::hand_over_identity(new_node_id).map_err(String::from)
+}
+
+/// Signs a handover with a backed-up key when the old device is gone. Channel keys
+/// cannot be carried over this way; admins must re-share them to the new key.
+pub fn hand_over_from_backup(backup_path: String, new_node_id: PublicKeyHex) -> Result<Record, String> {
+    v2::hand_over_from_backup(backup_path, new_node_id).map_err(String::from)
+}
+
+pub fn run_simulation(scenario_json: String) -> Result<SimulationReport, String> {
+    v2::run_simulation(scenario_json).map_err(String::from)
+}
+
+pub fn run_simulation_file(path: String) -> Result<SimulationReport, String> {
+    v2::run_simulation_file(path).map_err(String::from)
+}
+
+/// Enables fault injection for inbound records and sets the bridge clock skew.
+pub fn set_chaos(config: ChaosConfig) {
+    let _call = CallTimer::start("set_chaos");
+    clock::set_skew(config.clock_skew_secs);
+    CONTEXT.lock().unwrap().chaos = Some(ChaosInjector::new(config));
+}
+
+pub fn clear_chaos() {
+    let _call = CallTimer::start("clear_chaos");
+    clock::set_skew(0);
+    CONTEXT.lock().unwrap().chaos = None;
+}
+
+pub fn run_simulation_with_chaos(scenario_json: String, config: ChaosConfig) -> Result<SimulationReport, String> {
+    v2::run_simulation_with_chaos(scenario_json, config).map_err(String::from)
+}
+
+pub fn generate_test_vectors(seed: u64) -> Result<TestVectors, String> {
+    v2::generate_test_vectors(seed).map_err(String::from)
+}
+
+pub fn parse_frame(bytes: Vec<u8>) -> Result<Frame, String> {
+    v2::parse_frame(bytes).map_err(String::from)
+}
+
+pub fn parse_block_envelope(bytes: Vec<u8>) -> Result<BlockEnvelope, String> {
+    v2::parse_block_envelope(bytes).map_err(String::from)
+}
+
+pub fn parse_handshake(bytes: Vec<u8>) -> Result<Handshake, String> {
+    v2::parse_handshake(bytes).map_err(String::from)
+}
+
+#[cfg(feature = "bench")]
+pub fn run_builtin_benchmarks() -> bench::BenchReport {
+    let _call = CallTimer::start("run_builtin_benchmarks");
+    bench::run()
+}
+
+/// Indexes loaded blocks on a background thread, one batch per lock acquisition.
+/// The watchdog restarts the thread if it stops making progress. In
+/// single-threaded mode nothing is started; the host calls
+/// `run_background_step` instead.
+pub fn start_index_builder() {
+    let _call = CallTimer::start("start_index_builder");
+    let generation = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        if ctx.config.single_threaded || !ctx.index_builder.claim() {
+            return;
+        }
+        let restart: RestartHook = Arc::new(|_| restart_index_builder());
+        ctx.watchdog.watch(INDEX_BUILDER, INDEX_BUILDER_TIMEOUT, Some(restart), clock::now_secs())
+    };
+    spawn_index_builder(generation);
+}
+
+/// Starts a builder in place of a stalled one, which keeps the claim. The
+/// stalled thread stops at its next heartbeat, as its generation is superseded.
+fn restart_index_builder() {
+    let generation = CONTEXT.lock().unwrap().watchdog.generation(INDEX_BUILDER);
+    if let Some(generation) = generation {
+        spawn_index_builder(generation);
+    }
+}
+
+fn spawn_index_builder(generation: u64) {
+    std::thread::spawn(move || {
+        let _running = ThreadGuard::enter(ThreadRole::IndexBuilder);
+        loop {
+            let (ready, pending) = {
+                let mut ctx = CONTEXT.lock().unwrap();
+                if !ctx.worker_heartbeat(INDEX_BUILDER, generation) {
+                    return;
+                }
+                ctx.build_indexes(index_builder::BATCH_SIZE);
+                let ready = ctx.indexes_ready();
+                if ready {
+                    ctx.index_builder.release();
+                    ctx.watchdog.unwatch(INDEX_BUILDER);
+                }
+                (ready, ctx.take_pending())
+            };
+            run_pending(pending);
+            if ready {
+                return;
+            }
+            std::thread::yield_now();
+        }
+    });
+}
+
+/// Does one batch of background work on the calling thread and returns
+/// whether more is left: indexing first, then warming prefetched queries.
+/// Single-threaded hosts call it from their own loop.
+pub fn run_background_step() -> bool {
+    let _call = CallTimer::start("run_background_step");
+    let (more, pending) = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        ctx.poll_transport(sync::FRAMES_PER_STEP);
+        ctx.data_mule.expire(clock::now_secs());
+        ctx.flush_wake_queue();
+        ctx.flush_quiet_queue(clock::now_secs());
+        ctx.flush_airtime_queue();
+        ctx.deadlines.prune(clock::now_secs());
+        ctx.build_indexes(index_builder::BATCH_SIZE);
+        let more = !ctx.indexes_ready();
+        if !more {
+            ctx.warm_prefetched();
+            ctx.warm_saved_queries();
+            ctx.scheduler_heartbeat();
+            ctx.run_scheduled_backup(clock::now_secs());
+            ctx.run_scheduled_cloud_sync(clock::now_secs());
+            ctx.run_scheduled_stats_publish(clock::now_secs());
+            ctx.run_scheduled_group_syncs(clock::now_secs());
+        }
+        (more, ctx.take_pending())
+    };
+    run_pending(pending);
+    more
+}
+
+/// Runs maintenance for at most about `max_ms`, for OS background schedulers
+/// that kill long tasks. Each step is bounded and its progress saved, so the
+/// next slice resumes with the task this one stopped at.
+pub fn run_maintenance_slice(max_ms: u64) -> MaintenanceReport {
+    let _call = CallTimer::start("run_maintenance_slice");
+    let started = clock::now_millis();
+    let mut report = MaintenanceReport::default();
+    CONTEXT.lock().unwrap().begin_maintenance_slice(&mut report);
+    loop {
+        let (finished, pending) = {
+            let mut ctx = CONTEXT.lock().unwrap();
+            (ctx.maintenance_step(&mut report), ctx.take_pending())
+        };
+        run_pending(pending);
+        if finished {
+            report.round_finished = true;
+            break;
+        }
+        if clock::now_millis().saturating_sub(started) >= max_ms {
+            break;
+        }
+    }
+    CONTEXT.lock().unwrap().end_maintenance_slice(&mut report);
+    report.elapsed_ms = clock::now_millis().saturating_sub(started);
+    report
+}
+
+/// Syncs with `peer` for at most about `max_ms`. The exchange continues in the
+/// next slice unless it went quiet, and needs an attached transport.
+pub fn run_sync_slice(peer: PeerId, max_ms: u64) -> Result<SyncSliceReport, String> {
+    v2::run_sync_slice(peer, max_ms).map_err(String::from)
+}
+
+pub fn indexes_ready() -> bool {
+    let _call = CallTimer::start("indexes_ready");
+    CONTEXT.lock().unwrap().indexes_ready()
+}
+
+pub fn index_status() -> IndexStatus {
+    let _call = CallTimer::start("index_status");
+    CONTEXT.lock().unwrap().index_builder.status()
+}
+
+pub fn create_checkpoint() -> Checkpoint {
+    let _call = CallTimer::start("create_checkpoint");
+    CONTEXT.lock().unwrap().create_checkpoint()
+}
+
+pub fn list_checkpoints() -> Vec<Checkpoint> {
+    let _call = CallTimer::start("list_checkpoints");
+    CONTEXT.lock().unwrap().checkpoints.clone()
+}
+
+/// The ancestors of `block_id` back to the nearest checkpoint, oldest first,
+/// for rendering a provenance timeline.
+pub fn lineage(block_id: BlockId) -> Result<Lineage, String> {
+    v2::lineage(block_id).map_err(String::from)
+}
+
+pub fn export_delta(since_checkpoint_id: String, path: String) -> Result<Checkpoint, String> {
+    v2::export_delta(since_checkpoint_id, path).map_err(String::from)
+}
+
+pub fn import_delta(path: String) -> Result<DeltaImport, String> {
+    v2::import_delta(path).map_err(String::from)
+}
+
+/// Writes every block to a snapshot file at `path`, to back up the node or
+/// move it to another device.
+pub fn export_snapshot(path: String) -> Result<Checkpoint, String> {
+    v2::export_snapshot(path).map_err(String::from)
+}
+
+/// Verifies a snapshot and merges its blocks into the local tangle; their
+/// readings are indexed in the background.
+pub fn import_snapshot(path: String) -> Result<SnapshotImport, String> {
+    v2::import_snapshot(path).map_err(String::from)
+}
+
+pub fn payload_stats() -> PayloadStats {
+    let _call = CallTimer::start("payload_stats");
+    CONTEXT.lock().unwrap().payloads.stats()
+}
+
+/// Sensor blocks in local insertion order.
+pub fn list_blocks_page(cursor: Option<String>, limit: usize) -> Result<Page<TangleBlock>, String> {
+    v2::list_blocks_page(cursor, limit).map_err(String::from)
+}
+
+/// Blocks matching `filter`, in local insertion order.
+pub fn query_blocks_page(filter: BlockFilter, cursor: Option<String>, limit: usize) -> Result<Page<TangleBlock>, String> {
+    v2::query_blocks_page(filter, cursor, limit).map_err(String::from)
+}
+
+/// The block as JSON, in the format of block store and snapshot files.
+pub fn get_block(id: BlockId) -> Result<String, String> {
+    v2::get_block(id).map_err(String::from)
+}
+
+/// Blocks no local block names as a parent yet.
+pub fn list_tips() -> Vec<BlockId> {
+    let _call = CallTimer::start("list_tips");
+    CONTEXT.lock().unwrap().list_tips()
+}
+
+/// Neighbours of `peer_id`, ordered by peer id.
+pub fn list_peers_page(peer_id: PeerId, cursor: Option<String>, limit: usize) -> Result<Page<PeerId>, String> {
+    v2::list_peers_page(peer_id, cursor, limit).map_err(String::from)
+}
+
+/// Alert records in the order they were stored.
+pub fn list_alert_blocks_page(cursor: Option<String>, limit: usize) -> Result<Page<Record>, String> {
+    v2::list_alert_blocks_page(cursor, limit).map_err(String::from)
+}
+
+/// Active alerts ordered by metric, then sensor id.
+pub fn active_alerts_page(cursor: Option<String>, limit: usize) -> Result<Page<ActiveAlert>, String> {
+    v2::active_alerts_page(cursor, limit).map_err(String::from)
+}
+
+pub fn add_peers_bulk(peers: Vec<PeerSpec>) -> BulkReport {
+    let _call = CallTimer::start("add_peers_bulk");
+    CONTEXT.lock().unwrap().add_peers_bulk(peers)
+}
+
+pub fn add_connections_bulk(connections: Vec<(PeerId, PeerId, f32)>) -> Result<BulkReport, String> {
+    v2::add_connections_bulk(connections).map_err(String::from)
+}
+
+pub fn set_route_redundancy(priority: Priority, k: u8) -> Result<(), String> {
+    v2::set_route_redundancy(priority, k).map_err(String::from)
+}
+
+pub fn routing_config() -> RoutingConfig {
+    let _call = CallTimer::start("routing_config");
+    CONTEXT.lock().unwrap().routing.clone()
+}
+
+pub fn plan_propagation(destination: PeerId, priority: Priority) -> RoutePlan {
+    let _call = CallTimer::start("plan_propagation");
+    CONTEXT.lock().unwrap().plan_propagation(&destination, priority)
+}
+
+pub fn advertise_haves() -> Result<Record, String> {
+    v2::advertise_haves().map_err(String::from)
+}
+
+pub fn blocks_to_send(peer: PeerId, candidates: Vec<BlockId>) -> Vec<BlockId> {
+    let _call = CallTimer::start("blocks_to_send");
+    CONTEXT.lock().unwrap().blocks_to_send(&peer, candidates)
+}
+
+pub fn suppression_stats() -> SuppressionStats {
+    let _call = CallTimer::start("suppression_stats");
+    CONTEXT.lock().unwrap().have_lists.stats()
+}
+
+pub fn schedule_sends(peer: PeerId, candidates: Vec<BlockId>) -> Vec<BlockId> {
+    let _call = CallTimer::start("schedule_sends");
+    CONTEXT.lock().unwrap().schedule_sends(&peer, candidates)
+}
+
+pub fn ack_block(peer: PeerId, block_id: BlockId) -> bool {
+    let _call = CallTimer::start("ack_block");
+    CONTEXT.lock().unwrap().ack_block(&peer, &block_id)
+}
+
+pub fn send_windows() -> Vec<PeerWindow> {
+    let _call = CallTimer::start("send_windows");
+    CONTEXT.lock().unwrap().congestion.windows()
+}
+
+/// Persists the metrics history to `path`, loading what is already stored there.
+pub fn set_metrics_history_path(path: String) -> Result<(), String> {
+    v2::set_metrics_history_path(path).map_err(String::from)
+}
+
+/// Call from a timer to keep sampling while no blocks arrive.
+pub fn sample_metrics() -> Result<(), String> {
+    v2::sample_metrics().map_err(String::from)
+}
+
+pub fn metrics_history(metric: String, from: u64, to: u64, resolution: u64) -> Vec<HistoryPoint> {
+    let _call = CallTimer::start("metrics_history");
+    CONTEXT.lock().unwrap().metrics_history.query(&metric, from, to, resolution)
+}
+
+pub fn list_history_metrics() -> Vec<String> {
+    let _call = CallTimer::start("list_history_metrics");
+    CONTEXT.lock().unwrap().metrics_history.metrics()
+}
+
+/// JSON description of every public bridge function, DTO, event type and error
+/// prefix, generated from the sources at build time.
+pub fn api_schema() -> String {
+    let _call = CallTimer::start("api_schema");
+    include_str!(concat!(env!("OUT_DIR"), "/api_schema.json")).to_string()
+}
+
+/// API versions this build serves. Version 1 is the crate root; version 2 lives
+/// in [`v2`].
+pub fn bridge_api_version() -> ApiVersion {
+    let _call = CallTimer::start("bridge_api_version");
+    v2::api_version()
+}
+
+/// The optional features this binary was built with and the transports and
+/// backends active now.
+pub fn capabilities() -> CapabilityReport {
+    let _call = CallTimer::start("capabilities");
+    CONTEXT.lock().unwrap().capabilities()
+}
+
+/// Opens a batch whose blocks are stored together by `commit_batch`.
+pub fn begin_batch() -> u64 {
+    let _call = CallTimer::start("begin_batch");
+    CONTEXT.lock().unwrap().batches.begin()
+}
+
+/// Validates and signs a block into the batch. Nothing is stored yet. An
+/// invalid block discards the batch.
+pub fn add_to_batch(batch_id: u64, data: Vec<u8>, parents: Vec<BlockId>) -> Result<BlockId, String> {
+    v2::add_to_batch(batch_id, data, parents).map_err(String::from)
+}
+
+/// Stores, indexes and queues every block of the batch for propagation, or none
+/// of them. The batch is closed either way.
+pub fn commit_batch(batch_id: u64) -> Result<Vec<BlockId>, String> {
+    v2::commit_batch(batch_id).map_err(String::from)
+}
+
+pub fn abort_batch(batch_id: u64) -> bool {
+    let _call = CallTimer::start("abort_batch");
+    CONTEXT.lock().unwrap().batches.abort(batch_id)
+}
+
+/// Compare-and-set: writes only if the key's current token equals
+/// `expected_token` (`None` when the key should be unset). On conflict the error
+/// includes the current value.
+pub fn set_shared_config(key: String, value: String, expected_token: Option<String>) -> Result<ConfigEntry, String> {
+    v2::set_shared_config(key, value, expected_token).map_err(String::from)
+}
+
+pub fn get_shared_config(key: String) -> Option<ConfigEntry> {
+    let _call = CallTimer::start("get_shared_config");
+    CONTEXT.lock().unwrap().shared_config.get(&key).cloned()
+}
+
+pub fn list_shared_config() -> Vec<ConfigEntry> {
+    let _call = CallTimer::start("list_shared_config");
+    CONTEXT.lock().unwrap().shared_config.list()
+}
+
+/// Called when blocks are pruned, rolled up, redacted or archived.
+pub fn subscribe_lifecycle_events<F>(callback: F) -> u64
+where
+    F: Fn(LifecycleEvent) + Send + Sync + 'static,
+{
+    let _call = CallTimer::start("subscribe_lifecycle_events");
+    let callback: LifecycleCallback = Arc::new(callback);
+    CONTEXT.lock().unwrap().lifecycle.subscribe(callback)
+}
+
+pub fn unsubscribe_lifecycle_events(subscription_id: u64) -> bool {
+    let _call = CallTimer::start("unsubscribe_lifecycle_events");
+    CONTEXT.lock().unwrap().lifecycle.unsubscribe(subscription_id)
+}
+
+/// Forecasts disk usage under a hypothetical retention or roll-up policy from
+/// the blocks held now and the recent ingest rate. Nothing is removed.
+pub fn simulate_retention(policy: RetentionPolicy) -> Result<RetentionForecast, String> {
+    v2::simulate_retention(policy).map_err(String::from)
+}
+
+/// Removes local blocks whose reading timestamp is before `cutoff`.
+pub fn prune_blocks_before(cutoff: u64, reason: String) -> Vec<BlockId> {
+    let _call = CallTimer::start("prune_blocks_before");
+    let (ids, pending) = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        let ids = ctx.prune_blocks_before(cutoff, &reason);
+        (ids, ctx.take_pending())
+    };
+    run_pending(pending);
+    ids
+}
+
+pub fn archive_blocks_before(cutoff: u64, path: String) -> Result<Vec<BlockId>, String> {
+    v2::archive_blocks_before(cutoff, path).map_err(String::from)
+}
+
+pub fn roll_up_blocks_before(cutoff: u64) -> Result<Vec<Record>, String> {
+    v2::roll_up_blocks_before(cutoff).map_err(String::from)
+}
+
+pub fn redact_block(block_id: BlockId, reason: String) -> Result<Record, String> {
+    v2::redact_block(block_id, reason).map_err(String::from)
+}
+
+/// Changes after `cursor` (0 for everything retained), for incremental cache
+/// maintenance. Call again with the last `seq` until it returns nothing.
+pub fn changes_since(cursor: u64) -> Vec<ChangeRecord> {
+    let _call = CallTimer::start("changes_since");
+    CONTEXT.lock().unwrap().changes.since(cursor)
+}
+
+/// Cursor of the latest change, for consumers that start from a full refresh.
+pub fn change_feed_head() -> u64 {
+    let _call = CallTimer::start("change_feed_head");
+    CONTEXT.lock().unwrap().changes.head()
+}
+
+/// Network id announced to peers and used to pick the accepted suites.
+pub fn set_network_id(network_id: String) {
+    let _call = CallTimer::start("set_network_id");
+    CONTEXT.lock().unwrap().suites.set_network_id(&network_id);
+}
+
+/// Suites accepted on `network_id`, most preferred first. The first one signs
+/// local records. List both old and new suites while a network migrates.
+pub fn set_network_suites(network_id: String, suites: Vec<Suite>) -> Result<(), String> {
+    v2::set_network_suites(network_id, suites).map_err(String::from)
+}
+
+pub fn accepted_suites() -> Vec<Suite> {
+    let _call = CallTimer::start("accepted_suites");
+    CONTEXT.lock().unwrap().suites.accepted()
+}
+
+/// Suite to use with `peer`, or `None` when the two share no suite.
+pub fn negotiate_suite(peer: PeerId) -> Option<Suite> {
+    let _call = CallTimer::start("negotiate_suite");
+    CONTEXT.lock().unwrap().suites.negotiate(peer.as_str())
+}
+
+/// How Ed25519 and post-quantum signatures combine when accepting records.
+/// Mesh policies can set it too.
+pub fn set_hybrid_policy(policy: HybridPolicy) {
+    let _call = CallTimer::start("set_hybrid_policy");
+    CONTEXT.lock().unwrap().hybrid.policy = policy;
+}
+
+/// Which encoding this node signs records and blocks in and whether it still
+/// accepts legacy signatures. Switch signing to canonical once every peer runs a
+/// version that verifies it, then stop accepting legacy at the end of the
+/// transition.
+pub fn set_encoding_policy(policy: EncodingPolicy) -> Result<(), String> {
+    v2::set_encoding_policy(policy).map_err(String::from)
+}
+
+pub fn verify_block_envelope(envelope: BlockEnvelope) -> bool {
+    let _call = CallTimer::start("verify_block_envelope");
+    CONTEXT.lock().unwrap().verify_block_envelope(&envelope)
+}
+
+/// Loads or creates the node's post-quantum key under `path` and signs every
+/// later record with it as well. Returns the post-quantum public key.
+#[cfg(feature = "pq-hybrid")]
+pub fn enable_hybrid_signing(path: String) -> Result<String, String> {
+    v2::enable_hybrid_signing(path).map_err(String::from)
+}
+
+/// Signatures left before the post-quantum key is exhausted.
+#[cfg(feature = "pq-hybrid")]
+pub fn pq_signatures_remaining() -> Option<u32> {
+    let _call = CallTimer::start("pq_signatures_remaining");
+    CONTEXT.lock().unwrap().pq_signatures_remaining()
+}
+
+#[cfg(feature = "pq-hybrid")]
+pub fn sign_block_envelope(block: TangleBlock) -> Result<BlockEnvelope, String> {
+    v2::sign_block_envelope(block).map_err(String::from)
+}
+
+/// Streams this node's log entries at `min_level` or above to an admin peer
+/// for `duration_secs` (at most an hour), sealed to the admin's key.
+pub fn start_log_stream(peer: PeerId, duration_secs: u64, min_level: LogLevel) -> Result<LogStreamStatus, String> {
+    v2::start_log_stream(peer, duration_secs, min_level).map_err(String::from)
+}
+
+pub fn stop_log_stream() -> Option<LogStreamStatus> {
+    let _call = CallTimer::start("stop_log_stream");
+    CONTEXT.lock().unwrap().stop_log_stream()
+}
+
+pub fn log_stream_status() -> Option<LogStreamStatus> {
+    let _call = CallTimer::start("log_stream_status");
+    CONTEXT.lock().unwrap().log_stream_status()
+}
+
+/// The next frame for the host transport to send to the admin, if any.
+pub fn take_log_stream_frame() -> Result<Option<LogStreamFrame>, String> {
+    v2::take_log_stream_frame().map_err(String::from)
+}
+
+/// Decrypts a log stream frame addressed to this node.
+pub fn open_log_stream_frame(frame: Vec<u8>) -> Result<Vec<LogEntry>, String> {
+    v2::open_log_stream_frame(frame).map_err(String::from)
+}
+
+/// Call counts and latency histograms per public function, by name. These
+/// accessors are not counted themselves.
+pub fn call_metrics() -> Vec<CallStats> {
+    CALLS.lock().unwrap().stats()
+}
+
+/// Calls slower than the budget are logged as warnings, without their
+/// arguments. `function: None` sets the default for every function; a
+/// function's own budget of `None` falls back to that default.
+pub fn set_call_budget(function: Option<String>, budget_micros: Option<u64>) {
+    let mut calls = CALLS.lock().unwrap();
+    match function {
+        Some(function) => calls.set_budget(&function, budget_micros),
+        None => calls.default_budget_micros = budget_micros.unwrap_or(DEFAULT_CALL_BUDGET_MICROS),
+    }
+}
+
+pub fn reset_call_metrics() {
+    CALLS.lock().unwrap().reset();
+}
+
+pub fn subscribe_health_events<F>(callback: F) -> u64
+where
+    F: Fn(HealthEvent) + Send + Sync + 'static,
+{
+    let _call = CallTimer::start("subscribe_health_events");
+    let callback: HealthCallback = Arc::new(callback);
+    CONTEXT.lock().unwrap().watchdog.subscribe(callback)
+}
+
+pub fn unsubscribe_health_events(subscription_id: u64) -> bool {
+    let _call = CallTimer::start("unsubscribe_health_events");
+    CONTEXT.lock().unwrap().watchdog.unsubscribe(subscription_id)
+}
+
+/// Watches a host-side component such as the propagation worker. It must
+/// call `component_heartbeat` at least every `timeout_secs`.
+pub fn watch_component(component: String, timeout_secs: u64) {
+    let _call = CallTimer::start("watch_component");
+    CONTEXT.lock().unwrap().watchdog.watch(&component, timeout_secs, None, clock::now_secs());
+}
+
+/// Like `watch_component`, and calls `restart` with the component name when
+/// it stalls, up to three times per stall.
+pub fn watch_component_with_restart<F>(component: String, timeout_secs: u64, restart: F)
+where
+    F: Fn(String) + Send + Sync + 'static,
+{
+    let _call = CallTimer::start("watch_component_with_restart");
+    let restart: RestartHook = Arc::new(restart);
+    CONTEXT.lock().unwrap().watchdog.watch(&component, timeout_secs, Some(restart), clock::now_secs());
+}
+
+pub fn unwatch_component(component: String) -> bool {
+    let _call = CallTimer::start("unwatch_component");
+    CONTEXT.lock().unwrap().watchdog.unwatch(&component)
+}
+
+pub fn component_heartbeat(component: String) {
+    let _call = CallTimer::start("component_heartbeat");
+    let pending = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        ctx.component_heartbeat(&component);
+        ctx.take_pending()
+    };
+    run_pending(pending);
+}
+
+/// Checks heartbeat deadlines; call it from a host timer.
+pub fn check_watchdog() -> Vec<HealthEvent> {
+    let _call = CallTimer::start("check_watchdog");
+    let (events, pending) = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        let events = ctx.check_watchdog();
+        (events, ctx.take_pending())
+    };
+    run_pending(pending);
+    events
+}
+
+pub fn component_health() -> Vec<ComponentHealth> {
+    let _call = CallTimer::start("component_health");
+    CONTEXT.lock().unwrap().watchdog.components()
+}
+
+/// Worker counts and single-threaded mode. Pools already running keep their
+/// size until they are next started.
+pub fn configure_bridge(config: BridgeConfig) -> Result<(), String> {
+    v2::configure_bridge(config).map_err(String::from)
+}
+
+pub fn bridge_config() -> BridgeConfig {
+    let _call = CallTimer::start("bridge_config");
+    CONTEXT.lock().unwrap().config.clone()
+}
+
+/// Node counters, including the bridge threads running now.
+pub fn node_status() -> NodeStatusSnapshot {
+    let _call = CallTimer::start("node_status");
+    CONTEXT.lock().unwrap().status_snapshot()
+}
+
+/// Tells the bridge a query the app will make at startup. Indexes are built
+/// and the result computed in the background, so the first call is answered
+/// from the warmed result. Single-threaded hosts warm it with
+/// `run_background_step`.
+pub fn prefetch(spec: QuerySpec) {
+    let _call = CallTimer::start("prefetch");
+    {
+        let mut ctx = CONTEXT.lock().unwrap();
+        ctx.prefetch.request(spec);
+        if ctx.config.single_threaded || !ctx.prefetch.claim() {
+            return;
+        }
+    }
+    std::thread::spawn(|| {
+        let _running = ThreadGuard::enter(ThreadRole::Prefetch);
+        while run_background_step() {
+            std::thread::yield_now();
+        }
+        CONTEXT.lock().unwrap().prefetch.release();
+    });
+}
+
+pub fn prefetch_status() -> PrefetchStatus {
+    let _call = CallTimer::start("prefetch_status");
+    CONTEXT.lock().unwrap().prefetch_status()
+}
+
+/// Payload sizes per channel as JSON, CBOR and compressed JSON, with the
+/// encoding that would store this node's data in the fewest bytes.
+pub fn storage_analysis() -> StorageAnalysis {
+    let _call = CallTimer::start("storage_analysis");
+    CONTEXT.lock().unwrap().storage_analysis()
+}
+
+/// Signs the numeric records of a SenML pack (RFC 8428, JSON or CBOR) as sensor
+/// blocks, one per device and time. Names and units that match a `SensorData`
+/// field fill it; other measurements are indexed under their own name.
+pub fn ingest_senml(json_or_cbor: Vec<u8>) -> Result<SenmlIngest, String> {
+    v2::ingest_senml(json_or_cbor).map_err(String::from)
+}
+
+/// Decodes a Sparkplug B message received on `topic` from the host's MQTT
+/// client. Numeric metrics become sensor blocks; birth and death certificates
+/// update `sparkplug_devices`.
+#[cfg(feature = "sparkplug")]
+pub fn ingest_sparkplug(topic: String, payload: Vec<u8>) -> Result<SparkplugIngest, String> {
+    v2::ingest_sparkplug(topic, payload).map_err(String::from)
+}
+
+/// Edge nodes and devices seen in Sparkplug births and deaths, with whether
+/// each is online.
+#[cfg(feature = "sparkplug")]
+pub fn sparkplug_devices() -> Vec<SparkplugDevice> {
+    let _call = CallTimer::start("sparkplug_devices");
+    CONTEXT.lock().unwrap().sparkplug.devices()
+}
+
+/// Attributes uplinks from the LoRaWAN device `dev_eui` to `sensor_id`.
+pub fn register_lorawan_sensor(dev_eui: String, sensor_id: String) -> Result<(), String> {
+    v2::register_lorawan_sensor(dev_eui, sensor_id).map_err(String::from)
+}
+
+pub fn remove_lorawan_sensor(dev_eui: String) -> bool {
+    let _call = CallTimer::start("remove_lorawan_sensor");
+    CONTEXT.lock().unwrap().lorawan.remove_sensor(&dev_eui)
+}
+
+pub fn lorawan_sensors() -> Vec<LorawanSensor> {
+    let _call = CallTimer::start("lorawan_sensors");
+    CONTEXT.lock().unwrap().lorawan.sensors()
+}
+
+/// Decodes uplinks on `f_port` with `codec`; `None` sets the codec for every
+/// port without one of its own.
+pub fn register_lorawan_codec(f_port: Option<u8>, codec: UplinkCodec) {
+    let _call = CallTimer::start("register_lorawan_codec");
+    CONTEXT.lock().unwrap().lorawan.register_codec(f_port, codec);
+}
+
+pub fn remove_lorawan_codec(f_port: Option<u8>) -> bool {
+    let _call = CallTimer::start("remove_lorawan_codec");
+    CONTEXT.lock().unwrap().lorawan.remove_codec(f_port)
+}
+
+/// Decodes an uplink from a registered device and signs its values as one
+/// reading of the device's sensor. Custom decoders run without the context
+/// lock, so they may call back into the bridge.
+pub fn ingest_lorawan_uplink(uplink: LorawanUplink) -> Result<LorawanIngest, String> {
+    v2::ingest_lorawan_uplink(uplink).map_err(String::from)
+}
+
+/// Gossips and syncs blocks over UDP, one frame per datagram, from a socket
+/// bound to `bind_address` (`host:port`). Returns the bound address.
+pub fn attach_udp_transport(bind_address: String) -> Result<String, String> {
+    v2::attach_udp_transport(bind_address).map_err(String::from)
+}
+
+pub fn attach_transport(transport: Box<dyn Transport>) {
+    let _call = CallTimer::start("attach_transport");
+    CONTEXT.lock().unwrap().attach_transport(transport);
+}
+
+pub fn detach_transport() -> bool {
+    let _call = CallTimer::start("detach_transport");
+    CONTEXT.lock().unwrap().detach_transport()
+}
+
+pub fn set_peer_address(peer: PeerId, address: String) -> Result<(), String> {
+    v2::set_peer_address(peer, address).map_err(String::from)
+}
+
+/// Catches up with `peer` by exchanging recent block ids and fetching the
+/// missing blocks. Runs by itself when a connection to the local node is added.
+pub fn sync_with_peer(peer: PeerId) -> Result<(), String> {
+    v2::sync_with_peer(peer).map_err(String::from)
+}
+
+/// Handles up to `max_frames` frames waiting on the transport. Also done by
+/// `run_background_step`.
+pub fn poll_transport(max_frames: usize) -> usize {
+    let _call = CallTimer::start("poll_transport");
+    let (handled, pending) = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        let handled = ctx.poll_transport(max_frames);
+        (handled, ctx.take_pending())
+    };
+    run_pending(pending);
+    handled
+}
+
+pub fn sync_stats() -> SyncStats {
+    let _call = CallTimer::start("sync_stats");
+    CONTEXT.lock().unwrap().sync.stats()
+}
+
+/// Traffic exchanged with `peer_id` since the bridge started, or `None` if no
+/// frame has passed between them.
+pub fn peer_stats(peer_id: PeerId) -> Option<PeerStats> {
+    let _call = CallTimer::start("peer_stats");
+    CONTEXT.lock().unwrap().flow_stats.peer_stats(&peer_id)
+}
+
+/// Message counts, recent rates and top sources of `channel` since the bridge
+/// started, or `None` if no message has been seen on it.
+pub fn channel_stats(channel: String) -> Option<ChannelStats> {
+    let _call = CallTimer::start("channel_stats");
+    CONTEXT.lock().unwrap().flow_stats.channel_stats(&channel, clock::now_secs())
+}
+
+/// Exports the latest value of every sensor metric through MQTT discovery, so
+/// Home Assistant and compatible hubs show them without an integration.
+pub fn enable_home_assistant(config: HomeAssistantConfig) -> Result<(), String> {
+    v2::enable_home_assistant(config).map_err(String::from)
+}
+
+/// Returns the retained "offline" availability message to publish, if any.
+pub fn disable_home_assistant() -> Vec<MqttMessage> {
+    let _call = CallTimer::start("disable_home_assistant");
+    CONTEXT.lock().unwrap().disable_home_assistant()
+}
+
+pub fn home_assistant_config() -> Option<HomeAssistantConfig> {
+    let _call = CallTimer::start("home_assistant_config");
+    CONTEXT.lock().unwrap().home_assistant.config().cloned()
+}
+
+/// Discovery configs and state updates for the host's MQTT client to publish.
+pub fn take_home_assistant_messages() -> Vec<MqttMessage> {
+    let _call = CallTimer::start("take_home_assistant_messages");
+    CONTEXT.lock().unwrap().take_home_assistant_messages()
+}
+
+/// Answers a read-only OGC SensorThings `GET` for `path` (with its query, e.g.
+/// `/Datastreams('s1|temperature')/Observations?$top=10`) from the host's HTTP
+/// server. Links in the response are built on `base_url`.
+#[cfg(feature = "sensorthings")]
+pub fn sensorthings_get(base_url: String, path: String) -> SensorThingsResponse {
+    let _call = CallTimer::start("sensorthings_get");
+    CONTEXT.lock().unwrap().sensorthings_get(&base_url, &path, None)
+}
+
+/// `sensorthings_get` for a request carrying `If-None-Match`: answers `304`
+/// with an empty body when the header names the current ETag.
+#[cfg(feature = "sensorthings")]
+pub fn sensorthings_get_conditional(base_url: String, path: String, if_none_match: Option<String>) -> SensorThingsResponse {
+    let _call = CallTimer::start("sensorthings_get_conditional");
+    CONTEXT.lock().unwrap().sensorthings_get(&base_url, &path, if_none_match.as_deref())
+}
+
+/// Reloads the blocks stored under the node directory `path` and persists every
+/// block added from now on. Returns how many blocks were loaded; their readings
+/// are indexed in the background.
+pub fn open_tangle(path: String) -> Result<usize, String> {
+    v2::open_tangle(path).map_err(String::from)
+}
+
+/// Like `open_tangle`, with the block store file at `file_path`, for hosts that
+/// keep data outside the node directory.
+pub fn open_block_store(file_path: String) -> Result<usize, String> {
+    v2::open_block_store(file_path).map_err(String::from)
+}
+
+/// Syncs the block store to disk. Call it before the app is suspended.
+pub fn flush_block_store() -> Result<(), String> {
+    v2::flush_block_store().map_err(String::from)
+}
+
+/// Rewrites the block store without removed blocks.
+pub fn compact_block_store() -> Result<BlockStoreStatus, String> {
+    v2::compact_block_store().map_err(String::from)
+}
+
+pub fn block_store_status() -> Option<BlockStoreStatus> {
+    let _call = CallTimer::start("block_store_status");
+    CONTEXT.lock().unwrap().block_store_status()
+}
+
+/// Calls `callback` with every `BridgeEvent`: blocks created or refused,
+/// connections added and peers removed. Callbacks run after the context lock is
+/// released, so they may call back into the bridge. Returns the id to pass to
+/// `unsubscribe_events`.
+pub fn subscribe_events<F>(callback: F) -> u64
+where
+    F: Fn(BridgeEvent) + Send + Sync + 'static,
+{
+    let _call = CallTimer::start("subscribe_events");
+    let callback: EventCallback = Arc::new(callback);
+    CONTEXT.lock().unwrap().events.subscribe(callback)
+}
+
+pub fn unsubscribe_events(subscription_id: u64) -> bool {
+    let _call = CallTimer::start("unsubscribe_events");
+    CONTEXT.lock().unwrap().events.unsubscribe(subscription_id)
+}
+
+/// Signs `vertices` as this node's area of interest. While peers scope
+/// propagation, they stop pushing blocks located outside it.
+pub fn declare_geofence(vertices: Vec<GeoPoint>) -> Result<Record, String> {
+    v2::declare_geofence(vertices).map_err(String::from)
+}
+
+/// Withdraws this node's area of interest.
+pub fn clear_geofence() -> Result<Record, String> {
+    v2::clear_geofence().map_err(String::from)
+}
+
+pub fn local_geofence() -> Option<Geofence> {
+    let _call = CallTimer::start("local_geofence");
+    CONTEXT.lock().unwrap().geofences.local().cloned()
+}
+
+/// The newest area declared by each other node that has one.
+pub fn declared_geofences() -> Vec<GeofenceDeclaration> {
+    let _call = CallTimer::start("declared_geofences");
+    CONTEXT.lock().unwrap().geofences.declared()
+}
+
+/// Whether blocks outside a peer's declared area are kept from it. On by default.
+pub fn set_geofence_scoping(enabled: bool) {
+    let _call = CallTimer::start("set_geofence_scoping");
+    CONTEXT.lock().unwrap().geofences.scoping = enabled;
+}
+
+/// Block pushes skipped because the block lay outside the peer's area.
+pub fn geofence_skipped_pushes() -> u64 {
+    let _call = CallTimer::start("geofence_skipped_pushes");
+    CONTEXT.lock().unwrap().geofences.skipped()
+}
+
+/// Like `subscribe_events`, but only for `BlockInserted` and `BlockReceived`
+/// events whose reading has a `latitude` and `longitude` inside `vertices`.
+/// Returns the id to pass to `unsubscribe_geofenced_events`.
+pub fn subscribe_geofenced_events<F>(vertices: Vec<GeoPoint>, callback: F) -> Result<u64, String>
+where
+    F: Fn(BridgeEvent) + Send + Sync + 'static,
+{
+    v2::subscribe_geofenced_events(vertices, callback).map_err(String::from)
+}
+
+pub fn unsubscribe_geofenced_events(subscription_id: u64) -> bool {
+    let _call = CallTimer::start("unsubscribe_geofenced_events");
+    CONTEXT.lock().unwrap().geofences.unsubscribe(subscription_id)
+}
+
+/// Turns on courier mode: each `report_encounter` starts a sync bounded by
+/// `config`'s byte and time budgets.
+pub fn enable_data_mule(config: DataMuleConfig) -> Result<(), String> {
+    v2::enable_data_mule(config).map_err(String::from)
+}
+
+pub fn disable_data_mule() {
+    let _call = CallTimer::start("disable_data_mule");
+    CONTEXT.lock().unwrap().disable_data_mule()
+}
+
+pub fn data_mule_config() -> Option<DataMuleConfig> {
+    let _call = CallTimer::start("data_mule_config");
+    CONTEXT.lock().unwrap().data_mule.config()
+}
+
+/// For the host's proximity callback: opens an encounter with `peer` and syncs
+/// with it over the attached transport. Returns the encounter id.
+pub fn report_encounter(peer: PeerId) -> Result<u64, String> {
+    v2::report_encounter(peer).map_err(String::from)
+}
+
+/// For the host's callback when `peer` goes out of range.
+pub fn end_encounter(peer: PeerId) -> Option<Encounter> {
+    let _call = CallTimer::start("end_encounter");
+    CONTEXT.lock().unwrap().end_encounter(&peer)
+}
+
+/// Closed encounters, oldest first, followed by the open ones.
+pub fn encounter_log() -> Vec<Encounter> {
+    let _call = CallTimer::start("encounter_log");
+    CONTEXT.lock().unwrap().data_mule.encounters()
+}
+
+/// Encounters that started within `[from, to]`, with their peer, bytes and
+/// blocks exchanged; `Encounter::duration` gives how long each lasted.
+pub fn encounter_history(from: u64, to: u64) -> Vec<Encounter> {
+    let _call = CallTimer::start("encounter_history");
+    CONTEXT.lock().unwrap().encounter_history(from, to)
+}
+
+/// How long blocks received in encounters within `[from, to]` took to arrive
+/// after their reading, per region of 0.1° cells.
+pub fn delivery_latency_by_region(from: u64, to: u64) -> Vec<RegionLatency> {
+    let _call = CallTimer::start("delivery_latency_by_region");
+    CONTEXT.lock().unwrap().encounter_latencies.by_region(from, to)
+}
+
+/// Battery level the host reports for relay election, `None` when mains
+/// powered. Advertised in the next announcement.
+pub fn set_battery_level(percent: Option<u8>) -> Result<(), String> {
+    v2::set_battery_level(percent).map_err(String::from)
+}
+
+pub fn set_relay_config(config: RelayConfig) -> Result<(), String> {
+    v2::set_relay_config(config).map_err(String::from)
+}
+
+pub fn relay_config() -> RelayConfig {
+    let _call = CallTimer::start("relay_config");
+    CONTEXT.lock().unwrap().relay.config
+}
+
+/// Whether this node currently relays blocks from peers; also in `node_status()`.
+pub fn relay_role() -> RelayRole {
+    let _call = CallTimer::start("relay_role");
+    CONTEXT.lock().unwrap().relay_role()
+}
+
+/// The wake schedule of the active policy, if any.
+pub fn wake_schedule() -> Option<WakeSchedule> {
+    let _call = CallTimer::start("wake_schedule");
+    CONTEXT.lock().unwrap().wake.schedule()
+}
+
+/// Whether the host should keep this node's radio on now. Always true unless
+/// a wake schedule is in force and a battery level was reported.
+pub fn radio_awake() -> bool {
+    let _call = CallTimer::start("radio_awake");
+    CONTEXT.lock().unwrap().radio_awake(clock::now_secs())
+}
+
+/// Start and end of the next window in which `node_id` listens, or `None` when
+/// its radio is always on. Pass this node's id to plan the host's radio timer.
+pub fn next_wake_window(node_id: String) -> Option<(u64, u64)> {
+    let _call = CallTimer::start("next_wake_window");
+    CONTEXT.lock().unwrap().wake_window_of(&node_id, clock::now_secs())
+}
+
+/// Blocks held for each sleeping peer until its window opens.
+pub fn deferred_sends() -> HashMap<PeerId, usize> {
+    let _call = CallTimer::start("deferred_sends");
+    CONTEXT.lock().unwrap().wake.deferred()
+}
+
+/// Per-operation costs of the host platform, e.g.
+/// `EnergyCoefficients::microcontroller()`. Phone figures by default.
+pub fn set_energy_coefficients(coefficients: EnergyCoefficients) -> Result<(), String> {
+    v2::set_energy_coefficients(coefficients).map_err(String::from)
+}
+
+/// Estimated energy per subsystem since the last `reset_energy_counters`,
+/// largest first.
+pub fn energy_report() -> EnergyReport {
+    let _call = CallTimer::start("energy_report");
+    CONTEXT.lock().unwrap().energy_report()
+}
+
+pub fn reset_energy_counters() {
+    let _call = CallTimer::start("reset_energy_counters");
+    energy::reset_counters();
+}
+
+/// Battery voltage, state of charge, charging state and solar output from the
+/// host. Advertised in the next announcement and shown in `node_status()`.
+/// Returns the power mode in force afterwards.
+pub fn report_power_state(state: PowerState) -> Result<PowerMode, String> {
+    v2::report_power_state(state).map_err(String::from)
+}
+
+/// Pins the power mode; `None` switches automatically on the thresholds again.
+pub fn set_power_mode(mode: Option<PowerMode>) {
+    let _call = CallTimer::start("set_power_mode");
+    let pending = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        ctx.set_power_mode(mode);
+        ctx.take_pending()
+    };
+    run_pending(pending);
+}
+
+pub fn power_mode() -> PowerMode {
+    let _call = CallTimer::start("power_mode");
+    CONTEXT.lock().unwrap().power.mode()
+}
+
+pub fn set_power_thresholds(thresholds: PowerThresholds) -> Result<(), String> {
+    v2::set_power_thresholds(thresholds).map_err(String::from)
+}
+
+/// Daily UTC windows of radio silence. Frames sent during one are queued and
+/// go out once it ends; an empty list lifts every window.
+pub fn set_quiet_windows(windows: Vec<QuietWindow>) -> Result<(), String> {
+    v2::set_quiet_windows(windows).map_err(String::from)
+}
+
+pub fn quiet_windows() -> Vec<QuietWindow> {
+    let _call = CallTimer::start("quiet_windows");
+    CONTEXT.lock().unwrap().quiet_hours.windows().to_vec()
+}
+
+/// Whether the radio is silent now, when that changes, and how many frames
+/// are waiting.
+pub fn quiet_hours_status() -> QuietStatus {
+    let _call = CallTimer::start("quiet_hours_status");
+    CONTEXT.lock().unwrap().quiet_hours.status(clock::now_secs())
+}
+
+/// Limits the transport named `transport` (`"udp"`, or what a custom transport
+/// returns from `Transport::name`) to a duty-cycle budget, e.g.
+/// `DutyCycleBudget::eu868(AirtimeModel::Lora { spreading_factor: 9, bandwidth_hz: 125_000 })`.
+/// `None` lifts the limit.
+pub fn set_airtime_budget(transport: String, budget: Option<DutyCycleBudget>) -> Result<(), String> {
+    v2::set_airtime_budget(transport, budget).map_err(String::from)
+}
+
+/// Airtime used and remaining in the current window of each budgeted
+/// transport, with the frames waiting for it.
+pub fn airtime_status() -> Vec<AirtimeStatus> {
+    let _call = CallTimer::start("airtime_status");
+    CONTEXT.lock().unwrap().airtime_status()
+}
+
+/// Sends a signed record, such as a diagnostics request, to one node. It goes
+/// along the cheapest known path, with each relay forwarding to the next hop,
+/// and is flooded when no path is known or a hop is unreachable.
+pub fn send_directed(record: Record, destination: PeerId) -> Result<DirectedDelivery, String> {
+    v2::send_directed(record, destination).map_err(String::from)
+}
+
+/// Like `send_directed`, but relays drop the record and the destination
+/// ignores it after `deadline`, so a stale command is never carried out late.
+pub fn send_directed_by(record: Record, destination: PeerId, deadline: u64) -> Result<DirectedDelivery, String> {
+    v2::send_directed_by(record, destination, deadline).map_err(String::from)
+}
+
+/// Sends a signed record to each of `targets` along its own directed path,
+/// relayed through the mesh, and asks every target for a delivery receipt.
+/// With a `deadline`, copies not delivered by then are dropped and their
+/// targets reported as expired.
+pub fn send_to_many(record: Record, targets: Vec<PeerId>, deadline: Option<u64>) -> Result<MulticastDelivery, String> {
+    v2::send_to_many(record, targets, deadline).map_err(String::from)
+}
+
+/// Per-target state of a record sent with `send_to_many`; `None` if it was
+/// not sent that way or has been forgotten.
+pub fn delivery_status(record_id: String) -> Option<MulticastDelivery> {
+    let _call = CallTimer::start("delivery_status");
+    CONTEXT.lock().unwrap().multicasts.get(&record_id, clock::now_secs())
+}
+
+pub fn source_routing_stats() -> RoutingStats {
+    let _call = CallTimer::start("source_routing_stats");
+    CONTEXT.lock().unwrap().source_routing.stats()
+}
+
+/// Drops copies of block or record `id` still waiting in the outbox after
+/// `deadline`, raising `DeliveryExpired` for each.
+pub fn set_delivery_deadline(id: String, deadline: u64) {
+    let _call = CallTimer::start("set_delivery_deadline");
+    CONTEXT.lock().unwrap().deadlines.set(&id, deadline);
+}
+
+/// Deadline-bound sends per peer: how many went out in time and how many expired.
+pub fn delivery_stats() -> Vec<PeerDeliveryStats> {
+    let _call = CallTimer::start("delivery_stats");
+    CONTEXT.lock().unwrap().deadlines.stats()
+}
+
+/// Rejection notices received from peers, per rejecting node and error kind,
+/// most frequent first.
+pub fn rejection_stats() -> Vec<RejectionStats> {
+    let _call = CallTimer::start("rejection_stats");
+    CONTEXT.lock().unwrap().rejections.stats()
+}
+
+pub fn reset_rejection_stats() {
+    let _call = CallTimer::start("reset_rejection_stats");
+    CONTEXT.lock().unwrap().rejections.reset();
+}
+
+/// Starts a tangle explorer session showing `start_id` alone and returns its id.
+/// At most 16 sessions stay open; opening another closes the oldest.
+pub fn open_explorer(start_id: String) -> Result<u64, String> {
+    v2::open_explorer(start_id).map_err(String::from)
+}
+
+/// Adds the parents and up to 64 children of `node` to the session's view
+/// and returns only the nodes that appeared.
+pub fn expand(session: u64, node: String) -> Result<ExplorerDelta, String> {
+    v2::expand(session, node).map_err(String::from)
+}
+
+/// Removes what expanding `node` added and returns the ids that left the view.
+pub fn collapse(session: u64, node: String) -> Result<ExplorerDelta, String> {
+    v2::collapse(session, node).map_err(String::from)
+}
+
+/// Every node in the session's view, for redrawing from scratch.
+pub fn current_view(session: u64) -> Result<ExplorerView, String> {
+    v2::current_view(session).map_err(String::from)
+}
+
+pub fn close_explorer(session: u64) -> bool {
+    let _call = CallTimer::start("close_explorer");
+    CONTEXT.lock().unwrap().explorer.close(session)
+}
+
+/// Registers `query` under its name, replacing any query of that name. Once
+/// `init_context` has bound a node, definitions are saved in its directory and
+/// reloaded on the next start.
+pub fn register_saved_query(query: SavedQuery) -> Result<(), String> {
+    v2::register_saved_query(query).map_err(String::from)
+}
+
+pub fn remove_saved_query(name: String) -> Result<bool, String> {
+    v2::remove_saved_query(name).map_err(String::from)
+}
+
+pub fn saved_queries() -> Vec<SavedQuery> {
+    let _call = CallTimer::start("saved_queries");
+    CONTEXT.lock().unwrap().saved_queries.list()
+}
+
+/// Runs a registered query. `params` fill its `$name` placeholders, and
+/// `from` and `to` (in seconds) set the time range.
+pub fn run_saved_query(name: String, params: BTreeMap<String, String>) -> Result<SavedQueryResult, String> {
+    v2::run_saved_query(name, params).map_err(String::from)
+}
+
+/// Declares a view kept up to date as readings arrive, replacing any view of
+/// that name. Definitions are saved in the node directory like saved queries.
+pub fn declare_materialized_view(definition: ViewDefinition) -> Result<(), String> {
+    v2::declare_materialized_view(definition).map_err(String::from)
+}
+
+pub fn drop_materialized_view(name: String) -> Result<bool, String> {
+    v2::drop_materialized_view(name).map_err(String::from)
+}
+
+pub fn materialized_views() -> Vec<ViewDefinition> {
+    let _call = CallTimer::start("materialized_views");
+    CONTEXT.lock().unwrap().materialized_views.list()
+}
+
+pub fn materialized_view_rows(name: String) -> Result<Vec<ViewRow>, String> {
+    v2::materialized_view_rows(name).map_err(String::from)
+}
+
+/// One row of a view without reading the rest: a sensor id for per-sensor
+/// views, a day's start in seconds for daily ones.
+pub fn materialized_view_row(name: String, key: String) -> Result<Option<ViewRow>, String> {
+    v2::materialized_view_row(name, key).map_err(String::from)
+}
+
+/// Lets unauthenticated clients read metric aggregates and public channel
+/// messages through `gateway_get`, within per-address request budgets.
+pub fn enable_gateway(config: GatewayConfig) -> Result<(), String> {
+    v2::enable_gateway(config).map_err(String::from)
+}
+
+pub fn disable_gateway() {
+    let _call = CallTimer::start("disable_gateway");
+    CONTEXT.lock().unwrap().disable_gateway()
+}
+
+pub fn gateway_config() -> Option<GatewayConfig> {
+    let _call = CallTimer::start("gateway_config");
+    CONTEXT.lock().unwrap().gateway.config().cloned()
+}
+
+/// Answers a public `GET` for `path` (with its query string) from the host's
+/// HTTP server. `client` is the caller's IP address, used for throttling.
+pub fn gateway_get(client: String, path: String) -> GatewayResponse {
+    let _call = CallTimer::start("gateway_get");
+    CONTEXT.lock().unwrap().gateway_get(&client, &path, None)
+}
+
+/// `gateway_get` for a request carrying `If-None-Match`: answers `304` with an
+/// empty body when the header names the current ETag.
+pub fn gateway_get_conditional(client: String, path: String, if_none_match: Option<String>) -> GatewayResponse {
+    let _call = CallTimer::start("gateway_get_conditional");
+    CONTEXT.lock().unwrap().gateway_get(&client, &path, if_none_match.as_deref())
+}
+
+pub fn gateway_stats() -> GatewayStats {
+    let _call = CallTimer::start("gateway_stats");
+    CONTEXT.lock().unwrap().gateway.stats()
+}
+
+/// Lets the bridge reach remote services such as an S3 backup target or a
+/// cloud sync service. The host's HTTP stack sends each request.
+pub fn set_http_client(client: Box<dyn HttpClient>) {
+    let _call = CallTimer::start("set_http_client");
+    CONTEXT.lock().unwrap().http_client = Some(client);
+}
+
+/// Uploads an encrypted backup to the S3 target in `BridgeConfig` now,
+/// without waiting for the schedule.
+pub fn backup_to_s3() -> Result<S3Backup, String> {
+    v2::backup_to_s3().map_err(String::from)
+}
+
+pub fn s3_backup_status() -> S3BackupStatus {
+    let _call = CallTimer::start("s3_backup_status");
+    CONTEXT.lock().unwrap().s3_backups.status()
+}
+
+/// Merges the blocks of the backup at `key` into the tangle. A delta brings
+/// its full backup with it.
+pub fn restore_from_s3(key: String) -> Result<SnapshotImport, String> {
+    v2::restore_from_s3(key).map_err(String::from)
+}
+
+/// Starts mirroring the tangle to the EcoBlock service at `config.url`. Rounds
+/// run from `run_background_step` every `interval_secs`.
+pub fn enable_cloud_sync(config: CloudSyncConfig) -> Result<(), String> {
+    v2::enable_cloud_sync(config).map_err(String::from)
+}
+
+pub fn disable_cloud_sync() -> bool {
+    let _call = CallTimer::start("disable_cloud_sync");
+    CONTEXT.lock().unwrap().disable_cloud_sync()
+}
+
+/// Runs a cloud sync round now, without waiting for the schedule.
+pub fn cloud_sync_now() -> Result<CloudSyncReport, String> {
+    v2::cloud_sync_now().map_err(String::from)
+}
+
+pub fn cloud_sync_status() -> CloudSyncStatus {
+    let _call = CallTimer::start("cloud_sync_status");
+    CONTEXT.lock().unwrap().cloud_sync.status()
+}
+
+/// Serves this process's context to other processes over a Unix socket,
+/// `bridge.sock` in the node directory unless `socket_path` is given. Returns
+/// the socket path for `IpcClient::connect`.
+#[cfg(unix)]
+pub fn serve_ipc(socket_path: Option<String>) -> Result<String, String> {
+    v2::serve_ipc(socket_path).map_err(String::from)
+}
+
+/// Stops serving IPC. Connected clients are dropped.
+#[cfg(unix)]
+pub fn stop_ipc() -> bool {
+    let _call = CallTimer::start("stop_ipc");
+    let mut ctx = CONTEXT.lock().unwrap();
+    ctx.watchdog.unwatch(watchdog::IPC);
+    ctx.ipc.stop()
+}
+
+#[cfg(unix)]
+pub fn ipc_status() -> IpcStatus {
+    let _call = CallTimer::start("ipc_status");
+    CONTEXT.lock().unwrap().ipc.status()
+}
+
+/// Queues work for the host; `payload` is JSON. The job is on disk before
+/// this returns, and `JobQueued` is raised for it.
+pub fn enqueue_job(kind: String, payload: String) -> Result<u64, String> {
+    v2::enqueue_job(kind, payload).map_err(String::from)
+}
+
+/// Hands out up to 64 waiting jobs, oldest first. Each stays with the host
+/// for 5 minutes; one not completed by then is handed out again.
+pub fn poll_jobs() -> Vec<Job> {
+    let _call = CallTimer::start("poll_jobs");
+    CONTEXT.lock().unwrap().jobs.poll(clock::now_secs())
+}
+
+pub fn complete_job(id: u64) -> Result<bool, String> {
+    v2::complete_job(id).map_err(String::from)
+}
+
+/// Every queued job, including those handed out and not yet completed.
+pub fn list_jobs() -> Vec<Job> {
+    let _call = CallTimer::start("list_jobs");
+    CONTEXT.lock().unwrap().jobs.list()
+}
+
+/// Sets the unit symbol and decimals `metric` is shown with, in place of its
+/// default.
+#[cfg(feature = "presentation")]
+pub fn set_metric_format(format: MetricFormat) -> Result<(), String> {
+    v2::set_metric_format(format).map_err(String::from)
+}
+
+#[cfg(feature = "presentation")]
+pub fn remove_metric_format(metric: String) -> bool {
+    let _call = CallTimer::start("remove_metric_format");
+    CONTEXT.lock().unwrap().metric_formats.remove(&metric)
+}
+
+/// Formats configured with `set_metric_format`.
+#[cfg(feature = "presentation")]
+pub fn metric_formats() -> Vec<MetricFormat> {
+    let _call = CallTimer::start("metric_formats");
+    CONTEXT.lock().unwrap().metric_formats.list()
+}
+
+/// `value` of `metric` as shown in the locale with BCP 47 tag `locale`.
+#[cfg(feature = "presentation")]
+pub fn format_value(metric: String, value: f64, locale: String) -> FormattedValue {
+    let _call = CallTimer::start("format_value");
+    CONTEXT.lock().unwrap().metric_formats.value(&metric, value, &Locale::from_tag(&locale))
+}
+
+#[cfg(feature = "presentation")]
+pub fn format_aggregate(aggregate: MetricAggregate, locale: String) -> FormattedAggregate {
+    let _call = CallTimer::start("format_aggregate");
+    aggregate.formatted(&CONTEXT.lock().unwrap().metric_formats, &Locale::from_tag(&locale))
+}
+
+/// The latest values, 24-hour ranges and active alerts of
+/// `dashboard_summary()`, formatted.
+#[cfg(feature = "presentation")]
+pub fn formatted_dashboard(locale: String) -> FormattedDashboard {
+    let _call = CallTimer::start("formatted_dashboard");
+    CONTEXT.lock().unwrap().formatted_dashboard(&Locale::from_tag(&locale))
+}
+
+/// Compiles and installs a Rhai script run on every event of its trigger,
+/// replacing a script of the same name. Kept in the node directory.
+#[cfg(feature = "scripting")]
+pub fn install_script(definition: ScriptDefinition) -> Result<(), String> {
+    v2::install_script(definition).map_err(String::from)
+}
+
+#[cfg(feature = "scripting")]
+pub fn remove_script(name: String) -> Result<bool, String> {
+    v2::remove_script(name).map_err(String::from)
+}
+
+/// Installed scripts with their run and error counts.
+#[cfg(feature = "scripting")]
+pub fn scripts() -> Vec<ScriptStatus> {
+    let _call = CallTimer::start("scripts");
+    CONTEXT.lock().unwrap().scripts.list()
 }
 
-pub fn list_peers(peer_id: String) -> Vec<String> {
-    CONTEXT.lock().unwrap().list_peers(&peer_id)
+/// Runs a read-only GraphQL query over blocks, sensors, readings, peers and
+/// alerts, for a host serving `/graphql` itself. `variables` is the request's
+/// variables object as JSON.
+#[cfg(feature = "graphql")]
+pub fn graphql_query(query: String, variables: Option<String>) -> GraphqlResponse {
+    let _call = CallTimer::start("graphql_query");
+    CONTEXT.lock().unwrap().graphql(&query, variables.as_deref())
 }
@@ -0,0 +1,227 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::sync::Arc;
+use ecoblock_storage::tangle::block::TangleBlock;
+use serde::{Deserialize, Serialize};
+use crate::aggregate::MetricAggregate;
+use crate::block_id::BlockId;
+use crate::change_feed::ChangeKind;
+use crate::error::BridgeError;
+use crate::payloads::payload_hash;
+use crate::readings::Reading;
+use crate::records::Record;
+use crate::{clock, EcoBlockContext};
+
+pub type LifecycleCallback = Arc<dyn Fn(LifecycleEvent) + Send + Sync>;
+
+pub const ROLLUP_KIND: &str = "rollup";
+pub const REDACTION_KIND: &str = "redaction";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LifecycleAction {
+    Pruned,
+    /// Replaced by summary records.
+    RolledUp,
+    Redacted,
+    /// Written to an archive file, then removed.
+    Archived,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LifecycleEvent {
+    pub action: LifecycleAction,
+    pub block_ids: Vec<BlockId>,
+    pub reason: String,
+    /// Summary record ids for roll-ups, the archive path for archives.
+    pub related: Vec<String>,
+    pub at: u64,
+}
+
+/// Summary of one sensor's blocks that a roll-up removed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RollupBody {
+    pub sensor_id: String,
+    pub from: u64,
+    pub to: u64,
+    pub aggregates: Vec<MetricAggregate>,
+    pub block_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RedactionBody {
+    pub block_id: String,
+    pub reason: String,
+}
+
+#[derive(Default)]
+pub struct Lifecycle {
+    next_id: u64,
+    subscribers: Vec<(u64, LifecycleCallback)>,
+    /// Redacted block ids with the key that redacted them; such blocks are
+    /// refused if they arrive again signed by that key.
+    redacted: HashMap<String, String>,
+}
+
+impl Lifecycle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&mut self, callback: LifecycleCallback) -> u64 {
+        self.next_id += 1;
+        self.subscribers.push((self.next_id, callback));
+        self.next_id
+    }
+
+    pub fn unsubscribe(&mut self, id: u64) -> bool {
+        let before = self.subscribers.len();
+        self.subscribers.retain(|(sid, _)| *sid != id);
+        self.subscribers.len() != before
+    }
+
+    pub fn subscribers(&self) -> Vec<LifecycleCallback> {
+        self.subscribers.iter().map(|(_, cb)| cb.clone()).collect()
+    }
+
+    pub fn is_redacted(&self, block: &TangleBlock) -> bool {
+        self.redacted.get(&block.id) == Some(&block.public_key)
+    }
+}
+
+impl EcoBlockContext {
+    /// Ids of stored blocks whose reading timestamp is before `cutoff`.
+    fn blocks_before(&self, cutoff: u64) -> Vec<String> {
+        self.block_log
+            .iter()
+            .filter(|b| b.data.data.timestamp < cutoff)
+            .map(|b| b.id.clone())
+            .collect()
+    }
+
+    /// Drops blocks from the block log, payload store and reading index. The
+    /// tangle keeps them for parent references.
+    fn remove_blocks(&mut self, ids: &[String]) -> Vec<TangleBlock> {
+        let removed: Vec<TangleBlock> = ids.iter().filter_map(|id| self.block_log.remove(id)).collect();
+        for block in &removed {
+            if let Ok(bytes) = serde_json::to_vec(&block.data.data) {
+                self.payloads.release(&payload_hash(&bytes)).ok();
+            }
+        }
+        self.readings.remove(&removed.iter().map(|b| b.id.clone()).collect());
+        self.materialized_views.invalidate();
+        for block in &removed {
+            self.block_signatures.remove(&block.id);
+            self.persist_remove(&block.id);
+            self.record_change(ChangeKind::BlockRemoved, block.id.as_str());
+        }
+        removed
+    }
+
+    fn emit_lifecycle(&mut self, action: LifecycleAction, blocks: &[TangleBlock], reason: &str, related: Vec<String>) {
+        if blocks.is_empty() {
+            return;
+        }
+        let event = LifecycleEvent {
+            action,
+            block_ids: blocks.iter().map(|b| BlockId::from_storage(b.id.clone())).collect(),
+            reason: reason.to_string(),
+            related,
+            at: clock::now_secs(),
+        };
+        for callback in self.lifecycle.subscribers() {
+            let event = event.clone();
+            self.pending.push(Box::new(move || callback(event)));
+        }
+    }
+
+    pub fn prune_blocks_before(&mut self, cutoff: u64, reason: &str) -> Vec<BlockId> {
+        let ids = self.blocks_before(cutoff);
+        let removed = self.remove_blocks(&ids);
+        self.emit_lifecycle(LifecycleAction::Pruned, &removed, reason, Vec::new());
+        removed.into_iter().map(|b| BlockId::from_storage(b.id)).collect()
+    }
+
+    /// Writes the blocks to `path` as a JSON array before removing them; nothing
+    /// is removed if the write fails.
+    pub fn archive_blocks_before(&mut self, cutoff: u64, path: &str) -> Result<Vec<BlockId>, BridgeError> {
+        let ids = self.blocks_before(cutoff);
+        let blocks: Vec<&TangleBlock> = ids.iter().filter_map(|id| self.block_log.get(id)).collect();
+        let json = serde_json::to_vec(&blocks).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        fs::write(path, json).map_err(|e| BridgeError::Io(e.to_string()))?;
+        let removed = self.remove_blocks(&ids);
+        self.emit_lifecycle(LifecycleAction::Archived, &removed, "archived", vec![path.to_string()]);
+        Ok(removed.into_iter().map(|b| BlockId::from_storage(b.id)).collect())
+    }
+
+    /// Replaces the blocks with one signed summary record per sensor. Nothing
+    /// is removed unless every summary could be signed.
+    pub fn roll_up_blocks_before(&mut self, cutoff: u64) -> Result<Vec<Record>, BridgeError> {
+        let ids = self.blocks_before(cutoff);
+        let mut by_sensor: BTreeMap<String, Vec<&Reading>> = BTreeMap::new();
+        for reading in ids.iter().filter_map(|id| self.readings.get(id)) {
+            by_sensor.entry(reading.sensor_id.clone()).or_default().push(reading);
+        }
+        let now = clock::now_secs();
+        let summaries = by_sensor
+            .into_iter()
+            .map(|(sensor_id, readings)| {
+                let metrics: HashSet<&String> = readings.iter().flat_map(|r| r.values.keys()).collect();
+                let mut aggregates: Vec<MetricAggregate> = metrics
+                    .into_iter()
+                    .map(|m| MetricAggregate::from_values(m, readings.iter().filter_map(|r| r.value(m))))
+                    .collect();
+                aggregates.sort_by(|a, b| a.metric.cmp(&b.metric));
+                let body = RollupBody {
+                    sensor_id,
+                    from: readings.iter().map(|r| r.timestamp).min().unwrap_or_default(),
+                    to: readings.iter().map(|r| r.timestamp).max().unwrap_or_default(),
+                    aggregates,
+                    block_ids: readings.iter().map(|r| r.block_id.clone()).collect(),
+                };
+                self.sign_record(ROLLUP_KIND, serde_json::to_value(&body).unwrap_or_default(), now)
+            })
+            .collect::<Result<Vec<Record>, BridgeError>>()?;
+        for record in &summaries {
+            self.records.insert(record.clone());
+        }
+        let removed = self.remove_blocks(&ids);
+        let related = summaries.iter().map(|r| r.id.clone()).collect();
+        self.emit_lifecycle(LifecycleAction::RolledUp, &removed, "rolled up", related);
+        Ok(summaries)
+    }
+
+    /// Signs a redaction of a block created by this node and removes it locally;
+    /// peers remove it when the redaction reaches them.
+    pub fn redact_block(&mut self, block_id: &BlockId, reason: &str) -> Result<Record, BridgeError> {
+        let block = self
+            .block_log
+            .get(block_id.as_str())
+            .ok_or_else(|| BridgeError::NotFound(format!("Unknown block: {}", block_id)))?;
+        if block.public_key != self.keypair.public_key_hex() {
+            return Err(BridgeError::Unauthorized(format!("block {} was not created by this node", block_id)));
+        }
+        let body = RedactionBody {
+            block_id: block_id.to_string(),
+            reason: reason.to_string(),
+        };
+        let record = self.sign_record(REDACTION_KIND, serde_json::to_value(&body).unwrap_or_default(), clock::now_secs())?;
+        self.apply_redaction(&record)?;
+        self.records.insert(record.clone());
+        Ok(record)
+    }
+
+    /// Only the key that signed a block may redact it.
+    pub(crate) fn apply_redaction(&mut self, record: &Record) -> Result<(), BridgeError> {
+        let body: RedactionBody = serde_json::from_value(record.body.clone())
+            .map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+        if let Some(block) = self.block_log.get(&body.block_id) {
+            if block.public_key != record.author {
+                return Err(BridgeError::Unauthorized(format!("{} cannot redact block {}", record.author, body.block_id)));
+            }
+        }
+        self.lifecycle.redacted.insert(body.block_id.clone(), record.author.clone());
+        let removed = self.remove_blocks(&[body.block_id]);
+        self.emit_lifecycle(LifecycleAction::Redacted, &removed, &body.reason, vec![record.id.clone()]);
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+//! Provenance of a block for rendering as a timeline: the chain of ancestors
+//! from the block back to the nearest checkpoint, following the first parent
+//! held locally at each hop, as `git log --first-parent` does.
+
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+use crate::EcoBlockContext;
+
+/// Hops followed before the chain is cut off.
+pub const MAX_LINEAGE_HOPS: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LineageHop {
+    pub block_id: String,
+    pub signer: String,
+    pub timestamp: u64,
+    /// Sensor of the block's reading, if it carries one.
+    pub sensor_id: Option<String>,
+    /// Parents besides the one followed; the timeline can mark the merge.
+    pub other_parents: Vec<String>,
+    /// Seconds since the previous hop of the timeline; `None` on the first.
+    pub gap_secs: Option<u64>,
+}
+
+/// Why the chain stops where it does.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LineageEnd {
+    /// The oldest hop is covered by this checkpoint, the first to cover it.
+    Checkpoint { id: String, created_at: u64 },
+    /// The oldest hop has no parents.
+    Genesis,
+    /// The oldest hop names parents, none of them held locally.
+    MissingParent { ids: Vec<String> },
+    /// `MAX_LINEAGE_HOPS` were followed.
+    Truncated,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lineage {
+    pub block_id: String,
+    /// Oldest first, ending with the block itself.
+    pub hops: Vec<LineageHop>,
+    pub end: LineageEnd,
+}
+
+impl EcoBlockContext {
+    pub fn lineage(&self, block_id: &str) -> Result<Lineage, BridgeError> {
+        let mut current = self.block_log.get(block_id).ok_or_else(|| BridgeError::NotFound(format!("Unknown block {}", block_id)))?;
+        let mut hops = Vec::new();
+        let end = loop {
+            let followed = current.data.parents.iter().find(|p| self.block_log.contains(p));
+            hops.push(LineageHop {
+                block_id: current.id.clone(),
+                signer: current.public_key.clone(),
+                timestamp: current.data.data.timestamp,
+                sensor_id: self.readings.get(&current.id).map(|r| r.sensor_id.clone()),
+                other_parents: current.data.parents.iter().filter(|&p| Some(p) != followed).cloned().collect(),
+                gap_secs: None,
+            });
+            let position = self.block_log.position(&current.id);
+            let checkpoint = self
+                .checkpoints
+                .iter()
+                .filter(|c| position.is_some_and(|p| p < c.block_count))
+                .min_by_key(|c| c.block_count);
+            if let Some(checkpoint) = checkpoint {
+                break LineageEnd::Checkpoint {
+                    id: checkpoint.id.clone(),
+                    created_at: checkpoint.created_at,
+                };
+            }
+            if hops.len() == MAX_LINEAGE_HOPS {
+                break LineageEnd::Truncated;
+            }
+            match followed.and_then(|p| self.block_log.get(p)) {
+                Some(parent) => current = parent,
+                None if current.data.parents.is_empty() => break LineageEnd::Genesis,
+                None => break LineageEnd::MissingParent { ids: current.data.parents.clone() },
+            }
+        };
+        hops.reverse();
+        let gaps: Vec<u64> = hops.windows(2).map(|w| w[1].timestamp.saturating_sub(w[0].timestamp)).collect();
+        for (hop, gap) in hops.iter_mut().skip(1).zip(gaps) {
+            hop.gap_secs = Some(gap);
+        }
+        Ok(Lineage {
+            block_id: block_id.to_string(),
+            hops,
+            end,
+        })
+    }
+}
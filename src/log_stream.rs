@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+use crate::logs::{LogEntry, LogLevel};
+use crate::peer_id::PeerId;
+use crate::wire::{self, Frame, FrameKind, MAX_PAYLOAD_LEN};
+use crate::{clock, sealed, EcoBlockContext};
+
+/// Longest window a single `start_log_stream` call may open.
+pub const MAX_LOG_STREAM_SECS: u64 = 3600;
+/// Entries held between two `take_log_stream_frame` calls; older ones are dropped.
+pub const MAX_STREAM_BUFFER: usize = 500;
+/// Ephemeral key, nonce and tag added by sealing.
+const SEAL_OVERHEAD: usize = 128;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogStreamStatus {
+    pub peer: PeerId,
+    pub min_level: LogLevel,
+    pub started_at: u64,
+    pub until: u64,
+    pub sent: u64,
+    /// Entries dropped because the host did not take frames fast enough.
+    pub dropped: u64,
+}
+
+/// A frame for the host transport to deliver to `peer`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogStreamFrame {
+    pub peer: PeerId,
+    pub frame: Vec<u8>,
+}
+
+#[derive(Debug, Default)]
+pub struct LogStream {
+    active: Option<LogStreamStatus>,
+    buffer: Vec<LogEntry>,
+}
+
+impl LogStream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The open stream, if its window has not ended by `now`.
+    pub fn status(&self, now: u64) -> Option<LogStreamStatus> {
+        self.active.clone().filter(|s| now < s.until)
+    }
+
+    pub fn offer(&mut self, entry: &LogEntry) {
+        let Some(status) = self.active.as_mut().filter(|s| entry.timestamp < s.until) else {
+            return;
+        };
+        if entry.level < status.min_level {
+            return;
+        }
+        if self.buffer.len() == MAX_STREAM_BUFFER {
+            self.buffer.remove(0);
+            status.dropped += 1;
+        }
+        self.buffer.push(entry.clone());
+    }
+}
+
+impl EcoBlockContext {
+    /// Streams log entries at `min_level` or above to `peer` for `duration`
+    /// seconds. Only policy admins may receive them, and a new call replaces
+    /// the running stream.
+    pub fn start_log_stream(&mut self, peer: &PeerId, duration: u64, min_level: LogLevel) -> Result<LogStreamStatus, BridgeError> {
+        let me = self.keypair.public_key_hex();
+        if !self.policy.authorities(&me).iter().any(|a| a == peer.as_str()) {
+            return Err(BridgeError::Unauthorized(format!("{} is not an admin of this node", peer)));
+        }
+        if peer.public_key().is_none() {
+            return Err(BridgeError::InvalidInput(format!("Invalid peer id: {} is not a public key", peer)));
+        }
+        if duration == 0 || duration > MAX_LOG_STREAM_SECS {
+            return Err(BridgeError::InvalidInput(format!("Invalid duration: log streams last 1 to {} seconds", MAX_LOG_STREAM_SECS)));
+        }
+        let now = clock::now_secs();
+        let status = LogStreamStatus {
+            peer: peer.clone(),
+            min_level,
+            started_at: now,
+            until: now + duration,
+            sent: 0,
+            dropped: 0,
+        };
+        self.log_stream.active = Some(status.clone());
+        self.log_stream.buffer.clear();
+        self.log(LogLevel::Info, format!("Streaming logs to {} for {}s", peer, duration));
+        Ok(status)
+    }
+
+    pub fn stop_log_stream(&mut self) -> Option<LogStreamStatus> {
+        let status = self.log_stream.active.take()?;
+        self.log_stream.buffer.clear();
+        self.log(LogLevel::Info, format!("Stopped log stream to {}", status.peer));
+        Some(status)
+    }
+
+    /// Seals buffered entries to the admin's key as one `LogStream` frame,
+    /// as many as fit the wire payload limit; call again until it returns
+    /// `None`. Entries buffered before the window ended are still delivered,
+    /// then the stream is closed.
+    pub fn take_log_stream_frame(&mut self) -> Result<Option<LogStreamFrame>, BridgeError> {
+        let now = clock::now_secs();
+        let Some(status) = self.log_stream.active.as_mut() else {
+            return Ok(None);
+        };
+        let peer = status.peer.clone();
+        if self.log_stream.buffer.is_empty() {
+            if now >= status.until {
+                self.log_stream.active = None;
+                self.log(LogLevel::Info, format!("Log stream to {} ended", peer));
+            }
+            return Ok(None);
+        }
+        let mut count = self.log_stream.buffer.len();
+        let json = loop {
+            let json = serde_json::to_vec(&self.log_stream.buffer[..count]).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+            if json.len() <= MAX_PAYLOAD_LEN - SEAL_OVERHEAD || count == 1 {
+                break json;
+            }
+            count /= 2;
+        };
+        let payload = sealed::seal_to(peer.as_str(), &json)?;
+        let frame = Frame::new(FrameKind::LogStream, payload).encode()?;
+        self.log_stream.buffer.drain(..count);
+        status.sent += count as u64;
+        Ok(Some(LogStreamFrame { peer, frame }))
+    }
+
+    pub fn log_stream_status(&self) -> Option<LogStreamStatus> {
+        self.log_stream.status(clock::now_secs())
+    }
+
+    /// Opens a frame received from a node streaming its logs to this one.
+    pub fn open_log_stream_frame(&self, bytes: &[u8]) -> Result<Vec<LogEntry>, BridgeError> {
+        let frame = wire::parse_frame(bytes)?;
+        if frame.kind != FrameKind::LogStream {
+            return Err(BridgeError::Wire(format!("expected a log stream frame, got {:?}", frame.kind)));
+        }
+        let opened = sealed::open_sealed(&self.keypair, &frame.payload)?;
+        serde_json::from_slice(&opened).map_err(|e| BridgeError::Deserialization(e.to_string()))
+    }
+}
@@ -0,0 +1,61 @@
+use std::collections::VecDeque;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_LOG_CAPACITY: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub timestamp: u64,
+    pub level: LogLevel,
+    pub message: String,
+}
+
+/// Bounded in-memory log of notable bridge events, oldest entries dropped first.
+#[derive(Debug)]
+pub struct LogBuffer {
+    capacity: usize,
+    entries: VecDeque<LogEntry>,
+}
+
+impl Default for LogBuffer {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_LOG_CAPACITY)
+    }
+}
+
+impl LogBuffer {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, timestamp: u64, level: LogLevel, message: String) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(LogEntry { timestamp, level, message });
+    }
+
+    pub fn recent(&self, limit: usize, min_level: LogLevel) -> Vec<LogEntry> {
+        let mut entries: Vec<LogEntry> = self
+            .entries
+            .iter()
+            .rev()
+            .filter(|e| e.level >= min_level)
+            .take(limit)
+            .cloned()
+            .collect();
+        entries.reverse();
+        entries
+    }
+}
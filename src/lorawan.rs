@@ -0,0 +1,236 @@
+//! Uplinks forwarded by a LoRaWAN network server. The host hands each uplink
+//! to `ingest_lorawan_uplink`; the payload is decoded by the codec registered
+//! for its FPort and signed as a reading of the sensor registered for its DevEUI.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use crate::block_id::BlockId;
+use crate::error::BridgeError;
+use crate::ingest::ReadingGroups;
+use crate::EcoBlockContext;
+
+/// Turns an uplink payload into named values.
+pub type UplinkDecoder = Arc<dyn Fn(&[u8]) -> Result<BTreeMap<String, f64>, BridgeError> + Send + Sync>;
+
+#[derive(Clone)]
+pub enum UplinkCodec {
+    CayenneLpp,
+    Custom(UplinkDecoder),
+}
+
+impl UplinkCodec {
+    pub fn decode(&self, payload: &[u8]) -> Result<BTreeMap<String, f64>, BridgeError> {
+        match self {
+            Self::CayenneLpp => decode_cayenne_lpp(payload),
+            Self::Custom(decoder) => decoder(payload),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LorawanUplink {
+    /// 16 hex digits.
+    pub dev_eui: String,
+    pub f_port: u8,
+    pub f_cnt: u32,
+    pub payload: Vec<u8>,
+    /// Seconds; the time of ingest when the network server gives none.
+    pub received_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LorawanSensor {
+    pub dev_eui: String,
+    pub sensor_id: String,
+    pub last_f_cnt: Option<u32>,
+    pub last_uplink: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LorawanIngest {
+    pub sensor_id: String,
+    /// `None` for duplicates and for uplinks that decoded to no values.
+    pub block: Option<BlockId>,
+    /// The same frame counter as the previous uplink, as when several
+    /// gateways heard it.
+    pub duplicate: bool,
+}
+
+#[derive(Default)]
+pub struct LorawanState {
+    sensors: BTreeMap<String, LorawanSensor>,
+    /// `None` is the codec for ports without one of their own.
+    codecs: HashMap<Option<u8>, UplinkCodec>,
+}
+
+/// DevEUIs are compared in lower case.
+fn normalize_eui(dev_eui: &str) -> Result<String, BridgeError> {
+    if dev_eui.len() != 16 || !dev_eui.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(BridgeError::InvalidInput(format!("Invalid DevEUI {:?}: expected 16 hex digits", dev_eui)));
+    }
+    Ok(dev_eui.to_ascii_lowercase())
+}
+
+impl LorawanState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register_sensor(&mut self, dev_eui: &str, sensor_id: &str) -> Result<(), BridgeError> {
+        if sensor_id.is_empty() {
+            return Err(BridgeError::InvalidInput("Invalid sensor id: empty".to_string()));
+        }
+        let dev_eui = normalize_eui(dev_eui)?;
+        self.sensors.insert(
+            dev_eui.clone(),
+            LorawanSensor {
+                dev_eui,
+                sensor_id: sensor_id.to_string(),
+                last_f_cnt: None,
+                last_uplink: None,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn remove_sensor(&mut self, dev_eui: &str) -> bool {
+        self.sensors.remove(&dev_eui.to_ascii_lowercase()).is_some()
+    }
+
+    pub fn sensors(&self) -> Vec<LorawanSensor> {
+        self.sensors.values().cloned().collect()
+    }
+
+    pub fn register_codec(&mut self, f_port: Option<u8>, codec: UplinkCodec) {
+        self.codecs.insert(f_port, codec);
+    }
+
+    pub fn remove_codec(&mut self, f_port: Option<u8>) -> bool {
+        self.codecs.remove(&f_port).is_some()
+    }
+
+    /// The codec for `uplink`, once its device and port are known to be accepted.
+    pub fn codec_for(&self, uplink: &LorawanUplink) -> Result<UplinkCodec, BridgeError> {
+        let dev_eui = normalize_eui(&uplink.dev_eui)?;
+        if !self.sensors.contains_key(&dev_eui) {
+            return Err(BridgeError::NotFound(format!("Unknown LoRaWAN device {}", dev_eui)));
+        }
+        if !(1..=223).contains(&uplink.f_port) {
+            return Err(BridgeError::InvalidInput(format!("Invalid LoRaWAN uplink: FPort {} is not an application port", uplink.f_port)));
+        }
+        self.codecs
+            .get(&Some(uplink.f_port))
+            .or_else(|| self.codecs.get(&None))
+            .cloned()
+            .ok_or_else(|| BridgeError::NotFound(format!("Unknown LoRaWAN codec for FPort {}", uplink.f_port)))
+    }
+}
+
+/// Cayenne LPP data types by code, each with the metric name, width, signedness
+/// and divisor of its fields.
+const LPP_TYPES: &[(u8, &[(&str, usize, bool, f64)])] = &[
+    (0, &[("digital_input", 1, false, 1.0)]),
+    (1, &[("digital_output", 1, false, 1.0)]),
+    (2, &[("analog_input", 2, true, 100.0)]),
+    (3, &[("analog_output", 2, true, 100.0)]),
+    (100, &[("generic", 4, false, 1.0)]),
+    (101, &[("illuminance", 2, false, 1.0)]),
+    (102, &[("presence", 1, false, 1.0)]),
+    (103, &[("temperature", 2, true, 10.0)]),
+    (104, &[("humidity", 1, false, 2.0)]),
+    (113, &[("acceleration_x", 2, true, 1000.0), ("acceleration_y", 2, true, 1000.0), ("acceleration_z", 2, true, 1000.0)]),
+    (115, &[("pressure", 2, false, 10.0)]),
+    (116, &[("voltage", 2, false, 100.0)]),
+    (117, &[("current", 2, false, 1000.0)]),
+    (118, &[("frequency", 4, false, 1.0)]),
+    (120, &[("percentage", 1, false, 1.0)]),
+    (121, &[("altitude", 2, true, 1.0)]),
+    (125, &[("concentration", 2, false, 1.0)]),
+    (128, &[("power", 2, false, 1.0)]),
+    (130, &[("distance", 4, false, 1000.0)]),
+    (131, &[("energy", 4, false, 1000.0)]),
+    (132, &[("direction", 2, false, 1.0)]),
+    (133, &[("unix_time", 4, false, 1.0)]),
+    (134, &[("gyration_x", 2, true, 100.0), ("gyration_y", 2, true, 100.0), ("gyration_z", 2, true, 100.0)]),
+    (135, &[("red", 1, false, 1.0), ("green", 1, false, 1.0), ("blue", 1, false, 1.0)]),
+    (136, &[("latitude", 3, true, 10000.0), ("longitude", 3, true, 10000.0), ("gps_altitude", 3, true, 100.0)]),
+    (142, &[("switch", 1, false, 1.0)]),
+];
+
+/// Decodes a Cayenne LPP payload in the units of the format (°C, %RH, hPa, V,
+/// A, g, °/s, degrees). A metric sent on several channels is suffixed with
+/// `_<channel>`; one sent on a single channel keeps its bare name.
+pub fn decode_cayenne_lpp(payload: &[u8]) -> Result<BTreeMap<String, f64>, BridgeError> {
+    let mut values: Vec<(u8, &str, f64)> = Vec::new();
+    let mut pos = 0;
+    while pos < payload.len() {
+        let (channel, code) = match payload.get(pos..pos + 2) {
+            Some(&[channel, code]) => (channel, code),
+            _ => return Err(BridgeError::Decoding("Cayenne LPP payload truncated".to_string())),
+        };
+        pos += 2;
+        let (_, fields) = LPP_TYPES
+            .iter()
+            .find(|(c, _)| *c == code)
+            .ok_or_else(|| BridgeError::Decoding(format!("unknown Cayenne LPP type {}", code)))?;
+        for &(name, width, signed, divisor) in fields.iter() {
+            let bytes = payload
+                .get(pos..pos + width)
+                .ok_or_else(|| BridgeError::Decoding("Cayenne LPP payload truncated".to_string()))?;
+            pos += width;
+            let raw = bytes.iter().fold(0u64, |n, b| n << 8 | u64::from(*b));
+            let raw = if signed {
+                let shift = 64 - 8 * width as u32;
+                ((raw << shift) as i64 >> shift) as f64
+            } else {
+                raw as f64
+            };
+            values.push((channel, name, raw / divisor));
+        }
+    }
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for (_, name, _) in &values {
+        *counts.entry(*name).or_default() += 1;
+    }
+    Ok(values
+        .into_iter()
+        .map(|(channel, name, value)| {
+            let key = if counts[name] > 1 { format!("{}_{}", name, channel) } else { name.to_string() };
+            (key, value)
+        })
+        .collect())
+}
+
+impl EcoBlockContext {
+    /// Signs the decoded `values` of `uplink` as one reading of its registered
+    /// sensor. A repeated frame counter is reported as a duplicate and stores
+    /// nothing.
+    pub fn ingest_lorawan(&mut self, uplink: &LorawanUplink, values: BTreeMap<String, f64>) -> Result<LorawanIngest, BridgeError> {
+        let dev_eui = normalize_eui(&uplink.dev_eui)?;
+        let sensor = self.lorawan.sensors.get(&dev_eui).ok_or_else(|| BridgeError::NotFound(format!("Unknown LoRaWAN device {}", dev_eui)))?;
+        let mut ingest = LorawanIngest {
+            sensor_id: sensor.sensor_id.clone(),
+            block: None,
+            duplicate: sensor.last_f_cnt == Some(uplink.f_cnt),
+        };
+        if ingest.duplicate {
+            return Ok(ingest);
+        }
+        if let Some(name) = values.keys().find(|k| k.is_empty() || *k == "sensor_id" || *k == "timestamp") {
+            return Err(BridgeError::InvalidInput(format!("Invalid decoded value name {:?}", name)));
+        }
+        let timestamp = uplink.received_at.unwrap_or_else(crate::clock::now_secs);
+        let mut groups = ReadingGroups::new();
+        for (name, value) in &values {
+            groups.insert(Some(ingest.sensor_id.clone()), timestamp, name, None, *value);
+        }
+        let readings = groups.into_readings();
+        ingest.block = self.ingest_decoded(&readings)?.into_iter().next();
+        if let Some(sensor) = self.lorawan.sensors.get_mut(&dev_eui) {
+            sensor.last_f_cnt = Some(uplink.f_cnt);
+            sensor.last_uplink = Some(timestamp);
+        }
+        Ok(ingest)
+    }
+}
@@ -0,0 +1,319 @@
+//! Time-bounded entry points for OS background schedulers (WorkManager,
+//! BGTaskScheduler), which give a task a few seconds and kill it when they run
+//! out. `run_maintenance_slice` works through the maintenance tasks in a fixed
+//! order, one bounded step at a time, and `run_sync_slice` runs anti-entropy
+//! with one peer. Both stop at their time budget and record their progress in
+//! `maintenance.json` in the node directory, so the next slice, even in a new
+//! process, resumes where the last one stopped.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+use crate::logs::LogLevel;
+use crate::peer_id::PeerId;
+use crate::watchdog::{self, RestartHook};
+use crate::{clock, index_builder, EcoBlockContext, CONTEXT};
+
+pub const MAINTENANCE_FILE: &str = "maintenance.json";
+/// Slices killed while on a task before the task is skipped, so a task that
+/// gets the process killed cannot stall the others.
+pub const MAX_ATTEMPTS: u32 = 3;
+/// A sync slice finishes once no frame has arrived for this long.
+pub const SYNC_IDLE_MS: u64 = 500;
+/// Pause between transport polls while waiting for frames.
+pub const SYNC_POLL_MS: u64 = 20;
+/// The block store is compacted once it holds at least this many lines for
+/// removed blocks and twice as many lines as live blocks.
+const COMPACTION_MIN_DEAD: usize = 1024;
+
+pub fn maintenance_path(path: &Path) -> PathBuf {
+    path.join(MAINTENANCE_FILE)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceTask {
+    /// Expires encounters and flushes the wake, quiet-hours and airtime queues.
+    #[default]
+    Queues,
+    /// Indexes loaded blocks, one batch per step.
+    Indexes,
+    Watchdog,
+    SilentSensors,
+    /// Warms prefetched and saved queries.
+    Caches,
+    /// Samples call metrics and publishes the stats summary when due.
+    MetricsSample,
+    Backup,
+    CloudSync,
+    /// Syncs with group members whose group sync interval has passed.
+    GroupSync,
+    /// Compacts the block store when removed blocks make up most of it.
+    Compaction,
+    Flush,
+}
+
+impl MaintenanceTask {
+    pub const ALL: [MaintenanceTask; 11] = [
+        MaintenanceTask::Queues,
+        MaintenanceTask::Indexes,
+        MaintenanceTask::Watchdog,
+        MaintenanceTask::SilentSensors,
+        MaintenanceTask::Caches,
+        MaintenanceTask::MetricsSample,
+        MaintenanceTask::Backup,
+        MaintenanceTask::CloudSync,
+        MaintenanceTask::GroupSync,
+        MaintenanceTask::Compaction,
+        MaintenanceTask::Flush,
+    ];
+
+    fn following(self) -> Option<MaintenanceTask> {
+        let position = Self::ALL.iter().position(|t| *t == self)?;
+        Self::ALL.get(position + 1).copied()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceReport {
+    /// Tasks finished in this slice, in order.
+    pub completed: Vec<MaintenanceTask>,
+    pub skipped: Vec<MaintenanceTask>,
+    /// True when the slice reached the end of the task list.
+    pub round_finished: bool,
+    /// Task the next slice starts with.
+    pub next: MaintenanceTask,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncSliceReport {
+    pub frames: usize,
+    /// New blocks stored from any peer during the slice.
+    pub blocks_received: u64,
+    /// True when the exchange went quiet; the next slice starts a new one.
+    pub finished: bool,
+    pub elapsed_ms: u64,
+}
+
+/// An exchange with one peer that has not gone quiet yet.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncSession {
+    pub started_at: u64,
+    pub slices: u32,
+    pub frames: u64,
+    pub blocks_received: u64,
+    /// Frames in the last slice; none means the summary is sent again.
+    pub last_frames: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceProgress {
+    pub next: MaintenanceTask,
+    /// Slices that started `next` and were killed before they ended.
+    pub attempts: u32,
+    pub rounds: u64,
+    pub last_round_at: Option<u64>,
+    pub sync: BTreeMap<String, SyncSession>,
+}
+
+impl MaintenanceProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(&mut self, path: &Path) -> Result<(), BridgeError> {
+        if !path.exists() {
+            return Ok(());
+        }
+        let bytes = fs::read(path).map_err(|e| BridgeError::Io(e.to_string()))?;
+        *self = serde_json::from_slice(&bytes).map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+        Ok(())
+    }
+
+    fn save(&self, path: &Path) -> Result<(), BridgeError> {
+        let json = serde_json::to_vec_pretty(self).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        fs::write(path, json).map_err(|e| BridgeError::Io(e.to_string()))
+    }
+
+    fn advance(&mut self) -> bool {
+        self.attempts = 0;
+        match self.next.following() {
+            Some(task) => {
+                self.next = task;
+                false
+            }
+            None => {
+                self.next = MaintenanceTask::Queues;
+                self.rounds += 1;
+                self.last_round_at = Some(clock::now_secs());
+                true
+            }
+        }
+    }
+}
+
+impl EcoBlockContext {
+    fn persist_maintenance(&mut self) {
+        let Some(path) = &self.node_path else {
+            return;
+        };
+        if let Err(e) = self.maintenance.save(&maintenance_path(path)) {
+            self.log(LogLevel::Warn, format!("Maintenance progress not saved: {}", e));
+        }
+    }
+
+    /// Counts the slice against the task it resumes until it ends normally.
+    pub(crate) fn begin_maintenance_slice(&mut self, report: &mut MaintenanceReport) {
+        self.maintenance.attempts += 1;
+        while self.maintenance.attempts > MAX_ATTEMPTS {
+            let task = self.maintenance.next;
+            self.log(LogLevel::Warn, format!("Maintenance: skipping {:?} after {} unfinished attempts", task, MAX_ATTEMPTS));
+            report.skipped.push(task);
+            if self.maintenance.advance() {
+                report.round_finished = true;
+            }
+            self.maintenance.attempts = 1;
+        }
+        self.persist_maintenance();
+    }
+
+    /// Runs one bounded step of the current task. True once the round is
+    /// finished.
+    pub(crate) fn maintenance_step(&mut self, report: &mut MaintenanceReport) -> bool {
+        let task = self.maintenance.next;
+        let now = clock::now_secs();
+        let done = match task {
+            MaintenanceTask::Queues => {
+                self.data_mule.expire(now);
+                self.flush_wake_queue();
+                self.flush_quiet_queue(now);
+                self.flush_airtime_queue();
+                self.deadlines.prune(now);
+                true
+            }
+            MaintenanceTask::Indexes => {
+                self.build_indexes(index_builder::BATCH_SIZE);
+                self.indexes_ready()
+            }
+            MaintenanceTask::Watchdog => {
+                self.check_watchdog();
+                true
+            }
+            MaintenanceTask::SilentSensors => {
+                self.check_silent_sensors();
+                true
+            }
+            MaintenanceTask::Caches => {
+                self.warm_prefetched();
+                self.warm_saved_queries();
+                true
+            }
+            MaintenanceTask::MetricsSample => {
+                if let Err(e) = self.sample_metrics() {
+                    self.log(LogLevel::Warn, format!("Maintenance: metrics sample failed: {}", e));
+                }
+                self.run_scheduled_stats_publish(now);
+                true
+            }
+            MaintenanceTask::Backup => {
+                self.scheduler_heartbeat();
+                self.run_scheduled_backup(now);
+                true
+            }
+            MaintenanceTask::CloudSync => {
+                self.run_scheduled_cloud_sync(now);
+                true
+            }
+            MaintenanceTask::GroupSync => {
+                self.run_scheduled_group_syncs(now);
+                true
+            }
+            MaintenanceTask::Compaction => {
+                let live = self.block_log.len();
+                let worthwhile = self.block_store.as_ref().is_some_and(|s| {
+                    let entries = s.status(live).entries;
+                    entries >= live * 2 && entries - live >= COMPACTION_MIN_DEAD
+                });
+                // Compaction writes a new file and renames it over the old one,
+                // so a kill midway leaves the old store intact.
+                if worthwhile {
+                    if let Err(e) = self.compact_block_store() {
+                        self.log(LogLevel::Warn, format!("Maintenance: compaction failed: {}", e));
+                    }
+                }
+                true
+            }
+            MaintenanceTask::Flush => {
+                if self.block_store.is_some() {
+                    if let Err(e) = self.flush_block_store() {
+                        self.log(LogLevel::Warn, format!("Maintenance: flush failed: {}", e));
+                    }
+                }
+                true
+            }
+        };
+        if !done {
+            return false;
+        }
+        report.completed.push(task);
+        let finished = self.maintenance.advance();
+        // The next task counts as started in this slice.
+        self.maintenance.attempts = 1;
+        self.persist_maintenance();
+        finished
+    }
+
+    pub(crate) fn end_maintenance_slice(&mut self, report: &mut MaintenanceReport) {
+        self.maintenance.attempts = 0;
+        self.persist_maintenance();
+        report.next = self.maintenance.next;
+    }
+
+    /// Starts or resumes the exchange with `peer`, sending the summary when it
+    /// is new or the last slice heard nothing back.
+    pub(crate) fn begin_sync_slice(&mut self, peer: &PeerId) -> Result<(), BridgeError> {
+        if !self.sync.is_attached() {
+            return Err(BridgeError::NotFound("Unknown transport: none is attached".to_string()));
+        }
+        let session = self.maintenance.sync.get(peer.as_str());
+        if session.is_none_or(|s| s.last_frames == 0) {
+            self.sync_with_peer(peer)?;
+        }
+        let session = self.maintenance.sync.entry(peer.as_str().to_string()).or_insert_with(|| SyncSession {
+            started_at: clock::now_secs(),
+            ..SyncSession::default()
+        });
+        session.slices += 1;
+        let resync = peer.clone();
+        let restart: RestartHook = Arc::new(move |_| {
+            let mut ctx = CONTEXT.lock().unwrap();
+            if let Err(e) = ctx.sync_with_peer(&resync) {
+                ctx.log(LogLevel::Warn, format!("Sync with {} not restarted: {}", resync, e));
+            }
+        });
+        self.watch_or_heartbeat(&watchdog::sync_component(peer), watchdog::SYNC_TIMEOUT, Some(restart));
+        Ok(())
+    }
+
+    /// Scheduled tasks run from background steps and maintenance rounds; the
+    /// watchdog reports them degraded when neither runs for a day.
+    pub(crate) fn scheduler_heartbeat(&mut self) {
+        self.watch_or_heartbeat(watchdog::SCHEDULER, watchdog::SCHEDULER_TIMEOUT, None);
+    }
+
+    pub(crate) fn end_sync_slice(&mut self, peer: &PeerId, report: &SyncSliceReport) {
+        if report.finished {
+            self.maintenance.sync.remove(peer.as_str());
+            self.watchdog.unwatch(&watchdog::sync_component(peer));
+        } else if let Some(session) = self.maintenance.sync.get_mut(peer.as_str()) {
+            session.frames += report.frames as u64;
+            session.blocks_received += report.blocks_received;
+            session.last_frames = report.frames;
+        }
+        self.persist_maintenance();
+    }
+}
@@ -0,0 +1,259 @@
+//! Materialized views for dashboards. A view names a metric and a shape
+//! (latest value per sensor, daily average, ...) and is kept up to date as
+//! readings are indexed, so reading a row costs one lookup instead of a pass
+//! over every reading. Definitions are kept in the node directory. Flagging or
+//! removing readings marks the views stale; they are rebuilt once on the next
+//! read.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+use crate::readings::Reading;
+use crate::EcoBlockContext;
+
+const MATERIALIZED_VIEWS_FILE: &str = "materialized_views.json";
+pub const MAX_MATERIALIZED_VIEWS: usize = 64;
+const DAY_SECS: u64 = 24 * 3600;
+
+/// Where the node at `path` keeps its view definitions.
+pub fn materialized_views_path(path: &Path) -> PathBuf {
+    path.join(MATERIALIZED_VIEWS_FILE)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViewKind {
+    /// The newest value per sensor, keyed by sensor id.
+    LatestPerSensor,
+    /// Mean per UTC day, keyed by the day's start.
+    DailyAverage,
+    /// Mean per sensor over every reading.
+    AveragePerSensor,
+    /// Smallest and largest value per UTC day; the row value is the maximum.
+    DailyRange,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViewDefinition {
+    pub name: String,
+    pub metric: String,
+    pub kind: ViewKind,
+}
+
+impl ViewDefinition {
+    pub fn validate(&self) -> Result<(), BridgeError> {
+        if self.name.is_empty() || self.metric.is_empty() {
+            return Err(BridgeError::InvalidInput("Invalid materialized view: name and metric must not be empty".to_string()));
+        }
+        Ok(())
+    }
+
+    fn key(&self, reading: &Reading) -> String {
+        match self.kind {
+            ViewKind::LatestPerSensor | ViewKind::AveragePerSensor => reading.sensor_id.clone(),
+            ViewKind::DailyAverage | ViewKind::DailyRange => (reading.timestamp - reading.timestamp % DAY_SECS).to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ViewRow {
+    /// The sensor id or the day's start.
+    pub key: String,
+    pub value: f64,
+    /// Smallest value, for `DailyRange`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    pub count: u64,
+    /// Timestamp of the newest reading in the row.
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Accumulator {
+    sum: f64,
+    min: f64,
+    max: f64,
+    count: u64,
+    last: (u64, f64),
+}
+
+impl Accumulator {
+    fn new(timestamp: u64, value: f64) -> Self {
+        Self {
+            sum: 0.0,
+            min: value,
+            max: value,
+            count: 0,
+            last: (timestamp, value),
+        }
+    }
+
+    fn add(&mut self, timestamp: u64, value: f64) {
+        self.sum += value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.count += 1;
+        if timestamp >= self.last.0 {
+            self.last = (timestamp, value);
+        }
+    }
+
+    fn row(&self, kind: ViewKind, key: &str) -> ViewRow {
+        let value = match kind {
+            ViewKind::LatestPerSensor => self.last.1,
+            ViewKind::DailyAverage | ViewKind::AveragePerSensor => self.sum / self.count.max(1) as f64,
+            ViewKind::DailyRange => self.max,
+        };
+        ViewRow {
+            key: key.to_string(),
+            value,
+            min: (kind == ViewKind::DailyRange).then_some(self.min),
+            count: self.count,
+            updated_at: self.last.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct View {
+    definition: ViewDefinition,
+    rows: HashMap<String, Accumulator>,
+}
+
+impl View {
+    fn new(definition: ViewDefinition) -> Self {
+        Self {
+            definition,
+            rows: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, reading: &Reading, value: f64) {
+        self.rows
+            .entry(self.definition.key(reading))
+            .or_insert_with(|| Accumulator::new(reading.timestamp, value))
+            .add(reading.timestamp, value);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MaterializedViews {
+    views: BTreeMap<String, View>,
+    /// Set when readings were flagged or removed since the views were built.
+    stale: bool,
+}
+
+impl MaterializedViews {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn list(&self) -> Vec<ViewDefinition> {
+        self.views.values().map(|v| v.definition.clone()).collect()
+    }
+
+    pub fn invalidate(&mut self) {
+        self.stale = !self.views.is_empty();
+    }
+
+    /// Loads definitions only; rows fill in as the index builder indexes readings.
+    pub fn load(&mut self, path: &Path) -> Result<(), BridgeError> {
+        if !path.exists() {
+            return Ok(());
+        }
+        let bytes = fs::read(path).map_err(|e| BridgeError::Io(e.to_string()))?;
+        let definitions: Vec<ViewDefinition> = serde_json::from_slice(&bytes).map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+        self.views = definitions.into_iter().map(|d| (d.name.clone(), View::new(d))).collect();
+        self.stale = false;
+        Ok(())
+    }
+
+    fn save(&self, path: &Path) -> Result<(), BridgeError> {
+        let json = serde_json::to_vec_pretty(&self.list()).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        fs::write(path, json).map_err(|e| BridgeError::Io(e.to_string()))
+    }
+}
+
+impl EcoBlockContext {
+    fn persist_materialized_views(&self) -> Result<(), BridgeError> {
+        match &self.node_path {
+            Some(path) => self.materialized_views.save(&materialized_views_path(path)),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds an unflagged reading to every view over one of its metrics.
+    pub(crate) fn update_materialized_views(&mut self, reading: &Reading) {
+        let derived = &self.derived;
+        for view in self.materialized_views.views.values_mut() {
+            if let Some(value) = derived.value_of(reading, &view.definition.metric) {
+                view.add(reading, value);
+            }
+        }
+    }
+
+    fn build_view(&self, definition: ViewDefinition) -> View {
+        let mut view = View::new(definition);
+        for reading in self.readings.iter() {
+            if let Some(value) = self.derived.value_of(reading, &view.definition.metric) {
+                view.add(reading, value);
+            }
+        }
+        view
+    }
+
+    fn refresh_materialized_views(&mut self) {
+        if !self.materialized_views.stale {
+            return;
+        }
+        let definitions = self.materialized_views.list();
+        for definition in definitions {
+            let view = self.build_view(definition);
+            self.materialized_views.views.insert(view.definition.name.clone(), view);
+        }
+        self.materialized_views.stale = false;
+    }
+
+    /// Adds or replaces the view named `definition.name`, filled from the
+    /// readings already indexed.
+    pub fn declare_materialized_view(&mut self, definition: ViewDefinition) -> Result<(), BridgeError> {
+        definition.validate()?;
+        let views = &self.materialized_views.views;
+        if !views.contains_key(&definition.name) && views.len() >= MAX_MATERIALIZED_VIEWS {
+            return Err(BridgeError::InvalidInput(format!("Invalid materialized view {}: at most {} views", definition.name, MAX_MATERIALIZED_VIEWS)));
+        }
+        let view = self.build_view(definition);
+        self.materialized_views.views.insert(view.definition.name.clone(), view);
+        self.persist_materialized_views()
+    }
+
+    pub fn drop_materialized_view(&mut self, name: &str) -> Result<bool, BridgeError> {
+        let removed = self.materialized_views.views.remove(name).is_some();
+        if removed {
+            self.persist_materialized_views()?;
+        }
+        Ok(removed)
+    }
+
+    /// Every row of the view named `name`, by key.
+    pub fn materialized_view_rows(&mut self, name: &str) -> Result<Vec<ViewRow>, BridgeError> {
+        self.refresh_materialized_views();
+        let view = self.materialized_views.views.get(name).ok_or_else(|| BridgeError::NotFound(format!("Unknown materialized view {}", name)))?;
+        let mut rows: Vec<ViewRow> = view.rows.iter().map(|(key, acc)| acc.row(view.definition.kind, key)).collect();
+        match view.definition.kind {
+            ViewKind::DailyAverage | ViewKind::DailyRange => rows.sort_by_key(|r| r.key.parse::<u64>().unwrap_or_default()),
+            ViewKind::LatestPerSensor | ViewKind::AveragePerSensor => rows.sort_by(|a, b| a.key.cmp(&b.key)),
+        }
+        Ok(rows)
+    }
+
+    /// One row of the view named `name`: a sensor id, or a day's start in seconds.
+    pub fn materialized_view_row(&mut self, name: &str, key: &str) -> Result<Option<ViewRow>, BridgeError> {
+        self.refresh_materialized_views();
+        let view = self.materialized_views.views.get(name).ok_or_else(|| BridgeError::NotFound(format!("Unknown materialized view {}", name)))?;
+        Ok(view.rows.get(key).map(|acc| acc.row(view.definition.kind, key)))
+    }
+}
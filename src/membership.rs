@@ -0,0 +1,172 @@
+use std::collections::{BTreeMap, HashMap};
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+use crate::records::Record;
+
+pub const INVITATION_KIND: &str = "invitation";
+pub const ACCEPTANCE_KIND: &str = "invitation_accept";
+pub const REVOCATION_KIND: &str = "membership_revoke";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Role {
+    Member,
+    Admin,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Member {
+    pub public_key: String,
+    pub role: Role,
+    pub invited_by: Option<String>,
+    pub joined_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InvitationBody {
+    pub scope: String,
+    pub role: Role,
+    /// Restricts the invitation to one key; `None` lets any holder of the invitation accept it.
+    pub invitee: Option<String>,
+    pub expires_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AcceptanceBody {
+    pub invitation_id: String,
+    pub scope: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RevocationBody {
+    pub scope: String,
+    pub member: String,
+}
+
+fn parse<T: serde::de::DeserializeOwned>(record: &Record) -> Result<T, BridgeError> {
+    serde_json::from_value(record.body.clone()).map_err(|e| BridgeError::Deserialization(e.to_string()))
+}
+
+#[derive(Debug, Default)]
+pub struct Roster {
+    scopes: HashMap<String, BTreeMap<String, Member>>,
+    invitations: HashMap<String, (String, InvitationBody)>,
+}
+
+impl Roster {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.contains_key(scope)
+    }
+
+    pub fn members(&self, scope: &str) -> Vec<Member> {
+        self.scopes
+            .get(scope)
+            .map(|m| m.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn scopes(&self) -> Vec<String> {
+        let mut scopes: Vec<String> = self.scopes.keys().cloned().collect();
+        scopes.sort();
+        scopes
+    }
+
+    pub fn invitation(&self, id: &str) -> Option<(String, InvitationBody)> {
+        self.invitations.get(id).cloned()
+    }
+
+    pub fn role_of(&self, scope: &str, public_key: &str) -> Option<Role> {
+        self.scopes.get(scope)?.get(public_key).map(|m| m.role)
+    }
+
+    /// Scopes without a roster are open; otherwise the key needs at least `role`.
+    pub fn is_authorized(&self, scope: &str, public_key: &str, role: Role) -> bool {
+        match self.scopes.get(scope) {
+            None => true,
+            Some(members) => members.get(public_key).is_some_and(|m| m.role >= role),
+        }
+    }
+
+    /// Makes `admin` the first administrator of a scope that has no roster yet.
+    pub fn bootstrap(&mut self, scope: &str, admin: &str, now: u64) {
+        self.scopes.entry(scope.to_string()).or_insert_with(|| {
+            let mut members = BTreeMap::new();
+            members.insert(
+                admin.to_string(),
+                Member {
+                    public_key: admin.to_string(),
+                    role: Role::Admin,
+                    invited_by: None,
+                    joined_at: now,
+                },
+            );
+            members
+        });
+    }
+
+    /// Carries every role held by `old` over to `new`.
+    pub fn replace_member(&mut self, old: &str, new: &str) {
+        for members in self.scopes.values_mut() {
+            if let Some(mut member) = members.remove(old) {
+                member.public_key = new.to_string();
+                members.insert(new.to_string(), member);
+            }
+        }
+    }
+
+    pub fn apply_invitation(&mut self, record: &Record) -> Result<InvitationBody, BridgeError> {
+        let body: InvitationBody = parse(record)?;
+        if self.has_scope(&body.scope) && !self.is_authorized(&body.scope, &record.author, Role::Admin) {
+            return Err(BridgeError::Unauthorized(format!("{} is not an admin of {}", record.author, body.scope)));
+        }
+        self.invitations
+            .insert(record.id.clone(), (record.author.clone(), body.clone()));
+        Ok(body)
+    }
+
+    pub fn apply_acceptance(&mut self, record: &Record) -> Result<Member, BridgeError> {
+        let body: AcceptanceBody = parse(record)?;
+        let (inviter, invitation) = self
+            .invitations
+            .get(&body.invitation_id)
+            .cloned()
+            .ok_or_else(|| BridgeError::NotFound(format!("Unknown invitation: {}", body.invitation_id)))?;
+        if invitation.scope != body.scope {
+            return Err(BridgeError::Rejected(format!("Invitation {} is for another scope", body.invitation_id)));
+        }
+        if record.timestamp > invitation.expires_at {
+            return Err(BridgeError::Rejected(format!("Invitation {} has expired", body.invitation_id)));
+        }
+        if invitation.invitee.as_ref().is_some_and(|k| k != &record.author) {
+            return Err(BridgeError::Unauthorized(format!("Invitation {} was issued to another key", body.invitation_id)));
+        }
+        self.bootstrap(&invitation.scope, &inviter, record.timestamp);
+        if !self.is_authorized(&invitation.scope, &inviter, Role::Admin) {
+            return Err(BridgeError::Unauthorized(format!("{} is no longer an admin of {}", inviter, invitation.scope)));
+        }
+        let member = Member {
+            public_key: record.author.clone(),
+            role: invitation.role,
+            invited_by: Some(inviter),
+            joined_at: record.timestamp,
+        };
+        if let Some(members) = self.scopes.get_mut(&invitation.scope) {
+            members.insert(member.public_key.clone(), member.clone());
+        }
+        Ok(member)
+    }
+
+    pub fn apply_revocation(&mut self, record: &Record) -> Result<RevocationBody, BridgeError> {
+        let body: RevocationBody = parse(record)?;
+        if !self.is_authorized(&body.scope, &record.author, Role::Admin) || !self.has_scope(&body.scope) {
+            return Err(BridgeError::Unauthorized(format!("{} is not an admin of {}", record.author, body.scope)));
+        }
+        if let Some(members) = self.scopes.get_mut(&body.scope) {
+            members.remove(&body.member);
+        }
+        Ok(body)
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::call_metrics::CALLS;
+use crate::error::BridgeError;
+use crate::peer_id::PeerId;
+use crate::{clock, EcoBlockContext};
+
+pub const DEFAULT_SAMPLE_INTERVAL: u64 = 60;
+/// One week of samples at the default interval.
+pub const DEFAULT_CAPACITY: usize = 7 * 24 * 60;
+pub const DEFAULT_PERSIST_INTERVAL: u64 = 600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HistoryPoint {
+    /// Start of the bucket.
+    pub timestamp: u64,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub count: usize,
+}
+
+#[derive(Debug)]
+pub struct MetricsHistory {
+    pub sample_interval: u64,
+    pub persist_interval: u64,
+    capacity: usize,
+    series: BTreeMap<String, VecDeque<(u64, f64)>>,
+    last_sample: Option<u64>,
+    last_persist: u64,
+    path: Option<PathBuf>,
+}
+
+impl Default for MetricsHistory {
+    fn default() -> Self {
+        Self {
+            sample_interval: DEFAULT_SAMPLE_INTERVAL,
+            persist_interval: DEFAULT_PERSIST_INTERVAL,
+            capacity: DEFAULT_CAPACITY,
+            series: BTreeMap::new(),
+            last_sample: None,
+            last_persist: 0,
+            path: None,
+        }
+    }
+}
+
+impl MetricsHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Persists to `path` from now on, first loading any history already stored there.
+    pub fn attach(&mut self, path: &Path) -> Result<(), BridgeError> {
+        if path.exists() {
+            let bytes = fs::read(path).map_err(|e| BridgeError::Io(e.to_string()))?;
+            let stored: BTreeMap<String, VecDeque<(u64, f64)>> =
+                serde_json::from_slice(&bytes).map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+            for (metric, samples) in stored {
+                let series = self.series.entry(metric).or_default();
+                let mut merged: Vec<(u64, f64)> = samples.into_iter().chain(series.drain(..)).collect();
+                merged.sort_by_key(|(t, _)| *t);
+                let skip = merged.len().saturating_sub(self.capacity);
+                series.extend(merged.into_iter().skip(skip));
+            }
+        }
+        self.path = Some(path.to_path_buf());
+        Ok(())
+    }
+
+    pub fn persist(&mut self, now: u64) -> Result<(), BridgeError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let json = serde_json::to_vec(&self.series).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        fs::write(path, json).map_err(|e| BridgeError::Io(e.to_string()))?;
+        self.last_persist = now;
+        Ok(())
+    }
+
+    pub fn is_due(&self, now: u64) -> bool {
+        self.last_sample.is_none_or(|t| now.saturating_sub(t) >= self.sample_interval)
+    }
+
+    /// Appends one sample per metric, dropping the oldest beyond capacity, and
+    /// persists when the persist interval has passed.
+    pub fn record(&mut self, now: u64, values: Vec<(&str, f64)>) -> Result<(), BridgeError> {
+        for (metric, value) in values {
+            let series = self.series.entry(metric.to_string()).or_default();
+            if series.len() == self.capacity {
+                series.pop_front();
+            }
+            series.push_back((now, value));
+        }
+        self.last_sample = Some(now);
+        if now.saturating_sub(self.last_persist) >= self.persist_interval {
+            self.persist(now)?;
+        }
+        Ok(())
+    }
+
+    pub fn metrics(&self) -> Vec<String> {
+        self.series.keys().cloned().collect()
+    }
+
+    /// Samples within `[from, to]` grouped into buckets of `resolution` seconds.
+    pub fn query(&self, metric: &str, from: u64, to: u64, resolution: u64) -> Vec<HistoryPoint> {
+        let resolution = resolution.max(1);
+        let mut buckets: BTreeMap<u64, Vec<f64>> = BTreeMap::new();
+        for (t, v) in self.series.get(metric).into_iter().flatten() {
+            if *t >= from && *t <= to {
+                buckets.entry(t - t % resolution).or_default().push(*v);
+            }
+        }
+        buckets
+            .into_iter()
+            .map(|(timestamp, values)| HistoryPoint {
+                timestamp,
+                min: values.iter().copied().fold(f64::INFINITY, f64::min),
+                max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+                mean: values.iter().sum::<f64>() / values.len() as f64,
+                count: values.len(),
+            })
+            .collect()
+    }
+}
+
+impl EcoBlockContext {
+    /// Records node health metrics if a sample is due.
+    pub fn sample_metrics(&mut self) -> Result<(), BridgeError> {
+        let now = clock::now_secs();
+        if !self.metrics_history.is_due(now) {
+            return Ok(());
+        }
+        let peers = self.list_peers(&PeerId::from_mesh(self.keypair.public_key_hex())).len();
+        let in_flight: usize = self.congestion.windows().iter().map(|w| w.in_flight).sum();
+        let suppression = self.have_lists.stats();
+        let airtime: Vec<(String, f64)> = self
+            .airtime_status()
+            .into_iter()
+            .map(|a| (format!("airtime_remaining_ms_{}", a.transport), a.remaining_ms as f64))
+            .collect();
+        let mut values = vec![
+            ("peers", peers as f64),
+            ("tangle_size", self.tangle.len() as f64),
+            ("records", self.records.len() as f64),
+            ("readings", self.readings.len() as f64),
+            ("active_alerts", self.thresholds.active_alerts().len() as f64),
+            ("in_flight", in_flight as f64),
+            ("sends_suppressed", suppression.sends_suppressed as f64),
+            ("slow_calls", CALLS.lock().map(|c| c.slow_calls()).unwrap_or_default() as f64),
+        ];
+        values.extend(airtime.iter().map(|(metric, value)| (metric.as_str(), *value)));
+        if let Some(rate) = self.deadlines.on_time_rate() {
+            values.push(("on_time_rate", rate));
+        }
+        self.metrics_history.record(now, values)
+    }
+}
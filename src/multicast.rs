@@ -0,0 +1,176 @@
+//! Targeted delivery of one record to several nodes, such as a command for a
+//! group of sensors or a private message. Each target gets its own directed
+//! copy, routed through the mesh by `source_routing` and relayed hop by hop,
+//! and answers with a signed delivery receipt routed back to the author.
+//! The receipts make delivery state per target queryable.
+
+use std::collections::{BTreeMap, VecDeque};
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+use crate::events::BridgeEvent;
+use crate::logs::LogLevel;
+use crate::peer_id::PeerId;
+use crate::records::Record;
+use crate::{clock, EcoBlockContext};
+
+pub const DELIVERY_RECEIPT_KIND: &str = "delivery_receipt";
+pub const MAX_RECIPIENTS: usize = 64;
+/// Deliveries remembered; the oldest are forgotten first.
+const MAX_TRACKED: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeliveryReceiptBody {
+    pub record_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum TargetState {
+    /// Sent; no receipt yet.
+    Pending,
+    Delivered { at: u64 },
+    /// No receipt arrived before the deadline.
+    Expired,
+    /// Not sent, for example because the target is this node.
+    Failed { error: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TargetDelivery {
+    pub target: PeerId,
+    /// Path the copy took from this node; `None` when it was flooded.
+    pub route: Option<Vec<PeerId>>,
+    pub state: TargetState,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MulticastDelivery {
+    pub record_id: String,
+    pub sent_at: u64,
+    pub deadline: Option<u64>,
+    pub targets: Vec<TargetDelivery>,
+}
+
+impl MulticastDelivery {
+    pub fn delivered(&self) -> usize {
+        self.targets.iter().filter(|t| matches!(t.state, TargetState::Delivered { .. })).count()
+    }
+
+    /// Marks targets still pending once the deadline has passed.
+    fn expire(&mut self, now: u64) {
+        if self.deadline.is_some_and(|deadline| now > deadline) {
+            for target in self.targets.iter_mut().filter(|t| t.state == TargetState::Pending) {
+                target.state = TargetState::Expired;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Multicasts {
+    deliveries: BTreeMap<String, MulticastDelivery>,
+    order: VecDeque<String>,
+}
+
+impl Multicasts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, record_id: &str, now: u64) -> Option<MulticastDelivery> {
+        let mut delivery = self.deliveries.get(record_id)?.clone();
+        delivery.expire(now);
+        Some(delivery)
+    }
+
+    /// Tracked deliveries, oldest first.
+    pub fn list(&self, now: u64) -> Vec<MulticastDelivery> {
+        self.order.iter().filter_map(|id| self.get(id, now)).collect()
+    }
+
+    fn track(&mut self, delivery: MulticastDelivery) {
+        if !self.deliveries.contains_key(&delivery.record_id) {
+            if self.order.len() == MAX_TRACKED {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.deliveries.remove(&oldest);
+                }
+            }
+            self.order.push_back(delivery.record_id.clone());
+        }
+        self.deliveries.insert(delivery.record_id.clone(), delivery);
+    }
+}
+
+impl EcoBlockContext {
+    /// Sends `record` to every target along its own directed path and tracks
+    /// which targets confirm receipt. Targets that cannot be sent to are
+    /// reported as failed; the call fails only without a transport.
+    pub fn send_to_many(&mut self, record: Record, targets: &[PeerId], deadline: Option<u64>) -> Result<MulticastDelivery, BridgeError> {
+        if targets.is_empty() || targets.len() > MAX_RECIPIENTS {
+            return Err(BridgeError::InvalidInput(format!("Invalid targets: between 1 and {} required, got {}", MAX_RECIPIENTS, targets.len())));
+        }
+        if !self.sync.is_attached() {
+            return Err(BridgeError::NotFound("Unknown transport: none is attached".to_string()));
+        }
+        let mut deliveries = Vec::new();
+        for target in targets {
+            if deliveries.iter().any(|d: &TargetDelivery| &d.target == target) {
+                continue;
+            }
+            let delivery = match self.route_record(record.clone(), target, deadline, true) {
+                Ok(sent) => TargetDelivery {
+                    target: target.clone(),
+                    route: sent.route,
+                    state: TargetState::Pending,
+                },
+                Err(error) => TargetDelivery {
+                    target: target.clone(),
+                    route: None,
+                    state: TargetState::Failed { error: error.to_string() },
+                },
+            };
+            deliveries.push(delivery);
+        }
+        let delivery = MulticastDelivery {
+            record_id: record.id,
+            sent_at: clock::now_secs(),
+            deadline,
+            targets: deliveries,
+        };
+        self.multicasts.track(delivery.clone());
+        Ok(delivery)
+    }
+
+    pub(crate) fn send_delivery_receipt(&mut self, record_id: &str, author: &PeerId) {
+        let body = serde_json::to_value(DeliveryReceiptBody { record_id: record_id.to_string() }).unwrap_or_default();
+        let sent = self
+            .sign_record(DELIVERY_RECEIPT_KIND, body, clock::now_secs())
+            .and_then(|receipt| self.route_record(receipt, author, None, false));
+        if let Err(e) = sent {
+            self.log(LogLevel::Warn, format!("Delivery receipt for record {} not sent: {}", record_id, e));
+        }
+    }
+
+    /// Marks the receipt's author as having received the record, if it is one
+    /// of the record's targets.
+    pub(crate) fn apply_delivery_receipt(&mut self, receipt: &Record) {
+        let Ok(body) = serde_json::from_value::<DeliveryReceiptBody>(receipt.body.clone()) else {
+            return;
+        };
+        let Some(delivery) = self.multicasts.deliveries.get_mut(&body.record_id) else {
+            return;
+        };
+        let Some(target) = delivery.targets.iter_mut().find(|t| t.target.as_str() == receipt.author) else {
+            return;
+        };
+        if matches!(target.state, TargetState::Delivered { .. }) {
+            return;
+        }
+        target.state = TargetState::Delivered { at: clock::now_secs() };
+        let target = target.target.clone();
+        self.emit_event(BridgeEvent::DeliveryConfirmed {
+            record_id: body.record_id,
+            target,
+        });
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+use crate::peer_id::PeerId;
+use crate::EcoBlockContext;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+    /// Alert blocks.
+    Critical,
+}
+
+/// Number of node-disjoint paths to use per priority class; 0 means plain flooding.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoutingConfig {
+    pub redundancy: BTreeMap<Priority, u8>,
+}
+
+impl Default for RoutingConfig {
+    fn default() -> Self {
+        Self {
+            redundancy: BTreeMap::from([(Priority::Low, 0), (Priority::Normal, 0), (Priority::High, 2), (Priority::Critical, 3)]),
+        }
+    }
+}
+
+pub const MAX_REDUNDANCY: u8 = 8;
+
+impl RoutingConfig {
+    pub fn set_redundancy(&mut self, priority: Priority, k: u8) -> Result<(), BridgeError> {
+        if k > MAX_REDUNDANCY {
+            return Err(BridgeError::InvalidInput(format!("Redundancy {} exceeds {}", k, MAX_REDUNDANCY)));
+        }
+        self.redundancy.insert(priority, k);
+        Ok(())
+    }
+
+    pub fn redundancy(&self, priority: Priority) -> u8 {
+        self.redundancy.get(&priority).copied().unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoutePlan {
+    pub priority: Priority,
+    pub requested: u8,
+    /// Each path starts at the local node and ends at the destination. Fewer than
+    /// `requested` paths are returned when the mesh does not have that many.
+    pub paths: Vec<Vec<PeerId>>,
+    /// True when the plan falls back to flooding (redundancy 0).
+    pub flood: bool,
+}
+
+impl RoutePlan {
+    /// Distinct first hops to send to.
+    pub fn next_hops(&self) -> Vec<PeerId> {
+        let mut hops: Vec<PeerId> = self.paths.iter().filter_map(|p| p.get(1).cloned()).collect();
+        hops.sort();
+        hops.dedup();
+        hops
+    }
+}
+
+/// Lowest-cost path avoiding `blocked` nodes and `used` edges, treating link
+/// weights as costs.
+fn shortest_path(
+    source: &str,
+    target: &str,
+    neighbors: &dyn Fn(&str) -> Vec<(String, f32)>,
+    blocked: &HashSet<String>,
+    used: &HashSet<(String, String)>,
+) -> Option<Vec<String>> {
+    let mut dist: HashMap<String, f32> = HashMap::from([(source.to_string(), 0.0)]);
+    let mut prev: HashMap<String, String> = HashMap::new();
+    let mut done: HashSet<String> = HashSet::new();
+    loop {
+        let (node, cost) = dist
+            .iter()
+            .filter(|(n, _)| !done.contains(*n))
+            .min_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(n, c)| (n.clone(), *c))?;
+        if node == target {
+            break;
+        }
+        done.insert(node.clone());
+        for (next, weight) in neighbors(&node) {
+            if done.contains(&next) || (blocked.contains(&next) && next != target) || used.contains(&(node.clone(), next.clone())) {
+                continue;
+            }
+            let candidate = cost + if weight.is_finite() { weight.max(0.0) } else { f32::MAX };
+            if dist.get(&next).is_none_or(|d| candidate < *d) {
+                dist.insert(next.clone(), candidate);
+                prev.insert(next, node.clone());
+            }
+        }
+    }
+    let mut path = vec![target.to_string()];
+    while let Some(p) = prev.get(path.last()?) {
+        path.push(p.clone());
+    }
+    path.reverse();
+    Some(path)
+}
+
+/// Up to `k` paths from `source` to `target` sharing no intermediate node and
+/// no edge, found greedily cheapest first.
+pub fn disjoint_paths(source: &str, target: &str, k: usize, neighbors: &dyn Fn(&str) -> Vec<(String, f32)>) -> Vec<Vec<String>> {
+    let mut blocked = HashSet::new();
+    let mut used = HashSet::new();
+    let mut paths = Vec::new();
+    if source == target {
+        return paths;
+    }
+    while paths.len() < k {
+        let Some(path) = shortest_path(source, target, neighbors, &blocked, &used) else {
+            break;
+        };
+        for pair in path.windows(2) {
+            used.insert((pair[0].clone(), pair[1].clone()));
+        }
+        blocked.extend(path[1..path.len() - 1].iter().cloned());
+        paths.push(path);
+    }
+    paths
+}
+
+impl EcoBlockContext {
+    pub fn plan_propagation(&self, destination: &PeerId, priority: Priority) -> RoutePlan {
+        let requested = self.routing.redundancy(priority);
+        let source = self.keypair.public_key_hex();
+        let neighbors = |node: &str| self.mesh.get_neighbors(node).unwrap_or_default();
+        let paths = disjoint_paths(&source, destination.as_str(), usize::from(requested), &neighbors)
+            .into_iter()
+            .map(|p| p.into_iter().map(PeerId::from_mesh).collect())
+            .collect();
+        RoutePlan {
+            priority,
+            requested,
+            paths,
+            flood: requested == 0,
+        }
+    }
+}
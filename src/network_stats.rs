@@ -0,0 +1,173 @@
+//! Deployment-wide health without central infrastructure. Each node
+//! periodically publishes a compact signed summary of itself on the `ops`
+//! channel; every node keeps the latest summary per author and aggregates them
+//! into a `NetworkOverview`. Restricting the channel's roster or visibility
+//! limits who may publish or read the summaries.
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::channels::ChannelMessageBody;
+use crate::error::BridgeError;
+use crate::logs::LogLevel;
+use crate::peer_id::PeerId;
+use crate::records::Record;
+use crate::{clock, EcoBlockContext};
+
+pub const OPS_CHANNEL: &str = "ops";
+pub const DEFAULT_STATS_INTERVAL: u64 = 3600;
+/// Summaries older than this many intervals count their node as stale.
+pub const STALE_INTERVALS: u64 = 3;
+
+/// What a node says about itself on the ops channel.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeStatsSummary {
+    pub node_id: String,
+    pub published_at: u64,
+    pub uptime: u64,
+    pub blocks: u64,
+    pub records: u64,
+    pub readings: u64,
+    /// Size of the block store file; 0 for a node without one.
+    pub store_bytes: u64,
+    /// Direct neighbours.
+    pub peers: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkOverview {
+    /// Latest summary of every node, this one included, by node id.
+    pub nodes: Vec<NodeStatsSummary>,
+    /// Nodes whose latest summary is older than the stale limit.
+    pub stale: Vec<String>,
+    /// The totals and ranges below cover fresh summaries only.
+    pub total_blocks: u64,
+    pub total_records: u64,
+    pub total_store_bytes: u64,
+    /// Fewest and most blocks held by a node; a wide gap points at a node
+    /// that is not syncing.
+    pub min_blocks: u64,
+    pub max_blocks: u64,
+    pub oldest_summary: Option<u64>,
+}
+
+pub struct NetworkStats {
+    pub interval: u64,
+    last_published: Option<u64>,
+    remote: HashMap<String, NodeStatsSummary>,
+}
+
+impl Default for NetworkStats {
+    fn default() -> Self {
+        Self {
+            interval: DEFAULT_STATS_INTERVAL,
+            last_published: None,
+            remote: HashMap::new(),
+        }
+    }
+}
+
+impl NetworkStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_due(&self, now: u64) -> bool {
+        self.interval > 0 && self.last_published.is_none_or(|last| now.saturating_sub(last) >= self.interval)
+    }
+
+    pub fn mark_published(&mut self, now: u64) {
+        self.last_published = Some(now);
+    }
+
+    /// Keeps only the most recent summary per node.
+    pub fn receive(&mut self, summary: NodeStatsSummary) {
+        match self.remote.get(&summary.node_id) {
+            Some(existing) if existing.published_at >= summary.published_at => {}
+            _ => {
+                self.remote.insert(summary.node_id.clone(), summary);
+            }
+        }
+    }
+
+    pub fn overview(&self, local: NodeStatsSummary, now: u64) -> NetworkOverview {
+        let mut nodes: Vec<NodeStatsSummary> = self
+            .remote
+            .values()
+            .filter(|n| n.node_id != local.node_id)
+            .cloned()
+            .collect();
+        nodes.push(local);
+        nodes.sort_by(|a, b| a.node_id.cmp(&b.node_id));
+        let stale_after = self.interval.max(1).saturating_mul(STALE_INTERVALS);
+        let (fresh, stale): (Vec<&NodeStatsSummary>, Vec<&NodeStatsSummary>) =
+            nodes.iter().partition(|n| now.saturating_sub(n.published_at) <= stale_after);
+        NetworkOverview {
+            stale: stale.iter().map(|n| n.node_id.clone()).collect(),
+            total_blocks: fresh.iter().map(|n| n.blocks).sum(),
+            total_records: fresh.iter().map(|n| n.records).sum(),
+            total_store_bytes: fresh.iter().map(|n| n.store_bytes).sum(),
+            min_blocks: fresh.iter().map(|n| n.blocks).min().unwrap_or_default(),
+            max_blocks: fresh.iter().map(|n| n.blocks).max().unwrap_or_default(),
+            oldest_summary: fresh.iter().map(|n| n.published_at).min(),
+            nodes,
+        }
+    }
+}
+
+impl EcoBlockContext {
+    pub fn local_stats_summary(&self) -> NodeStatsSummary {
+        let local = self.keypair.public_key_hex();
+        let blocks = self.block_log.len();
+        NodeStatsSummary {
+            node_id: local.clone(),
+            published_at: clock::now_secs(),
+            uptime: self.local_relay_metrics().uptime,
+            blocks: blocks as u64,
+            records: self.records.len() as u64,
+            readings: self.readings.len() as u64,
+            store_bytes: self.block_store.as_ref().map(|s| s.status(blocks).bytes).unwrap_or_default(),
+            peers: self.list_peers(&PeerId::from_mesh(local)).len() as u32,
+        }
+    }
+
+    /// Publishes this node's summary on the ops channel and returns the
+    /// record id.
+    pub fn publish_stats_summary(&mut self) -> Result<String, BridgeError> {
+        let local = self.local_stats_summary();
+        let data = serde_json::to_vec(&local).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        let id = self.publish_to_channel(OPS_CHANNEL, &data)?;
+        self.network_stats.mark_published(local.published_at);
+        Ok(id)
+    }
+
+    pub(crate) fn run_scheduled_stats_publish(&mut self, now: u64) {
+        if !self.network_stats.is_due(now) {
+            return;
+        }
+        if let Err(e) = self.publish_stats_summary() {
+            // Not retried before the next interval.
+            self.network_stats.mark_published(now);
+            self.log(LogLevel::Warn, format!("Stats summary not published: {}", e));
+        }
+    }
+
+    /// Takes in a summary carried by an ops channel message. Other messages on
+    /// the channel, and sealed ones this node cannot open, are ignored.
+    pub(crate) fn apply_ops_message(&mut self, record: &Record, body: &ChannelMessageBody) -> Result<(), BridgeError> {
+        let Ok(data) = self.channels.open(&body.payload) else {
+            return Ok(());
+        };
+        let Ok(summary) = serde_json::from_value::<NodeStatsSummary>(data) else {
+            return Ok(());
+        };
+        if summary.node_id != record.author {
+            return Err(BridgeError::Unauthorized(format!("Stats summary {} not signed by its node", record.id)));
+        }
+        self.network_stats.receive(summary);
+        Ok(())
+    }
+
+    pub fn network_overview(&self) -> NetworkOverview {
+        self.network_stats.overview(self.local_stats_summary(), clock::now_secs())
+    }
+}
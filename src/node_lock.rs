@@ -0,0 +1,104 @@
+//! Keeps two processes from opening the same node. Both would append to the
+//! block store and rewrite the node's JSON files, corrupting them. The lock is
+//! an OS advisory lock on `bridge.lock` in the node directory, held on an open
+//! handle for as long as the context is bound; the file records the owner's
+//! process id for error messages. It is taken by `init_context` and released
+//! when that context is dropped. The OS releases it when the owner exits, even
+//! on a crash, so a stale file never blocks the node and a recycled process id
+//! cannot keep it locked.
+
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use crate::error::BridgeError;
+
+pub fn node_lock_path(node_path: &Path) -> PathBuf {
+    node_path.join("bridge.lock")
+}
+
+#[derive(Debug)]
+pub struct NodeLock {
+    path: PathBuf,
+    /// Holds the lock until dropped.
+    file: File,
+}
+
+impl NodeLock {
+    /// Fails with `AlreadyInitialized` while another handle, in this process
+    /// or another, holds the node.
+    pub fn acquire(node_path: &Path) -> Result<Self, BridgeError> {
+        let path = node_lock_path(node_path);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|e| BridgeError::Io(e.to_string()))?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let holder = fs::read_to_string(&path).ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+                return Err(BridgeError::AlreadyInitialized(format!(
+                    "node at {} is open in process {}; connect to it over IPC",
+                    node_path.display(),
+                    holder.as_deref().unwrap_or("unknown")
+                )));
+            }
+            Err(TryLockError::Error(e)) => return Err(BridgeError::Io(e.to_string())),
+        }
+        file.set_len(0).map_err(|e| BridgeError::Io(e.to_string()))?;
+        file.write_all(std::process::id().to_string().as_bytes()).map_err(|e| BridgeError::Io(e.to_string()))?;
+        Ok(Self { path, file })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for NodeLock {
+    /// The file is left in place: removing it would let a process that opened
+    /// it just before lock a different file than the next one to create it.
+    fn drop(&mut self) {
+        let _ = self.file.set_len(0);
+        let _ = self.file.unlock();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ecoblock-lock-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn a_held_node_cannot_be_locked_again_until_released() {
+        let dir = node_dir("contention");
+        let lock = NodeLock::acquire(&dir).unwrap();
+        assert_eq!(fs::read_to_string(lock.path()).unwrap(), std::process::id().to_string());
+
+        let error = NodeLock::acquire(&dir).unwrap_err();
+        assert!(matches!(error, BridgeError::AlreadyInitialized(_)), "{}", error);
+        assert!(error.to_string().contains(&std::process::id().to_string()), "{}", error);
+
+        drop(lock);
+        assert!(NodeLock::acquire(&dir).is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_lock_file_nobody_holds_is_taken_over_whatever_process_it_names() {
+        let dir = node_dir("takeover");
+        // A crashed owner, or one whose id now belongs to a live process.
+        fs::write(node_lock_path(&dir), "1").unwrap();
+        let lock = NodeLock::acquire(&dir).unwrap();
+        assert_eq!(fs::read_to_string(lock.path()).unwrap(), std::process::id().to_string());
+        drop(lock);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
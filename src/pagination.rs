@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+
+pub const MAX_PAGE_SIZE: usize = 500;
+
+/// One page of a list. `next_cursor` is `None` on the last page; pass it back
+/// unchanged to continue. Cursors are opaque and only valid for the list that
+/// produced them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+fn clamp(limit: usize) -> usize {
+    limit.clamp(1, MAX_PAGE_SIZE)
+}
+
+fn decode(cursor: &str, prefix: &str) -> Result<String, BridgeError> {
+    let bytes = hex::decode(cursor).map_err(|_| BridgeError::InvalidInput("Invalid cursor".to_string()))?;
+    let text = String::from_utf8(bytes).map_err(|_| BridgeError::InvalidInput("Invalid cursor".to_string()))?;
+    text.strip_prefix(prefix)
+        .map(str::to_string)
+        .ok_or_else(|| BridgeError::InvalidInput("Invalid cursor".to_string()))
+}
+
+/// Pages an append-only sequence in insertion order. Items carry their position
+/// in the underlying log, so filtered views stay stable while new items are
+/// appended: a cursor resumes right after the last item returned.
+pub fn by_position<T>(items: impl Iterator<Item = (usize, T)>, cursor: Option<&str>, limit: usize) -> Result<Page<T>, BridgeError> {
+    let start = match cursor {
+        Some(c) => decode(c, "p:")?.parse::<usize>().map_err(|_| BridgeError::InvalidInput("Invalid cursor".to_string()))?,
+        None => 0,
+    };
+    let limit = clamp(limit);
+    let mut items = items.skip_while(|(pos, _)| *pos < start).peekable();
+    let mut page = Vec::new();
+    let mut last = start;
+    while page.len() < limit {
+        let Some((pos, item)) = items.next() else {
+            break;
+        };
+        last = pos;
+        page.push(item);
+    }
+    let next_cursor = items.peek().map(|_| hex::encode(format!("p:{}", last + 1)));
+    Ok(Page { items: page, next_cursor })
+}
+
+/// Pages items ordered by a unique string key; a cursor resumes after the last
+/// key returned, so items inserted or removed elsewhere never shift the page.
+pub fn by_key<T>(mut items: Vec<(String, T)>, cursor: Option<&str>, limit: usize) -> Result<Page<T>, BridgeError> {
+    let after = cursor.map(|c| decode(c, "k:")).transpose()?;
+    items.sort_by(|a, b| a.0.cmp(&b.0));
+    let limit = clamp(limit);
+    let mut items = items
+        .into_iter()
+        .filter(|(key, _)| after.as_ref().is_none_or(|a| key > a))
+        .peekable();
+    let mut page = Vec::new();
+    let mut last = None;
+    while page.len() < limit {
+        let Some((key, item)) = items.next() else {
+            break;
+        };
+        last = Some(key);
+        page.push(item);
+    }
+    let next_cursor = match (items.peek(), last) {
+        (Some(_), Some(key)) => Some(hex::encode(format!("k:{}", key))),
+        _ => None,
+    };
+    Ok(Page { items: page, next_cursor })
+}
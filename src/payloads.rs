@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::error::BridgeError;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayloadStats {
+    pub unique_payloads: usize,
+    pub references: usize,
+    pub bytes_stored: usize,
+    /// Bytes that would be stored without deduplication.
+    pub bytes_referenced: usize,
+}
+
+#[derive(Debug)]
+struct Entry {
+    bytes: Vec<u8>,
+    refs: usize,
+}
+
+/// Content-addressed payload bytes (SHA-256 hex → bytes) with reference counts.
+/// When opened on a directory, each payload is written once as `<dir>/<hash>`
+/// and removed when its last reference is released.
+#[derive(Debug, Default)]
+pub struct PayloadStore {
+    entries: HashMap<String, Entry>,
+    dir: Option<PathBuf>,
+}
+
+pub fn payload_hash(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+impl PayloadStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn open(dir: &Path) -> Result<Self, BridgeError> {
+        fs::create_dir_all(dir).map_err(|e| BridgeError::Io(e.to_string()))?;
+        Ok(Self {
+            entries: HashMap::new(),
+            dir: Some(dir.to_path_buf()),
+        })
+    }
+
+    /// Adds a reference to `bytes`, storing them only if no identical payload exists.
+    pub fn retain(&mut self, bytes: &[u8]) -> Result<String, BridgeError> {
+        let hash = payload_hash(bytes);
+        if let Some(entry) = self.entries.get_mut(&hash) {
+            entry.refs += 1;
+            return Ok(hash);
+        }
+        if let Some(dir) = &self.dir {
+            fs::write(dir.join(&hash), bytes).map_err(|e| BridgeError::Io(e.to_string()))?;
+        }
+        self.entries.insert(hash.clone(), Entry { bytes: bytes.to_vec(), refs: 1 });
+        Ok(hash)
+    }
+
+    /// Drops one reference; the payload is deleted once nothing refers to it.
+    pub fn release(&mut self, hash: &str) -> Result<(), BridgeError> {
+        let Some(entry) = self.entries.get_mut(hash) else {
+            return Ok(());
+        };
+        entry.refs -= 1;
+        if entry.refs == 0 {
+            self.entries.remove(hash);
+            if let Some(dir) = &self.dir {
+                fs::remove_file(dir.join(hash)).map_err(|e| BridgeError::Io(e.to_string()))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, hash: &str) -> Option<&[u8]> {
+        self.entries.get(hash).map(|e| e.bytes.as_slice())
+    }
+
+    pub fn refs(&self, hash: &str) -> usize {
+        self.entries.get(hash).map_or(0, |e| e.refs)
+    }
+
+    pub fn stats(&self) -> PayloadStats {
+        self.entries.values().fold(PayloadStats::default(), |mut stats, entry| {
+            stats.unique_payloads += 1;
+            stats.references += entry.refs;
+            stats.bytes_stored += entry.bytes.len();
+            stats.bytes_referenced += entry.bytes.len() * entry.refs;
+            stats
+        })
+    }
+}
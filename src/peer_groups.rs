@@ -0,0 +1,149 @@
+//! Peer groups such as `couriers`, `gateways` or `classroom-A`. Peers are
+//! tagged into groups in the address book, and a policy set per group applies
+//! to every member: how often to sync with it, how early it is pushed new
+//! blocks and records, and which channels it is pushed. A peer in several
+//! groups gets the most generous setting of each.
+
+use std::collections::{BTreeMap, BTreeSet};
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+use crate::logs::LogLevel;
+use crate::multipath::Priority;
+use crate::peer_id::PeerId;
+use crate::EcoBlockContext;
+
+pub const MAX_GROUP_NAME_LEN: usize = 64;
+/// Shortest sync interval a group may ask for.
+pub const MIN_SYNC_INTERVAL_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupPolicy {
+    /// Seconds between anti-entropy syncs with each connected member; `None`
+    /// syncs only on connection and when the host asks.
+    pub sync_interval_secs: Option<u64>,
+    /// Members are pushed new blocks and records before peers of lower
+    /// priority; ungrouped peers are `Normal`.
+    pub priority: Option<Priority>,
+    /// Channels whose messages are pushed to members; `None` pushes all.
+    /// Members can still fetch other channels through sync.
+    pub channels: Option<BTreeSet<String>>,
+}
+
+impl GroupPolicy {
+    pub fn validate(&self) -> Result<(), BridgeError> {
+        if self.sync_interval_secs.is_some_and(|s| s < MIN_SYNC_INTERVAL_SECS) {
+            return Err(BridgeError::InvalidInput(format!("Invalid group policy: sync interval must be at least {} seconds", MIN_SYNC_INTERVAL_SECS)));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerGroup {
+    pub name: String,
+    pub members: Vec<PeerId>,
+    pub policy: GroupPolicy,
+}
+
+pub fn validate_group_name(group: &str) -> Result<(), BridgeError> {
+    if group.is_empty() || group.len() > MAX_GROUP_NAME_LEN {
+        return Err(BridgeError::InvalidInput(format!("Invalid group {:?}: names are 1 to {} bytes", group, MAX_GROUP_NAME_LEN)));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub struct GroupPolicies {
+    policies: BTreeMap<String, GroupPolicy>,
+}
+
+impl GroupPolicies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, group: &str) -> Option<&GroupPolicy> {
+        self.policies.get(group)
+    }
+}
+
+impl EcoBlockContext {
+    pub fn tag_peer(&mut self, node_id: &PeerId, group: &str) -> Result<(), BridgeError> {
+        validate_group_name(group)?;
+        self.address_book.tag(node_id.as_str(), group)
+    }
+
+    pub fn set_group_policy(&mut self, group: &str, policy: GroupPolicy) -> Result<(), BridgeError> {
+        validate_group_name(group)?;
+        policy.validate()?;
+        self.group_policies.policies.insert(group.to_string(), policy);
+        Ok(())
+    }
+
+    pub fn remove_group_policy(&mut self, group: &str) -> bool {
+        self.group_policies.policies.remove(group).is_some()
+    }
+
+    /// Every group that has members or a policy, by name.
+    pub fn peer_groups(&self) -> Vec<PeerGroup> {
+        let tagged = self.address_book.groups();
+        let mut groups: BTreeMap<String, PeerGroup> = BTreeMap::new();
+        for name in self.group_policies.policies.keys().chain(tagged.iter()) {
+            groups.entry(name.clone()).or_insert_with(|| PeerGroup {
+                name: name.clone(),
+                members: self.address_book.members(name),
+                policy: self.group_policies.get(name).cloned().unwrap_or_default(),
+            });
+        }
+        groups.into_values().collect()
+    }
+
+    /// Policies of the groups `peer` belongs to.
+    fn group_policies_of(&self, peer: &PeerId) -> Vec<&GroupPolicy> {
+        self.address_book
+            .get(peer.as_str())
+            .map(|entry| entry.groups.iter().filter_map(|g| self.group_policies.get(g)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Highest priority of the peer's groups, `Normal` for ungrouped peers.
+    pub fn peer_priority(&self, peer: &PeerId) -> Priority {
+        self.group_policies_of(peer).iter().filter_map(|p| p.priority).max().unwrap_or(Priority::Normal)
+    }
+
+    /// Whether messages of `channel` are pushed to `peer`.
+    pub(crate) fn pushes_channel(&self, peer: &PeerId, channel: &str) -> bool {
+        let policies = self.group_policies_of(peer);
+        policies.is_empty() || policies.iter().any(|p| p.channels.as_ref().is_none_or(|c| c.contains(channel)))
+    }
+
+    /// `peers` with the highest priority first; peers of equal priority keep
+    /// their order.
+    pub(crate) fn by_priority(&self, mut peers: Vec<PeerId>) -> Vec<PeerId> {
+        peers.sort_by_key(|peer| std::cmp::Reverse(self.peer_priority(peer)));
+        peers
+    }
+
+    /// Syncs with every connected group member whose shortest group sync
+    /// interval has passed since the last sync.
+    pub(crate) fn run_scheduled_group_syncs(&mut self, now: u64) {
+        if !self.sync.is_attached() || self.group_policies.policies.is_empty() {
+            return;
+        }
+        let local = PeerId::from_mesh(self.keypair.public_key_hex());
+        let due: Vec<PeerId> = self
+            .list_peers(&local)
+            .into_iter()
+            .filter(|peer| {
+                let interval = self.group_policies_of(peer).iter().filter_map(|p| p.sync_interval_secs).min();
+                let last = self.flow_stats.peer_stats(peer).and_then(|s| s.last_sync);
+                interval.is_some_and(|i| last.is_none_or(|l| now >= l + i))
+            })
+            .collect();
+        for peer in due {
+            if let Err(e) = self.sync_with_peer(&peer) {
+                self.log(LogLevel::Warn, format!("Group sync with {} failed: {}", peer, e));
+            }
+        }
+    }
+}
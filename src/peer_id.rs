@@ -0,0 +1,119 @@
+use std::borrow::Borrow;
+use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+
+pub const PUBLIC_KEY_HEX_LEN: usize = 64;
+pub const MAX_PEER_ID_LEN: usize = 128;
+
+/// Hex-encoded Ed25519 public key: 64 hex characters, normalized to lowercase.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PublicKeyHex(String);
+
+impl PublicKeyHex {
+    pub fn parse(key: &str) -> Result<Self, BridgeError> {
+        if key.len() != PUBLIC_KEY_HEX_LEN || !key.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(BridgeError::InvalidInput(format!("Invalid public key: expected {} hex characters, got {:?}", PUBLIC_KEY_HEX_LEN, key)));
+        }
+        Ok(Self(key.to_ascii_lowercase()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        hex::decode_to_slice(&self.0, &mut bytes).ok();
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; 32]) -> Self {
+        Self(hex::encode(bytes))
+    }
+}
+
+/// Identifier of a node in the mesh. Nodes are normally named by their public
+/// key, but other ids (simulated or legacy nodes) are accepted: 1 to 128
+/// printable ASCII characters. Aliases are never peer ids.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PeerId(String);
+
+impl PeerId {
+    pub fn parse(id: &str) -> Result<Self, BridgeError> {
+        if id.is_empty() || id.len() > MAX_PEER_ID_LEN || !id.bytes().all(|b| b.is_ascii_graphic()) {
+            return Err(BridgeError::InvalidInput(format!("Invalid peer id: {:?}", id)));
+        }
+        Ok(Self(id.to_string()))
+    }
+
+    /// Wraps an id read back from the mesh, which only holds validated ids.
+    pub(crate) fn from_mesh(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The peer's public key, when the id is one.
+    pub fn public_key(&self) -> Option<PublicKeyHex> {
+        PublicKeyHex::parse(&self.0).ok()
+    }
+}
+
+macro_rules! string_newtype_impls {
+    ($name:ident) => {
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = BridgeError;
+
+            fn from_str(s: &str) -> Result<Self, BridgeError> {
+                Self::parse(s)
+            }
+        }
+
+        impl TryFrom<String> for $name {
+            type Error = BridgeError;
+
+            fn try_from(s: String) -> Result<Self, BridgeError> {
+                Self::parse(&s)
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl Borrow<str> for $name {
+            fn borrow(&self) -> &str {
+                &self.0
+            }
+        }
+    };
+}
+
+string_newtype_impls!(PublicKeyHex);
+string_newtype_impls!(PeerId);
+
+impl From<PublicKeyHex> for PeerId {
+    fn from(key: PublicKeyHex) -> Self {
+        Self(key.0)
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::{BTreeSet, HashSet};
+use serde::{Deserialize, Serialize};
+use crate::change_feed::ChangeKind;
+use crate::channels::Visibility;
+use crate::error::BridgeError;
+use crate::hybrid::HybridPolicy;
+use crate::logs::LogLevel;
+use crate::records::Record;
+use crate::thresholds::ThresholdRule;
+use crate::validation::RateRule;
+use crate::wake_windows::WakeSchedule;
+use crate::{clock, EcoBlockContext};
+
+pub const POLICY_KIND: &str = "policy";
+pub const POLICY_STATUS_KIND: &str = "policy_status";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelDefinition {
+    pub name: String,
+    pub visibility: Visibility,
+}
+
+/// Mesh-wide configuration. Every node applies the highest version signed by an admin key.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct PolicyDocument {
+    pub deployment: String,
+    pub version: u64,
+    pub retention_days: Option<u32>,
+    pub rate_rules: Vec<RateRule>,
+    pub thresholds: Vec<ThresholdRule>,
+    pub admin_keys: Vec<String>,
+    pub channels: Vec<ChannelDefinition>,
+    pub pow_difficulty: Option<u8>,
+    /// When set, the policy is trialled for this many seconds before it becomes final.
+    #[serde(default)]
+    pub trial_secs: Option<u64>,
+    /// Number of failure reports during the trial that roll the policy back.
+    #[serde(default)]
+    pub rollback_quorum: Option<usize>,
+    /// How Ed25519 and post-quantum signatures combine when accepting records.
+    #[serde(default)]
+    pub hybrid_signatures: Option<HybridPolicy>,
+    /// Listening slots for nodes on battery; `None` keeps every radio on.
+    #[serde(default)]
+    pub wake_schedule: Option<WakeSchedule>,
+    /// Lets alert records through during quiet hours.
+    #[serde(default)]
+    pub quiet_hours_alerts: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyStatusBody {
+    pub policy_record_id: String,
+    pub version: u64,
+    pub accepted: bool,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyTrial {
+    pub record_id: String,
+    pub version: u64,
+    pub until: u64,
+    pub quorum: usize,
+    pub accepted_by: BTreeSet<String>,
+    pub failed_on: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrialOutcome {
+    Pending,
+    Committed,
+    RolledBack,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppliedPolicy {
+    pub record_id: String,
+    pub author: String,
+    pub applied_at: u64,
+    pub document: PolicyDocument,
+}
+
+#[derive(Debug, Default)]
+pub struct PolicyState {
+    current: Option<AppliedPolicy>,
+    bootstrap_keys: Vec<String>,
+    previous: Option<AppliedPolicy>,
+    trial: Option<PolicyTrial>,
+    rejected_versions: HashSet<u64>,
+}
+
+impl PolicyState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn current(&self) -> Option<&AppliedPolicy> {
+        self.current.as_ref()
+    }
+
+    pub fn set_bootstrap_keys(&mut self, keys: Vec<String>) {
+        self.bootstrap_keys = keys;
+    }
+
+    /// Admin keys of the active policy, else the configured bootstrap keys, else the local key.
+    pub fn authorities(&self, local: &str) -> Vec<String> {
+        match &self.current {
+            Some(applied) if !applied.document.admin_keys.is_empty() => applied.document.admin_keys.clone(),
+            _ if !self.bootstrap_keys.is_empty() => self.bootstrap_keys.clone(),
+            _ => vec![local.to_string()],
+        }
+    }
+
+    pub fn check(&self, record: &Record, document: &PolicyDocument, local: &str) -> Result<(), BridgeError> {
+        if !self.authorities(local).contains(&record.author) {
+            return Err(BridgeError::Unauthorized(format!("{} may not publish policy", record.author)));
+        }
+        if let Some(current) = &self.current {
+            if current.document.deployment != document.deployment {
+                return Err(BridgeError::Rejected(format!("Policy for deployment {} ignored", document.deployment)));
+            }
+            if document.version <= current.document.version {
+                return Err(BridgeError::Rejected(format!("Stale policy version {}", document.version)));
+            }
+        }
+        if self.rejected_versions.contains(&document.version) {
+            return Err(BridgeError::Rejected(format!("Policy version {} was rolled back", document.version)));
+        }
+        Ok(())
+    }
+
+    pub fn set_current(&mut self, applied: AppliedPolicy) {
+        self.current = Some(applied);
+    }
+
+    pub fn trial(&self) -> Option<&PolicyTrial> {
+        self.trial.as_ref()
+    }
+}
+
+impl EcoBlockContext {
+    pub fn publish_policy(&mut self, document: PolicyDocument) -> Result<Record, BridgeError> {
+        let body = serde_json::to_value(&document).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        let record = self.sign_record(POLICY_KIND, body, clock::now_secs())?;
+        self.apply_policy_record(&record)?;
+        self.records.insert(record.clone());
+        Ok(record)
+    }
+
+    /// Applies a policy record. A failure to apply is reported with a `policy_status`
+    /// record and the previous policy is restored; trialled policies also report success.
+    pub(crate) fn apply_policy_record(&mut self, record: &Record) -> Result<(), BridgeError> {
+        let document: PolicyDocument = serde_json::from_value(record.body.clone())
+            .map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+        self.policy.check(record, &document, &self.keypair.public_key_hex())?;
+        if let Err(e) = self.apply_policy(&document) {
+            self.restore_policy(self.policy.current.clone());
+            self.report_policy_status(record, &document, Some(e.clone()));
+            return Err(e);
+        }
+        let now = clock::now_secs();
+        self.log(LogLevel::Info, format!("Applied policy version {}", document.version));
+        self.record_change(ChangeKind::ConfigUpdated, "policy");
+        let previous = self.policy.current.take();
+        if let Some(trial_secs) = document.trial_secs {
+            self.policy.previous = previous;
+            self.policy.trial = Some(PolicyTrial {
+                record_id: record.id.clone(),
+                version: document.version,
+                until: now + trial_secs,
+                quorum: document.rollback_quorum.unwrap_or(1).max(1),
+                accepted_by: BTreeSet::new(),
+                failed_on: BTreeSet::new(),
+            });
+            self.report_policy_status(record, &document, None);
+        }
+        self.policy.set_current(AppliedPolicy {
+            record_id: record.id.clone(),
+            author: record.author.clone(),
+            applied_at: now,
+            document,
+        });
+        Ok(())
+    }
+
+    fn report_policy_status(&mut self, policy: &Record, document: &PolicyDocument, failure: Option<String>) {
+        let body = PolicyStatusBody {
+            policy_record_id: policy.id.clone(),
+            version: document.version,
+            accepted: failure.is_none(),
+            reason: failure,
+        };
+        let body = serde_json::to_value(&body).unwrap_or_default();
+        let record = match self.sign_record(POLICY_STATUS_KIND, body, clock::now_secs()) {
+            Ok(record) => record,
+            Err(e) => {
+                self.log(LogLevel::Warn, format!("Status for policy {} not published: {}", policy.id, e));
+                return;
+            }
+        };
+        self.record_policy_status(&record);
+        self.records.insert(record);
+    }
+
+    fn restore_policy(&mut self, applied: Option<AppliedPolicy>) {
+        let document = applied.as_ref().map(|a| a.document.clone()).unwrap_or_default();
+        self.apply_policy(&document).ok();
+        self.policy.current = applied;
+    }
+
+    /// Counts a status report against the running trial, rolling back on quorum.
+    pub(crate) fn record_policy_status(&mut self, record: &Record) -> TrialOutcome {
+        let Ok(status) = serde_json::from_value::<PolicyStatusBody>(record.body.clone()) else {
+            return TrialOutcome::Pending;
+        };
+        let Some(trial) = self.policy.trial.as_mut() else {
+            return TrialOutcome::Pending;
+        };
+        if trial.record_id != status.policy_record_id {
+            return TrialOutcome::Pending;
+        }
+        if status.accepted {
+            trial.accepted_by.insert(record.author.clone());
+        } else {
+            trial.failed_on.insert(record.author.clone());
+        }
+        if trial.failed_on.len() >= trial.quorum {
+            self.rollback_policy();
+            return TrialOutcome::RolledBack;
+        }
+        TrialOutcome::Pending
+    }
+
+    fn rollback_policy(&mut self) {
+        let Some(trial) = self.policy.trial.take() else {
+            return;
+        };
+        self.log(LogLevel::Warn, format!("Rolled back policy version {}", trial.version));
+        self.policy.rejected_versions.insert(trial.version);
+        let previous = self.policy.previous.take();
+        self.restore_policy(previous);
+    }
+
+    /// Commits the trial policy once its window elapsed without a failure quorum.
+    pub fn check_policy_trial(&mut self) -> TrialOutcome {
+        match &self.policy.trial {
+            None => TrialOutcome::Committed,
+            Some(trial) if clock::now_secs() >= trial.until => {
+                self.policy.trial = None;
+                self.policy.previous = None;
+                TrialOutcome::Committed
+            }
+            Some(_) => TrialOutcome::Pending,
+        }
+    }
+
+    pub(crate) fn apply_policy(&mut self, document: &PolicyDocument) -> Result<(), BridgeError> {
+        if let Some(difficulty) = document.pow_difficulty {
+            self.pow.set_difficulty(difficulty)?;
+        }
+        if let Some(policy) = document.hybrid_signatures {
+            self.hybrid.policy = policy;
+        }
+        if let Some(schedule) = &document.wake_schedule {
+            schedule.validate()?;
+        }
+        self.wake.set_schedule(document.wake_schedule);
+        self.quiet_hours.alert_override = document.quiet_hours_alerts;
+        self.rate_rules = Default::default();
+        for rule in &document.rate_rules {
+            self.rate_rules.set_rule(rule.clone());
+        }
+        for metric in self.thresholds.metrics() {
+            if !document.thresholds.iter().any(|t| t.metric == metric) {
+                self.thresholds.remove_rule(&metric);
+            }
+        }
+        for rule in &document.thresholds {
+            if self.thresholds.rule(&rule.metric) != Some(rule) {
+                self.thresholds.set_rule(rule.clone());
+            }
+        }
+        for channel in &document.channels {
+            self.channels.set_visibility(&channel.name, channel.visibility);
+        }
+        Ok(())
+    }
+}
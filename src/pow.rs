@@ -0,0 +1,101 @@
+use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::error::BridgeError;
+use crate::power::PowerState;
+use crate::relay::RelayMetrics;
+use crate::suites::Suite;
+
+pub const ANNOUNCEMENT_KIND: &str = "announcement";
+
+/// Largest difficulty accepted from configuration, to keep announcement cost bounded on phones.
+pub const MAX_DIFFICULTY: u8 = 32;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnnouncementBody {
+    pub node_id: String,
+    pub difficulty: u8,
+    pub nonce: u64,
+    /// Suites the node accepts, in order of preference. Empty from nodes that
+    /// predate suite negotiation.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub suites: Vec<Suite>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network_id: Option<String>,
+    /// Post-quantum public key of hybrid signers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pq_key: Option<String>,
+    /// Battery, links and uptime for relay election.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relay: Option<RelayMetrics>,
+    /// Latest battery and solar telemetry of the node.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub power: Option<PowerState>,
+}
+
+fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in hash {
+        if *byte == 0 {
+            bits += 8;
+        } else {
+            bits += byte.leading_zeros();
+            break;
+        }
+    }
+    bits
+}
+
+fn digest(node_id: &str, nonce: u64) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(node_id.as_bytes());
+    hasher.update(nonce.to_be_bytes());
+    hasher.finalize().into()
+}
+
+pub fn verify(node_id: &str, nonce: u64, difficulty: u8) -> bool {
+    leading_zero_bits(&digest(node_id, nonce)) >= u32::from(difficulty)
+}
+
+pub fn solve(node_id: &str, difficulty: u8) -> u64 {
+    (0..u64::MAX)
+        .find(|nonce| verify(node_id, *nonce, difficulty))
+        .unwrap_or(u64::MAX)
+}
+
+#[derive(Debug, Default)]
+pub struct PowGate {
+    /// Required leading zero bits; 0 disables the check.
+    pub difficulty: u8,
+    verified: HashSet<String>,
+}
+
+impl PowGate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_difficulty(&mut self, difficulty: u8) -> Result<(), BridgeError> {
+        if difficulty > MAX_DIFFICULTY {
+            return Err(BridgeError::InvalidInput(format!("Proof-of-work difficulty {} exceeds {}", difficulty, MAX_DIFFICULTY)));
+        }
+        self.difficulty = difficulty;
+        Ok(())
+    }
+
+    pub fn is_verified(&self, node_id: &str) -> bool {
+        self.verified.contains(node_id)
+    }
+
+    /// Accepts an announcement whose proof meets the current difficulty.
+    pub fn admit(&mut self, author: &str, body: &AnnouncementBody) -> Result<(), BridgeError> {
+        if body.node_id != author {
+            return Err(BridgeError::Unauthorized(format!("Announcement for {} signed by {}", body.node_id, author)));
+        }
+        if body.difficulty < self.difficulty || !verify(&body.node_id, body.nonce, body.difficulty) {
+            return Err(BridgeError::Unauthorized(format!("Insufficient proof of work from {}", author)));
+        }
+        self.verified.insert(author.to_string());
+        Ok(())
+    }
+}
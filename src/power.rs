@@ -0,0 +1,167 @@
+//! Battery and solar telemetry from the host, and the power mode it selects.
+//! The latest state is advertised in announcements and shown in `node_status`.
+//! Below the configured charge thresholds the node stops relaying (`Saver`)
+//! and then stops gossiping altogether (`Critical`); peers still pick its
+//! blocks up through sync summaries.
+
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+use crate::events::BridgeEvent;
+use crate::logs::LogLevel;
+use crate::{clock, EcoBlockContext};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChargingState {
+    Discharging,
+    Charging,
+    Full,
+    /// No battery, or running from the grid.
+    MainsPowered,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PowerState {
+    pub battery_voltage: Option<f64>,
+    /// State of charge, 0 to 100.
+    pub state_of_charge: Option<u8>,
+    pub charging: ChargingState,
+    /// Solar panel output, for nodes that measure it.
+    #[serde(default)]
+    pub solar_watts: Option<f64>,
+    #[serde(default)]
+    pub reported_at: u64,
+}
+
+impl PowerState {
+    pub fn validate(&self) -> Result<(), BridgeError> {
+        if self.state_of_charge.is_some_and(|soc| soc > 100) {
+            return Err(BridgeError::InvalidInput(format!("Invalid state of charge {:?}: expected 0 to 100", self.state_of_charge)));
+        }
+        for (name, value) in [("battery voltage", self.battery_voltage), ("solar output", self.solar_watts)] {
+            if value.is_some_and(|v| !v.is_finite() || v < 0.0) {
+                return Err(BridgeError::InvalidInput(format!("Invalid {} {:?}", name, value)));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum PowerMode {
+    #[default]
+    Normal,
+    /// Pushes only its own blocks, as a relay-election leaf.
+    Saver,
+    /// Pushes nothing; blocks leave only when peers sync.
+    Critical,
+}
+
+/// State of charge below which the node enters each mode. It only returns to a
+/// higher mode once the charge is `hysteresis` points above the threshold, or
+/// when it charges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PowerThresholds {
+    pub saver_below: u8,
+    pub critical_below: u8,
+    pub hysteresis: u8,
+}
+
+impl Default for PowerThresholds {
+    fn default() -> Self {
+        Self {
+            saver_below: 30,
+            critical_below: 10,
+            hysteresis: 5,
+        }
+    }
+}
+
+impl PowerThresholds {
+    pub fn validate(&self) -> Result<(), BridgeError> {
+        if self.critical_below > self.saver_below || self.saver_below > 100 {
+            return Err(BridgeError::InvalidInput("Invalid power thresholds: expected critical_below <= saver_below <= 100".to_string()));
+        }
+        Ok(())
+    }
+
+    /// The mode for `state` when the node is currently in `current`.
+    pub fn mode_for(&self, state: &PowerState, current: PowerMode) -> PowerMode {
+        let Some(soc) = state.state_of_charge else {
+            return current;
+        };
+        if state.charging != ChargingState::Discharging {
+            return PowerMode::Normal;
+        }
+        let above = |threshold: u8| soc >= threshold.saturating_add(self.hysteresis);
+        let mode = if soc < self.critical_below {
+            PowerMode::Critical
+        } else if soc < self.saver_below {
+            PowerMode::Saver
+        } else {
+            PowerMode::Normal
+        };
+        match current {
+            // Only step up past the hysteresis band.
+            PowerMode::Critical if mode < current && !above(self.critical_below) => PowerMode::Critical,
+            PowerMode::Saver | PowerMode::Critical if mode == PowerMode::Normal && !above(self.saver_below) => PowerMode::Saver,
+            _ => mode,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PowerManager {
+    pub thresholds: PowerThresholds,
+    state: Option<PowerState>,
+    mode: PowerMode,
+    /// Set by the host to pin a mode; `None` follows the thresholds.
+    manual: Option<PowerMode>,
+}
+
+impl PowerManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn state(&self) -> Option<PowerState> {
+        self.state
+    }
+
+    pub fn mode(&self) -> PowerMode {
+        self.manual.unwrap_or(self.mode)
+    }
+}
+
+impl EcoBlockContext {
+    /// Records the host's latest power telemetry and switches mode if a
+    /// threshold was crossed. The charge also feeds relay election.
+    pub fn report_power_state(&mut self, mut state: PowerState) -> Result<PowerMode, BridgeError> {
+        state.validate()?;
+        state.reported_at = clock::now_secs();
+        self.relay.battery_percent = match state.charging {
+            ChargingState::MainsPowered => None,
+            _ => state.state_of_charge,
+        };
+        let from = self.power.mode();
+        self.power.state = Some(state);
+        self.power.mode = self.power.thresholds.mode_for(&state, self.power.mode);
+        self.power_mode_changed(from);
+        Ok(self.power.mode())
+    }
+
+    /// Pins the power mode, or with `None` returns to automatic switching.
+    pub fn set_power_mode(&mut self, mode: Option<PowerMode>) {
+        let from = self.power.mode();
+        self.power.manual = mode;
+        self.power_mode_changed(from);
+    }
+
+    fn power_mode_changed(&mut self, from: PowerMode) {
+        let to = self.power.mode();
+        if from == to {
+            return;
+        }
+        self.log(LogLevel::Info, format!("Power mode changed from {:?} to {:?}", from, to));
+        self.emit_event(BridgeEvent::PowerModeChanged { from, to });
+    }
+}
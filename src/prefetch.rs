@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::dashboard::DashboardSummary;
+use crate::peer_id::PeerId;
+use crate::{clock, EcoBlockContext};
+
+/// Warmed results older than this are recomputed, since dashboards cover
+/// time windows ending now.
+pub const MAX_PREFETCH_AGE: u64 = 30;
+
+/// A query the host app will make at startup.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum QuerySpec {
+    /// `dashboard_summary()`, which includes the latest readings.
+    Dashboard,
+    /// `list_peers(peer_id)`.
+    PeerList(PeerId),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrefetchStatus {
+    pub requested: usize,
+    pub warmed: usize,
+    /// Queries answered from a warmed result.
+    pub hits: u64,
+    pub misses: u64,
+}
+
+#[derive(Debug, Clone)]
+enum Warmed {
+    Dashboard(DashboardSummary),
+    Peers(Vec<PeerId>),
+}
+
+/// What a warmed result was computed from; any change makes it stale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    changes: u64,
+    readings: usize,
+    records: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct Prefetcher {
+    specs: Vec<QuerySpec>,
+    warmed: HashMap<QuerySpec, (Stamp, u64, Warmed)>,
+    hits: u64,
+    misses: u64,
+    running: bool,
+}
+
+impl Prefetcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn request(&mut self, spec: QuerySpec) {
+        if !self.specs.contains(&spec) {
+            self.specs.push(spec);
+        }
+    }
+
+    /// Marks the warming thread as started; false if one is already running.
+    pub fn claim(&mut self) -> bool {
+        !std::mem::replace(&mut self.running, true)
+    }
+
+    pub fn release(&mut self) {
+        self.running = false;
+    }
+
+    fn fresh(&self, spec: &QuerySpec, stamp: Stamp, now: u64) -> Option<&Warmed> {
+        self.warmed
+            .get(spec)
+            .filter(|(s, at, _)| *s == stamp && now.saturating_sub(*at) <= MAX_PREFETCH_AGE)
+            .map(|(_, _, warmed)| warmed)
+    }
+}
+
+impl EcoBlockContext {
+    fn prefetch_stamp(&self) -> Stamp {
+        Stamp {
+            changes: self.changes.head(),
+            readings: self.readings.len(),
+            records: self.records.len(),
+        }
+    }
+
+    /// Computes every requested query that has no fresh result yet.
+    pub fn warm_prefetched(&mut self) {
+        let (stamp, now) = (self.prefetch_stamp(), clock::now_secs());
+        let stale: Vec<QuerySpec> = self
+            .prefetch
+            .specs
+            .iter()
+            .filter(|s| self.prefetch.fresh(s, stamp, now).is_none())
+            .cloned()
+            .collect();
+        for spec in stale {
+            let warmed = match &spec {
+                QuerySpec::Dashboard => Warmed::Dashboard(self.dashboard_summary()),
+                QuerySpec::PeerList(peer) => Warmed::Peers(self.list_peers(peer)),
+            };
+            self.prefetch.warmed.insert(spec, (stamp, now, warmed));
+        }
+    }
+
+    pub fn cached_dashboard_summary(&mut self) -> DashboardSummary {
+        let (stamp, now) = (self.prefetch_stamp(), clock::now_secs());
+        if let Some(Warmed::Dashboard(summary)) = self.prefetch.fresh(&QuerySpec::Dashboard, stamp, now) {
+            let summary = summary.clone();
+            self.prefetch.hits += 1;
+            return summary;
+        }
+        self.prefetch.misses += 1;
+        self.dashboard_summary()
+    }
+
+    pub fn cached_peers(&mut self, peer_id: &PeerId) -> Vec<PeerId> {
+        let (stamp, now) = (self.prefetch_stamp(), clock::now_secs());
+        if let Some(Warmed::Peers(peers)) = self.prefetch.fresh(&QuerySpec::PeerList(peer_id.clone()), stamp, now) {
+            let peers = peers.clone();
+            self.prefetch.hits += 1;
+            return peers;
+        }
+        self.prefetch.misses += 1;
+        self.list_peers(peer_id)
+    }
+
+    pub fn prefetch_status(&self) -> PrefetchStatus {
+        let (stamp, now) = (self.prefetch_stamp(), clock::now_secs());
+        PrefetchStatus {
+            requested: self.prefetch.specs.len(),
+            warmed: self.prefetch.specs.iter().filter(|s| self.prefetch.fresh(s, stamp, now).is_some()).count(),
+            hits: self.prefetch.hits,
+            misses: self.prefetch.misses,
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Dry run of block creation, so UIs can show what is wrong with an input
+//! before the user submits it. `validate_block_input` runs the same checks as
+//! `try_create_block` without signing or storing anything, and reports every
+//! problem it finds rather than stopping at the first.
+
+use ecoblock_core::domain::tangle_data::TangleBlockData;
+use ecoblock_core::domain::SensorData;
+use serde::{Deserialize, Serialize};
+use crate::block_id::BlockId;
+use crate::error::{BridgeError, ErrorKind};
+use crate::validation::RateAction;
+use crate::wire::MAX_PAYLOAD_LEN;
+use crate::EcoBlockContext;
+
+/// Room left in a frame for the id, public key and signature of a block.
+pub const BLOCK_SIGNATURE_OVERHEAD: usize = 512;
+/// Largest encoded data and parents of a block that still fits in one frame.
+pub const MAX_BLOCK_DATA_LEN: usize = MAX_PAYLOAD_LEN - BLOCK_SIGNATURE_OVERHEAD;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationStage {
+    Schema,
+    Size,
+    Parents,
+    Authorization,
+    Rates,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    pub stage: ValidationStage,
+    pub kind: ErrorKind,
+    /// The text `try_create_block` fails with, for errors.
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationReport {
+    /// True when creating the block would succeed.
+    pub valid: bool,
+    /// Encoded data and parents; `None` if the data does not parse.
+    pub size_bytes: Option<usize>,
+    /// Problems that make creation fail.
+    pub errors: Vec<ValidationIssue>,
+    /// Problems the block would be created with, such as a flagged reading or
+    /// parents not held locally.
+    pub warnings: Vec<ValidationIssue>,
+}
+
+impl ValidationIssue {
+    fn new(stage: ValidationStage, error: BridgeError) -> Self {
+        Self {
+            stage,
+            kind: error.kind(),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Parses block data as `try_create_block` does: as sensor data, and as the
+/// JSON payload that gets indexed.
+pub(crate) fn decode_block_input(data: &[u8]) -> Result<(SensorData, serde_json::Value), BridgeError> {
+    let error = |e: serde_json::Error| BridgeError::Deserialization(format!("Erreur de désérialisation SensorData: {}", e));
+    let sensor_data = serde_json::from_slice(data).map_err(error)?;
+    let payload = serde_json::from_slice(data).map_err(error)?;
+    Ok((sensor_data, payload))
+}
+
+impl EcoBlockContext {
+    /// Refuses blocks too large to be gossiped in one frame.
+    pub(crate) fn check_block_size(&self, block_data: &TangleBlockData) -> Result<usize, BridgeError> {
+        let size = serde_json::to_vec(block_data)
+            .map_err(|e| BridgeError::Serialization(e.to_string()))?
+            .len();
+        if size > MAX_BLOCK_DATA_LEN {
+            return Err(BridgeError::InvalidInput(format!("Invalid block: {} bytes of data exceeds {}", size, MAX_BLOCK_DATA_LEN)));
+        }
+        Ok(size)
+    }
+
+    /// Peers refuse blocks signed by a retired key, so none are made with it.
+    pub(crate) fn check_block_author(&self) -> Result<(), BridgeError> {
+        let key = self.keypair.public_key_hex();
+        if self.retired_keys.contains(&key) {
+            return Err(BridgeError::Unauthorized(format!("key {} has been retired", key)));
+        }
+        Ok(())
+    }
+
+    pub fn validate_block_input(&self, data: &[u8], parents: &[BlockId]) -> ValidationReport {
+        let (mut errors, mut warnings) = (Vec::new(), Vec::new());
+        let mut size_bytes = None;
+        match decode_block_input(data) {
+            Ok((sensor_data, payload)) => {
+                let block_data = TangleBlockData {
+                    parents: parents.iter().cloned().map(String::from).collect(),
+                    data: sensor_data,
+                };
+                match self.check_block_size(&block_data) {
+                    Ok(size) => size_bytes = Some(size),
+                    Err(e) => errors.push(ValidationIssue::new(ValidationStage::Size, e)),
+                }
+                for violation in self.check_rates(&payload) {
+                    if violation.action == RateAction::Reject {
+                        let error = BridgeError::Rejected(format!("Bloc rejeté: {}", violation));
+                        errors.push(ValidationIssue::new(ValidationStage::Rates, error));
+                    } else {
+                        let warning = BridgeError::Rejected(format!("Reading will be flagged: {}", violation));
+                        warnings.push(ValidationIssue::new(ValidationStage::Rates, warning));
+                    }
+                }
+            }
+            Err(e) => errors.push(ValidationIssue::new(ValidationStage::Schema, e)),
+        }
+        for parent in parents.iter().filter(|p| !self.block_log.contains(p.as_str())) {
+            warnings.push(ValidationIssue::new(
+                ValidationStage::Parents,
+                BridgeError::NotFound(format!("Unknown parent {}: not held locally", parent)),
+            ));
+        }
+        if let Err(e) = self.check_block_author() {
+            errors.push(ValidationIssue::new(ValidationStage::Authorization, e));
+        }
+        ValidationReport {
+            valid: errors.is_empty(),
+            size_bytes,
+            errors,
+            warnings,
+        }
+    }
+}
@@ -0,0 +1,317 @@
+//! Display formatting for query results, so the Android, iOS and web
+//! frontends show the same reading the same way. Each metric has a unit symbol
+//! and a number of decimals, defaulting to the units the bridge stores; the
+//! locale picks the decimal and grouping separators.
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use crate::aggregate::MetricAggregate;
+use crate::dashboard::{DashboardSummary, LatestValue, MetricRange};
+use crate::error::BridgeError;
+use crate::home_assistant;
+use crate::thresholds::{ActiveAlert, ThresholdDirection};
+use crate::EcoBlockContext;
+
+/// Most decimals a metric may be shown with.
+pub const MAX_PRECISION: u8 = 6;
+/// Decimals for metrics without a known or configured format.
+pub const DEFAULT_PRECISION: u8 = 2;
+const NO_BREAK_SPACE: char = '\u{a0}';
+const NARROW_NO_BREAK_SPACE: char = '\u{202f}';
+
+/// Separators of a locale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Locale {
+    pub decimal: char,
+    pub group: char,
+    /// Groups of two digits above the thousands, as in 12,34,567.
+    pub indian_grouping: bool,
+    /// Whether a space separates a number from `%`.
+    pub percent_space: bool,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self {
+            decimal: '.',
+            group: ',',
+            indian_grouping: false,
+            percent_space: false,
+        }
+    }
+}
+
+impl Locale {
+    /// The separators for a BCP 47 tag such as `de`, `fr-CA` or `en_IN`.
+    /// Unknown languages get English separators.
+    pub fn from_tag(tag: &str) -> Self {
+        let mut parts = tag.split(['-', '_']);
+        let language = parts.next().unwrap_or_default().to_ascii_lowercase();
+        let region = parts.next().unwrap_or_default().to_ascii_uppercase();
+        let english = Self::default();
+        let comma = |group, percent_space| Self {
+            decimal: ',',
+            group,
+            indian_grouping: false,
+            percent_space,
+        };
+        match (language.as_str(), region.as_str()) {
+            ("en", "IN") | ("hi", _) | ("bn", _) => Self {
+                indian_grouping: true,
+                ..english
+            },
+            ("de", "CH") | ("de", "LI") | ("it", "CH") => Self {
+                group: '’',
+                ..english
+            },
+            ("de", _) | ("da", _) => comma('.', true),
+            ("es", _) | ("el", _) | ("tr", _) => comma('.', language == "es"),
+            ("it", _) | ("nl", _) | ("pt", _) | ("id", _) => comma('.', false),
+            ("fr", _) => comma(NARROW_NO_BREAK_SPACE, true),
+            ("sv", _) | ("nb", _) | ("no", _) | ("fi", _) | ("ru", _) | ("uk", _) | ("cs", _) | ("sk", _) | ("bg", _) => {
+                comma(NO_BREAK_SPACE, true)
+            }
+            ("pl", _) | ("hu", _) => comma(NO_BREAK_SPACE, false),
+            _ => english,
+        }
+    }
+}
+
+/// How a metric's values are shown.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetricFormat {
+    pub metric: String,
+    /// Symbol after the number, such as `°C`.
+    pub unit: Option<String>,
+    /// Decimals shown.
+    pub precision: u8,
+}
+
+impl MetricFormat {
+    pub fn validate(&self) -> Result<(), BridgeError> {
+        if self.metric.is_empty() || self.precision > MAX_PRECISION {
+            return Err(BridgeError::InvalidInput(format!("Invalid metric format for {:?}: needs a metric and at most {} decimals", self.metric, MAX_PRECISION)));
+        }
+        Ok(())
+    }
+
+    /// The format of `metric` when none is configured.
+    pub fn default_for(metric: &str) -> Self {
+        let precision = match metric {
+            "temperature" | "pm25" | "pressure" | "power" => 1,
+            "humidity" | "co2" | "illuminance" => 0,
+            _ => DEFAULT_PRECISION,
+        };
+        Self {
+            metric: metric.to_string(),
+            unit: home_assistant::device_class(metric).map(|(_, unit)| unit.to_string()),
+            precision,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormattedValue {
+    pub value: f64,
+    /// The number alone, such as `1.234,5`.
+    pub number: String,
+    pub unit: Option<String>,
+    /// Number and unit, such as `1.234,5 hPa`.
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormattedAggregate {
+    pub metric: String,
+    pub count: String,
+    pub min: FormattedValue,
+    pub max: FormattedValue,
+    pub mean: FormattedValue,
+    pub sum: FormattedValue,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormattedLatest {
+    pub sensor_id: String,
+    pub metric: String,
+    pub value: FormattedValue,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormattedRange {
+    pub metric: String,
+    pub min: FormattedValue,
+    pub max: FormattedValue,
+    pub count: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormattedAlert {
+    pub sensor_id: String,
+    pub metric: String,
+    pub direction: ThresholdDirection,
+    pub since: u64,
+    pub last_value: FormattedValue,
+}
+
+/// The value parts of a `DashboardSummary`, formatted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormattedDashboard {
+    pub latest: Vec<FormattedLatest>,
+    pub last_24h: Vec<FormattedRange>,
+    pub active_alerts: Vec<FormattedAlert>,
+}
+
+fn group_digits(digits: &str, locale: &Locale) -> String {
+    let mut groups = Vec::new();
+    let mut rest = digits;
+    let mut size = 3;
+    while rest.len() > size {
+        let (head, tail) = rest.split_at(rest.len() - size);
+        groups.push(tail);
+        rest = head;
+        if locale.indian_grouping {
+            size = 2;
+        }
+    }
+    groups.push(rest);
+    groups.reverse();
+    groups.join(&locale.group.to_string())
+}
+
+/// `value` with `precision` decimals and the separators of `locale`.
+/// Non-finite values are shown as `–`.
+pub fn format_number(value: f64, precision: u8, locale: &Locale) -> String {
+    if !value.is_finite() {
+        return "–".to_string();
+    }
+    let text = format!("{:.*}", usize::from(precision), value.abs());
+    let (whole, fraction) = text.split_once('.').unwrap_or((text.as_str(), ""));
+    // Values that round to zero lose their sign.
+    let negative = value < 0.0 && text.chars().any(|c| c.is_ascii_digit() && c != '0');
+    let mut number = String::new();
+    if negative {
+        number.push('-');
+    }
+    number.push_str(&group_digits(whole, locale));
+    if !fraction.is_empty() {
+        number.push(locale.decimal);
+        number.push_str(fraction);
+    }
+    number
+}
+
+#[derive(Debug, Default)]
+pub struct MetricFormats {
+    formats: BTreeMap<String, MetricFormat>,
+}
+
+impl MetricFormats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, format: MetricFormat) -> Result<(), BridgeError> {
+        format.validate()?;
+        self.formats.insert(format.metric.clone(), format);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, metric: &str) -> bool {
+        self.formats.remove(metric).is_some()
+    }
+
+    /// Configured formats, by metric.
+    pub fn list(&self) -> Vec<MetricFormat> {
+        self.formats.values().cloned().collect()
+    }
+
+    pub fn get(&self, metric: &str) -> MetricFormat {
+        self.formats.get(metric).cloned().unwrap_or_else(|| MetricFormat::default_for(metric))
+    }
+
+    pub fn value(&self, metric: &str, value: f64, locale: &Locale) -> FormattedValue {
+        let format = self.get(metric);
+        let number = format_number(value, format.precision, locale);
+        let text = match format.unit.as_deref() {
+            None | Some("") => number.clone(),
+            Some("%") if !locale.percent_space => format!("{}%", number),
+            Some(unit) => format!("{}{}{}", number, NO_BREAK_SPACE, unit),
+        };
+        FormattedValue {
+            value,
+            number,
+            unit: format.unit,
+            text,
+        }
+    }
+
+    fn count(count: usize, locale: &Locale) -> String {
+        format_number(count as f64, 0, locale)
+    }
+}
+
+impl MetricAggregate {
+    pub fn formatted(&self, formats: &MetricFormats, locale: &Locale) -> FormattedAggregate {
+        let value = |v| formats.value(&self.metric, v, locale);
+        FormattedAggregate {
+            metric: self.metric.clone(),
+            count: MetricFormats::count(self.count, locale),
+            min: value(self.min),
+            max: value(self.max),
+            mean: value(self.mean),
+            sum: value(self.sum),
+        }
+    }
+}
+
+impl LatestValue {
+    pub fn formatted(&self, formats: &MetricFormats, locale: &Locale) -> FormattedLatest {
+        FormattedLatest {
+            sensor_id: self.sensor_id.clone(),
+            metric: self.metric.clone(),
+            value: formats.value(&self.metric, self.value, locale),
+            timestamp: self.timestamp,
+        }
+    }
+}
+
+impl MetricRange {
+    pub fn formatted(&self, formats: &MetricFormats, locale: &Locale) -> FormattedRange {
+        FormattedRange {
+            metric: self.metric.clone(),
+            min: formats.value(&self.metric, self.min, locale),
+            max: formats.value(&self.metric, self.max, locale),
+            count: MetricFormats::count(self.count, locale),
+        }
+    }
+}
+
+impl ActiveAlert {
+    pub fn formatted(&self, formats: &MetricFormats, locale: &Locale) -> FormattedAlert {
+        FormattedAlert {
+            sensor_id: self.sensor_id.clone(),
+            metric: self.metric.clone(),
+            direction: self.direction,
+            since: self.since,
+            last_value: formats.value(&self.metric, self.last_value, locale),
+        }
+    }
+}
+
+impl DashboardSummary {
+    pub fn formatted(&self, formats: &MetricFormats, locale: &Locale) -> FormattedDashboard {
+        FormattedDashboard {
+            latest: self.latest.iter().map(|v| v.formatted(formats, locale)).collect(),
+            last_24h: self.last_24h.iter().map(|r| r.formatted(formats, locale)).collect(),
+            active_alerts: self.active_alerts.iter().map(|a| a.formatted(formats, locale)).collect(),
+        }
+    }
+}
+
+impl EcoBlockContext {
+    pub fn formatted_dashboard(&self, locale: &Locale) -> FormattedDashboard {
+        self.dashboard_summary().formatted(&self.metric_formats, locale)
+    }
+}
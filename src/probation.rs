@@ -0,0 +1,131 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use ecoblock_storage::tangle::block::TangleBlock;
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+use crate::records::Record;
+
+pub const ENDORSEMENT_KIND: &str = "endorsement";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EndorsementBody {
+    pub key: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProbationPolicy {
+    pub enabled: bool,
+    pub required_endorsements: usize,
+    pub trusted_keys: Vec<String>,
+}
+
+impl Default for ProbationPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            required_endorsements: 1,
+            trusted_keys: Vec::new(),
+        }
+    }
+}
+
+/// What an endorsement lets out of the pool, each in arrival order.
+#[derive(Debug, Default)]
+pub struct Released {
+    pub records: Vec<Record>,
+    pub blocks: Vec<TangleBlock>,
+}
+
+#[derive(Debug, Default)]
+pub struct ProbationPool {
+    policy: ProbationPolicy,
+    trusted: HashSet<String>,
+    endorsements: HashMap<String, BTreeSet<String>>,
+    held: Vec<Record>,
+    held_blocks: Vec<TangleBlock>,
+}
+
+impl ProbationPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn policy(&self) -> ProbationPolicy {
+        self.policy.clone()
+    }
+
+    pub fn set_policy(&mut self, policy: ProbationPolicy) {
+        self.trusted = policy.trusted_keys.iter().cloned().collect();
+        self.policy = policy;
+    }
+
+    pub fn is_trusted(&self, key: &str) -> bool {
+        self.trusted.contains(key)
+    }
+
+    pub fn endorsers(&self, key: &str) -> Vec<String> {
+        self.endorsements
+            .get(key)
+            .map(|e| e.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn is_endorsed(&self, key: &str) -> bool {
+        self.is_trusted(key)
+            || self
+                .endorsements
+                .get(key)
+                .is_some_and(|e| e.len() >= self.policy.required_endorsements.max(1))
+    }
+
+    pub fn should_hold(&self, author: &str, local: &str) -> bool {
+        self.policy.enabled && author != local && !self.is_endorsed(author)
+    }
+
+    pub fn hold(&mut self, record: Record) -> bool {
+        if self.held.iter().any(|r| r.id == record.id) {
+            return false;
+        }
+        self.held.push(record);
+        true
+    }
+
+    pub fn held(&self) -> Vec<Record> {
+        self.held.clone()
+    }
+
+    pub fn hold_block(&mut self, block: TangleBlock) -> bool {
+        if self.held_blocks.iter().any(|b| b.id == block.id) {
+            return false;
+        }
+        self.held_blocks.push(block);
+        true
+    }
+
+    pub fn held_blocks(&self) -> Vec<TangleBlock> {
+        self.held_blocks.clone()
+    }
+
+    /// Counts an endorsement from a trusted key and returns the records and
+    /// blocks released once `key` reaches the required number of endorsements.
+    pub fn endorse(&mut self, endorser: &str, key: &str) -> Result<Released, BridgeError> {
+        if !self.is_trusted(endorser) {
+            return Err(BridgeError::Unauthorized(format!("{} is not a trusted key", endorser)));
+        }
+        self.endorsements
+            .entry(key.to_string())
+            .or_default()
+            .insert(endorser.to_string());
+        if !self.is_endorsed(key) {
+            return Ok(Released::default());
+        }
+        let (records, held) = std::mem::take(&mut self.held)
+            .into_iter()
+            .partition(|r| r.author == key);
+        self.held = held;
+        let (blocks, held_blocks) = std::mem::take(&mut self.held_blocks)
+            .into_iter()
+            .partition(|b| b.public_key == key);
+        self.held_blocks = held_blocks;
+        Ok(Released { records, blocks })
+    }
+}
@@ -0,0 +1,168 @@
+//! Radio silence windows. Some sites forbid transmitting at set times of day;
+//! frames sent during a quiet window are queued and go out when it ends. Alert
+//! records may still be sent when the active policy allows it.
+
+use std::collections::VecDeque;
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+use crate::peer_id::PeerId;
+use crate::wire::{self, BlockEnvelope, Frame, FrameKind};
+use crate::EcoBlockContext;
+
+pub const MINUTES_PER_DAY: u16 = 24 * 60;
+/// Frames queued during silence; the oldest are dropped first, and peers
+/// catch up on their blocks through sync summaries.
+pub const MAX_QUEUED_FRAMES: usize = 4096;
+
+/// Daily window in UTC minutes since midnight. A window whose end is before
+/// its start wraps past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietWindow {
+    pub start_minute: u16,
+    pub end_minute: u16,
+}
+
+impl QuietWindow {
+    pub fn validate(&self) -> Result<(), BridgeError> {
+        if self.start_minute >= MINUTES_PER_DAY || self.end_minute >= MINUTES_PER_DAY || self.start_minute == self.end_minute {
+            return Err(BridgeError::InvalidInput(format!(
+                "Invalid quiet window {}-{}: expected distinct minutes below {}",
+                self.start_minute, self.end_minute, MINUTES_PER_DAY
+            )));
+        }
+        Ok(())
+    }
+
+    fn contains(&self, minute: u16) -> bool {
+        if self.start_minute < self.end_minute {
+            minute >= self.start_minute && minute < self.end_minute
+        } else {
+            minute >= self.start_minute || minute < self.end_minute
+        }
+    }
+
+    /// Seconds from `now` until the window ends, if it is open at `now`.
+    fn remaining(&self, now: u64) -> Option<u64> {
+        let second = now % 86_400;
+        if !self.contains((second / 60) as u16) {
+            return None;
+        }
+        let end = u64::from(self.end_minute) * 60;
+        Some(if end > second { end - second } else { 86_400 - second + end })
+    }
+
+    /// Seconds from `now` until the window next opens.
+    fn until_start(&self, now: u64) -> u64 {
+        let second = now % 86_400;
+        let start = u64::from(self.start_minute) * 60;
+        if start > second {
+            start - second
+        } else {
+            86_400 - second + start
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietStatus {
+    pub quiet: bool,
+    /// When the current silence ends; `None` outside a window.
+    pub ends_at: Option<u64>,
+    /// When the next window opens; `None` without windows.
+    pub next_start: Option<u64>,
+    pub queued_frames: usize,
+    /// Whether the active policy lets alert records through.
+    pub alerts_allowed: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct QuietHours {
+    windows: Vec<QuietWindow>,
+    queue: VecDeque<(PeerId, Frame)>,
+    /// Set from the active policy.
+    pub(crate) alert_override: bool,
+}
+
+impl QuietHours {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn windows(&self) -> &[QuietWindow] {
+        &self.windows
+    }
+
+    pub fn set_windows(&mut self, windows: Vec<QuietWindow>) -> Result<(), BridgeError> {
+        for window in &windows {
+            window.validate()?;
+        }
+        self.windows = windows;
+        Ok(())
+    }
+
+    /// Seconds until silence ends, if it applies at `now`. Overlapping windows
+    /// are followed to their last end.
+    fn silence_left(&self, now: u64) -> Option<u64> {
+        let mut left = None;
+        let mut at = now;
+        while let Some(remaining) = self.windows.iter().filter_map(|w| w.remaining(at)).max() {
+            let total = at + remaining - now;
+            if left.is_some_and(|l| l >= total) || total > 86_400 {
+                break;
+            }
+            left = Some(total);
+            at = now + total;
+        }
+        left
+    }
+
+    pub fn is_quiet(&self, now: u64) -> bool {
+        self.silence_left(now).is_some()
+    }
+
+    pub fn status(&self, now: u64) -> QuietStatus {
+        let ends_at = self.silence_left(now).map(|left| now + left);
+        QuietStatus {
+            quiet: ends_at.is_some(),
+            ends_at,
+            next_start: self.windows.iter().map(|w| now + w.until_start(now)).min(),
+            queued_frames: self.queue.len(),
+            alerts_allowed: self.alert_override,
+        }
+    }
+
+    /// Alert records pass while the policy allows them.
+    fn exempt(&self, frame: &Frame) -> bool {
+        self.alert_override
+            && frame.kind == FrameKind::Block
+            && matches!(wire::parse_block_envelope(&frame.payload), Ok(BlockEnvelope::Record(r)) if r.kind == "alert")
+    }
+
+    /// Queues `frame` if transmitting is not allowed at `now`, or hands it
+    /// back to be sent.
+    pub(crate) fn hold(&mut self, peer: &PeerId, frame: Frame, now: u64) -> Option<Frame> {
+        if !self.is_quiet(now) || self.exempt(&frame) {
+            return Some(frame);
+        }
+        if self.queue.len() == MAX_QUEUED_FRAMES {
+            self.queue.pop_front();
+        }
+        self.queue.push_back((peer.clone(), frame));
+        None
+    }
+}
+
+impl EcoBlockContext {
+    /// Sends the frames queued during silence once it has ended.
+    pub fn flush_quiet_queue(&mut self, now: u64) -> usize {
+        if self.quiet_hours.is_quiet(now) {
+            return 0;
+        }
+        let queued = std::mem::take(&mut self.quiet_hours.queue);
+        let count = queued.len();
+        for (peer, frame) in queued {
+            self.send_frame(&peer, frame);
+        }
+        count
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use serde_json::Value;
+
+/// Numeric values carried by one block, keyed by metric name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+    pub block_id: String,
+    pub sensor_id: String,
+    pub timestamp: u64,
+    pub values: BTreeMap<String, f64>,
+}
+
+impl Reading {
+    /// Every top-level numeric field except `timestamp` is treated as a metric.
+    /// Readings without a `sensor_id` field are attributed to the signing node.
+    pub fn from_payload(block_id: &str, signer: &str, payload: &Value, now: u64) -> Self {
+        let mut values = BTreeMap::new();
+        if let Some(fields) = payload.as_object() {
+            for (key, value) in fields {
+                if key == "timestamp" {
+                    continue;
+                }
+                if let Some(v) = value.as_f64() {
+                    values.insert(key.clone(), v);
+                }
+            }
+        }
+        let sensor_id = payload
+            .get("sensor_id")
+            .and_then(Value::as_str)
+            .unwrap_or(signer)
+            .to_string();
+        let timestamp = payload
+            .get("timestamp")
+            .and_then(Value::as_u64)
+            .unwrap_or(now);
+        Self {
+            block_id: block_id.to_string(),
+            sensor_id,
+            timestamp,
+            values,
+        }
+    }
+
+    pub fn value(&self, metric: &str) -> Option<f64> {
+        self.values.get(metric).copied()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryOptions {
+    pub include_flagged: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct ReadingStore {
+    readings: Vec<Reading>,
+    by_block: HashMap<String, usize>,
+    flagged: HashSet<String>,
+    last_values: HashMap<(String, String), (u64, f64)>,
+}
+
+impl ReadingStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, reading: Reading) {
+        if !self.flagged.contains(&reading.block_id) {
+            for (metric, value) in &reading.values {
+                let key = (reading.sensor_id.clone(), metric.clone());
+                let entry = self.last_values.entry(key).or_insert((reading.timestamp, *value));
+                if reading.timestamp >= entry.0 {
+                    *entry = (reading.timestamp, *value);
+                }
+            }
+        }
+        self.by_block.insert(reading.block_id.clone(), self.readings.len());
+        self.readings.push(reading);
+    }
+
+    pub fn get(&self, block_id: &str) -> Option<&Reading> {
+        self.by_block.get(block_id).map(|&i| &self.readings[i])
+    }
+
+    /// Most recent unflagged `(timestamp, value)` reported by a sensor for a metric.
+    pub fn last_value(&self, sensor_id: &str, metric: &str) -> Option<(u64, f64)> {
+        self.last_values
+            .get(&(sensor_id.to_string(), metric.to_string()))
+            .copied()
+    }
+
+    pub fn contains(&self, block_id: &str) -> bool {
+        self.by_block.contains_key(block_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.readings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.readings.is_empty()
+    }
+
+    /// Readings that have not been flagged as erroneous.
+    pub fn iter(&self) -> impl Iterator<Item = &Reading> {
+        self.iter_with(QueryOptions::default())
+    }
+
+    pub fn iter_with(&self, options: QueryOptions) -> impl Iterator<Item = &Reading> {
+        self.readings
+            .iter()
+            .filter(move |r| options.include_flagged || !self.flagged.contains(&r.block_id))
+    }
+
+    /// Unflagged readings whose timestamp falls within `[from, to]`.
+    pub fn in_range(&self, from: u64, to: u64) -> impl Iterator<Item = &Reading> {
+        self.in_range_with(from, to, QueryOptions::default())
+    }
+
+    pub fn in_range_with(&self, from: u64, to: u64, options: QueryOptions) -> impl Iterator<Item = &Reading> {
+        self.iter_with(options)
+            .filter(move |r| r.timestamp >= from && r.timestamp <= to)
+    }
+
+    /// Drops the readings of `block_ids`. Last values are kept.
+    pub fn remove(&mut self, block_ids: &HashSet<String>) {
+        self.readings.retain(|r| !block_ids.contains(&r.block_id));
+        self.by_block = self.readings.iter().enumerate().map(|(i, r)| (r.block_id.clone(), i)).collect();
+        self.flagged.retain(|id| !block_ids.contains(id));
+    }
+
+    pub fn flag(&mut self, block_id: &str) {
+        self.flagged.insert(block_id.to_string());
+    }
+
+    pub fn is_flagged(&self, block_id: &str) -> bool {
+        self.flagged.contains(block_id)
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use ecoblock_crypto::keys::keypair::CryptoKeypair;
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::canonical::{self, Encoding};
+use crate::hybrid::{self, PqSignature};
+use crate::suites::Suite;
+
+/// Signed bridge-level block carrying a non-sensor payload (alerts, annotations, ...).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub id: String,
+    pub kind: String,
+    pub author: String,
+    pub timestamp: u64,
+    pub body: Value,
+    pub signature: String,
+    /// Omitted for the default suite, so such records keep their original encoding.
+    #[serde(default, skip_serializing_if = "Suite::is_default")]
+    pub suite: Suite,
+    /// Omitted for the legacy encoding, which older nodes assume.
+    #[serde(default, skip_serializing_if = "Encoding::is_legacy")]
+    pub encoding: Encoding,
+    /// Post-quantum signature over the same bytes, from hybrid signers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pq_signature: Option<PqSignature>,
+}
+
+impl Record {
+    pub fn sign(kind: &str, body: Value, keypair: &CryptoKeypair, timestamp: u64) -> Self {
+        Self::sign_with(Suite::default(), Encoding::Legacy, kind, body, keypair, timestamp)
+    }
+
+    pub fn sign_with(suite: Suite, encoding: Encoding, kind: &str, body: Value, keypair: &CryptoKeypair, timestamp: u64) -> Self {
+        let author = keypair.public_key_hex();
+        let bytes = Self::signing_bytes(suite, encoding, kind, &author, timestamp, &body);
+        let signature = hex::encode(keypair.sign(&bytes).to_bytes());
+        Self {
+            id: hex::encode(suite.digest(&bytes)),
+            kind: kind.to_string(),
+            author,
+            timestamp,
+            body,
+            signature,
+            suite,
+            encoding,
+            pq_signature: None,
+        }
+    }
+
+    /// Checks that the id matches the content and the signature matches the author key.
+    pub fn verify(&self) -> bool {
+        self.id_matches() && verify_signature(&self.author, &self.signed_bytes(), &self.signature)
+    }
+
+    pub fn id_matches(&self) -> bool {
+        hex::encode(self.suite.digest(&self.signed_bytes())) == self.id
+    }
+
+    /// Checks the post-quantum signature against `public_key`; `None` if the
+    /// record has none.
+    pub fn verify_pq(&self, public_key: &str) -> Option<bool> {
+        let signature = self.pq_signature.as_ref()?;
+        Some(hybrid::verify(signature, &self.signed_bytes(), public_key))
+    }
+
+    /// The bytes both signatures cover.
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        Self::signing_bytes(self.suite, self.encoding, &self.kind, &self.author, self.timestamp, &self.body)
+    }
+
+    /// Non-default suites are bound into the signed bytes so a record cannot be
+    /// relabelled with another suite. Canonical messages always name the suite.
+    fn signing_bytes(suite: Suite, encoding: Encoding, kind: &str, author: &str, timestamp: u64, body: &Value) -> Vec<u8> {
+        if !encoding.is_legacy() {
+            canonical::message(&(suite.name(), kind, author, timestamp, body)).unwrap_or_default()
+        } else if suite.is_default() {
+            serde_json::to_vec(&(kind, author, timestamp, body)).unwrap_or_default()
+        } else {
+            serde_json::to_vec(&(suite.name(), kind, author, timestamp, body)).unwrap_or_default()
+        }
+    }
+}
+
+pub fn verify_signature(public_key_hex: &str, message: &[u8], signature_hex: &str) -> bool {
+    let key_bytes: Option<[u8; 32]> = hex::decode(public_key_hex).ok().and_then(|b| b.try_into().ok());
+    let sig_bytes: Option<[u8; 64]> = hex::decode(signature_hex).ok().and_then(|b| b.try_into().ok());
+    let (Some(key_bytes), Some(sig_bytes)) = (key_bytes, sig_bytes) else {
+        return false;
+    };
+    let Ok(key) = VerifyingKey::from_bytes(&key_bytes) else {
+        return false;
+    };
+    key.verify_strict(message, &Signature::from_bytes(&sig_bytes)).is_ok()
+}
+
+#[derive(Debug, Default)]
+pub struct RecordLog {
+    records: Vec<Record>,
+    by_id: HashMap<String, usize>,
+}
+
+impl RecordLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `false` if a record with the same id is already stored.
+    pub fn insert(&mut self, record: Record) -> bool {
+        if self.by_id.contains_key(&record.id) {
+            return false;
+        }
+        self.by_id.insert(record.id.clone(), self.records.len());
+        self.records.push(record);
+        true
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Record> {
+        self.by_id.get(id).map(|&i| &self.records[i])
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn latest_from(&self, author: &str) -> Option<u64> {
+        self.records
+            .iter()
+            .filter(|r| r.author == author)
+            .map(|r| r.timestamp)
+            .max()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Record> {
+        self.records.iter()
+    }
+
+    pub fn kinds(&self) -> Vec<String> {
+        let mut kinds: Vec<String> = self.records.iter().map(|r| r.kind.clone()).collect();
+        kinds.sort();
+        kinds.dedup();
+        kinds
+    }
+
+    pub fn of_kind<'a>(&'a self, kind: &'a str) -> impl Iterator<Item = &'a Record> {
+        self.records.iter().filter(move |r| r.kind == kind)
+    }
+}
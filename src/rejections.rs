@@ -0,0 +1,147 @@
+//! Rejection feedback. A node that refuses a block or record from a peer sends
+//! it a short signed notice saying what was refused and why. The sender counts
+//! the notices per rejecting node and error kind, so a misconfigured key or
+//! network shows up in `rejection_stats()` instead of as silent data loss.
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::error::{BridgeError, ErrorKind};
+use crate::logs::LogLevel;
+use crate::peer_id::PeerId;
+use crate::records::Record;
+use crate::wire::{self, BlockEnvelope, Frame, FrameKind};
+use crate::{clock, EcoBlockContext};
+
+pub const REJECTION_KIND: &str = "rejection";
+/// Notices sent to one peer per minute; further rejections go unreported.
+pub const MAX_NOTICES_PER_MINUTE: u32 = 10;
+/// Reasons are cut to this many bytes to keep notices small.
+const MAX_REASON_LEN: usize = 160;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RejectionNotice {
+    /// The refused block or record, when the frame could be parsed that far.
+    pub id: Option<String>,
+    pub kind: ErrorKind,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RejectionStats {
+    /// The node that refused this node's blocks.
+    pub by: String,
+    pub kind: ErrorKind,
+    pub count: u64,
+    pub last_id: Option<String>,
+    pub last_reason: String,
+    pub last_at: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Rejections {
+    /// Start of the current minute and notices sent in it, per peer.
+    sent: HashMap<PeerId, (u64, u32)>,
+    received: HashMap<(String, ErrorKind), RejectionStats>,
+}
+
+impl Rejections {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn allow_notice(&mut self, peer: &PeerId, now: u64) -> bool {
+        let (start, count) = self.sent.entry(peer.clone()).or_insert((now, 0));
+        if now.saturating_sub(*start) >= 60 {
+            *start = now;
+            *count = 0;
+        }
+        *count += 1;
+        *count <= MAX_NOTICES_PER_MINUTE
+    }
+
+    /// Most frequent first.
+    pub fn stats(&self) -> Vec<RejectionStats> {
+        let mut stats: Vec<RejectionStats> = self.received.values().cloned().collect();
+        stats.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.by.cmp(&b.by)));
+        stats
+    }
+
+    pub fn reset(&mut self) {
+        self.received.clear();
+    }
+}
+
+fn truncate(reason: &str) -> String {
+    let mut end = reason.len().min(MAX_REASON_LEN);
+    while !reason.is_char_boundary(end) {
+        end -= 1;
+    }
+    reason[..end].to_string()
+}
+
+impl EcoBlockContext {
+    /// Tells `peer` that the block frame in `bytes` was refused with `error`.
+    /// Other frames, and notices themselves, are not answered.
+    pub(crate) fn notify_rejection(&mut self, peer: &PeerId, bytes: &[u8], error: &BridgeError) {
+        let Ok(frame) = wire::parse_frame(bytes) else {
+            return;
+        };
+        if frame.kind != FrameKind::Block {
+            return;
+        }
+        let id = match wire::parse_block_envelope(&frame.payload) {
+            Ok(BlockEnvelope::Record(record)) if record.kind == REJECTION_KIND => return,
+            Ok(BlockEnvelope::Record(record)) => Some(record.id),
+            Ok(BlockEnvelope::Sensor(block)) => Some(block.id),
+            Ok(BlockEnvelope::CanonicalSensor(canonical)) => Some(canonical.block.id),
+            Ok(BlockEnvelope::HybridSensor(hybrid)) => Some(hybrid.block.id),
+            Err(_) => None,
+        };
+        let now = clock::now_secs();
+        if !self.rejections.allow_notice(peer, now) {
+            return;
+        }
+        let notice = RejectionNotice {
+            id,
+            kind: error.kind(),
+            reason: truncate(&error.to_string()),
+        };
+        let body = serde_json::to_value(&notice).unwrap_or_default();
+        let record = match self.sign_record(REJECTION_KIND, body, now) {
+            Ok(record) => record,
+            Err(e) => {
+                self.log(LogLevel::Warn, format!("Rejection notice for {} not sent: {}", peer, e));
+                return;
+            }
+        };
+        if let Ok(payload) = serde_json::to_vec(&BlockEnvelope::Record(record)) {
+            self.send_frame(peer, Frame::new(FrameKind::Block, payload));
+        }
+    }
+
+    pub(crate) fn apply_rejection(&mut self, record: &Record) -> Result<(), BridgeError> {
+        let notice: RejectionNotice = serde_json::from_value(record.body.clone())
+            .map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+        self.log(
+            LogLevel::Warn,
+            format!("{} refused {}: {}", record.author, notice.id.as_deref().unwrap_or("a frame"), notice.reason),
+        );
+        let stats = self
+            .rejections
+            .received
+            .entry((record.author.clone(), notice.kind))
+            .or_insert_with(|| RejectionStats {
+                by: record.author.clone(),
+                kind: notice.kind,
+                count: 0,
+                last_id: None,
+                last_reason: String::new(),
+                last_at: 0,
+            });
+        stats.count += 1;
+        stats.last_id = notice.id;
+        stats.last_reason = notice.reason;
+        stats.last_at = record.timestamp;
+        Ok(())
+    }
+}
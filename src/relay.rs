@@ -0,0 +1,151 @@
+//! Relay election for dense neighbourhoods. Nodes advertise their battery,
+//! link count and uptime in their announcement; each node ranks itself among
+//! its direct neighbours and acts as a relay only when it is among the best
+//! few. Leaves still push their own blocks but no longer forward others'.
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::peer_id::PeerId;
+use crate::power::PowerMode;
+use crate::{clock, EcoBlockContext};
+
+/// Uptime beyond which a node counts as fully stable.
+const STABLE_AFTER_SECS: u64 = 24 * 3600;
+/// Links beyond which more do not raise the score.
+const MAX_SCORED_LINKS: u32 = 10;
+/// Battery assumed for nodes that do not report one (mains powered or unknown).
+const UNKNOWN_BATTERY: u8 = 50;
+
+/// What a node advertises about itself for the election.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayMetrics {
+    /// `None` for mains-powered nodes and hosts that do not report it.
+    pub battery_percent: Option<u8>,
+    /// Direct mesh neighbours.
+    pub links: u32,
+    /// Seconds since the node's bridge context was created.
+    pub uptime: u64,
+}
+
+impl RelayMetrics {
+    /// Battery, links and stability weighted equally, each scaled to 0..=1.
+    pub fn score(&self) -> f64 {
+        let battery = f64::from(self.battery_percent.unwrap_or(UNKNOWN_BATTERY).min(100)) / 100.0;
+        let links = f64::from(self.links.min(MAX_SCORED_LINKS)) / f64::from(MAX_SCORED_LINKS);
+        let stability = self.uptime.min(STABLE_AFTER_SECS) as f64 / STABLE_AFTER_SECS as f64;
+        (battery + links + stability) / 3.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RelayRole {
+    /// Forwards blocks received from peers. Every node is a relay outside
+    /// dense neighbourhoods.
+    #[default]
+    Relay,
+    /// Pushes only its own blocks, and may duty-cycle its radio.
+    Leaf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayConfig {
+    pub enabled: bool,
+    /// Neighbourhoods (the node and its direct neighbours) smaller than this
+    /// are not dense, so every node in them relays.
+    pub dense_threshold: usize,
+    /// Relays elected per dense neighbourhood.
+    pub relays: usize,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dense_threshold: 8,
+            relays: 2,
+        }
+    }
+}
+
+pub struct RelayState {
+    pub config: RelayConfig,
+    pub battery_percent: Option<u8>,
+    started_at: u64,
+    /// Newest metrics advertised by each node, with the announcement time.
+    peers: HashMap<String, (u64, RelayMetrics)>,
+}
+
+impl Default for RelayState {
+    fn default() -> Self {
+        Self {
+            config: RelayConfig::default(),
+            battery_percent: None,
+            started_at: clock::now_secs(),
+            peers: HashMap::new(),
+        }
+    }
+}
+
+impl RelayState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn receive(&mut self, node_id: &str, announced_at: u64, metrics: RelayMetrics) {
+        match self.peers.get(node_id) {
+            Some((at, _)) if *at >= announced_at => {}
+            _ => {
+                self.peers.insert(node_id.to_string(), (announced_at, metrics));
+            }
+        }
+    }
+
+    pub fn metrics_of(&self, node_id: &str) -> Option<RelayMetrics> {
+        self.peers.get(node_id).map(|(_, m)| *m)
+    }
+}
+
+impl EcoBlockContext {
+    pub fn local_relay_metrics(&self) -> RelayMetrics {
+        let local = PeerId::from_mesh(self.keypair.public_key_hex());
+        RelayMetrics {
+            battery_percent: self.relay.battery_percent,
+            links: self.list_peers(&local).len() as u32,
+            uptime: clock::now_secs().saturating_sub(self.relay.started_at),
+        }
+    }
+
+    /// This node's role among its direct neighbours. Neighbours that have not
+    /// advertised metrics take part with an unknown battery, no links and no
+    /// uptime. Ties are broken by node id. Outside `PowerMode::Normal` the node
+    /// is always a leaf.
+    pub fn relay_role(&self) -> RelayRole {
+        if self.power.mode() != PowerMode::Normal {
+            return RelayRole::Leaf;
+        }
+        let config = self.relay.config;
+        let local = self.keypair.public_key_hex();
+        let neighbours = self.list_peers(&PeerId::from_mesh(local.clone()));
+        if !config.enabled || neighbours.len() + 1 < config.dense_threshold {
+            return RelayRole::Relay;
+        }
+        let unknown = RelayMetrics {
+            battery_percent: None,
+            links: 0,
+            uptime: 0,
+        };
+        let own = self.local_relay_metrics().score();
+        let better = neighbours
+            .iter()
+            .filter(|peer| {
+                let score = self.relay.metrics_of(peer.as_str()).unwrap_or(unknown).score();
+                score > own || (score == own && peer.as_str() < local.as_str())
+            })
+            .count();
+        if better < config.relays {
+            RelayRole::Relay
+        } else {
+            RelayRole::Leaf
+        }
+    }
+}
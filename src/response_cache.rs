@@ -0,0 +1,113 @@
+//! Conditional responses for the HTTP endpoints the host serves
+//! (`sensorthings_get`, `gateway_get`). Each answer carries an ETag tied to
+//! the tangle position it was computed at, and answers are kept in a cache
+//! that the next change-feed entry, record or indexed reading makes stale.
+//! A client sending the ETag back in `If-None-Match` gets `304 Not Modified`
+//! with an empty body, so polling dashboards download unchanged data once.
+
+use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use crate::EcoBlockContext;
+
+const MAX_CACHED_RESPONSES: usize = 256;
+
+/// What a response was computed from; any change makes it stale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Stamp {
+    changes: u64,
+    records: usize,
+    readings: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CachedResponse {
+    pub status: u16,
+    pub content_type: String,
+    pub body: String,
+    pub etag: String,
+}
+
+impl CachedResponse {
+    pub(crate) fn new(stamp: Stamp, status: u16, content_type: String, body: String) -> Self {
+        let digest = Sha256::digest(body.as_bytes());
+        Self {
+            etag: format!("\"{}-{}\"", stamp.changes, hex::encode(&digest[..8])),
+            status,
+            content_type,
+            body,
+        }
+    }
+
+    /// The `304` answer for a client that already holds this response.
+    pub(crate) fn not_modified(&self) -> Self {
+        Self {
+            status: 304,
+            body: String::new(),
+            ..self.clone()
+        }
+    }
+}
+
+/// True when the `If-None-Match` header value names `etag`.
+pub(crate) fn matches(if_none_match: Option<&str>, etag: &str) -> bool {
+    let Some(header) = if_none_match else {
+        return false;
+    };
+    header.split(',').map(str::trim).any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+#[derive(Debug, Default)]
+pub struct ResponseCache {
+    entries: HashMap<String, (Stamp, u64, CachedResponse)>,
+}
+
+impl ResponseCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The response cached under `key` at `stamp`, if not older than `max_age`
+    /// seconds.
+    pub(crate) fn get(&self, key: &str, stamp: Stamp, now: u64, max_age: Option<u64>) -> Option<&CachedResponse> {
+        self.entries
+            .get(key)
+            .filter(|(at, computed_at, _)| *at == stamp && max_age.is_none_or(|age| now.saturating_sub(*computed_at) < age))
+            .map(|(_, _, response)| response)
+    }
+
+    /// Keeps successful responses only; errors are cheap to recompute.
+    pub(crate) fn insert(&mut self, key: &str, stamp: Stamp, now: u64, response: &CachedResponse) {
+        if response.status != 200 {
+            return;
+        }
+        if self.entries.len() >= MAX_CACHED_RESPONSES {
+            self.entries.retain(|_, (at, _, _)| *at == stamp);
+            if self.entries.len() >= MAX_CACHED_RESPONSES {
+                self.entries.clear();
+            }
+        }
+        self.entries.insert(key.to_string(), (stamp, now, response.clone()));
+    }
+}
+
+impl EcoBlockContext {
+    pub(crate) fn response_stamp(&self) -> Stamp {
+        Stamp {
+            changes: self.changes.head(),
+            records: self.records.len(),
+            readings: self.readings.len(),
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! What-if planner for retention. `simulate_retention` plays a retention or
+//! roll-up policy forward over the blocks held now and the recent ingest rate,
+//! without removing anything, so operators can compare policies before
+//! enabling destructive pruning. Sizes are encoded block sizes, before any
+//! payload sharing or store file overhead.
+
+use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+use crate::{clock, EcoBlockContext};
+
+const DAY_SECS: u64 = 86_400;
+/// Days of recent blocks the ingest rate is measured over.
+pub const RATE_WINDOW_DAYS: u64 = 7;
+pub const MAX_HORIZON_DAYS: u32 = 3650;
+/// Estimated size of one roll-up record: envelope, signature and aggregates.
+const ROLLUP_RECORD_BYTES: u64 = 1024;
+/// A roll-up record lists each block it replaced by id.
+const ROLLUP_BYTES_PER_BLOCK: u64 = 67;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionAction {
+    /// As `prune_blocks_before`.
+    Prune,
+    /// As `roll_up_blocks_before`: one summary record per sensor and run.
+    RollUp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Age in days past which blocks are removed; `None` keeps everything.
+    pub keep_days: Option<u32>,
+    pub action: RetentionAction,
+    /// Days between runs of the policy, the first run being today.
+    pub run_every_days: u32,
+    /// Days to forecast.
+    pub horizon_days: u32,
+}
+
+impl RetentionPolicy {
+    pub fn validate(&self) -> Result<(), BridgeError> {
+        if self.keep_days == Some(0) || self.run_every_days == 0 {
+            return Err(BridgeError::InvalidInput("Invalid retention policy: keep_days and run_every_days must be at least 1".to_string()));
+        }
+        if self.horizon_days == 0 || self.horizon_days > MAX_HORIZON_DAYS {
+            return Err(BridgeError::InvalidInput(format!("Invalid retention policy: horizon_days must be between 1 and {}", MAX_HORIZON_DAYS)));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPoint {
+    /// Days from now.
+    pub day: u32,
+    pub blocks: u64,
+    /// Roll-up records kept in place of removed blocks.
+    pub rollup_records: u64,
+    pub bytes: u64,
+    /// Bytes the node would hold on this day with no policy at all.
+    pub bytes_without_policy: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetentionForecast {
+    pub policy: RetentionPolicy,
+    pub current_blocks: u64,
+    pub current_bytes: u64,
+    pub avg_block_bytes: u64,
+    /// Blocks per day over the last `RATE_WINDOW_DAYS`.
+    pub ingest_blocks_per_day: f64,
+    /// Sensors that sent blocks over the same window.
+    pub active_sensors: u64,
+    /// Blocks the first run would remove today.
+    pub removed_now: u64,
+    /// One point per day, from today to the horizon.
+    pub points: Vec<RetentionPoint>,
+    pub peak_bytes: u64,
+}
+
+impl EcoBlockContext {
+    pub fn simulate_retention(&self, policy: RetentionPolicy) -> Result<RetentionForecast, BridgeError> {
+        policy.validate()?;
+        let now = clock::now_secs();
+        let mut timestamps = Vec::with_capacity(self.block_log.len());
+        let mut current_bytes = 0u64;
+        let mut sensors = HashSet::new();
+        let window_start = now.saturating_sub(RATE_WINDOW_DAYS * DAY_SECS);
+        let mut recent = 0u64;
+        for block in self.block_log.iter() {
+            let timestamp = block.data.data.timestamp;
+            timestamps.push(timestamp);
+            current_bytes += serde_json::to_vec(block).map(|b| b.len() as u64).unwrap_or_default();
+            if timestamp >= window_start && timestamp <= now {
+                recent += 1;
+                if let Some(reading) = self.readings.get(&block.id) {
+                    sensors.insert(reading.sensor_id.clone());
+                }
+            }
+        }
+        timestamps.sort_unstable();
+        let current_blocks = timestamps.len() as u64;
+        let avg_block_bytes = current_bytes.checked_div(current_blocks).unwrap_or_default();
+        // A node younger than the window is measured over its own age.
+        let measured_secs = timestamps.first().map_or(0, |oldest| now.saturating_sub((*oldest).max(window_start))).max(DAY_SECS);
+        let per_day = recent as f64 * DAY_SECS as f64 / measured_secs as f64;
+        let active_sensors = sensors.len() as u64;
+
+        let held_existing = |cutoff: u64| (timestamps.len() - timestamps.partition_point(|t| *t < cutoff)) as u64;
+        let ingested = |secs: u64| (per_day * secs as f64 / DAY_SECS as f64).round() as u64;
+        let mut points = Vec::with_capacity(policy.horizon_days as usize + 1);
+        let (mut removed_before, mut rollup_records, mut removed_now) = (0u64, 0u64, 0u64);
+        for day in 0..=policy.horizon_days {
+            let at = now + day as u64 * DAY_SECS;
+            let new_total = ingested(at - now);
+            let (blocks, removed) = match policy.keep_days {
+                Some(keep_days) => {
+                    let last_run = now + (day - day % policy.run_every_days) as u64 * DAY_SECS;
+                    let cutoff = last_run.saturating_sub(keep_days as u64 * DAY_SECS);
+                    // Blocks ingested from now on are spread evenly over time.
+                    let new_removed = ingested(cutoff.saturating_sub(now).min(at - now));
+                    let removed = current_blocks - held_existing(cutoff) + new_removed;
+                    (current_blocks + new_total - removed, removed)
+                }
+                None => (current_blocks + new_total, 0),
+            };
+            if day == 0 {
+                removed_now = removed;
+            }
+            // Removals only change on run days, and each run that removes
+            // blocks writes one summary per sensor.
+            if policy.action == RetentionAction::RollUp && removed > removed_before {
+                rollup_records += active_sensors.max(1);
+            }
+            removed_before = removed;
+            let rollup_bytes = match policy.action {
+                RetentionAction::Prune => 0,
+                RetentionAction::RollUp => rollup_records * ROLLUP_RECORD_BYTES + removed * ROLLUP_BYTES_PER_BLOCK,
+            };
+            points.push(RetentionPoint {
+                day,
+                blocks,
+                rollup_records,
+                bytes: blocks * avg_block_bytes + rollup_bytes,
+                bytes_without_policy: (current_blocks + new_total) * avg_block_bytes,
+            });
+        }
+        Ok(RetentionForecast {
+            policy,
+            current_blocks,
+            current_bytes,
+            avg_block_bytes,
+            ingest_blocks_per_day: per_day,
+            active_sensors,
+            removed_now,
+            peak_bytes: points.iter().map(|p| p.bytes).max().unwrap_or_default(),
+            points,
+        })
+    }
+}
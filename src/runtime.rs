@@ -0,0 +1,157 @@
+//! Worker counts for the threads the bridge starts, and a count of the ones
+//! running. Low-end phones can cap the pools or run everything on the
+//! calling thread.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use ecoblock_storage::tangle::block::TangleBlock;
+use serde::{Deserialize, Serialize};
+use crate::energy;
+use crate::error::BridgeError;
+use crate::s3_backup::S3BackupConfig;
+
+/// Blocks below this count are verified on the calling thread.
+const MIN_PARALLEL_BLOCKS: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BridgeConfig {
+    /// Threads verifying signatures of bulk block imports.
+    pub verification_workers: usize,
+    /// Concurrent tasks a transport may run.
+    pub transport_tasks: usize,
+    /// Threads running scheduled background jobs.
+    pub scheduler_workers: usize,
+    /// Start no threads at all. Background work then runs only when the host
+    /// calls `run_background_step`.
+    pub single_threaded: bool,
+    /// Where scheduled encrypted backups are uploaded; `None` disables them.
+    #[serde(default)]
+    pub backup: Option<S3BackupConfig>,
+}
+
+impl Default for BridgeConfig {
+    fn default() -> Self {
+        Self {
+            verification_workers: std::thread::available_parallelism().map(|n| n.get().min(4)).unwrap_or(1),
+            transport_tasks: 2,
+            scheduler_workers: 1,
+            single_threaded: false,
+            backup: None,
+        }
+    }
+}
+
+impl BridgeConfig {
+    pub fn validate(&self) -> Result<(), BridgeError> {
+        if self.verification_workers == 0 || self.transport_tasks == 0 || self.scheduler_workers == 0 {
+            return Err(BridgeError::InvalidInput("Invalid bridge config: worker counts must be at least 1".to_string()));
+        }
+        match &self.backup {
+            Some(backup) => backup.validate(),
+            None => Ok(()),
+        }
+    }
+
+    /// Threads the verification pool may use, 1 meaning the calling thread.
+    pub fn verification_threads(&self) -> usize {
+        if self.single_threaded {
+            1
+        } else {
+            self.verification_workers
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ThreadRole {
+    Verification,
+    IndexBuilder,
+    Transport,
+    Scheduler,
+    Prefetch,
+    Ipc,
+}
+
+impl ThreadRole {
+    const ALL: [ThreadRole; 6] = [
+        ThreadRole::Verification,
+        ThreadRole::IndexBuilder,
+        ThreadRole::Transport,
+        ThreadRole::Scheduler,
+        ThreadRole::Prefetch,
+        ThreadRole::Ipc,
+    ];
+
+    fn slot(self) -> usize {
+        self as usize
+    }
+}
+
+static RUNNING: [AtomicUsize; 6] = [const { AtomicUsize::new(0) }; 6];
+
+/// Counts a bridge thread as running for as long as it is alive.
+pub struct ThreadGuard(ThreadRole);
+
+impl ThreadGuard {
+    pub fn enter(role: ThreadRole) -> Self {
+        RUNNING[role.slot()].fetch_add(1, Ordering::Relaxed);
+        Self(role)
+    }
+}
+
+impl Drop for ThreadGuard {
+    fn drop(&mut self) {
+        RUNNING[self.0.slot()].fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThreadUsage {
+    pub single_threaded: bool,
+    pub verification: usize,
+    pub index_builder: usize,
+    pub transport: usize,
+    pub scheduler: usize,
+    pub prefetch: usize,
+    /// Threads serving other processes over IPC.
+    #[serde(default)]
+    pub ipc: usize,
+    /// Bridge threads running now, excluding host threads calling in.
+    pub total: usize,
+}
+
+pub fn thread_usage(config: &BridgeConfig) -> ThreadUsage {
+    let running = |role: ThreadRole| RUNNING[role.slot()].load(Ordering::Relaxed);
+    ThreadUsage {
+        single_threaded: config.single_threaded,
+        verification: running(ThreadRole::Verification),
+        index_builder: running(ThreadRole::IndexBuilder),
+        transport: running(ThreadRole::Transport),
+        scheduler: running(ThreadRole::Scheduler),
+        prefetch: running(ThreadRole::Prefetch),
+        ipc: running(ThreadRole::Ipc),
+        total: ThreadRole::ALL.iter().map(|r| running(*r)).sum(),
+    }
+}
+
+/// The first block whose signature does not verify, checked on up to
+/// `workers` threads.
+pub fn find_invalid(blocks: &[TangleBlock], workers: usize) -> Option<&TangleBlock> {
+    if workers <= 1 || blocks.len() < MIN_PARALLEL_BLOCKS {
+        return blocks.iter().find(|b| !energy::verify_block(b));
+    }
+    let chunk = blocks.len().div_ceil(workers);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = blocks
+            .chunks(chunk)
+            .map(|part| {
+                scope.spawn(move || {
+                    let _running = ThreadGuard::enter(ThreadRole::Verification);
+                    part.iter().find(|b| !energy::verify_block(b))
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .find_map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+    })
+}
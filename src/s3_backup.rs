@@ -0,0 +1,388 @@
+//! Encrypted backups to an S3-compatible object store, so a gateway's tangle
+//! survives the loss of its hardware. `run_background_step` uploads a backup
+//! every `interval_secs`: a full snapshot, then `deltas_per_full` deltas each
+//! holding every block added since that snapshot, so any backup restores from
+//! at most two objects. Objects are sealed with ChaCha20-Poly1305 under a key
+//! from the config, and requests are signed with AWS Signature Version 4 and
+//! sent through the host's `HttpClient`.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::delta::{checkpoint_id, Checkpoint};
+use crate::error::BridgeError;
+use crate::http::{self, HttpRequest, HttpResponse};
+use crate::logs::LogLevel;
+use crate::snapshot::{Snapshot, SnapshotImport, SNAPSHOT_FORMAT, SNAPSHOT_VERSION};
+use crate::{clock, EcoBlockContext};
+
+const S3_BACKUPS_FILE: &str = "s3_backups.json";
+/// Starts every backup object, before the nonce and ciphertext.
+const OBJECT_MAGIC: &[u8] = b"EBK1";
+const NONCE_LEN: usize = 12;
+
+/// Where the node at `path` keeps the list of its uploaded backups.
+pub fn s3_backups_path(path: &Path) -> PathBuf {
+    path.join(S3_BACKUPS_FILE)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct S3BackupConfig {
+    /// Service URL such as `https://s3.eu-west-1.amazonaws.com` or a MinIO
+    /// address. Objects are addressed path-style under it.
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    /// Put before every object key, e.g. `gateways/`.
+    #[serde(default)]
+    pub prefix: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// 64 hex characters. Keep a copy away from the gateway: backups cannot be
+    /// restored without it.
+    pub encryption_key: String,
+    pub interval_secs: u64,
+    /// Backups kept; older ones are deleted once no kept delta needs them.
+    pub retain: usize,
+    /// Deltas uploaded between two full snapshots.
+    pub deltas_per_full: u32,
+}
+
+impl S3BackupConfig {
+    pub fn validate(&self) -> Result<(), BridgeError> {
+        http::split_url(&self.endpoint)?;
+        if self.region.is_empty() || self.bucket.is_empty() || self.access_key_id.is_empty() || self.secret_access_key.is_empty() {
+            return Err(BridgeError::InvalidInput("Invalid backup config: region, bucket and credentials must not be empty".to_string()));
+        }
+        if self.interval_secs == 0 || self.retain == 0 {
+            return Err(BridgeError::InvalidInput("Invalid backup config: interval_secs and retain must be positive".to_string()));
+        }
+        self.cipher().map(|_| ())
+    }
+
+    fn cipher(&self) -> Result<ChaCha20Poly1305, BridgeError> {
+        let key = hex::decode(&self.encryption_key).map_err(|_| BridgeError::InvalidInput("Invalid backup encryption key: expected hex".to_string()))?;
+        if key.len() != 32 {
+            return Err(BridgeError::InvalidInput(format!("Invalid backup encryption key: expected 32 bytes, got {}", key.len())));
+        }
+        Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupKind {
+    Full,
+    /// Blocks added since the full backup named in `base`.
+    Delta,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct S3Backup {
+    pub key: String,
+    pub kind: BackupKind,
+    pub base: Option<String>,
+    pub blocks: usize,
+    /// Size of the encrypted object.
+    pub bytes: usize,
+    pub created_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct S3BackupStatus {
+    /// Oldest first.
+    pub backups: Vec<S3Backup>,
+    pub last_attempt: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+/// What an object holds once decrypted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BackupObject {
+    kind: BackupKind,
+    base: Option<String>,
+    snapshot: Snapshot,
+}
+
+#[derive(Debug, Default)]
+pub struct S3Backups {
+    backups: Vec<S3Backup>,
+    /// The full backup this process uploaded last and the block log position
+    /// it covered. After a restart the next backup is a full one.
+    full: Option<(String, usize)>,
+    deltas_since_full: u32,
+    last_attempt: Option<u64>,
+    last_error: Option<String>,
+}
+
+impl S3Backups {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn status(&self) -> S3BackupStatus {
+        S3BackupStatus {
+            backups: self.backups.clone(),
+            last_attempt: self.last_attempt,
+            last_error: self.last_error.clone(),
+        }
+    }
+
+    pub fn load(&mut self, path: &Path) -> Result<(), BridgeError> {
+        if !path.exists() {
+            return Ok(());
+        }
+        let bytes = fs::read(path).map_err(|e| BridgeError::Io(e.to_string()))?;
+        self.backups = serde_json::from_slice(&bytes).map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+        Ok(())
+    }
+
+    fn save(&self, path: &Path) -> Result<(), BridgeError> {
+        let json = serde_json::to_vec_pretty(&self.backups).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        fs::write(path, json).map_err(|e| BridgeError::Io(e.to_string()))
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; 64];
+    if key.len() > block.len() {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let inner = Sha256::new().chain_update(block.map(|b| b ^ 0x36)).chain_update(data).finalize();
+    Sha256::new().chain_update(block.map(|b| b ^ 0x5c)).chain_update(inner).finalize().into()
+}
+
+/// Percent-encodes an object path the way Signature Version 4 expects.
+fn uri_encode(path: &str) -> String {
+    path.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Builds a request for `key`, signed with Signature Version 4.
+fn signed_request(config: &S3BackupConfig, method: &str, key: &str, body: Vec<u8>, now: u64) -> Result<HttpRequest, BridgeError> {
+    let (host, base_path) = http::split_url(&config.endpoint)?;
+    let scheme = &config.endpoint[..config.endpoint.find("://").unwrap_or_default()];
+    let path = uri_encode(&format!("{}/{}/{}", base_path, config.bucket, key));
+    let amz_date = clock::iso8601(now).replace(['-', ':'], "");
+    let date = &amz_date[..8];
+    let payload_hash = hex::encode(Sha256::digest(&body));
+    let canonical = format!(
+        "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\nhost;x-amz-content-sha256;x-amz-date\n{}",
+        method, path, host, payload_hash, amz_date, payload_hash
+    );
+    let scope = format!("{}/{}/s3/aws4_request", date, config.region);
+    let string_to_sign = format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", amz_date, scope, hex::encode(Sha256::digest(canonical.as_bytes())));
+    let mut signing_key = hmac_sha256(format!("AWS4{}", config.secret_access_key).as_bytes(), date.as_bytes());
+    for part in [config.region.as_str(), "s3", "aws4_request"] {
+        signing_key = hmac_sha256(&signing_key, part.as_bytes());
+    }
+    let signature = hex::encode(hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+    Ok(HttpRequest {
+        method: method.to_string(),
+        url: format!("{}://{}{}", scheme, host, path),
+        headers: vec![
+            ("host".to_string(), host.to_string()),
+            ("x-amz-content-sha256".to_string(), payload_hash),
+            ("x-amz-date".to_string(), amz_date.clone()),
+            (
+                "authorization".to_string(),
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={}",
+                    config.access_key_id, scope, signature
+                ),
+            ),
+        ],
+        body,
+    })
+}
+
+fn seal(config: &S3BackupConfig, plaintext: &[u8]) -> Result<Vec<u8>, BridgeError> {
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = config.cipher()?.encrypt(&nonce, plaintext).map_err(|e| BridgeError::Crypto(format!("{:?}", e)))?;
+    Ok([OBJECT_MAGIC, nonce.as_slice(), &ciphertext].concat())
+}
+
+fn open(config: &S3BackupConfig, object: &[u8]) -> Result<Vec<u8>, BridgeError> {
+    let sealed = object
+        .strip_prefix(OBJECT_MAGIC)
+        .filter(|rest| rest.len() > NONCE_LEN)
+        .ok_or_else(|| BridgeError::InvalidInput("Invalid backup object: not an EcoBlock backup".to_string()))?;
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    config
+        .cipher()?
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| BridgeError::Crypto("backup object does not open with the configured key".to_string()))
+}
+
+impl EcoBlockContext {
+    fn backup_config(&self) -> Result<S3BackupConfig, BridgeError> {
+        self.config.backup.clone().ok_or_else(|| BridgeError::InvalidInput("Invalid backup: no S3 target in BridgeConfig".to_string()))
+    }
+
+    fn s3_call(&self, config: &S3BackupConfig, method: &str, key: &str, body: Vec<u8>) -> Result<HttpResponse, BridgeError> {
+        let client = self.http_client.as_ref().ok_or_else(|| BridgeError::NotFound("Unknown HTTP client: none is set".to_string()))?;
+        let request = signed_request(config, method, key, body, clock::now_secs())?;
+        let response = client.execute(&request)?;
+        match response.status {
+            404 => Err(BridgeError::NotFound(format!("Unknown backup object {}", key))),
+            _ if response.is_success() => Ok(response),
+            status => Err(BridgeError::Io(format!("S3 {} {} answered {}", method, key, status))),
+        }
+    }
+
+    fn persist_s3_backups(&self) -> Result<(), BridgeError> {
+        match &self.node_path {
+            Some(path) => self.s3_backups.save(&s3_backups_path(path)),
+            None => Ok(()),
+        }
+    }
+
+    /// Uploads a backup now: a full snapshot when due, a delta otherwise.
+    pub fn backup_to_s3(&mut self) -> Result<S3Backup, BridgeError> {
+        let config = self.backup_config()?;
+        let now = clock::now_secs();
+        let node_id = self.keypair.public_key_hex();
+        let base = self.s3_backups.full.clone().filter(|_| self.s3_backups.deltas_since_full < config.deltas_per_full);
+        let (kind, checkpoint, blocks) = match &base {
+            None => {
+                let checkpoint = self.create_checkpoint();
+                (BackupKind::Full, checkpoint, self.block_log.iter().cloned().collect::<Vec<_>>())
+            }
+            Some((_, position)) => {
+                let blocks: Vec<_> = self.block_log.since(*position).cloned().collect();
+                let checkpoint = Checkpoint {
+                    id: checkpoint_id(blocks.iter().map(|b| b.id.as_str())),
+                    block_count: self.block_log.end(),
+                    created_at: now,
+                };
+                (BackupKind::Delta, checkpoint, blocks)
+            }
+        };
+        let block_count = checkpoint.block_count;
+        let object = BackupObject {
+            kind,
+            base: base.as_ref().map(|(key, _)| key.clone()),
+            snapshot: Snapshot {
+                format: SNAPSHOT_FORMAT.to_string(),
+                version: SNAPSHOT_VERSION,
+                node_id: node_id.clone(),
+                checkpoint,
+                blocks,
+            },
+        };
+        let json = serde_json::to_vec(&object).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        let sealed = seal(&config, &json)?;
+        let suffix = if kind == BackupKind::Full { "full" } else { "delta" };
+        let key = format!("{}{}/{}-{}.ebk", config.prefix, node_id, now, suffix);
+        let backup = S3Backup {
+            key: key.clone(),
+            kind,
+            base: object.base,
+            blocks: object.snapshot.blocks.len(),
+            bytes: sealed.len(),
+            created_at: now,
+        };
+        self.s3_call(&config, "PUT", &key, sealed)?;
+        self.s3_call(&config, "PUT", &format!("{}{}/latest", config.prefix, node_id), key.clone().into_bytes())?;
+        match kind {
+            BackupKind::Full => {
+                self.s3_backups.full = Some((key.clone(), block_count));
+                self.s3_backups.deltas_since_full = 0;
+            }
+            BackupKind::Delta => self.s3_backups.deltas_since_full += 1,
+        }
+        self.s3_backups.backups.push(backup.clone());
+        self.log(LogLevel::Info, format!("Uploaded {} backup {} ({} blocks)", suffix, key, backup.blocks));
+        self.prune_s3_backups(&config);
+        self.persist_s3_backups()?;
+        Ok(backup)
+    }
+
+    /// Deletes backups beyond `retain`, except full ones a kept or future
+    /// delta is based on.
+    fn prune_s3_backups(&mut self, config: &S3BackupConfig) {
+        let excess = self.s3_backups.backups.len().saturating_sub(config.retain);
+        let mut needed: HashSet<String> = self.s3_backups.backups[excess..].iter().filter_map(|b| b.base.clone()).collect();
+        needed.extend(self.s3_backups.full.as_ref().map(|(key, _)| key.clone()));
+        let old: Vec<S3Backup> = self.s3_backups.backups.drain(..excess).collect();
+        let mut kept = Vec::new();
+        for backup in old {
+            if needed.contains(&backup.key) {
+                kept.push(backup);
+                continue;
+            }
+            if let Err(e) = self.s3_call(config, "DELETE", &backup.key, Vec::new()) {
+                self.log(LogLevel::Warn, format!("Could not delete old backup {}: {}", backup.key, e));
+                kept.push(backup);
+            }
+        }
+        kept.append(&mut self.s3_backups.backups);
+        self.s3_backups.backups = kept;
+    }
+
+    fn fetch_backup(&self, config: &S3BackupConfig, key: &str) -> Result<BackupObject, BridgeError> {
+        let response = self.s3_call(config, "GET", key, Vec::new())?;
+        let json = open(config, &response.body)?;
+        serde_json::from_slice(&json).map_err(|e| BridgeError::Deserialization(e.to_string()))
+    }
+
+    /// Downloads the backup at `key` and merges its blocks, after those of its
+    /// full backup for a delta. `key` may also be `<prefix><node_id>/latest`.
+    pub fn restore_from_s3(&mut self, key: &str) -> Result<SnapshotImport, BridgeError> {
+        let config = self.backup_config()?;
+        let key = if key.ends_with("/latest") {
+            let pointer = self.s3_call(&config, "GET", key, Vec::new())?;
+            String::from_utf8(pointer.body).map_err(|_| BridgeError::InvalidInput(format!("Invalid backup pointer {}", key)))?
+        } else {
+            key.to_string()
+        };
+        let object = self.fetch_backup(&config, &key)?;
+        let base = match (&object.kind, &object.base) {
+            (BackupKind::Delta, Some(base)) => {
+                let full = self.fetch_backup(&config, base)?;
+                if full.kind != BackupKind::Full {
+                    return Err(BridgeError::InvalidInput(format!("Invalid backup {}: base {} is not a full backup", key, base)));
+                }
+                Some(self.import_snapshot_data(full.snapshot)?)
+            }
+            (BackupKind::Delta, None) => return Err(BridgeError::InvalidInput(format!("Invalid backup {}: delta without a base", key))),
+            (BackupKind::Full, _) => None,
+        };
+        let mut import = self.import_snapshot_data(object.snapshot)?;
+        if let Some(base) = base {
+            import.imported += base.imported;
+            import.already_present += base.already_present;
+        }
+        self.log(LogLevel::Info, format!("Restored {} blocks from backup {}", import.imported, key));
+        Ok(import)
+    }
+
+    /// Uploads a backup when one is due and both a target and a client are set.
+    pub(crate) fn run_scheduled_backup(&mut self, now: u64) {
+        let Some(config) = &self.config.backup else {
+            return;
+        };
+        let due = self.s3_backups.last_attempt.is_none_or(|at| now.saturating_sub(at) >= config.interval_secs);
+        if !due || self.http_client.is_none() {
+            return;
+        }
+        self.s3_backups.last_attempt = Some(now);
+        match self.backup_to_s3() {
+            Ok(_) => self.s3_backups.last_error = None,
+            Err(e) => {
+                self.log(LogLevel::Warn, format!("Scheduled backup failed: {}", e));
+                self.s3_backups.last_error = Some(e.to_string());
+            }
+        }
+    }
+}
@@ -0,0 +1,309 @@
+//! Named queries. A host registers a query once (metric, filter, grouping,
+//! aggregation, ordering) and runs it by name with parameters. Definitions are
+//! kept in the node directory, and results are cached until new readings or
+//! changes arrive. Queries whose parameters all have defaults are computed
+//! ahead of time by `run_background_step`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::aggregate::MetricAggregate;
+use crate::error::BridgeError;
+use crate::{clock, EcoBlockContext};
+
+const SAVED_QUERIES_FILE: &str = "saved_queries.json";
+/// Cached results of queries over a window ending now are recomputed after
+/// this many seconds even without new data.
+pub const MAX_CACHE_AGE: u64 = 30;
+pub const MAX_SAVED_QUERIES: usize = 256;
+
+/// Where the node at `path` keeps its named queries.
+pub fn saved_queries_path(path: &Path) -> PathBuf {
+    path.join(SAVED_QUERIES_FILE)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    /// One row over every matching reading.
+    #[default]
+    All,
+    Sensor,
+    /// Buckets of this many seconds, keyed by their start.
+    Interval(u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Aggregation {
+    Mean,
+    Min,
+    Max,
+    Sum,
+    Count,
+    /// The newest value in the group.
+    Last,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryOrder {
+    #[default]
+    KeyAscending,
+    KeyDescending,
+    ValueAscending,
+    ValueDescending,
+}
+
+/// String fields may contain `$name` placeholders, filled from the parameters
+/// given to `run_saved_query` or else from `defaults`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedQuery {
+    pub name: String,
+    pub metric: String,
+    #[serde(default)]
+    pub sensor_id: Option<String>,
+    /// Readings from the last this many seconds; the `from` and `to`
+    /// parameters set an absolute range instead. Everything when neither is given.
+    #[serde(default)]
+    pub window_secs: Option<u64>,
+    #[serde(default)]
+    pub group_by: GroupBy,
+    pub aggregation: Aggregation,
+    #[serde(default)]
+    pub order: QueryOrder,
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub defaults: BTreeMap<String, String>,
+}
+
+impl SavedQuery {
+    pub fn validate(&self) -> Result<(), BridgeError> {
+        if self.name.is_empty() || self.metric.is_empty() {
+            return Err(BridgeError::InvalidInput("Invalid saved query: name and metric must not be empty".to_string()));
+        }
+        if self.group_by == GroupBy::Interval(0) || self.window_secs == Some(0) {
+            return Err(BridgeError::InvalidInput(format!("Invalid saved query {}: intervals and windows must be positive", self.name)));
+        }
+        Ok(())
+    }
+
+    /// True when the query runs without any parameter from the caller.
+    fn self_contained(&self) -> bool {
+        self.fill(&BTreeMap::new()).is_ok()
+    }
+
+    fn fill(&self, params: &BTreeMap<String, String>) -> Result<(String, Option<String>), BridgeError> {
+        let lookup = |text: &str| -> Result<String, BridgeError> {
+            let Some(name) = text.strip_prefix('$') else {
+                return Ok(text.to_string());
+            };
+            params
+                .get(name)
+                .or_else(|| self.defaults.get(name))
+                .cloned()
+                .ok_or_else(|| BridgeError::InvalidInput(format!("Invalid parameters for query {}: missing ${}", self.name, name)))
+        };
+        let metric = lookup(&self.metric)?;
+        let sensor_id = self.sensor_id.as_deref().map(lookup).transpose()?;
+        Ok((metric, sensor_id))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryRow {
+    /// The sensor id, the bucket start, or `all`.
+    pub key: String,
+    pub value: f64,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedQueryResult {
+    pub name: String,
+    pub rows: Vec<QueryRow>,
+    pub computed_at: u64,
+    /// Served from the cache rather than computed for this call.
+    pub cached: bool,
+}
+
+/// What a cached result was computed from; any change makes it stale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    changes: u64,
+    readings: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct SavedQueries {
+    queries: BTreeMap<String, SavedQuery>,
+    cache: HashMap<(String, BTreeMap<String, String>), (Stamp, SavedQueryResult)>,
+}
+
+impl SavedQueries {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SavedQuery> {
+        self.queries.get(name)
+    }
+
+    pub fn list(&self) -> Vec<SavedQuery> {
+        self.queries.values().cloned().collect()
+    }
+
+    pub fn load(&mut self, path: &Path) -> Result<(), BridgeError> {
+        if !path.exists() {
+            return Ok(());
+        }
+        let bytes = fs::read(path).map_err(|e| BridgeError::Io(e.to_string()))?;
+        let queries: Vec<SavedQuery> = serde_json::from_slice(&bytes).map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+        self.queries = queries.into_iter().map(|q| (q.name.clone(), q)).collect();
+        self.cache.clear();
+        Ok(())
+    }
+
+    fn save(&self, path: &Path) -> Result<(), BridgeError> {
+        let json = serde_json::to_vec_pretty(&self.list()).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        fs::write(path, json).map_err(|e| BridgeError::Io(e.to_string()))
+    }
+}
+
+fn aggregate(aggregation: Aggregation, values: &[(u64, f64)]) -> f64 {
+    let stats = || MetricAggregate::from_values("", values.iter().map(|(_, v)| *v));
+    match aggregation {
+        Aggregation::Mean => stats().mean,
+        Aggregation::Min => stats().min,
+        Aggregation::Max => stats().max,
+        Aggregation::Sum => stats().sum,
+        Aggregation::Count => values.len() as f64,
+        Aggregation::Last => values.iter().max_by_key(|(t, _)| *t).map(|(_, v)| *v).unwrap_or_default(),
+    }
+}
+
+impl EcoBlockContext {
+    fn saved_query_stamp(&self) -> Stamp {
+        Stamp {
+            changes: self.changes.head(),
+            readings: self.readings.len(),
+        }
+    }
+
+    fn persist_saved_queries(&self) -> Result<(), BridgeError> {
+        match &self.node_path {
+            Some(path) => self.saved_queries.save(&saved_queries_path(path)),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds or replaces the query named `query.name`.
+    pub fn register_saved_query(&mut self, query: SavedQuery) -> Result<(), BridgeError> {
+        query.validate()?;
+        if !self.saved_queries.queries.contains_key(&query.name) && self.saved_queries.queries.len() >= MAX_SAVED_QUERIES {
+            return Err(BridgeError::InvalidInput(format!("Invalid saved query {}: at most {} queries", query.name, MAX_SAVED_QUERIES)));
+        }
+        let name = query.name.clone();
+        self.saved_queries.cache.retain(|(n, _), _| *n != name);
+        self.saved_queries.queries.insert(name, query);
+        self.persist_saved_queries()
+    }
+
+    pub fn remove_saved_query(&mut self, name: &str) -> Result<bool, BridgeError> {
+        self.saved_queries.cache.retain(|(n, _), _| n != name);
+        let removed = self.saved_queries.queries.remove(name).is_some();
+        if removed {
+            self.persist_saved_queries()?;
+        }
+        Ok(removed)
+    }
+
+    fn compute_saved_query(&self, query: &SavedQuery, params: &BTreeMap<String, String>, now: u64) -> Result<Vec<QueryRow>, BridgeError> {
+        let (metric, sensor_id) = query.fill(params)?;
+        let number = |name: &str| -> Result<Option<u64>, BridgeError> {
+            params
+                .get(name)
+                .map(|v| v.parse().map_err(|_| BridgeError::InvalidInput(format!("Invalid parameter {}={:?}: expected seconds", name, v))))
+                .transpose()
+        };
+        let from = number("from")?.or_else(|| query.window_secs.map(|w| now.saturating_sub(w))).unwrap_or(0);
+        let to = number("to")?.unwrap_or(u64::MAX);
+        // Buckets sort by start time, sensors by id.
+        let mut groups: BTreeMap<(u64, String), Vec<(u64, f64)>> = BTreeMap::new();
+        for reading in self.readings.in_range(from, to) {
+            if sensor_id.as_ref().is_some_and(|s| *s != reading.sensor_id) {
+                continue;
+            }
+            let Some(value) = self.derived.value_of(reading, &metric) else {
+                continue;
+            };
+            let key = match query.group_by {
+                GroupBy::All => (0, "all".to_string()),
+                GroupBy::Sensor => (0, reading.sensor_id.clone()),
+                GroupBy::Interval(secs) => (reading.timestamp - reading.timestamp % secs, String::new()),
+            };
+            groups.entry(key).or_default().push((reading.timestamp, value));
+        }
+        let interval = matches!(query.group_by, GroupBy::Interval(_));
+        let mut rows: Vec<QueryRow> = groups
+            .into_iter()
+            .map(|((start, key), values)| QueryRow {
+                key: if interval { start.to_string() } else { key },
+                value: aggregate(query.aggregation, &values),
+                count: values.len(),
+            })
+            .collect();
+        match query.order {
+            QueryOrder::KeyAscending => {}
+            QueryOrder::KeyDescending => rows.reverse(),
+            QueryOrder::ValueAscending => rows.sort_by(|a, b| a.value.total_cmp(&b.value)),
+            QueryOrder::ValueDescending => rows.sort_by(|a, b| b.value.total_cmp(&a.value)),
+        }
+        if let Some(limit) = query.limit {
+            rows.truncate(limit);
+        }
+        Ok(rows)
+    }
+
+    /// Runs the query named `name`, answering from the cache when nothing has
+    /// changed since it was last computed with the same parameters.
+    pub fn run_saved_query(&mut self, name: &str, params: BTreeMap<String, String>) -> Result<SavedQueryResult, BridgeError> {
+        let query = self.saved_queries.get(name).cloned().ok_or_else(|| BridgeError::NotFound(format!("Unknown saved query {}", name)))?;
+        let (stamp, now) = (self.saved_query_stamp(), clock::now_secs());
+        let key = (name.to_string(), params);
+        if let Some((at, result)) = self.saved_queries.cache.get(&key) {
+            let windowed = query.window_secs.is_some() && !key.1.contains_key("from");
+            if *at == stamp && !(windowed && now.saturating_sub(result.computed_at) > MAX_CACHE_AGE) {
+                let mut result = result.clone();
+                result.cached = true;
+                return Ok(result);
+            }
+        }
+        let rows = self.compute_saved_query(&query, &key.1, now)?;
+        let result = SavedQueryResult {
+            name: name.to_string(),
+            rows,
+            computed_at: now,
+            cached: false,
+        };
+        self.saved_queries.cache.insert(key, (stamp, result.clone()));
+        Ok(result)
+    }
+
+    /// Computes the queries that need no caller parameters, so the first
+    /// `run_saved_query` is served from the cache.
+    pub fn warm_saved_queries(&mut self) {
+        let names: Vec<String> = self
+            .saved_queries
+            .queries
+            .values()
+            .filter(|q| q.self_contained())
+            .map(|q| q.name.clone())
+            .collect();
+        for name in names {
+            let _ = self.run_saved_query(&name, BTreeMap::new());
+        }
+    }
+}
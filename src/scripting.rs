@@ -0,0 +1,330 @@
+//! User scripts run on bridge events, so deployments can transform data, raise
+//! alerts or emit derived blocks without an app release. Scripts are written in
+//! Rhai and define `fn on_event(event)`. They run sandboxed: no file, network
+//! or host access, and bounded operations, call depth and value sizes. Instead
+//! of side effects a script returns its actions, a map or an array of maps
+//! such as `#{ action: "alert", message: "pump dry" }`, which the bridge
+//! carries out. Installed scripts are kept in the node directory.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use rhai::{Dynamic, Engine, Scope, AST};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::block_id::BlockId;
+use crate::error::BridgeError;
+use crate::logs::LogLevel;
+use crate::thresholds::ThresholdEvent;
+use crate::{clock, EcoBlockContext};
+
+const SCRIPTS_FILE: &str = "scripts.json";
+pub const SCRIPT_ALERT_KIND: &str = "script_alert";
+pub const MAX_SCRIPTS: usize = 32;
+pub const MAX_SOURCE_BYTES: usize = 64 * 1024;
+/// Rhai operations one run may take before it is stopped.
+pub const MAX_OPERATIONS: u64 = 100_000;
+/// Actions one run may return; the rest are dropped.
+pub const MAX_ACTIONS: usize = 16;
+/// Failed runs in a row after which a script is disabled until reinstalled.
+pub const MAX_CONSECUTIVE_ERRORS: u32 = 5;
+const ENTRY_POINT: &str = "on_event";
+
+pub fn scripts_path(path: &Path) -> PathBuf {
+    path.join(SCRIPTS_FILE)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptTrigger {
+    /// A block was stored, created locally or received.
+    Block,
+    /// A threshold was crossed or cleared.
+    Threshold,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScriptDefinition {
+    pub name: String,
+    pub trigger: ScriptTrigger,
+    /// Rhai source defining `fn on_event(event)`.
+    pub source: String,
+}
+
+/// What a script's `on_event` receives, as a Rhai map.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "trigger", rename_all = "snake_case")]
+pub enum ScriptEvent {
+    Block {
+        block_id: String,
+        signer: String,
+        sensor_id: String,
+        timestamp: u64,
+        values: BTreeMap<String, f64>,
+    },
+    Threshold(ThresholdEvent),
+}
+
+impl ScriptEvent {
+    fn trigger(&self) -> ScriptTrigger {
+        match self {
+            ScriptEvent::Block { .. } => ScriptTrigger::Block,
+            ScriptEvent::Threshold(_) => ScriptTrigger::Threshold,
+        }
+    }
+
+    /// The block the event is about; derived blocks name it as their parent.
+    fn block_id(&self) -> &str {
+        match self {
+            ScriptEvent::Block { block_id, .. } => block_id,
+            ScriptEvent::Threshold(event) => event.block_id.as_str(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ScriptAction {
+    /// Signs and gossips a `script_alert` record.
+    Alert { message: String },
+    /// Creates a block from sensor data, with the event's block as parent.
+    /// Blocks made by scripts do not trigger scripts.
+    Block { data: Value },
+    Log { message: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScriptStatus {
+    pub name: String,
+    pub trigger: ScriptTrigger,
+    pub enabled: bool,
+    pub runs: u64,
+    pub errors: u64,
+    pub actions: u64,
+    pub last_error: Option<String>,
+}
+
+struct InstalledScript {
+    definition: ScriptDefinition,
+    ast: AST,
+    status: ScriptStatus,
+    consecutive_errors: u32,
+}
+
+pub struct Scripts {
+    engine: Engine,
+    scripts: BTreeMap<String, InstalledScript>,
+    /// Set while actions are carried out, so they cannot trigger scripts.
+    running: bool,
+}
+
+impl Default for Scripts {
+    fn default() -> Self {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.set_max_call_levels(16);
+        engine.set_max_expr_depths(64, 32);
+        engine.set_max_string_size(16 * 1024);
+        engine.set_max_array_size(1024);
+        engine.set_max_map_size(1024);
+        engine.on_print(|_| {});
+        engine.on_debug(|_, _, _| {});
+        Self {
+            engine,
+            scripts: BTreeMap::new(),
+            running: false,
+        }
+    }
+}
+
+impl Scripts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn compile(&self, definition: ScriptDefinition) -> Result<InstalledScript, BridgeError> {
+        if definition.name.is_empty() || definition.source.len() > MAX_SOURCE_BYTES {
+            return Err(BridgeError::InvalidInput(format!("Invalid script {:?}: needs a name and at most {} bytes of source", definition.name, MAX_SOURCE_BYTES)));
+        }
+        let ast = self
+            .engine
+            .compile(&definition.source)
+            .map_err(|e| BridgeError::InvalidInput(format!("Invalid script {}: {}", definition.name, e)))?;
+        if !ast.iter_functions().any(|f| f.name == ENTRY_POINT && f.params.len() == 1) {
+            return Err(BridgeError::InvalidInput(format!("Invalid script {}: must define fn {}(event)", definition.name, ENTRY_POINT)));
+        }
+        Ok(InstalledScript {
+            status: ScriptStatus {
+                name: definition.name.clone(),
+                trigger: definition.trigger,
+                enabled: true,
+                runs: 0,
+                errors: 0,
+                actions: 0,
+                last_error: None,
+            },
+            definition,
+            ast,
+            consecutive_errors: 0,
+        })
+    }
+
+    pub fn list(&self) -> Vec<ScriptStatus> {
+        self.scripts.values().map(|s| s.status.clone()).collect()
+    }
+
+    pub fn definitions(&self) -> Vec<ScriptDefinition> {
+        self.scripts.values().map(|s| s.definition.clone()).collect()
+    }
+
+    pub fn load(&mut self, path: &Path) -> Result<(), BridgeError> {
+        if !path.exists() {
+            return Ok(());
+        }
+        let bytes = fs::read(path).map_err(|e| BridgeError::Io(e.to_string()))?;
+        let definitions: Vec<ScriptDefinition> = serde_json::from_slice(&bytes).map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+        let mut scripts = BTreeMap::new();
+        for definition in definitions {
+            scripts.insert(definition.name.clone(), self.compile(definition)?);
+        }
+        self.scripts = scripts;
+        Ok(())
+    }
+
+    fn save(&self, path: &Path) -> Result<(), BridgeError> {
+        let json = serde_json::to_vec_pretty(&self.definitions()).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        fs::write(path, json).map_err(|e| BridgeError::Io(e.to_string()))
+    }
+
+    /// Runs the enabled scripts for `event` and returns their actions and the
+    /// failures to log.
+    fn run(&mut self, event: &ScriptEvent) -> (Vec<(String, ScriptAction)>, Vec<String>) {
+        let (mut actions, mut failures) = (Vec::new(), Vec::new());
+        let Ok(argument) = rhai::serde::to_dynamic(event) else {
+            return (actions, failures);
+        };
+        let trigger = event.trigger();
+        for script in self.scripts.values_mut().filter(|s| s.status.enabled && s.definition.trigger == trigger) {
+            script.status.runs += 1;
+            let returned = self
+                .engine
+                .call_fn::<Dynamic>(&mut Scope::new(), &script.ast, ENTRY_POINT, (argument.clone(),))
+                .map_err(|e| BridgeError::Expression(e.to_string()))
+                .and_then(|output| parse_actions(&output));
+            match returned {
+                Ok(returned) => {
+                    script.consecutive_errors = 0;
+                    script.status.actions += returned.len() as u64;
+                    actions.extend(returned.into_iter().map(|a| (script.definition.name.clone(), a)));
+                }
+                Err(e) => {
+                    script.status.errors += 1;
+                    script.consecutive_errors += 1;
+                    if script.consecutive_errors >= MAX_CONSECUTIVE_ERRORS {
+                        script.status.enabled = false;
+                    }
+                    failures.push(format!("Script {} failed: {}", script.definition.name, e));
+                    script.status.last_error = Some(e.to_string());
+                }
+            }
+        }
+        (actions, failures)
+    }
+}
+
+/// Unit, one action map, or an array of them.
+fn parse_actions(output: &Dynamic) -> Result<Vec<ScriptAction>, BridgeError> {
+    let value: Value = rhai::serde::from_dynamic(output).map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+    let items = match value {
+        Value::Null => Vec::new(),
+        Value::Array(items) => items,
+        item => vec![item],
+    };
+    items
+        .into_iter()
+        .take(MAX_ACTIONS)
+        .map(|item| serde_json::from_value(item).map_err(|e| BridgeError::InvalidInput(format!("Invalid action: {}", e))))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ScriptAlertBody {
+    script: String,
+    message: String,
+    block_id: String,
+}
+
+impl EcoBlockContext {
+    fn persist_scripts(&self) -> Result<(), BridgeError> {
+        match &self.node_path {
+            Some(path) => self.scripts.save(&scripts_path(path)),
+            None => Ok(()),
+        }
+    }
+
+    /// Compiles and installs a script, replacing one of the same name.
+    pub fn install_script(&mut self, definition: ScriptDefinition) -> Result<(), BridgeError> {
+        if !self.scripts.scripts.contains_key(&definition.name) && self.scripts.scripts.len() >= MAX_SCRIPTS {
+            return Err(BridgeError::InvalidInput(format!("Invalid script {}: at most {} scripts", definition.name, MAX_SCRIPTS)));
+        }
+        let script = self.scripts.compile(definition)?;
+        self.scripts.scripts.insert(script.definition.name.clone(), script);
+        self.persist_scripts()
+    }
+
+    pub fn remove_script(&mut self, name: &str) -> Result<bool, BridgeError> {
+        let removed = self.scripts.scripts.remove(name).is_some();
+        if removed {
+            self.persist_scripts()?;
+        }
+        Ok(removed)
+    }
+
+    pub(crate) fn run_scripts(&mut self, event: ScriptEvent) {
+        if self.scripts.running || self.scripts.scripts.is_empty() {
+            return;
+        }
+        let (actions, failures) = self.scripts.run(&event);
+        for failure in failures {
+            self.log(LogLevel::Warn, failure);
+        }
+        self.scripts.running = true;
+        for (script, action) in actions {
+            self.apply_script_action(&script, &event, action);
+        }
+        self.scripts.running = false;
+    }
+
+    fn apply_script_action(&mut self, script: &str, event: &ScriptEvent, action: ScriptAction) {
+        match action {
+            ScriptAction::Alert { message } => {
+                self.log(LogLevel::Warn, format!("Script {} alert: {}", script, message));
+                let body = ScriptAlertBody {
+                    script: script.to_string(),
+                    message,
+                    block_id: event.block_id().to_string(),
+                };
+                let body = serde_json::to_value(&body).unwrap_or_default();
+                match self.sign_record(SCRIPT_ALERT_KIND, body, clock::now_secs()) {
+                    Ok(record) => {
+                        self.gossip_record(&record);
+                        self.records.insert(record);
+                    }
+                    Err(e) => self.log(LogLevel::Warn, format!("Script {} alert not published: {}", script, e)),
+                }
+            }
+            ScriptAction::Block { data } => {
+                let parents = vec![BlockId::from_storage(event.block_id().to_string())];
+                let created = serde_json::to_vec(&data)
+                    .map_err(|e| BridgeError::Serialization(e.to_string()))
+                    .and_then(|bytes| self.try_create_block(bytes, parents));
+                if let Err(e) = created {
+                    self.log(LogLevel::Warn, format!("Script {} block not created: {}", script, e));
+                }
+            }
+            ScriptAction::Log { message } => {
+                self.log(LogLevel::Info, format!("Script {}: {}", script, message));
+            }
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use crypto_box::aead::OsRng;
+use crypto_box::{PublicKey, SecretKey};
+use ecoblock_crypto::keys::keypair::CryptoKeypair;
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use crate::error::BridgeError;
+
+/// Encrypts `message` so that only the holder of the node key `public_key_hex` can open it.
+pub fn seal_to(public_key_hex: &str, message: &[u8]) -> Result<Vec<u8>, BridgeError> {
+    let bytes: [u8; 32] = hex::decode(public_key_hex)
+        .map_err(|e| BridgeError::Decoding(e.to_string()))?
+        .try_into()
+        .map_err(|_| BridgeError::Crypto("public key must be 32 bytes".to_string()))?;
+    let verifying = VerifyingKey::from_bytes(&bytes).map_err(|e| BridgeError::Crypto(format!("{:?}", e)))?;
+    let recipient = PublicKey::from_bytes(verifying.to_montgomery().to_bytes());
+    recipient
+        .seal(&mut OsRng, message)
+        .map_err(|e| BridgeError::Crypto(format!("{:?}", e)))
+}
+
+pub fn open_sealed(keypair: &CryptoKeypair, sealed: &[u8]) -> Result<Vec<u8>, BridgeError> {
+    let key_bytes = keypair.to_bytes();
+    let seed: [u8; 32] = key_bytes
+        .get(..32)
+        .and_then(|s| s.try_into().ok())
+        .ok_or_else(|| BridgeError::Crypto("keypair too short".to_string()))?;
+    let secret = SecretKey::from_bytes(SigningKey::from_bytes(&seed).to_scalar_bytes());
+    secret.unseal(sealed).map_err(|e| BridgeError::Crypto(format!("{:?}", e)))
+}
@@ -0,0 +1,265 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use crate::block_id::BlockId;
+use crate::error::BridgeError;
+use crate::ingest::{DecodedReading, ReadingGroups};
+use crate::wire::MAX_PAYLOAD_LEN;
+use crate::EcoBlockContext;
+
+/// Times below 2^28 seconds are relative to now (RFC 8428 section 4.5.3).
+const RELATIVE_TIME_LIMIT: f64 = 268_435_456.0;
+const MAX_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SenmlIngest {
+    pub blocks: Vec<BlockId>,
+    /// Records with only string, boolean or data values, or a sum.
+    pub skipped: usize,
+}
+
+/// CBOR labels from RFC 8428 section 6, as their JSON names.
+fn cbor_label(key: &str) -> &str {
+    match key {
+        "-1" => "bver",
+        "-2" => "bn",
+        "-3" => "bt",
+        "-4" => "bu",
+        "-5" => "bv",
+        "-6" => "bs",
+        "0" => "n",
+        "1" => "u",
+        "2" => "v",
+        "3" => "vs",
+        "4" => "vb",
+        "5" => "s",
+        "6" => "t",
+        "7" => "ut",
+        "8" => "vd",
+        other => other,
+    }
+}
+
+fn valid_name(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| c.is_ascii_alphanumeric())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || "-:./_".contains(c))
+}
+
+fn number(record: &Map<String, Value>, field: &str) -> Option<f64> {
+    record.get(field).and_then(Value::as_f64)
+}
+
+fn text<'a>(record: &'a Map<String, Value>, field: &str) -> Option<&'a str> {
+    record.get(field).and_then(Value::as_str)
+}
+
+/// Parses a SenML pack in JSON or CBOR, resolving base fields and relative
+/// times, and groups the numeric records by device and time.
+pub fn parse(bytes: &[u8], now: u64) -> Result<Vec<DecodedReading>, BridgeError> {
+    parse_counted(bytes, now).map(|(readings, _)| readings)
+}
+
+fn parse_counted(bytes: &[u8], now: u64) -> Result<(Vec<DecodedReading>, usize), BridgeError> {
+    if bytes.len() > MAX_PAYLOAD_LEN {
+        return Err(BridgeError::InvalidInput(format!("Invalid SenML pack: {} bytes exceeds {}", bytes.len(), MAX_PAYLOAD_LEN)));
+    }
+    let pack = match bytes.iter().find(|b| !b.is_ascii_whitespace()) {
+        Some(b'[') => serde_json::from_slice(bytes).map_err(|e| BridgeError::Deserialization(e.to_string()))?,
+        _ => Cbor::decode(bytes)?,
+    };
+    let Value::Array(records) = pack else {
+        return Err(BridgeError::InvalidInput("Invalid SenML pack: expected an array of records".to_string()));
+    };
+    let (mut base_name, mut base_time, mut base_unit, mut base_value) = (String::new(), 0.0, None, 0.0);
+    let mut groups = ReadingGroups::new();
+    let mut skipped = 0;
+    for (i, record) in records.iter().enumerate() {
+        let Value::Object(raw) = record else {
+            return Err(BridgeError::InvalidInput(format!("Invalid SenML record {}: expected a map", i)));
+        };
+        let record: Map<String, Value> = raw.iter().map(|(k, v)| (cbor_label(k).to_string(), v.clone())).collect();
+        if let Some(bn) = text(&record, "bn") {
+            base_name = bn.to_string();
+        }
+        if let Some(bt) = number(&record, "bt") {
+            base_time = bt;
+        }
+        if let Some(bu) = text(&record, "bu") {
+            base_unit = Some(bu.to_string());
+        }
+        if let Some(bv) = number(&record, "bv") {
+            base_value = bv;
+        }
+        let name = format!("{}{}", base_name, text(&record, "n").unwrap_or_default());
+        if !valid_name(&name) {
+            return Err(BridgeError::InvalidInput(format!("Invalid SenML record {}: bad name {:?}", i, name)));
+        }
+        let Some(value) = number(&record, "v") else {
+            skipped += 1;
+            continue;
+        };
+        let time = base_time + number(&record, "t").unwrap_or_default();
+        let time = if time < RELATIVE_TIME_LIMIT { now as f64 + time } else { time };
+        let unit = text(&record, "u").map(str::to_string).or_else(|| base_unit.clone());
+        let (sensor_id, metric) = match name.rfind([':', '/']) {
+            Some(at) if at > 0 => (Some(name[..at].to_string()), name[at + 1..].to_string()),
+            _ => (None, name.clone()),
+        };
+        if metric.is_empty() || metric == "sensor_id" || metric == "timestamp" {
+            return Err(BridgeError::InvalidInput(format!("Invalid SenML record {}: bad measurement name {:?}", i, metric)));
+        }
+        let timestamp = time.max(0.0) as u64;
+        groups.insert(sensor_id, timestamp, &metric, unit.as_deref(), base_value + value);
+    }
+    Ok((groups.into_readings(), skipped))
+}
+
+/// Just enough CBOR (RFC 8949) for SenML: definite and indefinite lengths,
+/// integer map keys as decimal strings, tags skipped.
+struct Cbor<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cbor<'a> {
+    fn decode(bytes: &'a [u8]) -> Result<Value, BridgeError> {
+        let mut cbor = Cbor { bytes, pos: 0 };
+        let value = cbor.value(0)?.ok_or_else(|| Self::error("unexpected break"))?;
+        if cbor.pos != bytes.len() {
+            return Err(Self::error("trailing bytes"));
+        }
+        Ok(value)
+    }
+
+    fn error(message: &str) -> String {
+        BridgeError::Deserialization(format!("CBOR {}", message))
+    }
+
+    fn take(&mut self, n: u64) -> Result<&'a [u8], BridgeError> {
+        let end = usize::try_from(n).ok().and_then(|n| self.pos.checked_add(n)).filter(|&end| end <= self.bytes.len());
+        let end = end.ok_or_else(|| Self::error("truncated"))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn uint(&mut self, len: u64) -> Result<u64, BridgeError> {
+        Ok(self.take(len)?.iter().fold(0, |n, b| n << 8 | u64::from(*b)))
+    }
+
+    /// The argument of a head; `None` for an indefinite length.
+    fn argument(&mut self, info: u8) -> Result<Option<u64>, BridgeError> {
+        match info {
+            0..=23 => Ok(Some(u64::from(info))),
+            24 => self.uint(1).map(Some),
+            25 => self.uint(2).map(Some),
+            26 => self.uint(4).map(Some),
+            27 => self.uint(8).map(Some),
+            31 => Ok(None),
+            _ => Err(Self::error("reserved additional info")),
+        }
+    }
+
+    /// Decodes one item; `None` is the break stopping an indefinite item.
+    fn value(&mut self, depth: usize) -> Result<Option<Value>, BridgeError> {
+        if depth > MAX_DEPTH {
+            return Err(Self::error("nested too deeply"));
+        }
+        let head = self.take(1)?[0];
+        let (major, info) = (head >> 5, head & 0x1f);
+        if major == 7 {
+            return match info {
+                20 => Ok(Some(Value::Bool(false))),
+                21 => Ok(Some(Value::Bool(true))),
+                22 | 23 => Ok(Some(Value::Null)),
+                25 => Ok(Some(Value::from(f16_to_f64(self.uint(2)? as u16)))),
+                26 => Ok(Some(Value::from(f64::from(f32::from_bits(self.uint(4)? as u32))))),
+                27 => Ok(Some(Value::from(f64::from_bits(self.uint(8)?)))),
+                31 => Ok(None),
+                _ => Err(Self::error("unsupported simple value")),
+            };
+        }
+        let argument = self.argument(info)?;
+        let value = match (major, argument) {
+            (0, Some(n)) => Value::from(n),
+            (1, Some(n)) => Value::from(-1 - i64::try_from(n).map_err(|_| Self::error("integer out of range"))?),
+            (2, _) => Value::from(hex::encode(self.string(major, argument)?)),
+            (3, _) => {
+                let bytes = self.string(major, argument)?;
+                Value::from(String::from_utf8(bytes).map_err(|_| Self::error("invalid UTF-8"))?)
+            }
+            (4, _) => {
+                let mut items = Vec::new();
+                while argument != Some(items.len() as u64) {
+                    match self.value(depth + 1)? {
+                        Some(item) => items.push(item),
+                        None if argument.is_none() => break,
+                        None => return Err(Self::error("unexpected break")),
+                    }
+                }
+                Value::Array(items)
+            }
+            (5, _) => {
+                let mut fields = Map::new();
+                while argument != Some(fields.len() as u64) {
+                    let key = match self.value(depth + 1)? {
+                        Some(Value::String(key)) => key,
+                        Some(Value::Number(key)) => key.to_string(),
+                        Some(_) => return Err(Self::error("unsupported map key")),
+                        None if argument.is_none() => break,
+                        None => return Err(Self::error("unexpected break")),
+                    };
+                    let value = self.value(depth + 1)?.ok_or_else(|| Self::error("unexpected break"))?;
+                    fields.insert(key, value);
+                }
+                Value::Object(fields)
+            }
+            (6, Some(_)) => self.value(depth + 1)?.ok_or_else(|| Self::error("unexpected break"))?,
+            _ => return Err(Self::error("malformed item")),
+        };
+        Ok(Some(value))
+    }
+
+    /// Bytes of a byte or text string, joining indefinite-length chunks.
+    fn string(&mut self, major: u8, argument: Option<u64>) -> Result<Vec<u8>, BridgeError> {
+        if let Some(len) = argument {
+            return self.take(len).map(<[u8]>::to_vec);
+        }
+        let mut bytes = Vec::new();
+        loop {
+            let head = self.take(1)?[0];
+            if head == 0xff {
+                return Ok(bytes);
+            }
+            match (head >> 5, self.argument(head & 0x1f)?) {
+                (chunk_major, Some(len)) if chunk_major == major => bytes.extend_from_slice(self.take(len)?),
+                _ => return Err(Self::error("malformed string chunk")),
+            }
+        }
+    }
+}
+
+fn f16_to_f64(half: u16) -> f64 {
+    let exponent = i32::from((half >> 10) & 0x1f);
+    let mantissa = f64::from(half & 0x3ff);
+    let magnitude = match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        31 if mantissa == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        _ => (mantissa + 1024.0) * 2f64.powi(exponent - 25),
+    };
+    if half & 0x8000 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+impl EcoBlockContext {
+    /// Signs one block per device and time in a SenML pack. Every block is
+    /// validated before the first is stored, so a rejected pack stores nothing.
+    pub fn ingest_senml(&mut self, bytes: &[u8]) -> Result<SenmlIngest, BridgeError> {
+        let (readings, skipped) = parse_counted(bytes, crate::clock::now_secs())?;
+        let blocks = self.ingest_decoded(&readings)?;
+        Ok(SenmlIngest { blocks, skipped })
+    }
+}
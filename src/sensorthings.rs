@@ -0,0 +1,416 @@
+//! Read-only OGC SensorThings API 1.1 over the local readings. Each sensor is a
+//! Thing with a Sensor of the same id, each of its metrics a Datastream with an
+//! ObservedProperty per metric name, and each reading value an Observation. The
+//! host's HTTP server routes `GET` requests under its base URL to `sensorthings_get`.
+//! Answers carry an ETag and are cached until the tangle changes.
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::error::BridgeError;
+use crate::gateway::percent_decode;
+use crate::readings::Reading;
+use crate::response_cache::{self, CachedResponse};
+use crate::{clock, inventory, EcoBlockContext};
+
+pub const DEFAULT_TOP: usize = 100;
+pub const MAX_TOP: usize = 1000;
+const OM_MEASUREMENT: &str = "http://www.opengis.net/def/observationType/OGC-OM/2.0/OM_Measurement";
+/// Separates the sensor id from the metric in Datastream ids, and the block id
+/// from the metric in Observation ids.
+const ID_SEPARATOR: char = '|';
+const ENTITY_SETS: [&str; 5] = ["Things", "Sensors", "Datastreams", "Observations", "ObservedProperties"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SensorThingsResponse {
+    pub status: u16,
+    pub content_type: String,
+    pub body: String,
+    /// Sent on successful and `304` answers.
+    pub etag: Option<String>,
+}
+
+impl SensorThingsResponse {
+    fn json(status: u16, body: Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json".to_string(),
+            body: body.to_string(),
+            etag: None,
+        }
+    }
+
+    fn error(status: u16, message: String) -> Self {
+        Self::json(status, json!({ "code": status, "type": "error", "message": message }))
+    }
+}
+
+/// Unit name, symbol and UCUM definition of metrics with a known unit.
+fn unit(metric: &str) -> (&'static str, &'static str, &'static str) {
+    match metric {
+        "temperature" => ("degree Celsius", "°C", "http://unitsofmeasure.org/ucum.html#para-30"),
+        "humidity" => ("percent", "%", "http://unitsofmeasure.org/ucum.html#para-29"),
+        "co2" => ("parts per million", "ppm", "http://unitsofmeasure.org/ucum.html#para-29"),
+        "pm25" => ("microgram per cubic meter", "µg/m³", "http://unitsofmeasure.org/ucum.html#para-30"),
+        _ => ("", "", ""),
+    }
+}
+
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'\'' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// `Name('id')` with quotes doubled, percent-encoded for a URL path.
+fn entity_path(set: &str, id: &str) -> String {
+    format!("{}('{}')", set, percent_encode(&id.replace('\'', "''")))
+}
+
+/// Splits `Name('id')` or `Name(42)` into the name and the id.
+fn parse_segment(segment: &str) -> Result<(&str, Option<String>), BridgeError> {
+    let Some(open) = segment.find('(') else {
+        return Ok((segment, None));
+    };
+    let key = segment[open + 1..].strip_suffix(')').ok_or_else(|| BridgeError::InvalidInput(format!("Invalid path segment {:?}", segment)))?;
+    let id = match key.strip_prefix('\'').and_then(|k| k.strip_suffix('\'')) {
+        Some(quoted) => quoted.replace("''", "'"),
+        None if !key.is_empty() && key.bytes().all(|b| b.is_ascii_digit()) => key.to_string(),
+        None => return Err(BridgeError::InvalidInput(format!("Invalid entity id {:?}", key))),
+    };
+    Ok((&segment[..open], Some(id)))
+}
+
+struct Options {
+    top: usize,
+    skip: usize,
+    count: bool,
+    descending: bool,
+}
+
+fn parse_options(query: &str) -> Result<Options, BridgeError> {
+    let mut options = Options {
+        top: DEFAULT_TOP,
+        skip: 0,
+        count: false,
+        descending: false,
+    };
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = percent_decode(value)?;
+        let number = || value.parse::<usize>().map_err(|_| BridgeError::InvalidInput(format!("Invalid {} {:?}", key, value)));
+        match key {
+            "$top" => options.top = number()?.min(MAX_TOP),
+            "$skip" => options.skip = number()?,
+            "$count" => options.count = value == "true",
+            "$orderby" => match value.trim() {
+                "phenomenonTime" | "phenomenonTime asc" => options.descending = false,
+                "phenomenonTime desc" => options.descending = true,
+                _ => return Err(BridgeError::InvalidInput(format!("Invalid $orderby {:?}: only phenomenonTime is supported", value))),
+            },
+            _ => return Err(BridgeError::InvalidInput(format!("Invalid query option {}: not supported by this endpoint", key))),
+        }
+    }
+    Ok(options)
+}
+
+/// Metrics of each sensor, from the indexed readings.
+struct Catalog {
+    sensors: BTreeMap<String, (Vec<String>, u64, u64)>,
+}
+
+impl Catalog {
+    fn datastream(&self, id: &str) -> Option<(&str, &str)> {
+        self.sensors.iter().find_map(|(sensor, (metrics, _, _))| {
+            let metric = id.strip_prefix(sensor.as_str())?.strip_prefix(ID_SEPARATOR)?;
+            metrics.iter().find(|m| *m == metric).map(|m| (sensor.as_str(), m.as_str()))
+        })
+    }
+
+    fn datastreams(&self) -> Vec<(&str, &str)> {
+        self.sensors
+            .iter()
+            .flat_map(|(sensor, (metrics, _, _))| metrics.iter().map(move |m| (sensor.as_str(), m.as_str())))
+            .collect()
+    }
+}
+
+struct Api<'a> {
+    base: &'a str,
+    catalog: Catalog,
+    readings: Vec<&'a Reading>,
+}
+
+impl Api<'_> {
+    fn link(&self, path: &str) -> String {
+        format!("{}/{}", self.base, path)
+    }
+
+    fn thing(&self, sensor: &str) -> Value {
+        let (_, count, last) = &self.catalog.sensors[sensor];
+        let path = entity_path("Things", sensor);
+        json!({
+            "@iot.id": sensor,
+            "@iot.selfLink": self.link(&path),
+            "name": sensor,
+            "description": format!("EcoBlock sensor {}", sensor),
+            "properties": { "readingCount": count, "lastReading": clock::iso8601(*last) },
+            "Datastreams@iot.navigationLink": self.link(&format!("{}/Datastreams", path)),
+        })
+    }
+
+    fn sensor(&self, sensor: &str) -> Value {
+        let path = entity_path("Sensors", sensor);
+        json!({
+            "@iot.id": sensor,
+            "@iot.selfLink": self.link(&path),
+            "name": sensor,
+            "description": format!("EcoBlock sensor {}", sensor),
+            "encodingType": "text/plain",
+            "metadata": "Signed readings from an EcoBlock tangle",
+            "Datastreams@iot.navigationLink": self.link(&format!("{}/Datastreams", path)),
+        })
+    }
+
+    fn observed_property(&self, metric: &str) -> Value {
+        let path = entity_path("ObservedProperties", metric);
+        json!({
+            "@iot.id": metric,
+            "@iot.selfLink": self.link(&path),
+            "name": metric,
+            "definition": format!("urn:ecoblock:metric:{}", metric),
+            "description": format!("EcoBlock metric {}", metric),
+            "Datastreams@iot.navigationLink": self.link(&format!("{}/Datastreams", path)),
+        })
+    }
+
+    fn datastream(&self, sensor: &str, metric: &str) -> Value {
+        let id = format!("{}{}{}", sensor, ID_SEPARATOR, metric);
+        let path = entity_path("Datastreams", &id);
+        let (name, symbol, definition) = unit(metric);
+        let times: Vec<u64> = self.observations_of(sensor, metric).iter().map(|r| r.timestamp).collect();
+        let mut datastream = json!({
+            "@iot.id": id,
+            "@iot.selfLink": self.link(&path),
+            "name": format!("{} {}", sensor, metric),
+            "description": format!("{} of EcoBlock sensor {}", metric, sensor),
+            "observationType": OM_MEASUREMENT,
+            "unitOfMeasurement": { "name": name, "symbol": symbol, "definition": definition },
+            "Thing@iot.navigationLink": self.link(&format!("{}/Thing", path)),
+            "Sensor@iot.navigationLink": self.link(&format!("{}/Sensor", path)),
+            "ObservedProperty@iot.navigationLink": self.link(&format!("{}/ObservedProperty", path)),
+            "Observations@iot.navigationLink": self.link(&format!("{}/Observations", path)),
+        });
+        if let (Some(first), Some(last)) = (times.iter().min(), times.iter().max()) {
+            datastream["phenomenonTime"] = json!(format!("{}/{}", clock::iso8601(*first), clock::iso8601(*last)));
+        }
+        datastream
+    }
+
+    fn observation(&self, reading: &Reading, metric: &str) -> Value {
+        let id = format!("{}{}{}", reading.block_id, ID_SEPARATOR, metric);
+        let path = entity_path("Observations", &id);
+        let time = clock::iso8601(reading.timestamp);
+        json!({
+            "@iot.id": id,
+            "@iot.selfLink": self.link(&path),
+            "phenomenonTime": time,
+            "resultTime": time,
+            "result": reading.value(metric),
+            "parameters": { "blockId": reading.block_id },
+            "Datastream@iot.navigationLink": self.link(&format!("{}/Datastream", path)),
+        })
+    }
+
+    fn observations_of(&self, sensor: &str, metric: &str) -> Vec<&Reading> {
+        self.readings
+            .iter()
+            .copied()
+            .filter(|r| r.sensor_id == sensor && r.values.contains_key(metric))
+            .collect()
+    }
+
+    fn all_observations(&self) -> Vec<(&Reading, String)> {
+        self.readings
+            .iter()
+            .flat_map(|r| r.values.keys().map(move |m| (*r, m.clone())))
+            .collect()
+    }
+
+    fn observation_by_id(&self, id: &str) -> Option<(&Reading, String)> {
+        let (block, metric) = id.rsplit_once(ID_SEPARATOR)?;
+        let reading = self.readings.iter().find(|r| r.block_id == block && r.values.contains_key(metric))?;
+        Some((*reading, metric.to_string()))
+    }
+
+    fn collection(&self, entities: Vec<Value>, options: &Options, path: &str) -> Value {
+        let total = entities.len();
+        let page: Vec<Value> = entities.into_iter().skip(options.skip).take(options.top).collect();
+        let mut body = json!({ "value": page });
+        if options.count {
+            body["@iot.count"] = json!(total);
+        }
+        if options.skip + options.top < total {
+            let mut next = format!("{}?$top={}&$skip={}", self.link(path), options.top, options.skip + options.top);
+            if options.count {
+                next.push_str("&$count=true");
+            }
+            if options.descending {
+                next.push_str("&$orderby=phenomenonTime%20desc");
+            }
+            body["@iot.nextLink"] = json!(next);
+        }
+        body
+    }
+
+    fn observations(&self, mut observations: Vec<(&Reading, String)>, options: &Options, path: &str) -> Value {
+        observations.sort_by_key(|(r, _)| r.timestamp);
+        if options.descending {
+            observations.reverse();
+        }
+        let entities = observations.iter().map(|(r, m)| self.observation(r, m)).collect();
+        self.collection(entities, options, path)
+    }
+
+    fn not_found(set: &str, id: &str) -> SensorThingsResponse {
+        SensorThingsResponse::error(404, format!("Unknown {} entity {:?}", set, id))
+    }
+
+    /// Answers a path below the base URL, such as `/Things('a')/Datastreams`.
+    fn get(&self, path: &str, options: &Options) -> SensorThingsResponse {
+        let trimmed = path.trim_matches('/');
+        if trimmed.is_empty() {
+            let sets: Vec<Value> = ENTITY_SETS.iter().map(|s| json!({ "name": s, "url": self.link(s) })).collect();
+            return SensorThingsResponse::json(200, json!({ "value": sets }));
+        }
+        let segments: Vec<&str> = trimmed.split('/').collect();
+        let parsed = match segments.iter().map(|s| parse_segment(s)).collect::<Result<Vec<_>, _>>() {
+            Ok(parsed) => parsed,
+            Err(e) => return SensorThingsResponse::error(400, e.to_string()),
+        };
+        let body = match parsed.as_slice() {
+            [("Things", None)] => self.collection(self.catalog.sensors.keys().map(|s| self.thing(s)).collect(), options, trimmed),
+            [("Sensors", None)] => self.collection(self.catalog.sensors.keys().map(|s| self.sensor(s)).collect(), options, trimmed),
+            [("Datastreams", None)] => {
+                let entities = self.catalog.datastreams().into_iter().map(|(s, m)| self.datastream(s, m)).collect();
+                self.collection(entities, options, trimmed)
+            }
+            [("ObservedProperties", None)] => {
+                let mut metrics: Vec<&str> = self.catalog.datastreams().into_iter().map(|(_, m)| m).collect();
+                metrics.sort_unstable();
+                metrics.dedup();
+                self.collection(metrics.into_iter().map(|m| self.observed_property(m)).collect(), options, trimmed)
+            }
+            [("Observations", None)] => self.observations(self.all_observations(), options, trimmed),
+            [("Things" | "Sensors", Some(id)), rest @ ..] => {
+                if !self.catalog.sensors.contains_key(id) {
+                    return Self::not_found(parsed[0].0, id);
+                }
+                match rest {
+                    [] if parsed[0].0 == "Things" => self.thing(id),
+                    [] => self.sensor(id),
+                    [("Datastreams", None)] => {
+                        let entities = self.catalog.datastreams().into_iter().filter(|(s, _)| *s == id.as_str()).map(|(s, m)| self.datastream(s, m)).collect();
+                        self.collection(entities, options, trimmed)
+                    }
+                    _ => return SensorThingsResponse::error(404, format!("Unknown path {}", path)),
+                }
+            }
+            [("ObservedProperties", Some(id)), rest @ ..] => {
+                let streams: Vec<(&str, &str)> = self.catalog.datastreams().into_iter().filter(|(_, m)| *m == id.as_str()).collect();
+                if streams.is_empty() {
+                    return Self::not_found("ObservedProperties", id);
+                }
+                match rest {
+                    [] => self.observed_property(id),
+                    [("Datastreams", None)] => self.collection(streams.into_iter().map(|(s, m)| self.datastream(s, m)).collect(), options, trimmed),
+                    _ => return SensorThingsResponse::error(404, format!("Unknown path {}", path)),
+                }
+            }
+            [("Datastreams", Some(id)), rest @ ..] => {
+                let Some((sensor, metric)) = self.catalog.datastream(id) else {
+                    return Self::not_found("Datastreams", id);
+                };
+                match rest {
+                    [] => self.datastream(sensor, metric),
+                    [("Thing", None)] => self.thing(sensor),
+                    [("Sensor", None)] => self.sensor(sensor),
+                    [("ObservedProperty", None)] => self.observed_property(metric),
+                    [("Observations", None)] => {
+                        let observations = self.observations_of(sensor, metric).into_iter().map(|r| (r, metric.to_string())).collect();
+                        self.observations(observations, options, trimmed)
+                    }
+                    _ => return SensorThingsResponse::error(404, format!("Unknown path {}", path)),
+                }
+            }
+            [("Observations", Some(id)), rest @ ..] => {
+                let Some((reading, metric)) = self.observation_by_id(id) else {
+                    return Self::not_found("Observations", id);
+                };
+                match rest {
+                    [] => self.observation(reading, &metric),
+                    [("Datastream", None)] => self.datastream(&reading.sensor_id, &metric),
+                    _ => return SensorThingsResponse::error(404, format!("Unknown path {}", path)),
+                }
+            }
+            _ => return SensorThingsResponse::error(404, format!("Unknown path {}", path)),
+        };
+        SensorThingsResponse::json(200, body)
+    }
+}
+
+impl EcoBlockContext {
+    /// Answers a SensorThings `GET` for `path_and_query` (relative to
+    /// `base_url`, e.g. `/Things?$top=10`). Links in the response are absolute,
+    /// built on `base_url`.
+    /// Answers from the cache while nothing has changed, and with `304` when
+    /// `if_none_match` names the current answer.
+    pub fn sensorthings_get(&mut self, base_url: &str, path_and_query: &str, if_none_match: Option<&str>) -> SensorThingsResponse {
+        let (key, stamp) = (format!("sensorthings {} {}", base_url, path_and_query), self.response_stamp());
+        let now = clock::now_secs();
+        let response = match self.responses.get(&key, stamp, now, None) {
+            Some(cached) => cached.clone(),
+            None => {
+                let response = self.compute_sensorthings(base_url, path_and_query);
+                if response.status != 200 {
+                    return response;
+                }
+                let response = CachedResponse::new(stamp, response.status, response.content_type, response.body);
+                self.responses.insert(&key, stamp, now, &response);
+                response
+            }
+        };
+        let response = if response_cache::matches(if_none_match, &response.etag) {
+            response.not_modified()
+        } else {
+            response
+        };
+        SensorThingsResponse {
+            status: response.status,
+            content_type: response.content_type,
+            body: response.body,
+            etag: Some(response.etag),
+        }
+    }
+
+    fn compute_sensorthings(&self, base_url: &str, path_and_query: &str) -> SensorThingsResponse {
+        let (path, query) = path_and_query.split_once('?').unwrap_or((path_and_query, ""));
+        let (path, options) = match percent_decode(path).and_then(|p| parse_options(query).map(|o| (p, o))) {
+            Ok(parsed) => parsed,
+            Err(e) => return SensorThingsResponse::error(400, e.to_string()),
+        };
+        let sensors = inventory::summarize(&self.readings)
+            .into_iter()
+            .map(|s| (s.sensor_id, (s.metrics, s.reading_count, s.last_reading)))
+            .collect();
+        let api = Api {
+            base: base_url.trim_end_matches('/'),
+            catalog: Catalog { sensors },
+            readings: self.readings.iter().collect(),
+        };
+        api.get(&path, &options)
+    }
+}
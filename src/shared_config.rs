@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use crate::change_feed::ChangeKind;
+use crate::error::BridgeError;
+use crate::logs::LogLevel;
+use crate::records::Record;
+use crate::{clock, EcoBlockContext};
+
+pub const SHARED_CONFIG_KIND: &str = "shared_config";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SharedConfigBody {
+    pub key: String,
+    pub value: String,
+    pub version: u64,
+    /// Token of the entry this write replaced.
+    pub previous: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigEntry {
+    pub key: String,
+    pub value: String,
+    pub version: u64,
+    /// Id of the record that wrote this value. Pass it back as the expected token
+    /// to update the entry.
+    pub token: String,
+    pub author: String,
+    pub updated_at: u64,
+}
+
+/// A compare-and-set whose expected token no longer matches.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigConflict {
+    pub key: String,
+    pub expected: Option<String>,
+    pub current: Option<Box<ConfigEntry>>,
+}
+
+impl std::fmt::Display for ConfigConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let expected = self.expected.as_deref().unwrap_or("none");
+        match &self.current {
+            Some(c) => write!(
+                f,
+                "{} expected {}, current version {} token {} value {}",
+                self.key, expected, c.version, c.token, c.value
+            ),
+            None => write!(f, "{} expected {}, but it is not set", self.key, expected),
+        }
+    }
+}
+
+impl From<ConfigConflict> for BridgeError {
+    fn from(conflict: ConfigConflict) -> Self {
+        BridgeError::Conflict(conflict.to_string())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SharedConfig {
+    entries: BTreeMap<String, ConfigEntry>,
+}
+
+impl SharedConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&ConfigEntry> {
+        self.entries.get(key)
+    }
+
+    pub fn list(&self) -> Vec<ConfigEntry> {
+        self.entries.values().cloned().collect()
+    }
+
+    /// Keeps the higher version, breaking ties by token, so every node settles on
+    /// the same value whatever order writes arrive in. Returns the entry replaced
+    /// when `entry` wins.
+    fn apply(&mut self, entry: ConfigEntry) -> Option<Option<ConfigEntry>> {
+        let current = self.entries.get(&entry.key);
+        if current.is_some_and(|c| (c.version, &c.token) >= (entry.version, &entry.token)) {
+            return None;
+        }
+        Some(self.entries.insert(entry.key.clone(), entry))
+    }
+}
+
+impl EcoBlockContext {
+    /// Writes `value` if the current entry's token is still `expected`; `None`
+    /// expects the key to be unset.
+    pub fn set_shared_config(&mut self, key: &str, value: String, expected: Option<String>) -> Result<ConfigEntry, BridgeError> {
+        let current = self.shared_config.get(key).cloned();
+        if current.as_ref().map(|c| &c.token) != expected.as_ref() {
+            return Err(ConfigConflict {
+                key: key.to_string(),
+                expected,
+                current: current.map(Box::new),
+            }
+            .into());
+        }
+        let body = SharedConfigBody {
+            key: key.to_string(),
+            value,
+            version: current.as_ref().map_or(1, |c| c.version + 1),
+            previous: current.map(|c| c.token),
+        };
+        let record = self.sign_record(SHARED_CONFIG_KIND, serde_json::to_value(&body).unwrap_or_default(), clock::now_secs())?;
+        self.apply_shared_config(&record).ok();
+        self.records.insert(record);
+        Ok(self.shared_config.get(key).cloned().expect("entry just written"))
+    }
+
+    pub(crate) fn apply_shared_config(&mut self, record: &Record) -> Result<(), BridgeError> {
+        let body: SharedConfigBody = serde_json::from_value(record.body.clone())
+            .map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+        let entry = ConfigEntry {
+            key: body.key.clone(),
+            value: body.value,
+            version: body.version,
+            token: record.id.clone(),
+            author: record.author.clone(),
+            updated_at: record.timestamp,
+        };
+        let Some(replaced) = self.shared_config.apply(entry) else {
+            return Ok(());
+        };
+        if let Some(replaced) = replaced.filter(|r| body.previous.as_deref() != Some(r.token.as_str())) {
+            self.log(
+                LogLevel::Warn,
+                format!("Concurrent update of shared config {}: {} replaces {}", body.key, record.id, replaced.token),
+            );
+        }
+        self.record_change(ChangeKind::ConfigUpdated, body.key);
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinkSpec {
+    pub from: String,
+    pub to: String,
+    /// Tick at which the link comes up (inclusive).
+    #[serde(default)]
+    pub up_at: u64,
+    /// Tick at which the link goes down (exclusive); `None` keeps it up.
+    #[serde(default)]
+    pub down_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeneratorSpec {
+    pub node: String,
+    pub every: u64,
+    #[serde(default)]
+    pub start: u64,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartitionSpec {
+    pub from: u64,
+    pub until: u64,
+    /// Nodes in different groups cannot exchange messages while the partition lasts.
+    pub groups: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    pub nodes: Vec<String>,
+    pub links: Vec<LinkSpec>,
+    pub generators: Vec<GeneratorSpec>,
+    #[serde(default)]
+    pub partitions: Vec<PartitionSpec>,
+    /// Every this many ticks, linked nodes also exchange everything the other lacks.
+    #[serde(default)]
+    pub anti_entropy_every: Option<u64>,
+    pub duration: u64,
+}
+
+impl Scenario {
+    pub fn from_json(json: &str) -> Result<Self, BridgeError> {
+        let scenario: Scenario = serde_json::from_str(json).map_err(|e| BridgeError::Scenario(e.to_string()))?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    pub fn load(path: &str) -> Result<Self, BridgeError> {
+        let json = fs::read_to_string(path).map_err(|e| BridgeError::Io(e.to_string()))?;
+        Self::from_json(&json)
+    }
+
+    fn validate(&self) -> Result<(), BridgeError> {
+        let nodes: BTreeSet<&str> = self.nodes.iter().map(String::as_str).collect();
+        let known = |n: &str| {
+            if nodes.contains(n) {
+                Ok(())
+            } else {
+                Err(BridgeError::Scenario(format!("unknown node {}", n)))
+            }
+        };
+        for link in &self.links {
+            known(&link.from)?;
+            known(&link.to)?;
+        }
+        for generator in &self.generators {
+            known(&generator.node)?;
+            if generator.every == 0 {
+                return Err(BridgeError::Scenario(format!("generator on {} has every = 0", generator.node)));
+            }
+        }
+        for partition in &self.partitions {
+            partition.groups.iter().flatten().try_for_each(|n| known(n))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulationReport {
+    pub scenario: String,
+    pub ticks: u64,
+    pub blocks_created: u64,
+    pub messages_sent: u64,
+    pub messages_dropped: u64,
+    /// First tick after which every node held every block, if that happened.
+    pub converged_at: Option<u64>,
+    pub mean_delivery_ticks: f64,
+    pub max_delivery_ticks: u64,
+    /// Average fraction of all blocks missing per node at the end of the run.
+    pub divergence: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    Now,
+    Drop,
+    /// Deliver this many ticks later than normal.
+    Delay(u64),
+}
+
+/// Decides how a message sent at `tick` is delivered; used to inject faults.
+pub trait DeliveryFilter {
+    fn deliver(&mut self, tick: u64, from: &str, to: &str) -> Delivery;
+}
+
+pub struct AlwaysDeliver;
+
+impl DeliveryFilter for AlwaysDeliver {
+    fn deliver(&mut self, _tick: u64, _from: &str, _to: &str) -> Delivery {
+        Delivery::Now
+    }
+}
+
+fn partitioned(scenario: &Scenario, tick: u64, a: &str, b: &str) -> bool {
+    scenario
+        .partitions
+        .iter()
+        .filter(|p| tick >= p.from && tick < p.until)
+        .any(|p| {
+            let group_of = |n: &str| p.groups.iter().position(|g| g.iter().any(|m| m == n));
+            match (group_of(a), group_of(b)) {
+                (Some(x), Some(y)) => x != y,
+                _ => false,
+            }
+        })
+}
+
+pub fn run(scenario: &Scenario) -> SimulationReport {
+    run_with(scenario, &mut AlwaysDeliver)
+}
+
+/// Push gossip: every tick each node forwards the blocks it learned in the
+/// previous tick to every neighbour reachable over an up, unpartitioned link,
+/// plus a full difference exchange on anti-entropy ticks.
+pub fn run_with(scenario: &Scenario, filter: &mut dyn DeliveryFilter) -> SimulationReport {
+    let mut known: HashMap<&str, BTreeSet<u64>> = scenario.nodes.iter().map(|n| (n.as_str(), BTreeSet::new())).collect();
+    let mut fresh: HashMap<&str, BTreeSet<u64>> = known.clone();
+    let mut created_at: BTreeMap<u64, u64> = BTreeMap::new();
+    let mut delivered_at: BTreeMap<u64, u64> = BTreeMap::new();
+    let (mut messages_sent, mut messages_dropped, mut next_block) = (0u64, 0u64, 0u64);
+    let mut converged_at = None;
+    let mut delayed: BTreeMap<u64, Vec<(&str, u64)>> = BTreeMap::new();
+
+    for tick in 0..scenario.duration {
+        for generator in &scenario.generators {
+            let produced = tick.saturating_sub(generator.start) / generator.every;
+            if tick >= generator.start && (tick - generator.start) % generator.every == 0 && produced < generator.count {
+                let node = generator.node.as_str();
+                known.get_mut(node).map(|k| k.insert(next_block));
+                fresh.get_mut(node).map(|f| f.insert(next_block));
+                created_at.insert(next_block, tick);
+                next_block += 1;
+            }
+        }
+
+        let mut arriving: HashMap<&str, BTreeSet<u64>> = HashMap::new();
+        for (node, block) in delayed.remove(&tick).unwrap_or_default() {
+            arriving.entry(node).or_default().insert(block);
+        }
+        let sync_tick = scenario.anti_entropy_every.is_some_and(|e| e > 0 && tick % e == 0);
+        for link in scenario.links.iter().filter(|l| tick >= l.up_at && l.down_at.is_none_or(|d| tick < d)) {
+            for (from, to) in [(link.from.as_str(), link.to.as_str()), (link.to.as_str(), link.from.as_str())] {
+                if partitioned(scenario, tick, from, to) {
+                    continue;
+                }
+                let outgoing: Vec<u64> = if sync_tick {
+                    known[from].difference(&known[to]).copied().collect()
+                } else {
+                    fresh[from].iter().copied().collect()
+                };
+                for block in outgoing {
+                    messages_sent += 1;
+                    match filter.deliver(tick, from, to) {
+                        Delivery::Now => {
+                            arriving.entry(to).or_default().insert(block);
+                        }
+                        Delivery::Drop => messages_dropped += 1,
+                        Delivery::Delay(ticks) => delayed.entry(tick + ticks).or_default().push((to, block)),
+                    }
+                }
+            }
+        }
+
+        fresh.values_mut().for_each(BTreeSet::clear);
+        for (node, blocks) in arriving {
+            let node_known = known.entry(node).or_default();
+            for block in blocks {
+                if node_known.insert(block) {
+                    fresh.entry(node).or_default().insert(block);
+                }
+            }
+        }
+
+        for block in created_at.keys() {
+            if !delivered_at.contains_key(block) && known.values().all(|k| k.contains(block)) {
+                delivered_at.insert(*block, tick);
+            }
+        }
+        let all_delivered = !created_at.is_empty() && delivered_at.len() == created_at.len();
+        converged_at = match (converged_at, all_delivered) {
+            (None, true) => Some(tick),
+            (_, false) => None,
+            (c, true) => c,
+        };
+    }
+
+    let latencies: Vec<u64> = delivered_at.iter().map(|(b, t)| t - created_at[b]).collect();
+    let total = created_at.len() as f64;
+    let divergence = if total == 0.0 || known.is_empty() {
+        0.0
+    } else {
+        known.values().map(|k| 1.0 - k.len() as f64 / total).sum::<f64>() / known.len() as f64
+    };
+    SimulationReport {
+        scenario: scenario.name.clone(),
+        ticks: scenario.duration,
+        blocks_created: next_block,
+        messages_sent,
+        messages_dropped,
+        converged_at,
+        mean_delivery_ticks: if latencies.is_empty() {
+            0.0
+        } else {
+            latencies.iter().sum::<u64>() as f64 / latencies.len() as f64
+        },
+        max_delivery_ticks: latencies.iter().copied().max().unwrap_or(0),
+        divergence,
+    }
+}
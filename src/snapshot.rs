@@ -0,0 +1,92 @@
+use std::fs;
+use std::path::Path;
+use ecoblock_storage::tangle::block::TangleBlock;
+use serde::{Deserialize, Serialize};
+use crate::delta::{checkpoint_id, Checkpoint};
+use crate::error::BridgeError;
+use crate::logs::LogLevel;
+use crate::runtime;
+use crate::EcoBlockContext;
+
+pub const SNAPSHOT_FORMAT: &str = "ecoblock-snapshot";
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Every sensor block of a node, as written by `export_snapshot`. Field order
+/// and block order are fixed, so the same block set always serializes the same.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub format: String,
+    pub version: u32,
+    pub node_id: String,
+    pub checkpoint: Checkpoint,
+    /// In insertion order, so parents come before their children.
+    pub blocks: Vec<TangleBlock>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotImport {
+    /// The node that exported the snapshot.
+    pub node_id: String,
+    pub checkpoint: Checkpoint,
+    pub imported: usize,
+    pub already_present: usize,
+}
+
+impl EcoBlockContext {
+    /// Writes all blocks to `path` through a temporary file, so an interrupted
+    /// export never leaves a truncated snapshot. Returns the checkpoint it covers.
+    pub fn export_snapshot(&mut self, path: &str) -> Result<Checkpoint, BridgeError> {
+        let checkpoint = self.create_checkpoint();
+        let snapshot = Snapshot {
+            format: SNAPSHOT_FORMAT.to_string(),
+            version: SNAPSHOT_VERSION,
+            node_id: self.keypair.public_key_hex(),
+            checkpoint: checkpoint.clone(),
+            blocks: self.block_log.iter().cloned().collect(),
+        };
+        let json = serde_json::to_vec(&snapshot).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        let tmp = Path::new(path).with_extension("tmp");
+        fs::write(&tmp, json).map_err(|e| BridgeError::Io(e.to_string()))?;
+        fs::rename(&tmp, path).map_err(|e| BridgeError::Io(e.to_string()))?;
+        Ok(checkpoint)
+    }
+
+    /// Merges the blocks of a snapshot into the local tangle. The snapshot is
+    /// refused as a whole if its blocks do not match its checkpoint, any block
+    /// fails verification or any signer is retired. Imported blocks are indexed
+    /// lazily like `load_blocks`.
+    pub fn import_snapshot(&mut self, path: &str) -> Result<SnapshotImport, BridgeError> {
+        let bytes = fs::read(path).map_err(|e| BridgeError::Io(e.to_string()))?;
+        let snapshot: Snapshot = serde_json::from_slice(&bytes).map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+        self.import_snapshot_data(snapshot)
+    }
+
+    pub(crate) fn import_snapshot_data(&mut self, snapshot: Snapshot) -> Result<SnapshotImport, BridgeError> {
+        if snapshot.format != SNAPSHOT_FORMAT || snapshot.version > SNAPSHOT_VERSION {
+            return Err(BridgeError::InvalidInput(format!("Invalid snapshot: format {} version {} is not supported", snapshot.format, snapshot.version)));
+        }
+        if checkpoint_id(snapshot.blocks.iter().map(|b| b.id.as_str())) != snapshot.checkpoint.id {
+            return Err(BridgeError::InvalidInput(format!("Invalid snapshot: blocks do not match checkpoint {}", snapshot.checkpoint.id)));
+        }
+        if let Some(bad) = runtime::find_invalid(&snapshot.blocks, self.config.verification_threads()) {
+            return Err(BridgeError::Crypto(format!("snapshot block {} failed verification", bad.id)));
+        }
+        if let Some(bad) = snapshot.blocks.iter().find(|b| self.retired_keys.contains(&b.public_key)) {
+            return Err(BridgeError::Unauthorized(format!("snapshot block {} is signed by retired key {}", bad.id, bad.public_key)));
+        }
+        let total = snapshot.blocks.len();
+        let already_present = snapshot.blocks.iter().filter(|b| self.block_log.contains(&b.id)).count();
+        let before = self.block_log.len();
+        self.load_blocks(snapshot.blocks);
+        let imported = self.block_log.len() - before;
+        if imported + already_present < total {
+            self.log(LogLevel::Info, format!("{} redacted snapshot blocks skipped", total - imported - already_present));
+        }
+        Ok(SnapshotImport {
+            node_id: snapshot.node_id,
+            checkpoint: snapshot.checkpoint,
+            imported,
+            already_present,
+        })
+    }
+}
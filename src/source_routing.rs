@@ -0,0 +1,235 @@
+//! Directed delivery of records such as commands and diagnostics requests.
+//! The sender computes the cheapest path through the known topology and puts
+//! it in the frame; each relay forwards to the next hop on the path. When no
+//! path is known, or a hop cannot be reached, the message is flooded instead:
+//! every node passes it to all its neighbours until it reaches the destination
+//! or its hop limit.
+
+use std::collections::{HashSet, VecDeque};
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+use crate::logs::LogLevel;
+use crate::multicast::DELIVERY_RECEIPT_KIND;
+use crate::multipath::disjoint_paths;
+use crate::peer_id::PeerId;
+use crate::records::Record;
+use crate::wire::{Frame, FrameKind};
+use crate::{clock, EcoBlockContext};
+
+/// Hops a flooded message may travel.
+pub const FLOOD_HOPS: u8 = 16;
+/// Message ids remembered to drop copies arriving over another path.
+const SEEN_CAPACITY: usize = 4096;
+
+/// Payload of a `Routed` frame.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoutedMessage {
+    pub destination: String,
+    /// Node ids from the sender to the destination; empty when flooding.
+    pub route: Vec<String>,
+    /// Index in `route` of the node the frame is sent to.
+    pub hop: usize,
+    /// Hops left when flooding.
+    pub hops_left: u8,
+    /// After this time relays drop the message and the destination ignores it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<u64>,
+    /// Asks the destination to route a delivery receipt back to the author.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub receipt: bool,
+    pub record: Record,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirectedDelivery {
+    /// The path used, from this node to the destination; `None` when flooded.
+    pub route: Option<Vec<PeerId>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoutingStats {
+    /// Messages this node sent along a computed route.
+    pub routed: u64,
+    /// Messages this node flooded, as sender or after a failed hop.
+    pub flooded: u64,
+    /// Messages passed on for other nodes.
+    pub forwarded: u64,
+    /// Messages received for this node.
+    pub delivered: u64,
+    /// Routes abandoned at this node because the next hop was unreachable.
+    pub fallbacks: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct SourceRouting {
+    seen: HashSet<String>,
+    order: VecDeque<String>,
+    stats: RoutingStats,
+}
+
+impl SourceRouting {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn stats(&self) -> RoutingStats {
+        self.stats
+    }
+
+    /// False if the message was already handled here. Copies of one record
+    /// sent to several destinations are told apart by destination.
+    fn first_sighting(&mut self, record_id: &str, destination: &str) -> bool {
+        let id = format!("{}:{}", record_id, destination);
+        if !self.seen.insert(id.clone()) {
+            return false;
+        }
+        if self.order.len() == SEEN_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.order.push_back(id);
+        true
+    }
+}
+
+impl EcoBlockContext {
+    /// Sends `record` to `destination` along the cheapest known path, or floods
+    /// it when there is none. With a `deadline`, copies not sent by then are
+    /// dropped along the way.
+    pub fn send_directed(&mut self, record: Record, destination: &PeerId, deadline: Option<u64>) -> Result<DirectedDelivery, BridgeError> {
+        self.route_record(record, destination, deadline, false)
+    }
+
+    /// `send_directed`, optionally asking the destination for a receipt.
+    pub(crate) fn route_record(&mut self, record: Record, destination: &PeerId, deadline: Option<u64>, receipt: bool) -> Result<DirectedDelivery, BridgeError> {
+        if !self.sync.is_attached() {
+            return Err(BridgeError::NotFound("Unknown transport: none is attached".to_string()));
+        }
+        let local = self.keypair.public_key_hex();
+        if destination.as_str() == local {
+            return Err(BridgeError::InvalidInput(format!("Invalid destination {}: this node", destination)));
+        }
+        if let Some(deadline) = deadline {
+            if deadline <= clock::now_secs() {
+                return Err(BridgeError::InvalidInput(format!("Invalid deadline {}: already passed", deadline)));
+            }
+            self.deadlines.set(&record.id, deadline);
+        }
+        self.source_routing.first_sighting(&record.id, destination.as_str());
+        let neighbors = |node: &str| self.mesh.get_neighbors(node).unwrap_or_default();
+        let route = disjoint_paths(&local, destination.as_str(), 1, &neighbors).pop();
+        if let Some(route) = route {
+            let message = RoutedMessage {
+                destination: destination.to_string(),
+                route: route.clone(),
+                hop: 1,
+                hops_left: 0,
+                deadline,
+                receipt,
+                record: record.clone(),
+            };
+            if self.send_routed(&message) {
+                self.source_routing.stats.routed += 1;
+                return Ok(DirectedDelivery {
+                    route: Some(route.into_iter().map(PeerId::from_mesh).collect()),
+                });
+            }
+        }
+        self.source_routing.stats.flooded += 1;
+        self.flood(record, destination.as_str(), FLOOD_HOPS, deadline, receipt, None);
+        Ok(DirectedDelivery { route: None })
+    }
+
+    fn send_routed(&mut self, message: &RoutedMessage) -> bool {
+        let Some(next) = message.route.get(message.hop).map(|id| PeerId::from_mesh(id.clone())) else {
+            return false;
+        };
+        let local = PeerId::from_mesh(self.keypair.public_key_hex());
+        if !self.list_peers(&local).contains(&next) {
+            return false;
+        }
+        match serde_json::to_vec(message) {
+            Ok(payload) => self.send_frame(&next, Frame::new(FrameKind::Routed, payload)),
+            Err(_) => false,
+        }
+    }
+
+    fn flood(&mut self, record: Record, destination: &str, hops_left: u8, deadline: Option<u64>, receipt: bool, except: Option<&PeerId>) {
+        let message = RoutedMessage {
+            destination: destination.to_string(),
+            route: Vec::new(),
+            hop: 0,
+            hops_left,
+            deadline,
+            receipt,
+            record,
+        };
+        let Ok(payload) = serde_json::to_vec(&message) else {
+            return;
+        };
+        let local = PeerId::from_mesh(self.keypair.public_key_hex());
+        for peer in self.list_peers(&local) {
+            if Some(&peer) != except {
+                self.send_frame(&peer, Frame::new(FrameKind::Routed, payload.clone()));
+            }
+        }
+    }
+
+    /// Delivers a routed message addressed to this node, or passes it on.
+    /// Records are verified before being forwarded, so relays do not spread
+    /// forged traffic.
+    pub(crate) fn receive_routed(&mut self, from: &PeerId, mut message: RoutedMessage) -> Result<(), BridgeError> {
+        if !self.verify_record(&message.record) {
+            return Err(BridgeError::InvalidInput(format!("Invalid signature on record {}", message.record.id)));
+        }
+        if !self.source_routing.first_sighting(&message.record.id, &message.destination) {
+            return Ok(());
+        }
+        if let Some(deadline) = message.deadline {
+            if clock::now_secs() > deadline {
+                self.log(LogLevel::Info, format!("Dropped record {} for {}: deadline {} passed", message.record.id, message.destination, deadline));
+                return Ok(());
+            }
+            self.deadlines.set(&message.record.id, deadline);
+        }
+        let local = self.keypair.public_key_hex();
+        if message.destination == local {
+            self.source_routing.stats.delivered += 1;
+            if message.record.kind == DELIVERY_RECEIPT_KIND {
+                self.apply_delivery_receipt(&message.record);
+                return Ok(());
+            }
+            let author = PeerId::from_mesh(message.record.author.clone());
+            let record_id = message.record.id.clone();
+            self.receive_record(message.record)?;
+            if message.receipt {
+                self.send_delivery_receipt(&record_id, &author);
+            }
+            return Ok(());
+        }
+        if message.route.is_empty() {
+            if message.hops_left > 1 {
+                self.source_routing.stats.forwarded += 1;
+                self.flood(message.record, &message.destination, message.hops_left - 1, message.deadline, message.receipt, Some(from));
+            }
+            return Ok(());
+        }
+        if message.route.get(message.hop) != Some(&local) {
+            return Err(BridgeError::InvalidInput(format!("Invalid route for record {}: this node is not hop {}", message.record.id, message.hop)));
+        }
+        message.hop += 1;
+        if self.send_routed(&message) {
+            self.source_routing.stats.forwarded += 1;
+            return Ok(());
+        }
+        self.source_routing.stats.fallbacks += 1;
+        self.source_routing.stats.flooded += 1;
+        self.log(
+            LogLevel::Info,
+            format!("Next hop toward {} unreachable, flooding record {}", message.destination, message.record.id),
+        );
+        self.flood(message.record, &message.destination, FLOOD_HOPS, message.deadline, message.receipt, Some(from));
+        Ok(())
+    }
+}
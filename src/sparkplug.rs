@@ -0,0 +1,383 @@
+//! Sparkplug B (Eclipse Tahu) payload decoding for gateways bridging an
+//! industrial MQTT broker. The host's MQTT client passes each message's topic
+//! and payload to `ingest_sparkplug`.
+
+use std::collections::{BTreeMap, HashMap};
+use serde::{Deserialize, Serialize};
+use crate::block_id::BlockId;
+use crate::error::BridgeError;
+use crate::ingest::{sensor_field, ReadingGroups};
+use crate::logs::LogLevel;
+use crate::wire::MAX_PAYLOAD_LEN;
+use crate::{clock, EcoBlockContext};
+
+pub const NAMESPACE: &str = "spBv1.0";
+const BD_SEQ: &str = "bdSeq";
+/// Metric name prefixes for control and configuration, not measurements.
+const CONTROL_PREFIXES: [&str; 3] = ["Node Control/", "Device Control/", "Properties/"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MessageType {
+    NodeBirth,
+    NodeDeath,
+    DeviceBirth,
+    DeviceDeath,
+    NodeData,
+    DeviceData,
+    NodeCommand,
+    DeviceCommand,
+    State,
+}
+
+impl MessageType {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "NBIRTH" => Self::NodeBirth,
+            "NDEATH" => Self::NodeDeath,
+            "DBIRTH" => Self::DeviceBirth,
+            "DDEATH" => Self::DeviceDeath,
+            "NDATA" => Self::NodeData,
+            "DDATA" => Self::DeviceData,
+            "NCMD" => Self::NodeCommand,
+            "DCMD" => Self::DeviceCommand,
+            "STATE" => Self::State,
+            _ => return None,
+        })
+    }
+
+    fn is_device(self) -> bool {
+        matches!(self, Self::DeviceBirth | Self::DeviceDeath | Self::DeviceData | Self::DeviceCommand)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SparkplugDevice {
+    /// `group/edge_node` for edge nodes, `group/edge_node/device` for devices.
+    pub id: String,
+    pub online: bool,
+    /// When the last birth or death certificate was received.
+    pub since: u64,
+    pub last_seen: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SparkplugIngest {
+    pub message_type: MessageType,
+    pub device: String,
+    pub blocks: Vec<BlockId>,
+    /// Metrics without a numeric value, control metrics, and aliases never
+    /// named in a birth certificate.
+    pub skipped: usize,
+    /// Data arrived from a device with no birth on record, or with unknown
+    /// aliases. The host should publish a rebirth command.
+    pub rebirth_needed: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct SparkplugState {
+    devices: BTreeMap<String, SparkplugDevice>,
+    /// Metric names by device and alias, from birth certificates.
+    aliases: HashMap<(String, u64), String>,
+    /// `bdSeq` of each edge node's current session.
+    sessions: HashMap<String, u64>,
+}
+
+impl SparkplugState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn devices(&self) -> Vec<SparkplugDevice> {
+        self.devices.values().cloned().collect()
+    }
+
+    fn set_online(&mut self, id: &str, online: bool, now: u64) {
+        let device = self.devices.entry(id.to_string()).or_insert_with(|| SparkplugDevice {
+            id: id.to_string(),
+            online,
+            since: now,
+            last_seen: now,
+        });
+        device.online = online;
+        device.since = now;
+        device.last_seen = now;
+    }
+
+    fn is_online(&self, id: &str) -> bool {
+        self.devices.get(id).is_some_and(|d| d.online)
+    }
+}
+
+enum Value {
+    Unsigned(u64),
+    Float(f64),
+    Other,
+}
+
+#[derive(Default)]
+struct Metric {
+    name: Option<String>,
+    alias: Option<u64>,
+    timestamp: Option<u64>,
+    datatype: u64,
+    is_null: bool,
+    value: Option<Value>,
+}
+
+impl Metric {
+    /// The value as a number, by Sparkplug datatype. Booleans, strings, bytes,
+    /// date-times, datasets and templates have none.
+    fn number(&self) -> Option<f64> {
+        if self.is_null {
+            return None;
+        }
+        match (self.datatype, self.value.as_ref()?) {
+            (1, Value::Unsigned(v)) => Some(f64::from(*v as u8 as i8)),
+            (2, Value::Unsigned(v)) => Some(f64::from(*v as u16 as i16)),
+            (3, Value::Unsigned(v)) => Some(f64::from(*v as u32 as i32)),
+            (4, Value::Unsigned(v)) => Some(*v as i64 as f64),
+            (5..=8, Value::Unsigned(v)) => Some(*v as f64),
+            (9 | 10, Value::Float(v)) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Payload {
+    timestamp: Option<u64>,
+    metrics: Vec<Metric>,
+}
+
+enum Field<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+    Fixed32(u32),
+}
+
+/// Protobuf wire format reader, enough for the Sparkplug B schema.
+struct Proto<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Proto<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn error(message: &str) -> String {
+        BridgeError::Deserialization(format!("Sparkplug payload {}", message))
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], BridgeError> {
+        let end = self.pos.checked_add(n).filter(|&end| end <= self.bytes.len()).ok_or_else(|| Self::error("truncated"))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn varint(&mut self) -> Result<u64, BridgeError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(Self::error("varint too long"))
+    }
+
+    /// The next field number and value, or `None` at the end.
+    fn field(&mut self) -> Result<Option<(u64, Field<'a>)>, BridgeError> {
+        if self.pos == self.bytes.len() {
+            return Ok(None);
+        }
+        let key = self.varint()?;
+        let field = match key & 7 {
+            0 => Field::Varint(self.varint()?),
+            1 => Field::Fixed64(u64::from_le_bytes(self.take(8)?.try_into().unwrap_or_default())),
+            2 => {
+                let len = usize::try_from(self.varint()?).map_err(|_| Self::error("length out of range"))?;
+                Field::Bytes(self.take(len)?)
+            }
+            5 => Field::Fixed32(u32::from_le_bytes(self.take(4)?.try_into().unwrap_or_default())),
+            _ => return Err(Self::error("unsupported wire type")),
+        };
+        Ok(Some((key >> 3, field)))
+    }
+}
+
+fn decode_metric(bytes: &[u8]) -> Result<Metric, BridgeError> {
+    let mut metric = Metric::default();
+    let mut proto = Proto::new(bytes);
+    while let Some((number, field)) = proto.field()? {
+        match (number, field) {
+            (1, Field::Bytes(b)) => metric.name = Some(String::from_utf8(b.to_vec()).map_err(|_| Proto::error("metric name is not UTF-8"))?),
+            (2, Field::Varint(v)) => metric.alias = Some(v),
+            (3, Field::Varint(v)) => metric.timestamp = Some(v),
+            (4, Field::Varint(v)) => metric.datatype = v,
+            (7, Field::Varint(v)) => metric.is_null = v != 0,
+            (10 | 11, Field::Varint(v)) => metric.value = Some(Value::Unsigned(v)),
+            (12, Field::Fixed32(v)) => metric.value = Some(Value::Float(f64::from(f32::from_bits(v)))),
+            (13, Field::Fixed64(v)) => metric.value = Some(Value::Float(f64::from_bits(v))),
+            (14..=19, _) => metric.value = Some(Value::Other),
+            _ => {}
+        }
+    }
+    Ok(metric)
+}
+
+fn decode_payload(bytes: &[u8]) -> Result<Payload, BridgeError> {
+    let mut payload = Payload::default();
+    let mut proto = Proto::new(bytes);
+    while let Some((number, field)) = proto.field()? {
+        match (number, field) {
+            (1, Field::Varint(v)) => payload.timestamp = Some(v),
+            (2, Field::Bytes(b)) => payload.metrics.push(decode_metric(b)?),
+            _ => {}
+        }
+    }
+    Ok(payload)
+}
+
+/// Splits `spBv1.0/group/TYPE/edge_node[/device]` into the message type and
+/// the node or device id.
+fn parse_topic(topic: &str) -> Result<(MessageType, String), BridgeError> {
+    let parts: Vec<&str> = topic.split('/').collect();
+    if parts.first() != Some(&NAMESPACE) || parts.len() < 3 {
+        return Err(BridgeError::InvalidInput(format!("Invalid Sparkplug topic {:?}", topic)));
+    }
+    if parts[1] == "STATE" {
+        return Ok((MessageType::State, parts[2..].join("/")));
+    }
+    let kind = MessageType::parse(parts[2]).ok_or_else(|| BridgeError::InvalidInput(format!("Invalid Sparkplug message type {:?}", parts[2])))?;
+    let expected = if kind.is_device() { 5 } else { 4 };
+    if parts.len() != expected || parts.iter().any(|p| p.is_empty()) {
+        return Err(BridgeError::InvalidInput(format!("Invalid Sparkplug topic {:?}", topic)));
+    }
+    let mut id = format!("{}/{}", parts[1], parts[3]);
+    if kind.is_device() {
+        id = format!("{}/{}", id, parts[4]);
+    }
+    Ok((kind, id))
+}
+
+impl EcoBlockContext {
+    /// Handles one Sparkplug B message. Births record metric aliases and mark
+    /// the node or device online; deaths mark it offline. Numeric metrics of
+    /// births and data messages are signed as sensor blocks, one per device and
+    /// timestamp, with the device id as `sensor_id`. Commands and host state
+    /// messages are accepted and ignored.
+    pub fn ingest_sparkplug(&mut self, topic: &str, bytes: &[u8]) -> Result<SparkplugIngest, BridgeError> {
+        let (kind, device) = parse_topic(topic)?;
+        let mut ingest = SparkplugIngest {
+            message_type: kind,
+            device: device.clone(),
+            blocks: Vec::new(),
+            skipped: 0,
+            rebirth_needed: false,
+        };
+        if matches!(kind, MessageType::State | MessageType::NodeCommand | MessageType::DeviceCommand) {
+            return Ok(ingest);
+        }
+        if bytes.len() > MAX_PAYLOAD_LEN {
+            return Err(BridgeError::InvalidInput(format!("Invalid Sparkplug payload: {} bytes exceeds {}", bytes.len(), MAX_PAYLOAD_LEN)));
+        }
+        let payload = decode_payload(bytes)?;
+        let now = clock::now_secs();
+        let bd_seq = payload
+            .metrics
+            .iter()
+            .find(|m| m.name.as_deref() == Some(BD_SEQ))
+            .and_then(|m| match m.value {
+                Some(Value::Unsigned(v)) => Some(v),
+                _ => None,
+            });
+        match kind {
+            MessageType::NodeBirth | MessageType::DeviceBirth => {
+                if kind == MessageType::NodeBirth {
+                    // A new session invalidates every alias and device of the node.
+                    let prefix = format!("{}/", device);
+                    self.sparkplug.aliases.retain(|(id, _), _| *id != device && !id.starts_with(&prefix));
+                    for (id, d) in self.sparkplug.devices.iter_mut() {
+                        if id.starts_with(&prefix) {
+                            d.online = false;
+                        }
+                    }
+                    if let Some(seq) = bd_seq {
+                        self.sparkplug.sessions.insert(device.clone(), seq);
+                    }
+                } else {
+                    self.sparkplug.aliases.retain(|(id, _), _| *id != device);
+                }
+                for metric in &payload.metrics {
+                    if let (Some(name), Some(alias)) = (&metric.name, metric.alias) {
+                        self.sparkplug.aliases.insert((device.clone(), alias), name.clone());
+                    }
+                }
+                self.sparkplug.set_online(&device, true, now);
+            }
+            MessageType::NodeDeath => {
+                let current = self.sparkplug.sessions.get(&device).copied();
+                if bd_seq.is_some() && current.is_some() && bd_seq != current {
+                    // The will of an earlier session, delivered late.
+                    return Ok(ingest);
+                }
+                let prefix = format!("{}/", device);
+                let ids: Vec<String> = self
+                    .sparkplug
+                    .devices
+                    .keys()
+                    .filter(|id| **id == device || id.starts_with(&prefix))
+                    .cloned()
+                    .collect();
+                for id in ids {
+                    self.sparkplug.set_online(&id, false, now);
+                }
+                self.sparkplug.set_online(&device, false, now);
+                self.log(LogLevel::Info, format!("Sparkplug node {} went offline", device));
+                return Ok(ingest);
+            }
+            MessageType::DeviceDeath => {
+                self.sparkplug.set_online(&device, false, now);
+                self.log(LogLevel::Info, format!("Sparkplug device {} went offline", device));
+                return Ok(ingest);
+            }
+            _ => {
+                if !self.sparkplug.is_online(&device) {
+                    ingest.rebirth_needed = true;
+                }
+                if let Some(d) = self.sparkplug.devices.get_mut(&device) {
+                    d.last_seen = now;
+                }
+            }
+        }
+        let mut groups = ReadingGroups::new();
+        for metric in &payload.metrics {
+            let name = metric
+                .name
+                .clone()
+                .or_else(|| metric.alias.and_then(|a| self.sparkplug.aliases.get(&(device.clone(), a)).cloned()));
+            let Some(name) = name else {
+                ingest.skipped += 1;
+                ingest.rebirth_needed = true;
+                continue;
+            };
+            let value = metric.number();
+            if name == BD_SEQ || CONTROL_PREFIXES.iter().any(|p| name.starts_with(p)) || value.is_none() {
+                ingest.skipped += 1;
+                continue;
+            }
+            let short = name.rsplit('/').next().unwrap_or(&name);
+            let metric_name = if sensor_field(short, None, 0.0).is_some() { short } else { name.as_str() };
+            let millis = metric.timestamp.or(payload.timestamp);
+            let timestamp = millis.map_or(now, |ms| ms / 1000);
+            groups.insert(Some(device.clone()), timestamp, metric_name, None, value.unwrap_or_default());
+        }
+        ingest.blocks = self.ingest_decoded(&groups.into_readings())?;
+        Ok(ingest)
+    }
+}
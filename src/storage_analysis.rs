@@ -0,0 +1,198 @@
+use std::collections::{BTreeMap, HashMap};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::channels::{ChannelMessageBody, CHANNEL_MESSAGE_KIND};
+use crate::payloads::PayloadStats;
+use crate::EcoBlockContext;
+
+/// Matches further back than DEFLATE's window are not counted.
+const WINDOW: usize = 32 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Encoding {
+    Json,
+    Cbor,
+    /// JSON compressed per channel, as a block log segment would be.
+    CompressedJson,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelStorage {
+    /// `None` for sensor blocks, which belong to no channel.
+    pub channel: Option<String>,
+    pub payloads: usize,
+    pub json_bytes: usize,
+    pub cbor_bytes: usize,
+    /// Estimated; the bridge does not compress payloads itself.
+    pub compressed_bytes: usize,
+    /// `compressed_bytes / json_bytes`, or 1 when there is nothing stored.
+    pub compression_ratio: f64,
+    pub recommended: Encoding,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StorageAnalysis {
+    pub channels: Vec<ChannelStorage>,
+    pub json_bytes: usize,
+    pub cbor_bytes: usize,
+    pub compressed_bytes: usize,
+    /// The encoding that stores all channels in the fewest bytes.
+    pub recommended: Encoding,
+    /// Bytes `recommended` would save over JSON.
+    pub savings_bytes: usize,
+    /// What deduplication already saves on sensor payloads.
+    pub deduplication: PayloadStats,
+}
+
+#[derive(Default)]
+struct Sizes {
+    payloads: usize,
+    json: Vec<u8>,
+    cbor: usize,
+}
+
+impl Sizes {
+    fn add(&mut self, value: &Value) {
+        if let Ok(bytes) = serde_json::to_vec(value) {
+            self.payloads += 1;
+            self.json.extend_from_slice(&bytes);
+            self.cbor += cbor_len(value);
+        }
+    }
+}
+
+fn recommend(json: usize, cbor: usize, compressed: usize) -> Encoding {
+    if compressed < cbor && compressed < json {
+        Encoding::CompressedJson
+    } else if cbor < json {
+        Encoding::Cbor
+    } else {
+        Encoding::Json
+    }
+}
+
+fn cbor_head_len(n: u64) -> usize {
+    match n {
+        0..=23 => 1,
+        24..=0xff => 2,
+        0x100..=0xffff => 3,
+        0x1_0000..=0xffff_ffff => 5,
+        _ => 9,
+    }
+}
+
+/// Size of `value` encoded as CBOR (RFC 8949), with floats in the shortest of
+/// single or double precision that keeps their value.
+pub fn cbor_len(value: &Value) -> usize {
+    match value {
+        Value::Null | Value::Bool(_) => 1,
+        Value::Number(n) => {
+            if let Some(u) = n.as_u64() {
+                cbor_head_len(u)
+            } else if let Some(i) = n.as_i64() {
+                cbor_head_len(!(i as u64))
+            } else {
+                let f = n.as_f64().unwrap_or_default();
+                if (f as f32) as f64 == f {
+                    5
+                } else {
+                    9
+                }
+            }
+        }
+        Value::String(s) => cbor_head_len(s.len() as u64) + s.len(),
+        Value::Array(items) => cbor_head_len(items.len() as u64) + items.iter().map(cbor_len).sum::<usize>(),
+        Value::Object(fields) => {
+            cbor_head_len(fields.len() as u64)
+                + fields
+                    .iter()
+                    .map(|(k, v)| cbor_head_len(k.len() as u64) + k.len() + cbor_len(v))
+                    .sum::<usize>()
+        }
+    }
+}
+
+/// Approximate DEFLATE output size: greedy LZ77 matching, costing a literal
+/// at 9 bits and a match at 24.
+pub fn estimate_compressed_len(bytes: &[u8]) -> usize {
+    let mut last_seen: HashMap<[u8; MIN_MATCH], usize> = HashMap::new();
+    let mut bits = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let mut len = 0;
+        if i + MIN_MATCH <= bytes.len() {
+            let key = [bytes[i], bytes[i + 1], bytes[i + 2]];
+            if let Some(&j) = last_seen.get(&key).filter(|&&j| i - j <= WINDOW) {
+                len = bytes[i..].iter().zip(&bytes[j..]).take(MAX_MATCH).take_while(|(a, b)| a == b).count();
+            }
+            last_seen.insert(key, i);
+        }
+        if len >= MIN_MATCH {
+            for k in i + 1..(i + len).min(bytes.len() + 1 - MIN_MATCH) {
+                last_seen.insert([bytes[k], bytes[k + 1], bytes[k + 2]], k);
+            }
+            bits += 24;
+            i += len;
+        } else {
+            bits += 9;
+            i += 1;
+        }
+    }
+    bits.div_ceil(8)
+}
+
+impl EcoBlockContext {
+    /// Payload sizes per channel under each encoding, and which would suit
+    /// this node's data best.
+    pub fn storage_analysis(&self) -> StorageAnalysis {
+        let mut groups: BTreeMap<Option<String>, Sizes> = BTreeMap::new();
+        for block in self.block_log.iter() {
+            if let Ok(value) = serde_json::to_value(&block.data.data) {
+                groups.entry(None).or_default().add(&value);
+            }
+        }
+        for record in self.records.of_kind(CHANNEL_MESSAGE_KIND) {
+            if let Ok(body) = serde_json::from_value::<ChannelMessageBody>(record.body.clone()) {
+                if let Ok(value) = serde_json::to_value(&body.payload) {
+                    groups.entry(Some(body.channel)).or_default().add(&value);
+                }
+            }
+        }
+        let channels: Vec<ChannelStorage> = groups
+            .into_iter()
+            .map(|(channel, sizes)| {
+                let json_bytes = sizes.json.len();
+                let compressed_bytes = estimate_compressed_len(&sizes.json);
+                ChannelStorage {
+                    channel,
+                    payloads: sizes.payloads,
+                    json_bytes,
+                    cbor_bytes: sizes.cbor,
+                    compressed_bytes,
+                    compression_ratio: if json_bytes == 0 { 1.0 } else { compressed_bytes as f64 / json_bytes as f64 },
+                    recommended: recommend(json_bytes, sizes.cbor, compressed_bytes),
+                }
+            })
+            .collect();
+        let json_bytes = channels.iter().map(|c| c.json_bytes).sum();
+        let cbor_bytes = channels.iter().map(|c| c.cbor_bytes).sum();
+        let compressed_bytes = channels.iter().map(|c| c.compressed_bytes).sum();
+        let recommended = recommend(json_bytes, cbor_bytes, compressed_bytes);
+        let best = match recommended {
+            Encoding::Json => json_bytes,
+            Encoding::Cbor => cbor_bytes,
+            Encoding::CompressedJson => compressed_bytes,
+        };
+        StorageAnalysis {
+            channels,
+            json_bytes,
+            cbor_bytes,
+            compressed_bytes,
+            recommended,
+            savings_bytes: json_bytes - best,
+            deduplication: self.payloads.stats(),
+        }
+    }
+}
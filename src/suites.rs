@@ -0,0 +1,141 @@
+use std::collections::{BTreeMap, HashMap};
+use blake2::digest::consts::U32;
+use blake2::Blake2b;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::energy;
+use crate::error::BridgeError;
+use crate::records::Record;
+use crate::EcoBlockContext;
+
+pub const DEFAULT_NETWORK_ID: &str = "default";
+
+/// Signature and hash algorithms used for record ids, signatures and frames.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Suite {
+    /// The original format; records without a suite field use it.
+    #[default]
+    Ed25519Sha256,
+    Ed25519Blake2b,
+}
+
+impl Suite {
+    pub const ALL: [Suite; 2] = [Suite::Ed25519Sha256, Suite::Ed25519Blake2b];
+
+    pub fn id(self) -> u8 {
+        match self {
+            Suite::Ed25519Sha256 => 1,
+            Suite::Ed25519Blake2b => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.id() == id)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Suite::Ed25519Sha256 => "ed25519-sha256",
+            Suite::Ed25519Blake2b => "ed25519-blake2b",
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == Suite::default()
+    }
+
+    /// 32-byte content digest.
+    pub fn digest(self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            Suite::Ed25519Sha256 => Sha256::digest(bytes).to_vec(),
+            Suite::Ed25519Blake2b => Blake2b::<U32>::digest(bytes).to_vec(),
+        }
+    }
+}
+
+/// Suites accepted on each network id, in order of preference, and the suites
+/// each peer announced.
+#[derive(Debug)]
+pub struct SuiteRegistry {
+    network_id: String,
+    by_network: BTreeMap<String, Vec<Suite>>,
+    peers: HashMap<String, Vec<Suite>>,
+}
+
+impl Default for SuiteRegistry {
+    fn default() -> Self {
+        Self {
+            network_id: DEFAULT_NETWORK_ID.to_string(),
+            by_network: BTreeMap::new(),
+            peers: HashMap::new(),
+        }
+    }
+}
+
+impl SuiteRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn network_id(&self) -> &str {
+        &self.network_id
+    }
+
+    pub fn set_network_id(&mut self, network_id: &str) {
+        self.network_id = network_id.to_string();
+    }
+
+    pub fn set_network_suites(&mut self, network_id: &str, suites: Vec<Suite>) -> Result<(), BridgeError> {
+        if suites.is_empty() {
+            return Err(BridgeError::Crypto(format!("network {} needs at least one suite", network_id)));
+        }
+        let mut unique = Vec::new();
+        for suite in suites {
+            if !unique.contains(&suite) {
+                unique.push(suite);
+            }
+        }
+        self.by_network.insert(network_id.to_string(), unique);
+        Ok(())
+    }
+
+    /// Suites accepted on the current network. Networks never configured accept
+    /// only the default suite.
+    pub fn accepted(&self) -> Vec<Suite> {
+        self.by_network.get(&self.network_id).cloned().unwrap_or_else(|| vec![Suite::default()])
+    }
+
+    pub fn accepts(&self, suite: Suite) -> bool {
+        self.accepted().contains(&suite)
+    }
+
+    /// Suite used for records signed locally.
+    pub fn preferred(&self) -> Suite {
+        self.accepted()[0]
+    }
+
+    pub fn record_peer(&mut self, peer: &str, suites: Vec<Suite>) {
+        self.peers.insert(peer.to_string(), suites);
+    }
+
+    /// First locally accepted suite the peer also supports. Peers that never
+    /// announced suites are assumed to support only the default one.
+    pub fn negotiate(&self, peer: &str) -> Option<Suite> {
+        let theirs = self.peers.get(peer).cloned().unwrap_or_else(|| vec![Suite::default()]);
+        self.accepted().into_iter().find(|s| theirs.contains(s))
+    }
+}
+
+impl EcoBlockContext {
+    /// Signs with the preferred suite, adding a post-quantum signature when a
+    /// hybrid signer is loaded. Fails when that signer cannot sign any more.
+    pub(crate) fn sign_record(&self, kind: &str, body: serde_json::Value, timestamp: u64) -> Result<Record, BridgeError> {
+        #[allow(unused_mut)]
+        let mut record = Record::sign_with(self.suites.preferred(), self.encoding.sign, kind, body, &self.keypair, timestamp);
+        energy::count_signature();
+        #[cfg(feature = "pq-hybrid")]
+        self.attach_pq_signature(&mut record)?;
+        Ok(record)
+    }
+}
@@ -0,0 +1,557 @@
+use ecoblock_storage::tangle::block::TangleBlock;
+use serde::{Deserialize, Serialize};
+use crate::block_id::BlockId;
+use crate::channels::{ChannelMessageBody, CHANNEL_MESSAGE_KIND};
+use crate::energy;
+use crate::error::BridgeError;
+use crate::events::BridgeEvent;
+use crate::have_lists::MAX_HAVE_IDS;
+use crate::logs::LogLevel;
+use crate::peer_id::PeerId;
+use crate::pow::ANNOUNCEMENT_KIND;
+use crate::power::PowerMode;
+use crate::records::Record;
+use crate::relay::RelayRole;
+use crate::source_routing::RoutedMessage;
+use crate::transport::Transport;
+use crate::watchdog;
+use crate::wire::{self, BlockEnvelope, Frame, FrameKind};
+use crate::{clock, EcoBlockContext};
+
+/// Frames handled per `run_background_step`.
+pub const FRAMES_PER_STEP: usize = 64;
+
+/// Payload of a `Sync` frame.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SyncMessage {
+    /// Ids of the sender's most recent blocks. The receiver asks for those it
+    /// lacks and, unless this is a `reply`, answers with its own summary.
+    Summary { ids: Vec<String>, reply: bool },
+    /// Blocks the sender is missing, answered with a `Block` frame each.
+    Want { ids: Vec<String> },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncStats {
+    pub frames_received: u64,
+    pub frames_sent: u64,
+    /// New blocks stored from peers.
+    pub blocks_received: u64,
+    /// Frames refused: malformed, badly signed, or from unknown or retired keys.
+    pub rejected: u64,
+    pub send_errors: u64,
+}
+
+#[derive(Default)]
+pub struct SyncState {
+    transport: Option<Box<dyn Transport>>,
+    stats: SyncStats,
+}
+
+impl SyncState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_attached(&self) -> bool {
+        self.transport.is_some()
+    }
+
+    pub fn stats(&self) -> SyncStats {
+        self.stats
+    }
+
+    pub fn transport_name(&self) -> Option<String> {
+        self.transport.as_ref().map(|t| t.name().to_string())
+    }
+}
+
+impl EcoBlockContext {
+    /// Replaces the transport blocks are gossiped and synced over. The
+    /// watchdog reports it degraded once nothing polls it for
+    /// `TRANSPORT_TIMEOUT` seconds.
+    pub fn attach_transport(&mut self, transport: Box<dyn Transport>) {
+        self.sync.transport = Some(transport);
+        self.watchdog.watch(watchdog::TRANSPORT, watchdog::TRANSPORT_TIMEOUT, None, clock::now_secs());
+    }
+
+    pub fn detach_transport(&mut self) -> bool {
+        self.watchdog.unwatch(watchdog::TRANSPORT);
+        self.sync.transport.take().is_some()
+    }
+
+    pub fn set_peer_address(&mut self, peer: &PeerId, address: &str) -> Result<(), BridgeError> {
+        let transport = self.sync.transport.as_mut().ok_or_else(|| BridgeError::NotFound("Unknown transport: none is attached".to_string()))?;
+        transport.set_peer_address(peer, address)
+    }
+
+    /// Sends `frame` to `peer`. False if it could not be sent or queued.
+    pub(crate) fn send_frame(&mut self, peer: &PeerId, frame: Frame) -> bool {
+        if !self.sync.is_attached() {
+            return false;
+        }
+        let tracked = self.deadlines.tracked(&frame);
+        if let Some((id, deadline)) = tracked.clone().filter(|(_, deadline)| clock::now_secs() > *deadline) {
+            self.delivery_expired(peer, id, deadline);
+            return false;
+        }
+        // Frames sent during quiet hours wait for the window to end.
+        let Some(frame) = self.quiet_hours.hold(peer, frame, clock::now_secs()) else {
+            return true;
+        };
+        let bytes = match frame.encode() {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                self.sync.stats.send_errors += 1;
+                self.log(LogLevel::Warn, format!("Frame to {} not sent: {}", peer, e));
+                return false;
+            }
+        };
+        // Frames that would overrun the radio's duty cycle wait for airtime.
+        let Some(transport) = self.sync.transport.as_ref() else {
+            return false;
+        };
+        let Some(frame) = self.airtime.admit(transport.name(), peer, frame, bytes.len(), clock::now_millis()) else {
+            return true;
+        };
+        // Frames beyond the budget of a data-mule encounter are dropped.
+        if !self.data_mule.admit_send(peer, bytes.len(), frame.kind == FrameKind::Block, clock::now_secs()) {
+            return false;
+        }
+        let Some(transport) = self.sync.transport.as_mut() else {
+            return false;
+        };
+        match transport.send(peer, &bytes) {
+            Ok(()) => {
+                self.sync.stats.frames_sent += 1;
+                self.flow_stats.frame_sent(peer, bytes.len());
+                energy::count_radio_sent(bytes.len());
+                if tracked.is_some() {
+                    self.delivered_on_time(peer);
+                }
+                true
+            }
+            Err(e) => {
+                self.sync.stats.send_errors += 1;
+                self.log(LogLevel::Warn, format!("Frame to {} not sent: {}", peer, e));
+                false
+            }
+        }
+    }
+
+    fn send_sync(&mut self, peer: &PeerId, message: &SyncMessage) {
+        if let Ok(payload) = serde_json::to_vec(message) {
+            self.send_frame(peer, Frame::new(FrameKind::Sync, payload));
+        }
+    }
+
+    pub(crate) fn send_block(&mut self, peer: &PeerId, block: &TangleBlock) {
+        if let Ok(payload) = serde_json::to_vec(&self.sensor_envelope(block)) {
+            if self.send_frame(peer, Frame::new(FrameKind::Block, payload)) {
+                self.flow_stats.peer(peer).blocks_sent += 1;
+            }
+        }
+    }
+
+    /// Pushes a signed record, such as an alert, to every direct neighbour,
+    /// highest group priority first. Channel messages skip neighbours whose
+    /// groups do not take the channel.
+    pub(crate) fn gossip_record(&mut self, record: &Record) {
+        if !self.sync.is_attached() {
+            return;
+        }
+        let Ok(payload) = serde_json::to_vec(&BlockEnvelope::Record(record.clone())) else {
+            return;
+        };
+        let channel = if record.kind == CHANNEL_MESSAGE_KIND {
+            serde_json::from_value::<ChannelMessageBody>(record.body.clone()).ok().map(|body| body.channel)
+        } else {
+            None
+        };
+        let local = PeerId::from_mesh(self.keypair.public_key_hex());
+        for peer in self.by_priority(self.list_peers(&local)) {
+            if channel.as_ref().is_some_and(|c| !self.pushes_channel(&peer, c)) {
+                continue;
+            }
+            self.send_frame(&peer, Frame::new(FrameKind::Block, payload.clone()));
+        }
+    }
+
+    /// Pushes `block` to every direct neighbour that has not advertised it,
+    /// except the one it came from, and within the peer's declared geofence.
+    /// Leaves of a relay election only push their own blocks. Peers outside
+    /// their wake window get the block when it opens. Nothing is pushed in
+    /// `PowerMode::Critical`. Peers of higher group priority go first.
+    pub(crate) fn gossip_block(&mut self, block: &TangleBlock, except: Option<&PeerId>) {
+        if !self.sync.is_attached() || self.power.mode() == PowerMode::Critical {
+            return;
+        }
+        if except.is_some() && self.relay_role() == RelayRole::Leaf {
+            return;
+        }
+        let local = PeerId::from_mesh(self.keypair.public_key_hex());
+        let id = BlockId::from_storage(block.id.clone());
+        for peer in self.by_priority(self.list_peers(&local)) {
+            if Some(&peer) == except || self.blocks_to_send(&peer, vec![id.clone()]).is_empty() {
+                continue;
+            }
+            if self.defer_until_awake(&peer, &block.id) {
+                continue;
+            }
+            self.send_block(&peer, block);
+        }
+    }
+
+    pub(crate) fn summary_ids(&self) -> Vec<String> {
+        self.block_log.iter().rev().take(MAX_HAVE_IDS).map(|b| b.id.clone()).collect()
+    }
+
+    /// This node's latest announcement, made now if there is none.
+    pub(crate) fn own_announcement(&mut self) -> Result<Record, BridgeError> {
+        let local = self.keypair.public_key_hex();
+        let announcement = self
+            .records
+            .iter()
+            .rev()
+            .find(|r| r.kind == ANNOUNCEMENT_KIND && r.author == local)
+            .cloned();
+        match announcement {
+            Some(record) => Ok(record),
+            None => self.announce(),
+        }
+    }
+
+    /// Starts anti-entropy with `peer`: sends this node's announcement, so the
+    /// peer accepts its blocks, and a summary of its most recent blocks.
+    pub fn sync_with_peer(&mut self, peer: &PeerId) -> Result<(), BridgeError> {
+        if !self.sync.is_attached() {
+            return Err(BridgeError::NotFound("Unknown transport: none is attached".to_string()));
+        }
+        let announcement = self.own_announcement()?;
+        let payload = serde_json::to_vec(&announcement).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+        self.send_frame(peer, Frame::new(FrameKind::Handshake, payload));
+        let ids = self.summary_ids();
+        self.send_sync(peer, &SyncMessage::Summary { ids, reply: false });
+        self.flow_stats.peer(peer).last_sync = Some(clock::now_secs());
+        Ok(())
+    }
+
+    /// Verifies and stores a block received from a peer, indexes its reading
+    /// and gossips it on. Parents not held locally are requested from `from`.
+    /// Returns false for blocks already held or redacted.
+    pub fn receive_block(&mut self, block: TangleBlock, from: Option<&PeerId>) -> Result<bool, BridgeError> {
+        if self.block_log.contains(&block.id) {
+            return Ok(false);
+        }
+        if !self.verify_block_envelope(&BlockEnvelope::Sensor(block.clone())) {
+            return Err(BridgeError::Crypto(format!("invalid signature on block {}", block.id)));
+        }
+        self.accept_block(block, from)
+    }
+
+    /// `receive_block` for a block whose signatures were already checked, as
+    /// those of block frames and blocks released from probation are.
+    pub(crate) fn accept_block(&mut self, block: TangleBlock, from: Option<&PeerId>) -> Result<bool, BridgeError> {
+        if self.chaos.as_mut().is_some_and(|c| c.drop_message()) || self.block_log.contains(&block.id) {
+            return Ok(false);
+        }
+        if self.retired_keys.contains(&block.public_key) {
+            return Err(BridgeError::Unauthorized(format!("key {} has been retired", block.public_key)));
+        }
+        if !self.is_known_author(&block.public_key) {
+            return Err(BridgeError::Unauthorized(format!("unknown key {} has not announced itself with proof of work", block.public_key)));
+        }
+        // Held blocks are neither stored, indexed nor gossiped until their key
+        // is endorsed.
+        if self.probation.should_hold(&block.public_key, &self.keypair.public_key_hex()) {
+            self.probation.hold_block(block);
+            return Ok(false);
+        }
+        let missing: Vec<String> = block.data.parents.iter().filter(|p| !self.block_log.contains(p)).cloned().collect();
+        if !self.store_block(block.clone()) {
+            return Ok(false);
+        }
+        self.sync.stats.blocks_received += 1;
+        if let Some(peer) = from {
+            self.flow_stats.peer(peer).blocks_received += 1;
+        }
+        let payload = serde_json::to_value(&block.data.data).unwrap_or_default();
+        self.index_reading(&block.id, &block.public_key, &payload);
+        if from.is_some_and(|peer| self.data_mule.record_block_received(peer)) {
+            self.record_delivery_latency(&block.id, clock::now_secs());
+        }
+        let event = BridgeEvent::BlockReceived {
+            id: BlockId::from_storage(block.id.clone()),
+            from: from.cloned(),
+        };
+        self.emit_event(event.clone());
+        self.emit_geofenced(&event);
+        self.gossip_block(&block, from);
+        if let Some(peer) = from.filter(|_| !missing.is_empty()) {
+            self.send_sync(peer, &SyncMessage::Want { ids: missing });
+        }
+        Ok(true)
+    }
+
+    /// Handles one encoded frame from `from`.
+    pub fn receive_frame(&mut self, from: &PeerId, bytes: &[u8]) -> Result<(), BridgeError> {
+        self.flow_stats.frame_received(from, bytes.len(), clock::now_secs());
+        let result = self.handle_frame(from, bytes);
+        if result.is_err() {
+            self.flow_stats.peer(from).invalid += 1;
+        }
+        result
+    }
+
+    fn handle_frame(&mut self, from: &PeerId, bytes: &[u8]) -> Result<(), BridgeError> {
+        self.data_mule.record_received(from, bytes.len());
+        energy::count_radio_received(bytes.len());
+        let frame = wire::parse_frame(bytes)?;
+        if !self.suites.accepts(frame.suite) {
+            return Err(BridgeError::Crypto(format!("frame uses suite {}, not accepted on network {}", frame.suite.name(), self.suites.network_id())));
+        }
+        match frame.kind {
+            FrameKind::Block => {
+                let envelope = wire::parse_block_envelope(&frame.payload)?;
+                // Applies the hybrid signature policy as well as the classic check.
+                if !self.verify_block_envelope(&envelope) {
+                    return Err(BridgeError::Crypto("block envelope failed verification".to_string()));
+                }
+                match envelope {
+                    BlockEnvelope::Sensor(block) => self.accept_block(block, Some(from)).map(|_| ()),
+                    BlockEnvelope::CanonicalSensor(canonical) => self.accept_signed_block(canonical.block, Some(canonical.signature), Some(from)).map(|_| ()),
+                    BlockEnvelope::HybridSensor(hybrid) => self.accept_signed_block(hybrid.block, hybrid.signature, Some(from)).map(|_| ()),
+                    BlockEnvelope::Record(record) => self.receive_record(record).map(|_| ()),
+                }
+            }
+            FrameKind::Handshake => {
+                let handshake = wire::parse_handshake(&frame.payload)?;
+                self.receive_record(handshake.record).map(|_| ())
+            }
+            FrameKind::Sync => {
+                let message: SyncMessage = serde_json::from_slice(&frame.payload).map_err(|e| BridgeError::Wire(e.to_string()))?;
+                self.handle_sync(from, message);
+                Ok(())
+            }
+            FrameKind::Routed => {
+                let message: RoutedMessage = serde_json::from_slice(&frame.payload).map_err(|e| BridgeError::Wire(e.to_string()))?;
+                self.receive_routed(from, message)
+            }
+            FrameKind::LogStream => Err(BridgeError::Wire("log stream frames are opened with open_log_stream_frame".to_string())),
+        }
+    }
+
+    fn handle_sync(&mut self, from: &PeerId, message: SyncMessage) {
+        match message {
+            SyncMessage::Summary { ids, reply } => {
+                let missing: Vec<String> = ids.iter().filter(|id| !self.block_log.contains(id)).take(MAX_HAVE_IDS).cloned().collect();
+                self.have_lists.receive(from.as_str(), ids, clock::now_secs());
+                self.flow_stats.peer(from).last_sync = Some(clock::now_secs());
+                if !missing.is_empty() {
+                    self.send_sync(from, &SyncMessage::Want { ids: missing });
+                }
+                if !reply {
+                    let ids = self.summary_ids();
+                    self.send_sync(from, &SyncMessage::Summary { ids, reply: true });
+                }
+            }
+            SyncMessage::Want { ids } => {
+                for id in ids.iter().take(MAX_HAVE_IDS) {
+                    if let Some(block) = self.block_log.get(id).cloned() {
+                        self.send_block(from, &block);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handles up to `max_frames` waiting frames and returns how many there were.
+    /// Refused frames are counted and logged, not returned as errors.
+    pub fn poll_transport(&mut self, max_frames: usize) -> usize {
+        if self.sync.is_attached() {
+            self.component_heartbeat(watchdog::TRANSPORT);
+        }
+        let mut handled = 0;
+        while handled < max_frames {
+            let Some(transport) = self.sync.transport.as_mut() else {
+                break;
+            };
+            let (peer, bytes) = match transport.receive() {
+                Ok(Some(frame)) => frame,
+                Ok(None) => break,
+                Err(e) => {
+                    self.log(LogLevel::Error, format!("Transport receive failed: {}", e));
+                    break;
+                }
+            };
+            handled += 1;
+            self.sync.stats.frames_received += 1;
+            if let Err(e) = self.receive_frame(&peer, &bytes) {
+                self.sync.stats.rejected += 1;
+                self.log(LogLevel::Warn, format!("Frame from {} rejected: {}", peer, e));
+                self.notify_rejection(&peer, &bytes, &e);
+            }
+        }
+        handled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use ecoblock_core::domain::SensorData;
+    use crate::probation::ProbationPolicy;
+    use super::*;
+
+    type Sent = Arc<Mutex<Vec<(PeerId, Vec<u8>)>>>;
+
+    /// Keeps sent frames for the test to hand to the receiving context.
+    struct Outbox(Sent);
+
+    impl Transport for Outbox {
+        fn send(&mut self, peer: &PeerId, frame: &[u8]) -> Result<(), BridgeError> {
+            self.0.lock().unwrap().push((peer.clone(), frame.to_vec()));
+            Ok(())
+        }
+
+        fn receive(&mut self) -> Result<Option<(PeerId, Vec<u8>)>, BridgeError> {
+            Ok(None)
+        }
+
+        fn set_peer_address(&mut self, _peer: &PeerId, _address: &str) -> Result<(), BridgeError> {
+            Ok(())
+        }
+    }
+
+    struct Node {
+        ctx: EcoBlockContext,
+        id: PeerId,
+        sent: Sent,
+    }
+
+    fn node() -> Node {
+        let mut ctx = EcoBlockContext::new();
+        let sent = Sent::default();
+        ctx.attach_transport(Box::new(Outbox(sent.clone())));
+        let id = PeerId::from_mesh(ctx.keypair.public_key_hex());
+        Node { ctx, id, sent }
+    }
+
+    fn create_block(node: &mut Node, i: u64) -> TangleBlock {
+        let data = SensorData {
+            pm25: 12.0,
+            co2: 410.0,
+            temperature: 21.0,
+            humidity: 45.0,
+            timestamp: 1_700_000_000 + i,
+        };
+        let id = node.ctx.try_create_block(serde_json::to_vec(&data).unwrap(), Vec::new()).unwrap();
+        node.ctx.block_log.get(id.as_str()).cloned().unwrap()
+    }
+
+    fn block_frame(block: TangleBlock) -> Vec<u8> {
+        let payload = serde_json::to_vec(&BlockEnvelope::Sensor(block)).unwrap();
+        Frame::new(FrameKind::Block, payload).encode().unwrap()
+    }
+
+    /// Hands frames between `a` and `b` until neither has more to send.
+    fn exchange(a: &mut Node, b: &mut Node) {
+        loop {
+            let to_b: Vec<_> = a.sent.lock().unwrap().drain(..).collect();
+            let to_a: Vec<_> = b.sent.lock().unwrap().drain(..).collect();
+            if to_b.is_empty() && to_a.is_empty() {
+                return;
+            }
+            for (peer, bytes) in to_b {
+                assert_eq!(peer, b.id);
+                b.ctx.receive_frame(&a.id, &bytes).unwrap();
+            }
+            for (peer, bytes) in to_a {
+                assert_eq!(peer, a.id);
+                a.ctx.receive_frame(&b.id, &bytes).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn summary_and_want_bring_a_new_peer_up_to_date() {
+        let (mut a, mut b) = (node(), node());
+        let blocks: Vec<TangleBlock> = (0..3).map(|i| create_block(&mut a, i)).collect();
+        let held = create_block(&mut b, 10);
+
+        a.ctx.sync_with_peer(&b.id).unwrap();
+        exchange(&mut a, &mut b);
+
+        for block in &blocks {
+            assert!(b.ctx.block_log.contains(&block.id), "block {} not synced", block.id);
+        }
+        assert!(a.ctx.block_log.contains(&held.id));
+        assert_eq!(b.ctx.sync.stats().blocks_received, 3);
+        assert_eq!(a.ctx.sync.stats().blocks_received, 1);
+    }
+
+    #[test]
+    fn blocks_with_bad_signatures_are_rejected() {
+        let (mut a, mut b) = (node(), node());
+        let mut block = create_block(&mut a, 0);
+        block.data.data.co2 += 1.0;
+
+        let error = b.ctx.receive_frame(&a.id, &block_frame(block.clone())).unwrap_err();
+        assert!(matches!(error, BridgeError::Crypto(_)), "{}", error);
+        assert!(b.ctx.receive_block(block.clone(), None).is_err());
+        assert!(!b.ctx.block_log.contains(&block.id));
+    }
+
+    #[test]
+    fn blocks_from_retired_keys_are_rejected() {
+        let (mut a, mut b) = (node(), node());
+        let block = create_block(&mut a, 0);
+        b.ctx.retired_keys.insert(block.public_key.clone());
+
+        let error = b.ctx.receive_frame(&a.id, &block_frame(block.clone())).unwrap_err();
+        assert!(matches!(error, BridgeError::Unauthorized(_)), "{}", error);
+        assert!(b.ctx.receive_block(block.clone(), None).is_err());
+        assert!(!b.ctx.block_log.contains(&block.id));
+    }
+
+    #[test]
+    fn blocks_from_unendorsed_keys_wait_for_an_endorsement() {
+        let (mut a, mut b) = (node(), node());
+        let block = create_block(&mut a, 0);
+        let trusted = b.ctx.keypair.public_key_hex();
+        b.ctx.probation.set_policy(ProbationPolicy {
+            enabled: true,
+            required_endorsements: 1,
+            trusted_keys: vec![trusted],
+        });
+
+        b.ctx.receive_frame(&a.id, &block_frame(block.clone())).unwrap();
+        assert!(!b.ctx.block_log.contains(&block.id));
+        assert!(!b.ctx.readings.contains(&block.id));
+        let held: Vec<String> = b.ctx.probation.held_blocks().into_iter().map(|b| b.id).collect();
+        assert_eq!(held, vec![block.id.clone()]);
+
+        b.ctx.endorse_key(&block.public_key).unwrap();
+        assert!(b.ctx.block_log.contains(&block.id));
+        assert!(b.ctx.readings.contains(&block.id));
+        assert!(b.ctx.probation.held_blocks().is_empty());
+    }
+
+    #[test]
+    fn published_inventories_reach_the_network_inventory_of_neighbours() {
+        let (mut a, mut b) = (node(), node());
+        create_block(&mut a, 0);
+        a.ctx.add_peer_connection(&a.id, &b.id, 1.0);
+        b.ctx.add_peer_connection(&b.id, &a.id, 1.0);
+        exchange(&mut a, &mut b);
+
+        let published = a.ctx.publish_inventory().unwrap();
+        exchange(&mut a, &mut b);
+
+        assert!(b.ctx.records.get(&published.id).is_some());
+        let network = b.ctx.network_inventory(u64::MAX);
+        let remote = network.nodes.iter().find(|n| n.node_id == a.id.as_str()).expect("inventory of a not received");
+        assert_eq!(remote.sensors, a.ctx.local_inventory().sensors);
+    }
+}
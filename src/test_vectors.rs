@@ -0,0 +1,168 @@
+use ecoblock_core::domain::tangle_data::TangleBlockData;
+use ecoblock_core::domain::SensorData;
+use ecoblock_crypto::keys::keypair::CryptoKeypair;
+use ecoblock_storage::tangle::block::TangleBlock;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use crate::block_id::BlockId;
+use crate::annotations::{self, ANNOTATION_KIND};
+use crate::error::BridgeError;
+use crate::pow::{self, AnnouncementBody, ANNOUNCEMENT_KIND};
+use crate::probation::{EndorsementBody, ENDORSEMENT_KIND};
+use crate::records::Record;
+
+/// Fixed timestamp used by every vector, so output depends on the seed only.
+pub const VECTOR_TIMESTAMP: u64 = 1_700_000_000;
+/// Kept low so vectors stay cheap to regenerate on constrained targets.
+pub const VECTOR_DIFFICULTY: u8 = 8;
+const MESSAGE: &[u8] = b"ecoblock test vector";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyVector {
+    pub secret_key_hex: String,
+    pub public_key_hex: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignatureVector {
+    pub public_key_hex: String,
+    pub message_hex: String,
+    pub signature_hex: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockVector {
+    pub sensor_data_json: String,
+    pub parents: Vec<String>,
+    pub block_json: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnvelopeVector {
+    /// Exact JSON the signature covers: `[kind, author, timestamp, body]`.
+    pub signing_bytes_hex: String,
+    pub record: Record,
+    pub record_json: String,
+}
+
+/// Join exchange between two nodes: both announce with proof of work, then the
+/// responder endorses the initiator.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HandshakeTranscript {
+    pub difficulty: u8,
+    pub messages: Vec<EnvelopeVector>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestVectors {
+    pub seed: u64,
+    pub keys: Vec<KeyVector>,
+    pub signatures: Vec<SignatureVector>,
+    pub blocks: Vec<BlockVector>,
+    pub envelopes: Vec<EnvelopeVector>,
+    pub handshake: HandshakeTranscript,
+}
+
+fn derive_keypair(seed: u64, index: u8) -> Result<CryptoKeypair, BridgeError> {
+    let mut hasher = Sha256::new();
+    hasher.update(b"ecoblock-test-vectors");
+    hasher.update(seed.to_be_bytes());
+    hasher.update([index]);
+    let secret: [u8; 32] = hasher.finalize().into();
+    CryptoKeypair::from_bytes(&secret).map_err(|e| BridgeError::Crypto(format!("{:?}", e)))
+}
+
+fn envelope(kind: &str, body: serde_json::Value, keypair: &CryptoKeypair) -> Result<EnvelopeVector, BridgeError> {
+    let record = Record::sign(kind, body, keypair, VECTOR_TIMESTAMP);
+    let signing_bytes = serde_json::to_vec(&(&record.kind, &record.author, record.timestamp, &record.body))
+        .map_err(|e| BridgeError::Serialization(e.to_string()))?;
+    let record_json = serde_json::to_string(&record).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+    Ok(EnvelopeVector {
+        signing_bytes_hex: hex::encode(signing_bytes),
+        record,
+        record_json,
+    })
+}
+
+fn announcement(keypair: &CryptoKeypair) -> Result<EnvelopeVector, BridgeError> {
+    let node_id = keypair.public_key_hex();
+    let body = AnnouncementBody {
+        nonce: pow::solve(&node_id, VECTOR_DIFFICULTY),
+        node_id,
+        difficulty: VECTOR_DIFFICULTY,
+        suites: Vec::new(),
+        network_id: None,
+        pq_key: None,
+        relay: None,
+        power: None,
+    };
+    let body = serde_json::to_value(&body).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+    envelope(ANNOUNCEMENT_KIND, body, keypair)
+}
+
+pub fn generate(seed: u64) -> Result<TestVectors, BridgeError> {
+    let initiator = derive_keypair(seed, 0)?;
+    let responder = derive_keypair(seed, 1)?;
+
+    let keys = [&initiator, &responder]
+        .iter()
+        .map(|k| KeyVector {
+            secret_key_hex: hex::encode(k.to_bytes()),
+            public_key_hex: k.public_key_hex(),
+        })
+        .collect();
+
+    let signatures = vec![SignatureVector {
+        public_key_hex: initiator.public_key_hex(),
+        message_hex: hex::encode(MESSAGE),
+        signature_hex: hex::encode(initiator.sign(MESSAGE).to_bytes()),
+    }];
+
+    let sensor_data = SensorData {
+        pm25: 12.5,
+        co2: 415.0,
+        temperature: 21.25,
+        humidity: 48.0,
+        timestamp: VECTOR_TIMESTAMP,
+    };
+    let genesis = TangleBlock::new(TangleBlockData { parents: Vec::new(), data: sensor_data.clone() }, &initiator);
+    let genesis_id = genesis.id.clone();
+    let child = TangleBlock::new(TangleBlockData { parents: vec![genesis_id.clone()], data: sensor_data.clone() }, &initiator);
+    let sensor_data_json = serde_json::to_string(&sensor_data).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+    let blocks = [genesis, child]
+        .into_iter()
+        .map(|block| {
+            Ok(BlockVector {
+                sensor_data_json: sensor_data_json.clone(),
+                parents: block.data.parents.clone(),
+                block_json: serde_json::to_string(&block).map_err(|e| BridgeError::Serialization(e.to_string()))?,
+            })
+        })
+        .collect::<Result<Vec<_>, BridgeError>>()?;
+
+    let envelopes = vec![
+        envelope(ANNOTATION_KIND, annotations::body(&BlockId::from_storage(genesis_id), "calibrated"), &initiator)?,
+        envelope("test_vector", json!({ "seed": seed, "values": [1, 2.5, -3] }), &responder)?,
+    ];
+
+    let endorsement = serde_json::to_value(EndorsementBody { key: initiator.public_key_hex() })
+        .map_err(|e| BridgeError::Serialization(e.to_string()))?;
+    let handshake = HandshakeTranscript {
+        difficulty: VECTOR_DIFFICULTY,
+        messages: vec![
+            announcement(&initiator)?,
+            announcement(&responder)?,
+            envelope(ENDORSEMENT_KIND, endorsement, &responder)?,
+        ],
+    };
+
+    Ok(TestVectors {
+        seed,
+        keys,
+        signatures,
+        blocks,
+        envelopes,
+        handshake,
+    })
+}
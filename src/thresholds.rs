@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use crate::block_id::BlockId;
+
+pub type ThresholdCallback = Arc<dyn Fn(ThresholdEvent) + Send + Sync>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThresholdRule {
+    pub metric: String,
+    pub upper: Option<f64>,
+    pub lower: Option<f64>,
+    /// Distance a value must move back past the limit before an alert clears.
+    pub hysteresis: f64,
+    /// Seconds a crossing (or recovery) must persist before it is reported.
+    pub min_duration: u64,
+    pub emit_alert_block: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ThresholdDirection {
+    Above,
+    Below,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ThresholdEventKind {
+    Raised,
+    Cleared,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThresholdEvent {
+    pub metric: String,
+    pub sensor_id: String,
+    pub block_id: BlockId,
+    pub kind: ThresholdEventKind,
+    pub direction: ThresholdDirection,
+    pub value: f64,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActiveAlert {
+    pub metric: String,
+    pub sensor_id: String,
+    pub direction: ThresholdDirection,
+    pub since: u64,
+    pub last_value: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AlertState {
+    Normal,
+    Crossing { direction: ThresholdDirection, since: u64 },
+    Active { direction: ThresholdDirection, since: u64 },
+    Recovering { direction: ThresholdDirection, since: u64, active_since: u64 },
+}
+
+impl ThresholdRule {
+    fn crossing(&self, value: f64) -> Option<ThresholdDirection> {
+        if self.upper.is_some_and(|u| value > u) {
+            Some(ThresholdDirection::Above)
+        } else if self.lower.is_some_and(|l| value < l) {
+            Some(ThresholdDirection::Below)
+        } else {
+            None
+        }
+    }
+
+    fn recovered(&self, direction: ThresholdDirection, value: f64) -> bool {
+        match direction {
+            ThresholdDirection::Above => self.upper.is_none_or(|u| value < u - self.hysteresis),
+            ThresholdDirection::Below => self.lower.is_none_or(|l| value > l + self.hysteresis),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ThresholdMonitor {
+    rules: HashMap<String, ThresholdRule>,
+    states: HashMap<(String, String), (AlertState, f64)>,
+    next_id: u64,
+    subscribers: Vec<(u64, ThresholdCallback)>,
+}
+
+impl ThresholdMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_rule(&mut self, rule: ThresholdRule) {
+        self.states.retain(|(metric, _), _| metric != &rule.metric);
+        self.rules.insert(rule.metric.clone(), rule);
+    }
+
+    pub fn remove_rule(&mut self, metric: &str) -> bool {
+        self.states.retain(|(m, _), _| m != metric);
+        self.rules.remove(metric).is_some()
+    }
+
+    pub fn rules(&self) -> Vec<ThresholdRule> {
+        self.rules.values().cloned().collect()
+    }
+
+    pub fn rule(&self, metric: &str) -> Option<&ThresholdRule> {
+        self.rules.get(metric)
+    }
+
+    pub fn metrics(&self) -> Vec<String> {
+        self.rules.keys().cloned().collect()
+    }
+
+    pub fn subscribe(&mut self, callback: ThresholdCallback) -> u64 {
+        self.next_id += 1;
+        self.subscribers.push((self.next_id, callback));
+        self.next_id
+    }
+
+    pub fn unsubscribe(&mut self, id: u64) -> bool {
+        let before = self.subscribers.len();
+        self.subscribers.retain(|(sid, _)| *sid != id);
+        self.subscribers.len() != before
+    }
+
+    pub fn subscribers(&self) -> Vec<ThresholdCallback> {
+        self.subscribers.iter().map(|(_, cb)| cb.clone()).collect()
+    }
+
+    pub fn active_alerts(&self) -> Vec<ActiveAlert> {
+        let mut alerts: Vec<ActiveAlert> = self
+            .states
+            .iter()
+            .filter_map(|((metric, sensor_id), (state, last_value))| match *state {
+                AlertState::Active { direction, since }
+                | AlertState::Recovering { direction, active_since: since, .. } => Some(ActiveAlert {
+                    metric: metric.clone(),
+                    sensor_id: sensor_id.clone(),
+                    direction,
+                    since,
+                    last_value: *last_value,
+                }),
+                _ => None,
+            })
+            .collect();
+        alerts.sort_by(|a, b| a.since.cmp(&b.since).then_with(|| a.metric.cmp(&b.metric)));
+        alerts
+    }
+
+    /// Feeds one value for `metric` from `sensor_id`, returning an event when an
+    /// alert is raised or cleared after persisting for the rule's minimum duration.
+    pub fn evaluate(&mut self, metric: &str, sensor_id: &str, block_id: &str, value: f64, timestamp: u64) -> Option<ThresholdEvent> {
+        let rule = self.rules.get(metric)?;
+        let key = (metric.to_string(), sensor_id.to_string());
+        let (state, _) = self.states.get(&key).copied().unwrap_or((AlertState::Normal, value));
+        let sustained = |since: u64| timestamp.saturating_sub(since) >= rule.min_duration;
+        let mut event = None;
+        let next = match state {
+            AlertState::Normal => match rule.crossing(value) {
+                Some(direction) if sustained(timestamp) => {
+                    event = Some((ThresholdEventKind::Raised, direction));
+                    AlertState::Active { direction, since: timestamp }
+                }
+                Some(direction) => AlertState::Crossing { direction, since: timestamp },
+                None => AlertState::Normal,
+            },
+            AlertState::Crossing { direction, since } => match rule.crossing(value) {
+                Some(d) if d == direction && sustained(since) => {
+                    event = Some((ThresholdEventKind::Raised, direction));
+                    AlertState::Active { direction, since }
+                }
+                Some(d) if d == direction => state,
+                Some(d) => AlertState::Crossing { direction: d, since: timestamp },
+                None => AlertState::Normal,
+            },
+            AlertState::Active { direction, since } => {
+                if !rule.recovered(direction, value) {
+                    state
+                } else if sustained(timestamp) {
+                    event = Some((ThresholdEventKind::Cleared, direction));
+                    AlertState::Normal
+                } else {
+                    AlertState::Recovering { direction, since: timestamp, active_since: since }
+                }
+            }
+            AlertState::Recovering { direction, since, active_since } => {
+                if !rule.recovered(direction, value) {
+                    AlertState::Active { direction, since: active_since }
+                } else if sustained(since) {
+                    event = Some((ThresholdEventKind::Cleared, direction));
+                    AlertState::Normal
+                } else {
+                    state
+                }
+            }
+        };
+        if next == AlertState::Normal {
+            self.states.remove(&key);
+        } else {
+            self.states.insert(key, (next, value));
+        }
+        event.map(|(kind, direction)| ThresholdEvent {
+            metric: metric.to_string(),
+            sensor_id: sensor_id.to_string(),
+            block_id: BlockId::from_storage(block_id),
+            kind,
+            direction,
+            value,
+            timestamp,
+        })
+    }
+}
@@ -0,0 +1,195 @@
+//! History of the mesh topology, for reconstructing what the network looked
+//! like when a data gap or partition occurred. Every change to the mesh is
+//! appended to `topology.log` in the node directory as a JSON line, and
+//! `init_context` replays the log into the mesh. `topology_at` replays it up to
+//! a point in time.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+use crate::logs::LogLevel;
+use crate::{clock, energy, EcoBlockContext};
+
+pub const TOPOLOGY_LOG_FILE: &str = "topology.log";
+
+pub fn topology_log_path(path: &Path) -> PathBuf {
+    path.join(TOPOLOGY_LOG_FILE)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum TopologyOp {
+    NodeAdded { node: String },
+    /// Also records a new weight for an existing connection.
+    ConnectionAdded { from: String, to: String, weight: f32 },
+}
+
+/// One line of the topology log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopologyChange {
+    pub at: u64,
+    #[serde(flatten)]
+    pub op: TopologyOp,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopologyEdge {
+    pub from: String,
+    pub to: String,
+    pub weight: f32,
+}
+
+/// The mesh as it stood at `at`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopologySnapshot {
+    pub at: u64,
+    /// Nodes added explicitly or as the end of a connection, sorted.
+    pub nodes: Vec<String>,
+    /// Sorted by `from`, then `to`.
+    pub edges: Vec<TopologyEdge>,
+    /// Changes replayed to build the snapshot.
+    pub changes: usize,
+}
+
+/// The changes in memory, oldest first, and the file they are appended to
+/// once the context has a node directory.
+#[derive(Debug, Default)]
+pub struct TopologyLog {
+    changes: Vec<TopologyChange>,
+    file: Option<File>,
+}
+
+impl TopologyLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the log at `path` and appends to it from now on. A torn last
+    /// line, left by a crash mid-write, is cut off.
+    pub fn open(&mut self, path: &Path) -> Result<(), BridgeError> {
+        let bytes = if path.exists() { fs::read(path).map_err(|e| BridgeError::Io(e.to_string()))? } else { Vec::new() };
+        let mut loaded = Vec::new();
+        let mut valid_len = 0;
+        for line in bytes.split_inclusive(|b| *b == b'\n') {
+            if !line.ends_with(b"\n") {
+                break;
+            }
+            let change: TopologyChange = serde_json::from_slice(line)
+                .map_err(|e| BridgeError::Deserialization(format!("{} line {}: {}", path.display(), loaded.len() + 1, e)))?;
+            loaded.push(change);
+            valid_len += line.len();
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path).map_err(|e| BridgeError::Io(e.to_string()))?;
+        if valid_len < bytes.len() {
+            file.set_len(valid_len as u64).map_err(|e| BridgeError::Io(e.to_string()))?;
+        }
+        self.changes = loaded;
+        self.file = Some(file);
+        Ok(())
+    }
+
+    fn append(&mut self, change: TopologyChange) -> Result<(), BridgeError> {
+        let written = match &mut self.file {
+            Some(file) => {
+                let mut line = serde_json::to_vec(&change).map_err(|e| BridgeError::Serialization(e.to_string()))?;
+                line.push(b'\n');
+                file.write_all(&line).map_err(|e| BridgeError::Io(e.to_string()))?;
+                energy::count_disk_write(line.len());
+                Ok(())
+            }
+            None => Ok(()),
+        };
+        self.changes.push(change);
+        written
+    }
+
+    /// All changes, oldest first.
+    pub fn changes(&self) -> &[TopologyChange] {
+        &self.changes
+    }
+
+    /// Changes made from `from` to `to`, both inclusive, oldest first.
+    pub fn between(&self, from: u64, to: u64) -> Vec<TopologyChange> {
+        self.changes.iter().filter(|c| c.at >= from && c.at <= to).cloned().collect()
+    }
+
+    /// Replays the changes made up to `at`, inclusive.
+    pub fn snapshot(&self, at: u64) -> TopologySnapshot {
+        let mut nodes = BTreeSet::new();
+        let mut edges: BTreeMap<(String, String), f32> = BTreeMap::new();
+        let mut changes = 0;
+        for change in self.changes.iter().filter(|c| c.at <= at) {
+            match &change.op {
+                TopologyOp::NodeAdded { node } => {
+                    nodes.insert(node.clone());
+                }
+                TopologyOp::ConnectionAdded { from, to, weight } => {
+                    nodes.insert(from.clone());
+                    nodes.insert(to.clone());
+                    edges.insert((from.clone(), to.clone()), *weight);
+                }
+            }
+            changes += 1;
+        }
+        TopologySnapshot {
+            at,
+            nodes: nodes.into_iter().collect(),
+            edges: edges.into_iter().map(|((from, to), weight)| TopologyEdge { from, to, weight }).collect(),
+            changes,
+        }
+    }
+}
+
+impl EcoBlockContext {
+    fn log_topology_change(&mut self, op: TopologyOp) {
+        let change = TopologyChange {
+            at: clock::now_secs(),
+            op,
+        };
+        if let Err(e) = self.topology_log.append(change) {
+            self.log(LogLevel::Warn, format!("Topology change not persisted: {}", e));
+        }
+    }
+
+    /// Adds `node` to the mesh and the topology log.
+    pub(crate) fn add_mesh_node(&mut self, node: &str) {
+        let known = self.mesh.get_neighbors(node).is_some();
+        self.mesh.add_node(node);
+        if !known {
+            self.log_topology_change(TopologyOp::NodeAdded { node: node.to_string() });
+        }
+    }
+
+    /// Adds or reweighs a connection in the mesh and the topology log.
+    /// Connections that already have `weight` are not logged again.
+    pub(crate) fn add_mesh_connection(&mut self, from: &str, to: &str, weight: f32) {
+        let unchanged = self
+            .mesh
+            .get_neighbors(from)
+            .is_some_and(|n| n.iter().any(|(peer, w)| peer == to && *w == weight));
+        self.mesh.add_connection(from, to, weight);
+        if !unchanged {
+            self.log_topology_change(TopologyOp::ConnectionAdded {
+                from: from.to_string(),
+                to: to.to_string(),
+                weight,
+            });
+        }
+    }
+
+    /// Opens the topology log of the node at `path` and replays it into the
+    /// mesh.
+    pub(crate) fn open_topology_log(&mut self, path: &Path) -> Result<(), BridgeError> {
+        self.topology_log.open(path)?;
+        for change in self.topology_log.changes() {
+            match &change.op {
+                TopologyOp::NodeAdded { node } => self.mesh.add_node(node),
+                TopologyOp::ConnectionAdded { from, to, weight } => self.mesh.add_connection(from, to, *weight),
+            }
+        }
+        Ok(())
+    }
+}
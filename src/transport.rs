@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use crate::error::BridgeError;
+use crate::peer_id::PeerId;
+
+/// Largest UDP payload over IPv4.
+pub const MAX_DATAGRAM_LEN: usize = 65_507;
+
+/// Carries encoded wire frames between this node and its peers. Calls are
+/// made with the context lock held, so implementations must not block for long
+/// or call back into the bridge.
+pub trait Transport: Send {
+    /// Sends one frame to `peer`.
+    fn send(&mut self, peer: &PeerId, frame: &[u8]) -> Result<(), BridgeError>;
+
+    /// The next frame received and the peer it came from, or `None` when
+    /// nothing is waiting. Never blocks.
+    fn receive(&mut self) -> Result<Option<(PeerId, Vec<u8>)>, BridgeError>;
+
+    /// Tells the transport where `peer` is reached, in its own address format.
+    fn set_peer_address(&mut self, peer: &PeerId, address: &str) -> Result<(), BridgeError>;
+
+    /// Names the transport for duty-cycle budgets and status.
+    fn name(&self) -> &str {
+        "custom"
+    }
+}
+
+/// One frame per datagram to peers at known `host:port` addresses. Datagrams
+/// from addresses of no known peer are dropped.
+#[derive(Debug)]
+pub struct UdpTransport {
+    socket: UdpSocket,
+    addresses: HashMap<PeerId, SocketAddr>,
+    peers: HashMap<SocketAddr, PeerId>,
+    buffer: Vec<u8>,
+}
+
+impl UdpTransport {
+    pub fn bind(address: &str) -> Result<Self, BridgeError> {
+        let socket = UdpSocket::bind(address).map_err(|e| BridgeError::Io(e.to_string()))?;
+        socket.set_nonblocking(true).map_err(|e| BridgeError::Io(e.to_string()))?;
+        Ok(Self {
+            socket,
+            addresses: HashMap::new(),
+            peers: HashMap::new(),
+            buffer: vec![0; MAX_DATAGRAM_LEN],
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, BridgeError> {
+        self.socket.local_addr().map_err(|e| BridgeError::Io(e.to_string()))
+    }
+}
+
+impl Transport for UdpTransport {
+    fn name(&self) -> &str {
+        "udp"
+    }
+
+    fn send(&mut self, peer: &PeerId, frame: &[u8]) -> Result<(), BridgeError> {
+        let address = self.addresses.get(peer).ok_or_else(|| BridgeError::NotFound(format!("Unknown address for peer {}", peer)))?;
+        if frame.len() > MAX_DATAGRAM_LEN {
+            return Err(BridgeError::Wire(format!("frame of {} bytes exceeds a datagram", frame.len())));
+        }
+        self.socket.send_to(frame, address).map_err(|e| BridgeError::Io(e.to_string()))?;
+        Ok(())
+    }
+
+    fn receive(&mut self) -> Result<Option<(PeerId, Vec<u8>)>, BridgeError> {
+        loop {
+            match self.socket.recv_from(&mut self.buffer) {
+                Ok((len, from)) => {
+                    if let Some(peer) = self.peers.get(&from) {
+                        return Ok(Some((peer.clone(), self.buffer[..len].to_vec())));
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(None),
+                Err(e) => return Err(BridgeError::Io(e.to_string())),
+            }
+        }
+    }
+
+    fn set_peer_address(&mut self, peer: &PeerId, address: &str) -> Result<(), BridgeError> {
+        let address = address
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut a| a.next())
+            .ok_or_else(|| BridgeError::InvalidInput(format!("Invalid address {:?}: expected host:port", address)))?;
+        if let Some(old) = self.addresses.insert(peer.clone(), address) {
+            self.peers.remove(&old);
+        }
+        self.peers.insert(address, peer.clone());
+        Ok(())
+    }
+}
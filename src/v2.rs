@@ -0,0 +1,991 @@
+//! Typed bridge API (version 2). Fallible functions return [`BridgeError`], so
+//! callers can match on the kind of error, and `create_block` returns a
+//! `BlockId`. The version 1 functions at the crate root wrap these and return
+//! the error text. Infallible functions are shared with version 1 and stay at
+//! the crate root.
+
+use serde::{Deserialize, Serialize};
+pub use crate::error::ErrorKind;
+use crate::*;
+
+pub const API_VERSION: u32 = 2;
+/// Oldest version still served. Version 1 is the crate-root API.
+pub const MIN_API_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiVersion {
+    pub current: u32,
+    pub supported: Vec<u32>,
+    /// Still served but scheduled for removal.
+    pub deprecated: Vec<u32>,
+}
+
+pub fn api_version() -> ApiVersion {
+    ApiVersion {
+        current: API_VERSION,
+        supported: (MIN_API_VERSION..=API_VERSION).collect(),
+        deprecated: (MIN_API_VERSION..API_VERSION).collect(),
+    }
+}
+
+pub fn create_block(data: Vec<u8>, parents: Vec<BlockId>) -> Result<BlockId, BridgeError> {
+    let _call = CallTimer::start("create_block");
+    let (result, pending) = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        let result = ctx.try_create_block(data, parents);
+        (result, ctx.take_pending())
+    };
+    run_pending(pending);
+    result
+}
+
+pub fn generate_keypair(path: String) -> Result<String, BridgeError> {
+    let _call = CallTimer::start("generate_keypair");
+    let keypair = CryptoKeypair::generate();
+    let bytes = keypair.to_bytes();
+    eprintln!("[generate_keypair] bytes len: {}", bytes.len());
+    fs::write(keypair_path(&path), &bytes).map_err(|e| BridgeError::Io(e.to_string()))?;
+    let file_bytes = fs::read(keypair_path(&path)).map_err(|e| BridgeError::Io(e.to_string()))?;
+    eprintln!("[generate_keypair] file bytes len: {}", file_bytes.len());
+    eprintln!("[generate_keypair] file bytes: {:?}", file_bytes);
+    Ok(keypair.public_key_hex())
+}
+
+pub fn get_public_key(path: String) -> Result<String, BridgeError> {
+    let _call = CallTimer::start("get_public_key");
+    let keypair = load_keypair(&path)?;
+    Ok(keypair.public_key_hex())
+}
+
+pub fn get_node_id(path: String) -> Result<String, BridgeError> {
+    let _call = CallTimer::start("get_node_id");
+    let keypair = load_keypair(&path)?;
+    let node_id = keypair.public_key_hex();
+    Ok(node_id)
+}
+
+pub fn initialize_tangle() -> Result<(), BridgeError> {
+    let _call = CallTimer::start("initialize_tangle");
+    let _tangle = Tangle::new();
+    Ok(())
+}
+
+pub fn initialize_mesh(path: String) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("initialize_mesh");
+    let mut mesh = TopologyGraph::new();
+    let node_id = get_node_id(path.clone())?;
+    mesh.add_node(&node_id);
+    Ok(())
+}
+
+pub fn create_local_node(path: String) -> Result<String, BridgeError> {
+    let _call = CallTimer::start("create_local_node");
+    if node_is_initialized(path.clone())? {
+        return Err(BridgeError::AlreadyInitialized(format!("a node already exists at {}", path)));
+    }
+    generate_keypair(path.clone())?;
+    initialize_tangle()?;
+    initialize_mesh(path.clone())?;
+    get_node_id(path)
+}
+
+pub fn reset_node(path: String) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("reset_node");
+    let _ = fs::remove_file(keypair_path(&path));
+    let store_file = block_store::block_store_path(&path);
+    let mut ctx = CONTEXT.lock().unwrap();
+    if ctx.node_path.as_deref() == Some(Path::new(&path)) {
+        *ctx = EcoBlockContext::new();
+    }
+    if ctx.block_store.as_ref().is_some_and(|s| s.path() == store_file) {
+        ctx.block_store = None;
+    }
+    let _ = fs::remove_file(store_file);
+    Ok(())
+}
+
+pub fn node_is_initialized(path: String) -> Result<bool, BridgeError> {
+    let _call = CallTimer::start("node_is_initialized");
+    Ok(keypair_path(&path).exists())
+}
+
+pub fn register_derived_metric(name: String, expression: String, mode: DerivedMode) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("register_derived_metric");
+    CONTEXT.lock().unwrap().register_derived_metric(&name, &expression, mode)
+}
+
+pub fn publish_inventory() -> Result<Record, BridgeError> {
+    let _call = CallTimer::start("publish_inventory");
+    CONTEXT.lock().unwrap().publish_inventory()
+}
+
+pub fn receive_record(record: Record) -> Result<bool, BridgeError> {
+    let _call = CallTimer::start("receive_record");
+    let mut ctx = CONTEXT.lock().unwrap();
+    let (id, kind, author) = (record.id.clone(), record.kind.clone(), record.author.clone());
+    let result = ctx.receive_record(record);
+    if let Err(e) = &result {
+        ctx.log(LogLevel::Warn, format!("Rejected {} record {} from {}: {}", kind, id, author, e));
+    }
+    result
+}
+
+pub fn publish_version() -> Result<Record, BridgeError> {
+    let _call = CallTimer::start("publish_version");
+    CONTEXT.lock().unwrap().publish_version()
+}
+
+pub fn annotate_block(block_id: BlockId, note: String) -> Result<Annotation, BridgeError> {
+    let _call = CallTimer::start("annotate_block");
+    CONTEXT.lock().unwrap().annotate_block(&block_id, &note)
+}
+
+pub fn flag_block(block_id: BlockId, reason: FlagReason, comment: Option<String>) -> Result<Flag, BridgeError> {
+    let _call = CallTimer::start("flag_block");
+    CONTEXT.lock().unwrap().flag_block(&block_id, reason, comment)
+}
+
+pub fn publish_to_channel(channel: String, data: Vec<u8>) -> Result<String, BridgeError> {
+    let _call = CallTimer::start("publish_to_channel");
+    CONTEXT.lock().unwrap().publish_to_channel(&channel, &data)
+}
+
+pub fn create_group_key(channel: String, tier: Visibility, members: Vec<PublicKeyHex>) -> Result<Record, BridgeError> {
+    let _call = CallTimer::start("create_group_key");
+    CONTEXT.lock().unwrap().create_group_key(&channel, tier, members.into_iter().map(String::from).collect())
+}
+
+pub fn rotate_group_key(channel: String, tier: Visibility) -> Result<Record, BridgeError> {
+    let _call = CallTimer::start("rotate_group_key");
+    CONTEXT.lock().unwrap().rotate_group_key(&channel, tier)
+}
+
+pub fn add_group_member(channel: String, tier: Visibility, member: PublicKeyHex) -> Result<Record, BridgeError> {
+    let _call = CallTimer::start("add_group_member");
+    CONTEXT.lock().unwrap().add_group_member(&channel, tier, member.as_str())
+}
+
+pub fn remove_group_member(channel: String, tier: Visibility, member: PublicKeyHex) -> Result<Record, BridgeError> {
+    let _call = CallTimer::start("remove_group_member");
+    CONTEXT.lock().unwrap().remove_group_member(&channel, tier, member.as_str())
+}
+
+pub fn invite_member(scope: String, role: Role, invitee: Option<PublicKeyHex>, ttl: u64) -> Result<Record, BridgeError> {
+    let _call = CallTimer::start("invite_member");
+    CONTEXT.lock().unwrap().invite_member(&scope, role, invitee.map(String::from), ttl)
+}
+
+pub fn accept_invitation(invitation: Record) -> Result<Record, BridgeError> {
+    let _call = CallTimer::start("accept_invitation");
+    CONTEXT.lock().unwrap().accept_invitation(invitation)
+}
+
+pub fn revoke_member(scope: String, member: PublicKeyHex) -> Result<Record, BridgeError> {
+    let _call = CallTimer::start("revoke_member");
+    CONTEXT.lock().unwrap().revoke_member(&scope, member.as_str())
+}
+
+pub fn set_pow_difficulty(bits: u8) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("set_pow_difficulty");
+    CONTEXT.lock().unwrap().pow.set_difficulty(bits)
+}
+
+pub fn announce() -> Result<Record, BridgeError> {
+    let _call = CallTimer::start("announce");
+    let (node_id, difficulty) = {
+        let ctx = CONTEXT.lock().unwrap();
+        (ctx.keypair.public_key_hex(), ctx.pow.difficulty)
+    };
+    let nonce = pow::solve(&node_id, difficulty);
+    CONTEXT.lock().unwrap().sign_announcement(difficulty, nonce)
+}
+
+pub fn endorse_key(key: PublicKeyHex) -> Result<Record, BridgeError> {
+    let _call = CallTimer::start("endorse_key");
+    CONTEXT.lock().unwrap().endorse_key(key.as_str())
+}
+
+pub fn publish_policy(document: PolicyDocument) -> Result<Record, BridgeError> {
+    let _call = CallTimer::start("publish_policy");
+    CONTEXT.lock().unwrap().publish_policy(document)
+}
+
+pub fn request_diagnostics(target: PublicKeyHex) -> Result<Record, BridgeError> {
+    let _call = CallTimer::start("request_diagnostics");
+    CONTEXT.lock().unwrap().request_diagnostics(target.as_str())
+}
+
+pub fn decommission_node(path: String, successor: Option<PeerId>, reason: String) -> Result<DecommissionReport, BridgeError> {
+    let _call = CallTimer::start("decommission_node");
+    let handoff = {
+        let ctx = CONTEXT.lock().unwrap();
+        ctx.check_open_node(Path::new(&path))?;
+        ctx.handoff_records()
+    };
+    if let Some(successor) = &successor {
+        for batch in handoff.chunks(decommission::HANDOFF_BATCH) {
+            let deadline = clock::now_secs() + decommission::HANDOFF_TIMEOUT_SECS;
+            CONTEXT.lock().unwrap().send_handoff(batch, successor, deadline)?;
+            wait_for_handoff(batch, successor, deadline)?;
+        }
+    }
+    let mut ctx = CONTEXT.lock().unwrap();
+    ctx.check_open_node(Path::new(&path))?;
+    let retirement = ctx.retire(successor.clone().map(String::from), &reason)?;
+    ctx.gossip_record(&retirement);
+    let mut wiped_files = Vec::new();
+    ctx.block_store = None;
+    for file in [keypair_path(&path), block_store::block_store_path(&path)] {
+        if decommission::wipe_file(&file)? {
+            wiped_files.push(file.display().to_string());
+        }
+    }
+    *ctx = EcoBlockContext::new();
+    Ok(DecommissionReport {
+        retirement,
+        successor,
+        handoff,
+        wiped_files,
+    })
+}
+
+/// Handles incoming frames, outside the context lock between polls, until
+/// `successor` has confirmed every record of `batch`.
+fn wait_for_handoff(batch: &[Record], successor: &PeerId, deadline: u64) -> Result<(), BridgeError> {
+    loop {
+        let (unconfirmed, pending) = {
+            let mut ctx = CONTEXT.lock().unwrap();
+            ctx.poll_transport(sync::FRAMES_PER_STEP);
+            (ctx.unconfirmed_handoff(batch), ctx.take_pending())
+        };
+        run_pending(pending);
+        if unconfirmed == 0 {
+            return Ok(());
+        }
+        if clock::now_secs() > deadline {
+            return Err(BridgeError::Io(format!(
+                "{} of {} handed-off records not confirmed by {}; the node was not retired or wiped",
+                unconfirmed,
+                batch.len(),
+                successor
+            )));
+        }
+        std::thread::sleep(std::time::Duration::from_millis(maintenance::SYNC_POLL_MS));
+    }
+}
+
+pub fn hand_over_identity(new_node_id: PublicKeyHex) -> Result<Record, BridgeError> {
+    let _call = CallTimer::start("hand_over_identity");
+    CONTEXT.lock().unwrap().hand_over_identity(&new_node_id)
+}
+
+pub fn hand_over_from_backup(backup_path: String, new_node_id: PublicKeyHex) -> Result<Record, BridgeError> {
+    let _call = CallTimer::start("hand_over_from_backup");
+    let old = load_keypair(&backup_path)?;
+    let record = handover::sign_handover(&old, &new_node_id, Vec::new());
+    let mut ctx = CONTEXT.lock().unwrap();
+    ctx.receive_record(record.clone())?;
+    Ok(record)
+}
+
+pub fn run_simulation(scenario_json: String) -> Result<SimulationReport, BridgeError> {
+    let _call = CallTimer::start("run_simulation");
+    let scenario = Scenario::from_json(&scenario_json)?;
+    Ok(simulator::run(&scenario))
+}
+
+pub fn run_simulation_file(path: String) -> Result<SimulationReport, BridgeError> {
+    let _call = CallTimer::start("run_simulation_file");
+    let scenario = Scenario::load(&path)?;
+    Ok(simulator::run(&scenario))
+}
+
+pub fn run_simulation_with_chaos(scenario_json: String, config: ChaosConfig) -> Result<SimulationReport, BridgeError> {
+    let _call = CallTimer::start("run_simulation_with_chaos");
+    let scenario = Scenario::from_json(&scenario_json)?;
+    Ok(simulator::run_with(&scenario, &mut ChaosInjector::new(config)))
+}
+
+pub fn generate_test_vectors(seed: u64) -> Result<TestVectors, BridgeError> {
+    let _call = CallTimer::start("generate_test_vectors");
+    test_vectors::generate(seed)
+}
+
+pub fn parse_frame(bytes: Vec<u8>) -> Result<Frame, BridgeError> {
+    let _call = CallTimer::start("parse_frame");
+    wire::parse_frame(&bytes)
+}
+
+pub fn parse_block_envelope(bytes: Vec<u8>) -> Result<BlockEnvelope, BridgeError> {
+    let _call = CallTimer::start("parse_block_envelope");
+    wire::parse_block_envelope(&bytes)
+}
+
+pub fn parse_handshake(bytes: Vec<u8>) -> Result<Handshake, BridgeError> {
+    let _call = CallTimer::start("parse_handshake");
+    wire::parse_handshake(&bytes)
+}
+
+pub fn export_delta(since_checkpoint_id: String, path: String) -> Result<Checkpoint, BridgeError> {
+    let _call = CallTimer::start("export_delta");
+    CONTEXT.lock().unwrap().export_delta(&since_checkpoint_id, &path)
+}
+
+pub fn import_delta(path: String) -> Result<DeltaImport, BridgeError> {
+    let _call = CallTimer::start("import_delta");
+    CONTEXT.lock().unwrap().import_delta(&path)
+}
+
+pub fn list_blocks_page(cursor: Option<String>, limit: usize) -> Result<Page<TangleBlock>, BridgeError> {
+    let _call = CallTimer::start("list_blocks_page");
+    let ctx = CONTEXT.lock().unwrap();
+    pagination::by_position(ctx.block_log.positions().map(|(i, b)| (i, b.clone())), cursor.as_deref(), limit)
+}
+
+pub fn list_peers_page(peer_id: PeerId, cursor: Option<String>, limit: usize) -> Result<Page<PeerId>, BridgeError> {
+    let _call = CallTimer::start("list_peers_page");
+    let peers = CONTEXT.lock().unwrap().list_peers(&peer_id);
+    pagination::by_key(peers.into_iter().map(|p| (p.to_string(), p)).collect(), cursor.as_deref(), limit)
+}
+
+pub fn list_alert_blocks_page(cursor: Option<String>, limit: usize) -> Result<Page<Record>, BridgeError> {
+    let _call = CallTimer::start("list_alert_blocks_page");
+    let ctx = CONTEXT.lock().unwrap();
+    let alerts = ctx.records.iter().enumerate().filter(|(_, r)| r.kind == "alert").map(|(i, r)| (i, r.clone()));
+    pagination::by_position(alerts, cursor.as_deref(), limit)
+}
+
+pub fn active_alerts_page(cursor: Option<String>, limit: usize) -> Result<Page<ActiveAlert>, BridgeError> {
+    let _call = CallTimer::start("active_alerts_page");
+    let alerts = CONTEXT.lock().unwrap().thresholds.active_alerts();
+    let keyed = alerts.into_iter().map(|a| (format!("{}\n{}", a.metric, a.sensor_id), a)).collect();
+    pagination::by_key(keyed, cursor.as_deref(), limit)
+}
+
+pub fn add_connections_bulk(connections: Vec<(PeerId, PeerId, f32)>) -> Result<BulkReport, BridgeError> {
+    let _call = CallTimer::start("add_connections_bulk");
+    let (result, pending) = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        let result = ctx.add_connections_bulk(connections);
+        (result, ctx.take_pending())
+    };
+    run_pending(pending);
+    result
+}
+
+pub fn set_route_redundancy(priority: Priority, k: u8) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("set_route_redundancy");
+    CONTEXT.lock().unwrap().routing.set_redundancy(priority, k)
+}
+
+pub fn advertise_haves() -> Result<Record, BridgeError> {
+    let _call = CallTimer::start("advertise_haves");
+    CONTEXT.lock().unwrap().advertise_haves()
+}
+
+pub fn set_metrics_history_path(path: String) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("set_metrics_history_path");
+    CONTEXT.lock().unwrap().metrics_history.attach(Path::new(&path))
+}
+
+pub fn sample_metrics() -> Result<(), BridgeError> {
+    let _call = CallTimer::start("sample_metrics");
+    CONTEXT.lock().unwrap().sample_metrics()
+}
+
+pub fn add_to_batch(batch_id: u64, data: Vec<u8>, parents: Vec<BlockId>) -> Result<BlockId, BridgeError> {
+    let _call = CallTimer::start("add_to_batch");
+    let (result, pending) = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        let result = ctx.add_to_batch(batch_id, data, parents);
+        (result, ctx.take_pending())
+    };
+    run_pending(pending);
+    result
+}
+
+pub fn commit_batch(batch_id: u64) -> Result<Vec<BlockId>, BridgeError> {
+    let _call = CallTimer::start("commit_batch");
+    let (result, pending) = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        let result = ctx.commit_batch(batch_id);
+        (result, ctx.take_pending())
+    };
+    run_pending(pending);
+    result
+}
+
+pub fn set_shared_config(key: String, value: String, expected_token: Option<String>) -> Result<ConfigEntry, BridgeError> {
+    let _call = CallTimer::start("set_shared_config");
+    CONTEXT.lock().unwrap().set_shared_config(&key, value, expected_token)
+}
+
+pub fn archive_blocks_before(cutoff: u64, path: String) -> Result<Vec<BlockId>, BridgeError> {
+    let _call = CallTimer::start("archive_blocks_before");
+    let (result, pending) = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        let result = ctx.archive_blocks_before(cutoff, &path);
+        (result, ctx.take_pending())
+    };
+    run_pending(pending);
+    result
+}
+
+pub fn roll_up_blocks_before(cutoff: u64) -> Result<Vec<Record>, BridgeError> {
+    let _call = CallTimer::start("roll_up_blocks_before");
+    let (result, pending) = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        let result = ctx.roll_up_blocks_before(cutoff);
+        (result, ctx.take_pending())
+    };
+    run_pending(pending);
+    result
+}
+
+pub fn redact_block(block_id: BlockId, reason: String) -> Result<Record, BridgeError> {
+    let _call = CallTimer::start("redact_block");
+    let (result, pending) = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        let result = ctx.redact_block(&block_id, &reason);
+        (result, ctx.take_pending())
+    };
+    run_pending(pending);
+    result
+}
+
+pub fn set_network_suites(network_id: String, suites: Vec<Suite>) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("set_network_suites");
+    CONTEXT.lock().unwrap().suites.set_network_suites(&network_id, suites)
+}
+
+#[cfg(feature = "pq-hybrid")]
+pub fn enable_hybrid_signing(path: String) -> Result<String, BridgeError> {
+    let _call = CallTimer::start("enable_hybrid_signing");
+    let key_file = PathBuf::from(&path).join("node_pq_keypair.bin");
+    CONTEXT.lock().unwrap().load_pq_signer(&key_file)
+}
+
+#[cfg(feature = "pq-hybrid")]
+pub fn sign_block_envelope(block: TangleBlock) -> Result<BlockEnvelope, BridgeError> {
+    let _call = CallTimer::start("sign_block_envelope");
+    CONTEXT.lock().unwrap().sign_block_envelope(block)
+}
+
+pub fn start_log_stream(peer: PeerId, duration_secs: u64, min_level: LogLevel) -> Result<LogStreamStatus, BridgeError> {
+    let _call = CallTimer::start("start_log_stream");
+    CONTEXT.lock().unwrap().start_log_stream(&peer, duration_secs, min_level)
+}
+
+pub fn take_log_stream_frame() -> Result<Option<LogStreamFrame>, BridgeError> {
+    let _call = CallTimer::start("take_log_stream_frame");
+    CONTEXT.lock().unwrap().take_log_stream_frame()
+}
+
+pub fn open_log_stream_frame(frame: Vec<u8>) -> Result<Vec<LogEntry>, BridgeError> {
+    let _call = CallTimer::start("open_log_stream_frame");
+    CONTEXT.lock().unwrap().open_log_stream_frame(&frame)
+}
+
+pub fn configure_bridge(config: BridgeConfig) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("configure_bridge");
+    config.validate()?;
+    CONTEXT.lock().unwrap().config = config;
+    Ok(())
+}
+
+pub fn ingest_senml(json_or_cbor: Vec<u8>) -> Result<SenmlIngest, BridgeError> {
+    let _call = CallTimer::start("ingest_senml");
+    let (result, pending) = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        let result = ctx.ingest_senml(&json_or_cbor);
+        (result, ctx.take_pending())
+    };
+    run_pending(pending);
+    result
+}
+
+pub fn register_lorawan_sensor(dev_eui: String, sensor_id: String) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("register_lorawan_sensor");
+    CONTEXT.lock().unwrap().lorawan.register_sensor(&dev_eui, &sensor_id)
+}
+
+pub fn ingest_lorawan_uplink(uplink: LorawanUplink) -> Result<LorawanIngest, BridgeError> {
+    let _call = CallTimer::start("ingest_lorawan_uplink");
+    let codec = CONTEXT.lock().unwrap().lorawan.codec_for(&uplink)?;
+    let values = codec.decode(&uplink.payload)?;
+    let (result, pending) = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        let result = ctx.ingest_lorawan(&uplink, values);
+        (result, ctx.take_pending())
+    };
+    run_pending(pending);
+    result
+}
+
+pub fn query_blocks_page(filter: BlockFilter, cursor: Option<String>, limit: usize) -> Result<Page<TangleBlock>, BridgeError> {
+    let _call = CallTimer::start("query_blocks_page");
+    let matched = CONTEXT.lock().unwrap().query_blocks(&filter)?;
+    pagination::by_position(matched.into_iter(), cursor.as_deref(), limit)
+}
+
+pub fn get_block(id: BlockId) -> Result<String, BridgeError> {
+    let _call = CallTimer::start("get_block");
+    let ctx = CONTEXT.lock().unwrap();
+    let block = ctx.get_block(&id).ok_or_else(|| BridgeError::NotFound(format!("Unknown block {}", id)))?;
+    serde_json::to_string(block).map_err(|e| BridgeError::Serialization(e.to_string()))
+}
+
+pub fn export_snapshot(path: String) -> Result<Checkpoint, BridgeError> {
+    let _call = CallTimer::start("export_snapshot");
+    CONTEXT.lock().unwrap().export_snapshot(&path)
+}
+
+pub fn import_snapshot(path: String) -> Result<SnapshotImport, BridgeError> {
+    let _call = CallTimer::start("import_snapshot");
+    let import = CONTEXT.lock().unwrap().import_snapshot(&path)?;
+    start_index_builder();
+    Ok(import)
+}
+
+pub fn init_context(path: String) -> Result<String, BridgeError> {
+    let _call = CallTimer::start("init_context");
+    if !keypair_path(&path).exists() {
+        return Err(BridgeError::NotInitialized(format!("no node at {}; call create_local_node first", path)));
+    }
+    let keypair = load_keypair(&path)?;
+    let node_id = keypair.public_key_hex();
+    {
+        let mut ctx = CONTEXT.lock().map_err(|_| BridgeError::PoisonedLock("call shutdown_context to recover".to_string()))?;
+        if let Some(current) = &ctx.node_path {
+            return Err(BridgeError::AlreadyInitialized(format!("context is bound to {}", current.display())));
+        }
+        let mut fresh = EcoBlockContext::with_keypair(keypair);
+        fresh.node_lock = Some(NodeLock::acquire(Path::new(&path))?);
+        fresh.node_path = Some(PathBuf::from(&path));
+        fresh.open_block_store(&block_store::block_store_path(&path))?;
+        fresh.saved_queries.load(&saved_queries::saved_queries_path(Path::new(&path)))?;
+        fresh.materialized_views.load(&materialized_views::materialized_views_path(Path::new(&path)))?;
+        fresh.s3_backups.load(&s3_backup::s3_backups_path(Path::new(&path)))?;
+        fresh.maintenance.load(&maintenance::maintenance_path(Path::new(&path)))?;
+        fresh.open_topology_log(&topology_log::topology_log_path(Path::new(&path)))?;
+        fresh.jobs.load(&jobs::jobs_path(Path::new(&path)))?;
+        #[cfg(feature = "scripting")]
+        fresh.scripts.load(&scripting::scripts_path(Path::new(&path)))?;
+        *ctx = fresh;
+    }
+    start_index_builder();
+    Ok(node_id)
+}
+
+pub fn shutdown_context() -> Result<(), BridgeError> {
+    let _call = CallTimer::start("shutdown_context");
+    let mut poisoned = false;
+    let mut ctx = CONTEXT.lock().unwrap_or_else(|e| {
+        poisoned = true;
+        CONTEXT.clear_poison();
+        e.into_inner()
+    });
+    if ctx.node_path.is_none() && !poisoned {
+        return Err(BridgeError::NotInitialized("no context to shut down".to_string()));
+    }
+    let flushed = ctx.flush_block_store();
+    *ctx = EcoBlockContext::new();
+    flushed
+}
+
+pub fn declare_geofence(vertices: Vec<GeoPoint>) -> Result<Record, BridgeError> {
+    let _call = CallTimer::start("declare_geofence");
+    let area = Geofence::new(vertices)?;
+    CONTEXT.lock().unwrap().declare_geofence(Some(area))
+}
+
+pub fn clear_geofence() -> Result<Record, BridgeError> {
+    let _call = CallTimer::start("clear_geofence");
+    CONTEXT.lock().unwrap().declare_geofence(None)
+}
+
+pub fn subscribe_geofenced_events<F>(vertices: Vec<GeoPoint>, callback: F) -> Result<u64, BridgeError>
+where
+    F: Fn(BridgeEvent) + Send + Sync + 'static,
+{
+    let _call = CallTimer::start("subscribe_geofenced_events");
+    let area = Geofence::new(vertices)?;
+    let callback: EventCallback = Arc::new(callback);
+    Ok(CONTEXT.lock().unwrap().geofences.subscribe(area, callback))
+}
+
+pub fn enable_home_assistant(config: HomeAssistantConfig) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("enable_home_assistant");
+    CONTEXT.lock().unwrap().enable_home_assistant(config)
+}
+
+pub fn attach_udp_transport(bind_address: String) -> Result<String, BridgeError> {
+    let _call = CallTimer::start("attach_udp_transport");
+    let transport = UdpTransport::bind(&bind_address)?;
+    let address = transport.local_addr()?.to_string();
+    CONTEXT.lock().unwrap().attach_transport(Box::new(transport));
+    Ok(address)
+}
+
+pub fn set_peer_address(peer: PeerId, address: String) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("set_peer_address");
+    CONTEXT.lock().unwrap().set_peer_address(&peer, &address)
+}
+
+pub fn sync_with_peer(peer: PeerId) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("sync_with_peer");
+    CONTEXT.lock().unwrap().sync_with_peer(&peer)
+}
+
+pub fn enable_data_mule(config: DataMuleConfig) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("enable_data_mule");
+    CONTEXT.lock().unwrap().enable_data_mule(config)
+}
+
+pub fn report_encounter(peer: PeerId) -> Result<u64, BridgeError> {
+    let _call = CallTimer::start("report_encounter");
+    let (result, pending) = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        let result = ctx.report_encounter(&peer);
+        (result, ctx.take_pending())
+    };
+    run_pending(pending);
+    result
+}
+
+pub fn set_battery_level(percent: Option<u8>) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("set_battery_level");
+    if percent.is_some_and(|p| p > 100) {
+        return Err(BridgeError::InvalidInput(format!("Invalid battery level {:?}: expected 0 to 100", percent)));
+    }
+    CONTEXT.lock().unwrap().relay.battery_percent = percent;
+    Ok(())
+}
+
+pub fn set_relay_config(config: RelayConfig) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("set_relay_config");
+    if config.relays == 0 {
+        return Err(BridgeError::InvalidInput("Invalid relay config: at least one relay per neighbourhood".to_string()));
+    }
+    CONTEXT.lock().unwrap().relay.config = config;
+    Ok(())
+}
+
+pub fn set_energy_coefficients(coefficients: EnergyCoefficients) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("set_energy_coefficients");
+    coefficients.validate()?;
+    CONTEXT.lock().unwrap().energy_coefficients = coefficients;
+    Ok(())
+}
+
+pub fn report_power_state(state: PowerState) -> Result<PowerMode, BridgeError> {
+    let _call = CallTimer::start("report_power_state");
+    let (result, pending) = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        let result = ctx.report_power_state(state);
+        (result, ctx.take_pending())
+    };
+    run_pending(pending);
+    result
+}
+
+pub fn set_power_thresholds(thresholds: PowerThresholds) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("set_power_thresholds");
+    thresholds.validate()?;
+    CONTEXT.lock().unwrap().power.thresholds = thresholds;
+    Ok(())
+}
+
+pub fn set_quiet_windows(windows: Vec<QuietWindow>) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("set_quiet_windows");
+    CONTEXT.lock().unwrap().quiet_hours.set_windows(windows)
+}
+
+pub fn set_airtime_budget(transport: String, budget: Option<DutyCycleBudget>) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("set_airtime_budget");
+    CONTEXT.lock().unwrap().airtime.set_budget(&transport, budget)
+}
+
+pub fn send_directed(record: Record, destination: PeerId) -> Result<DirectedDelivery, BridgeError> {
+    let _call = CallTimer::start("send_directed");
+    let (result, pending) = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        let result = ctx.send_directed(record, &destination, None);
+        (result, ctx.take_pending())
+    };
+    run_pending(pending);
+    result
+}
+
+pub fn send_directed_by(record: Record, destination: PeerId, deadline: u64) -> Result<DirectedDelivery, BridgeError> {
+    let _call = CallTimer::start("send_directed_by");
+    let (result, pending) = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        let result = ctx.send_directed(record, &destination, Some(deadline));
+        (result, ctx.take_pending())
+    };
+    run_pending(pending);
+    result
+}
+
+pub fn open_explorer(start_id: String) -> Result<u64, BridgeError> {
+    let _call = CallTimer::start("open_explorer");
+    CONTEXT.lock().unwrap().open_explorer(&start_id)
+}
+
+pub fn expand(session: u64, node: String) -> Result<ExplorerDelta, BridgeError> {
+    let _call = CallTimer::start("expand");
+    CONTEXT.lock().unwrap().expand_explorer_node(session, &node)
+}
+
+pub fn collapse(session: u64, node: String) -> Result<ExplorerDelta, BridgeError> {
+    let _call = CallTimer::start("collapse");
+    CONTEXT.lock().unwrap().collapse_explorer_node(session, &node)
+}
+
+pub fn current_view(session: u64) -> Result<ExplorerView, BridgeError> {
+    let _call = CallTimer::start("current_view");
+    CONTEXT.lock().unwrap().explorer_view(session)
+}
+
+pub fn register_saved_query(query: SavedQuery) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("register_saved_query");
+    CONTEXT.lock().unwrap().register_saved_query(query)
+}
+
+pub fn remove_saved_query(name: String) -> Result<bool, BridgeError> {
+    let _call = CallTimer::start("remove_saved_query");
+    CONTEXT.lock().unwrap().remove_saved_query(&name)
+}
+
+pub fn run_saved_query(name: String, params: BTreeMap<String, String>) -> Result<SavedQueryResult, BridgeError> {
+    let _call = CallTimer::start("run_saved_query");
+    CONTEXT.lock().unwrap().run_saved_query(&name, params)
+}
+
+pub fn declare_materialized_view(definition: ViewDefinition) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("declare_materialized_view");
+    CONTEXT.lock().unwrap().declare_materialized_view(definition)
+}
+
+pub fn drop_materialized_view(name: String) -> Result<bool, BridgeError> {
+    let _call = CallTimer::start("drop_materialized_view");
+    CONTEXT.lock().unwrap().drop_materialized_view(&name)
+}
+
+pub fn materialized_view_rows(name: String) -> Result<Vec<ViewRow>, BridgeError> {
+    let _call = CallTimer::start("materialized_view_rows");
+    CONTEXT.lock().unwrap().materialized_view_rows(&name)
+}
+
+pub fn materialized_view_row(name: String, key: String) -> Result<Option<ViewRow>, BridgeError> {
+    let _call = CallTimer::start("materialized_view_row");
+    CONTEXT.lock().unwrap().materialized_view_row(&name, &key)
+}
+
+pub fn enable_gateway(config: GatewayConfig) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("enable_gateway");
+    CONTEXT.lock().unwrap().enable_gateway(config)
+}
+
+pub fn backup_to_s3() -> Result<S3Backup, BridgeError> {
+    let _call = CallTimer::start("backup_to_s3");
+    CONTEXT.lock().unwrap().backup_to_s3()
+}
+
+pub fn restore_from_s3(key: String) -> Result<SnapshotImport, BridgeError> {
+    let _call = CallTimer::start("restore_from_s3");
+    CONTEXT.lock().unwrap().restore_from_s3(&key)
+}
+
+pub fn enable_cloud_sync(config: CloudSyncConfig) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("enable_cloud_sync");
+    CONTEXT.lock().unwrap().enable_cloud_sync(config)
+}
+
+pub fn cloud_sync_now() -> Result<CloudSyncReport, BridgeError> {
+    let _call = CallTimer::start("cloud_sync_now");
+    let (result, pending) = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        (ctx.cloud_sync_now(), ctx.take_pending())
+    };
+    run_pending(pending);
+    result
+}
+
+pub fn run_sync_slice(peer: PeerId, max_ms: u64) -> Result<SyncSliceReport, BridgeError> {
+    let _call = CallTimer::start("run_sync_slice");
+    let started = clock::now_millis();
+    let received_before = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        ctx.begin_sync_slice(&peer)?;
+        ctx.sync.stats().blocks_received
+    };
+    let mut report = SyncSliceReport::default();
+    let mut last_frame = started;
+    loop {
+        let (handled, pending) = {
+            let mut ctx = CONTEXT.lock().unwrap();
+            (ctx.poll_transport(sync::FRAMES_PER_STEP), ctx.take_pending())
+        };
+        run_pending(pending);
+        let now = clock::now_millis();
+        report.frames += handled;
+        if handled > 0 {
+            last_frame = now;
+        } else if now.saturating_sub(last_frame) >= maintenance::SYNC_IDLE_MS {
+            report.finished = true;
+            break;
+        }
+        let elapsed = now.saturating_sub(started);
+        if elapsed >= max_ms {
+            break;
+        }
+        if handled == 0 {
+            std::thread::sleep(std::time::Duration::from_millis(maintenance::SYNC_POLL_MS.min(max_ms - elapsed)));
+        }
+    }
+    let mut ctx = CONTEXT.lock().unwrap();
+    report.blocks_received = ctx.sync.stats().blocks_received.saturating_sub(received_before);
+    report.elapsed_ms = clock::now_millis().saturating_sub(started);
+    ctx.end_sync_slice(&peer, &report);
+    Ok(report)
+}
+
+#[cfg(feature = "presentation")]
+pub fn set_metric_format(format: MetricFormat) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("set_metric_format");
+    CONTEXT.lock().unwrap().metric_formats.set(format)
+}
+
+#[cfg(unix)]
+pub fn serve_ipc(socket_path: Option<String>) -> Result<String, BridgeError> {
+    let _call = CallTimer::start("serve_ipc");
+    let mut ctx = CONTEXT.lock().unwrap();
+    let Some(node_path) = ctx.node_path.clone() else {
+        return Err(BridgeError::NotInitialized("call init_context before serve_ipc".to_string()));
+    };
+    if ctx.config.single_threaded {
+        return Err(BridgeError::InvalidInput("Invalid config: serving IPC needs threads and single_threaded is set".to_string()));
+    }
+    let socket = socket_path.map(PathBuf::from).unwrap_or_else(|| ipc::ipc_socket_path(&node_path));
+    let restart: RestartHook = Arc::new(|_| {
+        let mut ctx = CONTEXT.lock().unwrap();
+        let Some(generation) = ctx.watchdog.generation(watchdog::IPC) else {
+            return;
+        };
+        if let Err(e) = ctx.ipc.restart(generation) {
+            ctx.log(LogLevel::Error, format!("IPC accept loop not restarted: {}", e));
+        }
+    });
+    let generation = ctx.watchdog.watch(watchdog::IPC, watchdog::IPC_TIMEOUT, Some(restart), clock::now_secs());
+    if let Err(e) = ctx.ipc.serve(&socket, generation) {
+        ctx.watchdog.unwatch(watchdog::IPC);
+        return Err(e);
+    }
+    Ok(socket.display().to_string())
+}
+
+pub fn publish_stats_summary() -> Result<String, BridgeError> {
+    let _call = CallTimer::start("publish_stats_summary");
+    CONTEXT.lock().unwrap().publish_stats_summary()
+}
+
+pub fn lineage(block_id: BlockId) -> Result<Lineage, BridgeError> {
+    let _call = CallTimer::start("lineage");
+    CONTEXT.lock().unwrap().lineage(block_id.as_str())
+}
+
+pub fn enqueue_job(kind: String, payload: String) -> Result<u64, BridgeError> {
+    let _call = CallTimer::start("enqueue_job");
+    let payload = serde_json::from_str(&payload).map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+    let (id, pending) = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        let id = ctx.enqueue_job(&kind, payload)?;
+        (id, ctx.take_pending())
+    };
+    run_pending(pending);
+    Ok(id)
+}
+
+pub fn complete_job(id: u64) -> Result<bool, BridgeError> {
+    let _call = CallTimer::start("complete_job");
+    CONTEXT.lock().unwrap().complete_job(id)
+}
+
+pub fn set_encoding_policy(policy: EncodingPolicy) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("set_encoding_policy");
+    CONTEXT.lock().unwrap().set_encoding_policy(policy)
+}
+
+pub fn send_to_many(record: Record, targets: Vec<PeerId>, deadline: Option<u64>) -> Result<MulticastDelivery, BridgeError> {
+    let _call = CallTimer::start("send_to_many");
+    let (result, pending) = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        let result = ctx.send_to_many(record, &targets, deadline);
+        (result, ctx.take_pending())
+    };
+    run_pending(pending);
+    result
+}
+
+pub fn simulate_retention(policy: RetentionPolicy) -> Result<RetentionForecast, BridgeError> {
+    let _call = CallTimer::start("simulate_retention");
+    CONTEXT.lock().unwrap().simulate_retention(policy)
+}
+
+pub fn tag_peer(node_id: PeerId, group: String) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("tag_peer");
+    CONTEXT.lock().unwrap().tag_peer(&node_id, &group)
+}
+
+pub fn set_group_policy(group: String, policy: GroupPolicy) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("set_group_policy");
+    CONTEXT.lock().unwrap().set_group_policy(&group, policy)
+}
+
+#[cfg(feature = "scripting")]
+pub fn install_script(definition: ScriptDefinition) -> Result<(), BridgeError> {
+    let _call = CallTimer::start("install_script");
+    CONTEXT.lock().unwrap().install_script(definition)
+}
+
+#[cfg(feature = "scripting")]
+pub fn remove_script(name: String) -> Result<bool, BridgeError> {
+    let _call = CallTimer::start("remove_script");
+    CONTEXT.lock().unwrap().remove_script(&name)
+}
+
+#[cfg(feature = "sparkplug")]
+pub fn ingest_sparkplug(topic: String, payload: Vec<u8>) -> Result<SparkplugIngest, BridgeError> {
+    let _call = CallTimer::start("ingest_sparkplug");
+    let (result, pending) = {
+        let mut ctx = CONTEXT.lock().unwrap();
+        let result = ctx.ingest_sparkplug(&topic, &payload);
+        (result, ctx.take_pending())
+    };
+    run_pending(pending);
+    result
+}
+
+pub fn open_tangle(path: String) -> Result<usize, BridgeError> {
+    let _call = CallTimer::start("open_tangle");
+    open_block_store(block_store::block_store_path(&path).display().to_string())
+}
+
+pub fn open_block_store(file_path: String) -> Result<usize, BridgeError> {
+    let _call = CallTimer::start("open_block_store");
+    let loaded = CONTEXT.lock().unwrap().open_block_store(Path::new(&file_path))?;
+    start_index_builder();
+    Ok(loaded)
+}
+
+pub fn flush_block_store() -> Result<(), BridgeError> {
+    let _call = CallTimer::start("flush_block_store");
+    CONTEXT.lock().unwrap().flush_block_store()
+}
+
+pub fn compact_block_store() -> Result<BlockStoreStatus, BridgeError> {
+    let _call = CallTimer::start("compact_block_store");
+    CONTEXT.lock().unwrap().compact_block_store()
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::readings::Reading;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RateAction {
+    /// Insert the block but flag it as erroneous.
+    Flag,
+    /// Refuse to create the block.
+    Reject,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateRule {
+    pub metric: String,
+    /// Largest plausible change, in metric units per second.
+    pub max_rate_per_sec: f64,
+    pub action: RateAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateViolation {
+    pub metric: String,
+    pub sensor_id: String,
+    pub previous: f64,
+    pub current: f64,
+    pub rate_per_sec: f64,
+    pub action: RateAction,
+}
+
+impl std::fmt::Display for RateViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} on {} changed from {} to {} ({:.3}/s)",
+            self.metric, self.sensor_id, self.previous, self.current, self.rate_per_sec
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RateValidator {
+    rules: HashMap<String, RateRule>,
+}
+
+impl RateValidator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_rule(&mut self, rule: RateRule) {
+        self.rules.insert(rule.metric.clone(), rule);
+    }
+
+    pub fn remove_rule(&mut self, metric: &str) -> bool {
+        self.rules.remove(metric).is_some()
+    }
+
+    pub fn rules(&self) -> Vec<RateRule> {
+        self.rules.values().cloned().collect()
+    }
+
+    /// Compares each ruled metric with the sensor's previous accepted value.
+    /// Readings less than a second apart are compared as if one second apart.
+    pub fn check<F>(&self, reading: &Reading, previous: F) -> Vec<RateViolation>
+    where
+        F: Fn(&str, &str) -> Option<(u64, f64)>,
+    {
+        let mut violations = Vec::new();
+        for rule in self.rules.values() {
+            let Some(current) = reading.value(&rule.metric) else {
+                continue;
+            };
+            let Some((prev_ts, prev)) = previous(&reading.sensor_id, &rule.metric) else {
+                continue;
+            };
+            let dt = reading.timestamp.abs_diff(prev_ts).max(1) as f64;
+            let rate = (current - prev).abs() / dt;
+            if rate > rule.max_rate_per_sec {
+                violations.push(RateViolation {
+                    metric: rule.metric.clone(),
+                    sensor_id: reading.sensor_id.clone(),
+                    previous: prev,
+                    current,
+                    rate_per_sec: rate,
+                    action: rule.action,
+                });
+            }
+        }
+        violations.sort_by(|a, b| a.metric.cmp(&b.metric));
+        violations
+    }
+}
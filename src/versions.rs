@@ -0,0 +1,160 @@
+use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use crate::error::BridgeError;
+use crate::records::Record;
+use crate::{clock, EcoBlockContext};
+
+pub const VERSION_KIND: &str = "version";
+pub const DEFAULT_VERSION_INTERVAL: u64 = 6 * 3600;
+
+/// The build a node runs, as it signs it into `version` records.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SoftwareVersion {
+    pub node_id: String,
+    pub crate_version: String,
+    /// `None` when the build was made outside a git checkout.
+    pub git_hash: Option<String>,
+    /// Target OS and architecture, e.g. `android-aarch64`.
+    pub platform: String,
+    pub published_at: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BuildStatus {
+    Current,
+    /// An older crate version than the newest one seen in the mesh.
+    Outdated,
+    /// A git hash missing from the known builds, or an unparsable version.
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeVersion {
+    pub version: SoftwareVersion,
+    pub status: BuildStatus,
+}
+
+/// The build of this binary. The git hash is captured by the build script.
+pub fn local_build(node_id: &str, now: u64) -> SoftwareVersion {
+    SoftwareVersion {
+        node_id: node_id.to_string(),
+        crate_version: env!("CARGO_PKG_VERSION").to_string(),
+        git_hash: option_env!("ECOBLOCK_GIT_HASH").map(str::to_string),
+        platform: format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+        published_at: now,
+    }
+}
+
+/// Numeric `major.minor.patch`, ignoring any pre-release or build suffix.
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let core = version.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|p| p.parse::<u64>().ok());
+    let version = (parts.next()??, parts.next()??, parts.next()??);
+    parts.next().is_none().then_some(version)
+}
+
+pub struct VersionState {
+    pub interval: u64,
+    /// Git hashes of builds operators have vetted. When empty every hash is accepted.
+    pub known_builds: HashSet<String>,
+    last_published: Option<u64>,
+    remote: HashMap<String, SoftwareVersion>,
+}
+
+impl Default for VersionState {
+    fn default() -> Self {
+        Self {
+            interval: DEFAULT_VERSION_INTERVAL,
+            known_builds: HashSet::new(),
+            last_published: None,
+            remote: HashMap::new(),
+        }
+    }
+}
+
+impl VersionState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_due(&self, now: u64) -> bool {
+        self.last_published.is_none_or(|last| now.saturating_sub(last) >= self.interval)
+    }
+
+    pub fn mark_published(&mut self, now: u64) {
+        self.last_published = Some(now);
+    }
+
+    /// Keeps only the most recent version per node.
+    pub fn receive(&mut self, version: SoftwareVersion) {
+        match self.remote.get(&version.node_id) {
+            Some(existing) if existing.published_at >= version.published_at => {}
+            _ => {
+                self.remote.insert(version.node_id.clone(), version);
+            }
+        }
+    }
+
+    fn status(&self, version: &SoftwareVersion, newest: Option<(u64, u64, u64)>) -> BuildStatus {
+        let known = self.known_builds.is_empty()
+            || version.git_hash.as_ref().is_some_and(|h| self.known_builds.contains(h));
+        match parse_version(&version.crate_version) {
+            Some(parsed) if known => {
+                if newest.is_some_and(|n| parsed < n) {
+                    BuildStatus::Outdated
+                } else {
+                    BuildStatus::Current
+                }
+            }
+            _ => BuildStatus::Unknown,
+        }
+    }
+
+    /// Every known node's latest version, sorted by node id, rated against the
+    /// newest crate version among them.
+    pub fn network(&self, local: SoftwareVersion) -> Vec<NodeVersion> {
+        let mut versions: Vec<SoftwareVersion> = self
+            .remote
+            .values()
+            .filter(|v| v.node_id != local.node_id)
+            .cloned()
+            .collect();
+        versions.push(local);
+        versions.sort_by(|a, b| a.node_id.cmp(&b.node_id));
+        let newest = versions.iter().filter_map(|v| parse_version(&v.crate_version)).max();
+        versions
+            .into_iter()
+            .map(|version| NodeVersion {
+                status: self.status(&version, newest),
+                version,
+            })
+            .collect()
+    }
+}
+
+impl EcoBlockContext {
+    /// Signs this node's build as a `version` record for gossip.
+    pub fn publish_version(&mut self) -> Result<Record, BridgeError> {
+        let now = clock::now_secs();
+        let local = local_build(&self.keypair.public_key_hex(), now);
+        let body = serde_json::to_value(&local).unwrap_or_default();
+        let record = self.sign_record(VERSION_KIND, body, now)?;
+        self.records.insert(record.clone());
+        self.versions.mark_published(now);
+        Ok(record)
+    }
+
+    pub(crate) fn apply_version(&mut self, record: &Record) -> Result<(), BridgeError> {
+        let version: SoftwareVersion = serde_json::from_value(record.body.clone())
+            .map_err(|e| BridgeError::Deserialization(e.to_string()))?;
+        if version.node_id != record.author {
+            return Err(BridgeError::Unauthorized(format!("Version record {} not signed by its node", record.id)));
+        }
+        self.versions.receive(version);
+        Ok(())
+    }
+
+    pub fn network_versions(&self) -> Vec<NodeVersion> {
+        self.versions.network(local_build(&self.keypair.public_key_hex(), clock::now_secs()))
+    }
+}
@@ -0,0 +1,161 @@
+//! Coordinated wake windows. A policy can set a `WakeSchedule`: time is cut
+//! into periods from a shared epoch and each period into slots. A node on
+//! battery keeps its radio on only during its own slot, derived from its node
+//! id so every neighbour can compute it. Blocks gossiped to a sleeping peer are
+//! held until its next window opens.
+
+use std::collections::{HashMap, VecDeque};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::error::BridgeError;
+use crate::peer_id::PeerId;
+use crate::{clock, EcoBlockContext};
+
+/// Blocks held per sleeping peer; older ones are left to the next sync summary.
+pub const MAX_DEFERRED_PER_PEER: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WakeSchedule {
+    /// Start of the first period, in seconds.
+    pub epoch: u64,
+    /// Length of a period, in seconds.
+    pub period: u64,
+    /// How long each slot's radio stays on, in seconds.
+    pub window: u64,
+    /// Slots per period; slot `n` opens `n * period / slots` into the period.
+    pub slots: u32,
+}
+
+impl WakeSchedule {
+    pub fn validate(&self) -> Result<(), BridgeError> {
+        if self.period == 0 || self.window == 0 || self.slots == 0 {
+            return Err(BridgeError::InvalidInput("Invalid wake schedule: period, window and slots must be positive".to_string()));
+        }
+        if self.window > self.period / u64::from(self.slots) {
+            return Err(BridgeError::InvalidInput(format!(
+                "Invalid wake schedule: {} slots of {} seconds do not fit in a {} second period",
+                self.slots, self.window, self.period
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn slot_of(&self, node_id: &str) -> u32 {
+        let digest = Sha256::digest(node_id.as_bytes());
+        let mut head = [0u8; 8];
+        head.copy_from_slice(&digest[..8]);
+        (u64::from_be_bytes(head) % u64::from(self.slots)) as u32
+    }
+
+    /// Start and end of `node_id`'s window that is open at `now`, or else the
+    /// next one.
+    pub fn next_window(&self, node_id: &str, now: u64) -> (u64, u64) {
+        let offset = u64::from(self.slot_of(node_id)) * (self.period / u64::from(self.slots));
+        let period_start = self.epoch + now.saturating_sub(self.epoch) / self.period * self.period;
+        let start = period_start + offset;
+        if now < start + self.window {
+            (start, start + self.window)
+        } else {
+            (start + self.period, start + self.period + self.window)
+        }
+    }
+
+    pub fn is_awake(&self, node_id: &str, now: u64) -> bool {
+        let (start, end) = self.next_window(node_id, now);
+        now >= start && now < end
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct WakeState {
+    schedule: Option<WakeSchedule>,
+    deferred: HashMap<PeerId, VecDeque<String>>,
+}
+
+impl WakeState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn schedule(&self) -> Option<WakeSchedule> {
+        self.schedule
+    }
+
+    pub(crate) fn set_schedule(&mut self, schedule: Option<WakeSchedule>) {
+        if schedule.is_none() {
+            // Held blocks go out on the next gossip or sync instead.
+            self.deferred.clear();
+        }
+        self.schedule = schedule;
+    }
+
+    fn defer(&mut self, peer: &PeerId, block_id: &str) {
+        let queue = self.deferred.entry(peer.clone()).or_default();
+        if queue.len() == MAX_DEFERRED_PER_PEER {
+            queue.pop_front();
+        }
+        queue.push_back(block_id.to_string());
+    }
+
+    /// Blocks waiting for each sleeping peer's window.
+    pub fn deferred(&self) -> HashMap<PeerId, usize> {
+        self.deferred.iter().map(|(peer, queue)| (peer.clone(), queue.len())).collect()
+    }
+}
+
+impl EcoBlockContext {
+    /// Whether `node_id` follows the schedule: it announced a battery level,
+    /// or is this node and the host reported one.
+    fn duty_cycled(&self, node_id: &str) -> bool {
+        if node_id == self.keypair.public_key_hex() {
+            return self.relay.battery_percent.is_some();
+        }
+        self.relay.metrics_of(node_id).is_some_and(|m| m.battery_percent.is_some())
+    }
+
+    /// The window in which `node_id` listens next, or `None` when its radio is
+    /// always on.
+    pub fn wake_window_of(&self, node_id: &str, now: u64) -> Option<(u64, u64)> {
+        let schedule = self.wake.schedule?;
+        self.duty_cycled(node_id).then(|| schedule.next_window(node_id, now))
+    }
+
+    /// Whether this node's radio should be on at `now`.
+    pub fn radio_awake(&self, now: u64) -> bool {
+        self.wake_window_of(&self.keypair.public_key_hex(), now)
+            .is_none_or(|(start, end)| now >= start && now < end)
+    }
+
+    /// Holds `block_id` for `peer` when its radio is off; true if held.
+    pub(crate) fn defer_until_awake(&mut self, peer: &PeerId, block_id: &str) -> bool {
+        let now = clock::now_secs();
+        let asleep = self.wake_window_of(peer.as_str(), now).is_some_and(|(start, _)| now < start);
+        if asleep {
+            self.wake.defer(peer, block_id);
+        }
+        asleep
+    }
+
+    /// Sends the blocks held for peers whose window is open now.
+    pub fn flush_wake_queue(&mut self) -> usize {
+        let now = clock::now_secs();
+        let awake: Vec<PeerId> = self
+            .wake
+            .deferred
+            .keys()
+            .filter(|peer| self.wake_window_of(peer.as_str(), now).is_none_or(|(start, _)| now >= start))
+            .cloned()
+            .collect();
+        let mut sent = 0;
+        for peer in awake {
+            let ids = self.wake.deferred.remove(&peer).unwrap_or_default();
+            for id in ids {
+                if let Some(block) = self.block_log.get(&id).cloned() {
+                    self.send_block(&peer, &block);
+                    sent += 1;
+                }
+            }
+        }
+        sent
+    }
+}
@@ -0,0 +1,328 @@
+//! Heartbeat deadlines for long-running work. Bridge threads (the index
+//! builder and the IPC accept loop) are restarted when they stall; each start
+//! gets a new generation, and a thread that finds its generation superseded at
+//! its next heartbeat stops, so a stalled thread that wakes up never runs next
+//! to its replacement. Work the host drives (transport polling, sync sessions
+//! and scheduled tasks) is watched too, and reported when it stops.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use crate::logs::LogLevel;
+use crate::peer_id::PeerId;
+use crate::{clock, EcoBlockContext};
+
+pub type HealthCallback = Arc<dyn Fn(HealthEvent) + Send + Sync>;
+/// Called with the component name to restart a stuck component.
+pub type RestartHook = Arc<dyn Fn(String) + Send + Sync>;
+
+/// Restarts attempted per stall before the component is left degraded.
+pub const MAX_RESTARTS: u32 = 3;
+pub const INDEX_BUILDER: &str = "index_builder";
+pub const INDEX_BUILDER_TIMEOUT: u64 = 30;
+/// The thread accepting IPC connections, while IPC is served.
+pub const IPC: &str = "ipc";
+pub const IPC_TIMEOUT: u64 = 30;
+/// Polling of the attached transport, by `poll_transport` or background steps.
+pub const TRANSPORT: &str = "transport";
+pub const TRANSPORT_TIMEOUT: u64 = 120;
+/// Scheduled backups, cloud syncs, stats and group syncs.
+pub const SCHEDULER: &str = "scheduler";
+pub const SCHEDULER_TIMEOUT: u64 = 24 * 3600;
+/// Slices of a sync session, from its first slice until it finishes.
+pub const SYNC_TIMEOUT: u64 = 600;
+
+/// Shared by every context, so a thread left from a replaced context never
+/// matches a generation handed out later.
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+fn next_generation() -> u64 {
+    NEXT_GENERATION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// The component an open sync session with `peer` is watched as.
+pub fn sync_component(peer: &PeerId) -> String {
+    format!("sync:{}", peer)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HealthState {
+    /// Missed its heartbeat deadline.
+    Degraded,
+    /// Sent a heartbeat again after being degraded.
+    Recovered,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthEvent {
+    pub component: String,
+    pub state: HealthState,
+    /// Seconds since the last heartbeat.
+    pub silent_for: u64,
+    pub restart_attempted: bool,
+    pub at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComponentHealth {
+    pub component: String,
+    pub timeout: u64,
+    pub last_heartbeat: u64,
+    pub degraded: bool,
+    /// Restarts attempted during the current stall.
+    pub restarts: u32,
+    /// Changes with every registration and restart.
+    pub generation: u64,
+}
+
+struct Watched {
+    generation: u64,
+    timeout: u64,
+    last_heartbeat: u64,
+    degraded: bool,
+    restarts: u32,
+    last_restart: u64,
+    restart: Option<RestartHook>,
+}
+
+#[derive(Default)]
+pub struct Watchdog {
+    next_id: u64,
+    subscribers: Vec<(u64, HealthCallback)>,
+    components: BTreeMap<String, Watched>,
+}
+
+impl Watchdog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&mut self, callback: HealthCallback) -> u64 {
+        self.next_id += 1;
+        self.subscribers.push((self.next_id, callback));
+        self.next_id
+    }
+
+    pub fn unsubscribe(&mut self, id: u64) -> bool {
+        let before = self.subscribers.len();
+        self.subscribers.retain(|(sid, _)| *sid != id);
+        self.subscribers.len() != before
+    }
+
+    /// Starts watching `component`, or updates its timeout and hook; the
+    /// registration counts as a heartbeat. Returns the new generation, which
+    /// supersedes any earlier one.
+    pub fn watch(&mut self, component: &str, timeout: u64, restart: Option<RestartHook>, now: u64) -> u64 {
+        let generation = next_generation();
+        self.components.insert(
+            component.to_string(),
+            Watched {
+                generation,
+                timeout: timeout.max(1),
+                last_heartbeat: now,
+                degraded: false,
+                restarts: 0,
+                last_restart: 0,
+                restart,
+            },
+        );
+        generation
+    }
+
+    pub fn unwatch(&mut self, component: &str) -> bool {
+        self.components.remove(component).is_some()
+    }
+
+    pub fn is_watched(&self, component: &str) -> bool {
+        self.components.contains_key(component)
+    }
+
+    /// The generation current threads of `component` must carry.
+    pub fn generation(&self, component: &str) -> Option<u64> {
+        self.components.get(component).map(|w| w.generation)
+    }
+
+    /// Returns the recovery event if the component was degraded.
+    pub fn heartbeat(&mut self, component: &str, now: u64) -> Option<HealthEvent> {
+        let watched = self.components.get_mut(component)?;
+        let silent_for = now.saturating_sub(watched.last_heartbeat);
+        watched.last_heartbeat = now;
+        if !std::mem::replace(&mut watched.degraded, false) {
+            return None;
+        }
+        watched.restarts = 0;
+        Some(HealthEvent {
+            component: component.to_string(),
+            state: HealthState::Recovered,
+            silent_for,
+            restart_attempted: false,
+            at: now,
+        })
+    }
+
+    /// Marks components past their deadline degraded and returns the restart
+    /// hooks to run. A degraded component is restarted again each time another
+    /// timeout passes without a heartbeat, up to `MAX_RESTARTS` times. Each
+    /// restart moves the component to a new generation before its hook runs.
+    pub fn check(&mut self, now: u64) -> (Vec<HealthEvent>, Vec<(String, RestartHook)>) {
+        let mut events = Vec::new();
+        let mut restarts = Vec::new();
+        for (name, watched) in &mut self.components {
+            let silent_for = now.saturating_sub(watched.last_heartbeat);
+            let since_restart = now.saturating_sub(watched.last_restart.max(watched.last_heartbeat));
+            if silent_for <= watched.timeout || (watched.degraded && since_restart <= watched.timeout) {
+                continue;
+            }
+            let restart = watched.restart.clone().filter(|_| watched.restarts < MAX_RESTARTS);
+            if let Some(hook) = &restart {
+                watched.generation = next_generation();
+                watched.restarts += 1;
+                watched.last_restart = now;
+                restarts.push((name.clone(), hook.clone()));
+            }
+            if !watched.degraded {
+                watched.degraded = true;
+                events.push(HealthEvent {
+                    component: name.clone(),
+                    state: HealthState::Degraded,
+                    silent_for,
+                    restart_attempted: restart.is_some(),
+                    at: now,
+                });
+            }
+        }
+        (events, restarts)
+    }
+
+    pub fn components(&self) -> Vec<ComponentHealth> {
+        self.components
+            .iter()
+            .map(|(name, w)| ComponentHealth {
+                component: name.clone(),
+                timeout: w.timeout,
+                last_heartbeat: w.last_heartbeat,
+                degraded: w.degraded,
+                restarts: w.restarts,
+                generation: w.generation,
+            })
+            .collect()
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.components.values().any(|w| w.degraded)
+    }
+
+    fn subscribers(&self) -> Vec<HealthCallback> {
+        self.subscribers.iter().map(|(_, cb)| cb.clone()).collect()
+    }
+}
+
+impl EcoBlockContext {
+    fn queue_health_events(&mut self, events: &[HealthEvent]) {
+        for event in events {
+            let (level, what) = match event.state {
+                HealthState::Degraded => (LogLevel::Warn, "missed its heartbeat"),
+                HealthState::Recovered => (LogLevel::Info, "recovered"),
+            };
+            self.log(level, format!("Watchdog: {} {} after {}s", event.component, what, event.silent_for));
+            for callback in self.watchdog.subscribers() {
+                let event = event.clone();
+                self.pending.push(Box::new(move || callback(event)));
+            }
+        }
+    }
+
+    pub fn component_heartbeat(&mut self, component: &str) {
+        let event = self.watchdog.heartbeat(component, clock::now_secs());
+        self.queue_health_events(&event.into_iter().collect::<Vec<_>>());
+    }
+
+    /// Heartbeat of a bridge thread started as `generation`. False once a
+    /// restart has replaced it, and the thread must stop.
+    pub(crate) fn worker_heartbeat(&mut self, component: &str, generation: u64) -> bool {
+        if self.watchdog.generation(component).is_some_and(|current| current != generation) {
+            return false;
+        }
+        self.component_heartbeat(component);
+        true
+    }
+
+    /// Heartbeat of host-driven work, which starts being watched the first
+    /// time it runs.
+    pub(crate) fn watch_or_heartbeat(&mut self, component: &str, timeout: u64, restart: Option<RestartHook>) {
+        if self.watchdog.is_watched(component) {
+            self.component_heartbeat(component);
+        } else {
+            self.watchdog.watch(component, timeout, restart, clock::now_secs());
+        }
+    }
+
+    /// Raises degraded-health events for stalled components and queues their
+    /// restarts. Restarts run with the other callbacks, outside the lock.
+    pub fn check_watchdog(&mut self) -> Vec<HealthEvent> {
+        let (events, restarts) = self.watchdog.check(clock::now_secs());
+        for (name, hook) in restarts {
+            self.log(LogLevel::Warn, format!("Watchdog: restarting {}", name));
+            self.pending.push(Box::new(move || hook(name)));
+        }
+        self.queue_health_events(&events);
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use super::*;
+
+    #[test]
+    fn a_restart_supersedes_the_stalled_generation() {
+        let mut ctx = EcoBlockContext::new();
+        let restarted = Arc::new(AtomicUsize::new(0));
+        let counter = restarted.clone();
+        let hook: RestartHook = Arc::new(move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+        let stalled = ctx.watchdog.watch(INDEX_BUILDER, 5, Some(hook), 100);
+        assert!(ctx.worker_heartbeat(INDEX_BUILDER, stalled));
+
+        let (events, restarts) = ctx.watchdog.check(clock::now_secs() + 10);
+        assert_eq!(events.len(), 1);
+        assert!(events[0].restart_attempted);
+        for (name, hook) in restarts {
+            hook(name);
+        }
+        assert_eq!(restarted.load(Ordering::Relaxed), 1);
+
+        let current = ctx.watchdog.generation(INDEX_BUILDER).unwrap();
+        assert_ne!(current, stalled);
+        assert!(!ctx.worker_heartbeat(INDEX_BUILDER, stalled));
+        assert!(ctx.worker_heartbeat(INDEX_BUILDER, current));
+        assert!(!ctx.watchdog.is_degraded());
+    }
+
+    #[test]
+    fn generations_are_never_reused_across_contexts() {
+        let first = EcoBlockContext::new().watchdog.watch(IPC, IPC_TIMEOUT, None, 0);
+        let second = EcoBlockContext::new().watchdog.watch(IPC, IPC_TIMEOUT, None, 0);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn host_driven_work_is_watched_from_its_first_run() {
+        let mut ctx = EcoBlockContext::new();
+        assert!(!ctx.watchdog.is_watched(SCHEDULER));
+        ctx.scheduler_heartbeat();
+        let health = ctx.watchdog.components();
+        assert_eq!(health.len(), 1);
+        assert_eq!((health[0].component.as_str(), health[0].timeout), (SCHEDULER, SCHEDULER_TIMEOUT));
+
+        let (events, restarts) = ctx.watchdog.check(clock::now_secs() + SCHEDULER_TIMEOUT + 1);
+        assert_eq!(events.len(), 1);
+        assert!(!events[0].restart_attempted);
+        assert!(restarts.is_empty());
+        ctx.scheduler_heartbeat();
+        assert!(!ctx.watchdog.is_degraded());
+    }
+}
@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+pub type WindowCallback = Arc<dyn Fn(WindowedAggregate) + Send + Sync>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowedAggregate {
+    pub subscription_id: u64,
+    pub metric: String,
+    pub window_start: u64,
+    pub window_end: u64,
+    pub count: usize,
+    pub mean: f64,
+    pub max: f64,
+    /// Least-squares slope of the window, in metric units per second.
+    pub slope: f64,
+}
+
+impl WindowedAggregate {
+    fn compute(subscription_id: u64, metric: &str, start: u64, end: u64, samples: &[(u64, f64)]) -> Self {
+        let count = samples.len();
+        let n = count as f64;
+        let mean = samples.iter().map(|(_, v)| v).sum::<f64>() / n;
+        let max = samples.iter().map(|(_, v)| *v).fold(f64::NEG_INFINITY, f64::max);
+        let mean_t = samples.iter().map(|(t, _)| (*t - start) as f64).sum::<f64>() / n;
+        let (mut cov, mut var) = (0.0, 0.0);
+        for (t, v) in samples {
+            let dt = (*t - start) as f64 - mean_t;
+            cov += dt * (v - mean);
+            var += dt * dt;
+        }
+        Self {
+            subscription_id,
+            metric: metric.to_string(),
+            window_start: start,
+            window_end: end,
+            count,
+            mean,
+            max,
+            slope: if var == 0.0 { 0.0 } else { cov / var },
+        }
+    }
+}
+
+struct WindowSubscription {
+    id: u64,
+    metric: String,
+    window: u64,
+    step: u64,
+    next_emit: Option<u64>,
+    callback: WindowCallback,
+}
+
+#[derive(Default)]
+pub struct WindowSubscriptions {
+    next_id: u64,
+    subscriptions: Vec<WindowSubscription>,
+}
+
+impl WindowSubscriptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&mut self, metric: &str, window: u64, step: u64, callback: WindowCallback) -> u64 {
+        self.next_id += 1;
+        self.subscriptions.push(WindowSubscription {
+            id: self.next_id,
+            metric: metric.to_string(),
+            window,
+            step: step.max(1),
+            next_emit: None,
+            callback,
+        });
+        self.next_id
+    }
+
+    pub fn unsubscribe(&mut self, id: u64) -> bool {
+        let before = self.subscriptions.len();
+        self.subscriptions.retain(|s| s.id != id);
+        self.subscriptions.len() != before
+    }
+
+    pub fn len(&self) -> usize {
+        self.subscriptions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subscriptions.is_empty()
+    }
+
+    /// Called for each new reading. A subscription emits at most once per `step`
+    /// seconds of reading time, covering the `window` seconds ending at `timestamp`.
+    pub fn on_reading<F>(&mut self, timestamp: u64, has_metric: impl Fn(&str) -> bool, series: F) -> Vec<(WindowCallback, WindowedAggregate)>
+    where
+        F: Fn(&str, u64, u64) -> Vec<(u64, f64)>,
+    {
+        let mut due = Vec::new();
+        for sub in self.subscriptions.iter_mut() {
+            if !has_metric(&sub.metric) || sub.next_emit.is_some_and(|next| timestamp < next) {
+                continue;
+            }
+            let start = timestamp.saturating_sub(sub.window);
+            let samples = series(&sub.metric, start, timestamp);
+            if samples.is_empty() {
+                continue;
+            }
+            sub.next_emit = Some(timestamp.saturating_add(sub.step));
+            let aggregate = WindowedAggregate::compute(sub.id, &sub.metric, start, timestamp, &samples);
+            due.push((sub.callback.clone(), aggregate));
+        }
+        due
+    }
+}
@@ -0,0 +1,244 @@
+use ecoblock_storage::tangle::block::TangleBlock;
+use serde::{Deserialize, Serialize};
+use crate::canonical::CanonicalBlock;
+use crate::error::BridgeError;
+use crate::hybrid::HybridBlock;
+use crate::pow::{AnnouncementBody, ANNOUNCEMENT_KIND};
+use crate::records::Record;
+use crate::suites::Suite;
+use crate::transport::MAX_DATAGRAM_LEN;
+
+pub const WIRE_VERSION: u8 = 2;
+/// Version byte, kind byte, suite byte and big-endian u32 payload length.
+pub const FRAME_HEADER_LEN: usize = 7;
+/// Version 1 frames have no suite byte and imply the default suite.
+pub const V1_FRAME_HEADER_LEN: usize = 6;
+/// Upper bound on any payload sent or accepted from the network, so a frame
+/// always fits in one datagram; larger inputs are rejected before anything is
+/// allocated for them.
+pub const MAX_PAYLOAD_LEN: usize = MAX_DATAGRAM_LEN - FRAME_HEADER_LEN;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FrameKind {
+    Block,
+    Handshake,
+    /// Log entries sealed to the admin a node is streaming its logs to.
+    LogStream,
+    /// Block summaries and requests of the peer sync protocol.
+    Sync,
+    /// A record addressed to one node, forwarded hop by hop.
+    Routed,
+}
+
+impl FrameKind {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            1 => Some(FrameKind::Block),
+            2 => Some(FrameKind::Handshake),
+            3 => Some(FrameKind::LogStream),
+            4 => Some(FrameKind::Sync),
+            5 => Some(FrameKind::Routed),
+            _ => None,
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            FrameKind::Block => 1,
+            FrameKind::Handshake => 2,
+            FrameKind::LogStream => 3,
+            FrameKind::Sync => 4,
+            FrameKind::Routed => 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Frame {
+    pub version: u8,
+    pub kind: FrameKind,
+    pub suite: Suite,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(kind: FrameKind, payload: Vec<u8>) -> Self {
+        Self {
+            version: WIRE_VERSION,
+            kind,
+            suite: Suite::default(),
+            payload,
+        }
+    }
+
+    pub fn with_suite(mut self, suite: Suite) -> Self {
+        self.suite = suite;
+        self
+    }
+
+    /// Fails for payloads over `MAX_PAYLOAD_LEN`, which no peer would accept.
+    pub fn encode(&self) -> Result<Vec<u8>, BridgeError> {
+        bounded(&self.payload)?;
+        let mut out = Vec::with_capacity(FRAME_HEADER_LEN + self.payload.len());
+        out.push(self.version);
+        out.push(self.kind.to_byte());
+        if self.version >= 2 {
+            out.push(self.suite.id());
+        }
+        out.extend_from_slice(&(self.payload.len() as u32).to_be_bytes());
+        out.extend_from_slice(&self.payload);
+        Ok(out)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "block", rename_all = "snake_case")]
+pub enum BlockEnvelope {
+    Sensor(TangleBlock),
+    /// A sensor block with a canonical signature alongside its own.
+    CanonicalSensor(CanonicalBlock),
+    /// A sensor block with a post-quantum signature alongside its own.
+    HybridSensor(HybridBlock),
+    Record(Record),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Handshake {
+    pub record: Record,
+    pub announcement: AnnouncementBody,
+}
+
+fn bounded(bytes: &[u8]) -> Result<&[u8], BridgeError> {
+    if bytes.len() > MAX_PAYLOAD_LEN {
+        return Err(BridgeError::Wire(format!("payload of {} bytes exceeds {}", bytes.len(), MAX_PAYLOAD_LEN)));
+    }
+    Ok(bytes)
+}
+
+/// Parses exactly one frame of either wire version; trailing bytes are an error.
+pub fn parse_frame(bytes: &[u8]) -> Result<Frame, BridgeError> {
+    let Some(&version) = bytes.first() else {
+        return Err(BridgeError::Wire("truncated frame header".to_string()));
+    };
+    let header_len = match version {
+        1 => V1_FRAME_HEADER_LEN,
+        WIRE_VERSION => FRAME_HEADER_LEN,
+        _ => return Err(BridgeError::Wire(format!("unsupported version {}", version))),
+    };
+    if bytes.len() < header_len {
+        return Err(BridgeError::Wire("truncated frame header".to_string()));
+    }
+    let (header, rest) = bytes.split_at(header_len);
+    let kind = FrameKind::from_byte(header[1]).ok_or_else(|| BridgeError::Wire(format!("unknown frame kind {}", header[1])))?;
+    let suite = if version == 1 {
+        Suite::default()
+    } else {
+        Suite::from_id(header[2]).ok_or_else(|| BridgeError::Wire(format!("unknown suite {}", header[2])))?
+    };
+    let len_bytes: [u8; 4] = header[header_len - 4..].try_into().map_err(|_| BridgeError::Wire("truncated frame header".to_string()))?;
+    let len = u32::from_be_bytes(len_bytes) as usize;
+    if len > MAX_PAYLOAD_LEN {
+        return Err(BridgeError::Wire(format!("payload of {} bytes exceeds {}", len, MAX_PAYLOAD_LEN)));
+    }
+    if rest.len() != len {
+        return Err(BridgeError::Wire(format!("expected {} payload bytes, got {}", len, rest.len())));
+    }
+    Ok(Frame {
+        version,
+        kind,
+        suite,
+        payload: rest.to_vec(),
+    })
+}
+
+pub fn parse_block_envelope(bytes: &[u8]) -> Result<BlockEnvelope, BridgeError> {
+    serde_json::from_slice(bounded(bytes)?).map_err(|e| BridgeError::Wire(e.to_string()))
+}
+
+/// Parses an announcement record and its body. Signature and proof of work are
+/// left to the caller, so this stays cheap on hostile input.
+pub fn parse_handshake(bytes: &[u8]) -> Result<Handshake, BridgeError> {
+    let record: Record = serde_json::from_slice(bounded(bytes)?).map_err(|e| BridgeError::Wire(e.to_string()))?;
+    if record.kind != ANNOUNCEMENT_KIND {
+        return Err(BridgeError::Wire(format!("expected {} record, got {}", ANNOUNCEMENT_KIND, record.kind)));
+    }
+    let announcement = serde_json::from_value(record.body.clone()).map_err(|e| BridgeError::Wire(e.to_string()))?;
+    Ok(Handshake { record, announcement })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(version: u8, len: u32) -> Vec<u8> {
+        let mut bytes = vec![version, FrameKind::Sync.to_byte()];
+        if version >= 2 {
+            bytes.push(Suite::default().id());
+        }
+        bytes.extend_from_slice(&len.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn encoded_frames_parse_back() {
+        let frame = Frame::new(FrameKind::Block, b"{}".to_vec());
+        assert_eq!(parse_frame(&frame.encode().unwrap()).unwrap(), frame);
+    }
+
+    #[test]
+    fn frames_fit_in_one_datagram() {
+        let largest = Frame::new(FrameKind::Block, vec![0; MAX_PAYLOAD_LEN]).encode().unwrap();
+        assert_eq!(largest.len(), MAX_DATAGRAM_LEN);
+        assert!(Frame::new(FrameKind::Block, vec![0; MAX_PAYLOAD_LEN + 1]).encode().is_err());
+    }
+
+    #[test]
+    fn truncated_frames_are_rejected() {
+        let mut bytes = Frame::new(FrameKind::Sync, b"payload".to_vec()).encode().unwrap();
+        bytes.pop();
+        assert!(parse_frame(&bytes).is_err());
+        for len in 0..FRAME_HEADER_LEN {
+            assert!(parse_frame(&header(WIRE_VERSION, 0)[..len]).is_err());
+        }
+        assert!(parse_frame(&header(1, 0)[..V1_FRAME_HEADER_LEN - 1]).is_err());
+    }
+
+    #[test]
+    fn trailing_bytes_are_rejected() {
+        let mut bytes = Frame::new(FrameKind::Sync, b"payload".to_vec()).encode().unwrap();
+        bytes.push(0);
+        assert!(parse_frame(&bytes).is_err());
+    }
+
+    #[test]
+    fn oversized_lengths_are_rejected_before_reading_the_payload() {
+        let error = parse_frame(&header(WIRE_VERSION, MAX_PAYLOAD_LEN as u32 + 1)).unwrap_err();
+        assert!(matches!(&error, BridgeError::Wire(m) if m.contains("exceeds")), "{}", error);
+        assert!(parse_frame(&header(WIRE_VERSION, u32::MAX)).is_err());
+        assert!(parse_block_envelope(&vec![b' '; MAX_PAYLOAD_LEN + 1]).is_err());
+        assert!(parse_handshake(&vec![b' '; MAX_PAYLOAD_LEN + 1]).is_err());
+    }
+
+    #[test]
+    fn unknown_versions_kinds_and_suites_are_rejected() {
+        for version in [0, WIRE_VERSION + 1, u8::MAX] {
+            let error = parse_frame(&header(version, 0)).unwrap_err();
+            assert!(matches!(&error, BridgeError::Wire(m) if m.contains("unsupported version")), "{}", error);
+        }
+        let mut bytes = header(WIRE_VERSION, 0);
+        bytes[1] = 0;
+        assert!(parse_frame(&bytes).is_err());
+        let mut bytes = header(WIRE_VERSION, 0);
+        bytes[2] = u8::MAX;
+        assert!(parse_frame(&bytes).is_err());
+    }
+
+    #[test]
+    fn version_1_frames_imply_the_default_suite() {
+        let mut bytes = header(1, 2);
+        bytes.extend_from_slice(b"{}");
+        let frame = parse_frame(&bytes).unwrap();
+        assert_eq!(frame.version, 1);
+        assert_eq!(frame.suite, Suite::default());
+    }
+}
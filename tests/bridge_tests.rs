@@ -0,0 +1,69 @@
+use ecoblock_bridge::error::BridgeError;
+use ecoblock_bridge::v2::{add_to_batch, commit_batch, get_block};
+use ecoblock_bridge::{abort_batch, begin_batch, get_tangle_size};
+use ecoblock_core::domain::SensorData;
+use serial_test::serial;
+
+fn reading(timestamp: u64) -> Vec<u8> {
+    let data = SensorData {
+        pm25: 12.0,
+        co2: 410.0,
+        temperature: 21.0,
+        humidity: 45.0,
+        timestamp,
+    };
+    serde_json::to_vec(&data).unwrap()
+}
+
+#[test]
+#[serial]
+fn committed_batch_stores_every_block() {
+    let before = get_tangle_size();
+    let batch = begin_batch();
+    let first = add_to_batch(batch, reading(1_700_000_001), Vec::new()).unwrap();
+    let second = add_to_batch(batch, reading(1_700_000_002), vec![first.clone()]).unwrap();
+    assert_eq!(get_tangle_size(), before, "nothing is stored before the commit");
+
+    let ids = commit_batch(batch).unwrap();
+    assert_eq!(ids, vec![first.clone(), second.clone()]);
+    assert_eq!(get_tangle_size(), before + 2);
+    assert!(get_block(first).is_ok());
+    assert!(get_block(second).is_ok());
+}
+
+#[test]
+#[serial]
+fn one_invalid_block_rolls_back_the_whole_batch() {
+    let before = get_tangle_size();
+    let batch = begin_batch();
+    let valid = add_to_batch(batch, reading(1_700_000_011), Vec::new()).unwrap();
+    let error = add_to_batch(batch, b"not a reading".to_vec(), Vec::new()).unwrap_err();
+    assert!(matches!(error, BridgeError::Deserialization(_)), "{:?}", error);
+
+    let error = commit_batch(batch).unwrap_err();
+    assert!(matches!(error, BridgeError::NotFound(_)), "{:?}", error);
+    assert!(add_to_batch(batch, reading(1_700_000_012), Vec::new()).is_err());
+    assert_eq!(get_tangle_size(), before);
+    assert!(matches!(get_block(valid), Err(BridgeError::NotFound(_))));
+}
+
+#[test]
+#[serial]
+fn aborted_batch_stores_nothing() {
+    let before = get_tangle_size();
+    let batch = begin_batch();
+    let id = add_to_batch(batch, reading(1_700_000_021), Vec::new()).unwrap();
+    assert!(abort_batch(batch));
+    assert!(!abort_batch(batch));
+    assert!(commit_batch(batch).is_err());
+    assert_eq!(get_tangle_size(), before);
+    assert!(get_block(id).is_err());
+}
+
+#[test]
+#[serial]
+fn unknown_batches_are_refused() {
+    let error = commit_batch(u64::MAX).unwrap_err();
+    assert!(matches!(error, BridgeError::NotFound(_)), "{:?}", error);
+    assert_eq!(error.to_string(), format!("Unknown batch {}", u64::MAX));
+}